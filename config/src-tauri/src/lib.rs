@@ -1,15 +1,20 @@
 use std::{
-    collections::HashMap,
+    collections::{BTreeMap, BTreeSet, HashMap},
     io::{Cursor, Read, Seek, SeekFrom},
-    path::PathBuf,
+    path::{Path, PathBuf},
     process::{Child, Command},
     sync::Mutex,
 };
 
+use anyhow::bail;
+use base64::prelude::*;
 use r2d2::Pool;
 use r2d2_sqlite::SqliteConnectionManager;
+use rusqlite::{OptionalExtension, params, params_from_iter};
 use serde::{Deserialize, Serialize};
 
+mod pack_link;
+
 // ─── Update check ─────────────────────────────────────────────────────────────
 
 #[derive(Deserialize)]
@@ -43,12 +48,24 @@ async fn check_for_update() -> Result<Option<String>, String> {
 use serde_json::Value as JsonValue;
 use indexmap::IndexMap;
 use shared::{
-    db::migrate,
+    db::{count_selection, migrate, read_stats, PackStats, Selection, SelectionCounts},
+    extract,
+    footprint::{self, FootprintEntry},
+    goals::Goal,
+    locale::{self, tr, tr_args, Localizer},
+    location::PackLocation,
     mode::{self, ModeEntry, Metadata, OptionType, OptionValue, ShowWhen},
-    read_pack::read_pack_metadata,
-    user_config::{self, AppConfig, Key, Mode},
+    rating,
+    read_pack::{copy_index, read_pack_metadata},
+    user_config::{
+        self, AppConfig, BatterySaver, BlendPack, Escalation, FavoritesMode, Hibernation,
+        ImageCache, Key, Mode, Pacing, PromptFailure, PromptLogging, RecentPack, Schedule,
+        StartDelay, Stats, TagMatchMode, Throttle, VideoHoverBehaviour, VideoPerformance,
+    },
+    user_data,
 };
 use tauri::{AppHandle, Manager};
+use tauri_plugin_deep_link::DeepLinkExt;
 use tempfile::NamedTempFile;
 use tokio::sync::oneshot;
 
@@ -97,10 +114,44 @@ pub struct ModeOptionsEntry {
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct ConfigDto {
     pub pack_path: Option<String>,
+    pub blend_pack: Option<BlendPack>,
     pub mode: ModeIdDto,
     pub mode_options: Vec<ModeOptionsEntry>,
     pub panic_button: Key,
+    pub mute_button: Option<Key>,
+    pub pause_button: Option<Key>,
+    pub block_button: Option<Key>,
+    pub favorite_button: Option<Key>,
+    pub favorites: FavoritesMode,
+    pub info_button: Option<Key>,
     pub disabled_monitors: Vec<String>,
+    pub video_hover: VideoHoverBehaviour,
+    pub video_performance: VideoPerformance,
+    pub image_cache: ImageCache,
+    pub verify_media: bool,
+    pub isolate_video_decoding: bool,
+    pub max_rating: Option<u32>,
+    pub excluded_tags: Vec<String>,
+    pub seed: Option<u64>,
+    pub daily_shuffle: bool,
+    pub metrics_port: Option<u16>,
+    pub battery_saver: BatterySaver,
+    pub throttle: Throttle,
+    pub start_delay: StartDelay,
+    pub schedule: Schedule,
+    pub escalation: Escalation,
+    pub pacing: Pacing,
+    pub prompt_failure: PromptFailure,
+    pub minigames: bool,
+    pub hibernation: Hibernation,
+    pub pause_while_recording: bool,
+    pub duck_other_apps: bool,
+    pub incognito: bool,
+    pub cursor_margin: Option<u32>,
+    pub pause_while_idle: Option<u32>,
+    pub language: Option<String>,
+    pub goals: Vec<Goal>,
+    pub prompt_logging: PromptLogging,
 }
 
 impl From<AppConfig> for ConfigDto {
@@ -116,10 +167,44 @@ impl From<AppConfig> for ConfigDto {
 
         ConfigDto {
             pack_path: c.pack_path.and_then(|p| p.to_str().map(str::to_string)),
+            blend_pack: c.blend_pack,
             mode: c.mode.into(),
             mode_options,
             panic_button: c.panic_button,
+            mute_button: c.mute_button,
+            pause_button: c.pause_button,
+            block_button: c.block_button,
+            favorite_button: c.favorite_button,
+            favorites: c.favorites,
+            info_button: c.info_button,
             disabled_monitors: c.disabled_monitors,
+            video_hover: c.video_hover,
+            video_performance: c.video_performance,
+            image_cache: c.image_cache,
+            verify_media: c.verify_media,
+            isolate_video_decoding: c.isolate_video_decoding,
+            max_rating: c.max_rating,
+            excluded_tags: c.excluded_tags,
+            seed: c.seed,
+            daily_shuffle: c.daily_shuffle,
+            metrics_port: c.metrics_port,
+            battery_saver: c.battery_saver,
+            throttle: c.throttle,
+            start_delay: c.start_delay,
+            schedule: c.schedule,
+            escalation: c.escalation,
+            pacing: c.pacing,
+            prompt_failure: c.prompt_failure,
+            minigames: c.minigames,
+            hibernation: c.hibernation,
+            pause_while_recording: c.pause_while_recording,
+            duck_other_apps: c.duck_other_apps,
+            incognito: c.incognito,
+            cursor_margin: c.cursor_margin,
+            pause_while_idle: c.pause_while_idle,
+            language: c.language,
+            goals: c.goals,
+            prompt_logging: c.prompt_logging,
         }
     }
 }
@@ -134,12 +219,47 @@ impl From<ConfigDto> for AppConfig {
 
         AppConfig {
             pack_path: dto.pack_path.map(PathBuf::from),
+            blend_pack: dto.blend_pack,
             uploaded_modes: Vec::new(),
             mode: dto.mode.into(),
             mode_options,
             tags: None,
+            tag_match_mode: TagMatchMode::default(),
             panic_button: dto.panic_button,
+            mute_button: dto.mute_button,
+            pause_button: dto.pause_button,
+            block_button: dto.block_button,
+            favorite_button: dto.favorite_button,
+            favorites: dto.favorites,
+            info_button: dto.info_button,
             disabled_monitors: dto.disabled_monitors,
+            video_hover: dto.video_hover,
+            video_performance: dto.video_performance,
+            image_cache: dto.image_cache,
+            verify_media: dto.verify_media,
+            isolate_video_decoding: dto.isolate_video_decoding,
+            max_rating: dto.max_rating,
+            excluded_tags: dto.excluded_tags,
+            seed: dto.seed,
+            daily_shuffle: dto.daily_shuffle,
+            metrics_port: dto.metrics_port,
+            battery_saver: dto.battery_saver,
+            throttle: dto.throttle,
+            start_delay: dto.start_delay,
+            schedule: dto.schedule,
+            escalation: dto.escalation,
+            pacing: dto.pacing,
+            prompt_failure: dto.prompt_failure,
+            minigames: dto.minigames,
+            hibernation: dto.hibernation,
+            pause_while_recording: dto.pause_while_recording,
+            duck_other_apps: dto.duck_other_apps,
+            incognito: dto.incognito,
+            cursor_margin: dto.cursor_margin,
+            pause_while_idle: dto.pause_while_idle,
+            language: dto.language,
+            goals: dto.goals,
+            prompt_logging: dto.prompt_logging,
         }
     }
 }
@@ -205,6 +325,15 @@ struct UploadedModeEntry {
 }
 
 struct LoadedPack {
+    path: PathBuf,
+    /// The header id and name, for the recent packs list.
+    id: String,
+    name: String,
+    /// The names of the pack's content rating levels, and the levels of its tags.
+    ratings: Vec<String>,
+    tag_ratings: BTreeMap<String, u32>,
+    // Declared before `_db_file` so the connections are closed before the file is deleted.
+    pool: Pool<SqliteConnectionManager>,
     _db_file: NamedTempFile,
     modes: Vec<PackModeEntry>,
 }
@@ -221,14 +350,14 @@ pub type State<'a> = tauri::State<'a, AppState>;
 
 // ─── Pack / mode loading ──────────────────────────────────────────────────────
 
+/// `path` can be a URL, see [`PackLocation::new`]. Only the header, metadata and index are fetched
+/// from one.
 fn load_pack(path: PathBuf) -> anyhow::Result<LoadedPack> {
-    let mut file = std::fs::File::open(&path)?;
-    let (header, _) = read_pack_metadata(&mut file)?;
+    let mut file = PackLocation::new(path.clone()).open()?;
+    let (header, pack_metadata) = read_pack_metadata(&mut file)?;
 
     let mut db_file = NamedTempFile::new()?;
-    file.seek(SeekFrom::Start(header.index_offset))?;
-    let mut db_data = (&mut file).take(header.index_length);
-    std::io::copy(&mut db_data, db_file.as_file_mut())?;
+    copy_index(&mut file, &header, db_file.as_file_mut())?;
 
     let manager = SqliteConnectionManager::file(db_file.path());
     let pool = Pool::builder().build(manager)?;
@@ -248,11 +377,179 @@ fn load_pack(path: PathBuf) -> anyhow::Result<LoadedPack> {
     }
 
     Ok(LoadedPack {
+        path,
+        id: header.id.to_string(),
+        ratings: pack_metadata.rating_names(),
+        tag_ratings: pack_metadata.tag_ratings,
+        name: pack_metadata.name,
+        pool,
         _db_file: db_file,
         modes,
     })
 }
 
+// ─── Preview ──────────────────────────────────────────────────────────────────
+
+#[derive(Serialize, Clone, Debug)]
+pub struct PreviewDto {
+    pub file_name: String,
+    pub file_type: String,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub tags: Vec<String>,
+    /// A `data:` URL for the webview to display, if the media can be shown at all.
+    pub src: Option<String>,
+    /// `false` if `src` is only the thumbnail: videos, and JPEG XL images, which webviews can't
+    /// decode.
+    pub full_size: bool,
+    /// How much media in the pack matches the current tag filter.
+    pub matching: u64,
+}
+
+/// Picks a random image or video from the pack, filtered by the selected tags the same way a
+/// session would, and reads it back (checking its checksum) so the config app can show it.
+fn random_preview(
+    path: &Path,
+    pool: &Pool<SqliteConnectionManager>,
+    tags: Option<&[String]>,
+    tag_match_mode: TagMatchMode,
+    max_rating: Option<u32>,
+    tags_above_rating: &[String],
+    excluded_tags: &[String],
+) -> anyhow::Result<Option<PreviewDto>> {
+    let conn = pool.get()?;
+
+    let mut filter = "file_type IN ('image', 'video')".to_string();
+    if let Some(tags) = tags {
+        for tag in tags {
+            let exists = conn
+                .query_row("SELECT 1 FROM tags WHERE name = ?", params![tag], |_| Ok(()))
+                .optional()?
+                .is_some();
+            if !exists {
+                bail!(tr_args("config-no-tag", [("tag", tag.clone().into())]));
+            }
+        }
+
+        filter.push_str(&format!(
+            " AND id IN (SELECT media_id FROM media_tags \
+             JOIN tags ON tags.id = media_tags.tag_id WHERE tags.name IN ({})",
+            vec!["?"; tags.len()].join(", ")
+        ));
+        if tag_match_mode == TagMatchMode::All {
+            let distinct = tags.iter().collect::<BTreeSet<_>>().len();
+            filter.push_str(&format!(
+                " GROUP BY media_id HAVING COUNT(DISTINCT media_tags.tag_id) = {distinct}"
+            ));
+        }
+        filter.push(')');
+    }
+    if let Some(max_rating) = max_rating {
+        filter.push_str(&format!(
+            " AND {}",
+            rating::media_filter(max_rating, tags_above_rating)
+        ));
+    }
+    if !excluded_tags.is_empty() {
+        filter.push_str(&format!(
+            " AND id NOT IN (SELECT media_id FROM media_tags \
+             JOIN tags ON tags.id = media_tags.tag_id WHERE tags.name IN ({}))",
+            vec!["?"; excluded_tags.len()].join(", ")
+        ));
+    }
+    let params = || {
+        tags.unwrap_or_default()
+            .iter()
+            .chain(tags_above_rating)
+            .chain(excluded_tags)
+    };
+
+    let matching: u64 = conn.query_row(
+        &format!("SELECT COUNT(*) FROM media WHERE {filter}"),
+        params_from_iter(params()),
+        |row| row.get(0),
+    )?;
+
+    let row = conn
+        .query_row(
+            &format!(
+                "SELECT id, file_name, file_type, \"offset\", length, width, height, codec, \
+                 checksum, thumbnail FROM media WHERE {filter} ORDER BY RANDOM() LIMIT 1"
+            ),
+            params_from_iter(params()),
+            |row| {
+                Ok((
+                    row.get::<_, u64>("id")?,
+                    row.get::<_, String>("file_name")?,
+                    row.get::<_, String>("file_type")?,
+                    row.get::<_, Option<u64>>("offset")?,
+                    row.get::<_, Option<u64>>("length")?,
+                    row.get::<_, Option<u32>>("width")?,
+                    row.get::<_, Option<u32>>("height")?,
+                    row.get::<_, Option<String>>("codec")?,
+                    row.get::<_, Option<Vec<u8>>>("checksum")?,
+                    row.get::<_, Option<Vec<u8>>>("thumbnail")?,
+                ))
+            },
+        )
+        .optional()?;
+
+    let Some((id, file_name, file_type, offset, length, width, height, codec, checksum, thumbnail)) =
+        row
+    else {
+        return Ok(None);
+    };
+
+    let mut stmt = conn.prepare(
+        "SELECT tags.name FROM media_tags JOIN tags ON tags.id = media_tags.tag_id \
+         WHERE media_tags.media_id = ? ORDER BY tags.name",
+    )?;
+    let media_tags = stmt
+        .query_map(params![id], |row| row.get(0))?
+        .collect::<rusqlite::Result<Vec<String>>>()?;
+
+    // Images added before the codec column existed are all AVIF.
+    let mime = match (file_type.as_str(), codec.as_deref()) {
+        ("image", None | Some("avif")) => Some("image/avif"),
+        ("image", Some("webp")) => Some("image/webp"),
+        _ => None,
+    };
+
+    let full = match (mime, offset, length) {
+        (Some(mime), Some(offset), Some(length)) => {
+            let mut file = PackLocation::new(path.to_path_buf()).open()?;
+            file.seek(SeekFrom::Start(offset))?;
+            let mut data = Vec::with_capacity(length as usize);
+            (&mut file).take(length).read_to_end(&mut data)?;
+
+            if let Some(checksum) = &checksum {
+                if blake3::hash(&data).as_bytes().as_slice() != checksum.as_slice() {
+                    bail!("\"{file_name}\" doesn't match its checksum; the pack may be corrupted");
+                }
+            }
+
+            Some((mime, data))
+        }
+        _ => None,
+    };
+
+    let full_size = full.is_some();
+    let src = full
+        .or(thumbnail.map(|thumbnail| ("image/webp", thumbnail)))
+        .map(|(mime, data)| format!("data:{mime};base64,{}", BASE64_STANDARD.encode(data)));
+
+    Ok(Some(PreviewDto {
+        file_name,
+        file_type,
+        width,
+        height,
+        tags: media_tags,
+        src,
+        full_size,
+        matching,
+    }))
+}
+
 fn load_mode_file(path: PathBuf) -> anyhow::Result<UploadedModeEntry> {
     let mut file = std::fs::File::open(&path)?;
     let (_, metadata) = mode::read_mode_metadata(&mut file)?;
@@ -432,6 +729,7 @@ fn save_config(state: State<'_>, config: ConfigDto) -> Result<(), String> {
     // Preserve fields managed separately from the DTO
     new_config.uploaded_modes = current.uploaded_modes.clone();
     new_config.tags = current.tags.clone();
+    new_config.tag_match_mode = current.tag_match_mode;
 
     let uploaded = state.uploaded.lock().unwrap();
     save_to_disk(&new_config, &uploaded).map_err(|e| e.to_string())?;
@@ -564,6 +862,8 @@ pub struct PickPackResult {
     pub pack_path: String,
     pub mode_groups: Vec<ModeGroupDto>,
     pub first_mode: Option<ModeIdDto>,
+    /// The names of the pack's content rating levels, mildest first.
+    pub ratings: Vec<String>,
 }
 
 #[tauri::command]
@@ -576,7 +876,27 @@ async fn pick_pack(
     let path = app_handle
         .dialog()
         .file()
-        .add_filter("Pack", &["lwpack"])
+        .add_filter(tr("config-pack-filter"), &["lwpack"])
+        .blocking_pick_file()
+        .and_then(|p| p.into_path().ok());
+
+    let Some(path) = path else {
+        return Ok(None);
+    };
+
+    switch_pack(&state, path).await.map(Some)
+}
+
+/// Asks for a second pack to blend into sessions (see [`BlendPack`]). Returns its path, once it's
+/// been checked that it really is a pack.
+#[tauri::command]
+async fn pick_blend_pack(app_handle: AppHandle) -> Result<Option<String>, String> {
+    use tauri_plugin_dialog::DialogExt;
+
+    let path = app_handle
+        .dialog()
+        .file()
+        .add_filter(tr("config-pack-filter"), &["lwpack"])
         .blocking_pick_file()
         .and_then(|p| p.into_path().ok());
 
@@ -584,6 +904,212 @@ async fn pick_pack(
         return Ok(None);
     };
 
+    let mut file = std::fs::File::open(&path).map_err(|e| e.to_string())?;
+    read_pack_metadata(&mut file).map_err(|e| e.to_string())?;
+
+    Ok(Some(path.to_string_lossy().into_owned()))
+}
+
+/// Plays the pack streamed from `url` (see [`PackLocation`]), rather than one on disk.
+#[tauri::command]
+async fn open_pack_url(state: State<'_>, url: String) -> Result<PickPackResult, String> {
+    let path = PathBuf::from(url.trim());
+    if !matches!(PackLocation::new(path.clone()), PackLocation::Url(_)) {
+        return Err(tr("config-pack-url-invalid"));
+    }
+
+    switch_pack(&state, path).await
+}
+
+#[tauri::command]
+async fn open_recent_pack(state: State<'_>, path: PathBuf) -> Result<PickPackResult, String> {
+    switch_pack(&state, path).await
+}
+
+#[tauri::command]
+fn get_recent_packs() -> Result<Vec<RecentPack>, String> {
+    user_config::load_recent_packs().map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn set_recent_pack_pinned(path: PathBuf, pinned: bool) -> Result<Vec<RecentPack>, String> {
+    user_config::set_recent_pack_pinned(&path, pinned).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn forget_recent_pack(path: PathBuf) -> Result<Vec<RecentPack>, String> {
+    user_config::forget_recent_pack(&path).map_err(|e| e.to_string())
+}
+
+/// How the last session went, if there's been one.
+#[tauri::command]
+fn get_stats() -> Result<Stats, String> {
+    unless_locked(user_config::load_stats())
+}
+
+#[tauri::command]
+fn prompt_history_count() -> Result<usize, String> {
+    user_config::load_prompt_history()
+        .map(|history| history.len())
+        .map_err(|e| e.to_string())
+}
+
+/// Saves the prompt history to a JSON file of the user's choosing. Returns whether it was saved.
+#[tauri::command]
+async fn export_prompt_history(app_handle: AppHandle) -> Result<bool, String> {
+    use tauri_plugin_dialog::DialogExt;
+
+    let path = app_handle
+        .dialog()
+        .file()
+        .set_file_name("prompt-history.json")
+        .add_filter(tr("config-json-filter"), &["json"])
+        .blocking_save_file()
+        .and_then(|p| p.into_path().ok());
+
+    let Some(path) = path else {
+        return Ok(false);
+    };
+
+    let history = user_config::load_prompt_history().map_err(|e| e.to_string())?;
+    let json = serde_json::to_string_pretty(&history).map_err(|e| e.to_string())?;
+    std::fs::write(path, json).map_err(|e| e.to_string())?;
+
+    Ok(true)
+}
+
+#[tauri::command]
+fn clear_prompt_history() -> Result<(), String> {
+    user_config::clear_prompt_history().map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn blocked_media_count() -> Result<usize, String> {
+    unless_locked(user_config::load_blocked_media().map(|blocked| blocked.len()))
+}
+
+#[tauri::command]
+fn clear_blocked_media() -> Result<(), String> {
+    user_config::clear_blocked_media().map_err(|e| e.to_string())
+}
+
+/// How many of the current pack's files are favorites.
+#[tauri::command]
+fn favorites_count(state: State<'_>) -> Result<usize, String> {
+    match state.pack.lock().unwrap().as_ref() {
+        Some(pack) => {
+            unless_locked(user_config::load_favorites(&pack.id).map(|favorites| favorites.len()))
+        }
+        None => Ok(0),
+    }
+}
+
+/// Copies the current pack's favorites into a folder of the user's choosing, in the formats
+/// they're stored in (see [`shared::extract`]). Returns how many were copied, or `None` if the
+/// user cancelled.
+#[tauri::command]
+async fn export_favorites(
+    app_handle: AppHandle,
+    state: State<'_>,
+) -> Result<Option<usize>, String> {
+    use tauri_plugin_dialog::DialogExt;
+
+    let (path, id, pool) = match state.pack.lock().unwrap().as_ref() {
+        Some(pack) => (pack.path.clone(), pack.id.clone(), pack.pool.clone()),
+        None => return Ok(None),
+    };
+
+    let dir = app_handle
+        .dialog()
+        .file()
+        .blocking_pick_folder()
+        .and_then(|p| p.into_path().ok());
+
+    let Some(dir) = dir else {
+        return Ok(None);
+    };
+
+    tokio::task::spawn_blocking(move || extract_favorites(&path, &id, &pool, &dir))
+        .await
+        .map_err(|e| e.to_string())?
+        .map(Some)
+        .map_err(|e| e.to_string())
+}
+
+fn extract_favorites(
+    path: &Path,
+    pack_id: &str,
+    pool: &Pool<SqliteConnectionManager>,
+    dir: &Path,
+) -> anyhow::Result<usize> {
+    let favorites = user_config::load_favorites(pack_id)?;
+    let entries = extract::entries_with_hashes(&*pool.get()?, &favorites)?;
+
+    let mut file = PackLocation::new(path.to_path_buf()).open()?;
+    for entry in &entries {
+        let data = extract::read_entry(&mut file, entry)?;
+
+        if let Some(checksum) = &entry.checksum {
+            if blake3::hash(&data).as_bytes().as_slice() != checksum.as_slice() {
+                bail!(
+                    "\"{}\" doesn't match its checksum; the pack may be corrupted",
+                    entry.name
+                );
+            }
+        }
+
+        std::fs::write(extract::unused_path(dir, &entry.name), data)?;
+    }
+
+    Ok(entries.len())
+}
+
+/// Treats the user data (see [`user_data`]) as empty while it's locked, so the settings that
+/// show it don't fail until the settings password is typed in.
+fn unless_locked<T: Default>(result: anyhow::Result<T>) -> Result<T, String> {
+    match result {
+        Err(err) if err.is::<user_data::Locked>() => Ok(T::default()),
+        result => result.map_err(|e| e.to_string()),
+    }
+}
+
+#[derive(Serialize)]
+pub struct UserDataStatusDto {
+    /// The settings password has to be typed in before the user data can be read.
+    pub locked: bool,
+    pub has_password: bool,
+}
+
+#[tauri::command]
+fn user_data_status() -> Result<UserDataStatusDto, String> {
+    Ok(UserDataStatusDto {
+        locked: user_data::is_locked().map_err(|e| e.to_string())?,
+        has_password: user_data::has_password().map_err(|e| e.to_string())?,
+    })
+}
+
+/// Unlocks the user data with the settings password. Returns whether it was the right one.
+#[tauri::command]
+async fn unlock_user_data(password: String) -> Result<bool, String> {
+    // Deriving the key is deliberately slow.
+    tokio::task::spawn_blocking(move || user_data::unlock(Some(&password)))
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| e.to_string())
+}
+
+/// Sets the settings password, or removes it if `password` is `None`. The user data has to be
+/// unlocked.
+#[tauri::command]
+async fn set_settings_password(password: Option<String>) -> Result<(), String> {
+    tokio::task::spawn_blocking(move || user_data::set_password(password.as_deref()))
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| e.to_string())
+}
+
+/// Loads the pack at `path`, makes it the configured pack and selects its first mode.
+async fn switch_pack(state: &AppState, path: PathBuf) -> Result<PickPackResult, String> {
     let loaded = tokio::task::spawn_blocking({
         let path = path.clone();
         move || load_pack(path)
@@ -599,7 +1125,12 @@ async fn pick_pack(
         })
     });
 
+    if let Err(err) = user_config::record_recent_pack(&path, &loaded.id, &loaded.name) {
+        tracing::error!("Failed to update recent packs: {err}");
+    }
+
     let pack_path_str = path.to_string_lossy().into_owned();
+    let ratings = loaded.ratings.clone();
     *state.pack.lock().unwrap() = Some(loaded);
 
     let mut config = state.config.lock().unwrap();
@@ -608,15 +1139,122 @@ async fn pick_pack(
         config.mode = m.clone().into();
     }
 
-    let groups = build_mode_groups(&state);
+    let groups = build_mode_groups(state);
     let uploaded = state.uploaded.lock().unwrap();
     save_to_disk(&config, &uploaded).map_err(|e| e.to_string())?;
 
-    Ok(Some(PickPackResult {
+    Ok(PickPackResult {
         pack_path: pack_path_str,
         mode_groups: groups,
         first_mode,
-    }))
+        ratings,
+    })
+}
+
+#[tauri::command]
+async fn get_preview(state: State<'_>) -> Result<Option<PreviewDto>, String> {
+    let (tags, tag_match_mode, max_rating, excluded_tags) = {
+        let config = state.config.lock().unwrap();
+        (
+            config.tags.clone(),
+            config.tag_match_mode,
+            config.max_rating,
+            config.excluded_tags.clone(),
+        )
+    };
+    let (path, pool, tags_above_rating) = match state.pack.lock().unwrap().as_ref() {
+        Some(pack) => (
+            pack.path.clone(),
+            pack.pool.clone(),
+            max_rating
+                .map(|max_rating| rating::tags_above(max_rating, &pack.tag_ratings))
+                .unwrap_or_default(),
+        ),
+        None => return Ok(None),
+    };
+
+    tokio::task::spawn_blocking(move || {
+        random_preview(
+            &path,
+            &pool,
+            tags.as_deref(),
+            tag_match_mode,
+            max_rating,
+            &tags_above_rating,
+            &excluded_tags,
+        )
+    })
+    .await
+    .map_err(|e| e.to_string())?
+    .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn get_pack_stats(state: State<'_>) -> Result<Option<PackStats>, String> {
+    let pool = match state.pack.lock().unwrap().as_ref() {
+        Some(pack) => pack.pool.clone(),
+        None => return Ok(None),
+    };
+
+    tokio::task::spawn_blocking(move || -> anyhow::Result<_> { read_stats(&*pool.get()?) })
+        .await
+        .map_err(|e| e.to_string())?
+        .map(Some)
+        .map_err(|e| e.to_string())
+}
+
+/// How much of each media type and text kind in the current pack the session would be left with,
+/// or `None` if there's no pack. The rating and excluded tags are passed in rather than read from
+/// the config, since they're checked as the user changes them, which can be before they're saved.
+#[tauri::command]
+async fn check_selection(
+    state: State<'_>,
+    max_rating: Option<u32>,
+    excluded_tags: Vec<String>,
+) -> Result<Option<SelectionCounts>, String> {
+    let (tags, tag_match_mode) = {
+        let config = state.config.lock().unwrap();
+        (config.tags.clone(), config.tag_match_mode)
+    };
+    let (pool, tags_above_rating) = match state.pack.lock().unwrap().as_ref() {
+        Some(pack) => (
+            pack.pool.clone(),
+            max_rating
+                .map(|max_rating| rating::tags_above(max_rating, &pack.tag_ratings))
+                .unwrap_or_default(),
+        ),
+        None => return Ok(None),
+    };
+
+    tokio::task::spawn_blocking(move || -> anyhow::Result<_> {
+        count_selection(
+            &*pool.get()?,
+            &Selection {
+                tags: tags.as_deref(),
+                tag_match_mode,
+                max_rating,
+                tags_above_rating: &tags_above_rating,
+                excluded_tags: &excluded_tags,
+            },
+        )
+    })
+    .await
+    .map_err(|e| e.to_string())?
+    .map(Some)
+    .map_err(|e| e.to_string())
+}
+
+/// The names of the current pack's content rating levels, mildest first, or none if there's no
+/// pack.
+#[tauri::command]
+fn get_pack_ratings(state: State<'_>) -> Vec<String> {
+    state
+        .pack
+        .lock()
+        .unwrap()
+        .as_ref()
+        .map(|pack| pack.ratings.clone())
+        .unwrap_or_default()
 }
 
 #[tauri::command]
@@ -646,7 +1284,7 @@ async fn upload_mode(
     let path = app_handle
         .dialog()
         .file()
-        .add_filter("Mode", &["lwmode"])
+        .add_filter(tr("config-mode-filter"), &["lwmode"])
         .blocking_pick_file()
         .and_then(|p| p.into_path().ok());
 
@@ -754,7 +1392,11 @@ fn launch_lewdware(state: State<'_>) -> Result<(), String> {
         }
     }
 
-    let mut cmd = find_lewdware().ok_or("Could not find lewdware binary")?;
+    let mut cmd = find_lewdware().ok_or_else(|| tr("config-lewdware-not-found"))?;
+    // So the player can read the user data without asking for the settings password again.
+    if let Some(key) = user_data::exported_key() {
+        cmd.env(user_data::KEY_VAR, key);
+    }
     let child = cmd.spawn().map_err(|e| e.to_string())?;
     *guard = Some(child);
     Ok(())
@@ -885,6 +1527,71 @@ fn open_logs() -> Result<(), String> {
     open_log_dir()
 }
 
+// ─── Privacy ──────────────────────────────────────────────────────────────────
+
+#[derive(Serialize)]
+struct PrivacyReportDto {
+    access: &'static [&'static str],
+    never_accessed: &'static [&'static str],
+}
+
+#[tauri::command]
+fn privacy_report() -> PrivacyReportDto {
+    PrivacyReportDto {
+        access: shared::privacy::SYSTEM_ACCESS,
+        never_accessed: shared::privacy::NEVER_ACCESSED,
+    }
+}
+
+/// Everything the player has left on disk (see [`shared::footprint`]).
+#[tauri::command]
+fn get_footprint() -> Vec<FootprintEntry> {
+    footprint::footprint(None)
+}
+
+/// Wipes everything [`get_footprint`] lists, returning the files that couldn't be. Not while a
+/// session's running, since it's still using them, whether or not it was started from here.
+#[tauri::command]
+fn wipe_footprint() -> Result<Vec<PathBuf>, String> {
+    if footprint::player_running() {
+        return Err(tr("config-footprint-running"));
+    }
+
+    Ok(footprint::wipe(&footprint::footprint(None)))
+}
+
+// ─── Localization ─────────────────────────────────────────────────────────────
+
+#[derive(Serialize)]
+struct LanguageDto {
+    code: &'static str,
+    name: &'static str,
+}
+
+#[derive(Serialize)]
+struct LocaleDto {
+    language: &'static str,
+    languages: Vec<LanguageDto>,
+    messages: HashMap<String, String>,
+}
+
+/// The frontend's messages, in the language currently picked in the config. Unlike the
+/// backend's own messages, these change as soon as a different language is picked.
+#[tauri::command]
+fn get_locale(state: State<'_>) -> LocaleDto {
+    let language = locale::resolve_language(state.config.lock().unwrap().language.as_deref());
+    let localizer = Localizer::new(language);
+
+    LocaleDto {
+        language,
+        languages: locale::LANGUAGES
+            .iter()
+            .map(|&(code, name)| LanguageDto { code, name })
+            .collect(),
+        messages: localizer.messages(),
+    }
+}
+
 // ─── Entry ────────────────────────────────────────────────────────────────────
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
@@ -897,6 +1604,7 @@ pub fn run() {
     let _log_guard = shared::logging::init("config");
 
     let config = user_config::load_config().unwrap_or_default();
+    locale::init(config.language.as_deref());
 
     let pack = config.pack_path.as_ref().and_then(|p| {
         load_pack(p.clone())
@@ -915,6 +1623,15 @@ pub fn run() {
         .collect();
 
     tauri::Builder::default()
+        // Has to come first, so a second instance started by a pack link exits before it sets
+        // anything else up. The link is passed on to the deep link plugin in this instance.
+        .plugin(tauri_plugin_single_instance::init(|app, _argv, _cwd| {
+            if let Some(window) = app.get_webview_window("main") {
+                let _ = window.unminimize();
+                let _ = window.set_focus();
+            }
+        }))
+        .plugin(tauri_plugin_deep_link::init())
         .plugin(tauri_plugin_dialog::init())
         .plugin(tauri_plugin_opener::init())
         .manage(AppState {
@@ -929,6 +1646,26 @@ pub fn run() {
                 let icon = tauri::image::Image::from_bytes(include_bytes!("../icons/icon.png"))?;
                 window.set_icon(icon)?;
             }
+
+            // Installed apps are registered for `lewd://` links by their installer, but AppImages
+            // and builds run from elsewhere have to do it themselves.
+            #[cfg(any(windows, target_os = "linux"))]
+            if let Err(err) = app.deep_link().register_all() {
+                tracing::error!("Failed to register for pack links: {err}");
+            }
+
+            let handle = app.handle().clone();
+            app.deep_link().on_open_url(move |event| {
+                for link in event.urls() {
+                    pack_link::handle(&handle, link);
+                }
+            });
+            if let Ok(Some(links)) = app.deep_link().get_current() {
+                for link in links {
+                    pack_link::handle(app.handle(), link);
+                }
+            }
+
             Ok(())
         })
         .invoke_handler(tauri::generate_handler![
@@ -939,17 +1676,42 @@ pub fn run() {
             get_mode_options,
             set_mode_option,
             pick_pack,
+            open_pack_url,
+            pick_blend_pack,
+            open_recent_pack,
+            get_recent_packs,
+            set_recent_pack_pinned,
+            forget_recent_pack,
+            get_preview,
+            get_pack_stats,
+            check_selection,
+            get_pack_ratings,
             remove_pack,
             upload_mode,
             remove_uploaded_mode,
             launch_lewdware,
             stop_lewdware,
             lewdware_running,
+            get_stats,
+            prompt_history_count,
+            export_prompt_history,
+            clear_prompt_history,
+            blocked_media_count,
+            clear_blocked_media,
+            favorites_count,
+            export_favorites,
+            user_data_status,
+            unlock_user_data,
+            set_settings_password,
             open_logs,
             check_for_update,
             input_monitoring_granted,
             request_input_monitoring,
             open_input_monitoring_settings,
+            privacy_report,
+            get_footprint,
+            wipe_footprint,
+            get_locale,
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
@@ -1,14 +1,23 @@
 import { invoke } from "@tauri-apps/api/core";
 import type {
   ConfigDto,
+  FootprintEntry,
   Key,
+  LocaleDto,
   ModeGroupDto,
   ModeId,
   MonitorDto,
   OptionEntryDto,
   OptionValue,
+  PackStats,
   PickPackResult,
+  PrivacyReport,
+  PreviewDto,
+  RecentPack,
+  SelectionCounts,
+  Stats,
   UploadModeResult,
+  UserDataStatus,
 } from "./types";
 
 export const api = {
@@ -27,6 +36,28 @@ export const api = {
 
   pickPack: () => invoke<PickPackResult | null>("pick_pack"),
 
+  pickBlendPack: () => invoke<string | null>("pick_blend_pack"),
+
+  openPackUrl: (url: string) => invoke<PickPackResult>("open_pack_url", { url }),
+
+  openRecentPack: (path: string) => invoke<PickPackResult>("open_recent_pack", { path }),
+
+  getRecentPacks: () => invoke<RecentPack[]>("get_recent_packs"),
+
+  setRecentPackPinned: (path: string, pinned: boolean) =>
+    invoke<RecentPack[]>("set_recent_pack_pinned", { path, pinned }),
+
+  forgetRecentPack: (path: string) => invoke<RecentPack[]>("forget_recent_pack", { path }),
+
+  getPreview: () => invoke<PreviewDto | null>("get_preview"),
+
+  getPackStats: () => invoke<PackStats | null>("get_pack_stats"),
+
+  checkSelection: (maxRating: number | null, excludedTags: string[]) =>
+    invoke<SelectionCounts | null>("check_selection", { maxRating, excludedTags }),
+
+  getPackRatings: () => invoke<string[]>("get_pack_ratings"),
+
   removePack: () => invoke<void>("remove_pack"),
 
   uploadMode: () => invoke<UploadModeResult | null>("upload_mode"),
@@ -40,6 +71,29 @@ export const api = {
 
   lewdwareRunning: () => invoke<boolean>("lewdware_running"),
 
+  getStats: () => invoke<Stats>("get_stats"),
+
+  promptHistoryCount: () => invoke<number>("prompt_history_count"),
+
+  exportPromptHistory: () => invoke<boolean>("export_prompt_history"),
+
+  clearPromptHistory: () => invoke<void>("clear_prompt_history"),
+
+  blockedMediaCount: () => invoke<number>("blocked_media_count"),
+
+  clearBlockedMedia: () => invoke<void>("clear_blocked_media"),
+
+  favoritesCount: () => invoke<number>("favorites_count"),
+
+  exportFavorites: () => invoke<number | null>("export_favorites"),
+
+  userDataStatus: () => invoke<UserDataStatus>("user_data_status"),
+
+  unlockUserData: (password: string) => invoke<boolean>("unlock_user_data", { password }),
+
+  setSettingsPassword: (password: string | null) =>
+    invoke<void>("set_settings_password", { password }),
+
   openLogs: () => invoke<void>("open_logs"),
 
   inputMonitoringGranted: () => invoke<boolean>("input_monitoring_granted"),
@@ -47,4 +101,12 @@ export const api = {
   requestInputMonitoring: () => invoke<boolean>("request_input_monitoring"),
 
   openInputMonitoringSettings: () => invoke<void>("open_input_monitoring_settings"),
+
+  privacyReport: () => invoke<PrivacyReport>("privacy_report"),
+
+  getFootprint: () => invoke<FootprintEntry[]>("get_footprint"),
+
+  wipeFootprint: () => invoke<string[]>("wipe_footprint"),
+
+  getLocale: () => invoke<LocaleDto>("get_locale"),
 };
@@ -0,0 +1,34 @@
+import { api } from "./api";
+
+// Messages come from the Fluent files in `shared/locales`, formatted by the backend with their
+// arguments left in as `{$name}`. Fluent's selectors aren't available here, so a message that
+// depends on a count has an attribute for each plural category that needs its own wording (e.g.
+// `.one`), picked using the `count` argument.
+
+class I18n {
+  language = $state("en");
+  languages = $state<{ code: string; name: string }[]>([]);
+  messages = $state<Record<string, string>>({});
+
+  async load() {
+    const locale = await api.getLocale();
+    this.language = locale.language;
+    this.languages = locale.languages;
+    this.messages = locale.messages;
+  }
+}
+
+export const i18n = new I18n();
+
+export function t(id: string, args: Record<string, string | number> = {}): string {
+  let message: string | undefined;
+  if (typeof args.count === "number") {
+    const category = new Intl.PluralRules(i18n.language).select(args.count);
+    message = i18n.messages[`${id}.${category}`];
+  }
+  message ??= i18n.messages[id] ?? id;
+
+  return message.replace(/\{\s*\$([\w-]+)\s*\}/g, (placeholder, name: string) =>
+    name in args ? String(args[name]) : placeholder,
+  );
+}
@@ -1,12 +1,29 @@
 import { api } from "./api";
+import { i18n } from "./i18n.svelte";
 import type {
+  BatterySaver,
+  BlendPack,
   ConfigDto,
+  Escalation,
+  FavoritesMode,
+  Goal,
+  Hibernation,
   Key,
   ModeGroupDto,
   ModeId,
   OptionEntryDto,
   OptionValue,
   MonitorDto,
+  Pacing,
+  PickPackResult,
+  PromptFailure,
+  PromptLogging,
+  RecentPack,
+  Schedule,
+  StartDelay,
+  Throttle,
+  VideoHoverBehaviour,
+  VideoPerformance,
 } from "./types";
 
 function updateOptionValue(
@@ -37,23 +54,31 @@ class AppStore {
   modeGroups = $state<ModeGroupDto[]>([]);
   modeOptions = $state<OptionEntryDto[]>([]);
   activeTab = $state<"general" | "pack_mode">("general");
+  recentPacks = $state<RecentPack[]>([]);
+  /** The names of the current pack's content rating levels, mildest first. */
+  ratings = $state<string[]>([]);
 
   get ready() {
     return this.config !== null;
   }
 
   async load() {
-    const [config, monitors, modeGroups, modeOptions] = await Promise.all([
+    const [config, monitors, modeGroups, modeOptions, recentPacks, ratings] = await Promise.all([
       api.getConfig(),
       api.getMonitors(),
       api.getModeGroups(),
       api.getModeOptions(),
+      api.getRecentPacks(),
+      api.getPackRatings(),
+      i18n.load(),
     ]);
 
     this.config = config;
     this.monitors = monitors;
     this.modeGroups = modeGroups;
     this.modeOptions = modeOptions;
+    this.recentPacks = recentPacks;
+    this.ratings = ratings;
   }
 
   async saveConfig() {
@@ -67,6 +92,244 @@ class AppStore {
     this.saveConfig();
   }
 
+  setMuteButton(key: Key | null) {
+    if (!this.config) return;
+    this.config = { ...this.config, mute_button: key };
+    this.saveConfig();
+  }
+
+  setPauseButton(key: Key | null) {
+    if (!this.config) return;
+    this.config = { ...this.config, pause_button: key };
+    this.saveConfig();
+  }
+
+  setBlockButton(key: Key | null) {
+    if (!this.config) return;
+    this.config = { ...this.config, block_button: key };
+    this.saveConfig();
+  }
+
+  setFavoriteButton(key: Key | null) {
+    if (!this.config) return;
+    this.config = { ...this.config, favorite_button: key };
+    this.saveConfig();
+  }
+
+  setFavorites(favorites: FavoritesMode) {
+    if (!this.config) return;
+    this.config = { ...this.config, favorites };
+    this.saveConfig();
+  }
+
+  setInfoButton(key: Key | null) {
+    if (!this.config) return;
+    this.config = { ...this.config, info_button: key };
+    this.saveConfig();
+  }
+
+  setVideoHover(behaviour: VideoHoverBehaviour) {
+    if (!this.config) return;
+    this.config = { ...this.config, video_hover: behaviour };
+    this.saveConfig();
+  }
+
+  setVideoPerformance(videoPerformance: Partial<VideoPerformance>) {
+    if (!this.config) return;
+    this.config = {
+      ...this.config,
+      video_performance: { ...this.config.video_performance, ...videoPerformance },
+    };
+    this.saveConfig();
+  }
+
+  setImageCache(megabytes: number) {
+    if (!this.config) return;
+    this.config = { ...this.config, image_cache: { megabytes } };
+    this.saveConfig();
+  }
+
+  setVerifyMedia(verify: boolean) {
+    if (!this.config) return;
+    this.config = { ...this.config, verify_media: verify };
+    this.saveConfig();
+  }
+
+  setIsolateVideoDecoding(isolate: boolean) {
+    if (!this.config) return;
+    this.config = { ...this.config, isolate_video_decoding: isolate };
+    this.saveConfig();
+  }
+
+  setPauseWhileRecording(pause: boolean) {
+    if (!this.config) return;
+    this.config = { ...this.config, pause_while_recording: pause };
+    this.saveConfig();
+  }
+
+  setMinigames(minigames: boolean) {
+    if (!this.config) return;
+    this.config = { ...this.config, minigames };
+    this.saveConfig();
+  }
+
+  setDuckOtherApps(duck: boolean) {
+    if (!this.config) return;
+    this.config = { ...this.config, duck_other_apps: duck };
+    this.saveConfig();
+  }
+
+  setIncognito(incognito: boolean) {
+    if (!this.config) return;
+    this.config = { ...this.config, incognito };
+    this.saveConfig();
+  }
+
+  setCursorMargin(cursor_margin: number | null) {
+    if (!this.config) return;
+    this.config = { ...this.config, cursor_margin };
+    this.saveConfig();
+  }
+
+  setPauseWhileIdle(pause_while_idle: number | null) {
+    if (!this.config) return;
+    this.config = { ...this.config, pause_while_idle };
+    this.saveConfig();
+  }
+
+  setMaxRating(max_rating: number | null) {
+    if (!this.config) return;
+    this.config = { ...this.config, max_rating };
+    this.saveConfig();
+  }
+
+  setExcludedTags(excluded_tags: string[]) {
+    if (!this.config) return;
+    this.config = { ...this.config, excluded_tags };
+    this.saveConfig();
+  }
+
+  setBlendPack(blend_pack: BlendPack | null) {
+    if (!this.config) return;
+    this.config = { ...this.config, blend_pack };
+    this.saveConfig();
+  }
+
+  async pickBlendPack() {
+    const path = await api.pickBlendPack();
+    if (path === null) return;
+    this.setBlendPack({ path, percent: this.config?.blend_pack?.percent ?? 25 });
+  }
+
+  setSeed(seed: number | null) {
+    if (!this.config) return;
+    this.config = { ...this.config, seed };
+    this.saveConfig();
+  }
+
+  setDailyShuffle(daily_shuffle: boolean) {
+    if (!this.config) return;
+    this.config = { ...this.config, daily_shuffle };
+    this.saveConfig();
+  }
+
+  setMetricsPort(port: number | null) {
+    if (!this.config) return;
+    this.config = { ...this.config, metrics_port: port };
+    this.saveConfig();
+  }
+
+  setBatterySaver(batterySaver: Partial<BatterySaver>) {
+    if (!this.config) return;
+    this.config = {
+      ...this.config,
+      battery_saver: { ...this.config.battery_saver, ...batterySaver },
+    };
+    this.saveConfig();
+  }
+
+  setThrottle(throttle: Partial<Throttle>) {
+    if (!this.config) return;
+    this.config = {
+      ...this.config,
+      throttle: { ...this.config.throttle, ...throttle },
+    };
+    this.saveConfig();
+  }
+
+  setStartDelay(startDelay: Partial<StartDelay>) {
+    if (!this.config) return;
+    this.config = {
+      ...this.config,
+      start_delay: { ...this.config.start_delay, ...startDelay },
+    };
+    this.saveConfig();
+  }
+
+  setEscalation(escalation: Partial<Escalation>) {
+    if (!this.config) return;
+    this.config = {
+      ...this.config,
+      escalation: { ...this.config.escalation, ...escalation },
+    };
+    this.saveConfig();
+  }
+
+  setPacing(pacing: Partial<Pacing>) {
+    if (!this.config) return;
+    this.config = {
+      ...this.config,
+      pacing: { ...this.config.pacing, ...pacing },
+    };
+    this.saveConfig();
+  }
+
+  setPromptFailure(promptFailure: Partial<PromptFailure>) {
+    if (!this.config) return;
+    this.config = {
+      ...this.config,
+      prompt_failure: { ...this.config.prompt_failure, ...promptFailure },
+    };
+    this.saveConfig();
+  }
+
+  setHibernation(hibernation: Partial<Hibernation>) {
+    if (!this.config) return;
+    this.config = {
+      ...this.config,
+      hibernation: { ...this.config.hibernation, ...hibernation },
+    };
+    this.saveConfig();
+  }
+
+  setSchedule(schedule: Partial<Schedule>) {
+    if (!this.config) return;
+    this.config = {
+      ...this.config,
+      schedule: { ...this.config.schedule, ...schedule },
+    };
+    this.saveConfig();
+  }
+
+  setPromptLogging(prompt_logging: PromptLogging) {
+    if (!this.config) return;
+    this.config = { ...this.config, prompt_logging };
+    this.saveConfig();
+  }
+
+  setGoals(goals: Goal[]) {
+    if (!this.config) return;
+    this.config = { ...this.config, goals };
+    this.saveConfig();
+  }
+
+  async setLanguage(language: string | null) {
+    if (!this.config) return;
+    this.config = { ...this.config, language };
+    await this.saveConfig();
+    await i18n.load();
+  }
+
   setMonitorEnabled(id: string, enabled: boolean) {
     if (!this.config) return;
     let disabled = [...this.config.disabled_monitors];
@@ -84,7 +347,29 @@ class AppStore {
 
   async pickPack() {
     const result = await api.pickPack();
-    if (!result || !this.config) return;
+    if (result) await this.applyPack(result);
+  }
+
+  async openPackUrl(url: string) {
+    await this.applyPack(await api.openPackUrl(url));
+  }
+
+  async openRecentPack(path: string) {
+    await this.applyPack(await api.openRecentPack(path));
+  }
+
+  async setRecentPackPinned(path: string, pinned: boolean) {
+    this.recentPacks = await api.setRecentPackPinned(path, pinned);
+  }
+
+  async forgetRecentPack(path: string) {
+    this.recentPacks = await api.forgetRecentPack(path);
+  }
+
+  async applyPack(result: PickPackResult) {
+    this.recentPacks = await api.getRecentPacks();
+    this.ratings = result.ratings;
+    if (!this.config) return;
     this.config = { ...this.config, pack_path: result.pack_path };
     if (result.first_mode) {
       await this.setMode(result.first_mode, result.mode_groups);
@@ -95,6 +380,7 @@ class AppStore {
 
   async removePack() {
     await api.removePack();
+    this.ratings = [];
     if (!this.config) return;
     this.config = { ...this.config, pack_path: null };
     this.modeGroups = await api.getModeGroups();
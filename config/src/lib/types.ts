@@ -10,12 +10,201 @@ export interface ModeOptionsEntry {
 
 export interface ConfigDto {
   pack_path: string | null;
+  /** A second pack mixed into random picks. */
+  blend_pack: BlendPack | null;
   mode: ModeId;
   mode_options: ModeOptionsEntry[];
   panic_button: Key;
+  mute_button: Key | null;
+  pause_button: Key | null;
+  block_button: Key | null;
+  favorite_button: Key | null;
+  favorites: FavoritesMode;
+  info_button: Key | null;
   disabled_monitors: string[];
+  video_hover: VideoHoverBehaviour;
+  video_performance: VideoPerformance;
+  image_cache: ImageCache;
+  verify_media: boolean;
+  isolate_video_decoding: boolean;
+  /** The highest content rating level to show, counting from 0. Unset shows everything. */
+  max_rating: number | null;
+  /** Files and texts with any of these tags are never shown. */
+  excluded_tags: string[];
+  seed: number | null;
+  /** Seeds sessions from the date when `seed` is unset. */
+  daily_shuffle: boolean;
+  /** Unset doesn't serve metrics. */
+  metrics_port: number | null;
+  battery_saver: BatterySaver;
+  throttle: Throttle;
+  start_delay: StartDelay;
+  schedule: Schedule;
+  escalation: Escalation;
+  pacing: Pacing;
+  prompt_failure: PromptFailure;
+  minigames: boolean;
+  hibernation: Hibernation;
+  pause_while_recording: boolean;
+  duck_other_apps: boolean;
+  incognito: boolean;
+  /** Popups never spawn within this many pixels of the cursor. Unset lets them go anywhere. */
+  cursor_margin: number | null;
+  /** Seconds without input before pausing. Unset never pauses. */
+  pause_while_idle: number | null;
+  /** Unset follows the system's language. */
+  language: string | null;
+  goals: Goal[];
+  prompt_logging: PromptLogging;
 }
 
+export interface BlendPack {
+  path: string;
+  /** The share of random picks taken from this pack, 0-100. */
+  percent: number;
+}
+
+export type PromptLogging = "off" | "hashed" | "plaintext";
+
+export type FavoritesMode = "normal" | "boost" | "only";
+
+export type GoalStat = "minutes" | "popups" | "prompts" | "choices";
+
+export interface Goal {
+  name: string;
+  stat: GoalStat;
+  target: number;
+  popup_set?: string;
+}
+
+export interface SessionStats {
+  minutes: number;
+  popups: number;
+  prompts: number;
+  choices: number;
+  prompts_failed: number;
+  popups_closed: number;
+  links: number;
+  /** Minutes, counting time the session was paused for. */
+  uptime: number;
+}
+
+/** How the last session went. Goals are in the order they were reached, then the rest. */
+export interface SessionSummary {
+  /** Seconds since the Unix epoch. */
+  ended_at: number;
+  stats: SessionStats;
+  goals: Goal[];
+  reached: number;
+}
+
+export interface SessionTotals {
+  sessions: number;
+  stats: SessionStats;
+}
+
+export interface Stats {
+  last_session: SessionSummary | null;
+  totals: SessionTotals;
+}
+
+export interface BatterySaver {
+  enabled: boolean;
+  below_percent: number | null;
+  rate: number;
+}
+
+export interface Throttle {
+  enabled: boolean;
+  rate: number;
+}
+
+export interface StartDelay {
+  /** 0 starts straight away. */
+  seconds: number;
+  countdown: boolean;
+}
+
+export interface Escalation {
+  enabled: boolean;
+  start_percent: number;
+  end_percent: number;
+  minutes: number;
+}
+
+export interface Pacing {
+  enabled: boolean;
+  min_percent: number;
+  max_percent: number;
+  step_percent: number;
+  quick_close_seconds: number;
+}
+
+export interface PromptFailure {
+  extra_popups: number;
+  /** 100 leaves the intensity alone. */
+  boost_percent: number;
+  boost_seconds: number;
+}
+
+export interface Hibernation {
+  enabled: boolean;
+  awake_minutes: number;
+  min_minutes: number;
+  max_minutes: number;
+  burst_percent: number;
+}
+
+export interface Schedule {
+  enabled: boolean;
+  windows: ScheduleWindow[];
+}
+
+export interface ScheduleWindow {
+  /** The days the window starts on, Monday first. */
+  days: boolean[];
+  /** Minutes past midnight. Ending before the start runs past midnight. */
+  start: number;
+  end: number;
+}
+
+export interface VideoPerformance {
+  /** Unset plays videos at their own frame rate. */
+  max_fps: number | null;
+  downscale: boolean;
+}
+
+export interface ImageCache {
+  /** 0 keeps no decoded images. */
+  megabytes: number;
+}
+
+export interface PreviewDto {
+  file_name: string;
+  file_type: string;
+  width: number | null;
+  height: number | null;
+  tags: string[];
+  src: string | null;
+  full_size: boolean;
+  matching: number;
+}
+
+/** What a pack is made up of, from the totals kept in its index. */
+export interface PackStats {
+  types: { file_type: string; count: number; bytes: number }[];
+  /** Largest tags first. */
+  tags: { tag: string; count: number; bytes: number }[];
+}
+
+/** How many of each media type and text kind the pack has, and how many the session can show. */
+export interface SelectionCounts {
+  media: { kind: string; total: number; matching: number }[];
+  texts: { kind: string; total: number; matching: number }[];
+}
+
+export type VideoHoverBehaviour = "None" | "Mute" | "Pause" | "Close";
+
 export interface Key {
   name: string;
   code: string;
@@ -85,12 +274,56 @@ export type OptionEntryDto =
   | { kind: "Option" } & ModeOptionDto
   | { kind: "Group" } & OptionGroupEntryDto;
 
+export interface RecentPack {
+  path: string;
+  id: string;
+  name: string;
+  /** Unix timestamp, in seconds. */
+  last_opened: number;
+  pinned: boolean;
+}
+
 export interface PickPackResult {
   pack_path: string;
   mode_groups: ModeGroupDto[];
   first_mode: ModeId | null;
+  /** The names of the pack's content rating levels, mildest first. */
+  ratings: string[];
 }
 
+/** Sent as `pack-link` events while a `lewd://add-pack` link is handled. */
+export type PackLinkEvent =
+  | { status: "downloading"; file_name: string; received: number; total: number | null }
+  | ({ status: "added" } & PickPackResult)
+  | { status: "failed"; error: string };
+
 export interface UploadModeResult {
   mode_groups: ModeGroupDto[];
 }
+
+export interface UserDataStatus {
+  /** The settings password has to be typed in before the user data can be read. */
+  locked: boolean;
+  has_password: boolean;
+}
+
+export interface PrivacyReport {
+  /** Ids of `config-privacy-<id>` messages. */
+  access: string[];
+  never_accessed: string[];
+}
+
+export type FootprintKind = "wallpaper" | "video-buffer" | "log" | "lock";
+
+/** A file the player has left on disk. */
+export interface FootprintEntry {
+  path: string;
+  kind: FootprintKind;
+  size: number;
+}
+
+export interface LocaleDto {
+  language: string;
+  languages: { code: string; name: string }[];
+  messages: Record<string, string>;
+}
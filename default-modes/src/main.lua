@@ -49,6 +49,11 @@ end)
 ---    movement_enabled: boolean,
 ---    movement_speed_min: number,
 ---    movement_speed_max: number,
+---    toasts_enabled: boolean,
+---    toast_frequency: number,
+---    toast_duration: number,
+---    toast_corner: "top-left" | "top-right" | "bottom-left" | "bottom-right",
+---    minigame_chance: number,
 ---}
 
 -- ── Helpers ────────────────────────────────────────────────────────────────
@@ -158,8 +163,14 @@ end
 
 -- ── Spawning ───────────────────────────────────────────────────────────────
 
+local function max_popups()
+	if not config.max_popups then return nil end
+	return math.floor(config.max_popups * lewdware.intensity() + 0.5)
+end
+
 local function should_spawn()
-	return not (#popup_types == 0 or dormant or (config.max_popups and popup_count >= config.max_popups))
+	local max = max_popups()
+	return not (#popup_types == 0 or dormant or (max and popup_count >= max))
 end
 
 -- spawn_opts: optional table with x, y (center coords), monitor.
@@ -171,14 +182,21 @@ local function open_popup(spawn_opts, close_trigger)
 
 	if not should_spawn() then return end
 
-	local media = lewdware.media.random({ type = popup_types })
-	if not media or not should_spawn() then return end
-
 	local window
-	if media.type == "image" then
-		window = lewdware.spawn_image_popup(media, spawn_opts)
-	elseif media.type == "video" then
-		window = lewdware.spawn_video_popup(media, spawn_opts)
+	-- Nothing comes back if the user has turned minigames off.
+	if math.random() < config.minigame_chance then
+		window = lewdware.spawn_minigame(spawn_opts)
+	end
+
+	if not window then
+		local media = lewdware.media.random({ type = popup_types })
+		if not media or not should_spawn() then return end
+
+		if media.type == "image" then
+			window = lewdware.spawn_image_popup(media, spawn_opts)
+		elseif media.type == "video" then
+			window = lewdware.spawn_video_popup(media, spawn_opts)
+		end
 	end
 
 	if not window then return end
@@ -233,9 +251,19 @@ local function next_delay_ms()
 	end
 end
 
+-- How long to wait before checking the intensity dial again while it's turned down to 0.
+local IDLE_CHECK_MS = 1000
+
 local function schedule_spawning()
 	if dormant then return end
-	lewdware.after(next_delay_ms(), function()
+
+	local intensity = lewdware.intensity()
+	if intensity <= 0 then
+		lewdware.after(IDLE_CHECK_MS, schedule_spawning)
+		return
+	end
+
+	lewdware.after(math.max(1, math.floor(next_delay_ms() / intensity)), function()
 		if not dormant then
 			open_popup()
 		end
@@ -264,6 +292,23 @@ spawn_audio = function()
 	end
 end
 
+-- ── Toasts ─────────────────────────────────────────────────────────────────
+
+local function schedule_toasts()
+	lewdware.after(secs(config.toast_frequency), function()
+		schedule_toasts()
+		if dormant then return end
+
+		local toast = lewdware.media.random_toast()
+		if toast then
+			lewdware.spawn_toast(toast, {
+				corner = config.toast_corner,
+				duration = secs(config.toast_duration),
+			})
+		end
+	end)
+end
+
 -- ── Dormancy ───────────────────────────────────────────────────────────────
 
 local function schedule_dormancy()
@@ -303,6 +348,10 @@ if config.audio_enabled then
 	spawn_audio()
 end
 
+if config.toasts_enabled then
+	schedule_toasts()
+end
+
 if config.dormancy_enabled then
 	schedule_dormancy()
 end
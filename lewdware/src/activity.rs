@@ -0,0 +1,63 @@
+//! Pauses the session while nobody's using the computer (see `pause_while_idle` in the config), so
+//! timers only run during actual use. The hotkey listener sees every key press and mouse movement
+//! anyway, so it notes the time of the last one, and a plain thread polls that.
+
+use std::{
+    sync::Mutex,
+    thread,
+    time::{Duration, Instant},
+};
+
+use winit::event_loop::EventLoopProxy;
+
+use crate::app::UserEvent;
+
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// When the hotkey listener last saw any input. Only tracked when `pause_while_idle` is set.
+static LAST_INPUT: Mutex<Option<Instant>> = Mutex::new(None);
+
+/// Called by the hotkey listener on every key press, click and mouse movement.
+pub fn record_input() {
+    *LAST_INPUT.lock().unwrap() = Some(Instant::now());
+}
+
+/// Starts a thread that sends [`UserEvent::SetIdle`] whenever there's been no input for
+/// `idle_after`, and again once there is. Does nothing if it's `None`.
+pub fn spawn_activity_watcher(
+    event_loop_proxy: EventLoopProxy<UserEvent>,
+    idle_after: Option<Duration>,
+) {
+    let Some(idle_after) = idle_after else {
+        return;
+    };
+
+    // Starting the session counts as using the computer.
+    record_input();
+
+    thread::spawn(move || {
+        let mut idle = false;
+
+        loop {
+            let now_idle = LAST_INPUT
+                .lock()
+                .unwrap()
+                .is_some_and(|last_input| last_input.elapsed() >= idle_after);
+
+            if now_idle != idle {
+                idle = now_idle;
+                tracing::info!("User {}", if idle { "went idle" } else { "is back" });
+
+                if event_loop_proxy
+                    .send_event(UserEvent::SetIdle { idle })
+                    .is_err()
+                {
+                    // The event loop has shut down.
+                    break;
+                }
+            }
+
+            thread::sleep(POLL_INTERVAL);
+        }
+    });
+}
@@ -1,9 +1,11 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::collections::hash_map::Entry;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 use anyhow::anyhow;
-use rand::random_range;
+use shared::locale::{tr, tr_args};
+use shared::read_pack::OpenProgress;
 use shared::user_config::AppConfig;
 use url::{Host, Url};
 use winit::dpi::LogicalPosition;
@@ -13,45 +15,90 @@ use winit::{
     application::ApplicationHandler,
     event::{ElementState, WindowEvent},
     event_loop::ActiveEventLoop,
-    window::WindowId,
+    window::{WindowId, WindowLevel},
 };
 
 use crate::audio::AudioPlayer;
 use crate::error::{LewdwareError, MonitorError, Result};
+use crate::governor::Governor;
 use crate::lua::{
-    self, AudioAction, ChoiceWindowOption, FontSize, LuaRequest, LuaThreadHandle, Notification,
+    self, AudioAction, ChoiceWindowOption, Coord, FontSize, LuaRequest, LuaThreadHandle, Notification,
     SpawnWindowOpts, TextFont, TextStyle, WallpaperMode, WindowAction, WindowProps,
     start_lua_thread,
 };
 use crate::media::{FileOrPath, ImageData};
-use crate::monitor::Monitors;
-use crate::utils::{calculate_media_popup_size, calculate_text_popup_size};
-use crate::video::VideoDecoder;
+use crate::metrics::METRICS;
+use crate::monitor::{Monitor, Monitors};
+use crate::pause::PauseReasons;
+use crate::utils::{
+    avoid_cursor, calculate_media_popup_size, calculate_text_popup_size, cursor_position,
+    random_position, set_tray_status, update_tray_intensity, update_tray_paused,
+};
+use crate::video::{VideoDecoder, VideoStats};
 use crate::wgpu::WgpuState;
 use crate::window::{
-    ChoiceWindow, HEADER_HEIGHT, ImageWindow, InnerWindow, PromptWindow, TextWindow, VideoWindow,
-    WindowOpts, WindowPool, WindowType,
+    ChoiceWindow, DEFAULT_PROMPT_SIZE, HEADER_HEIGHT, ImageWindow, InnerWindow, MinigameStyle,
+    MinigameWindow, PopupMedia, PromptStyle, PromptWindow, TextWindow, VideoWindow, WindowOpts,
+    WindowPool, WindowType,
 };
 
 /// The main app.
 /// * `windows`: A map containing all the windows spawned by the app. Since dropping a winit window
 ///   closes it, we can close windows by removing them from this map.
 /// * `default_wallpaper`: Stores the user's default wallpaper, so we can restore it on panic.
+/// * `video_wallpapers`: Windows playing a video wallpaper; see [`Self::set_video_wallpaper`].
 pub struct LewdwareApp {
     running: bool,
-    _config: Arc<AppConfig>,
+    config: Arc<AppConfig>,
     wgpu_state: Option<Arc<WgpuState>>,
     windows: HashMap<WindowId, WindowType>,
     audio_players: HashMap<u64, AudioPlayer>,
     current_audio_id: u64,
+    muted: bool,
+    // Kinds of media errors the user has already been told about (see `report_media_error`).
+    reported_media_errors: HashSet<&'static str>,
     default_wallpaper: Option<String>,
+    // Background windows playing a video wallpaper, one per monitor. These are kept in `windows`
+    // like any other video, but aren't counted as popups.
+    video_wallpapers: Vec<WindowId>,
+    video_wallpapers_paused: bool,
+    // Whether the session is paused, what's holding it back, and the audio and video popups that
+    // were paused because of it (so only those are resumed).
+    paused: bool,
+    pause_reasons: PauseReasons,
+    paused_audio: Vec<u64>,
+    paused_videos: Vec<WindowId>,
+    // The windows that were hidden because the screen is being recorded or shared (see
+    // `set_captured`).
+    hidden_windows: Vec<WindowId>,
+    // Whether the laptop is running on its battery (see `set_battery_saver`), and whether the
+    // governor has found the app to be overloaded (see `set_throttled`).
+    battery_saver: bool,
+    throttled: bool,
+    governor: Governor,
+    // Frame pacing of every video closed so far, logged on exit.
+    video_stats: VideoStats,
+    // The window under the cursor, and the focused one, which the block, favorite and info
+    // hotkeys act on (see `hotkey_target`).
+    hovered_window: Option<WindowId>,
+    focused_window: Option<WindowId>,
     lua_request_rx: tokio::sync::mpsc::Receiver<lua::LuaRequest>,
     lua_event_tx: tokio::sync::mpsc::UnboundedSender<lua::Event>,
     lua_thread_handle: LuaThreadHandle,
     monitors: Monitors,
     window_pool: WindowPool,
+    wipe_on_exit: bool,
 }
 
+/// Video wallpapers are paused while at least this many video popups are open.
+const MAX_VIDEO_POPUPS_WITH_WALLPAPER: usize = 3;
+
+/// How soon to update a window again after updating it failed.
+const ERROR_RETRY: Duration = Duration::from_millis(100);
+
+/// How long quitting waits for video decode helpers to exit once they've been killed.
+const CHILD_SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(2);
+
 enum WindowSizeBehaviour {
     ResizeWithMedia {
         width: u32,
@@ -69,11 +116,50 @@ enum WindowSizeBehaviour {
     },
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum UserEvent {
     Exit,
+    /// Sent by the tray's privacy wipe entry. Quits, then wipes everything the session wrote to
+    /// disk (see [`shared::footprint`]).
+    WipeAndExit,
     LuaRequest,
     AudioFinish { id: u64 },
+    ToggleMute,
+    /// Sent by the tray's pause entry. Pauses the session until it's sent again.
+    TogglePause,
+    /// Sent by the tray's intensity dial. Forwarded to the Lua thread; never saved to the config.
+    SetIntensity { level: u8 },
+    /// Sent by the lock watcher when the screen is locked or unlocked. The session is paused
+    /// while it's locked.
+    SetLocked { locked: bool },
+    /// Sent by the capture watcher when the screen starts or stops being recorded or shared (see
+    /// `pause_while_recording` in the config).
+    SetCaptured { captured: bool },
+    /// Sent by the schedule watcher when the session goes in or out of the hours set in the
+    /// config. The session is paused outside them.
+    SetScheduled { active: bool },
+    /// Sent by the activity watcher when there's been no input for a while, and once there is
+    /// again (see `pause_while_idle` in the config). The session is paused in between.
+    SetIdle { idle: bool },
+    /// Sent by the hibernation watcher when the session goes quiet, and again when it wakes up
+    /// (see [`shared::user_config::Hibernation`]).
+    SetHibernating { hibernating: bool },
+    /// Sent by the power watcher when the laptop is unplugged or plugged back in (see
+    /// [`shared::user_config::BatterySaver`]).
+    SetBatterySaver { on: bool },
+    /// Sent by the block hotkey (see `block_button` in the config).
+    BlockMedia,
+    /// Sent by the favorite hotkey (see `favorite_button` in the config).
+    ToggleFavorite,
+    /// Sent by the info hotkey (see `info_button` in the config).
+    ToggleInfo,
+    /// Sent by the tray's credits entry. Forwarded to the Lua thread, which has the pack's
+    /// metadata.
+    ShowCredits,
+    /// Sent by the media manager thread when it fails to load something.
+    MediaError { kind: &'static str, message: String },
+    /// Sent while the pack is being opened, which can take a few seconds for large packs.
+    PackOpenProgress(OpenProgress),
 }
 
 impl LewdwareApp {
@@ -117,20 +203,41 @@ impl LewdwareApp {
 
         Ok(Self {
             running: false,
-            _config: config,
+            config,
             wgpu_state: wgpu_state,
             windows: HashMap::new(),
             audio_players: HashMap::new(),
             current_audio_id: 0,
+            muted: false,
+            reported_media_errors: HashSet::new(),
             default_wallpaper: wallpaper,
+            video_wallpapers: Vec::new(),
+            video_wallpapers_paused: false,
+            paused: false,
+            paused_audio: Vec::new(),
+            paused_videos: Vec::new(),
+            pause_reasons: PauseReasons::default(),
+            hidden_windows: Vec::new(),
+            battery_saver: false,
+            throttled: false,
+            governor: Governor::new(),
+            video_stats: VideoStats::default(),
+            hovered_window: None,
+            focused_window: None,
             lua_request_rx,
             lua_event_tx,
             lua_thread_handle,
             monitors,
             window_pool: WindowPool::new(),
+            wipe_on_exit: false,
         })
     }
 
+    /// Whether the user asked for the session's files to be wiped once it's over.
+    pub fn wipe_on_exit(&self) -> bool {
+        self.wipe_on_exit
+    }
+
     /// Resolve a [`SpawnWindowOpts`] into a fully computed [`WindowOpts`], factoring in the
     /// monitor layout, GPU availability, and size constraints.
     fn resolve_window_opts(
@@ -204,6 +311,9 @@ impl LewdwareApp {
             outer_height += HEADER_HEIGHT + 2;
         }
 
+        // Only positions picked at random are moved out of the cursor's way.
+        let movable = (spawn_opts.x.is_none(), spawn_opts.y.is_none());
+
         let x: i32 = {
             let v = spawn_opts
                 .x
@@ -237,6 +347,18 @@ impl LewdwareApp {
             }
         };
 
+        let (x, y) = match (self.config.cursor_margin, cursor_position(&monitor_handle)) {
+            (Some(margin), Some(cursor)) if movable.0 || movable.1 => avoid_cursor(
+                (x, y),
+                (outer_width, outer_height),
+                (monitor_size.width, monitor_size.height),
+                cursor,
+                margin,
+                movable,
+            ),
+            _ => (x, y),
+        };
+
         let position = LogicalPosition::new(monitor_position.x + x, monitor_position.y + y);
 
         Ok(WindowOpts {
@@ -302,6 +424,25 @@ impl LewdwareApp {
     /// Release a window back to the pool. Moving offscreen rather than unmapping avoids
     /// the KWin strut relayout freeze on Dock-type windows.
     fn close_window(&mut self, window_type: WindowType) {
+        tracing::debug!(
+            "Closing window after {:?}",
+            window_type.popup().created_at().elapsed()
+        );
+        if let WindowType::Video(video_window) = &window_type {
+            let stats = video_window.stats();
+            tracing::info!("Video closed: {stats}");
+            self.video_stats.add(&stats);
+            METRICS.video_closed(&stats);
+        }
+        METRICS.window_closed();
+        // Pooled windows keep their id, so a stale one could block whatever the window shows next.
+        let id = window_type.inner_window().window().id();
+        if self.hovered_window == Some(id) {
+            self.hovered_window = None;
+        }
+        if self.focused_window == Some(id) {
+            self.focused_window = None;
+        }
         let transparent = window_type.inner_window().transparent();
         // Move offscreen before dropping InnerWindow so the surface is still alive when KWin
         // processes the XMoveWindow. Without this, transparent (wgpu) windows flash black at
@@ -312,10 +453,28 @@ impl LewdwareApp {
             .set_outer_position(LogicalPosition::new(-32000i32, -32000i32));
         let arc_window = window_type.into_inner_window().into_arc_window();
         self.window_pool.release(arc_window, transparent);
+
+        self.update_video_wallpapers();
+    }
+
+    /// The popup the block, favorite and info hotkeys act on, and the media it shows: the one under
+    /// the cursor, or else the focused one. `None` if that isn't showing media from the pack.
+    fn hotkey_target(&self) -> Option<(WindowId, u64)> {
+        let id = self.hovered_window.or(self.focused_window)?;
+        let media_id = self.windows.get(&id)?.popup().media_id()?;
+
+        Some((id, media_id))
+    }
+
+    fn send_lua_event(&self, event: lua::Event) {
+        if let Err(err) = self.lua_event_tx.send(event) {
+            tracing::error!("{err}");
+        }
     }
 
     fn spawn_image(
         &mut self,
+        media: PopupMedia,
         data: ImageData,
         opts: SpawnWindowOpts,
         event_loop: &ActiveEventLoop,
@@ -337,6 +496,7 @@ impl LewdwareApp {
 
         let mut image_window =
             ImageWindow::new(inner_window, data).map_err(|err| LewdwareError::WindowError(err))?;
+        image_window.set_media(media);
 
         // Render the image while still offscreen so the compositor has valid pixels before
         // XMoveWindow fires. For CPU (softbuffer) windows, X11 protocol ordering guarantees
@@ -353,18 +513,20 @@ impl LewdwareApp {
         image_window.inner_window.gpu_sync(idx);
 
         if visible {
-            image_window.inner_window.set_visible(true);
+            self.show_window(props.window_id, &mut image_window.inner_window);
         }
 
-        self.windows
-            .insert(props.window_id.clone(), WindowType::Image(image_window));
+        let window = WindowType::Image(image_window);
+        METRICS.window_opened(&window);
+        self.windows.insert(props.window_id.clone(), window);
 
         Ok(props)
     }
 
     fn spawn_video(
         &mut self,
-        video_player: VideoDecoder,
+        media: PopupMedia,
+        mut video_player: VideoDecoder,
         loop_video: bool,
         opts: SpawnWindowOpts,
         event_loop: &ActiveEventLoop,
@@ -387,21 +549,32 @@ impl LewdwareApp {
 
         window.request_redraw();
 
+        if self.config.video_performance.downscale {
+            let size = window.inner_size();
+            video_player.downscale_to(size.width, size.height);
+        }
+
         let mut video_window = VideoWindow::new(window, video_player, loop_video)
             .map_err(|err| LewdwareError::WindowError(err))?;
+        video_window.set_muted(self.muted);
+        video_window.set_hover_behaviour(self.config.video_hover);
+        video_window.set_media(media);
 
         if visible {
             if let Err(e) = video_window.inner_window.pre_show() {
                 tracing::warn!("video pre-show failed: {e}");
             }
-            video_window.inner_window.set_visible(true);
+            self.show_window(props.window_id, &mut video_window.inner_window);
         }
 
-        self.windows
-            .insert(props.window_id.clone(), WindowType::Video(video_window));
+        let window = WindowType::Video(video_window);
+        METRICS.window_opened(&window);
+        self.windows.insert(props.window_id.clone(), window);
 
         tracing::info!("{}", self.windows.len());
 
+        self.update_video_wallpapers();
+
         Ok(props)
     }
 
@@ -410,6 +583,7 @@ impl LewdwareApp {
         text: Option<String>,
         placeholder: Option<String>,
         initial_value: Option<String>,
+        style: PromptStyle,
         window_opts: SpawnWindowOpts,
         event_loop: &ActiveEventLoop,
     ) -> Result<WindowProps> {
@@ -418,8 +592,8 @@ impl LewdwareApp {
         let resolved = self.resolve_window_opts(
             window_opts,
             WindowSizeBehaviour::UseDefaults {
-                width: 400,
-                height: 400,
+                width: DEFAULT_PROMPT_SIZE,
+                height: DEFAULT_PROMPT_SIZE,
             },
             transparent,
             transparent,
@@ -428,18 +602,19 @@ impl LewdwareApp {
         let (window, props) = self.create_window(resolved, event_loop)?;
         let visible = props.visible;
 
-        let mut prompt_window = PromptWindow::new(window, text, placeholder, initial_value)
+        let mut prompt_window = PromptWindow::new(window, text, placeholder, initial_value, style)
             .map_err(|err| LewdwareError::WindowError(err))?;
 
         if visible {
             if let Err(e) = prompt_window.inner_window.pre_show() {
                 tracing::warn!("prompt pre-show failed: {e}");
             }
-            prompt_window.inner_window.set_visible(true);
+            self.show_window(props.window_id, &mut prompt_window.inner_window);
         }
 
-        self.windows
-            .insert(props.window_id.clone(), WindowType::Prompt(prompt_window));
+        let window = WindowType::Prompt(prompt_window);
+        METRICS.window_opened(&window);
+        self.windows.insert(props.window_id.clone(), window);
 
         Ok(props)
     }
@@ -473,11 +648,12 @@ impl LewdwareApp {
             if let Err(e) = choice_window.inner_window.pre_show() {
                 tracing::warn!("choice pre-show failed: {e}");
             }
-            choice_window.inner_window.set_visible(true);
+            self.show_window(props.window_id, &mut choice_window.inner_window);
         }
 
-        self.windows
-            .insert(props.window_id.clone(), WindowType::Choice(choice_window));
+        let window = WindowType::Choice(choice_window);
+        METRICS.window_opened(&window);
+        self.windows.insert(props.window_id.clone(), window);
 
         Ok(props)
     }
@@ -524,26 +700,279 @@ impl LewdwareApp {
             if let Err(e) = text_window.inner_window.pre_show() {
                 tracing::warn!("choice pre-show failed: {e}");
             }
-            text_window.inner_window.set_visible(true);
+            self.show_window(props.window_id, &mut text_window.inner_window);
         }
 
-        self.windows
-            .insert(props.window_id.clone(), WindowType::Text(text_window));
+        let window = WindowType::Text(text_window);
+        METRICS.window_opened(&window);
+        self.windows.insert(props.window_id.clone(), window);
 
         Ok(props)
     }
 
+    /// Spawns nothing if the user has turned minigames off, so modes can fall back to something
+    /// else.
+    fn spawn_minigame(
+        &mut self,
+        style: MinigameStyle,
+        window_opts: SpawnWindowOpts,
+        event_loop: &ActiveEventLoop,
+    ) -> Result<Option<WindowProps>> {
+        if !self.config.minigames {
+            return Ok(None);
+        }
+
+        let auto_transparent = window_opts.opacity.map_or(false, |o| o < 1.0);
+        let transparent = window_opts.transparent.unwrap_or(auto_transparent);
+        let resolved = self.resolve_window_opts(
+            window_opts,
+            WindowSizeBehaviour::UseDefaults {
+                width: 400,
+                height: 400,
+            },
+            transparent,
+            transparent,
+            event_loop,
+        )?;
+        let (window, props) = self.create_window(resolved, event_loop)?;
+        let visible = props.visible;
+
+        let mut minigame_window =
+            MinigameWindow::new(window, style).map_err(|err| LewdwareError::WindowError(err))?;
+
+        if visible {
+            if let Err(e) = minigame_window.inner_window.pre_show() {
+                tracing::warn!("minigame pre-show failed: {e}");
+            }
+            self.show_window(props.window_id, &mut minigame_window.inner_window);
+        }
+
+        let window = WindowType::Minigame(minigame_window);
+        METRICS.window_opened(&window);
+        self.windows.insert(props.window_id.clone(), window);
+
+        Ok(Some(props))
+    }
+
     fn spawn_audio(&mut self, audio_player: AudioPlayer) -> u64 {
         let id = self.current_audio_id;
         self.current_audio_id += 1;
 
+        audio_player.set_muted(self.muted);
         audio_player.play();
         self.audio_players.insert(id, audio_player);
+        METRICS.audio_started();
 
         id
     }
 
+    /// Mutes or unmutes every audio track and video, including ones spawned later.
+    fn set_muted(&mut self, muted: bool) {
+        tracing::info!("Setting muted to {muted}");
+        self.muted = muted;
+
+        for audio_player in self.audio_players.values() {
+            audio_player.set_muted(muted);
+        }
+
+        for window in self.windows.values_mut() {
+            if let WindowType::Video(video_window) = window {
+                video_window.set_muted(muted);
+            }
+        }
+    }
+
+    /// Pauses the session if anything's holding it back, or resumes it if nothing is, and shows
+    /// why in the tray.
+    fn update_paused(&mut self) {
+        let was_paused = self.paused;
+        self.set_paused(self.pause_reasons.is_paused());
+
+        // Left alone otherwise, since it might be showing something else (e.g. the pack opening).
+        if was_paused || self.paused {
+            set_tray_status(self.pause_reasons.status().map(tr).as_deref());
+        }
+    }
+
+    /// Pauses or resumes the session: the mode's timers are held back, and audio and video popups
+    /// stop playing. Anything the mode paused itself stays paused on resuming.
+    fn set_paused(&mut self, paused: bool) {
+        if paused == self.paused {
+            return;
+        }
+        tracing::info!("Setting paused to {paused}");
+        self.paused = paused;
+
+        if let Err(err) = self.lua_event_tx.send(lua::Event::SetPaused { paused }) {
+            tracing::error!("{err}");
+        }
+
+        if paused {
+            for (id, audio_player) in &self.audio_players {
+                if !audio_player.is_paused() {
+                    audio_player.pause();
+                    self.paused_audio.push(*id);
+                }
+            }
+
+            for (id, window) in self.windows.iter_mut() {
+                if let WindowType::Video(video_window) = window
+                    && !self.video_wallpapers.contains(id)
+                    && !video_window.is_paused()
+                {
+                    video_window.pause();
+                    self.paused_videos.push(*id);
+                }
+            }
+        } else {
+            for id in std::mem::take(&mut self.paused_audio) {
+                if let Some(audio_player) = self.audio_players.get(&id) {
+                    audio_player.play();
+                }
+            }
+
+            for id in std::mem::take(&mut self.paused_videos) {
+                if let Some(WindowType::Video(video_window)) = self.windows.get_mut(&id) {
+                    video_window.play();
+                }
+            }
+        }
+    }
+
+    /// Hides every window and pauses the session while the screen is being recorded or shared, and
+    /// shows the windows again once it stops. The user's own wallpaper is put back in the meantime;
+    /// the mode's comes back the next time it changes the wallpaper.
+    fn set_captured(&mut self, captured: bool) {
+        if captured == self.pause_reasons.captured {
+            return;
+        }
+        tracing::info!("Setting captured to {captured}");
+        self.pause_reasons.captured = captured;
+
+        if captured {
+            for (id, window) in self.windows.iter_mut() {
+                let inner_window = window.inner_window_mut();
+                if inner_window.is_visible() {
+                    inner_window.set_visible(false);
+                    self.hidden_windows.push(*id);
+                }
+            }
+
+            if let Some(wallpaper) = &self.default_wallpaper
+                && let Err(err) = wallpaper::set_from_path(wallpaper)
+            {
+                tracing::error!("Error setting wallpaper back to default: {}", err);
+            }
+        } else {
+            for id in std::mem::take(&mut self.hidden_windows) {
+                if let Some(window) = self.windows.get_mut(&id) {
+                    window.inner_window_mut().set_visible(true);
+                }
+            }
+        }
+
+        self.update_paused();
+        self.update_video_wallpapers();
+    }
+
+    /// Closes every popup and stops all audio, and pauses the session until it wakes up again,
+    /// when the mode is told to make up for it with a burst.
+    fn set_hibernating(&mut self, hibernating: bool) {
+        if hibernating == self.pause_reasons.hibernating {
+            return;
+        }
+        tracing::info!("Setting hibernating to {hibernating}");
+        self.pause_reasons.hibernating = hibernating;
+
+        if hibernating {
+            let popups: Vec<WindowId> = self
+                .windows
+                .keys()
+                .filter(|id| !self.video_wallpapers.contains(id))
+                .copied()
+                .collect();
+            for id in popups {
+                if let Some(window_type) = self.windows.remove(&id) {
+                    self.close_window(window_type);
+                }
+            }
+
+            // Each one sends `AudioFinish` once it's stopped, which tells the mode.
+            for audio_player in self.audio_players.values() {
+                audio_player.stop();
+            }
+        } else {
+            self.send_lua_event(lua::Event::Wake {
+                burst: self.config.hibernation.burst_percent as f64 / 100.0,
+            });
+        }
+
+        self.update_paused();
+    }
+
+    /// Shows a newly spawned window, or holds it back until the screen stops being captured.
+    fn show_window(&mut self, id: WindowId, inner_window: &mut InnerWindow) {
+        if self.pause_reasons.captured {
+            self.hidden_windows.push(id);
+        } else {
+            inner_window.set_visible(true);
+        }
+    }
+
+    /// Turns the battery saver on or off. On top of the load limits (see
+    /// [`Self::update_load_limits`]), wallpaper changes are ignored while it's on.
+    fn set_battery_saver(&mut self, on: bool) {
+        if on == self.battery_saver {
+            return;
+        }
+        tracing::info!("Setting battery saver to {on}");
+        self.battery_saver = on;
+        self.update_load_limits();
+    }
+
+    fn set_throttled(&mut self, throttled: bool) {
+        if throttled == self.throttled {
+            return;
+        }
+        self.throttled = throttled;
+        self.update_load_limits();
+    }
+
+    /// While the battery saver is on or the app is throttled, spawn rates are scaled down and
+    /// random queries return images instead of videos on the Lua thread, and video wallpapers
+    /// are paused.
+    fn update_load_limits(&mut self) {
+        let mut rate = 1.0;
+        if self.battery_saver {
+            rate *= self.config.battery_saver.rate;
+        }
+        if self.throttled {
+            rate *= self.config.throttle.rate;
+        }
+        let videos = !self.battery_saver && !self.throttled;
+
+        if let Err(err) = self
+            .lua_event_tx
+            .send(lua::Event::SetLoadLimits { rate, videos })
+        {
+            tracing::error!("{err}");
+        }
+
+        self.update_video_wallpapers();
+    }
+
     fn set_wallpaper(&mut self, file: FileOrPath, mode: Option<WallpaperMode>) -> Result<()> {
+        if self.battery_saver {
+            tracing::info!("Not changing the wallpaper while saving battery");
+            return Ok(());
+        }
+        if self.pause_reasons.captured {
+            tracing::info!("Not changing the wallpaper while the screen is being captured");
+            return Ok(());
+        }
+
+        self.close_video_wallpapers();
+
         wallpaper::set_from_path(file.path().to_str().ok_or(LewdwareError::Internal(
             "Tempfile does not have valid UTF-8 path",
         ))?)
@@ -566,7 +995,118 @@ impl LewdwareApp {
         Ok(())
     }
 
-    fn reset_wallpaper(&self) {
+    /// Plays a video wallpaper in a borderless, click-through window at the bottom of the stack
+    /// on each monitor, since most platforms have no native support for video wallpapers.
+    fn set_video_wallpaper(
+        &mut self,
+        video_players: Vec<(Monitor, VideoDecoder)>,
+        event_loop: &ActiveEventLoop,
+    ) -> Result<()> {
+        if self.battery_saver {
+            tracing::info!("Not changing the wallpaper while saving battery");
+            return Ok(());
+        }
+        if self.pause_reasons.captured {
+            tracing::info!("Not changing the wallpaper while the screen is being captured");
+            return Ok(());
+        }
+
+        self.close_video_wallpapers();
+
+        for (monitor, video_player) in video_players {
+            let opts = SpawnWindowOpts {
+                x: Some(Coord::Pixel(0)),
+                y: Some(Coord::Pixel(0)),
+                width: Some(Coord::Percent { percent: 100.0 }),
+                height: Some(Coord::Percent { percent: 100.0 }),
+                monitor: Some(monitor),
+                decorations: false,
+                closeable: false,
+                transparent: Some(false),
+                click_through: true,
+                ..Default::default()
+            };
+            let window_opts = self.resolve_window_opts(
+                opts,
+                WindowSizeBehaviour::UseDefaults {
+                    width: video_player.width() as u32,
+                    height: video_player.height() as u32,
+                },
+                true,
+                false,
+                event_loop,
+            )?;
+            let (window, props) = self.create_window(window_opts, event_loop)?;
+            window.window().set_window_level(WindowLevel::AlwaysOnBottom);
+
+            let mut video_window = VideoWindow::new(window, video_player, true)
+                .map_err(|err| LewdwareError::WindowError(err))?;
+            video_window.set_muted(true);
+
+            if let Err(e) = video_window.inner_window.pre_show() {
+                tracing::warn!("video wallpaper pre-show failed: {e}");
+            }
+            self.show_window(props.window_id, &mut video_window.inner_window);
+
+            self.windows
+                .insert(props.window_id, WindowType::Video(video_window));
+            self.video_wallpapers.push(props.window_id);
+        }
+
+        self.video_wallpapers_paused = false;
+        self.update_video_wallpapers();
+
+        Ok(())
+    }
+
+    fn close_video_wallpapers(&mut self) {
+        for id in std::mem::take(&mut self.video_wallpapers) {
+            if let Some(window_type) = self.windows.remove(&id) {
+                self.close_window(window_type);
+            }
+        }
+    }
+
+    /// Decoding a fullscreen video on every monitor is expensive, so video wallpapers are paused
+    /// while enough video popups are open to cover them up anyway, while saving battery or
+    /// throttled, and while they're hidden from a screen capture.
+    fn update_video_wallpapers(&mut self) {
+        if self.video_wallpapers.is_empty() {
+            return;
+        }
+
+        let video_popups = self
+            .windows
+            .iter()
+            .filter(|(id, window)| {
+                matches!(window, WindowType::Video(_)) && !self.video_wallpapers.contains(id)
+            })
+            .count();
+        let paused = self.battery_saver
+            || self.throttled
+            || self.pause_reasons.captured
+            || video_popups >= MAX_VIDEO_POPUPS_WITH_WALLPAPER;
+
+        // `VideoWindow::pause()` isn't idempotent, so only act when the state changes.
+        if paused == self.video_wallpapers_paused {
+            return;
+        }
+        self.video_wallpapers_paused = paused;
+
+        for id in &self.video_wallpapers {
+            if let Some(WindowType::Video(video_window)) = self.windows.get_mut(id) {
+                if paused {
+                    video_window.pause();
+                } else {
+                    video_window.play();
+                }
+            }
+        }
+    }
+
+    fn reset_wallpaper(&mut self) {
+        self.close_video_wallpapers();
+
         if let Some(wallpaper) = &self.default_wallpaper {
             if let Err(err) = wallpaper::set_from_path(wallpaper) {
                 tracing::error!("Error setting wallpaper back to default: {}", err);
@@ -614,31 +1154,61 @@ impl LewdwareApp {
         Ok(())
     }
 
+    /// Shows a notification the first time each kind of media error happens, so that problems
+    /// with the pack are visible without digging through the logs.
+    fn report_media_error(&mut self, kind: &'static str, message: String) {
+        if !self.reported_media_errors.insert(kind) {
+            return;
+        }
+
+        let result = self.show_notification(Notification {
+            summary: Some(tr_args(
+                "lewdware-media-error-title",
+                [("kind", tr(kind).into())],
+            )),
+            body: tr_args("lewdware-media-error", [("message", message.into())]),
+        });
+
+        if let Err(err) = result {
+            tracing::error!("{err}");
+        }
+    }
+
     fn process_lua_request(&mut self, request: LuaRequest, event_loop: &ActiveEventLoop) -> bool {
         if !match request {
             LuaRequest::SpawnImage {
+                media,
                 data,
                 window_opts,
                 tx,
             } => tx
-                .send(self.spawn_image(data, window_opts, event_loop))
+                .send(self.spawn_image(media, data, window_opts, event_loop))
                 .is_ok(),
             LuaRequest::SpawnVideo {
+                media,
                 video_player: data,
                 loop_video,
                 window_opts,
                 tx,
             } => tx
-                .send(self.spawn_video(data, loop_video, window_opts, event_loop))
+                .send(self.spawn_video(media, data, loop_video, window_opts, event_loop))
                 .is_ok(),
             LuaRequest::SpawnPrompt {
                 text,
                 placeholder,
                 initial_value,
+                style,
                 window_opts,
                 tx,
             } => tx
-                .send(self.spawn_prompt(text, placeholder, initial_value, window_opts, event_loop))
+                .send(self.spawn_prompt(
+                    text,
+                    placeholder,
+                    initial_value,
+                    style,
+                    window_opts,
+                    event_loop,
+                ))
                 .is_ok(),
             LuaRequest::SpawnChoice {
                 text,
@@ -656,6 +1226,13 @@ impl LewdwareApp {
             } => tx
                 .send(self.spawn_text(text, style, window_opts, event_loop))
                 .is_ok(),
+            LuaRequest::SpawnMinigame {
+                style,
+                window_opts,
+                tx,
+            } => tx
+                .send(self.spawn_minigame(style, window_opts, event_loop))
+                .is_ok(),
             LuaRequest::SpawnAudio {
                 audio_player: data,
                 tx,
@@ -663,6 +1240,9 @@ impl LewdwareApp {
             LuaRequest::SetWallpaper { file, mode, tx } => {
                 tx.send(self.set_wallpaper(file, mode)).is_ok()
             }
+            LuaRequest::SetVideoWallpaper { video_players, tx } => tx
+                .send(self.set_video_wallpaper(video_players, event_loop))
+                .is_ok(),
             LuaRequest::ResetWallpaper { tx } => tx.send(self.reset_wallpaper()).is_ok(),
             LuaRequest::OpenLink { url, tx } => tx.send(self.open_link(url)).is_ok(),
             LuaRequest::ShowNotification { notification, tx } => {
@@ -743,6 +1323,19 @@ impl LewdwareApp {
                                 _ => Err(LewdwareError::Internal("Invalid window type")),
                             })
                             .is_ok(),
+                        WindowAction::RestartTypingTest {
+                            tx,
+                            text,
+                            time_limit,
+                        } => tx
+                            .send(match entry.get_mut() {
+                                WindowType::Prompt(prompt) => {
+                                    prompt.restart_typing_test(text, time_limit);
+                                    Ok(())
+                                }
+                                _ => Err(LewdwareError::Internal("Invalid window type")),
+                            })
+                            .is_ok(),
                         WindowAction::SetOptions { tx, options } => tx
                             .send(match entry.get_mut() {
                                 WindowType::Choice(choice) => {
@@ -752,9 +1345,16 @@ impl LewdwareApp {
                                 _ => Err(LewdwareError::Internal("Invalid window type")),
                             })
                             .is_ok(),
-                        WindowAction::SetVisible { tx, visible } => tx
-                            .send(entry.get().inner_window().set_visible(visible))
-                            .is_ok(),
+                        WindowAction::SetVisible { tx, visible } => {
+                            self.hidden_windows.retain(|id| id != entry.key());
+                            if visible && self.pause_reasons.captured {
+                                // Shown once the screen stops being captured.
+                                self.hidden_windows.push(*entry.key());
+                            } else {
+                                entry.get_mut().inner_window_mut().set_visible(visible);
+                            }
+                            tx.send(()).is_ok()
+                        }
                         WindowAction::SetTitle { tx, title } => tx
                             .send(entry.get_mut().inner_window_mut().set_title(title))
                             .is_ok(),
@@ -829,85 +1429,63 @@ impl ApplicationHandler<UserEvent> for LewdwareApp {
         window_id: WindowId,
         event: WindowEvent,
     ) {
-        if let Entry::Occupied(mut entry) = self.windows.entry(window_id) {
-            match entry.get_mut() {
-                WindowType::Image(window) => match event {
-                    WindowEvent::RedrawRequested => {
-                        if let Err(err) = window.draw() {
-                            tracing::error!("Error drawing image window: {}", err);
-                        }
-                    }
-                    _ => {}
-                },
-                // Video windows are driven directly from `about_to_wait` instead of through
-                // `RedrawRequested` — see the comment there for why.
-                WindowType::Video(_) => {}
-                WindowType::Prompt(window) => match &event {
-                    WindowEvent::RedrawRequested => {
-                        window.render().unwrap_or_else(|err| {
-                            tracing::error!("Error rendering prompt window: {}", err);
-                        });
-                    }
-                    event => {
-                        window.handle_event(event);
-                    }
-                },
-                WindowType::Text(window) => match &event {
-                    WindowEvent::RedrawRequested => {
-                        window.render().unwrap_or_else(|err| {
-                            tracing::error!("Error rendering text window: {}", err);
-                        });
-                    }
-                    event => {
-                        window.handle_event(event);
-                    }
-                },
-                WindowType::Choice(window) => match &event {
-                    WindowEvent::RedrawRequested => {
-                        window.render().unwrap_or_else(|err| {
-                            tracing::error!("Error rendering prompt window: {}", err);
-                        });
-                    }
-                    event => {
-                        window.handle_event(event);
-                    }
-                },
-            }
+        let Entry::Occupied(mut entry) = self.windows.entry(window_id) else {
+            return;
+        };
 
-            // Global event handling
-            match event {
-                WindowEvent::CloseRequested => {
-                    let window_type = entry.remove();
-                    self.close_window(window_type);
-                }
-                WindowEvent::CursorMoved { position, .. } => {
-                    entry
-                        .get_mut()
-                        .inner_window_mut()
-                        .handle_cursor_moved(position);
-                }
-                WindowEvent::CursorLeft { .. } => {
-                    entry.get_mut().inner_window_mut().handle_cursor_left();
-                }
-                WindowEvent::MouseInput {
-                    state: ElementState::Pressed,
-                    button: MouseButton::Left,
-                    ..
-                } => {
-                    entry.get_mut().inner_window_mut().handle_mouse_down();
+        let window = entry.get_mut().popup_mut();
+        window.handle_event(&event);
+
+        // Global event handling
+        let close = match event {
+            WindowEvent::CloseRequested => true,
+            WindowEvent::CursorMoved { position, .. } => {
+                window.inner_window_mut().handle_cursor_moved(position);
+                false
+            }
+            WindowEvent::CursorEntered { .. } => {
+                self.hovered_window = Some(window_id);
+                false
+            }
+            WindowEvent::Focused(focused) => {
+                if focused {
+                    self.focused_window = Some(window_id);
+                } else if self.focused_window == Some(window_id) {
+                    self.focused_window = None;
                 }
-                WindowEvent::MouseInput {
-                    state: ElementState::Released,
-                    button: MouseButton::Left,
-                    ..
-                } => {
-                    if entry.get_mut().inner_window_mut().handle_mouse_up() {
-                        let window_type = entry.remove();
-                        self.close_window(window_type);
-                        return;
-                    }
+                false
+            }
+            WindowEvent::CursorLeft { .. } => {
+                if self.hovered_window == Some(window_id) {
+                    self.hovered_window = None;
                 }
-                _ => {}
+                window.inner_window_mut().handle_cursor_left();
+                false
+            }
+            WindowEvent::MouseInput {
+                state: ElementState::Pressed,
+                button: MouseButton::Left,
+                ..
+            } => {
+                window.inner_window_mut().handle_mouse_down();
+                false
+            }
+            WindowEvent::MouseInput {
+                state: ElementState::Released,
+                button: MouseButton::Left,
+                ..
+            } => window.inner_window_mut().handle_mouse_up(),
+            _ => false,
+        };
+
+        if close || window.wants_close() {
+            let window_type = entry.remove();
+            let open_for = window_type.popup().created_at().elapsed();
+            self.close_window(window_type);
+
+            // Not a video that finished, or closed itself on hover.
+            if close {
+                self.send_lua_event(lua::Event::PopupDismissed { open_for });
             }
         }
     }
@@ -919,6 +1497,10 @@ impl ApplicationHandler<UserEvent> for LewdwareApp {
             UserEvent::Exit => {
                 event_loop.exit();
             }
+            UserEvent::WipeAndExit => {
+                self.wipe_on_exit = true;
+                event_loop.exit();
+            }
             UserEvent::LuaRequest => {
                 self.process_lua_requests(event_loop);
             }
@@ -929,51 +1511,135 @@ impl ApplicationHandler<UserEvent> for LewdwareApp {
                     }
                 }
             }
+            UserEvent::ToggleMute => {
+                self.set_muted(!self.muted);
+            }
+            UserEvent::TogglePause => {
+                self.pause_reasons.user = !self.pause_reasons.user;
+                update_tray_paused(self.pause_reasons.user);
+                self.update_paused();
+            }
+            UserEvent::BlockMedia => {
+                if let Some((id, media_id)) = self.hotkey_target() {
+                    self.send_lua_event(lua::Event::BlockMedia { media_id });
+                    if let Some(window_type) = self.windows.remove(&id) {
+                        self.close_window(window_type);
+                    }
+                }
+            }
+            UserEvent::ToggleFavorite => {
+                if let Some((_, media_id)) = self.hotkey_target() {
+                    self.send_lua_event(lua::Event::ToggleFavorite { media_id });
+                }
+            }
+            UserEvent::ShowCredits => {
+                self.send_lua_event(lua::Event::ShowCredits);
+            }
+            UserEvent::ToggleInfo => {
+                if let Some((id, _)) = self.hotkey_target()
+                    && let Some(window) = self.windows.get_mut(&id)
+                {
+                    window.popup_mut().toggle_info();
+                }
+            }
+            UserEvent::SetIntensity { level } => {
+                update_tray_intensity(level);
+                if let Err(err) = self.lua_event_tx.send(lua::Event::IntensityChange { level }) {
+                    tracing::error!("{err}");
+                }
+            }
+            UserEvent::SetLocked { locked } => {
+                self.pause_reasons.locked = locked;
+                self.update_paused();
+            }
+            UserEvent::SetScheduled { active } => {
+                self.pause_reasons.off_schedule = !active;
+                self.update_paused();
+            }
+            UserEvent::SetIdle { idle } => {
+                self.pause_reasons.idle = idle;
+                self.update_paused();
+            }
+            UserEvent::SetHibernating { hibernating } => {
+                self.set_hibernating(hibernating);
+            }
+            UserEvent::SetCaptured { captured } => {
+                self.set_captured(captured);
+            }
+            UserEvent::SetBatterySaver { on } => {
+                self.set_battery_saver(on);
+            }
+            UserEvent::MediaError { kind, message } => {
+                self.report_media_error(kind, message);
+            }
+            UserEvent::PackOpenProgress(progress) => match progress {
+                OpenProgress::Done => set_tray_status(None),
+                progress => set_tray_status(Some(&progress.to_string())),
+            },
         }
     }
 
     fn about_to_wait(&mut self, event_loop: &ActiveEventLoop) {
-        let mut moving_windows = false;
-        let mut finished_videos = Vec::new();
+        // The earliest time any window next needs updating. Until then the loop sleeps, unless
+        // an event comes in.
+        let mut next_update = None;
+        let mut finished_windows = Vec::new();
+        let frame_start = Instant::now();
 
         for (id, window) in self.windows.iter_mut() {
+            let window = window.popup_mut();
+
             // Video windows are driven directly here rather than via `request_redraw()` /
             // `RedrawRequested`. On the Win32 backend, winit only reliably delivers
             // `RedrawRequested` to the last couple of windows that requested it within the same
             // `AboutToWait` cycle (https://github.com/rust-windowing/winit/issues/3648), so with
             // 3+ simultaneous video windows the rest would silently stop advancing.
-            if let WindowType::Video(video_window) = window {
-                match video_window.update() {
-                    Ok(true) => finished_videos.push(*id),
-                    Ok(false) => {}
-                    Err(err) => tracing::error!("Error updating video window: {err}"),
+            let window_update = match window.update() {
+                Ok(at) => at,
+                Err(err) => {
+                    tracing::error!("Error updating window: {err}");
+                    // Give it another try soon.
+                    Some(frame_start + ERROR_RETRY)
                 }
-                // Keep polling continuously while any video window exists, since we can no
-                // longer rely on `request_redraw()` to wake the loop back up for them.
-                moving_windows = true;
+            };
+            if window.wants_close() {
+                finished_windows.push(*id);
             }
 
             if window.inner_window().is_moving() {
                 window.inner_window_mut().update_position();
-                moving_windows = true;
             }
             if window.inner_window().is_fading() {
                 window.inner_window_mut().update_fade();
-                moving_windows = true; // reusing `moving_windows` to mean "animating windows"
             }
+
+            next_update = [next_update, window_update]
+                .into_iter()
+                .chain([window.inner_window().next_animation_update()])
+                .flatten()
+                .min();
         }
 
-        for id in finished_videos {
+        // Only passes that animated something count towards the frame time.
+        if next_update.is_some() {
+            self.governor.record_frame(frame_start.elapsed());
+        }
+        if self.config.throttle.enabled
+            && let Some(overloaded) = self.governor.poll()
+        {
+            self.set_throttled(overloaded);
+        }
+
+        for id in finished_windows {
             if let Some(window_type) = self.windows.remove(&id) {
                 self.close_window(window_type);
             }
         }
 
-        if moving_windows {
-            event_loop.set_control_flow(ControlFlow::Poll);
-        } else {
-            event_loop.set_control_flow(ControlFlow::Wait);
-        }
+        event_loop.set_control_flow(match next_update {
+            Some(at) => ControlFlow::WaitUntil(at),
+            None => ControlFlow::Wait,
+        });
     }
 }
 
@@ -983,6 +1649,17 @@ impl Drop for LewdwareApp {
         // so their temp files (extracted pack index, any in-flight media) get cleaned up via
         // `Drop` instead of being silently killed along with the process when `main` returns.
         self.lua_thread_handle.shutdown();
+        // Videos still open have decode threads that may not get to stop their helper
+        // processes before the engine exits (see `decode_helper`).
+        shared::children::shutdown(CHILD_SHUTDOWN_TIMEOUT);
+
+        let mut video_stats = self.video_stats;
+        for window in self.windows.values() {
+            if let WindowType::Video(video_window) = window {
+                video_stats.add(&video_window.stats());
+            }
+        }
+        tracing::info!("Videos this session: {video_stats}");
 
         if let Some(wallpaper) = &self.default_wallpaper {
             if let Err(err) = wallpaper::set_from_path(wallpaper) {
@@ -993,11 +1670,3 @@ impl Drop for LewdwareApp {
         }
     }
 }
-
-fn random_position(window_size: u32, total_size: u32) -> i32 {
-    if window_size > total_size {
-        0
-    } else {
-        random_range(0i32..=(total_size - window_size) as i32)
-    }
-}
@@ -10,11 +10,13 @@ use winit::event_loop::EventLoopProxy;
 
 use rodio::{DeviceSinkBuilder, MixerDeviceSink, Player, Source, buffer::SamplesBuffer};
 
-use crate::{app::UserEvent, media::MediaSource};
+use crate::{app::UserEvent, audio_session::DuckGuard, media::MediaSource};
 
 pub struct AudioPlayer {
     _stream: MixerDeviceSink,
     sink: Arc<Player>,
+    /// Other programs stay turned down while this is around.
+    _duck: Option<DuckGuard>,
 }
 
 impl AudioPlayer {
@@ -38,6 +40,7 @@ impl AudioPlayer {
         Ok(Self {
             _stream: stream,
             sink,
+            _duck: DuckGuard::new(),
         })
     }
 
@@ -49,6 +52,19 @@ impl AudioPlayer {
         self.sink.play();
     }
 
+    /// Stops the audio for good, as if it had finished.
+    pub fn stop(&self) {
+        self.sink.stop();
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.sink.is_paused()
+    }
+
+    pub fn set_muted(&self, muted: bool) {
+        self.sink.set_volume(if muted { 0.0 } else { 1.0 });
+    }
+
     pub fn position(&self) -> Duration {
         // Blocking!
         let pos = self.sink.get_pos();
@@ -0,0 +1,333 @@
+//! Turns other programs down while something of ours is playing (see `duck_other_apps` in the
+//! config), and back up once nothing is. Each [`AudioPlayer`](crate::audio::AudioPlayer) holds a
+//! [`DuckGuard`], and the first and last of them ducking and restoring are handed to a plain
+//! thread, since talking to the sound server can take a moment.
+
+use std::{
+    sync::{
+        Mutex,
+        mpsc::{self, Sender},
+    },
+    thread,
+};
+
+/// How loud other programs are left while ducked, relative to where they were.
+const DUCKED_VOLUME: f32 = 0.3;
+
+struct Ducking {
+    /// Tells the thread to duck (`true`) or restore (`false`). `None` while ducking is off.
+    tx: Option<Sender<bool>>,
+    /// How many guards are alive.
+    players: usize,
+}
+
+static DUCKING: Mutex<Ducking> = Mutex::new(Ducking {
+    tx: None,
+    players: 0,
+});
+
+/// Everything that's been turned down, so it can be put back.
+static LOWERED: Mutex<Vec<Lowered>> = Mutex::new(Vec::new());
+
+/// Another program's audio stream, and its volume before it was ducked.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(not(any(target_os = "linux", target_os = "windows")), allow(dead_code))]
+struct Lowered {
+    /// The stream's index on Linux, or its session instance identifier on Windows.
+    id: String,
+    volume: f32,
+}
+
+/// Starts the thread that ducks other programs. Does nothing unless `duck_other_apps` is set.
+pub fn spawn_audio_ducker(enabled: bool) {
+    if !enabled {
+        return;
+    }
+
+    let (tx, rx) = mpsc::channel();
+    DUCKING.lock().unwrap().tx = Some(tx);
+
+    thread::spawn(move || {
+        for duck in rx {
+            let mut lowered = LOWERED.lock().unwrap();
+
+            if duck {
+                if lowered.is_empty() {
+                    *lowered = lower_others();
+                    tracing::info!("Ducked {} other audio streams", lowered.len());
+                }
+            } else {
+                restore(&lowered);
+                lowered.clear();
+            }
+        }
+    });
+}
+
+/// Puts everything back straight away, e.g. on exit, when there's no waiting for the thread.
+/// Ducking stays off afterwards.
+pub fn restore_others() {
+    DUCKING.lock().unwrap().tx = None;
+
+    let mut lowered = LOWERED.lock().unwrap();
+    restore(&lowered);
+    lowered.clear();
+}
+
+/// Keeps other programs ducked for as long as it's alive.
+pub struct DuckGuard(());
+
+impl DuckGuard {
+    /// `None` if ducking is off.
+    pub fn new() -> Option<Self> {
+        let mut ducking = DUCKING.lock().unwrap();
+        let tx = ducking.tx.as_ref()?;
+
+        if ducking.players == 0 {
+            let _ = tx.send(true);
+        }
+        ducking.players += 1;
+
+        Some(Self(()))
+    }
+}
+
+impl Drop for DuckGuard {
+    fn drop(&mut self) {
+        let mut ducking = DUCKING.lock().unwrap();
+        ducking.players = ducking.players.saturating_sub(1);
+
+        if ducking.players == 0
+            && let Some(tx) = &ducking.tx
+        {
+            let _ = tx.send(false);
+        }
+    }
+}
+
+/// A stream from `pactl list sink-inputs`. PipeWire's `pipewire-pulse` understands `pactl` too.
+#[cfg(any(target_os = "linux", test))]
+#[derive(Debug, Clone, PartialEq)]
+struct SinkInput {
+    index: String,
+    /// As a fraction, from the first channel.
+    volume: f32,
+    pid: Option<u32>,
+}
+
+/// Picks the index, volume and process out of each `Sink Input #…` block, e.g.
+///
+/// ```text
+/// Sink Input #42
+///     Volume: front-left: 65536 / 100% / 0.00 dB,   front-right: 65536 / 100% / 0.00 dB
+///     Properties:
+///         application.process.id = "1234"
+/// ```
+#[cfg(any(target_os = "linux", test))]
+fn parse_sink_inputs(output: &str) -> Vec<SinkInput> {
+    let mut inputs: Vec<SinkInput> = Vec::new();
+
+    for line in output.lines().map(str::trim) {
+        if let Some(index) = line.strip_prefix("Sink Input #") {
+            inputs.push(SinkInput {
+                index: index.to_string(),
+                volume: 1.0,
+                pid: None,
+            });
+        } else if let Some(input) = inputs.last_mut() {
+            if let Some(volume) = line.strip_prefix("Volume:")
+                && let Some(percent) = volume
+                    .split('/')
+                    .nth(1)
+                    .and_then(|percent| percent.trim().strip_suffix('%')?.parse::<f32>().ok())
+            {
+                input.volume = percent / 100.0;
+            } else if let Some(pid) = line.strip_prefix("application.process.id = ") {
+                input.pid = pid.trim_matches('"').parse().ok();
+            }
+        }
+    }
+
+    inputs
+}
+
+#[cfg(target_os = "linux")]
+fn set_sink_input_volume(index: &str, volume: f32) {
+    use std::process::Command;
+
+    let percent = format!("{}%", (volume * 100.0).round());
+    if let Err(err) = Command::new("pactl")
+        .args(["set-sink-input-volume", index, &percent])
+        .output()
+    {
+        tracing::warn!("Couldn't set the volume of sink input {index}: {err}");
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn lower_others() -> Vec<Lowered> {
+    use std::process::Command;
+
+    let output = match Command::new("pactl").args(["list", "sink-inputs"]).output() {
+        Ok(output) => output,
+        Err(err) => {
+            tracing::warn!("Couldn't list audio streams to duck: {err}");
+            return Vec::new();
+        }
+    };
+
+    parse_sink_inputs(&String::from_utf8_lossy(&output.stdout))
+        .into_iter()
+        .filter(|input| input.pid != Some(std::process::id()))
+        .map(|input| {
+            set_sink_input_volume(&input.index, input.volume * DUCKED_VOLUME);
+            Lowered {
+                id: input.index,
+                volume: input.volume,
+            }
+        })
+        .collect()
+}
+
+/// Streams that have ended since are skipped over by `pactl`.
+#[cfg(target_os = "linux")]
+fn restore(lowered: &[Lowered]) {
+    for stream in lowered {
+        set_sink_input_volume(&stream.id, stream.volume);
+    }
+}
+
+/// Calls `f` with every audio session on the default output, other than our own and the system
+/// sounds, and its session instance identifier.
+#[cfg(target_os = "windows")]
+fn for_each_other_session(
+    mut f: impl FnMut(String, &windows::Win32::Media::Audio::ISimpleAudioVolume),
+) -> windows::core::Result<()> {
+    use windows::{
+        Win32::{
+            Foundation::S_OK,
+            Media::Audio::{
+                IAudioSessionControl2, IAudioSessionManager2, IMMDeviceEnumerator,
+                ISimpleAudioVolume, MMDeviceEnumerator, eMultimedia, eRender,
+            },
+            System::{
+                Com::{
+                    CLSCTX_ALL, COINIT_MULTITHREADED, CoCreateInstance, CoInitializeEx,
+                    CoTaskMemFree,
+                },
+                Threading::GetCurrentProcessId,
+            },
+        },
+        core::Interface,
+    };
+
+    unsafe {
+        // Already being initialised on this thread is fine.
+        let _ = CoInitializeEx(None, COINIT_MULTITHREADED);
+
+        let enumerator: IMMDeviceEnumerator =
+            CoCreateInstance(&MMDeviceEnumerator, None, CLSCTX_ALL)?;
+        let device = enumerator.GetDefaultAudioEndpoint(eRender, eMultimedia)?;
+        let manager: IAudioSessionManager2 = device.Activate(CLSCTX_ALL, None)?;
+        let sessions = manager.GetSessionEnumerator()?;
+
+        for i in 0..sessions.GetCount()? {
+            let control: IAudioSessionControl2 = sessions.GetSession(i)?.cast()?;
+            if control.GetProcessId()? == GetCurrentProcessId()
+                || control.IsSystemSoundsSession() == S_OK
+            {
+                continue;
+            }
+
+            let id = control.GetSessionInstanceIdentifier()?;
+            let id_string = id.to_string();
+            CoTaskMemFree(Some(id.0 as _));
+
+            f(id_string?, &control.cast::<ISimpleAudioVolume>()?);
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(target_os = "windows")]
+fn lower_others() -> Vec<Lowered> {
+    let mut lowered = Vec::new();
+
+    let result = for_each_other_session(|id, volume| unsafe {
+        if let Ok(level) = volume.GetMasterVolume()
+            && volume
+                .SetMasterVolume(level * DUCKED_VOLUME, std::ptr::null())
+                .is_ok()
+        {
+            lowered.push(Lowered { id, volume: level });
+        }
+    });
+    if let Err(err) = result {
+        tracing::warn!("Couldn't duck other audio sessions: {err}");
+    }
+
+    lowered
+}
+
+/// Sessions are looked up again by their identifier, since the volume controls can't be kept
+/// across threads.
+#[cfg(target_os = "windows")]
+fn restore(lowered: &[Lowered]) {
+    if lowered.is_empty() {
+        return;
+    }
+
+    let result = for_each_other_session(|id, volume| {
+        if let Some(stream) = lowered.iter().find(|stream| stream.id == id) {
+            let _ = unsafe { volume.SetMasterVolume(stream.volume, std::ptr::null()) };
+        }
+    });
+    if let Err(err) = result {
+        tracing::warn!("Couldn't restore other audio sessions: {err}");
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "windows")))]
+fn lower_others() -> Vec<Lowered> {
+    Vec::new()
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "windows")))]
+fn restore(_lowered: &[Lowered]) {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_sink_inputs() {
+        let output = "\
+Sink Input #42
+\tDriver: protocol-native.c
+\tVolume: front-left: 42598 /  65% / -11.23 dB,   front-right: 42598 /  65% / -11.23 dB
+\tProperties:
+\t\tapplication.name = \"Firefox\"
+\t\tapplication.process.id = \"1234\"
+
+Sink Input #43
+\tVolume: mono: 65536 / 100% / 0.00 dB
+";
+
+        assert_eq!(
+            parse_sink_inputs(output),
+            vec![
+                SinkInput {
+                    index: "42".to_string(),
+                    volume: 0.65,
+                    pid: Some(1234),
+                },
+                SinkInput {
+                    index: "43".to_string(),
+                    volume: 1.0,
+                    pid: None,
+                },
+            ]
+        );
+    }
+}
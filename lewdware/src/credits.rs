@@ -0,0 +1,102 @@
+//! The credits window, opened from the tray: who made the pack, whose work is in it, and what it
+//! can be shared under (see [`Credit`]).
+
+use shared::{
+    locale::{tr, tr_args},
+    read_pack::{Credit, Metadata},
+};
+
+/// The text of the credits window for a pack, one paragraph per credit under the pack's own.
+pub fn credits_text(metadata: &Metadata) -> String {
+    let mut paragraphs = vec![pack_paragraph(metadata)];
+
+    if metadata.credits.is_empty() {
+        paragraphs.push(tr("lewdware-credits-none"));
+    }
+    paragraphs.extend(metadata.credits.iter().map(credit_paragraph));
+
+    paragraphs.join("\n\n")
+}
+
+fn pack_paragraph(metadata: &Metadata) -> String {
+    let mut lines = vec![metadata.name.clone()];
+
+    if let Some(creator) = &metadata.creator {
+        lines.push(tr_args(
+            "lewdware-credits-creator",
+            [("creator", creator.as_str().into())],
+        ));
+    }
+    if let Some(version) = &metadata.version {
+        lines.push(tr_args(
+            "lewdware-credits-version",
+            [("version", version.as_str().into())],
+        ));
+    }
+    if let Some(license) = &metadata.license {
+        lines.push(license_line(license));
+    }
+
+    lines.join("\n")
+}
+
+fn credit_paragraph(credit: &Credit) -> String {
+    let mut lines = vec![match &credit.role {
+        Some(role) => tr_args(
+            "lewdware-credits-role",
+            [
+                ("name", credit.name.as_str().into()),
+                ("role", role.as_str().into()),
+            ],
+        ),
+        None => credit.name.clone(),
+    }];
+
+    if let Some(license) = &credit.license {
+        lines.push(license_line(license));
+    }
+    lines.extend(credit.urls.iter().cloned());
+
+    lines.join("\n")
+}
+
+fn license_line(license: &str) -> String {
+    tr_args("lewdware-credits-license", [("license", license.into())])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lists_every_credit_under_the_pack() {
+        let metadata = Metadata {
+            name: "Test pack".to_string(),
+            license: Some("CC BY-NC 4.0".to_string()),
+            credits: vec![
+                Credit {
+                    name: "Alice".to_string(),
+                    role: Some("Artist".to_string()),
+                    license: Some("CC BY 4.0".to_string()),
+                    urls: vec!["https://example.com/alice".to_string()],
+                },
+                Credit {
+                    name: "Bob".to_string(),
+                    ..Default::default()
+                },
+            ],
+            ..Default::default()
+        };
+
+        let text = credits_text(&metadata);
+        let paragraphs: Vec<_> = text.split("\n\n").collect();
+        assert_eq!(paragraphs.len(), 3);
+        assert!(paragraphs[0].starts_with("Test pack\n"));
+        assert!(paragraphs[0].contains("CC BY-NC 4.0"));
+        assert!(paragraphs[1].contains("Alice"));
+        assert!(paragraphs[1].contains("Artist"));
+        assert!(paragraphs[1].contains("CC BY 4.0"));
+        assert!(paragraphs[1].ends_with("\nhttps://example.com/alice"));
+        assert_eq!(paragraphs[2], "Bob");
+    }
+}
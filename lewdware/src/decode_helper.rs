@@ -0,0 +1,602 @@
+//! Decoding videos in a separate process, so a file that crashes ffmpeg only takes down the
+//! helper rather than the whole session. Off unless
+//! [`AppConfig::isolate_video_decoding`](shared::user_config::AppConfig::isolate_video_decoding)
+//! is set.
+//!
+//! The engine starts its own executable again with [`HELPER_ARG`], and tells it what to decode
+//! with a line of JSON on stdin. The helper decodes in software (hardware frames can't be handed
+//! to another process) and writes each frame into one of a few slots of a memory-mapped file in
+//! the temp dir, saying which on stdout. The engine copies the frame out and hands the slot back.
+//!
+//! A helper that crashes is started again from where it got to. Media that crashes it
+//! [`CRASHES_BEFORE_QUARANTINE`] times is left out of the rest of the session.
+
+use std::{
+    collections::HashMap,
+    env,
+    fs::OpenOptions,
+    io::{self, BufRead, BufReader, Lines, Write},
+    path::PathBuf,
+    process::{ChildStdin, ChildStdout, Command, Stdio},
+    sync::{
+        Arc, Mutex,
+        atomic::AtomicU64,
+        mpsc::{self, Receiver, SyncSender, sync_channel},
+    },
+    thread,
+    time::Duration,
+};
+
+use anyhow::{Context, Result, bail};
+use ffmpeg_next::{format::Pixel, frame::Video, software::scaling};
+use memmap2::{Mmap, MmapMut};
+use serde::{Deserialize, Serialize};
+use shared::{
+    children::{self, TrackedChild},
+    encode::VideoContainer,
+};
+
+use crate::{
+    media::{MediaSource, PackLocation},
+    utils,
+    video::{Downscaler, VideoFrame, VideoPixelFormat, spawn_video_stream},
+};
+
+/// Starts the engine as a decode helper, see [`run`].
+pub const HELPER_ARG: &str = "--decode-helper";
+
+/// How many frames the helper can get ahead of the engine.
+const SLOTS: usize = 4;
+
+/// How many times media can crash the helper before it's quarantined. The first crash might have
+/// been bad luck (running out of memory, say), so it gets one more go.
+const CRASHES_BEFORE_QUARANTINE: u32 = 2;
+
+/// What the helper exits with when it gives up on a video without crashing, e.g. because it
+/// isn't a video at all. This is what returning an error from `main` exits with.
+const ERROR_EXIT_CODE: i32 = 1;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct DecodeRequest {
+    location: PackLocation,
+    offset: u64,
+    length: u64,
+    container: Option<VideoContainer>,
+    loop_video: bool,
+    packed_alpha: bool,
+    max_fps: Option<u32>,
+    /// Frames up to and including this timestamp are skipped, so a restarted helper carries on
+    /// where the last one crashed.
+    resume_after: Option<Duration>,
+    /// The file the frames are written to.
+    buffer: PathBuf,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+enum ToHelper {
+    Decode(DecodeRequest),
+    /// The engine has copied the frame out of this slot, so it can be used again.
+    Free(usize),
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+enum FromHelper {
+    Metadata {
+        width: u32,
+        height: u32,
+        full_range: bool,
+    },
+    Frame {
+        slot: usize,
+        pts: Duration,
+        decode_time: Duration,
+    },
+    /// The end of the video, or of one time through it if it loops.
+    End,
+}
+
+/// Media that has crashed the helper, shared between the media thread, which leaves quarantined
+/// media out of its queries, and the threads reading from helpers. Trivially clonable.
+#[derive(Clone, Default)]
+pub struct Quarantine(Arc<Mutex<HashMap<u64, u32>>>);
+
+impl Quarantine {
+    /// Counts a crash of the helper while decoding the media. Returns whether it's now
+    /// quarantined.
+    pub fn record_crash(&self, id: u64) -> bool {
+        let mut crashes = self.0.lock().unwrap();
+        let count = crashes.entry(id).or_default();
+        *count += 1;
+        *count >= CRASHES_BEFORE_QUARANTINE
+    }
+
+    pub fn contains(&self, id: u64) -> bool {
+        self.0
+            .lock()
+            .unwrap()
+            .get(&id)
+            .is_some_and(|&count| count >= CRASHES_BEFORE_QUARANTINE)
+    }
+
+    pub fn ids(&self) -> Vec<u64> {
+        self.0
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|&(_, &count)| count >= CRASHES_BEFORE_QUARANTINE)
+            .map(|(&id, _)| id)
+            .collect()
+    }
+}
+
+/// Which media a helper is decoding, to quarantine if it keeps crashing.
+#[derive(Clone)]
+pub struct Isolation {
+    pub id: u64,
+    pub quarantine: Quarantine,
+}
+
+/// Decodes the video in a helper process. Otherwise the same as decoding it on a thread, except
+/// that frames are never hardware-decoded.
+pub fn spawn(
+    source: MediaSource,
+    loop_video: bool,
+    packed_alpha: bool,
+    max_fps: Option<u32>,
+    output_size: Arc<AtomicU64>,
+    isolation: Isolation,
+) -> Result<(
+    Receiver<Option<VideoFrame>>,
+    u32,
+    u32,
+    bool,
+    VideoPixelFormat,
+)> {
+    let buffer = tempfile::Builder::new()
+        .prefix("decode-")
+        .tempfile_in(utils::temp_dir())?;
+    let request = DecodeRequest {
+        location: source.location,
+        offset: source.offset,
+        length: source.length,
+        container: source.container,
+        loop_video,
+        packed_alpha,
+        max_fps,
+        resume_after: None,
+        buffer: buffer.path().to_path_buf(),
+    };
+
+    let mut helper = Helper::start(&request)?;
+    let Some(FromHelper::Metadata {
+        width,
+        height,
+        full_range,
+    }) = helper.recv()
+    else {
+        if helper.finish() {
+            isolation.quarantine.record_crash(isolation.id);
+            bail!("The video decoder crashed opening media {}", isolation.id);
+        }
+        bail!("The video decoder couldn't open media {}", isolation.id);
+    };
+
+    let frame_height = if packed_alpha { height * 2 } else { height };
+    let layout = FrameLayout::new(width, frame_height);
+    // Safety: the file is private to this session's temp dir, and only the helper writes to it.
+    // It writes each slot before saying it's ready, and doesn't touch it again until it's freed.
+    let mapped = unsafe { Mmap::map(buffer.as_file())? };
+
+    let (tx, rx) = sync_channel(2);
+    thread::spawn(move || {
+        read_frames(helper, request, &mapped, layout, tx, output_size, isolation);
+        // Windows won't delete a file that's still mapped.
+        drop(mapped);
+        drop(buffer);
+    });
+
+    Ok((rx, width, height, full_range, VideoPixelFormat::Yuv420p))
+}
+
+/// Passes frames on from the helper until the video finishes or the player is dropped, starting
+/// the helper again if it crashes.
+fn read_frames(
+    mut helper: Helper,
+    mut request: DecodeRequest,
+    mapped: &Mmap,
+    layout: FrameLayout,
+    tx: SyncSender<Option<VideoFrame>>,
+    output_size: Arc<AtomicU64>,
+    isolation: Isolation,
+) {
+    let (recycle_tx, recycle_rx) = sync_channel::<Video>(5);
+    let mut downscaler = Downscaler::new(output_size);
+
+    loop {
+        let Some(message) = helper.recv() else {
+            if !helper.finish() {
+                return;
+            }
+
+            if isolation.quarantine.record_crash(isolation.id) {
+                tracing::error!(
+                    "Media {} crashed the video decoder again, skipping it from now on",
+                    isolation.id
+                );
+                return;
+            }
+
+            tracing::warn!(
+                "The video decoder crashed on media {}, starting it again",
+                isolation.id
+            );
+            match Helper::start(&request) {
+                Ok(restarted) => helper = restarted,
+                Err(err) => {
+                    tracing::error!("Couldn't start the video decoder again: {err}");
+                    return;
+                }
+            }
+            continue;
+        };
+
+        match message {
+            // Sent again by a restarted helper.
+            FromHelper::Metadata { .. } => {}
+            FromHelper::End => {
+                if tx.send(None).is_err() {
+                    break;
+                }
+            }
+            FromHelper::Frame {
+                slot,
+                pts,
+                decode_time,
+            } => {
+                let Some(data) = mapped.get(slot * layout.size()..(slot + 1) * layout.size())
+                else {
+                    tracing::error!("The video decoder used a slot that doesn't exist: {slot}");
+                    break;
+                };
+
+                let mut frame = recycle_rx
+                    .try_recv()
+                    .ok()
+                    .filter(|frame| {
+                        frame.width() as usize == layout.width
+                            && frame.height() as usize == layout.height
+                    })
+                    .unwrap_or_else(|| {
+                        Video::new(Pixel::YUV420P, layout.width as u32, layout.height as u32)
+                    });
+                layout.read(data, &mut frame);
+                // If this fails, the helper has gone, which the next `recv` finds out about.
+                let _ = helper.send(&ToHelper::Free(slot));
+                request.resume_after = Some(pts);
+
+                let frame = VideoFrame {
+                    frame: downscaler.scale(frame),
+                    hardware_frame: None,
+                    pts,
+                    recycle_tx: recycle_tx.clone(),
+                    decode_time,
+                };
+                if tx.send(Some(frame)).is_err() {
+                    break;
+                }
+            }
+        }
+    }
+
+    helper.stop();
+}
+
+struct Helper {
+    child: TrackedChild,
+    stdin: ChildStdin,
+    stdout: Lines<BufReader<ChildStdout>>,
+}
+
+impl Helper {
+    fn start(request: &DecodeRequest) -> Result<Self> {
+        let mut child = children::spawn(
+            Command::new(env::current_exe()?)
+                .arg(HELPER_ARG)
+                .stdin(Stdio::piped())
+                .stdout(Stdio::piped()),
+        )
+        .context("Couldn't start the video decoder")?;
+
+        let stdin = child
+            .stdin
+            .take()
+            .context("No stdin for the video decoder")?;
+        let stdout = child
+            .stdout
+            .take()
+            .context("No stdout for the video decoder")?;
+
+        let mut helper = Self {
+            child,
+            stdin,
+            stdout: BufReader::new(stdout).lines(),
+        };
+        helper.send(&ToHelper::Decode(request.clone()))?;
+
+        Ok(helper)
+    }
+
+    fn send(&mut self, message: &ToHelper) -> io::Result<()> {
+        send(&mut self.stdin, message)
+    }
+
+    /// The next message from the helper, or `None` once it has exited.
+    fn recv(&mut self) -> Option<FromHelper> {
+        let line = self.stdout.next()?.ok()?;
+        match serde_json::from_str(&line) {
+            Ok(message) => Some(message),
+            Err(err) => {
+                tracing::error!("Unexpected message from the video decoder: {err}");
+                None
+            }
+        }
+    }
+
+    /// Waits for the helper to exit by itself. Returns whether it crashed, rather than finishing
+    /// the video or giving up on it.
+    fn finish(mut self) -> bool {
+        drop(self.stdin);
+        match self.child.wait() {
+            Ok(status) => !status.success() && status.code() != Some(ERROR_EXIT_CODE),
+            Err(err) => {
+                tracing::error!("Couldn't wait for the video decoder: {err}");
+                false
+            }
+        }
+    }
+
+    fn stop(mut self) {
+        self.child.kill();
+    }
+}
+
+fn send(writer: &mut impl Write, message: &impl Serialize) -> io::Result<()> {
+    serde_json::to_writer(&mut *writer, message)?;
+    writer.write_all(b"\n")?;
+    writer.flush()
+}
+
+/// Runs the engine as a decode helper, until the video ends or the engine closes its stdin.
+pub fn run() -> Result<()> {
+    let _log_guard = shared::logging::init("lewdware-decode-helper");
+
+    let mut line = String::new();
+    io::stdin().read_line(&mut line)?;
+    let ToHelper::Decode(request) = serde_json::from_str(&line)? else {
+        bail!("Expected a video to decode");
+    };
+
+    let source = MediaSource {
+        location: request.location,
+        offset: request.offset,
+        length: request.length,
+        container: request.container,
+    };
+    let (frames, width, height, full_range, _) = spawn_video_stream(
+        source,
+        request.loop_video,
+        request.packed_alpha,
+        request.max_fps,
+        Arc::new(AtomicU64::new(0)),
+        None,
+    )?;
+
+    let frame_height = if request.packed_alpha {
+        height * 2
+    } else {
+        height
+    };
+    let layout = FrameLayout::new(width, frame_height);
+    let file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(&request.buffer)?;
+    file.set_len((layout.size() * SLOTS) as u64)?;
+    // Safety: the engine only reads a slot after being told it's ready, and the file isn't
+    // resized again while it's mapped.
+    let mut mapped = unsafe { MmapMut::map_mut(&file)? };
+
+    let (free_tx, free_rx) = mpsc::channel();
+    for slot in 0..SLOTS {
+        free_tx.send(slot)?;
+    }
+    thread::spawn(move || {
+        for line in io::stdin().lines().map_while(Result::ok) {
+            match serde_json::from_str(&line) {
+                Ok(ToHelper::Free(slot)) => {
+                    if free_tx.send(slot).is_err() {
+                        break;
+                    }
+                }
+                Ok(message) => tracing::warn!("Unexpected message from the engine: {message:?}"),
+                Err(err) => tracing::warn!("Unexpected message from the engine: {err}"),
+            }
+        }
+    });
+
+    let mut stdout = io::stdout().lock();
+    send(
+        &mut stdout,
+        &FromHelper::Metadata {
+            width,
+            height,
+            full_range,
+        },
+    )?;
+
+    let mut scaler: Option<scaling::Context> = None;
+    for frame in frames {
+        let Some(frame) = frame else {
+            send(&mut stdout, &FromHelper::End)?;
+            continue;
+        };
+        if request.resume_after.is_some_and(|after| frame.pts <= after) {
+            continue;
+        }
+
+        // The engine has closed stdin, so it doesn't want any more frames.
+        let Ok(slot) = free_rx.recv() else {
+            break;
+        };
+
+        let converted = layout.convert(&frame.frame, &mut scaler)?;
+        let data = &mut mapped[slot * layout.size()..(slot + 1) * layout.size()];
+        layout.write(converted.as_ref().unwrap_or(&frame.frame), data);
+
+        send(
+            &mut stdout,
+            &FromHelper::Frame {
+                slot,
+                pts: frame.pts,
+                decode_time: frame.decode_time,
+            },
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Where the planes of a YUV 4:2:0 frame go in a slot, one after the other without padding.
+#[derive(Debug, Clone, Copy)]
+struct FrameLayout {
+    width: usize,
+    height: usize,
+}
+
+impl FrameLayout {
+    fn new(width: u32, height: u32) -> Self {
+        Self {
+            width: width as usize,
+            height: height as usize,
+        }
+    }
+
+    /// The width and height of each plane.
+    fn planes(&self) -> [(usize, usize); 3] {
+        let chroma = (self.width.div_ceil(2), self.height.div_ceil(2));
+        [(self.width, self.height), chroma, chroma]
+    }
+
+    fn size(&self) -> usize {
+        self.planes()
+            .iter()
+            .map(|(width, height)| width * height)
+            .sum()
+    }
+
+    /// `frame` converted to YUV 4:2:0 at this size, or `None` if it already is.
+    fn convert(
+        &self,
+        frame: &Video,
+        scaler: &mut Option<scaling::Context>,
+    ) -> Result<Option<Video>> {
+        if frame.format() == Pixel::YUV420P
+            && frame.width() as usize == self.width
+            && frame.height() as usize == self.height
+        {
+            return Ok(None);
+        }
+
+        let matches = |scaler: &scaling::Context| {
+            let input = scaler.input();
+            input.format == frame.format()
+                && input.width == frame.width()
+                && input.height == frame.height()
+        };
+        if !scaler.as_ref().is_some_and(matches) {
+            *scaler = Some(scaling::Context::get(
+                frame.format(),
+                frame.width(),
+                frame.height(),
+                Pixel::YUV420P,
+                self.width as u32,
+                self.height as u32,
+                scaling::Flags::BILINEAR,
+            )?);
+        }
+
+        let mut converted = Video::empty();
+        scaler
+            .as_mut()
+            .context("No scaler")?
+            .run(frame, &mut converted)?;
+        Ok(Some(converted))
+    }
+
+    fn write(&self, frame: &Video, out: &mut [u8]) {
+        let mut start = 0;
+        for (plane, (width, height)) in self.planes().into_iter().enumerate() {
+            let stride = frame.stride(plane);
+            let data = frame.data(plane);
+            for row in 0..height {
+                out[start..start + width].copy_from_slice(&data[row * stride..][..width]);
+                start += width;
+            }
+        }
+    }
+
+    fn read(&self, data: &[u8], frame: &mut Video) {
+        let mut start = 0;
+        for (plane, (width, height)) in self.planes().into_iter().enumerate() {
+            let stride = frame.stride(plane);
+            let out = frame.data_mut(plane);
+            for row in 0..height {
+                out[row * stride..][..width].copy_from_slice(&data[start..start + width]);
+                start += width;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frames_survive_the_trip_through_a_slot() {
+        let layout = FrameLayout::new(5, 3);
+        assert_eq!(layout.size(), 5 * 3 + 2 * (3 * 2));
+
+        let mut frame = Video::new(Pixel::YUV420P, 5, 3);
+        for plane in 0..3 {
+            for (i, byte) in frame.data_mut(plane).iter_mut().enumerate() {
+                *byte = (plane * 100 + i) as u8;
+            }
+        }
+
+        let mut slot = vec![0; layout.size()];
+        layout.write(&frame, &mut slot);
+        let mut copy = Video::new(Pixel::YUV420P, 5, 3);
+        layout.read(&slot, &mut copy);
+
+        for (plane, (width, height)) in layout.planes().into_iter().enumerate() {
+            for row in 0..height {
+                let original = &frame.data(plane)[row * frame.stride(plane)..][..width];
+                let copied = &copy.data(plane)[row * copy.stride(plane)..][..width];
+                assert_eq!(original, copied);
+            }
+        }
+    }
+
+    #[test]
+    fn media_is_quarantined_after_crashing_twice() {
+        let quarantine = Quarantine::default();
+        assert!(!quarantine.record_crash(1));
+        assert!(!quarantine.contains(1));
+        assert!(quarantine.ids().is_empty());
+
+        assert!(quarantine.record_crash(1));
+        assert!(quarantine.contains(1));
+        assert_eq!(quarantine.ids(), [1]);
+    }
+}
@@ -0,0 +1,197 @@
+//! Notices when the app is struggling to keep up, so spawning can be scaled back until it
+//! recovers (see [`Throttle`](shared::user_config::Throttle)). Weak hardware can usually cope
+//! with a few video popups, but not with however many a mode asks for.
+//!
+//! Two things are sampled on the main thread: how much CPU time the whole process used (which
+//! includes the Lua and media threads, and video decoding), and how long each pass over the
+//! windows in `about_to_wait` takes, which is where video frames are produced.
+
+use std::time::{Duration, Instant};
+
+const SAMPLE_INTERVAL: Duration = Duration::from_secs(2);
+/// Once throttled, stay throttled for at least this long. Throttling lowers the load by itself,
+/// so without this we'd flap between the two states.
+const MIN_THROTTLE: Duration = Duration::from_secs(30);
+
+/// The fraction of all CPU cores the process may use before it counts as overloaded, and the
+/// fraction it has to drop below to recover.
+const CPU_OVERLOADED: f64 = 0.75;
+const CPU_RECOVERED: f64 = 0.5;
+/// The same for the average time spent updating windows per frame. A 30 fps video has 33ms per
+/// frame, and needs to share that with everything else.
+const FRAME_OVERLOADED: Duration = Duration::from_millis(25);
+const FRAME_RECOVERED: Duration = Duration::from_millis(12);
+
+/// One sample of how busy the app has been.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Load {
+    /// The fraction of all CPU cores used, from 0 to 1.
+    pub cpu: f64,
+    /// The average time spent updating windows per frame, if any frames were rendered.
+    pub frame_time: Option<Duration>,
+}
+
+impl Load {
+    fn is_overloaded(&self) -> bool {
+        self.cpu >= CPU_OVERLOADED || self.frame_time.is_some_and(|time| time >= FRAME_OVERLOADED)
+    }
+
+    fn is_recovered(&self) -> bool {
+        self.cpu < CPU_RECOVERED && self.frame_time.is_none_or(|time| time < FRAME_RECOVERED)
+    }
+}
+
+pub struct Governor {
+    last_sample: Instant,
+    last_cpu_time: Option<Duration>,
+    frame_time: Duration,
+    frames: u32,
+    overloaded_since: Option<Instant>,
+}
+
+impl Governor {
+    pub fn new() -> Self {
+        Self {
+            last_sample: Instant::now(),
+            last_cpu_time: process_cpu_time(),
+            frame_time: Duration::ZERO,
+            frames: 0,
+            overloaded_since: None,
+        }
+    }
+
+    /// Records how long one pass over the windows took.
+    pub fn record_frame(&mut self, duration: Duration) {
+        self.frame_time += duration;
+        self.frames += 1;
+    }
+
+    /// Takes a sample if it's been long enough since the last one. Returns whether the app is
+    /// overloaded if that changed.
+    pub fn poll(&mut self) -> Option<bool> {
+        let elapsed = self.last_sample.elapsed();
+        if elapsed < SAMPLE_INTERVAL {
+            return None;
+        }
+
+        let cpu_time = process_cpu_time();
+        let cores = std::thread::available_parallelism().map_or(1, |n| n.get());
+        let cpu = match (self.last_cpu_time, cpu_time) {
+            (Some(last), Some(now)) => {
+                now.saturating_sub(last).as_secs_f64() / (elapsed.as_secs_f64() * cores as f64)
+            }
+            _ => 0.0,
+        };
+        let load = Load {
+            cpu,
+            frame_time: (self.frames > 0).then(|| self.frame_time / self.frames),
+        };
+
+        self.last_sample = Instant::now();
+        self.last_cpu_time = cpu_time;
+        self.frame_time = Duration::ZERO;
+        self.frames = 0;
+
+        let overloaded = self.overloaded_since.is_some();
+        let now_overloaded = next_state(self.overloaded_since.map(|since| since.elapsed()), load);
+        if now_overloaded == overloaded {
+            return None;
+        }
+
+        tracing::info!(
+            "{} ({load:?})",
+            if now_overloaded {
+                "Overloaded; throttling spawning"
+            } else {
+                "Recovered; no longer throttling spawning"
+            }
+        );
+        self.overloaded_since = now_overloaded.then(Instant::now);
+
+        Some(now_overloaded)
+    }
+}
+
+/// Whether the app should be throttled after a sample, given how long it's been throttled for.
+fn next_state(throttled_for: Option<Duration>, load: Load) -> bool {
+    match throttled_for {
+        None => load.is_overloaded(),
+        Some(duration) => duration < MIN_THROTTLE || !load.is_recovered(),
+    }
+}
+
+/// The CPU time used by every thread in the process so far.
+#[cfg(unix)]
+fn process_cpu_time() -> Option<Duration> {
+    let mut usage: libc::rusage = unsafe { std::mem::zeroed() };
+    if unsafe { libc::getrusage(libc::RUSAGE_SELF, &mut usage) } != 0 {
+        return None;
+    }
+
+    let duration =
+        |time: libc::timeval| Duration::new(time.tv_sec as u64, time.tv_usec as u32 * 1000);
+    Some(duration(usage.ru_utime) + duration(usage.ru_stime))
+}
+
+#[cfg(windows)]
+fn process_cpu_time() -> Option<Duration> {
+    use windows::Win32::{
+        Foundation::FILETIME,
+        System::Threading::{GetCurrentProcess, GetProcessTimes},
+    };
+
+    let mut creation = FILETIME::default();
+    let mut exit = FILETIME::default();
+    let mut kernel = FILETIME::default();
+    let mut user = FILETIME::default();
+    unsafe {
+        GetProcessTimes(
+            GetCurrentProcess(),
+            &mut creation,
+            &mut exit,
+            &mut kernel,
+            &mut user,
+        )
+    }
+    .ok()?;
+
+    // `FILETIME`s count 100ns intervals.
+    let duration = |time: FILETIME| {
+        Duration::from_nanos(
+            (((time.dwHighDateTime as u64) << 32) | time.dwLowDateTime as u64) * 100,
+        )
+    };
+    Some(duration(kernel) + duration(user))
+}
+
+#[cfg(not(any(unix, windows)))]
+fn process_cpu_time() -> Option<Duration> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn throttles_with_hysteresis() {
+        let load = |cpu, frame_time: Option<u64>| Load {
+            cpu,
+            frame_time: frame_time.map(Duration::from_millis),
+        };
+
+        assert!(!next_state(None, load(0.6, Some(20))));
+        assert!(next_state(None, load(0.8, None)));
+        assert!(next_state(None, load(0.1, Some(40))));
+
+        // Stays throttled until the load is well below the limit...
+        let throttled_for = Some(MIN_THROTTLE);
+        assert!(next_state(throttled_for, load(0.6, Some(5))));
+        assert!(next_state(throttled_for, load(0.1, Some(20))));
+        assert!(!next_state(throttled_for, load(0.1, Some(5))));
+        assert!(!next_state(throttled_for, load(0.1, None)));
+
+        // ...and for a minimum amount of time.
+        assert!(next_state(Some(Duration::from_secs(2)), load(0.0, None)));
+    }
+}
@@ -0,0 +1,54 @@
+//! Hibernation (see [`Hibernation`]): the session goes quiet every so often and wakes up with a
+//! burst. How long each one lasts is picked at random, so like the other watchers it's timed from
+//! a plain thread.
+
+use std::thread;
+
+use rand::RngExt;
+use shared::user_config::Hibernation;
+use winit::event_loop::EventLoopProxy;
+
+use crate::{app::UserEvent, rng};
+
+/// Starts a thread that sends [`UserEvent::SetHibernating`] at the start and end of each
+/// hibernation. Does nothing if hibernation is off, or if it's set to neither stay awake nor
+/// hibernate for any time at all, which would just flip back and forth as fast as it could.
+pub fn spawn_hibernation_watcher(
+    event_loop_proxy: EventLoopProxy<UserEvent>,
+    hibernation: Hibernation,
+    seed: u64,
+) {
+    let never_sleeps =
+        hibernation.awake_minutes == 0 && hibernation.min_minutes.max(hibernation.max_minutes) == 0;
+    if !hibernation.enabled || never_sleeps {
+        return;
+    }
+
+    thread::spawn(move || {
+        rng::seed(seed, rng::Stream::Hibernation);
+
+        loop {
+            thread::sleep(hibernation.awake());
+
+            let length = hibernation.length(rng::with_rng(|rng| rng.random::<f64>()));
+            tracing::info!("Hibernating for {length:?}");
+            if event_loop_proxy
+                .send_event(UserEvent::SetHibernating { hibernating: true })
+                .is_err()
+            {
+                // The event loop has shut down.
+                break;
+            }
+
+            thread::sleep(length);
+
+            tracing::info!("Waking up from hibernation");
+            if event_loop_proxy
+                .send_event(UserEvent::SetHibernating { hibernating: false })
+                .is_err()
+            {
+                break;
+            }
+        }
+    });
+}
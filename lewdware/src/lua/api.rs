@@ -1,8 +1,20 @@
-use std::{cell::Cell, collections::HashMap, rc::Rc, time::Duration};
+use std::{
+    cell::{Cell, RefCell},
+    collections::HashMap,
+    rc::Rc,
+    time::Duration,
+};
 
 use mlua::{ExternalError, ExternalResult, FromLua, IntoLua, Lua, LuaSerdeExt};
 use serde::{Deserialize, Serialize};
-use shared::mode::OptionValue;
+use shared::{
+    goals::GoalStat,
+    mode::OptionValue,
+    template,
+    texts::{PromptKind, TextKind},
+    user_config::TagMatchMode,
+};
+use tokio::sync::watch;
 use winit::dpi::LogicalSize;
 
 #[derive(Debug, Clone, Copy)]
@@ -67,17 +79,23 @@ impl<'de> Deserialize<'de> for Color {
 
 use crate::{
     lua::{
-        AudioHandles, Media, MediaData, MediaType, Window, Windows,
+        AudioHandles, Media, MediaData, MediaType, Prompt, Window, Windows,
         audio::AudioHandle,
-        interval::{Interval, Timer},
+        goals::Goals,
+        intensity::Intensity,
+        interval::{Interval, Timer, wait_unpaused},
         request::RequestSender,
         window::{
-            ChoiceWindow, ChoiceWindowOption, ImageWindow, PromptWindow, TextWindow, VideoWindow,
+            ChoiceWindow, ChoiceWindowOption, ImageWindow, MinigameWindow, PromptWindow,
+            TextWindow, TypingTest, VideoWindow,
         },
     },
     media::{MediaManager, MediaTypes},
     monitor::Monitor,
-    utils::calculate_media_popup_size,
+    popup_set,
+    toast::{self, Corner, Slot},
+    utils::{calculate_media_popup_size, cover_size, random_position},
+    window::{DEFAULT_PROMPT_SIZE, MinigameStyle, PopupMedia, PromptStyle},
 };
 
 pub fn create_api(
@@ -86,6 +104,9 @@ pub fn create_api(
     media_manager: MediaManager,
     windows: Windows,
     audio_handles: AudioHandles,
+    intensity: Rc<Intensity>,
+    goals: Rc<Goals>,
+    paused: watch::Receiver<bool>,
     config: HashMap<String, OptionValue>,
 ) -> mlua::Result<()> {
     let api_table = lua.create_table()?;
@@ -226,12 +247,79 @@ pub fn create_api(
         )?;
     }
 
+    {
+        let media_manager = media_manager.clone();
+
+        media_table.set(
+            "random_n",
+            lua.create_async_function(move |lua, (n, opts)| {
+                random_media_n(lua, n, opts, media_manager.clone())
+            })?,
+        )?;
+    }
+
+    {
+        let media_manager = media_manager.clone();
+
+        media_table.set(
+            "random_set",
+            lua.create_async_function(move |lua, opts| {
+                random_set(lua, opts, media_manager.clone())
+            })?,
+        )?;
+    }
+
+    {
+        let media_manager = media_manager.clone();
+
+        media_table.set(
+            "random_toast",
+            lua.create_async_function(move |lua, opts| {
+                random_toast(lua, opts, media_manager.clone())
+            })?,
+        )?;
+    }
+
+    {
+        let media_manager = media_manager.clone();
+
+        media_table.set(
+            "random_text",
+            lua.create_async_function(move |lua, (kind, opts)| {
+                random_text(lua, kind, opts, media_manager.clone())
+            })?,
+        )?;
+    }
+
+    {
+        let media_manager = media_manager.clone();
+
+        media_table.set(
+            "random_prompt",
+            lua.create_async_function(move |lua, opts| {
+                random_prompt(lua, opts, media_manager.clone())
+            })?,
+        )?;
+    }
+
+    {
+        let media_manager = media_manager.clone();
+
+        media_table.set(
+            "preload",
+            lua.create_async_function(move |lua, media| {
+                preload(lua, media, media_manager.clone())
+            })?,
+        )?;
+    }
+
     api_table.set("media", media_table)?;
 
     {
         let media_manager = media_manager.clone();
         let request_sender = request_sender.clone();
         let windows = windows.clone();
+        let goals = goals.clone();
 
         api_table.set(
             "spawn_image_popup",
@@ -242,6 +330,7 @@ pub fn create_api(
                     media_manager.clone(),
                     request_sender.clone(),
                     windows.clone(),
+                    goals.clone(),
                 )
             })?,
         )?;
@@ -251,6 +340,7 @@ pub fn create_api(
         let media_manager = media_manager.clone();
         let request_sender = request_sender.clone();
         let windows = windows.clone();
+        let goals = goals.clone();
 
         api_table.set(
             "spawn_video_popup",
@@ -261,19 +351,73 @@ pub fn create_api(
                     media_manager.clone(),
                     request_sender.clone(),
                     windows.clone(),
+                    goals.clone(),
                 )
             })?,
         )?;
     }
 
     {
+        let media_manager = media_manager.clone();
+        let request_sender = request_sender.clone();
+        let windows = windows.clone();
+        let goals = goals.clone();
+
+        api_table.set(
+            "spawn_popup_set",
+            lua.create_async_function(move |lua, args| {
+                spawn_popup_set(
+                    lua,
+                    args,
+                    media_manager.clone(),
+                    request_sender.clone(),
+                    windows.clone(),
+                    goals.clone(),
+                )
+            })?,
+        )?;
+    }
+
+    {
+        let media_manager = media_manager.clone();
+        let request_sender = request_sender.clone();
+        let windows = windows.clone();
+        let goals = goals.clone();
+        let toasts = Rc::new(Toasts::new(paused.clone()));
+
+        api_table.set(
+            "spawn_toast",
+            lua.create_async_function(move |lua, args| {
+                spawn_toast(
+                    lua,
+                    args,
+                    media_manager.clone(),
+                    request_sender.clone(),
+                    windows.clone(),
+                    goals.clone(),
+                    toasts.clone(),
+                )
+            })?,
+        )?;
+    }
+
+    {
+        let media_manager = media_manager.clone();
         let request_sender = request_sender.clone();
         let windows = windows.clone();
+        let goals = goals.clone();
 
         api_table.set(
             "spawn_prompt",
             lua.create_async_function(move |lua, args| {
-                spawn_prompt(lua, args, request_sender.clone(), windows.clone())
+                spawn_prompt(
+                    lua,
+                    args,
+                    media_manager.clone(),
+                    request_sender.clone(),
+                    windows.clone(),
+                    goals.clone(),
+                )
             })?,
         )?;
     }
@@ -281,11 +425,37 @@ pub fn create_api(
     {
         let request_sender = request_sender.clone();
         let windows = windows.clone();
+        let goals = goals.clone();
 
         api_table.set(
             "spawn_choice",
             lua.create_async_function(move |lua, args| {
-                spawn_choice(lua, args, request_sender.clone(), windows.clone())
+                spawn_choice(
+                    lua,
+                    args,
+                    request_sender.clone(),
+                    windows.clone(),
+                    goals.clone(),
+                )
+            })?,
+        )?;
+    }
+
+    {
+        let request_sender = request_sender.clone();
+        let windows = windows.clone();
+        let goals = goals.clone();
+
+        api_table.set(
+            "spawn_minigame",
+            lua.create_async_function(move |lua, args| {
+                spawn_minigame(
+                    lua,
+                    args,
+                    request_sender.clone(),
+                    windows.clone(),
+                    goals.clone(),
+                )
             })?,
         )?;
     }
@@ -293,11 +463,18 @@ pub fn create_api(
     {
         let request_sender = request_sender.clone();
         let windows = windows.clone();
+        let goals = goals.clone();
 
         api_table.set(
             "spawn_text_popup",
             lua.create_async_function(move |lua, args| {
-                spawn_text_popup(lua, args, request_sender.clone(), windows.clone())
+                spawn_text_popup(
+                    lua,
+                    args,
+                    request_sender.clone(),
+                    windows.clone(),
+                    goals.clone(),
+                )
             })?,
         )?;
     }
@@ -352,19 +529,25 @@ pub fn create_api(
     {
         let request_sender = request_sender.clone();
 
+        let goals = goals.clone();
+
         api_table.set(
             "open_link",
-            lua.create_async_function(move |lua, url| open_link(lua, url, request_sender.clone()))?,
+            lua.create_async_function(move |lua, url| {
+                open_link(lua, url, request_sender.clone(), goals.clone())
+            })?,
         )?;
     }
 
     {
         let request_sender = request_sender.clone();
 
+        let goals = goals.clone();
+
         api_table.set(
             "show_notification",
             lua.create_async_function(move |lua, notification| {
-                show_notification(lua, notification, request_sender.clone())
+                show_notification(lua, notification, request_sender.clone(), goals.clone())
             })?,
         )?;
     }
@@ -404,9 +587,38 @@ pub fn create_api(
         )?;
     }
 
-    api_table.set("after", lua.create_function(after)?)?;
+    {
+        let intensity = intensity.clone();
 
-    api_table.set("every", lua.create_function(every)?)?;
+        api_table.set(
+            "intensity",
+            lua.create_function(move |_, ()| Ok(intensity.multiplier()))?,
+        )?;
+    }
+
+    api_table.set(
+        "on_intensity_change",
+        lua.create_function(move |_, cb: mlua::Function| intensity.add_callback(cb))?,
+    )?;
+
+    api_table.set(
+        "on_goal",
+        lua.create_function(move |_, cb: mlua::Function| goals.add_callback(cb))?,
+    )?;
+
+    {
+        let paused = paused.clone();
+
+        api_table.set(
+            "after",
+            lua.create_function(move |lua, args| after(lua, args, paused.clone()))?,
+        )?;
+    }
+
+    api_table.set(
+        "every",
+        lua.create_function(move |lua, args| every(lua, args, paused.clone()))?,
+    )?;
 
     lua.globals().set("lewdware", api_table)?;
 
@@ -459,10 +671,11 @@ async fn get_audio(
 async fn list_media_type(
     types: MediaTypes,
     tags: Option<Vec<String>>,
+    tag_match: Option<TagMatchMode>,
     media_manager: MediaManager,
 ) -> mlua::Result<Vec<Media>> {
     media_manager
-        .list_media(types, tags)
+        .list_media(types, tags, tag_match)
         .await
         .map_err(|err| err.into_lua_err())
 }
@@ -490,6 +703,9 @@ struct QueryMediaOpts {
     #[serde(rename = "type")]
     types: Option<OneOrMore<MediaType>>,
     tags: Option<Vec<String>>,
+    /// Whether entries need any of `tags` or all of them. Defaults to the user's config.
+    #[serde(rename = "match")]
+    tag_match: Option<TagMatchMode>,
 }
 
 impl FromLua for QueryMediaOpts {
@@ -503,19 +719,28 @@ async fn list_media(
     opts: Option<QueryMediaOpts>,
     media_manager: MediaManager,
 ) -> mlua::Result<Vec<Media>> {
-    let (types, tags) = match opts {
-        Some(QueryMediaOpts { types, tags }) => {
-            (types.map_or(MediaTypes::ALL, |t| MediaTypes::from(t)), tags)
-        }
-        None => (MediaTypes::ALL, None),
+    let (types, tags, tag_match) = match opts {
+        Some(QueryMediaOpts {
+            types,
+            tags,
+            tag_match,
+        }) => (
+            types.map_or(MediaTypes::ALL, |t| MediaTypes::from(t)),
+            tags,
+            tag_match,
+        ),
+        None => (MediaTypes::ALL, None, None),
     };
 
-    list_media_type(types, tags, media_manager).await
+    list_media_type(types, tags, tag_match, media_manager).await
 }
 
 #[derive(Serialize, Deserialize, Default)]
 struct QueryMediaTypeOpts {
     tags: Option<Vec<String>>,
+    /// See [`QueryMediaOpts::tag_match`].
+    #[serde(rename = "match")]
+    tag_match: Option<TagMatchMode>,
 }
 
 impl FromLua for QueryMediaTypeOpts {
@@ -529,9 +754,9 @@ async fn list_images(
     opts: Option<QueryMediaTypeOpts>,
     media_manager: MediaManager,
 ) -> mlua::Result<Vec<Media>> {
-    let tags = opts.map_or(None, |x| x.tags);
+    let opts = opts.unwrap_or_default();
 
-    list_media_type(MediaTypes::IMAGE, tags, media_manager).await
+    list_media_type(MediaTypes::IMAGE, opts.tags, opts.tag_match, media_manager).await
 }
 
 async fn list_videos(
@@ -539,9 +764,9 @@ async fn list_videos(
     opts: Option<QueryMediaTypeOpts>,
     media_manager: MediaManager,
 ) -> mlua::Result<Vec<Media>> {
-    let tags = opts.map_or(None, |x| x.tags);
+    let opts = opts.unwrap_or_default();
 
-    list_media_type(MediaTypes::VIDEO, tags, media_manager).await
+    list_media_type(MediaTypes::VIDEO, opts.tags, opts.tag_match, media_manager).await
 }
 
 async fn list_audio(
@@ -549,19 +774,20 @@ async fn list_audio(
     opts: Option<QueryMediaTypeOpts>,
     media_manager: MediaManager,
 ) -> mlua::Result<Vec<Media>> {
-    let tags = opts.map_or(None, |x| x.tags);
+    let opts = opts.unwrap_or_default();
 
-    list_media_type(MediaTypes::AUDIO, tags, media_manager).await
+    list_media_type(MediaTypes::AUDIO, opts.tags, opts.tag_match, media_manager).await
 }
 
 async fn random_media_type(
     _: Lua,
     types: MediaTypes,
     tags: Option<Vec<String>>,
+    tag_match: Option<TagMatchMode>,
     media_manager: MediaManager,
 ) -> mlua::Result<Option<Media>> {
     media_manager
-        .random_media(types, tags)
+        .random_media(types, tags, tag_match)
         .await
         .map_err(|err| err.into_lua_err())
 }
@@ -571,14 +797,82 @@ async fn random_media(
     opts: Option<QueryMediaOpts>,
     media_manager: MediaManager,
 ) -> mlua::Result<Option<Media>> {
-    let (types, tags) = match opts {
-        Some(QueryMediaOpts { types, tags }) => {
-            (types.map_or(MediaTypes::ALL, |t| MediaTypes::from(t)), tags)
-        }
-        None => (MediaTypes::ALL, None),
+    let (types, tags, tag_match) = match opts {
+        Some(QueryMediaOpts {
+            types,
+            tags,
+            tag_match,
+        }) => (
+            types.map_or(MediaTypes::ALL, |t| MediaTypes::from(t)),
+            tags,
+            tag_match,
+        ),
+        None => (MediaTypes::ALL, None, None),
+    };
+
+    random_media_type(lua, types, tags, tag_match, media_manager).await
+}
+
+async fn random_media_n(
+    _: Lua,
+    n: usize,
+    opts: Option<QueryMediaOpts>,
+    media_manager: MediaManager,
+) -> mlua::Result<Vec<Media>> {
+    let (types, tags, tag_match) = match opts {
+        Some(QueryMediaOpts {
+            types,
+            tags,
+            tag_match,
+        }) => (
+            types.map_or(MediaTypes::ALL, |t| MediaTypes::from(t)),
+            tags,
+            tag_match,
+        ),
+        None => (MediaTypes::ALL, None, None),
     };
 
-    random_media_type(lua, types, tags, media_manager).await
+    media_manager
+        .random_media_n(types, tags, tag_match, n)
+        .await
+        .map_err(|err| err.into_lua_err())
+}
+
+async fn random_set(
+    _: Lua,
+    opts: Option<QueryMediaOpts>,
+    media_manager: MediaManager,
+) -> mlua::Result<Vec<Media>> {
+    let (types, tags, tag_match) = match opts {
+        Some(QueryMediaOpts {
+            types,
+            tags,
+            tag_match,
+        }) => (
+            types.map_or(MediaTypes::ALL, |t| MediaTypes::from(t)),
+            tags,
+            tag_match,
+        ),
+        None => (MediaTypes::ALL, None, None),
+    };
+
+    media_manager
+        .random_set(types, tags, tag_match)
+        .await
+        .map_err(|err| err.into_lua_err())
+}
+
+async fn random_toast(
+    _: Lua,
+    opts: Option<QueryMediaTypeOpts>,
+    media_manager: MediaManager,
+) -> mlua::Result<Option<Media>> {
+    let opts = opts.unwrap_or_default();
+
+    media_manager
+        .random_toast(opts.tags, opts.tag_match)
+        .await
+        .map_err(|err| err.into_lua_err())
 }
 
 async fn random_image(
@@ -586,9 +880,16 @@ async fn random_image(
     opts: Option<QueryMediaTypeOpts>,
     media_manager: MediaManager,
 ) -> mlua::Result<Option<Media>> {
-    let tags = opts.map_or(None, |x| x.tags);
+    let opts = opts.unwrap_or_default();
 
-    random_media_type(lua, MediaTypes::IMAGE, tags, media_manager).await
+    random_media_type(
+        lua,
+        MediaTypes::IMAGE,
+        opts.tags,
+        opts.tag_match,
+        media_manager,
+    )
+    .await
 }
 
 async fn random_video(
@@ -596,9 +897,16 @@ async fn random_video(
     opts: Option<QueryMediaTypeOpts>,
     media_manager: MediaManager,
 ) -> mlua::Result<Option<Media>> {
-    let tags = opts.map_or(None, |x| x.tags);
+    let opts = opts.unwrap_or_default();
 
-    random_media_type(lua, MediaTypes::VIDEO, tags, media_manager).await
+    random_media_type(
+        lua,
+        MediaTypes::VIDEO,
+        opts.tags,
+        opts.tag_match,
+        media_manager,
+    )
+    .await
 }
 
 async fn random_audio(
@@ -606,9 +914,48 @@ async fn random_audio(
     opts: Option<QueryMediaTypeOpts>,
     media_manager: MediaManager,
 ) -> mlua::Result<Option<Media>> {
-    let tags = opts.map_or(None, |x| x.tags);
+    let opts = opts.unwrap_or_default();
+
+    random_media_type(
+        lua,
+        MediaTypes::AUDIO,
+        opts.tags,
+        opts.tag_match,
+        media_manager,
+    )
+    .await
+}
+
+async fn random_text(
+    lua: Lua,
+    kind: mlua::Value,
+    opts: Option<QueryMediaTypeOpts>,
+    media_manager: MediaManager,
+) -> mlua::Result<Option<String>> {
+    let kind: TextKind = lua.from_value(kind)?;
+    let opts = opts.unwrap_or_default();
+
+    media_manager
+        .random_text(kind, opts.tags, opts.tag_match)
+        .await
+        .map_err(|err| err.into_lua_err())
+}
+
+async fn random_prompt(
+    _: Lua,
+    opts: Option<QueryMediaTypeOpts>,
+    media_manager: MediaManager,
+) -> mlua::Result<Option<Prompt>> {
+    let opts = opts.unwrap_or_default();
+
+    media_manager
+        .random_prompt(opts.tags, opts.tag_match)
+        .await
+        .map_err(|err| err.into_lua_err())
+}
 
-    random_media_type(lua, MediaTypes::AUDIO, tags, media_manager).await
+async fn preload(_: Lua, media: Media, media_manager: MediaManager) -> mlua::Result<()> {
+    media_manager.preload(media.id).await.into_lua_err()
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -811,6 +1158,7 @@ async fn spawn_text_popup(
     (text, opts): (String, Option<SpawnTextOpts>),
     request_sender: RequestSender,
     windows: Windows,
+    goals: Rc<Goals>,
 ) -> mlua::Result<Rc<TextWindow>> {
     let opts = opts.unwrap_or_default();
 
@@ -830,6 +1178,7 @@ async fn spawn_text_popup(
         .try_borrow_mut()
         .into_lua_err()?
         .insert(id, Window::Text(window.clone()));
+    goals.record(GoalStat::Popups);
 
     Ok(window)
 }
@@ -846,12 +1195,13 @@ impl FromLua for SpawnImageOpts {
     }
 }
 
-async fn spawn_image_popup(
+pub(super) async fn spawn_image_popup(
     _: Lua,
     (image, opts): (Media, Option<SpawnImageOpts>),
     media_manager: MediaManager,
     request_sender: RequestSender,
     windows: Windows,
+    goals: Rc<Goals>,
 ) -> mlua::Result<Rc<ImageWindow>> {
     let mut opts = opts.unwrap_or_default();
 
@@ -864,17 +1214,7 @@ async fn spawn_image_popup(
         _ => return Err("`image` is not an image".into_lua_err()),
     };
 
-    if opts.window_opts.transparent.is_none() {
-        let needs_transparent = media_transparent
-            || opts.window_opts.opacity.map_or(false, |o| o < 1.0)
-            || opts
-                .window_opts
-                .background_color
-                .map_or(false, |c| c.a < 1.0);
-        if needs_transparent {
-            opts.window_opts.transparent = Some(true);
-        }
-    }
+    set_default_transparent(&mut opts.window_opts, media_transparent);
 
     let monitor = match &opts.window_opts.monitor {
         Some(monitor) => request_sender
@@ -903,7 +1243,16 @@ async fn spawn_image_popup(
     opts.window_opts.width = Some(Coord::Pixel(width as i32));
     opts.window_opts.height = Some(Coord::Pixel(height as i32));
 
-    let props = request_sender.spawn_image(data, opts.window_opts).await?;
+    let media = PopupMedia {
+        id: image.id,
+        name: image.name.clone(),
+        tags: image.tags.clone(),
+        width: image_width,
+        height: image_height,
+    };
+    let props = request_sender
+        .spawn_image(media, data, opts.window_opts)
+        .await?;
 
     let id = props.window_id;
 
@@ -917,6 +1266,7 @@ async fn spawn_image_popup(
         .try_borrow_mut()
         .into_lua_err()?
         .insert(id, Window::Image(window.clone()));
+    goals.record(GoalStat::Popups);
 
     Ok(window)
 }
@@ -958,33 +1308,36 @@ async fn spawn_video_popup(
     media_manager: MediaManager,
     request_sender: RequestSender,
     windows: Windows,
+    goals: Rc<Goals>,
 ) -> mlua::Result<Rc<VideoWindow>> {
     let mut opts = opts.unwrap_or_default();
 
-    let media_transparent = match video.media_data {
-        MediaData::Video { transparent, .. } => transparent,
+    let (video_width, video_height, media_transparent) = match video.media_data {
+        MediaData::Video {
+            width,
+            height,
+            transparent,
+            ..
+        } => (width, height, transparent),
         _ => return Err("`video` is not an video".into_lua_err()),
     };
 
-    if opts.window_opts.transparent.is_none() {
-        let needs_transparent = media_transparent
-            || opts.window_opts.opacity.map_or(false, |o| o < 1.0)
-            || opts
-                .window_opts
-                .background_color
-                .map_or(false, |c| c.a < 1.0);
-        if needs_transparent {
-            opts.window_opts.transparent = Some(true);
-        }
-    }
+    set_default_transparent(&mut opts.window_opts, media_transparent);
 
     let data = media_manager
         .get_video_data(video.id, opts.loop_video, opts.audio)
         .await
         .into_lua_err()?;
 
+    let media = PopupMedia {
+        id: video.id,
+        name: video.name.clone(),
+        tags: video.tags.clone(),
+        width: video_width,
+        height: video_height,
+    };
     let props = request_sender
-        .spawn_video(data, opts.loop_video, opts.window_opts)
+        .spawn_video(media, data, opts.loop_video, opts.window_opts)
         .await?;
 
     let id = props.window_id;
@@ -999,15 +1352,448 @@ async fn spawn_video_popup(
         .try_borrow_mut()
         .into_lua_err()?
         .insert(id, Window::Video(window.clone()));
+    goals.record(GoalStat::Popups);
 
     Ok(window)
 }
 
+/// Makes the window transparent if it needs to be and `transparent` wasn't set explicitly.
+fn set_default_transparent(window_opts: &mut SpawnWindowOpts, media_transparent: bool) {
+    if window_opts.transparent.is_none() {
+        let needs_transparent = media_transparent
+            || window_opts.opacity.map_or(false, |o| o < 1.0)
+            || window_opts.background_color.map_or(false, |c| c.a < 1.0);
+        if needs_transparent {
+            window_opts.transparent = Some(true);
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct SpawnPopupSetOpts {
+    /// Space between the windows, in pixels.
+    #[serde(default)]
+    gap: u32,
+    #[serde(rename = "loop")]
+    #[serde(default = "return_true")]
+    loop_video: bool,
+    #[serde(default = "return_true")]
+    audio: bool,
+    #[serde(flatten)]
+    window_opts: SpawnWindowOpts,
+}
+
+impl Default for SpawnPopupSetOpts {
+    fn default() -> Self {
+        Self {
+            gap: 0,
+            loop_video: true,
+            audio: true,
+            window_opts: Default::default(),
+        }
+    }
+}
+
+impl FromLua for SpawnPopupSetOpts {
+    fn from_lua(value: mlua::Value, lua: &Lua) -> mlua::Result<Self> {
+        lua.from_value(value)
+    }
+}
+
+/// Spawns a popup for each image or video in `media`, side by side on one monitor. The windows
+/// are shown once they've all been spawned, and closing any of them closes the rest.
+pub(super) async fn spawn_popup_set(
+    lua: Lua,
+    (media, opts): (Vec<Media>, Option<SpawnPopupSetOpts>),
+    media_manager: MediaManager,
+    request_sender: RequestSender,
+    windows: Windows,
+    goals: Rc<Goals>,
+) -> mlua::Result<Vec<Window>> {
+    let opts = opts.unwrap_or_default();
+
+    if media.is_empty() {
+        return Err("`media` is empty".into_lua_err());
+    }
+
+    let sizes = media
+        .iter()
+        .map(|media| match media.media_data {
+            MediaData::Image { width, height, .. } | MediaData::Video { width, height, .. } => {
+                Ok((width, height))
+            }
+            MediaData::Audio { .. } => {
+                Err(format!("`{}` is not an image or video", media.name).into_lua_err())
+            }
+        })
+        .collect::<mlua::Result<Vec<_>>>()?;
+
+    let monitor = match &opts.window_opts.monitor {
+        Some(monitor) => request_sender
+            .get_monitor(monitor.id)
+            .await
+            .into_lua_err()?,
+        None => request_sender.random_monitor().await.into_lua_err()?,
+    };
+
+    let layout = popup_set::arrange(
+        &sizes,
+        opts.window_opts
+            .height
+            .as_ref()
+            .map(|height| height.to_pixels(monitor.height).max(0) as u32),
+        opts.window_opts.decorations,
+        opts.gap,
+        monitor.width,
+        monitor.height,
+    );
+
+    let place = |coord: &Option<Coord>, set_size: u32, monitor_size: u32| {
+        let position = match coord {
+            Some(coord) => opts
+                .window_opts
+                .anchor
+                .resolve(coord.to_pixels(monitor_size), set_size),
+            None => random_position(set_size, monitor_size),
+        };
+        if opts.window_opts.clamp {
+            position
+                .max(0)
+                .min(monitor_size.saturating_sub(set_size) as i32)
+        } else {
+            position
+        }
+    };
+    let x = place(&opts.window_opts.x, layout.outer_width, monitor.width);
+    let y = place(&opts.window_opts.y, layout.outer_height, monitor.height);
+
+    let mut spawned: Vec<Window> = Vec::with_capacity(media.len());
+
+    for (media, placement) in media.into_iter().zip(layout.windows) {
+        let mut window_opts = opts.window_opts.clone();
+        window_opts.monitor = Some(monitor.clone());
+        window_opts.x = Some(Coord::Pixel(x + placement.x));
+        window_opts.y = Some(Coord::Pixel(y));
+        window_opts.anchor = Anchor::TopLeft;
+        window_opts.width = Some(Coord::Pixel(placement.width as i32));
+        window_opts.height = Some(Coord::Pixel(placement.height as i32));
+        // Decoding can take a while, so the windows are hidden until they're all ready rather
+        // than appearing one at a time.
+        window_opts.visible = false;
+
+        let window = match media.media_data {
+            MediaData::Image { .. } => spawn_image_popup(
+                lua.clone(),
+                (media, Some(SpawnImageOpts { window_opts })),
+                media_manager.clone(),
+                request_sender.clone(),
+                windows.clone(),
+                goals.clone(),
+            )
+            .await
+            .map(Window::Image),
+            _ => spawn_video_popup(
+                lua.clone(),
+                (
+                    media,
+                    Some(SpawnVideoOpts {
+                        loop_video: opts.loop_video,
+                        audio: opts.audio,
+                        window_opts,
+                    }),
+                ),
+                media_manager.clone(),
+                request_sender.clone(),
+                windows.clone(),
+                goals.clone(),
+            )
+            .await
+            .map(Window::Video),
+        };
+
+        match window {
+            Ok(window) => spawned.push(window),
+            Err(err) => {
+                for window in &spawned {
+                    if let Err(err) = window.inner_window().close().await {
+                        tracing::debug!("Couldn't close window: {err}");
+                    }
+                }
+                return Err(err);
+            }
+        }
+    }
+
+    let ids: Vec<_> = spawned
+        .iter()
+        .map(|window| window.inner_window().id())
+        .collect();
+
+    for window in &spawned {
+        window.inner_window().link(&ids).into_lua_err()?;
+
+        if opts.window_opts.visible {
+            window.inner_window().set_visible(true).await?;
+        }
+    }
+
+    Ok(spawned)
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct SpawnToastOpts {
+    #[serde(default)]
+    corner: Corner,
+    /// How long the toast stays up, in milliseconds.
+    #[serde(default = "default_toast_duration")]
+    duration: u64,
+    /// It's made shorter if that would make it too wide.
+    #[serde(default = "default_toast_height")]
+    height: Coord,
+    /// Space between the toasts in a stack, in pixels.
+    #[serde(default = "default_toast_gap")]
+    gap: u32,
+    /// Space between the stack and the edges of the monitor, in pixels.
+    #[serde(default = "default_toast_margin")]
+    margin: u32,
+    /// The primary monitor if not given.
+    monitor: Option<Monitor>,
+    #[serde(default)]
+    opacity: Option<f32>,
+    #[serde(default)]
+    click_through: bool,
+}
+
+fn default_toast_duration() -> u64 {
+    4000
+}
+
+fn default_toast_height() -> Coord {
+    Coord::Percent { percent: 15.0 }
+}
+
+fn default_toast_gap() -> u32 {
+    8
+}
+
+fn default_toast_margin() -> u32 {
+    16
+}
+
+impl Default for SpawnToastOpts {
+    fn default() -> Self {
+        Self {
+            corner: Corner::default(),
+            duration: default_toast_duration(),
+            height: default_toast_height(),
+            gap: default_toast_gap(),
+            margin: default_toast_margin(),
+            monitor: None,
+            opacity: None,
+            click_through: false,
+        }
+    }
+}
+
+impl FromLua for SpawnToastOpts {
+    fn from_lua(value: mlua::Value, lua: &Lua) -> mlua::Result<Self> {
+        lua.from_value(value)
+    }
+}
+
+/// The toasts on screen (or being spawned), so that new ones can be stacked around them.
+struct Toasts {
+    active: RefCell<Vec<ActiveToast>>,
+    next_id: Cell<u64>,
+    /// Toasts don't close themselves while the session is paused, since they're hidden.
+    paused: watch::Receiver<bool>,
+}
+
+struct ActiveToast {
+    id: u64,
+    monitor: u64,
+    corner: Corner,
+    slot: Slot,
+    /// Unset until the window has been spawned.
+    window: Option<Window>,
+}
+
+impl Toasts {
+    fn new(paused: watch::Receiver<bool>) -> Self {
+        Self {
+            active: RefCell::new(Vec::new()),
+            next_id: Cell::new(0),
+            paused,
+        }
+    }
+
+    /// Finds room for a toast `height` tall in a corner of a monitor, and holds on to it until
+    /// the toast is closed. Returns the toast's id and its offset from the corner, or `None` if
+    /// the corner is full.
+    fn reserve(
+        &self,
+        monitor: u64,
+        corner: Corner,
+        height: u32,
+        gap: u32,
+        length: u32,
+    ) -> mlua::Result<Option<(u64, u32)>> {
+        let mut active = self.active.try_borrow_mut().into_lua_err()?;
+        active.retain(|toast| {
+            toast
+                .window
+                .as_ref()
+                .is_none_or(|window| !window.inner_window().closed())
+        });
+
+        let stack: Vec<_> = active
+            .iter()
+            .filter(|toast| toast.monitor == monitor && toast.corner == corner)
+            .map(|toast| toast.slot)
+            .collect();
+        let Some(offset) = toast::stack_offset(&stack, height, gap, length) else {
+            return Ok(None);
+        };
+
+        let id = self.next_id.get();
+        self.next_id.set(id + 1);
+        active.push(ActiveToast {
+            id,
+            monitor,
+            corner,
+            slot: Slot { offset, height },
+            window: None,
+        });
+
+        Ok(Some((id, offset)))
+    }
+
+    /// Fills in the window for a reserved toast, or gives up the reservation if it couldn't be
+    /// spawned.
+    fn spawned(&self, id: u64, window: Option<Window>) -> mlua::Result<()> {
+        let mut active = self.active.try_borrow_mut().into_lua_err()?;
+        match window {
+            Some(window) => {
+                if let Some(toast) = active.iter_mut().find(|toast| toast.id == id) {
+                    toast.window = Some(window);
+                }
+            }
+            None => active.retain(|toast| toast.id != id),
+        }
+
+        Ok(())
+    }
+}
+
+/// Spawns `image` as a toast: in a corner of a monitor, next to any toasts already there, closing
+/// itself after a few seconds. Returns `nil` if the corner is full.
+async fn spawn_toast(
+    lua: Lua,
+    (image, opts): (Media, Option<SpawnToastOpts>),
+    media_manager: MediaManager,
+    request_sender: RequestSender,
+    windows: Windows,
+    goals: Rc<Goals>,
+    toasts: Rc<Toasts>,
+) -> mlua::Result<Option<Rc<ImageWindow>>> {
+    let opts = opts.unwrap_or_default();
+
+    let (image_width, image_height) = match image.media_data {
+        MediaData::Image { width, height, .. } => (width, height),
+        _ => return Err("`image` is not an image".into_lua_err()),
+    };
+
+    let monitor = match &opts.monitor {
+        Some(monitor) => request_sender
+            .get_monitor(monitor.id)
+            .await
+            .into_lua_err()?,
+        None => request_sender.primary_monitor().await.into_lua_err()?,
+    };
+
+    let (width, height) = toast::size(
+        image_width,
+        image_height,
+        opts.height.to_pixels(monitor.height).max(1) as u32,
+        monitor.width,
+    );
+    let length = monitor.height.saturating_sub(opts.margin * 2);
+    let Some((toast_id, offset)) =
+        toasts.reserve(monitor.id, opts.corner, height, opts.gap, length)?
+    else {
+        return Ok(None);
+    };
+    let (x, y) = opts.corner.position(
+        offset,
+        (width, height),
+        opts.margin,
+        (monitor.width, monitor.height),
+    );
+
+    let window_opts = SpawnWindowOpts {
+        x: Some(Coord::Pixel(x)),
+        y: Some(Coord::Pixel(y)),
+        width: Some(Coord::Pixel(width as i32)),
+        height: Some(Coord::Pixel(height as i32)),
+        monitor: Some(monitor),
+        decorations: false,
+        opacity: opts.opacity,
+        click_through: opts.click_through,
+        ..Default::default()
+    };
+
+    let window = match spawn_image_popup(
+        lua,
+        (image, Some(SpawnImageOpts { window_opts })),
+        media_manager,
+        request_sender,
+        windows,
+        goals,
+    )
+    .await
+    {
+        Ok(window) => window,
+        Err(err) => {
+            toasts.spawned(toast_id, None)?;
+            return Err(err);
+        }
+    };
+
+    let toast = Window::Image(window.clone());
+    toasts.spawned(toast_id, Some(toast.clone()))?;
+
+    let duration = Duration::from_millis(opts.duration);
+    let mut paused = toasts.paused.clone();
+    tokio::task::spawn_local(async move {
+        tokio::time::sleep(duration).await;
+        wait_unpaused(&mut paused).await;
+
+        if !toast.inner_window().closed()
+            && let Err(err) = toast.inner_window().close().await
+        {
+            tracing::debug!("Couldn't close toast: {err}");
+        }
+    });
+
+    Ok(Some(window))
+}
+
 #[derive(Serialize, Deserialize, Default)]
 struct SpawnPromptOpts {
+    #[serde(default)]
+    kind: PromptKind,
     text: Option<String>,
+    /// What has to be typed in, if it isn't `text`.
+    answer: Option<String>,
+    /// Seconds to type a typing test's passage in. Worked out from its length by default.
+    time_limit: Option<f64>,
+    /// The percentage of a typing test's passage that has to be typed correctly, 90 by default.
+    accuracy: Option<f64>,
     placeholder: Option<String>,
     initial_value: Option<String>,
+    /// An image drawn behind the prompt, cropped to cover it.
+    background: Option<Media>,
+    text_color: Option<Color>,
     #[serde(flatten)]
     window_opts: SpawnWindowOpts,
 }
@@ -1021,16 +1807,83 @@ impl FromLua for SpawnPromptOpts {
 async fn spawn_prompt(
     _: Lua,
     opts: Option<SpawnPromptOpts>,
+    media_manager: MediaManager,
     request_sender: RequestSender,
     windows: Windows,
+    goals: Rc<Goals>,
 ) -> mlua::Result<Rc<PromptWindow>> {
-    let opts = opts.unwrap_or_default();
+    let mut opts = opts.unwrap_or_default();
+
+    let typing_test = if opts.kind == PromptKind::TypingTest {
+        let time_limit = opts
+            .time_limit
+            .map(Duration::try_from_secs_f64)
+            .transpose()
+            .map_err(|_| "`time_limit` must be a non-negative number of seconds".into_lua_err())?;
+        let accuracy = opts.accuracy.unwrap_or(90.0);
+        if accuracy.is_nan() {
+            return Err("`accuracy` must be a percentage".into_lua_err());
+        }
+
+        Some(TypingTest {
+            time_limit,
+            accuracy: accuracy.clamp(0.0, 100.0) / 100.0,
+        })
+    } else {
+        None
+    };
+
+    let mut style = PromptStyle {
+        kind: opts.kind,
+        background: None,
+        text_color: opts.text_color,
+        time_limit: typing_test
+            .map(|typing_test| typing_test.time_limit(opts.text.as_deref().unwrap_or_default())),
+    };
+    if let Some(background) = opts.background {
+        let (image_width, image_height) = match background.media_data {
+            MediaData::Image { width, height, .. } => (width, height),
+            _ => return Err("`background` is not an image".into_lua_err()),
+        };
+
+        // The background is decoded at the window's size, so that has to be settled here rather
+        // than when the window is created.
+        let monitor = match &opts.window_opts.monitor {
+            Some(monitor) => request_sender
+                .get_monitor(monitor.id)
+                .await
+                .into_lua_err()?,
+            None => request_sender.random_monitor().await.into_lua_err()?,
+        };
+        let size = |coord: &Option<Coord>, total| {
+            coord.as_ref().map_or(DEFAULT_PROMPT_SIZE, |coord| {
+                coord.to_pixels(total).max(1) as u32
+            })
+        };
+        let width = size(&opts.window_opts.width, monitor.width);
+        let height = size(&opts.window_opts.height, monitor.height);
+
+        let (cover_width, cover_height) = cover_size(image_width, image_height, width, height);
+        let physical_size =
+            LogicalSize::new(cover_width, cover_height).to_physical(monitor.scale_factor);
+        style.background = Some(
+            media_manager
+                .get_image_data(background.id, physical_size.width, physical_size.height)
+                .await
+                .into_lua_err()?,
+        );
+
+        opts.window_opts.monitor = Some(monitor);
+        opts.window_opts.width = Some(Coord::Pixel(width as i32));
+        opts.window_opts.height = Some(Coord::Pixel(height as i32));
+    }
 
     let props = request_sender
         .spawn_prompt(
             opts.text.clone(),
             opts.placeholder,
             opts.initial_value.clone(),
+            style,
             opts.window_opts,
         )
         .await?;
@@ -1040,6 +1893,8 @@ async fn spawn_prompt(
     let window = Rc::new(PromptWindow::new(
         props,
         opts.text,
+        opts.answer,
+        typing_test,
         opts.initial_value.unwrap_or_default(),
         request_sender.window_sender(id),
     ));
@@ -1048,6 +1903,7 @@ async fn spawn_prompt(
         .try_borrow_mut()
         .into_lua_err()?
         .insert(id, Window::Prompt(window.clone()));
+    goals.record(GoalStat::Popups);
 
     Ok(window)
 }
@@ -1072,6 +1928,7 @@ async fn spawn_choice(
     opts: Option<SpawnChoiceOpts>,
     request_sender: RequestSender,
     windows: Windows,
+    goals: Rc<Goals>,
 ) -> mlua::Result<Rc<ChoiceWindow>> {
     let opts = opts.unwrap_or_default();
 
@@ -1092,10 +1949,78 @@ async fn spawn_choice(
         .try_borrow_mut()
         .into_lua_err()?
         .insert(id, Window::Choice(window.clone()));
+    goals.record(GoalStat::Popups);
 
     Ok(window)
 }
 
+#[derive(Serialize, Deserialize, Default)]
+struct SpawnMinigameOpts {
+    /// How many times the target has to be clicked, 5 by default.
+    hits: Option<u32>,
+    /// The target's width, 40 pixels by default.
+    target_size: Option<f32>,
+    /// How fast the target starts off moving, 150 pixels a second by default. It speeds up with
+    /// every hit.
+    speed: Option<f32>,
+    target_color: Option<Color>,
+    #[serde(flatten)]
+    window_opts: SpawnWindowOpts,
+}
+
+impl FromLua for SpawnMinigameOpts {
+    fn from_lua(value: mlua::Value, lua: &Lua) -> mlua::Result<Self> {
+        lua.from_value(value)
+    }
+}
+
+async fn spawn_minigame(
+    _: Lua,
+    opts: Option<SpawnMinigameOpts>,
+    request_sender: RequestSender,
+    windows: Windows,
+    goals: Rc<Goals>,
+) -> mlua::Result<Option<Rc<MinigameWindow>>> {
+    let mut opts = opts.unwrap_or_default();
+    // Clicking the target is the only way to close it.
+    opts.window_opts.closeable = false;
+
+    let style = MinigameStyle {
+        hits: opts.hits.unwrap_or(5).max(1),
+        target_radius: opts.target_size.unwrap_or(40.0) / 2.0,
+        speed: opts.speed.unwrap_or(150.0),
+        target_color: opts.target_color.unwrap_or(Color {
+            r: 0.88,
+            g: 0.14,
+            b: 0.37,
+            a: 1.0,
+        }),
+    };
+
+    let Some(props) = request_sender
+        .spawn_minigame(style, opts.window_opts)
+        .await?
+    else {
+        return Ok(None);
+    };
+
+    let id = props.window_id;
+
+    let window = Rc::new(MinigameWindow::new(
+        props,
+        style.hits,
+        request_sender.window_sender(id),
+    ));
+
+    windows
+        .try_borrow_mut()
+        .into_lua_err()?
+        .insert(id, Window::Minigame(window.clone()));
+    goals.record(GoalStat::Popups);
+
+    Ok(Some(window))
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 pub enum WallpaperMode {
     #[serde(rename = "center")]
@@ -1125,25 +2050,44 @@ impl FromLua for SetWallpaperOpts {
 
 async fn set_wallpaper(
     _: Lua,
-    (image, opts): (Media, Option<SetWallpaperOpts>),
+    (media, opts): (Media, Option<SetWallpaperOpts>),
     media_manager: MediaManager,
     request_sender: RequestSender,
 ) -> mlua::Result<()> {
     let opts = opts.unwrap_or_default();
 
-    if !matches!(image.media_data, MediaData::Image { .. }) {
-        return Err("`image` is not an image".into_lua_err());
+    match media.media_data {
+        MediaData::Image { .. } => {
+            let file = media_manager
+                .get_image_file(media.id)
+                .await
+                .into_lua_err()?;
+
+            request_sender
+                .set_wallpaper(file, opts.mode)
+                .await
+                .into_lua_err()
+        }
+        MediaData::Video { .. } => {
+            // Few platforms support video wallpapers natively, so we play the video in a
+            // window behind everything else on each monitor. Each window needs its own decoder;
+            // audio is never played, since several copies of the track would overlap.
+            let mut video_players = Vec::new();
+            for monitor in request_sender.list_monitors().await.into_lua_err()? {
+                let video_player = media_manager
+                    .get_video_data(media.id, true, false)
+                    .await
+                    .into_lua_err()?;
+                video_players.push((monitor, video_player));
+            }
+
+            request_sender
+                .set_video_wallpaper(video_players)
+                .await
+                .into_lua_err()
+        }
+        _ => Err("`media` is not an image or video".into_lua_err()),
     }
-
-    let file = media_manager
-        .get_image_file(image.id)
-        .await
-        .into_lua_err()?;
-
-    request_sender
-        .set_wallpaper(file, opts.mode)
-        .await
-        .into_lua_err()
 }
 
 async fn reset_wallpaper(_: Lua, _: (), request_sender: RequestSender) -> mlua::Result<()> {
@@ -1198,8 +2142,15 @@ async fn play_audio(
     Ok(audio_handle)
 }
 
-async fn open_link(_: Lua, url: String, request_sender: RequestSender) -> mlua::Result<()> {
-    request_sender.open_link(url).await.into_lua_err()
+async fn open_link(
+    _: Lua,
+    url: String,
+    request_sender: RequestSender,
+    goals: Rc<Goals>,
+) -> mlua::Result<()> {
+    request_sender.open_link(url).await.into_lua_err()?;
+    goals.record_link();
+    Ok(())
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -1214,11 +2165,27 @@ impl FromLua for Notification {
     }
 }
 
+/// Shows `notification`, with placeholders like `{popups}` filled in from the session's stats
+/// (see [`shared::template`]), so pack texts can count along.
 async fn show_notification(
     _: Lua,
     notification: Notification,
     request_sender: RequestSender,
+    goals: Rc<Goals>,
 ) -> mlua::Result<()> {
+    let stats = goals.stats_now();
+    let expand = |text: &str| {
+        template::expand(text, |name| {
+            stats.variable(name).map(|value| value.to_string())
+        })
+        .into_owned()
+    };
+
+    let notification = Notification {
+        summary: notification.summary.as_deref().map(expand),
+        body: expand(&notification.body),
+    };
+
     request_sender
         .show_notification(notification)
         .await
@@ -1237,10 +2204,18 @@ async fn exit(_: Lua, _: (), request_sender: RequestSender) -> mlua::Result<()>
     request_sender.exit().await.into_lua_err()
 }
 
-fn after(_: &Lua, (ms, function): (u64, mlua::Function)) -> mlua::Result<Timer> {
-    Ok(Timer::new(Duration::from_millis(ms), function))
+fn after(
+    _: &Lua,
+    (ms, function): (u64, mlua::Function),
+    paused: watch::Receiver<bool>,
+) -> mlua::Result<Timer> {
+    Ok(Timer::new(Duration::from_millis(ms), function, paused))
 }
 
-fn every(_: &Lua, (ms, function): (u64, mlua::Function)) -> mlua::Result<Interval> {
-    Ok(Interval::new(Duration::from_millis(ms), function))
+fn every(
+    _: &Lua,
+    (ms, function): (u64, mlua::Function),
+    paused: watch::Receiver<bool>,
+) -> mlua::Result<Interval> {
+    Ok(Interval::new(Duration::from_millis(ms), function, paused))
 }
@@ -0,0 +1,171 @@
+use std::{
+    cell::{Cell, RefCell},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use shared::goals::{Goal, GoalStat, GoalTracker, Milestone, SessionStats, SessionSummary};
+use tokio::sync::mpsc::{UnboundedReceiver, UnboundedSender, unbounded_channel};
+
+/// A goal or milestone the session has just reached.
+pub enum Reached {
+    Goal(Goal),
+    Milestone(Milestone),
+}
+
+/// Counts towards the session's goals and the pack's milestones (see [`shared::goals`]). Lives on
+/// the Lua thread, which sees every popup, prompt and choice. Reached goals and milestones are
+/// sent down a channel, so the runtime can let the user know and play the goal's moment (or
+/// unlock the milestone's tags) without holding any borrows here.
+///
+/// Modes can react to goals with `lewdware.on_goal()`.
+pub struct Goals {
+    tracker: RefCell<GoalTracker>,
+    /// The milestones not reached yet.
+    milestones: RefCell<Vec<Milestone>>,
+    stats: Cell<SessionStats>,
+    /// Time spent in the session before it was last paused, and when it was last resumed (or
+    /// started), if it isn't paused now.
+    active_before: Cell<Duration>,
+    active_since: Cell<Option<Instant>>,
+    started: Instant,
+    callbacks: RefCell<Vec<mlua::Function>>,
+    reached_tx: UnboundedSender<Reached>,
+}
+
+impl Goals {
+    pub fn new(goals: Vec<Goal>, milestones: Vec<Milestone>) -> (Self, UnboundedReceiver<Reached>) {
+        let (reached_tx, reached_rx) = unbounded_channel();
+
+        let goals = Self {
+            tracker: RefCell::new(GoalTracker::new(goals)),
+            milestones: RefCell::new(milestones),
+            stats: Cell::new(SessionStats::default()),
+            active_before: Cell::new(Duration::ZERO),
+            active_since: Cell::new(Some(Instant::now())),
+            started: Instant::now(),
+            callbacks: RefCell::new(Vec::new()),
+            reached_tx,
+        };
+
+        (goals, reached_rx)
+    }
+
+    /// Counts one more popup, prompt or choice.
+    pub fn record(&self, stat: GoalStat) {
+        let mut stats = self.stats.get();
+        *stats.get_mut(stat) += 1;
+        self.stats.set(stats);
+
+        self.update();
+    }
+
+    /// Counts a prompt answered with something other than what it asked for. Only shown in
+    /// texts, so there are no goals to update.
+    pub fn record_failed_prompt(&self) {
+        let mut stats = self.stats.get();
+        stats.prompts_failed += 1;
+        self.stats.set(stats);
+    }
+
+    /// Counts a popup the user closed themselves. Like failed prompts, this isn't a goal.
+    pub fn record_closed_popup(&self) {
+        let mut stats = self.stats.get();
+        stats.popups_closed += 1;
+        self.stats.set(stats);
+    }
+
+    pub fn record_link(&self) {
+        let mut stats = self.stats.get();
+        stats.links += 1;
+        self.stats.set(stats);
+    }
+
+    /// Catches up on the time spent in the session. Should be called regularly, since nothing
+    /// else happens when a minute passes.
+    pub fn tick(&self) {
+        self.update();
+    }
+
+    /// Time doesn't count towards goals while the session is paused.
+    pub fn set_paused(&self, paused: bool) {
+        match (paused, self.active_since.get()) {
+            (true, Some(since)) => {
+                self.active_before
+                    .set(self.active_before.get() + since.elapsed());
+                self.active_since.set(None);
+            }
+            (false, None) => self.active_since.set(Some(Instant::now())),
+            _ => {}
+        }
+    }
+
+    pub fn add_callback(&self, cb: mlua::Function) -> mlua::Result<()> {
+        self.callbacks
+            .try_borrow_mut()
+            .map_err(mlua::Error::external)?
+            .push(cb);
+
+        Ok(())
+    }
+
+    pub fn callbacks(&self) -> anyhow::Result<Vec<mlua::Function>> {
+        Ok(self.callbacks.try_borrow()?.clone())
+    }
+
+    pub fn summary(&self) -> anyhow::Result<SessionSummary> {
+        let ended_at = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+        Ok(self
+            .tracker
+            .try_borrow()?
+            .summary(self.stats_now(), ended_at))
+    }
+
+    /// Everything counted so far.
+    pub fn stats_now(&self) -> SessionStats {
+        SessionStats {
+            minutes: self.active_time().as_secs() / 60,
+            uptime: self.started.elapsed().as_secs() / 60,
+            ..self.stats.get()
+        }
+    }
+
+    /// Time spent in the session so far, leaving out time it spent paused.
+    pub fn active_time(&self) -> Duration {
+        self.active_before.get()
+            + self
+                .active_since
+                .get()
+                .map_or(Duration::ZERO, |since| since.elapsed())
+    }
+
+    fn update(&self) {
+        let stats = self.stats_now();
+        let Ok(mut tracker) = self.tracker.try_borrow_mut() else {
+            return;
+        };
+
+        for goal in tracker.update(&stats) {
+            tracing::info!("Reached goal {:?}", goal.name);
+            // Only fails once the runtime has shut down.
+            let _ = self.reached_tx.send(Reached::Goal(goal));
+        }
+
+        let Ok(mut milestones) = self.milestones.try_borrow_mut() else {
+            return;
+        };
+
+        let (reached, pending) = milestones
+            .drain(..)
+            .partition(|milestone| milestone.reached(&stats));
+        *milestones = pending;
+
+        for milestone in reached {
+            tracing::info!(
+                "Reached milestone {:?}, unlocking {:?}",
+                milestone.name,
+                milestone.unlocks
+            );
+            let _ = self.reached_tx.send(Reached::Milestone(milestone));
+        }
+    }
+}
@@ -0,0 +1,119 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use shared::{
+    history::{PromptRecord, PromptResponse},
+    user_config::{PromptLogging, append_prompt_history},
+};
+
+/// Adds prompts to the session history as they're answered or closed, if the user has turned
+/// that on.
+pub struct PromptHistory {
+    logging: PromptLogging,
+    /// When the session started, in seconds since the Unix epoch.
+    session: u64,
+}
+
+impl PromptHistory {
+    pub fn new(logging: PromptLogging) -> Self {
+        Self {
+            logging,
+            session: now(),
+        }
+    }
+
+    /// Records `prompt` being answered with `text`, or closed without an answer if it's `None`.
+    /// `answer` is what it wanted typed in, if that isn't `prompt` itself, e.g. for a sum.
+    pub fn record(&self, prompt: Option<String>, answer: Option<&str>, text: Option<&str>) {
+        let Some(record) = self.to_record(prompt, answer, text) else {
+            return;
+        };
+
+        if let Err(err) = append_prompt_history(&record) {
+            tracing::error!("Couldn't add to the prompt history: {err}");
+        }
+    }
+
+    fn to_record(
+        &self,
+        prompt: Option<String>,
+        answer: Option<&str>,
+        text: Option<&str>,
+    ) -> Option<PromptRecord> {
+        let answer = answer.or(prompt.as_deref());
+        let response = match (self.logging, text) {
+            (PromptLogging::Off, _) => return None,
+            (_, None) => PromptResponse::Unanswered,
+            (PromptLogging::Hashed, Some(text)) => PromptResponse::Hashed {
+                blake3: blake3::hash(text.as_bytes()).to_hex().to_string(),
+                matches: PromptResponse::matches(answer, text),
+            },
+            (PromptLogging::Plaintext, Some(text)) => PromptResponse::Plaintext {
+                text: text.to_string(),
+                matches: PromptResponse::matches(answer, text),
+            },
+        };
+
+        Some(PromptRecord {
+            session: self.session,
+            at: now(),
+            prompt,
+            response,
+        })
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |time| time.as_secs())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hashes_responses_unless_asked_not_to() {
+        let prompt = || Some("Hello".to_string());
+
+        assert!(
+            PromptHistory::new(PromptLogging::Off)
+                .to_record(prompt(), None, Some("Hello"))
+                .is_none()
+        );
+
+        let record = PromptHistory::new(PromptLogging::Hashed)
+            .to_record(prompt(), None, Some("hello"))
+            .unwrap();
+        assert_eq!(
+            record.response,
+            PromptResponse::Hashed {
+                blake3: blake3::hash(b"hello").to_hex().to_string(),
+                matches: true,
+            }
+        );
+
+        let record = PromptHistory::new(PromptLogging::Plaintext)
+            .to_record(prompt(), None, None)
+            .unwrap();
+        assert_eq!(record.response, PromptResponse::Unanswered);
+        assert_eq!(record.prompt, prompt());
+    }
+
+    #[test]
+    fn sums_are_checked_against_their_answer() {
+        let history = PromptHistory::new(PromptLogging::Plaintext);
+        let record = history
+            .to_record(Some("7 × 8".to_string()), Some("56"), Some("56"))
+            .unwrap();
+
+        assert_eq!(record.prompt.as_deref(), Some("7 × 8"));
+        assert_eq!(
+            record.response,
+            PromptResponse::Plaintext {
+                text: "56".to_string(),
+                matches: true,
+            }
+        );
+    }
+}
@@ -0,0 +1,220 @@
+use std::cell::{Cell, RefCell};
+
+/// The highest level on the tray's intensity dial.
+pub const MAX_INTENSITY: u8 = 10;
+/// The level sessions start at, which leaves the mode's own settings untouched.
+pub const DEFAULT_INTENSITY: u8 = 5;
+
+/// The intensity dial set from the tray. It isn't saved anywhere: every session starts at
+/// [`DEFAULT_INTENSITY`]. Modes read it as a multiplier through `lewdware.intensity()`, and can
+/// react to changes with `lewdware.on_intensity_change()`.
+///
+/// The battery saver and the governor scale the multiplier down further while the laptop is
+/// unplugged or the app is overloaded, so modes slow down without having to know about it. The
+/// same goes for escalation (see [`shared::user_config::Escalation`]), which ramps it up over the
+/// session, pacing (see [`shared::user_config::Pacing`]), which follows how the user's keeping up,
+/// and the bursts after waking up from hibernation or failing a prompt (see [`Burst`]).
+pub struct Intensity {
+    level: Cell<u8>,
+    limit: Cell<f64>,
+    escalation: Cell<f64>,
+    pacing: Cell<f64>,
+    /// Each [`Burst`]'s factor, and the id of the burst that set it.
+    bursts: [Cell<(f64, u64)>; 2],
+    callbacks: RefCell<Vec<mlua::Function>>,
+}
+
+impl Intensity {
+    pub fn new() -> Self {
+        Self {
+            level: Cell::new(DEFAULT_INTENSITY),
+            limit: Cell::new(1.0),
+            escalation: Cell::new(1.0),
+            pacing: Cell::new(1.0),
+            bursts: [const { Cell::new((1.0, 0)) }; 2],
+            callbacks: RefCell::new(Vec::new()),
+        }
+    }
+
+    /// The multiplier modes should apply to their spawn rates and popup limits: `1.0` at the
+    /// default level, `0.0` at the bottom of the dial and `2.0` at the top.
+    pub fn multiplier(&self) -> f64 {
+        level_multiplier(self.level.get())
+            * self.limit.get()
+            * self.escalation.get()
+            * self.pacing.get()
+            * self
+                .bursts
+                .iter()
+                .map(|burst| burst.get().0)
+                .product::<f64>()
+    }
+
+    pub fn add_callback(&self, cb: mlua::Function) -> mlua::Result<()> {
+        self.callbacks
+            .try_borrow_mut()
+            .map_err(mlua::Error::external)?
+            .push(cb);
+
+        Ok(())
+    }
+
+    pub fn set_level(&self, level: u8) -> anyhow::Result<()> {
+        let level = level.min(MAX_INTENSITY);
+        if self.level.replace(level) == level {
+            return Ok(());
+        }
+
+        self.notify()
+    }
+
+    /// Sets what the multiplier is scaled by for the battery saver and the governor: `1.0`
+    /// while neither is limiting the session.
+    pub fn set_limit(&self, rate: f64) -> anyhow::Result<()> {
+        let rate = rate.clamp(0.0, 1.0);
+        if self.limit.replace(rate) == rate {
+            return Ok(());
+        }
+
+        self.notify()
+    }
+
+    /// Sets what the multiplier is scaled by for escalation: `1.0` while it's off.
+    pub fn set_escalation(&self, factor: f64) -> anyhow::Result<()> {
+        let factor = factor.max(0.0);
+        if self.escalation.replace(factor) == factor {
+            return Ok(());
+        }
+
+        self.notify()
+    }
+
+    /// What the multiplier is scaled by for pacing: `1.0` while it's off.
+    pub fn pacing(&self) -> f64 {
+        self.pacing.get()
+    }
+
+    pub fn set_pacing(&self, factor: f64) -> anyhow::Result<()> {
+        let factor = factor.max(0.0);
+        if self.pacing.replace(factor) == factor {
+            return Ok(());
+        }
+
+        self.notify()
+    }
+
+    /// Scales the multiplier by `factor` for a short burst, until [`Self::end_burst`] is called
+    /// with the id this returns. A later burst from the same source replaces this one, and bursts
+    /// from different sources stack.
+    pub fn start_burst(&self, source: Burst, factor: f64) -> anyhow::Result<u64> {
+        let burst = &self.bursts[source as usize];
+        let id = burst.get().1 + 1;
+        let factor = factor.max(0.0);
+        if burst.replace((factor, id)).0 != factor {
+            self.notify()?;
+        }
+
+        Ok(id)
+    }
+
+    /// Ends the burst [`Self::start_burst`] returned `id` for, unless it's been replaced since.
+    pub fn end_burst(&self, source: Burst, id: u64) -> anyhow::Result<()> {
+        let burst = &self.bursts[source as usize];
+        let (factor, current) = burst.get();
+        if current != id {
+            return Ok(());
+        }
+
+        burst.set((1.0, id));
+        if factor == 1.0 {
+            return Ok(());
+        }
+
+        self.notify()
+    }
+
+    fn notify(&self) -> anyhow::Result<()> {
+        let multiplier = self.multiplier();
+        let callbacks = self.callbacks.try_borrow()?.clone();
+
+        for cb in callbacks {
+            tokio::task::spawn_local(async move {
+                if let Err(err) = cb.call_async::<()>(multiplier).await {
+                    tracing::error!("{err}");
+                }
+            });
+        }
+
+        Ok(())
+    }
+}
+
+/// What a burst of intensity is for. Each source has its own factor, so one ending doesn't cut
+/// another short.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Burst {
+    /// Just after waking up from hibernation.
+    Wake,
+    /// Just after failing a prompt (see [`shared::user_config::PromptFailure`]).
+    PromptFailure,
+}
+
+fn level_multiplier(level: u8) -> f64 {
+    level as f64 / DEFAULT_INTENSITY as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_level_leaves_settings_unchanged() {
+        let intensity = Intensity::new();
+        assert_eq!(intensity.multiplier(), 1.0);
+
+        intensity.set_level(MAX_INTENSITY + 5).unwrap();
+        assert_eq!(intensity.multiplier(), 2.0);
+
+        intensity.set_level(0).unwrap();
+        assert_eq!(intensity.multiplier(), 0.0);
+    }
+
+    #[test]
+    fn limit_scales_the_level() {
+        let intensity = Intensity::new();
+        intensity.set_level(MAX_INTENSITY).unwrap();
+        intensity.set_limit(0.25).unwrap();
+        assert_eq!(intensity.multiplier(), 0.5);
+
+        intensity.set_limit(1.0).unwrap();
+        assert_eq!(intensity.multiplier(), 2.0);
+    }
+
+    #[test]
+    fn escalation_scales_the_level() {
+        let intensity = Intensity::new();
+        intensity.set_escalation(1.5).unwrap();
+        assert_eq!(intensity.multiplier(), 1.5);
+
+        intensity.set_limit(0.5).unwrap();
+        assert_eq!(intensity.multiplier(), 0.75);
+    }
+
+    #[test]
+    fn bursts_only_end_themselves() {
+        let intensity = Intensity::new();
+        let wake = intensity.start_burst(Burst::Wake, 3.0).unwrap();
+        let first = intensity.start_burst(Burst::PromptFailure, 2.0).unwrap();
+        assert_eq!(intensity.multiplier(), 6.0);
+
+        // A second failure extends the first's burst, and neither touches the wake burst.
+        let second = intensity.start_burst(Burst::PromptFailure, 2.0).unwrap();
+        intensity.end_burst(Burst::PromptFailure, first).unwrap();
+        assert_eq!(intensity.multiplier(), 6.0);
+        intensity.end_burst(Burst::PromptFailure, second).unwrap();
+        assert_eq!(intensity.multiplier(), 3.0);
+
+        intensity.end_burst(Burst::Wake, wake).unwrap();
+        assert_eq!(intensity.multiplier(), 1.0);
+    }
+}
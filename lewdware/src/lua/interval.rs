@@ -6,10 +6,27 @@ pub struct Timer {
     duration: tokio::time::Duration,
 }
 
+/// Waits until the session isn't paused (e.g. while the screen is locked). Returns whether it
+/// had to wait.
+pub(super) async fn wait_unpaused(paused: &mut watch::Receiver<bool>) -> bool {
+    if !*paused.borrow_and_update() {
+        return false;
+    }
+
+    // The sender only goes away when the Lua runtime does, so there's nothing to wait for then.
+    let _ = paused.wait_for(|paused| !paused).await;
+    true
+}
+
 impl Timer {
-    pub fn new(duration: tokio::time::Duration, function: mlua::Function) -> Self {
+    pub fn new(
+        duration: tokio::time::Duration,
+        function: mlua::Function,
+        mut paused: watch::Receiver<bool>,
+    ) -> Self {
         let task = tokio::task::spawn_local(async move {
             tokio::time::sleep(duration).await;
+            wait_unpaused(&mut paused).await;
 
             if let Err(err) = function.call_async::<()>(()).await {
                 tracing::error!("{err}");
@@ -41,7 +58,11 @@ pub struct Interval {
 }
 
 impl Interval {
-    pub fn new(duration: tokio::time::Duration, function: mlua::Function) -> Self {
+    pub fn new(
+        duration: tokio::time::Duration,
+        function: mlua::Function,
+        mut paused: watch::Receiver<bool>,
+    ) -> Self {
         let (interval_tx, mut interval_rx) = watch::channel(duration);
         interval_rx.mark_unchanged();
 
@@ -58,12 +79,8 @@ impl Interval {
                     select! {
                         tick = interval.tick() => {
                             last_tick = tick;
-
-                            if let Err(err) = function.call_async::<()>(()).await {
-                                tracing::error!("{err}");
-                            };
                         },
-                            result = interval_rx.changed() => {
+                        result = interval_rx.changed() => {
                             if !result.is_err() {
                                 let duration = *interval_rx.borrow();
                                 interval =
@@ -73,15 +90,24 @@ impl Interval {
                                 interval_rx_opt = None;
                                 tracing::warn!("interval watch channel closed");
                             }
+
+                            continue;
                         }
                     }
                 } else {
-                    interval.tick().await;
+                    last_tick = interval.tick().await;
+                }
 
-                    if let Err(err) = function.call_async::<()>(()).await {
-                        tracing::error!("{err}");
-                    };
+                if wait_unpaused(&mut paused).await {
+                    // Fire once on resuming, rather than once for every tick missed while paused,
+                    // and count the next tick from now.
+                    interval.reset();
+                    last_tick = Instant::now();
                 }
+
+                if let Err(err) = function.call_async::<()>(()).await {
+                    tracing::error!("{err}");
+                };
             }
         });
 
@@ -119,3 +145,124 @@ impl UserData for Interval {
         });
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use mlua::Lua;
+    use tokio::{task::LocalSet, time::sleep};
+
+    use super::*;
+
+    /// A function that counts its calls in the global `count`.
+    fn counter(lua: &Lua) -> mlua::Function {
+        lua.load("count = 0").exec().unwrap();
+        lua.load("function() count = count + 1 end").eval().unwrap()
+    }
+
+    fn count(lua: &Lua) -> u32 {
+        lua.globals().get("count").unwrap()
+    }
+
+    fn unpaused() -> watch::Receiver<bool> {
+        watch::channel(false).1
+    }
+
+    // Timers run on tokio's clock, so pausing it lets tests step through time without waiting.
+
+    #[tokio::test(start_paused = true)]
+    async fn timer_fires_once_after_its_duration() {
+        let lua = Lua::new();
+
+        LocalSet::new()
+            .run_until(async {
+                let _timer = Timer::new(Duration::from_millis(500), counter(&lua), unpaused());
+
+                sleep(Duration::from_millis(450)).await;
+                assert_eq!(count(&lua), 0);
+
+                sleep(Duration::from_millis(100)).await;
+                assert_eq!(count(&lua), 1);
+
+                sleep(Duration::from_secs(10)).await;
+                assert_eq!(count(&lua), 1);
+
+                // Stopped the way a mode would, through `timer:stop()`.
+                let stopped = lua
+                    .create_userdata(Timer::new(
+                        Duration::from_millis(500),
+                        counter(&lua),
+                        unpaused(),
+                    ))
+                    .unwrap();
+                stopped.call_method::<()>("stop", ()).unwrap();
+                sleep(Duration::from_secs(1)).await;
+                assert_eq!(count(&lua), 0);
+            })
+            .await;
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn interval_keeps_cadence_across_duration_changes() {
+        let lua = Lua::new();
+
+        LocalSet::new()
+            .run_until(async {
+                let interval = lua
+                    .create_userdata(Interval::new(
+                        Duration::from_millis(100),
+                        counter(&lua),
+                        unpaused(),
+                    ))
+                    .unwrap();
+
+                // Ticks at 100, 200 and 300.
+                sleep(Duration::from_millis(350)).await;
+                assert_eq!(count(&lua), 3);
+
+                // The next tick is measured from the last one, at 300 + 200.
+                interval.call_method::<()>("set_duration", 200).unwrap();
+                sleep(Duration::from_millis(100)).await;
+                assert_eq!(count(&lua), 3);
+                sleep(Duration::from_millis(100)).await;
+                assert_eq!(count(&lua), 4);
+
+                interval.call_method::<()>("stop", ()).unwrap();
+                sleep(Duration::from_secs(1)).await;
+                assert_eq!(count(&lua), 4);
+            })
+            .await;
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn paused_timers_wait_until_resumed() {
+        let lua = Lua::new();
+        let (paused_tx, paused_rx) = watch::channel(false);
+
+        LocalSet::new()
+            .run_until(async {
+                let _timer =
+                    Timer::new(Duration::from_millis(500), counter(&lua), paused_rx.clone());
+                let _interval = Interval::new(Duration::from_millis(100), counter(&lua), paused_rx);
+
+                // The interval ticks at 100, then everything is held back.
+                sleep(Duration::from_millis(150)).await;
+                assert_eq!(count(&lua), 1);
+                paused_tx.send(true).unwrap();
+                sleep(Duration::from_secs(1)).await;
+                assert_eq!(count(&lua), 1);
+
+                // On resuming, the timer and the interval each fire once, and the interval's next
+                // tick is a full period later.
+                paused_tx.send(false).unwrap();
+                sleep(Duration::from_millis(1)).await;
+                assert_eq!(count(&lua), 3);
+                sleep(Duration::from_millis(90)).await;
+                assert_eq!(count(&lua), 3);
+                sleep(Duration::from_millis(20)).await;
+                assert_eq!(count(&lua), 4);
+            })
+            .await;
+    }
+}
@@ -1,5 +1,6 @@
 use mlua::{FromLua, IntoLua, LuaSerdeExt, SerializeOptions};
 use serde::{Deserialize, Serialize};
+use shared::texts::PromptKind;
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Media {
@@ -7,6 +8,15 @@ pub struct Media {
     pub name: String,
     #[serde(flatten)]
     pub media_data: MediaData,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// The rest are set from the file's sidecar when it was packed, if it had one.
+    pub caption: Option<String>,
+    pub category: Option<String>,
+    pub weight: Option<f64>,
+    pub cooldown: Option<f64>,
+    /// The popup set the file belongs to, see `lewdware.media.random_set()`.
+    pub set: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -39,6 +49,25 @@ pub enum MediaType {
     Audio,
 }
 
+/// A prompt from `lewdware.media.random_prompt()`, with the look the pack gives it. Its fields
+/// line up with `lewdware.spawn_prompt()`'s options, so it can be passed straight to it.
+#[derive(Debug, Serialize, Clone)]
+pub struct Prompt {
+    pub kind: PromptKind,
+    pub text: String,
+    /// What has to be typed in, if it isn't `text`, e.g. the answer to a sum.
+    pub answer: Option<String>,
+    pub background: Option<Media>,
+    pub background_color: Option<String>,
+    pub text_color: Option<String>,
+}
+
+impl IntoLua for Prompt {
+    fn into_lua(self, lua: &mlua::Lua) -> mlua::Result<mlua::Value> {
+        lua.to_value_with(&self, SerializeOptions::new().serialize_none_to_null(false))
+    }
+}
+
 impl IntoLua for Media {
     fn into_lua(self, lua: &mlua::Lua) -> mlua::Result<mlua::Value> {
         lua.to_value_with(&self, SerializeOptions::new().serialize_none_to_null(false))
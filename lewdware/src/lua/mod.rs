@@ -1,23 +1,39 @@
 mod api;
 mod audio;
+mod goals;
+mod history;
+mod intensity;
 mod interval;
 mod media;
 mod mode;
 mod request;
 mod window;
 
-use std::{cell::RefCell, collections::HashMap, fs::File, io::Cursor, rc::Rc, sync::Arc, thread};
+use std::{
+    cell::{Cell, RefCell},
+    collections::HashMap,
+    fs::File,
+    io::Cursor,
+    rc::Rc,
+    sync::Arc,
+    thread,
+    time::Duration,
+};
 
 use anyhow::bail;
-use mlua::{ExternalResult, Lua, StdLib};
+use mlua::{ExternalResult, Lua, LuaSerdeExt, StdLib};
 use shared::{
+    goals::{Goal, GoalStat, Milestone},
+    history::PromptResponse,
+    locale::{tr, tr_args},
     mode::{Metadata, OptionValue, VERSION_MAJOR, read_mode_metadata},
-    user_config::AppConfig,
+    texts::TextKind,
+    user_config::{AppConfig, Pacing, PromptFailure, StartDelay, save_last_session},
 };
 use tokio::{
     sync::{
         mpsc::{Receiver, UnboundedSender, channel, unbounded_channel},
-        oneshot,
+        oneshot, watch,
     },
     task::LocalSet,
 };
@@ -25,32 +41,66 @@ use winit::{event_loop::EventLoopProxy, window::WindowId};
 
 use crate::{
     app::UserEvent,
+    credits::credits_text,
     lua::{
-        api::create_api,
+        api::{create_api, spawn_image_popup, spawn_popup_set},
         audio::AudioHandle,
+        goals::{Goals, Reached},
+        history::PromptHistory,
+        intensity::{Burst, Intensity},
         mode::{Mode, ReadSeek},
         request::RequestSender,
-        window::Window,
+        window::{PromptWindow, Window},
     },
-    media::MediaManager,
+    media::{MediaManager, MediaTypes},
     monitor::Monitor,
+    rng,
 };
 
 pub use api::{
     Color, Coord, FontSize, Notification, SpawnWindowOpts, TextAlign, TextFont, TextStyle,
     WallpaperMode,
 };
-pub use media::{Media, MediaData, MediaType};
+pub use intensity::{DEFAULT_INTENSITY, MAX_INTENSITY};
+pub use media::{Media, MediaData, MediaType, Prompt};
 pub use request::{AudioAction, LuaRequest, WindowAction};
 pub use window::{ChoiceWindowOption, Easing, FadeOpts, MoveOpts};
 
 pub enum Event {
     WindowClosed { id: WindowId },
+    /// The user closed a popup themselves, `open_for` after it opened, as well as its
+    /// `WindowClosed`.
+    PopupDismissed { open_for: Duration },
     MoveFinish { id: WindowId, move_id: u64, x: i32, y: i32 },
     AudioFinish { id: u64 },
-    PromptSubmit { id: WindowId, text: String },
+    /// `timed_out` is whether a typing test's time had run out, in which case the prompt may
+    /// have submitted itself.
+    PromptSubmit {
+        id: WindowId,
+        text: String,
+        timed_out: bool,
+    },
     ChoiceSelect { id: WindowId, option_id: String },
+    /// The user clicked a minigame's target, for the `hits`th time.
+    MinigameHit { id: WindowId, hits: u32 },
     FadeFinish { id: WindowId, fade_id: u64 },
+    IntensityChange { level: u8 },
+    /// The session was paused or resumed, e.g. because the screen was locked.
+    SetPaused { paused: bool },
+    /// The battery saver or the governor changed how much the session should do: `rate` scales
+    /// the intensity multiplier, and `videos` is whether random queries can return videos.
+    SetLoadLimits { rate: f64, videos: bool },
+    /// The session woke up from hibernation. The intensity multiplier is scaled by `burst` for a
+    /// while, to make up for the quiet.
+    Wake { burst: f64 },
+    /// The user blocked the media shown in a popup (see `block_button` in the config). The
+    /// popup has already been closed.
+    BlockMedia { media_id: u64 },
+    /// The user added the media shown in a popup to their favorites, or took it off them (see
+    /// `favorite_button` in the config).
+    ToggleFavorite { media_id: u64 },
+    /// The user asked to see the pack's credits, from the tray.
+    ShowCredits,
 }
 
 #[derive(Debug, Clone)]
@@ -82,6 +132,17 @@ pub struct LuaThreadHandle {
 /// yields) so a slow shutdown can never turn into the app failing to quit.
 const SHUTDOWN_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(5);
 
+/// How often time spent in the session is checked against the goals.
+const GOAL_TICK: Duration = Duration::from_secs(10);
+
+/// How often escalation moves the intensity multiplier along. Each step tells the mode about the
+/// new multiplier, so this is kept well above how often modes usually spawn.
+const ESCALATION_TICK: Duration = Duration::from_secs(30);
+/// How long the burst after waking up from hibernation lasts.
+const WAKE_BURST: Duration = Duration::from_secs(60);
+/// How long before the end of the start delay the second countdown notification is shown.
+const START_WARNING: Duration = Duration::from_secs(10);
+
 impl LuaThreadHandle {
     /// Signals the Lua thread to stop and waits (up to [`SHUTDOWN_TIMEOUT`]) for it, and the
     /// media manager thread it owns, to finish, running their `Drop` impls.
@@ -126,8 +187,22 @@ pub fn start_lua_thread(
             .build()
             .expect("Failed to build tokio runtime");
 
-        let (media_manager, _, media_manager_handle) = match MediaManager::open(
+        let seed = config.seed.unwrap_or_else(rand::random);
+
+        let (media_manager, pack_metadata, media_manager_handles) = match MediaManager::open(
             &config.pack_path.clone().unwrap(),
+            config.blend_pack.as_ref(),
+            config.verify_media,
+            config.max_rating,
+            config.excluded_tags.clone(),
+            config.tag_match_mode,
+            config.favorites,
+            shared::locale::preferred_language(config.language.as_deref()),
+            seed,
+            config.video_performance.max_fps,
+            // Isolated decoding hands frames over through a file.
+            config.isolate_video_decoding && !config.incognito,
+            config.image_cache.bytes(),
             event_loop_proxy.clone(),
             wgpu_device,
         ) {
@@ -208,13 +283,29 @@ pub fn start_lua_thread(
 
         let mode = Mode::new(file, files);
 
+        let credits = credits_text(&pack_metadata);
+        let (goals, mut reached_rx) = Goals::new(
+            config
+                .goals
+                .iter()
+                .cloned()
+                .chain(pack_metadata.goals)
+                .collect(),
+            pack_metadata.milestones,
+        );
+        let goals = Rc::new(goals);
+
         let mut local = LocalSet::new();
 
         let runtime = match LuaRuntime::new(
             mode,
             RequestSender::new(request_tx, event_loop_proxy),
             media_manager,
+            goals.clone(),
+            &config,
             mode_config,
+            seed,
+            credits,
         ) {
             Ok(x) => Rc::new(x),
             Err(err) => {
@@ -224,8 +315,13 @@ pub fn start_lua_thread(
         };
 
         let runtime_clone = runtime.clone();
+        let start_delay = config.start_delay.clone();
 
         local.spawn_local(async move {
+            if let Err(err) = runtime_clone.wait_start_delay(&start_delay).await {
+                tracing::error!("{err}");
+            }
+
             if let Err(err) = runtime_clone.run_entrypoint(entrypoint).await {
                 tracing::error!("{err}");
             }
@@ -233,6 +329,56 @@ pub fn start_lua_thread(
             tracing::info!("Code finished");
         });
 
+        {
+            let runtime = runtime.clone();
+
+            local.spawn_local(async move {
+                while let Some(reached) = reached_rx.recv().await {
+                    let runtime = runtime.clone();
+
+                    tokio::task::spawn_local(async move {
+                        let result = match reached {
+                            Reached::Goal(goal) => runtime.on_goal_reached(goal).await,
+                            Reached::Milestone(milestone) => {
+                                runtime.on_milestone_reached(milestone).await
+                            }
+                        };
+                        if let Err(err) = result {
+                            tracing::error!("{err}");
+                        }
+                    });
+                }
+            });
+        }
+
+        {
+            let goals = goals.clone();
+
+            local.spawn_local(async move {
+                let mut interval = tokio::time::interval(GOAL_TICK);
+                loop {
+                    interval.tick().await;
+                    goals.tick();
+                }
+            });
+        }
+
+        if config.escalation.enabled {
+            let runtime = runtime.clone();
+            let escalation = config.escalation.clone();
+
+            local.spawn_local(async move {
+                let mut interval = tokio::time::interval(ESCALATION_TICK);
+                loop {
+                    interval.tick().await;
+                    let factor = escalation.factor(runtime.goals.active_time());
+                    if let Err(err) = runtime.intensity.set_escalation(factor) {
+                        tracing::error!("{err}");
+                    }
+                }
+            });
+        }
+
         local.spawn_local(async move {
             while let Some(event) = event_rx.recv().await {
                 let runtime = runtime.clone();
@@ -261,8 +407,20 @@ pub fn start_lua_thread(
         // channel so it can shut down and clean up its temp files too.
         drop(local);
 
-        if media_manager_handle.join().is_err() {
-            tracing::error!("Media manager thread panicked");
+        match goals.summary() {
+            Ok(summary) => {
+                tracing::info!("Session summary: {summary:?}");
+                if let Err(err) = save_last_session(&summary) {
+                    tracing::error!("Couldn't save the session summary: {err}");
+                }
+            }
+            Err(err) => tracing::error!("{err}"),
+        }
+
+        for handle in media_manager_handles {
+            if handle.join().is_err() {
+                tracing::error!("Media manager thread panicked");
+            }
         }
 
         tracing::info!("Thread killed");
@@ -282,6 +440,17 @@ struct LuaRuntime {
     media_manager: MediaManager,
     windows: Windows,
     audio_handles: AudioHandles,
+    intensity: Rc<Intensity>,
+    goals: Rc<Goals>,
+    prompt_history: PromptHistory,
+    pacing: Pacing,
+    prompt_failure: PromptFailure,
+    /// Holds back timers and intervals while the session is paused.
+    paused: watch::Sender<bool>,
+    /// Whether the start delay is still being waited out, which stops goal time as well.
+    starting: Cell<bool>,
+    /// What the credits window shows (see [`credits_text`]).
+    credits: String,
     lua: Lua,
 }
 
@@ -290,24 +459,197 @@ impl LuaRuntime {
         mode: Mode,
         request_tx: RequestSender,
         media_manager: MediaManager,
-        config: HashMap<String, OptionValue>,
+        goals: Rc<Goals>,
+        config: &AppConfig,
+        mode_config: HashMap<String, OptionValue>,
+        seed: u64,
+        credits: String,
     ) -> anyhow::Result<Self> {
         let lua = create_sandboxed_lua()?;
 
+        // Lua seeds `math.random` itself when it starts, so seed it again to follow the session.
+        lua.globals()
+            .get::<mlua::Table>("math")?
+            .get::<mlua::Function>("randomseed")?
+            .call::<()>(seed as i64)?;
+        rng::seed(seed, rng::Stream::Lua);
+
         let mut runtime = Self {
             mode: Rc::new(mode),
             request_sender: request_tx,
             media_manager,
             windows: Rc::new(RefCell::new(HashMap::new())),
             audio_handles: Rc::new(RefCell::new(HashMap::new())),
+            intensity: Rc::new(Intensity::new()),
+            goals,
+            prompt_history: PromptHistory::new(config.prompt_logging),
+            pacing: config.pacing.clone(),
+            prompt_failure: config.prompt_failure.clone(),
+            paused: watch::Sender::new(false),
+            starting: Cell::new(false),
+            credits,
             lua,
         };
 
-        runtime.create_api(config)?;
+        runtime.create_api(mode_config)?;
 
         Ok(runtime)
     }
 
+    /// Waits out the user's start delay, if they have one, before the mode is run. Nothing can
+    /// spawn until the mode runs, so this holds back the whole session.
+    async fn wait_start_delay(&self, start_delay: &StartDelay) -> anyhow::Result<()> {
+        if start_delay.seconds == 0 {
+            return Ok(());
+        }
+
+        let delay = Duration::from_secs(start_delay.seconds.into());
+        tracing::info!("Waiting {delay:?} before starting");
+
+        self.starting.set(true);
+        self.goals.set_paused(true);
+
+        if start_delay.countdown && delay > START_WARNING * 2 {
+            self.show_countdown(delay).await?;
+            tokio::time::sleep(delay - START_WARNING).await;
+            self.show_countdown(START_WARNING).await?;
+            tokio::time::sleep(START_WARNING).await;
+        } else {
+            if start_delay.countdown {
+                self.show_countdown(delay).await?;
+            }
+            tokio::time::sleep(delay).await;
+        }
+
+        self.starting.set(false);
+        self.goals.set_paused(*self.paused.borrow());
+
+        Ok(())
+    }
+
+    async fn show_countdown(&self, remaining: Duration) -> anyhow::Result<()> {
+        let seconds = remaining.as_secs();
+        let minutes = if seconds < 60 {
+            0
+        } else {
+            (seconds as f64 / 60.0).round() as u64
+        };
+
+        self.request_sender
+            .show_notification(Notification {
+                summary: Some(tr("lewdware-start-delay")),
+                body: tr_args(
+                    "lewdware-start-delay-body",
+                    [("minutes", minutes.into()), ("seconds", seconds.into())],
+                ),
+            })
+            .await?;
+
+        Ok(())
+    }
+
+    /// Scales the intensity multiplier by `factor` for `length`, replacing any burst from the same
+    /// source that's still going.
+    fn burst(&self, source: Burst, factor: f64, length: Duration) -> anyhow::Result<()> {
+        let id = self.intensity.start_burst(source, factor)?;
+
+        let intensity = self.intensity.clone();
+        tokio::task::spawn_local(async move {
+            tokio::time::sleep(length).await;
+            if let Err(err) = intensity.end_burst(source, id) {
+                tracing::error!("{err}");
+            }
+        });
+
+        Ok(())
+    }
+
+    /// The consequences the user has set for answering a prompt wrong (see [`PromptFailure`]).
+    fn on_prompt_failed(&self) -> anyhow::Result<()> {
+        let failure = &self.prompt_failure;
+
+        if failure.boost_percent != 100 {
+            self.burst(
+                Burst::PromptFailure,
+                failure.boost_percent as f64 / 100.0,
+                Duration::from_secs(failure.boost_seconds.into()),
+            )?;
+        }
+
+        if failure.extra_popups > 0 {
+            let n = failure.extra_popups as usize;
+            let lua = self.lua.clone();
+            let media_manager = self.media_manager.clone();
+            let request_sender = self.request_sender.clone();
+            let windows = self.windows.clone();
+            let goals = self.goals.clone();
+
+            // Spawned, since the prompt's window is still borrowed.
+            tokio::task::spawn_local(async move {
+                let images = match media_manager
+                    .random_media_n(MediaTypes::IMAGE, None, None, n)
+                    .await
+                {
+                    Ok(images) => images,
+                    Err(err) => {
+                        tracing::error!("{err}");
+                        return;
+                    }
+                };
+
+                for image in images {
+                    if let Err(err) = spawn_image_popup(
+                        lua.clone(),
+                        (image, None),
+                        media_manager.clone(),
+                        request_sender.clone(),
+                        windows.clone(),
+                        goals.clone(),
+                    )
+                    .await
+                    {
+                        tracing::error!("{err}");
+                    }
+                }
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Swaps a failed typing test's passage for a new one from the pack, or gives the same one
+    /// another go if there aren't any others.
+    fn restart_typing_test(&self, prompt: Rc<PromptWindow>) {
+        let media_manager = self.media_manager.clone();
+
+        tokio::task::spawn_local(async move {
+            let passage = match media_manager
+                .random_text(TextKind::Prompt, None, None)
+                .await
+            {
+                Ok(passage) => passage,
+                Err(err) => {
+                    tracing::error!("{err}");
+                    None
+                }
+            };
+
+            if let Err(err) = prompt.restart_typing_test(passage).await {
+                tracing::error!("{err}");
+            }
+        });
+    }
+
+    /// Moves the pacing (see [`Pacing`]) a step up or down, if it's on.
+    fn pace(&self, harder: bool) -> anyhow::Result<()> {
+        if !self.pacing.enabled {
+            return Ok(());
+        }
+
+        self.intensity
+            .set_pacing(self.pacing.step(self.intensity.pacing(), harder))
+    }
+
     async fn run_entrypoint(&self, entrypoint: String) -> mlua::Result<()> {
         self.mode
             .load(&self.lua, entrypoint)
@@ -319,8 +661,40 @@ impl LuaRuntime {
     async fn handle_event(&self, event: Event) -> anyhow::Result<()> {
         match event {
             Event::WindowClosed { id } => {
-                if let Some(window) = self.windows.try_borrow_mut()?.remove(&id) {
+                let window = self.windows.try_borrow_mut()?.remove(&id);
+
+                if let Some(window) = window {
                     window.inner_window().on_close()?;
+
+                    if let Window::Prompt(prompt) = &window
+                        && !prompt.submitted()?
+                    {
+                        self.prompt_history.record(
+                            prompt.text()?,
+                            prompt.answer()?.as_deref(),
+                            None,
+                        );
+                    }
+
+                    // The rest of a popup set goes with it.
+                    for linked_id in window.inner_window().linked()? {
+                        let Some(linked) = self.windows.try_borrow()?.get(&linked_id).cloned()
+                        else {
+                            continue;
+                        };
+
+                        tokio::task::spawn_local(async move {
+                            if let Err(err) = linked.inner_window().close().await {
+                                tracing::debug!("Couldn't close linked window: {err}");
+                            }
+                        });
+                    }
+                }
+            }
+            Event::PopupDismissed { open_for } => {
+                self.goals.record_closed_popup();
+                if self.pacing.is_quick_close(open_for) {
+                    self.pace(true)?;
                 }
             }
             Event::MoveFinish { id, move_id, x, y } => {
@@ -338,11 +712,47 @@ impl LuaRuntime {
                     audio.on_finish()?;
                 }
             }
-            Event::PromptSubmit { id, text } => {
+            Event::PromptSubmit {
+                id,
+                text,
+                timed_out,
+            } => {
                 if let Some(window) = self.windows.try_borrow()?.get(&id).cloned() {
                     match window {
                         Window::Prompt(prompt) => {
-                            prompt.on_submit(text)?;
+                            let prompt_text = prompt.text()?;
+                            let answer = prompt.answer()?;
+                            let typing_test = prompt.typing_test()?;
+                            let failed = match &typing_test {
+                                Some(typing_test) => {
+                                    timed_out
+                                        || PromptResponse::accuracy(
+                                            prompt_text.as_deref().unwrap_or_default(),
+                                            &text,
+                                        ) < typing_test.accuracy
+                                }
+                                None => answer.as_deref().or(prompt_text.as_deref()).is_some_and(
+                                    |answer| !PromptResponse::matches(Some(answer), &text),
+                                ),
+                            };
+                            // A failed typing test isn't accepted, so it isn't answered yet.
+                            let rejected = failed && typing_test.is_some();
+                            if !rejected {
+                                self.goals.record(GoalStat::Prompts);
+                            }
+                            if failed {
+                                self.goals.record_failed_prompt();
+                                self.on_prompt_failed()?;
+                                prompt.on_fail(text.clone())?;
+                            }
+                            self.pace(failed)?;
+                            self.prompt_history
+                                .record(prompt_text, answer.as_deref(), Some(&text));
+                            if rejected {
+                                self.restart_typing_test(prompt);
+                            } else {
+                                prompt.on_submit(text)?;
+                            }
                         }
                         _ => bail!("Video finish event for a non-video window"),
                     }
@@ -355,17 +765,158 @@ impl LuaRuntime {
                 if let Some(window) = self.windows.try_borrow()?.get(&id).cloned() {
                     match window {
                         Window::Choice(prompt) => {
+                            self.goals.record(GoalStat::Choices);
                             prompt.on_select(choice_id)?;
                         }
                         _ => bail!("Video finish event for a non-video window"),
                     }
                 }
             }
+            Event::MinigameHit { id, hits } => {
+                if let Some(window) = self.windows.try_borrow()?.get(&id).cloned() {
+                    match window {
+                        Window::Minigame(minigame) => minigame.on_hit(hits)?,
+                        _ => bail!("Minigame hit event for a non-minigame window"),
+                    }
+                }
+            }
+            Event::IntensityChange { level } => {
+                self.intensity.set_level(level)?;
+            }
+            Event::SetPaused { paused } => {
+                self.goals.set_paused(paused || self.starting.get());
+                self.paused.send_replace(paused);
+            }
+            Event::SetLoadLimits { rate, videos } => {
+                self.media_manager.set_videos_enabled(videos);
+                self.intensity.set_limit(rate)?;
+            }
+            Event::Wake { burst } => self.burst(Burst::Wake, burst, WAKE_BURST)?,
+            Event::BlockMedia { media_id } => {
+                let media_manager = self.media_manager.clone();
+
+                tokio::task::spawn_local(async move {
+                    if let Err(err) = media_manager.block_media(media_id).await {
+                        tracing::error!("Couldn't block media: {err}");
+                    }
+                });
+            }
+            Event::ToggleFavorite { media_id } => {
+                let media_manager = self.media_manager.clone();
+                let request_sender = self.request_sender.clone();
+
+                tokio::task::spawn_local(async move {
+                    let favorite = match media_manager.toggle_favorite(media_id).await {
+                        Ok(favorite) => favorite,
+                        Err(err) => {
+                            tracing::error!("Couldn't favorite media: {err}");
+                            return;
+                        }
+                    };
+
+                    // There's nothing else to show that the key press did anything.
+                    let notification = Notification {
+                        summary: None,
+                        body: tr(if favorite {
+                            "lewdware-favorite-added"
+                        } else {
+                            "lewdware-favorite-removed"
+                        }),
+                    };
+                    if let Err(err) = request_sender.show_notification(notification).await {
+                        tracing::error!("{err}");
+                    }
+                });
+            }
+            Event::ShowCredits => self.show_credits().await?,
+        }
+
+        Ok(())
+    }
+
+    /// Opens a plain window with the pack's credits. It isn't one of the mode's windows, so
+    /// nothing in Lua can move or close it.
+    async fn show_credits(&self) -> anyhow::Result<()> {
+        let style = TextStyle {
+            font_size: FontSize::Value(16.0),
+            align: TextAlign::Left,
+            ..Default::default()
+        };
+        let window_opts = SpawnWindowOpts {
+            title: Some(tr("lewdware-credits-title")),
+            transparent: Some(false),
+            background_color: Some(Color {
+                r: 1.0,
+                g: 1.0,
+                b: 1.0,
+                a: 1.0,
+            }),
+            ..Default::default()
+        };
+
+        self.request_sender
+            .spawn_text(self.credits.clone(), style, window_opts)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Lets the user know they've reached `goal`, and plays its moment: the mode's `on_goal`
+    /// callbacks, and the pack's popup set for it, if it has one.
+    async fn on_goal_reached(&self, goal: Goal) -> anyhow::Result<()> {
+        self.request_sender
+            .show_notification(Notification {
+                summary: Some(tr("lewdware-goal-reached")),
+                body: goal.name.clone(),
+            })
+            .await?;
+
+        let value = self.lua.to_value(&goal)?;
+        for cb in self.goals.callbacks()? {
+            let value = value.clone();
+            tokio::task::spawn_local(async move {
+                if let Err(err) = cb.call_async::<()>(value).await {
+                    tracing::error!("{err}");
+                }
+            });
+        }
+
+        if let Some(set) = goal.popup_set {
+            let media = self.media_manager.popup_set(set.clone()).await?;
+            if media.is_empty() {
+                tracing::warn!("Goal {:?} has an empty popup set {set:?}", goal.name);
+                return Ok(());
+            }
+
+            spawn_popup_set(
+                self.lua.clone(),
+                (media, None),
+                self.media_manager.clone(),
+                self.request_sender.clone(),
+                self.windows.clone(),
+                self.goals.clone(),
+            )
+            .await?;
         }
 
         Ok(())
     }
 
+    /// Lets the media and texts with the milestone's tags through from now on, and lets the user
+    /// know there's more to come.
+    async fn on_milestone_reached(&self, milestone: Milestone) -> anyhow::Result<()> {
+        self.media_manager.unlock_tags(milestone.unlocks).await?;
+
+        self.request_sender
+            .show_notification(Notification {
+                summary: Some(tr("lewdware-milestone-reached")),
+                body: milestone.name,
+            })
+            .await?;
+
+        Ok(())
+    }
+
     fn create_api(&mut self, config: HashMap<String, OptionValue>) -> mlua::Result<()> {
         create_api(
             &self.lua,
@@ -373,6 +924,9 @@ impl LuaRuntime {
             self.media_manager.clone(),
             self.windows.clone(),
             self.audio_handles.clone(),
+            self.intensity.clone(),
+            self.goals.clone(),
+            self.paused.subscribe(),
             config,
         )?;
 
@@ -1,4 +1,4 @@
-use std::error::Error;
+use std::{error::Error, time::Duration};
 
 use tokio::sync::{mpsc::Sender, oneshot};
 use winit::{event_loop::EventLoopProxy, window::WindowId};
@@ -15,6 +15,7 @@ use crate::{
     media::{FileOrPath, ImageData},
     monitor::Monitor,
     video::VideoDecoder,
+    window::{MinigameStyle, PopupMedia, PromptStyle},
 };
 
 #[derive(Clone)]
@@ -78,10 +79,12 @@ impl RequestSender {
 
     pub async fn spawn_image(
         &self,
+        media: PopupMedia,
         data: ImageData,
         window_opts: SpawnWindowOpts,
     ) -> Result<WindowProps> {
         self.send(|tx| LuaRequest::SpawnImage {
+            media,
             data,
             window_opts,
             tx,
@@ -91,11 +94,13 @@ impl RequestSender {
 
     pub async fn spawn_video(
         &self,
+        media: PopupMedia,
         video_player: VideoDecoder,
         loop_video: bool,
         window_opts: SpawnWindowOpts,
     ) -> Result<WindowProps> {
         self.send(|tx| LuaRequest::SpawnVideo {
+            media,
             video_player,
             loop_video,
             window_opts,
@@ -109,12 +114,14 @@ impl RequestSender {
         text: Option<String>,
         placeholder: Option<String>,
         initial_value: Option<String>,
+        style: PromptStyle,
         window_opts: SpawnWindowOpts,
     ) -> Result<WindowProps> {
         self.send(|tx| LuaRequest::SpawnPrompt {
             text,
             placeholder,
             initial_value,
+            style,
             window_opts,
             tx,
         })
@@ -136,6 +143,20 @@ impl RequestSender {
         .await?
     }
 
+    /// `None` if the user has turned minigames off.
+    pub async fn spawn_minigame(
+        &self,
+        style: MinigameStyle,
+        window_opts: SpawnWindowOpts,
+    ) -> Result<Option<WindowProps>> {
+        self.send(|tx| LuaRequest::SpawnMinigame {
+            style,
+            window_opts,
+            tx,
+        })
+        .await?
+    }
+
     pub async fn spawn_text(
         &self,
         text: String,
@@ -156,6 +177,11 @@ impl RequestSender {
             .await?
     }
     
+    pub async fn set_video_wallpaper(&self, video_players: Vec<(Monitor, VideoDecoder)>) -> Result<()> {
+        self.send(|tx| LuaRequest::SetVideoWallpaper { video_players, tx })
+            .await?
+    }
+
     pub async fn reset_wallpaper(&self) -> Result<()> {
         Ok(self.send(|tx| LuaRequest::ResetWallpaper { tx }).await?)
     }
@@ -281,6 +307,16 @@ impl WindowRequestSender {
             .flatten()
     }
 
+    pub async fn restart_typing_test(&self, text: String, time_limit: Duration) -> Result<()> {
+        self.send(|tx| WindowAction::RestartTypingTest {
+            tx,
+            text,
+            time_limit,
+        })
+        .await
+        .flatten()
+    }
+
     pub async fn set_options(&self, options: Vec<ChoiceWindowOption>) -> Result<()> {
         self.send(|tx| WindowAction::SetOptions { tx, options })
             .await
@@ -337,12 +373,15 @@ impl AudioRequestSender {
 }
 
 pub enum LuaRequest {
+    /// `media` is the file the popup shows, so the user can block it or find out where it's from.
     SpawnImage {
+        media: PopupMedia,
         data: ImageData,
         window_opts: SpawnWindowOpts,
         tx: oneshot::Sender<Result<WindowProps>>,
     },
     SpawnVideo {
+        media: PopupMedia,
         video_player: VideoDecoder,
         loop_video: bool,
         window_opts: SpawnWindowOpts,
@@ -352,6 +391,7 @@ pub enum LuaRequest {
         text: Option<String>,
         placeholder: Option<String>,
         initial_value: Option<String>,
+        style: PromptStyle,
         window_opts: SpawnWindowOpts,
         tx: oneshot::Sender<Result<WindowProps>>,
     },
@@ -367,6 +407,11 @@ pub enum LuaRequest {
         window_opts: SpawnWindowOpts,
         tx: oneshot::Sender<Result<WindowProps>>,
     },
+    SpawnMinigame {
+        style: MinigameStyle,
+        window_opts: SpawnWindowOpts,
+        tx: oneshot::Sender<Result<Option<WindowProps>>>,
+    },
     SpawnAudio {
         audio_player: AudioPlayer,
         tx: oneshot::Sender<u64>,
@@ -376,6 +421,11 @@ pub enum LuaRequest {
         mode: Option<WallpaperMode>,
         tx: oneshot::Sender<Result<()>>,
     },
+    /// Play a video behind all other windows, one decoder per monitor.
+    SetVideoWallpaper {
+        video_players: Vec<(Monitor, VideoDecoder)>,
+        tx: oneshot::Sender<Result<()>>,
+    },
     ResetWallpaper {
         tx: oneshot::Sender<()>,
     },
@@ -482,6 +532,11 @@ pub enum WindowAction {
         tx: oneshot::Sender<Result<()>>,
         value: Option<String>,
     },
+    RestartTypingTest {
+        tx: oneshot::Sender<Result<()>>,
+        text: String,
+        time_limit: Duration,
+    },
     SetOptions {
         tx: oneshot::Sender<Result<()>>,
         options: Vec<ChoiceWindowOption>,
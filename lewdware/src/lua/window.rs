@@ -1,13 +1,14 @@
-use std::{cell::RefCell, rc::Rc};
+use std::{cell::RefCell, rc::Rc, time::Duration};
 
 use mlua::{
-    ExternalResult, FromLua, IntoLua, LuaSerdeExt, SerializeOptions, UserData, UserDataFields,
+    ExternalResult, FromLua, IntoLua, Lua, LuaSerdeExt, SerializeOptions, UserData, UserDataFields,
     UserDataMethods,
 };
 use serde::{Deserialize, Serialize};
 use winit::window::WindowId;
 
 use crate::{
+    error::Result,
     lua::{
         Media, WindowProps,
         api::{Anchor, Coord},
@@ -23,6 +24,7 @@ pub enum Window {
     Prompt(Rc<PromptWindow>),
     Choice(Rc<ChoiceWindow>),
     Text(Rc<TextWindow>),
+    Minigame(Rc<MinigameWindow>),
 }
 
 impl Window {
@@ -33,6 +35,20 @@ impl Window {
             Window::Prompt(prompt) => &prompt.inner_window,
             Window::Choice(choice) => &choice.inner_window,
             Window::Text(text) => &text.inner_window,
+            Window::Minigame(minigame) => &minigame.inner_window,
+        }
+    }
+}
+
+impl IntoLua for Window {
+    fn into_lua(self, lua: &Lua) -> mlua::Result<mlua::Value> {
+        match self {
+            Window::Image(image) => image.into_lua(lua),
+            Window::Video(video) => video.into_lua(lua),
+            Window::Prompt(prompt) => prompt.into_lua(lua),
+            Window::Choice(choice) => choice.into_lua(lua),
+            Window::Text(text) => text.into_lua(lua),
+            Window::Minigame(minigame) => minigame.into_lua(lua),
         }
     }
 }
@@ -116,18 +132,55 @@ pub struct PromptWindow {
     state: RefCell<PromptWindowState>,
 }
 
+/// How long a typing test gives for each character of the passage, unless the mode sets a time
+/// limit. About 25 words a minute.
+const TYPING_TIME_PER_CHAR: Duration = Duration::from_millis(500);
+const MIN_TYPING_TIME: Duration = Duration::from_secs(10);
+
+/// What a typing test's passage has to be typed in (see `PromptKind::TypingTest`).
+#[derive(Debug, Clone, Copy)]
+pub struct TypingTest {
+    /// Set by the mode, or worked out from the length of each passage.
+    pub time_limit: Option<Duration>,
+    /// The least [`PromptResponse::accuracy`](shared::history::PromptResponse::accuracy) that's
+    /// accepted.
+    pub accuracy: f64,
+}
+
+impl TypingTest {
+    pub fn time_limit(&self, passage: &str) -> Duration {
+        self.time_limit.unwrap_or_else(|| {
+            (TYPING_TIME_PER_CHAR * passage.chars().count() as u32).max(MIN_TYPING_TIME)
+        })
+    }
+}
+
 struct PromptWindowState {
     text: Option<String>,
+    /// What has to be typed in, if it isn't `text`.
+    answer: Option<String>,
+    typing_test: Option<TypingTest>,
     value: String,
+    submitted: bool,
     submit_callbacks: Vec<mlua::Function>,
+    fail_callbacks: Vec<mlua::Function>,
 }
 
 impl PromptWindowState {
-    fn new(text: Option<String>, value: String) -> Self {
+    fn new(
+        text: Option<String>,
+        answer: Option<String>,
+        typing_test: Option<TypingTest>,
+        value: String,
+    ) -> Self {
         Self {
             text,
+            answer,
+            typing_test,
             value,
+            submitted: false,
             submit_callbacks: Vec::new(),
+            fail_callbacks: Vec::new(),
         }
     }
 }
@@ -159,6 +212,16 @@ impl UserData for PromptWindow {
             Ok(())
         });
 
+        methods.add_method("on_fail", |_, this, cb: mlua::Function| {
+            this.state
+                .try_borrow_mut()
+                .into_lua_err()?
+                .fail_callbacks
+                .push(cb);
+
+            Ok(())
+        });
+
         methods.add_async_method("set_text", async |_, this, text: Option<String>| {
             this.inner_window
                 .request_sender
@@ -187,18 +250,65 @@ impl PromptWindow {
     pub fn new(
         props: WindowProps,
         text: Option<String>,
+        answer: Option<String>,
+        typing_test: Option<TypingTest>,
         value: String,
         request_sender: WindowRequestSender,
     ) -> Self {
         Self {
             inner_window: InnerWindow::new(props, request_sender),
-            state: RefCell::new(PromptWindowState::new(text, value)),
+            state: RefCell::new(PromptWindowState::new(text, answer, typing_test, value)),
         }
     }
 
+    /// The text the prompt asks for.
+    pub fn text(&self) -> anyhow::Result<Option<String>> {
+        Ok(self.state.try_borrow()?.text.clone())
+    }
+
+    /// What has to be typed in, if it isn't [`Self::text`], e.g. the answer to a sum.
+    pub fn answer(&self) -> anyhow::Result<Option<String>> {
+        Ok(self.state.try_borrow()?.answer.clone())
+    }
+
+    pub fn typing_test(&self) -> anyhow::Result<Option<TypingTest>> {
+        Ok(self.state.try_borrow()?.typing_test)
+    }
+
+    /// Starts a typing test over with `passage`, or the same one again, with its time limit
+    /// reset and nothing typed in.
+    pub async fn restart_typing_test(&self, passage: Option<String>) -> anyhow::Result<()> {
+        let (passage, time_limit) = {
+            let state = self.state.try_borrow()?;
+            let Some(typing_test) = state.typing_test else {
+                return Ok(());
+            };
+            let passage = passage.or_else(|| state.text.clone()).unwrap_or_default();
+            let time_limit = typing_test.time_limit(&passage);
+            (passage, time_limit)
+        };
+
+        self.inner_window
+            .request_sender
+            .restart_typing_test(passage.clone(), time_limit)
+            .await?;
+
+        let mut state = self.state.try_borrow_mut()?;
+        state.text = Some(passage);
+        state.value.clear();
+
+        Ok(())
+    }
+
+    /// Whether the user has answered the prompt yet.
+    pub fn submitted(&self) -> anyhow::Result<bool> {
+        Ok(self.state.try_borrow()?.submitted)
+    }
+
     pub fn on_submit(&self, text: String) -> anyhow::Result<()> {
         let callbacks = {
-            let state = self.state.try_borrow()?;
+            let mut state = self.state.try_borrow_mut()?;
+            state.submitted = true;
             state.submit_callbacks.clone()
         };
 
@@ -214,6 +324,24 @@ impl PromptWindow {
 
         Ok(())
     }
+
+    /// Called as well as [`Self::on_submit`] when what was submitted isn't what the prompt asked
+    /// for, so modes can have their own consequences for it.
+    pub fn on_fail(&self, text: String) -> anyhow::Result<()> {
+        let callbacks = self.state.try_borrow()?.fail_callbacks.clone();
+
+        for cb in callbacks {
+            let text = text.clone();
+
+            tokio::task::spawn_local(async move {
+                if let Err(err) = cb.call_async::<()>(text).await {
+                    tracing::error!("{err}");
+                }
+            });
+        }
+
+        Ok(())
+    }
 }
 
 pub struct ChoiceWindow {
@@ -371,6 +499,75 @@ impl TextWindow {
     }
 }
 
+pub struct MinigameWindow {
+    inner_window: InnerWindow,
+    hits_needed: u32,
+    state: RefCell<MinigameWindowState>,
+}
+
+struct MinigameWindowState {
+    hits: u32,
+    hit_callbacks: Vec<mlua::Function>,
+}
+
+impl UserData for MinigameWindow {
+    fn add_fields<F: UserDataFields<Self>>(fields: &mut F) {
+        InnerWindow::add_fields(fields);
+
+        fields.add_field("type", "minigame");
+
+        fields.add_field_method_get("hits", |_, this| {
+            Ok(this.state.try_borrow().into_lua_err()?.hits)
+        });
+        fields.add_field_method_get("hits_needed", |_, this| Ok(this.hits_needed));
+    }
+
+    fn add_methods<M: UserDataMethods<Self>>(methods: &mut M) {
+        InnerWindow::add_methods(methods);
+
+        methods.add_method("on_hit", |_, this, cb: mlua::Function| {
+            this.state
+                .try_borrow_mut()
+                .into_lua_err()?
+                .hit_callbacks
+                .push(cb);
+
+            Ok(())
+        });
+    }
+}
+
+impl MinigameWindow {
+    pub fn new(props: WindowProps, hits_needed: u32, request_sender: WindowRequestSender) -> Self {
+        Self {
+            inner_window: InnerWindow::new(props, request_sender),
+            hits_needed,
+            state: RefCell::new(MinigameWindowState {
+                hits: 0,
+                hit_callbacks: Vec::new(),
+            }),
+        }
+    }
+
+    pub fn on_hit(&self, hits: u32) -> anyhow::Result<()> {
+        let callbacks = {
+            let mut state = self.state.try_borrow_mut()?;
+            state.hits = hits;
+            state.hit_callbacks.clone()
+        };
+
+        for cb in callbacks {
+            tokio::task::spawn_local(async move {
+                if let Err(err) = cb.call_async::<()>(hits).await {
+                    tracing::error!("{err}");
+                }
+            });
+        }
+
+        Ok(())
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct ChoiceWindowOption {
     pub id: String,
@@ -406,6 +603,8 @@ struct InnerWindowState {
     visible: bool,
     closed: bool,
     close_callbacks: Vec<mlua::Function>,
+    /// The other windows in this window's popup set, which are closed along with it.
+    linked: Vec<WindowId>,
     move_callback: Option<(u64, mlua::Function)>,
     current_move_id: u64,
     fade_callback: Option<(u64, mlua::Function)>,
@@ -446,6 +645,12 @@ impl HasInnerWindow for TextWindow {
     }
 }
 
+impl HasInnerWindow for MinigameWindow {
+    fn inner_window(&self) -> &InnerWindow {
+        &self.inner_window
+    }
+}
+
 impl InnerWindow {
     pub fn new(props: WindowProps, request_tx: WindowRequestSender) -> Self {
         Self {
@@ -495,9 +700,7 @@ impl InnerWindow {
 
     fn add_methods<T: HasInnerWindow + 'static, M: UserDataMethods<T>>(methods: &mut M) {
         methods.add_async_method("close", async |_, this, _: ()| {
-            let inner_window = this.inner_window();
-
-            inner_window.request_sender.close().await.into_lua_err()?;
+            this.inner_window().close().await.into_lua_err()?;
 
             Ok(())
         });
@@ -576,19 +779,7 @@ impl InnerWindow {
         );
 
         methods.add_async_method("set_visible", async |_, this, visible: bool| {
-            this.inner_window()
-                .request_sender
-                .set_visible(visible)
-                .await
-                .into_lua_err()?;
-
-            this.inner_window()
-                .state
-                .try_borrow_mut()
-                .into_lua_err()?
-                .visible = visible;
-
-            Ok(())
+            this.inner_window().set_visible(visible).await
         });
 
         methods.add_async_method("set_title", async |_, this, title: Option<String>| {
@@ -612,6 +803,41 @@ impl InnerWindow {
         });
     }
 
+    pub fn id(&self) -> WindowId {
+        self.id
+    }
+
+    pub fn closed(&self) -> bool {
+        self.state.try_borrow().is_ok_and(|state| state.closed)
+    }
+
+    pub async fn close(&self) -> Result<()> {
+        self.request_sender.close().await
+    }
+
+    pub async fn set_visible(&self, visible: bool) -> mlua::Result<()> {
+        self.request_sender
+            .set_visible(visible)
+            .await
+            .into_lua_err()?;
+        self.state.try_borrow_mut().into_lua_err()?.visible = visible;
+
+        Ok(())
+    }
+
+    /// Ties this window's lifetime to the other windows in `set`: once any of them is closed, the
+    /// rest are closed too.
+    pub fn link(&self, set: &[WindowId]) -> anyhow::Result<()> {
+        self.state.try_borrow_mut()?.linked =
+            set.iter().copied().filter(|id| *id != self.id).collect();
+
+        Ok(())
+    }
+
+    pub fn linked(&self) -> anyhow::Result<Vec<WindowId>> {
+        Ok(self.state.try_borrow()?.linked.clone())
+    }
+
     pub fn on_close(&self) -> anyhow::Result<()> {
         self.state.try_borrow_mut().into_lua_err()?.closed = true;
 
@@ -683,6 +909,7 @@ impl InnerWindowState {
             visible,
             closed: false,
             close_callbacks: Vec::new(),
+            linked: Vec::new(),
             move_callback: None,
             current_move_id: 0,
             fade_callback: None,
@@ -1,27 +1,60 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
-use std::{env::args_os, fs::File, path::PathBuf};
+use std::{
+    env::args_os,
+    fs::File,
+    path::PathBuf,
+    time::{Duration, SystemTime},
+};
 
 use anyhow::{Context, Result};
 use pollster::block_on;
-use shared::user_config::{Mode, load_config};
+use shared::{
+    footprint,
+    user_config::{Mode, load_config},
+    user_data,
+};
 use winit::event_loop::EventLoop;
 
 use crate::{
-    app::LewdwareApp,
-    utils::{create_tray_icon, handle_sigterm, spawn_panic_thread},
+    activity::spawn_activity_watcher,
+    app::{LewdwareApp, UserEvent},
+    audio_session::{restore_others, spawn_audio_ducker},
+    hibernation::spawn_hibernation_watcher,
+    media::AvifDecoder,
+    metrics::spawn_metrics_server,
+    power::spawn_power_watcher,
+    schedule::spawn_schedule_watcher,
+    screen_capture::spawn_capture_watcher,
+    session_lock::spawn_lock_watcher,
+    utils::{create_tray_icon, handle_sigterm, spawn_hotkey_thread},
     wgpu::WgpuState,
 };
 
+mod activity;
 mod app;
 mod audio;
+mod audio_session;
+mod credits;
+mod decode_helper;
 mod egui;
 mod error;
+mod governor;
+mod hibernation;
 mod inner_window;
 mod lua;
 mod media;
+mod metrics;
 mod monitor;
+mod pause;
+mod popup_set;
+mod power;
+mod rng;
+mod schedule;
+mod screen_capture;
+mod session_lock;
 mod text_font;
+mod toast;
 mod utils;
 mod video;
 mod wgpu;
@@ -29,12 +62,22 @@ mod window;
 mod zero_copy;
 
 fn main() -> Result<()> {
-    let _log_guard = shared::logging::init("lewdware");
+    // Started again by ourselves to decode a video, see `decode_helper`.
+    if args_os().nth(1).is_some_and(|arg| arg == decode_helper::HELPER_ARG) {
+        return decode_helper::run();
+    }
 
-    let lock_path = dirs::runtime_dir()
-        .unwrap_or_else(std::env::temp_dir)
-        .join("lewdware.lock");
-    let lock_file = File::create(&lock_path).context("Failed to create lock file")?;
+    // Loaded before logging starts, since an incognito session doesn't write a log file.
+    let mut config = load_config()?;
+    let log_guard = if config.incognito {
+        shared::logging::init_stderr_only();
+        None
+    } else {
+        Some(shared::logging::init("lewdware"))
+    };
+    let started = SystemTime::now();
+
+    let lock_file = File::create(footprint::lock_path()).context("Failed to create lock file")?;
     if lock_file.try_lock().is_err() {
         tracing::error!("Another instance of lewdware is already running");
         return Ok(());
@@ -51,6 +94,7 @@ fn main() -> Result<()> {
 
     let mut mode_path = None;
     let mut mode = None;
+    let mut seed = None;
     while let Some(arg) = args.next() {
         if &arg == "--mode-path" {
             mode_path = Some(PathBuf::from(args.next().context("No mode path provided")?));
@@ -65,9 +109,31 @@ fn main() -> Result<()> {
                     .to_string(),
             )
         }
+
+        if &arg == "--seed" {
+            seed = Some(
+                args.next()
+                    .context("No seed provided")?
+                    .to_str()
+                    .context("Invalid UTF-8")?
+                    .parse::<u64>()
+                    .context("Invalid seed")?,
+            )
+        }
     }
 
-    let mut config = load_config()?;
+    shared::locale::init(config.language.as_deref());
+
+    // The config app passes on the key to the user data (blocked files, favorites and stats), so
+    // a settings password only has to be typed there. Without it, the store is only readable if
+    // it has no password.
+    if let Ok(key) = std::env::var(user_data::KEY_VAR) {
+        match user_data::unlock_with_key(&key) {
+            Ok(true) => {}
+            Ok(false) => tracing::warn!("The user data key from the config app is out of date"),
+            Err(err) => tracing::warn!("Couldn't unlock the user data: {err}"),
+        }
+    }
 
     if let (Some(mode_path), Some(mode)) = (mode_path, mode) {
         config.mode = Mode::File {
@@ -76,8 +142,25 @@ fn main() -> Result<()> {
         };
     }
 
+    // Always log the seed, so a session that went wrong can be run again with `--seed`.
+    let seed = seed
+        .or(config.seed)
+        .or(config
+            .daily_shuffle
+            .then(|| rng::daily_seed(SystemTime::now())))
+        .unwrap_or_else(|| rand::random::<u32>().into());
+    config.seed = Some(seed);
+    tracing::info!("Session seed: {seed}");
+    rng::seed(seed, rng::Stream::Main);
+
     tracing::debug!("{:?}", config);
 
+    // Packs without AVIF images work either way, so a missing decoder is only reported once an
+    // AVIF image is actually loaded (see `MediaError::NoAvifDecoder`).
+    if AvifDecoder::detect() == Some(AvifDecoder::Ffmpeg) {
+        tracing::warn!("This build can't decode AVIF images itself, falling back to ffmpeg")
+    }
+
     let mut event_loop_builder = EventLoop::with_user_event();
 
     #[cfg(target_os = "linux")]
@@ -106,11 +189,66 @@ fn main() -> Result<()> {
 
     handle_sigterm(proxy.clone());
 
-    spawn_panic_thread(proxy.clone(), config.panic_button.clone());
+    let mut hotkeys = vec![(config.panic_button.clone(), UserEvent::Exit)];
+    if let Some(mute_button) = &config.mute_button {
+        hotkeys.push((mute_button.clone(), UserEvent::ToggleMute));
+    }
+    if let Some(pause_button) = &config.pause_button {
+        hotkeys.push((pause_button.clone(), UserEvent::TogglePause));
+    }
+    if let Some(block_button) = &config.block_button {
+        hotkeys.push((block_button.clone(), UserEvent::BlockMedia));
+    }
+    if let Some(favorite_button) = &config.favorite_button {
+        hotkeys.push((favorite_button.clone(), UserEvent::ToggleFavorite));
+    }
+    if let Some(info_button) = &config.info_button {
+        hotkeys.push((info_button.clone(), UserEvent::ToggleInfo));
+    }
+    spawn_hotkey_thread(
+        proxy.clone(),
+        hotkeys,
+        config.cursor_margin.is_some(),
+        config.pause_while_idle.is_some(),
+    );
+    spawn_lock_watcher(proxy.clone());
+    spawn_power_watcher(proxy.clone(), config.battery_saver.clone());
+    spawn_capture_watcher(proxy.clone(), config.pause_while_recording);
+    spawn_schedule_watcher(proxy.clone(), config.schedule.clone());
+    spawn_hibernation_watcher(proxy.clone(), config.hibernation.clone(), seed);
+    spawn_audio_ducker(config.duck_other_apps);
+    spawn_activity_watcher(
+        proxy.clone(),
+        config
+            .pause_while_idle
+            .map(|seconds| Duration::from_secs(seconds.into())),
+    );
     create_tray_icon(proxy.clone())?;
+    if let Some(port) = config.metrics_port
+        && let Err(err) = spawn_metrics_server(port)
+    {
+        tracing::error!("Couldn't serve metrics on port {port}: {err}");
+    }
 
+    let incognito = config.incognito;
     let mut app = LewdwareApp::new(wgpu_state, proxy, config)?;
-    event_loop.run_app(&mut app)?;
+    let result = event_loop.run_app(&mut app);
+    // The app's audio players aren't dropped until after this returns, which is too late for the
+    // ducking thread.
+    restore_others();
+    result?;
+
+    if incognito || app.wipe_on_exit() {
+        // Closes the wallpapers' temp files, the log file and the lock file, so they can be wiped
+        // as well.
+        drop(app);
+        drop(log_guard);
+        drop(lock_file);
+
+        let entries = footprint::footprint(Some(started));
+        let failed = footprint::wipe(&entries);
+        tracing::info!("Wiped {} files", entries.len() - failed.len());
+    }
 
     Ok(())
 }
@@ -0,0 +1,73 @@
+//! Sums for arithmetic prompts (see [`PromptKind::Arithmetic`]), kept small enough to do in your
+//! head while popups keep coming.
+//!
+//! [`PromptKind::Arithmetic`]: shared::texts::PromptKind::Arithmetic
+
+use rand::{RngExt, rngs::StdRng};
+
+/// A sum to show, like `7 × 8`, and what it comes to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Problem {
+    pub text: String,
+    pub answer: i64,
+}
+
+pub fn random_problem(rng: &mut StdRng) -> Problem {
+    match rng.random_range(0..3) {
+        0 => {
+            let a = rng.random_range(2..=50);
+            let b = rng.random_range(2..=50);
+            Problem {
+                text: format!("{a} + {b}"),
+                answer: a + b,
+            }
+        }
+        // Never below zero, so there's no minus sign to type.
+        1 => {
+            let a = rng.random_range(10..=99);
+            let b = rng.random_range(1..a);
+            Problem {
+                text: format!("{a} − {b}"),
+                answer: a - b,
+            }
+        }
+        _ => {
+            let a = rng.random_range(2..=12);
+            let b = rng.random_range(2..=12);
+            Problem {
+                text: format!("{a} × {b}"),
+                answer: a * b,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+
+    use super::*;
+
+    #[test]
+    fn answers_add_up() {
+        let mut rng = StdRng::seed_from_u64(1);
+
+        for _ in 0..100 {
+            let problem = random_problem(&mut rng);
+            let parts: Vec<_> = problem.text.split(' ').collect();
+            let [a, op, b] = parts[..] else {
+                panic!("Unexpected problem {:?}", problem.text);
+            };
+            let (a, b): (i64, i64) = (a.parse().unwrap(), b.parse().unwrap());
+
+            let answer = match op {
+                "+" => a + b,
+                "−" => a - b,
+                "×" => a * b,
+                _ => panic!("Unexpected operator {op:?}"),
+            };
+            assert_eq!(problem.answer, answer);
+            assert!(problem.answer > 0);
+        }
+    }
+}
@@ -0,0 +1,138 @@
+//! Most pack images are stored as AVIF, which some minimal builds of the `image` crate can't
+//! decode. When that happens we fall back to decoding them with ffmpeg, which we need for video
+//! anyway. ffmpeg is also the only way we have of decoding JPEG XL images.
+
+use std::sync::LazyLock;
+
+use anyhow::{Context, Result, bail};
+use ffmpeg_next::{
+    self as ffmpeg, codec,
+    format::Pixel,
+    frame::Video,
+    software::scaling::{self, Flags},
+};
+use image::ImageFormat;
+
+use crate::media::{ImageData, MediaSource};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AvifDecoder {
+    /// The `image` crate's own decoder.
+    Native,
+    /// ffmpeg's AV1 decoder. Slower, but works without the `image` crate's AVIF support.
+    Ffmpeg,
+}
+
+static DECODER: LazyLock<Option<AvifDecoder>> = LazyLock::new(|| {
+    if ImageFormat::Avif.reading_enabled() {
+        Some(AvifDecoder::Native)
+    } else if ffmpeg::init().is_ok() && ffmpeg::decoder::find(codec::Id::AV1).is_some() {
+        Some(AvifDecoder::Ffmpeg)
+    } else {
+        None
+    }
+});
+
+impl AvifDecoder {
+    /// The decoder to use for AVIF images in this build, or `None` if nothing can decode them.
+    pub fn detect() -> Option<Self> {
+        *DECODER
+    }
+}
+
+/// Decode the first frame of an image with ffmpeg, scaled to `width`x`height`.
+pub fn decode_with_ffmpeg(source: &MediaSource, width: u32, height: u32) -> Result<ImageData> {
+    ffmpeg::init()?;
+
+    let (color, alpha) = {
+        let ictx = source.open()?;
+        let color = ictx
+            .streams()
+            .best(ffmpeg::media::Type::Video)
+            .context("Couldn't find image stream")?
+            .index();
+        // An AVIF's alpha channel is a separate greyscale image, which ffmpeg exposes as a second
+        // video stream.
+        let alpha = ictx
+            .streams()
+            .find(|stream| {
+                stream.index() != color
+                    && stream.parameters().medium() == ffmpeg::media::Type::Video
+            })
+            .map(|stream| stream.index());
+        (color, alpha)
+    };
+
+    let mut data = scale(&first_frame(source, color)?, Pixel::RGBA, 4, width, height)?;
+    if let Some(alpha) = alpha {
+        let alpha = scale(&first_frame(source, alpha)?, Pixel::GRAY8, 1, width, height)?;
+        for (pixel, alpha) in data.chunks_exact_mut(4).zip(alpha) {
+            pixel[3] = alpha;
+        }
+    }
+
+    ImageData::from_raw(width, height, data).context("Decoded image has the wrong size")
+}
+
+fn first_frame(source: &MediaSource, stream_index: usize) -> Result<Video> {
+    let mut ictx = source.open()?;
+
+    let stream = ictx
+        .stream(stream_index)
+        .context("Couldn't find image stream")?;
+
+    let mut decoder = codec::Context::from_parameters(stream.parameters())?
+        .decoder()
+        .video()?;
+
+    let mut decoded = Video::empty();
+
+    for (stream, packet) in ictx.packets() {
+        if stream.index() == stream_index {
+            decoder.send_packet(&packet)?;
+            if decoder.receive_frame(&mut decoded).is_ok() {
+                return Ok(decoded);
+            }
+        }
+    }
+
+    decoder.send_eof()?;
+    if decoder.receive_frame(&mut decoded).is_ok() {
+        return Ok(decoded);
+    }
+
+    bail!("Image contained no frames")
+}
+
+/// Scale `frame` to `width`x`height` in a packed `format` with `pixel_size` bytes per pixel,
+/// returning its pixels.
+fn scale(
+    frame: &Video,
+    format: Pixel,
+    pixel_size: usize,
+    width: u32,
+    height: u32,
+) -> Result<Vec<u8>> {
+    let mut scaler = scaling::Context::get(
+        frame.format(),
+        frame.width(),
+        frame.height(),
+        format,
+        width,
+        height,
+        Flags::BILINEAR,
+    )?;
+
+    let mut scaled = Video::empty();
+    scaler.run(frame, &mut scaled)?;
+
+    // Rows in the frame can be padded, so copy them out one by one.
+    let stride = scaled.stride(0);
+    let row_length = width as usize * pixel_size;
+    let mut data = Vec::with_capacity(row_length * height as usize);
+    for row in scaled.data(0).chunks(stride).take(height as usize) {
+        data.extend_from_slice(&row[..row_length]);
+    }
+
+    Ok(data)
+}
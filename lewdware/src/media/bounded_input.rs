@@ -10,20 +10,23 @@
 //! to reach the moov atom) and bounded to the given window instead of the whole file.
 
 use std::{
-    ffi::c_void,
-    fs::File,
-    io, mem,
+    ffi::{CString, c_void},
+    mem,
     ops::{Deref, DerefMut},
     os::raw::c_int,
-    path::Path,
     ptr,
 };
 
 use ffmpeg_next::{self as ffmpeg, Error as FfmpegError, ffi};
 
+use shared::{
+    encode::VideoContainer,
+    location::{PackLocation, PackReader},
+};
+
 /// Size of the buffer ffmpeg reads through. Larger than the 4096 bytes ffmpeg's own example
-/// uses since we're backed by real file I/O (one `pread` per underfilled buffer) rather than a
-/// plain memory copy.
+/// uses since we're backed by real file I/O (one `pread` per underfilled buffer, or an HTTP
+/// request now and then, see [`PackReader`]) rather than a plain memory copy.
 const AVIO_BUFFER_SIZE: usize = 32 * 1024;
 
 /// An `ffmpeg::format::context::Input` opened against a bounded region of a file. Derefs to
@@ -35,7 +38,7 @@ pub struct BoundedInput {
 }
 
 // Safety: mirrors `ffmpeg_next::format::context::Input`'s own `unsafe impl Send` (the underlying
-// AVFormatContext has no thread affinity), plus `BoundedFile`'s `File` and offsets, which are
+// AVFormatContext has no thread affinity), plus `BoundedFile`'s reader and offsets, which are
 // likewise `Send`. `BoundedInput` is only ever accessed from one thread at a time.
 unsafe impl Send for BoundedInput {}
 
@@ -74,29 +77,17 @@ impl Drop for BoundedInput {
 }
 
 /// Per-open state backing the read/seek callbacks. One instance per `BoundedInput`; each opens
-/// its own `File` handle, so no locking/sharing is needed even when several bounded inputs read
+/// its own reader, so no locking/sharing is needed even when several bounded inputs read
 /// the same pack file concurrently (e.g. a video's own stream plus its separately-decoded audio
 /// track).
 struct BoundedFile {
-    file: File,
+    reader: PackReader,
     offset: u64,
     length: u64,
     /// Current position, relative to `offset`, in `[0, length]`.
     pos: u64,
 }
 
-#[cfg(unix)]
-fn pread(file: &File, buf: &mut [u8], offset: u64) -> io::Result<usize> {
-    use std::os::unix::fs::FileExt;
-    file.read_at(buf, offset)
-}
-
-#[cfg(windows)]
-fn pread(file: &File, buf: &mut [u8], offset: u64) -> io::Result<usize> {
-    use std::os::windows::fs::FileExt;
-    file.seek_read(buf, offset)
-}
-
 unsafe extern "C" fn read_packet(opaque: *mut c_void, buf: *mut u8, buf_size: c_int) -> c_int {
     let state = unsafe { &mut *opaque.cast::<BoundedFile>() };
 
@@ -116,7 +107,7 @@ unsafe extern "C" fn read_packet(opaque: *mut c_void, buf: *mut u8, buf_size: c_
     // `buf_size`.
     let dest = unsafe { std::slice::from_raw_parts_mut(buf, to_read) };
 
-    match pread(&state.file, dest, state.offset + state.pos) {
+    match state.reader.read_at(dest, state.offset + state.pos) {
         Ok(0) => c_int::from(FfmpegError::Eof),
         Ok(n) => {
             state.pos += n as u64;
@@ -156,12 +147,18 @@ unsafe extern "C" fn seek(opaque: *mut c_void, offset: i64, whence: c_int) -> i6
     new_pos
 }
 
-/// Opens `path`, exposing only the bytes in `[offset, offset + length)` to ffmpeg as if it were
-/// the whole file.
-pub fn open_bounded(path: &Path, offset: u64, length: u64) -> anyhow::Result<BoundedInput> {
-    let file = File::open(path)?;
+/// Opens the pack at `location`, exposing only the bytes in `[offset, offset + length)` to ffmpeg
+/// as if it were the whole file. With a `container`, that demuxer is used rather than probing for
+/// one.
+pub fn open_bounded(
+    location: &PackLocation,
+    offset: u64,
+    length: u64,
+    container: Option<VideoContainer>,
+) -> anyhow::Result<BoundedInput> {
+    let reader = location.open()?;
     let opaque = Box::into_raw(Box::new(BoundedFile {
-        file,
+        reader,
         offset,
         length,
         pos: 0,
@@ -207,8 +204,20 @@ pub fn open_bounded(path: &Path, offset: u64, length: u64) -> anyhow::Result<Bou
         (*fmt_ctx).pb = avio_ctx;
         (*fmt_ctx).flags |= ffi::AVFMT_FLAG_CUSTOM_IO;
 
-        let ret =
-            ffi::avformat_open_input(&mut fmt_ctx, ptr::null(), ptr::null_mut(), ptr::null_mut());
+        let input_format = match container {
+            Some(container) => {
+                let name = CString::new(container.ffmpeg_format()).unwrap();
+                ffi::av_find_input_format(name.as_ptr())
+            }
+            None => ptr::null(),
+        };
+
+        let ret = ffi::avformat_open_input(
+            &mut fmt_ctx,
+            ptr::null(),
+            input_format as _,
+            ptr::null_mut(),
+        );
         if ret < 0 {
             // On failure ffmpeg frees the AVFormatContext we supplied (and sets fmt_ctx to
             // NULL), but leaves `pb` alone because of AVFMT_FLAG_CUSTOM_IO, so avio_ctx is still
@@ -281,7 +290,8 @@ mod tests {
         ffmpeg::init().unwrap();
 
         let (padded, offset, length) = write_padded_clip();
-        let bounded = open_bounded(padded.path(), offset, length).unwrap();
+        let location = PackLocation::File(padded.path().to_path_buf());
+        let bounded = open_bounded(&location, offset, length, None).unwrap();
 
         let mut direct_file = tempfile::NamedTempFile::new().unwrap();
         direct_file.write_all(TEST_CLIP).unwrap();
@@ -302,7 +312,9 @@ mod tests {
         ffmpeg::init().unwrap();
 
         let (padded, offset, length) = write_padded_clip();
-        let mut bounded = open_bounded(padded.path(), offset, length).unwrap();
+        let location = PackLocation::File(padded.path().to_path_buf());
+        let mut bounded =
+            open_bounded(&location, offset, length, Some(VideoContainer::Mp4)).unwrap();
 
         let video_stream_index = bounded
             .streams()
@@ -355,7 +367,7 @@ mod tests {
         file.flush().unwrap();
 
         let opaque = Box::into_raw(Box::new(BoundedFile {
-            file: file.reopen().unwrap(),
+            reader: PackReader::File(file.reopen().unwrap()),
             offset: 0,
             length: 11,
             pos: 5,
@@ -4,7 +4,7 @@ use std::path::Path;
 
 use anyhow::Result;
 use image::ImageReader;
-use rand::{random_range, rng, seq::IndexedRandom};
+use rand::{RngExt, seq::IndexedRandom};
 use walkdir::WalkDir;
 
 use ffmpeg_next as ffmpeg;
@@ -12,6 +12,7 @@ use ffmpeg_next as ffmpeg;
 use crate::media::process::{Audio, Media, MediaType, Processed, process_path};
 use crate::media::types::ImageData;
 use crate::media::{self, Image, types::FileOrPath};
+use crate::rng;
 
 pub struct MediaDir {
     media: Vec<Media>,
@@ -65,7 +66,7 @@ impl MediaDir {
     }
 
     pub fn get_random_image(&self) -> Result<Option<Image>> {
-        let index = random_range(0..self.image_count);
+        let index = rng::with_rng(|rng| rng.random_range(0..self.image_count));
 
         let (path, width, height, transparent) = match self
             .media
@@ -115,7 +116,7 @@ impl MediaDir {
     // }
 
     pub fn get_random_audio(&self) -> Result<Option<media::Audio>> {
-        let item = match self.audio.choose(&mut rng()) {
+        let item = match rng::with_rng(|rng| self.audio.choose(rng)) {
             Some(x) => x,
             None => return Ok(None),
         };
@@ -0,0 +1,131 @@
+//! Images stay in memory once they've been decoded, in case the same image pops up again at the
+//! same size (small packs show the same few images over and over), up to a budget in bytes. The
+//! least recently used are thrown away first once it's reached, so a long session's memory use
+//! stays put.
+
+use std::collections::VecDeque;
+
+use crate::{media::ImageData, metrics::METRICS};
+
+/// An image as it was decoded: which pack and file it came from (the same id can be in both the
+/// pack and one blended into it), and the size it was decoded at.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImageKey {
+    pub pack: String,
+    pub id: u64,
+    pub width: u32,
+    pub height: u32,
+}
+
+pub struct ImageCache {
+    limit: u64,
+    used: u64,
+    /// Least recently used first.
+    images: VecDeque<(ImageKey, ImageData)>,
+}
+
+impl ImageCache {
+    /// Keeps up to `limit` bytes of decoded images. 0 keeps none.
+    pub fn new(limit: u64) -> Self {
+        Self {
+            limit,
+            used: 0,
+            images: VecDeque::new(),
+        }
+    }
+
+    /// A copy of the image, if it's here. Copying it is still far quicker than decoding it again.
+    pub fn get(&mut self, key: &ImageKey) -> Option<ImageData> {
+        let index = self.images.iter().position(|(other, _)| other == key)?;
+        let entry = self.images.remove(index)?;
+        let image = entry.1.clone();
+        self.images.push_back(entry);
+
+        METRICS.image_cache_hit();
+        Some(image)
+    }
+
+    /// Keeps a copy of the image, making room for it if it has to. One bigger than the whole
+    /// budget isn't kept at all.
+    pub fn insert(&mut self, key: ImageKey, image: &ImageData) {
+        let size = image.as_raw().len() as u64;
+        if size > self.limit || self.images.iter().any(|(other, _)| *other == key) {
+            return;
+        }
+
+        while self.used + size > self.limit {
+            let Some((_, evicted)) = self.images.pop_front() else {
+                break;
+            };
+            self.used -= evicted.as_raw().len() as u64;
+            METRICS.image_cache_evicted();
+        }
+
+        self.images.push_back((key, image.clone()));
+        self.used += size;
+        METRICS.set_image_cache_bytes(self.used);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(id: u64) -> ImageKey {
+        ImageKey {
+            pack: "pack".to_string(),
+            id,
+            width: 10,
+            height: 10,
+        }
+    }
+
+    /// A 10x10 image, 400 bytes decoded.
+    fn image(value: u8) -> ImageData {
+        ImageData::from_pixel(10, 10, image::Rgba([value; 4]))
+    }
+
+    #[test]
+    fn evicts_least_recently_used_first() {
+        let mut cache = ImageCache::new(1000);
+        cache.insert(key(1), &image(1));
+        cache.insert(key(2), &image(2));
+        assert!(cache.get(&key(1)).is_some());
+
+        // Only two fit, so the one used longest ago makes room.
+        cache.insert(key(3), &image(3));
+        assert_eq!(cache.get(&key(1)), Some(image(1)));
+        assert_eq!(cache.get(&key(2)), None);
+        assert_eq!(cache.get(&key(3)), Some(image(3)));
+        assert_eq!(cache.used, 800);
+    }
+
+    #[test]
+    fn keys_include_the_pack_and_size() {
+        let mut cache = ImageCache::new(1000);
+        cache.insert(key(1), &image(1));
+
+        let other_size = ImageKey {
+            width: 20,
+            ..key(1)
+        };
+        let other_pack = ImageKey {
+            pack: "blended".to_string(),
+            ..key(1)
+        };
+        assert_eq!(cache.get(&other_size), None);
+        assert_eq!(cache.get(&other_pack), None);
+    }
+
+    #[test]
+    fn keeps_nothing_over_the_limit() {
+        let mut cache = ImageCache::new(300);
+        cache.insert(key(1), &image(1));
+        assert_eq!(cache.get(&key(1)), None);
+        assert_eq!(cache.used, 0);
+
+        let mut cache = ImageCache::new(0);
+        cache.insert(key(1), &image(1));
+        assert_eq!(cache.get(&key(1)), None);
+    }
+}
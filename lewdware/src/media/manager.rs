@@ -1,11 +1,28 @@
 use crate::app::UserEvent;
-use shared::read_pack::Metadata;
-use std::{error::Error, fmt::Display, io, path::Path, rc::Rc, sync::Arc, thread};
+use rand::RngExt;
+use shared::{
+    locale::tr,
+    read_pack::Metadata,
+    texts::TextKind,
+    user_config::{self, BlendPack, FavoritesMode, TagMatchMode},
+};
+use std::{
+    error::Error,
+    fmt::Display,
+    io,
+    path::Path,
+    rc::Rc,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, Ordering},
+    },
+    thread,
+};
 use winit::event_loop::EventLoopProxy;
 
 use tokio::{
     sync::{
-        mpsc::{Sender, channel},
+        mpsc::{Sender, channel, error::TrySendError},
         oneshot,
     },
     task::LocalSet,
@@ -13,98 +30,507 @@ use tokio::{
 
 use crate::{
     audio::AudioPlayer,
+    decode_helper::Isolation,
     error::LewdwareError,
-    lua::{Media, MediaType},
-    media::{FileOrPath, pack::MediaPack, types::ImageData},
+    lua::{Media, MediaType, Prompt},
+    media::{FileOrPath, image_cache::ImageCache, pack::MediaPack, types::ImageData},
+    rng,
     video::VideoDecoder,
 };
 
 /// Manages all the media (images, audio, videos). Trivially clonable.
+///
+/// Requests go through one of two queues: decoding popup media can take a while, so everything
+/// else (queries, wallpapers, audio) is sent on a separate priority queue that the media thread
+/// always drains first. See [`MediaRequest::is_priority`].
+///
+/// A second pack can be blended in (see [`BlendPack`]), with a media thread of its own. Random
+/// picks come from one pack or the other, and requests for a file go to the pack it came from.
+///
+/// Nothing here writes to the pack (see [`MediaPack`]); the only files created are temp copies
+/// of videos, in the temp dir.
 #[derive(Clone)]
 pub struct MediaManager {
-    tx: Sender<MediaRequest>,
+    queues: Queues,
+    blend: Option<Blend>,
     wgpu_device: Option<Arc<wgpu::Device>>,
+    // Cleared by the battery saver and the governor, so random queries stop returning videos.
+    videos_enabled: Arc<AtomicBool>,
+    max_video_fps: Option<u32>,
+    isolate_video_decoding: bool,
+    /// What queries get when they don't say how to match their tags.
+    tag_match_mode: TagMatchMode,
 }
 
 pub type Result<T, E = MediaError> = std::result::Result<T, E>;
 
+/// The request queues of a pack's media thread.
+#[derive(Clone)]
+struct Queues {
+    tx: Sender<MediaRequest>,
+    priority_tx: Sender<MediaRequest>,
+}
+
+/// The blended-in pack, and the share of random picks taken from it, from 0 to 1.
+#[derive(Clone)]
+struct Blend {
+    queues: Queues,
+    ratio: f64,
+}
+
+/// Set in the ids of media from the blended-in pack, so requests for it can be sent there. Ids
+/// are row ids in the pack's index, which never get anywhere near it.
+const BLENDED_ID: u64 = 1 << 48;
+
 impl MediaManager {
-    /// Start up the media manager thread, opening the specified pack file. Returns the pack
-    /// metadata and a handle for the spawned thread.
+    /// Start up the media manager thread, opening the specified pack file, and another for
+    /// `blend_pack` if it's set. Returns the (first) pack's metadata and handles for the spawned
+    /// threads.
     ///
-    /// The returned `JoinHandle` should be joined once every clone of this `MediaManager` has
-    /// been dropped, so the thread's request channel closes and it can shut down, running the
-    /// `Drop` impl of its `MediaPack` (which owns a `NamedTempFile` for the pack's extracted
+    /// The returned `JoinHandle`s should be joined once every clone of this `MediaManager` has
+    /// been dropped, so the threads' request channels close and they can shut down, running the
+    /// `Drop` impl of their `MediaPack` (which owns a `NamedTempFile` for the pack's extracted
     /// index). Otherwise that temp file is never cleaned up.
     pub fn open(
         pack_path: &Path,
+        blend_pack: Option<&BlendPack>,
+        verify_media: bool,
+        max_rating: Option<u32>,
+        excluded_tags: Vec<String>,
+        tag_match_mode: TagMatchMode,
+        favorites_mode: FavoritesMode,
+        language: Option<String>,
+        seed: u64,
+        max_video_fps: Option<u32>,
+        isolate_video_decoding: bool,
+        image_cache_bytes: u64,
         event_loop_proxy: EventLoopProxy<UserEvent>,
         wgpu_device: Option<Arc<wgpu::Device>>,
-    ) -> anyhow::Result<(Self, Metadata, thread::JoinHandle<()>)> {
-        let (tx, metadata, handle) = spawn_media_manager_thread(pack_path, event_loop_proxy)?;
+    ) -> anyhow::Result<(Self, Metadata, Vec<thread::JoinHandle<()>>)> {
+        // One budget for both packs, rather than one each.
+        let image_cache = Arc::new(Mutex::new(ImageCache::new(image_cache_bytes)));
+
+        let (queues, metadata, handle) = spawn_media_manager_thread(
+            pack_path,
+            false,
+            verify_media,
+            max_rating,
+            excluded_tags.clone(),
+            favorites_mode,
+            language.clone(),
+            seed,
+            image_cache.clone(),
+            event_loop_proxy.clone(),
+        )?;
+        let mut handles = vec![handle];
+
+        // The session can go on without the blended-in pack, so failing to open it isn't fatal.
+        let blend = blend_pack.and_then(|blend_pack| {
+            match spawn_media_manager_thread(
+                &blend_pack.path,
+                true,
+                verify_media,
+                max_rating,
+                excluded_tags,
+                favorites_mode,
+                language,
+                seed,
+                image_cache,
+                event_loop_proxy,
+            ) {
+                Ok((queues, _, handle)) => {
+                    handles.push(handle);
+                    Some(Blend {
+                        queues,
+                        ratio: f64::from(blend_pack.percent.min(100)) / 100.0,
+                    })
+                }
+                Err(err) => {
+                    tracing::error!(
+                        "Couldn't open {} to blend in: {err}",
+                        blend_pack.path.display()
+                    );
+                    None
+                }
+            }
+        });
 
-        Ok((Self { tx, wgpu_device }, metadata, handle))
+        Ok((
+            Self {
+                queues,
+                blend,
+                wgpu_device,
+                videos_enabled: Arc::new(AtomicBool::new(true)),
+                max_video_fps,
+                isolate_video_decoding,
+                tag_match_mode,
+            },
+            metadata,
+            handles,
+        ))
     }
 
     async fn send<T>(
         &self,
         request_builder: impl FnOnce(oneshot::Sender<T>) -> MediaRequest,
+    ) -> Result<T> {
+        self.send_to(&self.queues, request_builder).await
+    }
+
+    async fn send_to<T>(
+        &self,
+        queues: &Queues,
+        request_builder: impl FnOnce(oneshot::Sender<T>) -> MediaRequest,
     ) -> Result<T> {
         let (tx, rx) = oneshot::channel();
 
-        if let Err(_) = self.tx.send(request_builder(tx)).await {
-            return Err(MediaError::Internal(
-                "The media manager receiver was dropped",
-            ));
+        let request = request_builder(tx);
+        let queue = if request.is_priority() {
+            &queues.priority_tx
+        } else {
+            &queues.tx
+        };
+
+        // Try a non-blocking send first so that a full queue shows up in the logs; otherwise a
+        // burst of requests just quietly stalls the Lua tasks making them. We still fall back to
+        // waiting for space rather than dropping the request.
+        match queue.try_send(request) {
+            Ok(()) => {}
+            Err(TrySendError::Full(request)) => {
+                tracing::warn!(
+                    "Media request queue is full ({} requests); waiting for space",
+                    queue.max_capacity()
+                );
+
+                if queue.send(request).await.is_err() {
+                    return Err(MediaError::Internal(
+                        "The media manager receiver was dropped",
+                    ));
+                }
+            }
+            Err(TrySendError::Closed(_)) => {
+                return Err(MediaError::Internal(
+                    "The media manager receiver was dropped",
+                ));
+            }
         }
 
         rx.await
             .map_err(|_| MediaError::Internal("The response sender was dropped"))
     }
 
+    /// The packs to make a random pick from, in the order to try them, and whether each is the
+    /// blended-in one. The blended-in pack comes first for its share of picks, and either way the
+    /// other is tried if the first has nothing matching.
+    fn pick_order(&self) -> Vec<(&Queues, bool)> {
+        let Some(blend) = &self.blend else {
+            return vec![(&self.queues, false)];
+        };
+
+        if rng::with_rng(|rng| rng.random::<f64>()) < blend.ratio {
+            vec![(&blend.queues, true), (&self.queues, false)]
+        } else {
+            vec![(&self.queues, false), (&blend.queues, true)]
+        }
+    }
+
+    /// The pack a request for the media with `id` should go to, and its id there.
+    fn route(&self, id: u64) -> (&Queues, u64) {
+        match &self.blend {
+            Some(blend) if id & BLENDED_ID != 0 => (&blend.queues, id & !BLENDED_ID),
+            _ => (&self.queues, id),
+        }
+    }
+
     // async fn send(&self, request: MediaRequest) {
     //     if let Err(_) = self.tx.send(request).await {
     //         tracing::error!("Media request channel closed");
     //     }
     // }
 
+    /// Stops (or restarts) random queries from returning videos. Media fetched by name or
+    /// listed is unaffected.
+    pub fn set_videos_enabled(&self, enabled: bool) {
+        self.videos_enabled.store(enabled, Ordering::Relaxed);
+    }
+
+    /// While videos are disabled, queries that could return a video return an image instead.
+    /// Queries for only videos (e.g. `random_video()`) return nothing, since the mode is going to
+    /// treat the result as a video.
+    fn random_types(&self, types: MediaTypes) -> MediaTypes {
+        if !types.video || self.videos_enabled.load(Ordering::Relaxed) {
+            return types;
+        }
+
+        if types == MediaTypes::VIDEO {
+            MediaTypes::NONE
+        } else {
+            MediaTypes {
+                image: true,
+                video: false,
+                audio: types.audio,
+            }
+        }
+    }
+
+    /// The file called `name`, looked for in the blended-in pack too if the first doesn't have
+    /// it.
     pub async fn get_media(&self, name: String, types: MediaTypes) -> Result<Option<Media>> {
-        self.send(|tx| MediaRequest::GetMedia {
-            types,
-            name,
-            response_tx: tx,
-        })
-        .await?
+        let main = [(&self.queues, false)];
+        let blend = self.blend.iter().map(|blend| (&blend.queues, true));
+
+        for (queues, blended) in main.into_iter().chain(blend) {
+            let media = self
+                .send_to(queues, |tx| MediaRequest::GetMedia {
+                    types: types.clone(),
+                    name: name.clone(),
+                    response_tx: tx,
+                })
+                .await??;
+            if let Some(media) = media {
+                return Ok(Some(mark_blended(media, blended)));
+            }
+        }
+
+        Ok(None)
     }
 
     pub async fn random_media(
         &self,
         types: MediaTypes,
         tags: Option<Vec<String>>,
+        tag_match: Option<TagMatchMode>,
     ) -> Result<Option<Media>> {
-        self.send(|tx| MediaRequest::RandomMedia {
-            types,
-            tags,
-            response_tx: tx,
-        })
-        .await?
+        let tag_match = tag_match.unwrap_or(self.tag_match_mode);
+        let types = self.random_types(types);
+        for (queues, blended) in self.pick_order() {
+            let media = self
+                .send_to(queues, |tx| MediaRequest::RandomMedia {
+                    types: types.clone(),
+                    tags: tags.clone(),
+                    tag_match,
+                    response_tx: tx,
+                })
+                .await??;
+            if let Some(media) = media {
+                return Ok(Some(mark_blended(media, blended)));
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Fetch up to `n` distinct random media in one request, rather than sending `n` separate
+    /// `random_media` requests through the (bounded) request channel.
+    pub async fn random_media_n(
+        &self,
+        types: MediaTypes,
+        tags: Option<Vec<String>>,
+        tag_match: Option<TagMatchMode>,
+        n: usize,
+    ) -> Result<Vec<Media>> {
+        let tag_match = tag_match.unwrap_or(self.tag_match_mode);
+        let types = self.random_types(types);
+
+        // Each of the `n` picks is made from one pack or the other. The blended-in pack is asked
+        // for its share first, and the first pack makes up the rest, including any the blended-in
+        // one was short of.
+        let blended_share = match &self.blend {
+            Some(blend) => {
+                rng::with_rng(|rng| (0..n).filter(|_| rng.random::<f64>() < blend.ratio).count())
+            }
+            None => 0,
+        };
+        let blend = self.blend.iter().map(|blend| (&blend.queues, true));
+        let main = [(&self.queues, false)];
+
+        let mut picked = Vec::new();
+        for (queues, blended) in blend.chain(main) {
+            let wanted = if blended {
+                blended_share
+            } else {
+                n - picked.len()
+            };
+            if wanted == 0 {
+                continue;
+            }
+
+            let media = self
+                .send_to(queues, |tx| MediaRequest::RandomMediaN {
+                    types: types.clone(),
+                    tags: tags.clone(),
+                    tag_match,
+                    n: wanted,
+                    response_tx: tx,
+                })
+                .await??;
+            picked.extend(media.into_iter().map(|media| mark_blended(media, blended)));
+        }
+
+        Ok(picked)
+    }
+
+    /// Every file in a random popup set. See [`MediaPack::random_set`].
+    pub async fn random_set(
+        &self,
+        types: MediaTypes,
+        tags: Option<Vec<String>>,
+        tag_match: Option<TagMatchMode>,
+    ) -> Result<Vec<Media>> {
+        let tag_match = tag_match.unwrap_or(self.tag_match_mode);
+        let types = self.random_types(types);
+        for (queues, blended) in self.pick_order() {
+            let set = self
+                .send_to(queues, |tx| MediaRequest::RandomSet {
+                    types: types.clone(),
+                    tags: tags.clone(),
+                    tag_match,
+                    response_tx: tx,
+                })
+                .await??;
+            if !set.is_empty() {
+                return Ok(set
+                    .into_iter()
+                    .map(|media| mark_blended(media, blended))
+                    .collect());
+            }
+        }
+
+        Ok(Vec::new())
+    }
+
+    /// A random toast. See [`MediaPack::random_toast`].
+    pub async fn random_toast(
+        &self,
+        tags: Option<Vec<String>>,
+        tag_match: Option<TagMatchMode>,
+    ) -> Result<Option<Media>> {
+        let tag_match = tag_match.unwrap_or(self.tag_match_mode);
+        for (queues, blended) in self.pick_order() {
+            let media = self
+                .send_to(queues, |tx| MediaRequest::RandomToast {
+                    tags: tags.clone(),
+                    tag_match,
+                    response_tx: tx,
+                })
+                .await??;
+            if let Some(media) = media {
+                return Ok(Some(mark_blended(media, blended)));
+            }
+        }
+
+        Ok(None)
     }
 
+    /// Every image and video in the popup set called `set`, looked for in the blended-in pack
+    /// too if the first doesn't have it. See [`MediaPack::popup_set`].
+    pub async fn popup_set(&self, set: String) -> Result<Vec<Media>> {
+        let types = self.random_types(MediaTypes {
+            image: true,
+            video: true,
+            audio: false,
+        });
+        let main = [(&self.queues, false)];
+        let blend = self.blend.iter().map(|blend| (&blend.queues, true));
+
+        for (queues, blended) in main.into_iter().chain(blend) {
+            let media = self
+                .send_to(queues, |tx| MediaRequest::PopupSet {
+                    set: set.clone(),
+                    types: types.clone(),
+                    response_tx: tx,
+                })
+                .await??;
+            if !media.is_empty() {
+                return Ok(media
+                    .into_iter()
+                    .map(|media| mark_blended(media, blended))
+                    .collect());
+            }
+        }
+
+        Ok(Vec::new())
+    }
+
+    /// Every matching file, from both packs if one is blended in.
     pub async fn list_media(
         &self,
         types: MediaTypes,
         tags: Option<Vec<String>>,
+        tag_match: Option<TagMatchMode>,
     ) -> Result<Vec<Media>> {
-        self.send(|tx| MediaRequest::ListMedia {
-            types,
-            tags,
-            response_tx: tx,
-        })
-        .await?
+        let tag_match = tag_match.unwrap_or(self.tag_match_mode);
+        let main = [(&self.queues, false)];
+        let blend = self.blend.iter().map(|blend| (&blend.queues, true));
+
+        let mut listed = Vec::new();
+        for (queues, blended) in main.into_iter().chain(blend) {
+            let media = self
+                .send_to(queues, |tx| MediaRequest::ListMedia {
+                    types: types.clone(),
+                    tags: tags.clone(),
+                    tag_match,
+                    response_tx: tx,
+                })
+                .await??;
+            listed.extend(media.into_iter().map(|media| mark_blended(media, blended)));
+        }
+
+        Ok(listed)
+    }
+
+    pub async fn random_text(
+        &self,
+        kind: TextKind,
+        tags: Option<Vec<String>>,
+        tag_match: Option<TagMatchMode>,
+    ) -> Result<Option<String>> {
+        let tag_match = tag_match.unwrap_or(self.tag_match_mode);
+        for (queues, _) in self.pick_order() {
+            let text = self
+                .send_to(queues, |tx| MediaRequest::RandomText {
+                    kind,
+                    tags: tags.clone(),
+                    tag_match,
+                    response_tx: tx,
+                })
+                .await??;
+            if text.is_some() {
+                return Ok(text);
+            }
+        }
+
+        Ok(None)
+    }
+
+    pub async fn random_prompt(
+        &self,
+        tags: Option<Vec<String>>,
+        tag_match: Option<TagMatchMode>,
+    ) -> Result<Option<Prompt>> {
+        let tag_match = tag_match.unwrap_or(self.tag_match_mode);
+        for (queues, blended) in self.pick_order() {
+            let prompt = self
+                .send_to(queues, |tx| MediaRequest::RandomPrompt {
+                    tags: tags.clone(),
+                    tag_match,
+                    response_tx: tx,
+                })
+                .await??;
+            if let Some(mut prompt) = prompt {
+                prompt.background = prompt
+                    .background
+                    .map(|background| mark_blended(background, blended));
+                return Ok(Some(prompt));
+            }
+        }
+
+        Ok(None)
     }
 
     pub async fn get_image_data(&self, id: u64, width: u32, height: u32) -> Result<ImageData> {
-        self.send(|tx| MediaRequest::GetImageData {
+        let (queues, id) = self.route(id);
+        self.send_to(queues, |tx| MediaRequest::GetImageData {
             id,
             width,
             height,
@@ -114,7 +540,8 @@ impl MediaManager {
     }
 
     pub async fn get_image_file(&self, id: u64) -> Result<FileOrPath> {
-        self.send(|tx| MediaRequest::GetImageFile {
+        let (queues, id) = self.route(id);
+        self.send_to(queues, |tx| MediaRequest::GetImageFile {
             id,
             response_tx: tx,
         })
@@ -128,11 +555,14 @@ impl MediaManager {
         play_audio: bool,
     ) -> Result<VideoDecoder> {
         let wgpu_device = self.wgpu_device.clone();
-        self.send(|tx| MediaRequest::GetVideoData {
+        let (queues, id) = self.route(id);
+        self.send_to(queues, |tx| MediaRequest::GetVideoData {
             id,
             response_tx: tx,
             loop_video,
             play_audio,
+            max_fps: self.max_video_fps,
+            isolate: self.isolate_video_decoding,
             wgpu_device,
         })
         .await?
@@ -144,7 +574,8 @@ impl MediaManager {
         audio_id: u64,
         loop_audio: bool,
     ) -> Result<AudioPlayer> {
-        self.send(|tx| MediaRequest::GetAudioData {
+        let (queues, id) = self.route(id);
+        self.send_to(queues, |tx| MediaRequest::GetAudioData {
             id,
             audio_id,
             loop_audio,
@@ -153,6 +584,50 @@ impl MediaManager {
         .await?
     }
 
+    /// Gets the media ready ahead of time (see [`MediaPack::preload`]), e.g. the wallpaper and
+    /// audio for the next part of a session, so switching to them doesn't stall.
+    pub async fn preload(&self, id: u64) -> Result<()> {
+        let (queues, id) = self.route(id);
+        self.send_to(queues, |tx| MediaRequest::Preload {
+            id,
+            response_tx: tx,
+        })
+        .await?
+    }
+
+    /// Never shows the media again, in this session or later ones (see
+    /// [`shared::user_config::load_blocked_media`]).
+    pub async fn block_media(&self, id: u64) -> Result<()> {
+        let (queues, id) = self.route(id);
+        self.send_to(queues, |tx| MediaRequest::BlockMedia {
+            id,
+            response_tx: tx,
+        })
+        .await?
+    }
+
+    /// Adds the media to the pack's favorites, or takes it off them, for this session and later
+    /// ones (see [`shared::user_config::load_favorites`]). Returns whether it's now a favorite.
+    pub async fn toggle_favorite(&self, id: u64) -> Result<bool> {
+        let (queues, id) = self.route(id);
+        self.send_to(queues, |tx| MediaRequest::ToggleFavorite {
+            id,
+            response_tx: tx,
+        })
+        .await?
+    }
+
+    /// Lets the media with `tags` through once a milestone holding them back has been reached.
+    /// See [`MediaPack::unlock_tags`]. Only the first pack has milestones, so only its media is
+    /// ever held back.
+    pub async fn unlock_tags(&self, tags: Vec<String>) -> Result<()> {
+        self.send(|tx| MediaRequest::UnlockTags {
+            tags,
+            response_tx: tx,
+        })
+        .await?
+    }
+
     pub async fn get_mode(&self, id: u64) -> anyhow::Result<Vec<u8>> {
         self.send(|tx| MediaRequest::GetModeData {
             id,
@@ -162,16 +637,61 @@ impl MediaManager {
     }
 }
 
+/// Marks media from the blended-in pack with [`BLENDED_ID`].
+fn mark_blended(mut media: Media, blended: bool) -> Media {
+    if blended {
+        media.id |= BLENDED_ID;
+    }
+    media
+}
+
 fn spawn_media_manager_thread(
     pack_path: &Path,
+    blended: bool,
+    verify_media: bool,
+    max_rating: Option<u32>,
+    excluded_tags: Vec<String>,
+    favorites_mode: FavoritesMode,
+    language: Option<String>,
+    seed: u64,
+    image_cache: Arc<Mutex<ImageCache>>,
     event_loop_proxy: EventLoopProxy<UserEvent>,
-) -> anyhow::Result<(Sender<MediaRequest>, Metadata, thread::JoinHandle<()>)> {
+) -> anyhow::Result<(Queues, Metadata, thread::JoinHandle<()>)> {
     let (req_tx, mut req_rx) = channel(20);
+    let (priority_req_tx, mut priority_req_rx) = channel(20);
 
-    let file = MediaPack::open(pack_path)?;
+    let mut file = MediaPack::open_with_progress(pack_path, |progress| {
+        let _ = event_loop_proxy.send_event(UserEvent::PackOpenProgress(progress));
+    })?;
+    file.set_verify(verify_media);
+    file.set_max_rating(max_rating);
+    file.set_excluded_tags(excluded_tags);
+    file.set_favorites_mode(favorites_mode);
+    file.set_language(language);
+    file.set_image_cache(image_cache);
+    if blended {
+        // Milestones only count in the first pack, so the blended-in one's would never be
+        // reached.
+        file.unlock_all();
+    }
+    match user_config::load_blocked_media() {
+        Ok(blocked) => file.set_blocked(&blocked)?,
+        Err(err) => tracing::error!("Couldn't load blocked media: {err}"),
+    }
+    match user_config::load_favorites(&file.id()) {
+        Ok(favorites) => file.set_favorites(&favorites)?,
+        Err(err) => tracing::error!("Couldn't load favorites: {err}"),
+    }
     let metadata = file.metadata().clone();
 
     let handle = thread::spawn(move || {
+        let stream = if blended {
+            rng::Stream::BlendedMedia
+        } else {
+            rng::Stream::Media
+        };
+        rng::seed(seed, stream);
+
         let rt = tokio::runtime::Builder::new_current_thread()
             .enable_all()
             .build()
@@ -180,14 +700,40 @@ fn spawn_media_manager_thread(
         let local = LocalSet::new();
         local.spawn_local(async move {
             let manager = Rc::new(file);
+            let mut priority_open = true;
+
+            loop {
+                let (request, priority) = tokio::select! {
+                    biased;
+
+                    request = priority_req_rx.recv(), if priority_open => match request {
+                        Some(request) => (request, true),
+                        None => {
+                            priority_open = false;
+                            continue;
+                        }
+                    },
+                    request = req_rx.recv() => match request {
+                        Some(request) => (request, false),
+                        None => break,
+                    },
+                };
 
-            while let Some(request) = req_rx.recv().await {
                 let manager = manager.clone();
                 let event_loop_proxy = event_loop_proxy.clone();
 
                 tokio::task::spawn_local(async move {
                     handle_request(manager, request, event_loop_proxy).await;
                 });
+
+                // Tasks on this thread run in the order they were spawned, and decoding blocks
+                // the thread while it runs. If we kept pulling bulk requests off the queue here,
+                // a burst of popups would all be queued up ahead of any priority request that
+                // arrives a moment later. Yielding lets each bulk request start before we take
+                // the next one, so the priority queue gets checked in between.
+                if !priority {
+                    tokio::task::yield_now().await;
+                }
             }
 
             // Dropping `manager` here (rather than leaving it to fall out of scope with the
@@ -199,7 +745,11 @@ fn spawn_media_manager_thread(
         rt.block_on(local);
     });
 
-    Ok((req_tx, metadata, handle))
+    let queues = Queues {
+        tx: req_tx,
+        priority_tx: priority_req_tx,
+    };
+    Ok((queues, metadata, handle))
 }
 
 async fn handle_request(
@@ -207,70 +757,142 @@ async fn handle_request(
     request: MediaRequest,
     event_loop_proxy: EventLoopProxy<UserEvent>,
 ) {
+    let proxy = &event_loop_proxy;
+
     if !match request {
         MediaRequest::GetMedia {
             types,
             name,
             response_tx,
-        } => response_tx.send(pack.get_media(name, types)).is_ok(),
+        } => respond(response_tx, pack.get_media(name, types), proxy),
         MediaRequest::RandomMedia {
             types,
             tags,
+            tag_match,
+            response_tx,
+        } => respond(
+            response_tx,
+            pack.random_media(types, tags, tag_match),
+            proxy,
+        ),
+        MediaRequest::RandomMediaN {
+            types,
+            tags,
+            tag_match,
+            n,
             response_tx,
-        } => response_tx.send(pack.random_media(types, tags)).is_ok(),
+        } => respond(
+            response_tx,
+            pack.random_media_n(types, tags, tag_match, n),
+            proxy,
+        ),
+        MediaRequest::RandomSet {
+            types,
+            tags,
+            tag_match,
+            response_tx,
+        } => respond(response_tx, pack.random_set(types, tags, tag_match), proxy),
+        MediaRequest::RandomToast {
+            tags,
+            tag_match,
+            response_tx,
+        } => respond(response_tx, pack.random_toast(tags, tag_match), proxy),
+        MediaRequest::PopupSet {
+            set,
+            types,
+            response_tx,
+        } => respond(response_tx, pack.popup_set(set, types), proxy),
         MediaRequest::ListMedia {
             types,
             tags,
+            tag_match,
+            response_tx,
+        } => respond(response_tx, pack.list_media(types, tags, tag_match), proxy),
+        MediaRequest::RandomText {
+            kind,
+            tags,
+            tag_match,
             response_tx,
-        } => response_tx.send(pack.list_media(types, tags)).is_ok(),
+        } => respond(response_tx, pack.random_text(kind, tags, tag_match), proxy),
+        MediaRequest::RandomPrompt {
+            tags,
+            tag_match,
+            response_tx,
+        } => respond(response_tx, pack.random_prompt(tags, tag_match), proxy),
         MediaRequest::GetImageData {
             id,
             width,
             height,
             response_tx,
-        } => response_tx
-            .send(pack.get_image_data(id, width, height).await)
-            .is_ok(),
+        } => respond(
+            response_tx,
+            pack.get_image_data(id, width, height).await,
+            proxy,
+        ),
         MediaRequest::GetImageFile { id, response_tx } => {
-            response_tx.send(pack.get_image_file(id).await).is_ok()
+            respond(response_tx, pack.get_image_file(id).await, proxy)
         }
         MediaRequest::GetVideoData {
             id,
             play_audio,
             loop_video,
+            max_fps,
+            isolate,
             wgpu_device,
             response_tx,
-        } => response_tx
-            .send(pack.get_video_data(id).and_then(|data| {
+        } => respond(
+            response_tx,
+            pack.get_video_data(id).await.and_then(|data| {
+                let isolation = isolate.then(|| Isolation {
+                    id,
+                    quarantine: pack.quarantine(),
+                });
                 VideoDecoder::new(
                     data.source,
                     play_audio,
                     loop_video,
                     data.transparent,
+                    max_fps,
                     wgpu_device,
+                    isolation,
                 )
                 .map_err(|err| MediaError::VideoError(err))
-            }))
-            .is_ok(),
+            }),
+            proxy,
+        ),
         MediaRequest::GetAudioData {
             id,
             audio_id,
             loop_audio,
             response_tx,
-        } => response_tx
-            .send(pack.get_audio_data(id).and_then(|source| {
+        } => respond(
+            response_tx,
+            pack.get_audio_data(id).await.and_then(|source| {
                 AudioPlayer::new(
                     source,
                     loop_audio,
                     Some(audio_id),
-                    Some(event_loop_proxy),
+                    Some(event_loop_proxy.clone()),
                 )
                 .map_err(|err| MediaError::AudioError(err))
-            }))
-            .is_ok(),
+            }),
+            proxy,
+        ),
+        MediaRequest::Preload { id, response_tx } => {
+            respond(response_tx, pack.preload(id).await, proxy)
+        }
         MediaRequest::GetModeData { id, response_tx } => {
             response_tx.send(pack.get_mode(id)).is_ok()
         }
+        MediaRequest::BlockMedia { id, response_tx } => {
+            respond(response_tx, block_media(&pack, id), proxy)
+        }
+        MediaRequest::ToggleFavorite { id, response_tx } => {
+            respond(response_tx, toggle_favorite(&pack, id), proxy)
+        }
+        MediaRequest::UnlockTags { tags, response_tx } => {
+            respond(response_tx, Ok(pack.unlock_tags(&tags)), proxy)
+        }
     } {
         // The requester's oneshot receiver was dropped before we could respond. Normal when a
         // request is abandoned mid-flight, e.g. during shutdown when in-flight Lua tasks get
@@ -279,6 +901,55 @@ async fn handle_request(
     }
 }
 
+fn block_media(pack: &MediaPack, id: u64) -> Result<()> {
+    let hash = pack.block(id)?;
+
+    // Failing to save it only means it comes back next session, so there's no need to bother
+    // the user about it.
+    match user_config::block_media(&hash) {
+        Ok(count) => tracing::info!("Blocked media {id} ({count} blocked files)"),
+        Err(err) => tracing::error!("Couldn't save blocked media: {err}"),
+    }
+
+    Ok(())
+}
+
+fn toggle_favorite(pack: &MediaPack, id: u64) -> Result<bool> {
+    let (hash, favorite) = pack.toggle_favorite(id)?;
+
+    if let Err(err) = user_config::set_favorite(&pack.id(), &hash, favorite) {
+        tracing::error!("Couldn't save favorites: {err}");
+    }
+
+    Ok(favorite)
+}
+
+/// Sends `result` back to the requester. Errors are also reported to the main thread, which
+/// shows them to the user: the requester only gets a Lua error, which ends up in a log file that
+/// nobody sees (there's no console in release builds on Windows).
+fn respond<T>(
+    response_tx: oneshot::Sender<Result<T>>,
+    result: Result<T>,
+    event_loop_proxy: &EventLoopProxy<UserEvent>,
+) -> bool {
+    if let Err(err) = &result {
+        tracing::error!("{err}");
+
+        if let Some(kind) = err.report_kind() {
+            let event = UserEvent::MediaError {
+                kind,
+                message: err.to_string(),
+            };
+
+            if let Err(err) = event_loop_proxy.send_event(event) {
+                tracing::error!("Could not report media error: {err}");
+            }
+        }
+    }
+
+    response_tx.send(result).is_ok()
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct MediaTypes {
     pub image: bool,
@@ -347,13 +1018,49 @@ enum MediaRequest {
     RandomMedia {
         types: MediaTypes,
         tags: Option<Vec<String>>,
+        tag_match: TagMatchMode,
+        response_tx: oneshot::Sender<Result<Option<Media>>>,
+    },
+    RandomMediaN {
+        types: MediaTypes,
+        tags: Option<Vec<String>>,
+        tag_match: TagMatchMode,
+        n: usize,
+        response_tx: oneshot::Sender<Result<Vec<Media>>>,
+    },
+    RandomSet {
+        types: MediaTypes,
+        tags: Option<Vec<String>>,
+        tag_match: TagMatchMode,
+        response_tx: oneshot::Sender<Result<Vec<Media>>>,
+    },
+    RandomToast {
+        tags: Option<Vec<String>>,
+        tag_match: TagMatchMode,
         response_tx: oneshot::Sender<Result<Option<Media>>>,
     },
+    PopupSet {
+        set: String,
+        types: MediaTypes,
+        response_tx: oneshot::Sender<Result<Vec<Media>>>,
+    },
     ListMedia {
         types: MediaTypes,
         tags: Option<Vec<String>>,
+        tag_match: TagMatchMode,
         response_tx: oneshot::Sender<Result<Vec<Media>>>,
     },
+    RandomText {
+        kind: TextKind,
+        tags: Option<Vec<String>>,
+        tag_match: TagMatchMode,
+        response_tx: oneshot::Sender<Result<Option<String>>>,
+    },
+    RandomPrompt {
+        tags: Option<Vec<String>>,
+        tag_match: TagMatchMode,
+        response_tx: oneshot::Sender<Result<Option<Prompt>>>,
+    },
     GetImageData {
         id: u64,
         width: u32,
@@ -368,6 +1075,9 @@ enum MediaRequest {
         id: u64,
         play_audio: bool,
         loop_video: bool,
+        max_fps: Option<u32>,
+        /// Decode in a helper process, see [`crate::decode_helper`].
+        isolate: bool,
         wgpu_device: Option<Arc<wgpu::Device>>,
         response_tx: oneshot::Sender<Result<VideoDecoder>>,
     },
@@ -377,10 +1087,40 @@ enum MediaRequest {
         loop_audio: bool,
         response_tx: oneshot::Sender<Result<AudioPlayer>>,
     },
+    Preload {
+        id: u64,
+        response_tx: oneshot::Sender<Result<()>>,
+    },
     GetModeData {
         id: u64,
         response_tx: oneshot::Sender<anyhow::Result<Vec<u8>>>,
     },
+    BlockMedia {
+        id: u64,
+        response_tx: oneshot::Sender<Result<()>>,
+    },
+    ToggleFavorite {
+        id: u64,
+        response_tx: oneshot::Sender<Result<bool>>,
+    },
+    UnlockTags {
+        tags: Vec<String>,
+        response_tx: oneshot::Sender<Result<()>>,
+    },
+}
+
+impl MediaRequest {
+    /// Whether this request should skip ahead of bulk popup decoding. Queries are cheap, and
+    /// wallpapers and audio are usually tied to something the user is waiting on. Preloading is
+    /// for later, so it waits its turn.
+    fn is_priority(&self) -> bool {
+        !matches!(
+            self,
+            MediaRequest::GetImageData { .. }
+                | MediaRequest::GetVideoData { .. }
+                | MediaRequest::Preload { .. }
+        )
+    }
 }
 
 #[derive(Debug)]
@@ -392,6 +1132,10 @@ pub enum MediaError {
     VideoError(anyhow::Error),
     AudioError(anyhow::Error),
     Internal(&'static str),
+    /// The media's bytes didn't match the checksum stored in the pack.
+    Corrupted(u64),
+    /// Nothing in this build can decode AVIF images (see `AvifDecoder::detect`).
+    NoAvifDecoder,
 }
 
 impl Display for MediaError {
@@ -409,6 +1153,29 @@ impl Display for MediaError {
             MediaError::VideoError(err) => write!(f, "Error decoding video: {err}"),
             MediaError::AudioError(err) => write!(f, "Error decoding audio: {err}"),
             MediaError::Internal(err) => write!(f, "Internal error: {err}"),
+            MediaError::Corrupted(id) => write!(f, "Media {id} is corrupted (checksum mismatch)"),
+            MediaError::NoAvifDecoder => write!(f, "{}", tr("lewdware-no-avif")),
+        }
+    }
+}
+
+impl MediaError {
+    /// The id of a message describing what went wrong, if the user should be told about this
+    /// error. Used to only tell them once about each kind of problem, rather than once per failed
+    /// popup.
+    ///
+    /// Invalid tags aren't reported: they're a bug in the mode, and the mode gets the error.
+    pub fn report_kind(&self) -> Option<&'static str> {
+        match self {
+            MediaError::DbError(_) => Some("lewdware-media-error-index"),
+            MediaError::InvalidTag(_) => None,
+            MediaError::IoError(_) => Some("lewdware-media-error-io"),
+            MediaError::ImageError(_) => Some("lewdware-media-error-image"),
+            MediaError::VideoError(_) => Some("lewdware-media-error-video"),
+            MediaError::AudioError(_) => Some("lewdware-media-error-audio"),
+            MediaError::Internal(_) => Some("lewdware-media-error-internal"),
+            MediaError::Corrupted(_) => Some("lewdware-media-error-corrupted"),
+            MediaError::NoAvifDecoder => Some("lewdware-media-error-no-avif"),
         }
     }
 }
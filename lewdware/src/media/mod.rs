@@ -1,11 +1,16 @@
+mod arithmetic;
+mod avif;
 mod bounded_input;
 mod dev_pack;
 mod dir;
+mod image_cache;
 mod manager;
 mod pack;
 mod process;
 mod types;
 
+pub use avif::AvifDecoder;
 pub use manager::{MediaError, MediaManager, MediaTypes};
+pub use shared::location::PackLocation;
 
 pub use types::{Audio, FileOrPath, Image, ImageData, MediaSource, VideoData};
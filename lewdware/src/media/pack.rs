@@ -1,30 +1,46 @@
 use std::{
-    collections::HashMap,
-    fs,
-    io::{Read, Seek, SeekFrom, Write},
+    cell::RefCell,
+    collections::{BTreeSet, HashMap, HashSet, VecDeque},
+    io::{self, Read, Seek, SeekFrom},
     path::PathBuf,
+    sync::{Arc, Mutex},
+    time::Instant,
 };
 
-use image::{ImageFormat, ImageReader};
-use rusqlite::{Connection, MAIN_DB, Row, params, params_from_iter};
+use image::{
+    ImageError, ImageFormat, ImageReader,
+    error::{DecodingError, ImageFormatHint},
+};
+use rand::RngExt;
+use rusqlite::{
+    Connection, OptionalExtension, Row, functions::FunctionFlags, params, params_from_iter,
+};
 use shared::{
-    db::migrate,
-    read_pack::{Header, Metadata, read_pack_metadata},
+    encode::{ImageCodec, VideoContainer},
+    goals::locked_tags,
+    locale::language_matches,
+    pack_index::{PackIndex, StoredMedia, stored_media},
+    rating,
+    read_pack::{Header, Metadata, OpenProgress},
+    sidecar::TOAST_CATEGORY,
+    texts::{self, PromptKind, TextKind},
+    user_config::{FavoritesMode, TagMatchMode},
 };
 use tempfile::NamedTempFile;
-use tokio::{
-    fs::File,
-    io::{AsyncReadExt, AsyncSeekExt},
-    sync::oneshot,
-};
+use tokio::sync::oneshot;
 
 use crate::{
-    lua::{Media, MediaData},
+    decode_helper::Quarantine,
+    lua::{Media, MediaData, Prompt},
     media::{
-        VideoData,
+        PackLocation, VideoData, arithmetic,
+        avif::{AvifDecoder, decode_with_ffmpeg},
+        image_cache::{ImageCache, ImageKey},
         manager::{MediaError, MediaTypes, Result},
         types::{FileOrPath, ImageData, MediaSource},
     },
+    metrics::METRICS,
+    rng,
 };
 
 /// A simple utility to repeat variables n times in a SQLite query (i.e. returns "?,?,?,?..." n
@@ -40,41 +56,148 @@ fn repeat_vars(count: usize) -> String {
 /// A media pack, consisting of a header, some metadata and an SQLite database at the end, which
 /// contains information about all the media in the file. The database stores the offset and length
 /// of each image/video/audio file, which can be used to read it from the pack file.
+///
+/// The pack file is only ever opened for reading, and nothing is written next to it: the index
+/// is migrated and queried in memory, and what's been shown this session lives in temp tables.
+/// So packs can be played straight off read-only drives and network shares, or a web server (see
+/// [`PackLocation`]).
 pub struct MediaPack {
-    path: PathBuf,
+    location: PackLocation,
     db: Connection,
-    #[allow(unused)]
     header: Header,
     metadata: Metadata,
     tag_map: HashMap<String, u64>,
+    /// Whether to check media against its stored checksum before handing it to a decoder.
+    verify: bool,
+    /// Media that has already been checked, so each file is only hashed once per session.
+    verified: RefCell<HashSet<u64>>,
+    /// Media that failed its checksum. It's left out of all queries from then on.
+    corrupted: RefCell<HashSet<u64>>,
+    /// Media that crashed the video decoder, which is also left out. See [`crate::decode_helper`].
+    quarantine: Quarantine,
+    /// The user's language, for picking texts. See [`shared::texts`].
+    language: Option<String>,
+    /// The highest content rating the user wants to see, and the tags rated above it. See
+    /// [`shared::rating`].
+    max_rating: Option<u32>,
+    tags_above_rating: Vec<String>,
+    /// Tags the user never wants to see.
+    excluded_tags: Vec<String>,
+    /// Tags held back until one of the pack's milestones is reached. See
+    /// [`shared::goals::Milestone`].
+    locked_tags: RefCell<Vec<String>>,
+    favorites_mode: FavoritesMode,
+    /// When the pack was opened, which the pack's recency bias wears off from (see
+    /// [`Metadata::recency_bias`]).
+    opened: Instant,
+    /// When the newest file was added to the pack, in seconds since the Unix epoch. `None` if the
+    /// pack was made before files had the time they were added.
+    newest_added_at: Option<i64>,
+    /// Whether any file has a weight set in its sidecar. Random picks only go by the `weight`
+    /// column if so.
+    weighted: bool,
+    /// Images already copied out of the pack for [`Self::get_image_file`], newest last. See
+    /// [`Self::preload`].
+    preloaded: RefCell<VecDeque<(u64, NamedTempFile)>>,
+    /// Decoded images kept in case they're shown again, shared with the pack blended into this
+    /// one (or this one's, if it's the one blended in). See [`Self::set_image_cache`].
+    image_cache: Arc<Mutex<ImageCache>>,
+    /// The files picked at random most recently, newest last, and how many are kept there.
+    recent: RefCell<VecDeque<u64>>,
+    recent_limit: usize,
 }
 
+/// How many preloaded images are kept before the oldest is thrown away.
+const PRELOADED_FILES: usize = 4;
+
+/// How much more often favorites are picked than other media, with [`FavoritesMode::Boost`].
+const FAVORITES_BOOST: f64 = 4.0;
+
+/// How many of the last randomly picked files are kept from being picked again, so small packs
+/// don't show the same image a few popups in a row.
+const RECENT_MEDIA: usize = 8;
+
+#[derive(Clone)]
 struct MediaOpts {
     name: Option<String>,
     types: MediaTypes,
     tags: Option<Vec<String>>,
+    /// Whether media needs any of `tags`, or all of them.
+    tag_match: TagMatchMode,
+    /// Only media in this popup set.
+    set: Option<String>,
+    /// Only media in any popup set.
+    in_set: bool,
+    /// Only toasts, which are otherwise left out of random picks (see [`MediaPack::random_toast`]).
+    toasts: bool,
     random: bool,
-    single: bool,
+    /// Leave out the media picked most recently. See [`MediaPack::pick_random`].
+    exclude_recent: bool,
+    limit: Option<usize>,
 }
 
 impl MediaPack {
+    #[cfg(test)]
     pub fn open(path: impl Into<PathBuf>) -> anyhow::Result<Self> {
-        let path = path.into();
-        let mut file = fs::File::open(&path)?;
+        Self::open_with_progress(path, |_| {})
+    }
+
+    /// Opens the pack, calling `on_progress` as it goes, since reading the index of a large pack
+    /// can take a while. `path` can be a URL, see [`PackLocation::new`].
+    pub fn open_with_progress(
+        path: impl Into<PathBuf>,
+        mut on_progress: impl FnMut(OpenProgress),
+    ) -> anyhow::Result<Self> {
+        let location = PackLocation::new(path.into());
+        let mut file = location.open()?;
 
-        let (header, metadata) = read_pack_metadata(&mut file)?;
+        let PackIndex {
+            header,
+            metadata,
+            db: connection,
+        } = PackIndex::read(&mut file, &mut on_progress)?;
 
-        // Load the SQLite database straight into memory (no temp file: `deserialize_read_exact`
-        // hands the bytes we just read directly to SQLite's own in-memory representation via
-        // `sqlite3_deserialize`).
-        file.seek(SeekFrom::Start(header.index_offset))?;
-        let mut db_data = vec![0u8; header.index_length as usize];
-        file.read_exact(&mut db_data)?;
+        // Used in place of SQLite's RANDOM(), which can't be seeded, so random picks follow the
+        // session seed.
+        connection.create_scalar_function("random_order", 0, FunctionFlags::SQLITE_UTF8, |_| {
+            Ok(rng::with_rng(|rng| rng.random::<i64>()))
+        })?;
+        // Ordering by this (descending) picks each row with a chance proportional to its weight.
+        connection.create_scalar_function(
+            "random_weighted",
+            1,
+            FunctionFlags::SQLITE_UTF8,
+            |ctx| {
+                let weight = ctx.get::<f64>(0)?;
+                Ok(rng::with_rng(|rng| rng.random::<f64>()).powf(1.0 / weight))
+            },
+        )?;
+        connection.create_scalar_function(
+            "language_matches",
+            2,
+            FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC,
+            |ctx| {
+                let content = ctx.get::<Option<String>>(0)?;
+                let language = ctx.get::<Option<String>>(1)?;
+                Ok(content
+                    .zip(language)
+                    .is_some_and(|(content, language)| language_matches(&content, &language)))
+            },
+        )?;
 
-        let mut connection = Connection::open_in_memory()?;
-        connection.deserialize_read_exact(MAIN_DB, db_data.as_slice(), db_data.len(), false)?;
+        // How many times each file and tag has been shown this session, for media that's only
+        // picked once something else has been shown (`after_*` in the media table).
+        connection.execute_batch(
+            "CREATE TEMP TABLE shown_media (media_id INTEGER PRIMARY KEY, count INTEGER NOT NULL);
+             CREATE TEMP TABLE shown_tags (tag_id INTEGER PRIMARY KEY, count INTEGER NOT NULL);",
+        )?;
+        // Files the user has blocked, by the hash of their contents. See [`MediaPack::block`].
+        connection.execute_batch(
+            "CREATE TEMP TABLE blocked_media (hash BLOB PRIMARY KEY);
+             CREATE TEMP TABLE favorite_media (hash BLOB PRIMARY KEY);",
+        )?;
 
-        migrate(&connection)?;
+        on_progress(OpenProgress::Loading);
 
         let mut tag_map: HashMap<String, u64> = HashMap::new();
 
@@ -88,18 +211,198 @@ impl MediaPack {
             .collect::<rusqlite::Result<Vec<_>>>()?;
         }
 
+        let newest_added_at =
+            connection.query_row("SELECT MAX(added_at) FROM media", [], |row| row.get(0))?;
+        let weighted = connection.query_row(
+            "SELECT EXISTS (SELECT 1 FROM media WHERE weight IS NOT NULL)",
+            [],
+            |row| row.get(0),
+        )?;
+
+        on_progress(OpenProgress::Done);
+
+        let locked_tags = locked_tags(&metadata.milestones).into_iter().collect();
+
         Ok(MediaPack {
-            path,
+            location,
             db: connection,
             header,
             metadata,
             tag_map,
+            verify: false,
+            verified: RefCell::new(HashSet::new()),
+            corrupted: RefCell::new(HashSet::new()),
+            quarantine: Quarantine::default(),
+            language: None,
+            max_rating: None,
+            tags_above_rating: Vec::new(),
+            excluded_tags: Vec::new(),
+            locked_tags: RefCell::new(locked_tags),
+            favorites_mode: FavoritesMode::default(),
+            opened: Instant::now(),
+            newest_added_at,
+            weighted,
+            preloaded: RefCell::new(VecDeque::new()),
+            image_cache: Arc::new(Mutex::new(ImageCache::new(0))),
+            recent: RefCell::new(VecDeque::new()),
+            recent_limit: RECENT_MEDIA,
         })
     }
 
+    /// Keep decoded images in `cache`, which holds none by default.
+    pub fn set_image_cache(&mut self, cache: Arc<Mutex<ImageCache>>) {
+        self.image_cache = cache;
+    }
+
+    /// Check media against the checksum stored in the index before reading it. Media without a
+    /// checksum (added by an older pack editor) is always let through.
+    pub fn set_verify(&mut self, verify: bool) {
+        self.verify = verify;
+    }
+
+    /// The user's language, as a tag like `pt-BR`. Texts in it are picked over ones without a
+    /// language, and texts in other languages are never picked.
+    pub fn set_language(&mut self, language: Option<String>) {
+        self.language = language;
+    }
+
+    /// Leaves media and texts rated above `max_rating` out of every query, whether through their
+    /// own rating or their tags'.
+    pub fn set_max_rating(&mut self, max_rating: Option<u32>) {
+        self.max_rating = max_rating;
+        self.tags_above_rating = max_rating
+            .map(|max_rating| rating::tags_above(max_rating, &self.metadata.tag_ratings))
+            .unwrap_or_default();
+    }
+
+    /// Leaves media and texts with any of `tags` out of every query.
+    pub fn set_excluded_tags(&mut self, tags: Vec<String>) {
+        self.excluded_tags = tags;
+    }
+
+    /// Lets the media and texts with `tags` through, once the milestone holding them back has been
+    /// reached.
+    pub fn unlock_tags(&self, tags: &[String]) {
+        self.locked_tags
+            .borrow_mut()
+            .retain(|tag| !tags.contains(tag));
+    }
+
+    /// Lets through everything milestones were holding back, e.g. for a pack blended into
+    /// another's session, where its milestones never get reached.
+    pub fn unlock_all(&self) {
+        self.locked_tags.borrow_mut().clear();
+    }
+
+    /// The tags whose media and texts are left out of every query, whatever their rating: the
+    /// ones the user excluded, and the ones still locked by a milestone.
+    fn hidden_tags(&self) -> Vec<String> {
+        let mut tags = self.excluded_tags.clone();
+        tags.extend(self.locked_tags.borrow().iter().cloned());
+        tags
+    }
+
+    /// Leaves the files with these hashes (as hex, see [`shared::user_config::load_blocked_media`])
+    /// out of every query.
+    pub fn set_blocked<'a>(&self, hashes: impl IntoIterator<Item = &'a String>) -> Result<()> {
+        let mut stmt = self
+            .db
+            .prepare("INSERT OR IGNORE INTO blocked_media (hash) VALUES (unhex(?))")?;
+        for hash in hashes {
+            stmt.execute(params![hash])?;
+        }
+
+        Ok(())
+    }
+
+    /// Leaves the media out of every query from now on, along with any other media with the same
+    /// contents. Returns its hash as hex, to be saved for later sessions.
+    pub fn block(&self, id: u64) -> Result<String> {
+        let hash: String = self.db.query_row(
+            "SELECT lower(hex(hash)) FROM media WHERE id = ?",
+            params![id],
+            |row| row.get(0),
+        )?;
+        self.set_blocked([&hash])?;
+
+        Ok(hash)
+    }
+
+    /// The id from the pack's header, which the user's favorites are saved under.
+    pub fn id(&self) -> String {
+        self.header.id.to_string()
+    }
+
+    /// How favorites are picked by random queries.
+    pub fn set_favorites_mode(&mut self, mode: FavoritesMode) {
+        self.favorites_mode = mode;
+    }
+
+    /// Marks the files with these hashes (as hex) as favorites.
+    pub fn set_favorites<'a>(&self, hashes: impl IntoIterator<Item = &'a String>) -> Result<()> {
+        let mut stmt = self
+            .db
+            .prepare("INSERT OR IGNORE INTO favorite_media (hash) VALUES (unhex(?))")?;
+        for hash in hashes {
+            stmt.execute(params![hash])?;
+        }
+
+        Ok(())
+    }
+
+    /// Adds the media to the favorites, or takes it off them if it's already there. Returns its
+    /// hash as hex, and whether it's now a favorite.
+    pub fn toggle_favorite(&self, id: u64) -> Result<(String, bool)> {
+        let hash: String = self.db.query_row(
+            "SELECT lower(hex(hash)) FROM media WHERE id = ?",
+            params![id],
+            |row| row.get(0),
+        )?;
+
+        let removed = self.db.execute(
+            "DELETE FROM favorite_media WHERE hash = unhex(?)",
+            params![hash],
+        )?;
+        if removed == 0 {
+            self.set_favorites([&hash])?;
+        }
+
+        Ok((hash, removed == 0))
+    }
+
+    /// An expression for how much more often each file is picked for having been added recently,
+    /// going by the pack's recency bias. Files added at the same time as the newest get the whole
+    /// boost, falling off to none for ones added `days` or more before it, and the boost as a whole
+    /// falls off to none over the first `minutes` of the session. `None` once it has.
+    fn recency_weight(&self) -> Option<String> {
+        let bias = self.metadata.recency_bias?;
+        let newest = self.newest_added_at?;
+        if bias.boost <= 1.0 || bias.days == 0 || bias.minutes == 0 {
+            return None;
+        }
+
+        let remaining = 1.0 - self.opened.elapsed().as_secs_f64() / (bias.minutes as f64 * 60.0);
+        if remaining <= 0.0 {
+            return None;
+        }
+
+        let extra = (bias.boost - 1.0) * remaining;
+        // As a float, so SQLite doesn't divide the integer times by it as integers.
+        let window = format!("{:.1}", bias.days as f64 * 86400.0);
+        // Files without a time were added by an older pack editor, so they count as old.
+        Some(format!(
+            "(1.0 + {extra} * MAX(0.0, 1.0 - ({newest} - COALESCE(media.added_at, {newest} - {window}))
+                / {window}))"
+        ))
+    }
+
     fn build_sql(&self, opts: MediaOpts) -> Result<(String, Vec<Box<dyn rusqlite::ToSql + '_>>)> {
         let mut sql = "
-            SELECT id, file_name, file_type, offset, length, width, height, duration, audio, transparent
+            SELECT id, file_name, file_type, offset, length, width, height, duration, audio, transparent,
+                caption, category, weight, cooldown, popup_set,
+                (SELECT json_group_array(tags.name) FROM media_tags AS file_tags
+                    JOIN tags ON tags.id = file_tags.tag_id
+                    WHERE file_tags.media_id = media.id) AS tag_names
             FROM media
         "
         .to_string();
@@ -117,10 +420,89 @@ impl MediaPack {
             params.push(Box::new(name.clone()));
         }
 
+        if let Some(set) = &opts.set {
+            where_queries.push("media.popup_set = ?".to_string());
+            params.push(Box::new(set.clone()));
+        } else if opts.in_set {
+            where_queries.push("media.popup_set IS NOT NULL".to_string());
+        }
+
         if let Some(query) = self.build_media_types_query(&opts.types) {
             where_queries.push(query);
         }
 
+        if opts.toasts {
+            where_queries.push("media.category = ?".to_string());
+            params.push(Box::new(TOAST_CATEGORY));
+        } else if opts.random {
+            where_queries.push("media.category IS NOT ?".to_string());
+            params.push(Box::new(TOAST_CATEGORY));
+        }
+
+        let mut excluded = self.quarantine.ids();
+        excluded.extend(self.corrupted.borrow().iter());
+        if opts.exclude_recent {
+            excluded.extend(self.recent.borrow().iter());
+        }
+        if !excluded.is_empty() {
+            where_queries.push(format!("media.id NOT IN ({})", repeat_vars(excluded.len())));
+            for id in excluded {
+                params.push(Box::new(id));
+            }
+        }
+
+        where_queries.push("media.hash NOT IN (SELECT hash FROM blocked_media)".to_string());
+
+        // A weight of 0 keeps a file out of random picks, though it can still be asked for by name.
+        if opts.random && self.weighted {
+            where_queries.push("(media.weight IS NULL OR media.weight > 0)".to_string());
+        }
+
+        if opts.random && self.favorites_mode == FavoritesMode::Only {
+            where_queries.push(
+                "(NOT EXISTS (SELECT 1 FROM favorite_media)
+                    OR media.hash IN (SELECT hash FROM favorite_media))"
+                    .to_string(),
+            );
+        }
+
+        if let Some(max_rating) = self.max_rating {
+            where_queries.push(rating::media_filter(max_rating, &self.tags_above_rating));
+            for tag in &self.tags_above_rating {
+                params.push(Box::new(tag.clone()));
+            }
+        }
+
+        let hidden_tags = self.hidden_tags();
+        if !hidden_tags.is_empty() {
+            where_queries.push(format!(
+                "media.id NOT IN (SELECT media_tags.media_id FROM media_tags
+                    JOIN tags ON tags.id = media_tags.tag_id WHERE tags.name IN ({}))",
+                repeat_vars(hidden_tags.len())
+            ));
+            for tag in hidden_tags {
+                params.push(Box::new(tag));
+            }
+        }
+
+        // Asking for a file by name gets it regardless of what has been shown so far, and the
+        // rest of a set comes with whichever member was picked.
+        if opts.name.is_none() && opts.set.is_none() {
+            where_queries.push(
+                "(media.after_count IS NULL OR COALESCE(
+                    (SELECT SUM(shown_media.count) FROM shown_media
+                        JOIN media AS prior ON prior.id = shown_media.media_id
+                        WHERE prior.file_name = media.after_file),
+                    (SELECT shown_tags.count FROM shown_tags
+                        JOIN tags ON tags.id = shown_tags.tag_id
+                        WHERE tags.name = media.after_tag),
+                    0
+                ) >= media.after_count)"
+                    .to_string(),
+            );
+        }
+
+        let mut required_tags = None;
         if let Some(tags) = &opts.tags {
             let tag_ids = tags
                 .iter()
@@ -129,7 +511,10 @@ impl MediaPack {
                         .get(tag)
                         .ok_or(MediaError::InvalidTag(tag.clone()))
                 })
-                .collect::<Result<Vec<_>>>()?;
+                .collect::<Result<BTreeSet<_>>>()?;
+            if opts.tag_match == TagMatchMode::All {
+                required_tags = Some(tag_ids.len());
+            }
 
             where_queries.push(format!(
                 "media_tags.tag_id IN ({})",
@@ -145,12 +530,43 @@ impl MediaPack {
             sql.push_str(&format!("WHERE {} ", where_queries.join(" AND ")));
         }
 
+        // Media with more than one of the requested tags gets one row per matching tag from the
+        // join above.
+        if opts.tags.is_some() {
+            sql.push_str(" GROUP BY media.id ");
+        }
+        // With all of them asked for, it has to have a row for each.
+        if let Some(required_tags) = required_tags {
+            sql.push_str(" HAVING COUNT(DISTINCT media_tags.tag_id) = ? ");
+            params.push(Box::new(required_tags as u64));
+        }
+
         if opts.random {
-            sql.push_str(" ORDER BY RANDOM() ");
+            let mut weights = Vec::new();
+            if self.weighted {
+                weights.push("COALESCE(media.weight, 1.0)".to_string());
+            }
+            if self.favorites_mode == FavoritesMode::Boost {
+                weights.push(format!(
+                    "CASE WHEN media.hash IN (SELECT hash FROM favorite_media)
+                        THEN {FAVORITES_BOOST} ELSE 1.0 END"
+                ));
+            }
+            weights.extend(self.recency_weight());
+
+            if weights.is_empty() {
+                sql.push_str(" ORDER BY random_order() ");
+            } else {
+                sql.push_str(&format!(
+                    " ORDER BY random_weighted({}) DESC ",
+                    weights.join(" * ")
+                ));
+            }
         }
 
-        if opts.single {
-            sql.push_str(" LIMIT 1 ");
+        if let Some(limit) = opts.limit {
+            sql.push_str(" LIMIT ? ");
+            params.push(Box::new(limit as u64));
         }
 
         Ok((sql, params))
@@ -161,8 +577,13 @@ impl MediaPack {
             name: Some(name),
             types,
             tags: None,
+            tag_match: TagMatchMode::Any,
+            set: None,
+            in_set: false,
+            toasts: false,
             random: false,
-            single: true,
+            exclude_recent: false,
+            limit: Some(1),
         })?;
 
         let mut stmt = self.db.prepare(&sql)?;
@@ -176,29 +597,63 @@ impl MediaPack {
         &self,
         types: MediaTypes,
         tags: Option<Vec<String>>,
+        tag_match: TagMatchMode,
     ) -> Result<Option<Media>> {
-        let (sql, params) = self.build_sql(MediaOpts {
+        let picked = self.pick_random(MediaOpts {
             name: None,
             types,
             tags,
+            tag_match,
+            set: None,
+            in_set: false,
+            toasts: false,
             random: true,
-            single: true,
+            exclude_recent: true,
+            limit: Some(1),
         })?;
 
-        let mut stmt = self.db.prepare(&sql)?;
+        Ok(picked.into_iter().next())
+    }
 
-        stmt.query_and_then(params_from_iter(params), parse_media)?
-            .next()
-            .transpose()
+    /// Up to `n` distinct random media.
+    pub fn random_media_n(
+        &self,
+        types: MediaTypes,
+        tags: Option<Vec<String>>,
+        tag_match: TagMatchMode,
+        n: usize,
+    ) -> Result<Vec<Media>> {
+        self.pick_random(MediaOpts {
+            name: None,
+            types,
+            tags,
+            tag_match,
+            set: None,
+            in_set: false,
+            toasts: false,
+            random: true,
+            exclude_recent: true,
+            limit: Some(n),
+        })
     }
 
-    pub fn list_media(&self, types: MediaTypes, tags: Option<Vec<String>>) -> Result<Vec<Media>> {
+    pub fn list_media(
+        &self,
+        types: MediaTypes,
+        tags: Option<Vec<String>>,
+        tag_match: TagMatchMode,
+    ) -> Result<Vec<Media>> {
         let (sql, params) = self.build_sql(MediaOpts {
             name: None,
             types,
             tags,
+            tag_match,
+            set: None,
+            in_set: false,
+            toasts: false,
             random: false,
-            single: false,
+            exclude_recent: false,
+            limit: None,
         })?;
 
         let mut stmt = self.db.prepare(&sql)?;
@@ -207,6 +662,123 @@ impl MediaPack {
             .collect()
     }
 
+    /// A random image with the `toast` category. Toasts are only ever picked here, so that they
+    /// don't also turn up as ordinary popups.
+    pub fn random_toast(
+        &self,
+        tags: Option<Vec<String>>,
+        tag_match: TagMatchMode,
+    ) -> Result<Option<Media>> {
+        let picked = self.pick_random(MediaOpts {
+            name: None,
+            types: MediaTypes::IMAGE,
+            tags,
+            tag_match,
+            set: None,
+            in_set: false,
+            toasts: true,
+            random: true,
+            exclude_recent: true,
+            limit: Some(1),
+        })?;
+
+        Ok(picked.into_iter().next())
+    }
+
+    /// Every file in a random popup set, in order of file name. The set is picked from the media
+    /// matching `types` and `tags`, but all of its members of those types are returned, whether
+    /// or not they have the tags. Empty if no media matching the query is in a set.
+    pub fn random_set(
+        &self,
+        types: MediaTypes,
+        tags: Option<Vec<String>>,
+        tag_match: TagMatchMode,
+    ) -> Result<Vec<Media>> {
+        let picked = self.pick_random(MediaOpts {
+            name: None,
+            types: types.clone(),
+            tags,
+            tag_match,
+            set: None,
+            in_set: true,
+            toasts: false,
+            random: true,
+            exclude_recent: true,
+            limit: Some(1),
+        })?;
+
+        let Some(set) = picked.into_iter().next().and_then(|media| media.set) else {
+            return Ok(Vec::new());
+        };
+
+        self.popup_set(set, types)
+    }
+
+    /// Runs a random query, leaving out the files picked most recently (see [`RECENT_MEDIA`]).
+    /// When that leaves fewer than were asked for, the rest are picked from everything, recent
+    /// or not. The files picked count as recent from then on.
+    fn pick_random(&self, opts: MediaOpts) -> Result<Vec<Media>> {
+        let limit = opts.limit.unwrap_or(usize::MAX);
+        let query = |opts: MediaOpts| -> Result<Vec<Media>> {
+            let (sql, params) = self.build_sql(opts)?;
+            let mut stmt = self.db.prepare(&sql)?;
+            stmt.query_and_then(params_from_iter(params), parse_media)?
+                .collect()
+        };
+
+        let mut picked = query(opts.clone())?;
+        if opts.exclude_recent && picked.len() < limit {
+            let rest = query(MediaOpts {
+                exclude_recent: false,
+                ..opts
+            })?;
+            for media in rest {
+                if picked.len() >= limit {
+                    break;
+                }
+                if !picked.iter().any(|other| other.id == media.id) {
+                    picked.push(media);
+                }
+            }
+        }
+
+        let mut recent = self.recent.borrow_mut();
+        for media in &picked {
+            recent.retain(|&id| id != media.id);
+            recent.push_back(media.id);
+        }
+        while recent.len() > self.recent_limit {
+            recent.pop_front();
+        }
+
+        Ok(picked)
+    }
+
+    /// Every file of `types` in the popup set called `set`, in order of file name.
+    pub fn popup_set(&self, set: String, types: MediaTypes) -> Result<Vec<Media>> {
+        let (sql, params) = self.build_sql(MediaOpts {
+            name: None,
+            types,
+            tags: None,
+            tag_match: TagMatchMode::Any,
+            set: Some(set),
+            in_set: false,
+            toasts: false,
+            random: false,
+            exclude_recent: false,
+            limit: None,
+        })?;
+
+        let mut members = self
+            .db
+            .prepare(&sql)?
+            .query_and_then(params_from_iter(params), parse_media)?
+            .collect::<Result<Vec<_>>>()?;
+        members.sort_by(|a, b| a.name.cmp(&b.name));
+
+        Ok(members)
+    }
+
     fn build_media_types_query(&self, types: &MediaTypes) -> Option<String> {
         match *types {
             MediaTypes::ALL => None,
@@ -232,22 +804,119 @@ impl MediaPack {
     }
 
     pub async fn get_image_data(&self, id: u64, width: u32, height: u32) -> Result<ImageData> {
-        let (offset, length) = self.get_offset_length(id)?;
+        let key = ImageKey {
+            pack: self.id(),
+            id,
+            width,
+            height,
+        };
+        if let Some(image) = self.image_cache.lock().unwrap().get(&key) {
+            self.record_shown(id)?;
+            return Ok(image);
+        }
+
+        let (offset, length, codec) = self.get_image_location(id)?;
+        self.check_media(id, offset, length).await?;
+        self.record_shown(id)?;
+
+        let start = Instant::now();
+        let image = self
+            .read_image_data(offset, length, codec, width, height)
+            .await?;
+        METRICS.image_decoded(start.elapsed());
 
-        self.read_image_data(offset, length, width, height).await
+        self.image_cache.lock().unwrap().insert(key, &image);
+        Ok(image)
     }
 
     pub async fn get_image_file(&self, id: u64) -> Result<FileOrPath> {
-        let (offset, length) = self.get_offset_length(id)?;
+        if let Some(index) = self.preloaded_index(id) {
+            let (_, file) = self.preloaded.borrow_mut().remove(index).unwrap();
+            self.record_preloaded();
+            self.record_shown(id)?;
+            return Ok(FileOrPath::File(file));
+        }
+
+        let (offset, length, codec) = self.get_image_location(id)?;
+        self.check_media(id, offset, length).await?;
+        self.record_shown(id)?;
 
         Ok(FileOrPath::File(
-            self.write_to_temp_file(offset, length, ".avif").await?,
+            self.write_to_temp_file(offset, length, &format!(".{}", codec.extension()))
+                .await?,
         ))
     }
 
-    pub fn get_video_data(&self, id: u64) -> Result<VideoData> {
-        let (offset, length, width, height, transparent) = self.db.query_row(
-            "SELECT offset, length, width, height, transparent FROM media WHERE id = ?",
+    /// Does the slow part of fetching the media ahead of time, so it's ready straight away when
+    /// the mode uses it: images are copied out of the pack for [`Self::get_image_file`], and
+    /// everything is checked against its checksum. Doesn't count as showing the media.
+    pub async fn preload(&self, id: u64) -> Result<()> {
+        let file_type: String = self.db.query_row(
+            "SELECT file_type FROM media WHERE id = ?",
+            params![id],
+            |row| row.get("file_type"),
+        )?;
+
+        if file_type != "image" {
+            let (offset, length) = self.get_offset_length(id)?;
+            return self.check_media(id, offset, length).await;
+        }
+
+        if self.preloaded_index(id).is_some() {
+            return Ok(());
+        }
+
+        let (offset, length, codec) = self.get_image_location(id)?;
+        self.check_media(id, offset, length).await?;
+        let file = self
+            .write_to_temp_file(offset, length, &format!(".{}", codec.extension()))
+            .await?;
+
+        {
+            let mut preloaded = self.preloaded.borrow_mut();
+            preloaded.push_back((id, file));
+            if preloaded.len() > PRELOADED_FILES {
+                preloaded.pop_front();
+            }
+        }
+        self.record_preloaded();
+
+        Ok(())
+    }
+
+    /// Keeps [`METRICS`] up to date with how much has been preloaded.
+    fn record_preloaded(&self) {
+        let bytes = self
+            .preloaded
+            .borrow()
+            .iter()
+            .filter_map(|(_, file)| file.as_file().metadata().ok())
+            .map(|metadata| metadata.len())
+            .sum();
+        METRICS.set_preloaded_bytes(bytes);
+    }
+
+    fn preloaded_index(&self, id: u64) -> Option<usize> {
+        self.preloaded
+            .borrow()
+            .iter()
+            .position(|(other, _)| *other == id)
+    }
+
+    /// Shared with the video decoders, so they can quarantine media that crashes them.
+    pub fn quarantine(&self) -> Quarantine {
+        self.quarantine.clone()
+    }
+
+    pub async fn get_video_data(&self, id: u64) -> Result<VideoData> {
+        if self.quarantine.contains(id) {
+            return Err(MediaError::VideoError(anyhow::anyhow!(
+                "Media {id} crashed the video decoder, so it's being skipped"
+            )));
+        }
+
+        let (offset, length, width, height, transparent, container) = self.db.query_row(
+            "SELECT offset, length, width, height, transparent, container FROM media WHERE id = ?",
             params![id],
             |row| {
                 Ok((
@@ -256,78 +925,330 @@ impl MediaPack {
                     row.get("width")?,
                     row.get("height")?,
                     row.get("transparent")?,
+                    row.get::<_, Option<String>>("container")?,
                 ))
             },
         )?;
+        self.check_media(id, offset, length).await?;
+        self.record_shown(id)?;
+
+        // One this version doesn't know is left to ffmpeg to work out, as if it weren't recorded.
+        let container = container.and_then(|container| container.parse::<VideoContainer>().ok());
 
         Ok(VideoData {
-            source: self.media_source(offset, length),
+            source: MediaSource {
+                container,
+                ..self.media_source(offset, length)
+            },
             width,
             height,
             transparent,
         })
     }
 
-    pub fn get_audio_data(&self, id: u64) -> Result<MediaSource> {
+    pub async fn get_audio_data(&self, id: u64) -> Result<MediaSource> {
         let (offset, length) = self.get_offset_length(id)?;
+        self.check_media(id, offset, length).await?;
+        self.record_shown(id)?;
 
         Ok(self.media_source(offset, length))
     }
 
+    /// Counts a showing of the media and each of its tags, which may let other media through
+    /// (see `build_sql`).
+    fn record_shown(&self, id: u64) -> Result<()> {
+        self.db.execute(
+            "INSERT INTO shown_media (media_id, count) VALUES (?, 1)
+             ON CONFLICT (media_id) DO UPDATE SET count = count + 1",
+            params![id],
+        )?;
+        self.db.execute(
+            "INSERT INTO shown_tags (tag_id, count)
+             SELECT tag_id, 1 FROM media_tags WHERE media_id = ?
+             ON CONFLICT (tag_id) DO UPDATE SET count = count + 1",
+            params![id],
+        )?;
+
+        Ok(())
+    }
+
     fn media_source(&self, offset: u64, length: u64) -> MediaSource {
         MediaSource {
-            path: self.path.clone(),
+            location: self.location.clone(),
             offset,
             length,
+            container: None,
         }
     }
 
+    fn get_stored_media(&self, id: u64) -> Result<StoredMedia> {
+        stored_media(&self.db, id)?.ok_or(MediaError::DbError(rusqlite::Error::QueryReturnedNoRows))
+    }
+
     fn get_offset_length(&self, id: u64) -> Result<(u64, u64)> {
-        let mut stmt = self
-            .db
-            .prepare("SELECT offset, length FROM media WHERE id = ?")?;
+        self.get_stored_media(id)?
+            .range()
+            .ok_or(MediaError::Internal("Media has no data in the pack"))
+    }
 
-        stmt.query_row(params![id], |row| {
-            Ok((row.get("offset")?, row.get("length")?))
-        })
-        .map_err(|err| err.into())
+    /// Like `get_offset_length`, but also returns the format the image is stored in.
+    fn get_image_location(&self, id: u64) -> Result<(u64, u64, ImageCodec)> {
+        let media = self.get_stored_media(id)?;
+        let (offset, length) = media
+            .range()
+            .ok_or(MediaError::Internal("Media has no data in the pack"))?;
+        let codec = media
+            .image_codec()
+            .map_err(|_| MediaError::Internal("Unknown image codec"))?;
+
+        Ok((offset, length, codec))
     }
 
-    pub fn get_mode(&self, id: u64) -> anyhow::Result<Vec<u8>> {
-        let mut stmt = self.db.prepare("SELECT file FROM modes WHERE id = ?")?;
+    /// If verification is on, hash the media's bytes and compare them to its stored checksum.
+    async fn check_media(&self, id: u64, offset: u64, length: u64) -> Result<()> {
+        if !self.verify || self.verified.borrow().contains(&id) {
+            return Ok(());
+        }
 
-        stmt.query_row(params![id], |row| row.get("file"))
-            .map_err(|err| err.into())
+        if self.corrupted.borrow().contains(&id) {
+            return Err(MediaError::Corrupted(id));
+        }
+
+        if let Some(checksum) = self.get_stored_media(id)?.checksum {
+            // On a thread of its own, since reading a pack over HTTP blocks.
+            let location = self.location.clone();
+            let hash = tokio::task::spawn_blocking(move || -> io::Result<blake3::Hash> {
+                let mut file = location.open()?;
+                file.seek(SeekFrom::Start(offset))?;
+
+                let mut hasher = blake3::Hasher::new();
+                hasher.update_reader(file.take(length))?;
+                Ok(hasher.finalize())
+            })
+            .await
+            .map_err(|_| MediaError::Internal("Checking media against its checksum panicked"))??;
+
+            if hash.as_bytes().as_slice() != checksum.as_slice() {
+                tracing::warn!("Media {id} does not match its checksum, skipping it from now on");
+                self.corrupted.borrow_mut().insert(id);
+                return Err(MediaError::Corrupted(id));
+            }
+        }
+
+        self.verified.borrow_mut().insert(id);
+        Ok(())
     }
 
-    async fn read_image_data(
+    /// A random notification, prompt or link. With `tags`, only texts with at least one of them
+    /// (or all of them, with [`TagMatchMode::All`]) are picked. Unlike media, these tags don't
+    /// have to exist in the pack.
+    ///
+    /// Texts in the user's language are picked if there are any, otherwise ones without a
+    /// language.
+    pub fn random_text(
         &self,
-        offset: u64,
-        length: u64,
-        width: u32,
-        height: u32,
-    ) -> Result<ImageData> {
-        let mut file = std::fs::File::open(&self.path)?;
-        file.seek(SeekFrom::Start(offset))?;
-        let file = file.take(length);
+        kind: TextKind,
+        tags: Option<Vec<String>>,
+        tag_match: TagMatchMode,
+    ) -> Result<Option<String>> {
+        self.query_random_text(kind, tags, tag_match, |row| row.get("text"))
+    }
 
-        let mut reader = ImageReader::new(std::io::BufReader::new(file));
+    /// A random prompt, with the theme the pack gives it. The background is left out if the pack
+    /// doesn't have an image with that name.
+    pub fn random_prompt(
+        &self,
+        tags: Option<Vec<String>>,
+        tag_match: TagMatchMode,
+    ) -> Result<Option<Prompt>> {
+        let kinds = &self.metadata.prompt_kinds;
+        // The kinds that use the pack's own prompts.
+        let text_kinds: Vec<_> = if kinds.is_empty() {
+            vec![PromptKind::RepeatText]
+        } else {
+            [PromptKind::RepeatText, PromptKind::TypingTest]
+                .into_iter()
+                .filter(|kind| kinds.contains(kind))
+                .collect()
+        };
+        let arithmetic = kinds.contains(&PromptKind::Arithmetic);
+
+        let choice =
+            rng::with_rng(|rng| rng.random_range(0..text_kinds.len() + usize::from(arithmetic)));
+        if let Some(&kind) = text_kinds.get(choice) {
+            let prompt = self
+                .random_text_prompt(tags, tag_match)?
+                .map(|prompt| Prompt { kind, ..prompt });
+            // Packs with no prompts of their own (in the user's language, with the tags) can
+            // still pop up sums.
+            if prompt.is_some() || !arithmetic {
+                return Ok(prompt);
+            }
+        }
 
-        reader.set_format(ImageFormat::Avif);
+        let problem = rng::with_rng(arithmetic::random_problem);
+        Ok(Some(Prompt {
+            kind: PromptKind::Arithmetic,
+            text: problem.text,
+            answer: Some(problem.answer.to_string()),
+            background: None,
+            background_color: None,
+            text_color: None,
+        }))
+    }
 
-        let image = reader.decode()?;
+    fn random_text_prompt(
+        &self,
+        tags: Option<Vec<String>>,
+        tag_match: TagMatchMode,
+    ) -> Result<Option<Prompt>> {
+        let Some((text, theme)) =
+            self.query_random_text(TextKind::Prompt, tags, tag_match, |row| {
+                Ok((row.get("text")?, texts::read_theme(row)?))
+            })?
+        else {
+            return Ok(None);
+        };
 
-        if image.width() != width || image.height() != height {
-            let (tx, rx) = oneshot::channel();
+        let background = match theme.background {
+            Some(name) => self.get_media(name, MediaTypes::IMAGE)?,
+            None => None,
+        };
 
-            rayon::spawn(move || {
-                use fast_image_resize::{
-                    FilterType, PixelType, ResizeAlg, ResizeOptions, Resizer, images::Image,
-                };
+        Ok(Some(Prompt {
+            kind: PromptKind::RepeatText,
+            text,
+            answer: None,
+            background,
+            background_color: theme.background_color,
+            text_color: theme.text_color,
+        }))
+    }
 
-                let src: image::DynamicImage = image.into_rgba8().into();
-                let mut dst = Image::new(width, height, PixelType::U8x4);
-                let opts =
+    fn query_random_text<T>(
+        &self,
+        kind: TextKind,
+        tags: Option<Vec<String>>,
+        tag_match: TagMatchMode,
+        f: impl FnOnce(&Row<'_>) -> rusqlite::Result<T>,
+    ) -> Result<Option<T>> {
+        let mut sql = "SELECT *, language_matches(locale, ?) AS matches FROM texts
+             WHERE kind = ? AND (locale IS NULL OR matches)"
+            .to_string();
+        let mut params: Vec<Box<dyn rusqlite::ToSql>> =
+            vec![Box::new(self.language.clone()), Box::new(kind.as_str())];
+
+        if let Some(tags) = tags {
+            if tags.is_empty() {
+                return Ok(None);
+            }
+
+            let tags: BTreeSet<String> = tags.into_iter().collect();
+            let matching = match tag_match {
+                TagMatchMode::Any => "> 0".to_string(),
+                TagMatchMode::All => format!("= {}", tags.len()),
+            };
+            sql.push_str(&format!(
+                " AND (SELECT COUNT(DISTINCT value) FROM json_each(texts.tags)
+                    WHERE value IN ({})) {matching}",
+                repeat_vars(tags.len())
+            ));
+            for tag in tags {
+                params.push(Box::new(tag));
+            }
+        }
+
+        if let Some(filter) = rating::text_filter(&self.tags_above_rating) {
+            sql.push_str(&format!(" AND {filter}"));
+            for tag in &self.tags_above_rating {
+                params.push(Box::new(tag.clone()));
+            }
+        }
+
+        let hidden_tags = self.hidden_tags();
+        if let Some(filter) = rating::text_filter(&hidden_tags) {
+            sql.push_str(&format!(" AND {filter}"));
+            for tag in hidden_tags {
+                params.push(Box::new(tag));
+            }
+        }
+
+        sql.push_str(" ORDER BY matches DESC, random_order() LIMIT 1");
+
+        let mut stmt = self.db.prepare(&sql)?;
+
+        Ok(stmt.query_row(params_from_iter(params), f).optional()?)
+    }
+
+    pub fn get_mode(&self, id: u64) -> anyhow::Result<Vec<u8>> {
+        let mut stmt = self.db.prepare("SELECT file FROM modes WHERE id = ?")?;
+
+        stmt.query_row(params![id], |row| row.get("file"))
+            .map_err(|err| err.into())
+    }
+
+    async fn read_image_data(
+        &self,
+        offset: u64,
+        length: u64,
+        codec: ImageCodec,
+        width: u32,
+        height: u32,
+    ) -> Result<ImageData> {
+        let format = match codec {
+            ImageCodec::Avif if AvifDecoder::detect().is_none() => {
+                return Err(MediaError::NoAvifDecoder);
+            }
+            ImageCodec::Avif if AvifDecoder::detect() == Some(AvifDecoder::Native) => {
+                Some(ImageFormat::Avif)
+            }
+            ImageCodec::Webp => Some(ImageFormat::WebP),
+            ImageCodec::Avif | ImageCodec::Jxl => None,
+        };
+
+        // Decoded on a thread of its own, since reading a pack over HTTP blocks.
+        let Some(format) = format else {
+            // `decode_with_ffmpeg` scales the image as part of decoding, so there's nothing left
+            // to do afterwards.
+            let source = self.media_source(offset, length);
+            return tokio::task::spawn_blocking(move || decode_with_ffmpeg(&source, width, height))
+                .await
+                .map_err(|_| MediaError::Internal("Decoding an image panicked"))?
+                .map_err(|err| {
+                    ImageError::Decoding(DecodingError::new(
+                        ImageFormatHint::Name(codec.as_str().to_string()),
+                        err,
+                    ))
+                    .into()
+                });
+        };
+
+        let location = self.location.clone();
+        let image = tokio::task::spawn_blocking(move || -> Result<image::DynamicImage> {
+            let mut file = location.open()?;
+            file.seek(SeekFrom::Start(offset))?;
+            let file = file.take(length);
+
+            let mut reader = ImageReader::new(std::io::BufReader::new(file));
+
+            reader.set_format(format);
+
+            Ok(reader.decode()?)
+        })
+        .await
+        .map_err(|_| MediaError::Internal("Decoding an image panicked"))??;
+
+        if image.width() != width || image.height() != height {
+            let (tx, rx) = oneshot::channel();
+
+            rayon::spawn(move || {
+                use fast_image_resize::{
+                    FilterType, PixelType, ResizeAlg, ResizeOptions, Resizer, images::Image,
+                };
+
+                let src: image::DynamicImage = image.into_rgba8().into();
+                let mut dst = Image::new(width, height, PixelType::U8x4);
+                let opts =
                     ResizeOptions::new().resize_alg(ResizeAlg::Convolution(FilterType::Bilinear));
                 let result = Resizer::new()
                     .resize(&src, &mut dst, &opts)
@@ -346,22 +1267,38 @@ impl MediaPack {
         }
     }
 
+    /// Copies a file out of the pack, for the few things that need a real path (image
+    /// wallpapers). Videos and audio are read straight out of the pack instead (see
+    /// [`MediaSource`]).
+    ///
+    /// The file is copied a chunk at a time rather than read into memory whole. It's only handed
+    /// out once all of it has been written, and it lives in [`crate::utils::temp_dir`], which is
+    /// swept on the next startup if we crash before it's deleted.
     async fn write_to_temp_file(
         &self,
         offset: u64,
         length: u64,
         suffix: &str,
     ) -> Result<NamedTempFile> {
-        let mut tempfile = NamedTempFile::with_suffix_in(suffix, crate::utils::temp_dir())?;
-        let mut buffer = vec![0u8; length as usize];
-
-        let mut file = File::open(&self.path).await?;
-
-        file.seek(SeekFrom::Start(offset)).await?;
-
-        file.read_exact(&mut buffer).await?;
-
-        tempfile.write_all(&buffer)?;
+        let tempfile = NamedTempFile::with_suffix_in(suffix, crate::utils::temp_dir())?;
+        let mut out = tempfile.reopen()?;
+
+        // On a thread of its own, since reading a pack over HTTP blocks.
+        let location = self.location.clone();
+        let copied = tokio::task::spawn_blocking(move || -> io::Result<u64> {
+            let mut file = location.open()?;
+            file.seek(SeekFrom::Start(offset))?;
+            io::copy(&mut file.take(length), &mut out)
+        })
+        .await
+        .map_err(|_| MediaError::Internal("Copying media out of the pack panicked"))??;
+        if copied != length {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "The pack ended partway through a file",
+            )
+            .into());
+        }
 
         Ok(tempfile)
     }
@@ -394,18 +1331,62 @@ fn parse_media(row: &Row<'_>) -> Result<Media> {
         id: row.get("id")?,
         name: row.get("file_name")?,
         media_data,
+        tags: serde_json::from_str(&row.get::<_, String>("tag_names")?)
+            .map_err(|_| MediaError::Internal("Invalid tag list"))?,
+        caption: row.get("caption")?,
+        category: row.get("category")?,
+        weight: row.get("weight")?,
+        cooldown: row.get("cooldown")?,
+        set: row.get("popup_set")?,
     })
 }
 
 #[cfg(test)]
 mod tests {
-    use std::io::Write as _;
+    use std::{fs, io::Write as _, time::Duration};
 
     use ffmpeg_next as ffmpeg;
-    use shared::read_pack::HEADER_SIZE;
+    use rusqlite::MAIN_DB;
+    use shared::{
+        db::migrate,
+        goals::{GoalStat, Milestone},
+        read_pack::{HEADER_SIZE, RecencyBias, VERSION_1},
+    };
 
     use super::*;
 
+    /// Where [`write_pack`] puts the media bytes it's given, for index rows that point into them.
+    const DATA_OFFSET: u64 = HEADER_SIZE as u64;
+
+    /// Writes a pack file laid out as the header, `data`, `metadata`, then `db` as the index.
+    fn write_pack(db: &Connection, metadata: &Metadata, data: &[u8]) -> NamedTempFile {
+        let db_bytes = db.serialize(MAIN_DB).unwrap();
+        let metadata_bytes = metadata.to_buf().unwrap();
+
+        let mut header = Header::new();
+        header.version = VERSION_1;
+        header.metadata_offset = DATA_OFFSET + data.len() as u64;
+        header.metadata_length = metadata_bytes.len() as u64;
+        header.index_offset = header.metadata_offset + header.metadata_length;
+        header.index_length = db_bytes.len() as u64;
+
+        let mut file = NamedTempFile::new().unwrap();
+        file.write_all(&header.to_buf().unwrap()).unwrap();
+        file.write_all(data).unwrap();
+        file.write_all(&metadata_bytes).unwrap();
+        file.write_all(&db_bytes).unwrap();
+        file.flush().unwrap();
+        file
+    }
+
+    /// Opens a pack with `db` as its index, default metadata and no media data. The file has to
+    /// outlive the pack.
+    fn pack_from_db(db: &Connection) -> (NamedTempFile, MediaPack) {
+        let file = write_pack(db, &Metadata::default(), &[]);
+        let pack = MediaPack::open(file.path()).unwrap();
+        (file, pack)
+    }
+
     /// The pack-editor (`pack-editor/src-tauri/src/pack.rs`, `Pack::save`) doesn't build the
     /// index via `rusqlite`'s `serialize()` -- it runs `VACUUM` on a plain on-disk connection
     /// opened through `SqliteConnectionManager::file(...)` (default journal mode, no WAL), then
@@ -472,6 +1453,7 @@ mod tests {
         let metadata_bytes = metadata.to_buf().unwrap();
 
         let mut header = Header::new();
+        header.version = VERSION_1;
         header.metadata_offset = HEADER_SIZE as u64;
         header.metadata_length = metadata_bytes.len() as u64;
         header.index_offset = header.metadata_offset + header.metadata_length;
@@ -488,7 +1470,11 @@ mod tests {
         assert_eq!(pack.metadata().name, "test-pack");
 
         let results = pack
-            .list_media(MediaTypes::ALL, Some(vec!["test-tag".to_string()]))
+            .list_media(
+                MediaTypes::ALL,
+                Some(vec!["test-tag".to_string()]),
+                TagMatchMode::Any,
+            )
             .unwrap();
 
         assert_eq!(results.len(), 1);
@@ -504,18 +1490,830 @@ mod tests {
 
         // Also confirm a tag that doesn't exist is rejected rather than silently ignored.
         assert!(matches!(
-            pack.list_media(MediaTypes::ALL, Some(vec!["nonexistent".to_string()])),
+            pack.list_media(
+                MediaTypes::ALL,
+                Some(vec!["nonexistent".to_string()]),
+                TagMatchMode::Any
+            ),
             Err(MediaError::InvalidTag(_))
         ));
     }
 
+    #[test]
+    fn never_writes_to_the_pack() {
+        let db = Connection::open_in_memory().unwrap();
+        migrate(&db).unwrap();
+        db.execute(
+            "INSERT INTO media (file_name, file_type, width, height, hash)
+             VALUES ('pic.avif', 'image', 1, 1, x'00')",
+            [],
+        )
+        .unwrap();
+
+        let file = write_pack(&db, &Metadata::default(), &[]);
+
+        let original = fs::read(file.path()).unwrap();
+        let mut permissions = fs::metadata(file.path()).unwrap().permissions();
+        permissions.set_readonly(true);
+        fs::set_permissions(file.path(), permissions).unwrap();
+
+        let pack = MediaPack::open(file.path()).unwrap();
+        let media = pack
+            .random_media(MediaTypes::ALL, None, TagMatchMode::Any)
+            .unwrap()
+            .unwrap();
+        pack.record_shown(media.id).unwrap();
+        drop(pack);
+
+        assert_eq!(fs::read(file.path()).unwrap(), original);
+    }
+
+    #[test]
+    fn recent_media_is_held_back_until_nothing_else_is_left() {
+        let db = Connection::open_in_memory().unwrap();
+        migrate(&db).unwrap();
+        for i in 0..3 {
+            db.execute(
+                "INSERT INTO media (file_name, file_type, width, height, hash)
+                 VALUES (?, 'image', 1, 1, ?)",
+                params![format!("{i}.avif"), vec![i as u8]],
+            )
+            .unwrap();
+        }
+
+        let (_file, pack) = pack_from_db(&db);
+        let pick = || {
+            pack.random_media(MediaTypes::ALL, None, TagMatchMode::Any)
+                .unwrap()
+                .unwrap()
+                .id
+        };
+
+        let mut picked = vec![pick(), pick(), pick()];
+        picked.sort();
+        assert_eq!(picked, [1, 2, 3]);
+
+        // Everything has been picked recently, so anything goes.
+        pick();
+        // Asking for more than are left tops them up with recent ones.
+        assert_eq!(
+            pack.random_media_n(MediaTypes::ALL, None, TagMatchMode::Any, 3)
+                .unwrap()
+                .len(),
+            3
+        );
+    }
+
+    #[test]
+    fn random_media_n_returns_distinct_media() {
+        let db = Connection::open_in_memory().unwrap();
+        migrate(&db).unwrap();
+
+        db.execute("INSERT INTO tags (name) VALUES ('a'), ('b')", [])
+            .unwrap();
+        for i in 0..5 {
+            db.execute(
+                "INSERT INTO media (file_name, file_type, width, height, hash)
+                 VALUES (?, 'image', 1, 1, ?)",
+                params![format!("{i}.avif"), vec![i as u8]],
+            )
+            .unwrap();
+        }
+        // Media 1 has both tags, so the tag join produces two rows for it.
+        db.execute(
+            "INSERT INTO media_tags (media_id, tag_id) VALUES (1, 1), (1, 2), (2, 1)",
+            [],
+        )
+        .unwrap();
+
+        let (_file, pack) = pack_from_db(&db);
+
+        assert_eq!(
+            pack.random_media_n(MediaTypes::ALL, None, TagMatchMode::Any, 3)
+                .unwrap()
+                .len(),
+            3
+        );
+        assert_eq!(
+            pack.random_media_n(MediaTypes::ALL, None, TagMatchMode::Any, 10)
+                .unwrap()
+                .len(),
+            5
+        );
+
+        let mut tagged = pack
+            .random_media_n(
+                MediaTypes::ALL,
+                Some(vec!["a".to_string(), "b".to_string()]),
+                TagMatchMode::Any,
+                10,
+            )
+            .unwrap()
+            .into_iter()
+            .map(|media| media.id)
+            .collect::<Vec<_>>();
+        tagged.sort();
+        assert_eq!(tagged, vec![1, 2]);
+    }
+
+    #[test]
+    fn all_tags_have_to_match() {
+        let db = Connection::open_in_memory().unwrap();
+        migrate(&db).unwrap();
+        db.execute_batch(
+            "INSERT INTO tags (name) VALUES ('a'), ('b');
+             INSERT INTO media (file_name, file_type, width, height, hash)
+                VALUES ('both.avif', 'image', 1, 1, x'01'),
+                       ('a.avif', 'image', 1, 1, x'02'),
+                       ('b.avif', 'image', 1, 1, x'03');
+             INSERT INTO media_tags (media_id, tag_id) VALUES (1, 1), (1, 2), (2, 1), (3, 2);
+             INSERT INTO texts (kind, text, tags) VALUES ('notification', 'Both', '[\"a\", \"b\"]');
+             INSERT INTO texts (kind, text, tags) VALUES ('notification', 'Just a', '[\"a\"]');",
+        )
+        .unwrap();
+
+        let (_file, pack) = pack_from_db(&db);
+        let tags = || Some(vec!["a".to_string(), "b".to_string(), "a".to_string()]);
+
+        let names = |tag_match| {
+            let mut names = pack
+                .list_media(MediaTypes::ALL, tags(), tag_match)
+                .unwrap()
+                .into_iter()
+                .map(|media| media.name)
+                .collect::<Vec<_>>();
+            names.sort();
+            names
+        };
+        assert_eq!(names(TagMatchMode::Any), ["a.avif", "b.avif", "both.avif"]);
+        assert_eq!(names(TagMatchMode::All), ["both.avif"]);
+
+        for _ in 0..10 {
+            let text = pack
+                .random_text(TextKind::Notification, tags(), TagMatchMode::All)
+                .unwrap();
+            assert_eq!(text.as_deref(), Some("Both"));
+        }
+    }
+
+    #[tokio::test]
+    async fn verify_skips_media_with_bad_checksum() {
+        const GOOD: &[u8] = b"good audio bytes";
+        const BAD: &[u8] = b"bad audio bytes!";
+
+        let db = Connection::open_in_memory().unwrap();
+        migrate(&db).unwrap();
+        db.execute_batch(
+            "INSERT INTO media (file_name, file_type, duration, hash) VALUES ('good.opus', 'audio', 1.0, x'01');
+             INSERT INTO media (file_name, file_type, duration, hash) VALUES ('bad.opus', 'audio', 1.0, x'02');",
+        )
+        .unwrap();
+
+        let good_offset = DATA_OFFSET;
+        let bad_offset = good_offset + GOOD.len() as u64;
+
+        // Both checksums are of the good bytes, so the second entry doesn't match.
+        let checksum = blake3::hash(GOOD).as_bytes().to_vec();
+        db.execute(
+            "UPDATE media SET offset = ?, length = ?, checksum = ? WHERE id = 1",
+            params![good_offset, GOOD.len() as u64, checksum],
+        )
+        .unwrap();
+        db.execute(
+            "UPDATE media SET offset = ?, length = ?, checksum = ? WHERE id = 2",
+            params![bad_offset, BAD.len() as u64, checksum],
+        )
+        .unwrap();
+
+        let file = write_pack(&db, &Metadata::default(), &[GOOD, BAD].concat());
+        let mut pack = MediaPack::open(file.path()).unwrap();
+
+        // Without verification, corrupted media is handed straight to the decoder.
+        assert!(pack.get_audio_data(2).await.is_ok());
+
+        pack.set_verify(true);
+        assert!(pack.get_audio_data(1).await.is_ok());
+        assert!(matches!(
+            pack.get_audio_data(2).await,
+            Err(MediaError::Corrupted(2))
+        ));
+
+        let remaining = pack
+            .list_media(MediaTypes::ALL, None, TagMatchMode::Any)
+            .unwrap();
+        assert_eq!(remaining.len(), 1);
+        assert_eq!(remaining[0].name, "good.opus");
+    }
+
+    #[tokio::test]
+    async fn only_after_holds_media_back_until_shown() {
+        let db = Connection::open_in_memory().unwrap();
+        migrate(&db).unwrap();
+        db.execute_batch(
+            "INSERT INTO tags (name) VALUES ('intro');
+             INSERT INTO media (file_name, file_type, duration, offset, length, hash)
+                VALUES ('intro.opus', 'audio', 1.0, 0, 0, x'01');
+             INSERT INTO media (file_name, file_type, duration, offset, length, hash, after_file, after_count)
+                VALUES ('second.opus', 'audio', 1.0, 0, 0, x'02', 'intro.opus', 2);
+             INSERT INTO media (file_name, file_type, duration, offset, length, hash, after_tag, after_count)
+                VALUES ('tagged.opus', 'audio', 1.0, 0, 0, x'03', 'intro', 1);
+             INSERT INTO media_tags (media_id, tag_id) VALUES (1, 1);",
+        )
+        .unwrap();
+
+        let (_file, pack) = pack_from_db(&db);
+        let eligible = || {
+            pack.list_media(MediaTypes::ALL, None, TagMatchMode::Any)
+                .unwrap()
+                .into_iter()
+                .map(|media| media.name)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(eligible(), ["intro.opus"]);
+        // Held back media can still be asked for by name.
+        assert!(
+            pack.get_media("second.opus".to_string(), MediaTypes::ALL)
+                .unwrap()
+                .is_some()
+        );
+
+        pack.get_audio_data(1).await.unwrap();
+        assert_eq!(eligible(), ["intro.opus", "tagged.opus"]);
+
+        pack.get_audio_data(1).await.unwrap();
+        assert_eq!(eligible(), ["intro.opus", "second.opus", "tagged.opus"]);
+    }
+
+    #[test]
+    fn random_set_returns_every_member() {
+        let db = Connection::open_in_memory().unwrap();
+        migrate(&db).unwrap();
+        db.execute_batch(
+            "INSERT INTO tags (name) VALUES ('left');
+             INSERT INTO media (file_name, file_type, width, height, hash, popup_set)
+                VALUES ('c.avif', 'image', 1, 1, x'01', 'triptych');
+             INSERT INTO media (file_name, file_type, width, height, hash, popup_set)
+                VALUES ('a.avif', 'image', 1, 1, x'02', 'triptych');
+             INSERT INTO media (file_name, file_type, width, height, hash, popup_set)
+                VALUES ('b.avif', 'image', 1, 1, x'03', 'triptych');
+             INSERT INTO media (file_name, file_type, width, height, hash)
+                VALUES ('alone.avif', 'image', 1, 1, x'04');
+             INSERT INTO media_tags (media_id, tag_id) VALUES (2, 1);",
+        )
+        .unwrap();
+
+        let (_file, pack) = pack_from_db(&db);
+        let names = |media: Vec<Media>| {
+            media
+                .into_iter()
+                .map(|media| media.name)
+                .collect::<Vec<_>>()
+        };
+
+        // Only one member has the tag, but the whole set comes back.
+        assert_eq!(
+            names(
+                pack.random_set(
+                    MediaTypes::ALL,
+                    Some(vec!["left".to_string()]),
+                    TagMatchMode::Any
+                )
+                .unwrap()
+            ),
+            ["a.avif", "b.avif", "c.avif"]
+        );
+        assert_eq!(
+            names(
+                pack.random_set(MediaTypes::IMAGE, None, TagMatchMode::Any)
+                    .unwrap()
+            ),
+            ["a.avif", "b.avif", "c.avif"]
+        );
+        assert!(
+            pack.random_set(MediaTypes::VIDEO, None, TagMatchMode::Any)
+                .unwrap()
+                .is_empty()
+        );
+
+        let tags = |name: &str| {
+            pack.get_media(name.to_string(), MediaTypes::ALL)
+                .unwrap()
+                .unwrap()
+                .tags
+        };
+        assert_eq!(tags("a.avif"), ["left"]);
+        assert!(tags("alone.avif").is_empty());
+    }
+
+    #[test]
+    fn toasts_are_only_picked_as_toasts() {
+        let db = Connection::open_in_memory().unwrap();
+        migrate(&db).unwrap();
+        db.execute_batch(
+            "INSERT INTO media (file_name, file_type, width, height, hash, category)
+                VALUES ('toast.avif', 'image', 1, 1, x'01', 'toast');
+             INSERT INTO media (file_name, file_type, width, height, hash, category)
+                VALUES ('popup.avif', 'image', 1, 1, x'02', 'wallpaper');
+             INSERT INTO media (file_name, file_type, width, height, hash)
+                VALUES ('plain.avif', 'image', 1, 1, x'03');",
+        )
+        .unwrap();
+
+        let (_file, pack) = pack_from_db(&db);
+
+        let mut names: Vec<_> = pack
+            .random_media_n(MediaTypes::ALL, None, TagMatchMode::Any, 10)
+            .unwrap()
+            .into_iter()
+            .map(|media| media.name)
+            .collect();
+        names.sort();
+        assert_eq!(names, ["plain.avif", "popup.avif"]);
+
+        let toast = pack.random_toast(None, TagMatchMode::Any).unwrap().unwrap();
+        assert_eq!(toast.name, "toast.avif");
+        // Still there when asked for by name.
+        assert!(
+            pack.get_media("toast.avif".to_string(), MediaTypes::ALL)
+                .unwrap()
+                .is_some()
+        );
+    }
+
+    #[test]
+    fn max_rating_leaves_out_rated_media_and_texts() {
+        let db = Connection::open_in_memory().unwrap();
+        migrate(&db).unwrap();
+        db.execute_batch(
+            "INSERT INTO tags (name) VALUES ('wild');
+             INSERT INTO media (file_name, file_type, duration, hash, rating)
+                VALUES ('soft.opus', 'audio', 1, x'01', 0);
+             INSERT INTO media (file_name, file_type, duration, hash, rating)
+                VALUES ('hard.opus', 'audio', 1, x'02', 1);
+             INSERT INTO media (file_name, file_type, duration, hash)
+                VALUES ('tagged.opus', 'audio', 1, x'03');
+             INSERT INTO media_tags (media_id, tag_id) VALUES (3, 1);
+             INSERT INTO texts (kind, text, tags) VALUES ('notification', 'Wild', '[\"wild\"]');",
+        )
+        .unwrap();
+
+        let metadata = Metadata {
+            tag_ratings: [("wild".to_string(), 1)].into(),
+            ..Default::default()
+        };
+        let file = write_pack(&db, &metadata, &[]);
+        let mut pack = MediaPack::open(file.path()).unwrap();
+        let names = |pack: &MediaPack| {
+            pack.list_media(MediaTypes::ALL, None, TagMatchMode::Any)
+                .unwrap()
+                .into_iter()
+                .map(|media| media.name)
+                .collect::<Vec<_>>()
+        };
+        let text = |pack: &MediaPack| {
+            pack.random_text(TextKind::Notification, None, TagMatchMode::Any)
+                .unwrap()
+        };
+
+        assert_eq!(names(&pack), ["soft.opus", "hard.opus", "tagged.opus"]);
+        assert_eq!(text(&pack).as_deref(), Some("Wild"));
+
+        pack.set_max_rating(Some(0));
+        assert_eq!(names(&pack), ["soft.opus"]);
+        assert_eq!(text(&pack), None);
+        assert!(
+            pack.get_media("hard.opus".to_string(), MediaTypes::ALL)
+                .unwrap()
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn milestones_hold_their_tags_back_until_unlocked() {
+        let db = Connection::open_in_memory().unwrap();
+        migrate(&db).unwrap();
+        db.execute_batch(
+            "INSERT INTO tags (name) VALUES ('later');
+             INSERT INTO media (file_name, file_type, duration, hash)
+                VALUES ('now.opus', 'audio', 1, x'01');
+             INSERT INTO media (file_name, file_type, duration, hash)
+                VALUES ('later.opus', 'audio', 1, x'02');
+             INSERT INTO media_tags (media_id, tag_id) VALUES (2, 1);
+             INSERT INTO texts (kind, text, tags) VALUES ('notification', 'Later', '[\"later\"]');",
+        )
+        .unwrap();
+
+        let metadata = Metadata {
+            milestones: vec![Milestone {
+                name: "Ten minutes in".to_string(),
+                stat: GoalStat::Minutes,
+                target: 10,
+                unlocks: vec!["later".to_string()],
+            }],
+            ..Default::default()
+        };
+        let file = write_pack(&db, &metadata, &[]);
+        let pack = MediaPack::open(file.path()).unwrap();
+        let names = |pack: &MediaPack| {
+            pack.list_media(
+                MediaTypes::ALL,
+                Some(vec!["later".to_string()]),
+                TagMatchMode::Any,
+            )
+            .unwrap()
+            .into_iter()
+            .map(|media| media.name)
+            .collect::<Vec<_>>()
+        };
+        let text = |pack: &MediaPack| {
+            pack.random_text(TextKind::Notification, None, TagMatchMode::Any)
+                .unwrap()
+        };
+
+        assert!(names(&pack).is_empty());
+        assert_eq!(text(&pack), None);
+
+        pack.unlock_tags(&["later".to_string()]);
+        assert_eq!(names(&pack), ["later.opus"]);
+        assert_eq!(text(&pack).as_deref(), Some("Later"));
+    }
+
+    #[test]
+    fn excluded_tags_are_left_out() {
+        let db = Connection::open_in_memory().unwrap();
+        migrate(&db).unwrap();
+        db.execute_batch(
+            "INSERT INTO tags (name) VALUES ('kept'), ('excluded');
+             INSERT INTO media (file_name, file_type, duration, hash)
+                VALUES ('kept.opus', 'audio', 1, x'01');
+             INSERT INTO media (file_name, file_type, duration, hash)
+                VALUES ('both.opus', 'audio', 1, x'02');
+             INSERT INTO media_tags (media_id, tag_id) VALUES (1, 1), (2, 1), (2, 2);
+             INSERT INTO texts (kind, text, tags) VALUES ('prompt', 'Excluded', '[\"excluded\"]');",
+        )
+        .unwrap();
+
+        let (_file, mut pack) = pack_from_db(&db);
+        pack.set_excluded_tags(vec!["excluded".to_string()]);
+
+        // Having a wanted tag as well doesn't let a file through.
+        let names: Vec<_> = pack
+            .list_media(
+                MediaTypes::ALL,
+                Some(vec!["kept".to_string()]),
+                TagMatchMode::Any,
+            )
+            .unwrap()
+            .into_iter()
+            .map(|media| media.name)
+            .collect();
+        assert_eq!(names, ["kept.opus"]);
+        assert!(
+            pack.random_text(TextKind::Prompt, None, TagMatchMode::Any)
+                .unwrap()
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn blocked_media_is_left_out() {
+        let db = Connection::open_in_memory().unwrap();
+        migrate(&db).unwrap();
+        db.execute_batch(
+            "INSERT INTO media (file_name, file_type, duration, hash)
+                VALUES ('kept.opus', 'audio', 1, x'01');
+             INSERT INTO media (file_name, file_type, duration, hash)
+                VALUES ('blocked.opus', 'audio', 1, x'02');
+             INSERT INTO media (file_name, file_type, duration, hash)
+                VALUES ('earlier.opus', 'audio', 1, x'0a');",
+        )
+        .unwrap();
+
+        let (_file, pack) = pack_from_db(&db);
+        let names = || {
+            pack.list_media(MediaTypes::ALL, None, TagMatchMode::Any)
+                .unwrap()
+                .into_iter()
+                .map(|media| media.name)
+                .collect::<Vec<_>>()
+        };
+
+        // Blocked in an earlier session.
+        pack.set_blocked(&["0a".to_string()]).unwrap();
+        assert_eq!(names(), ["kept.opus", "blocked.opus"]);
+
+        assert_eq!(pack.block(2).unwrap(), "02");
+        assert_eq!(names(), ["kept.opus"]);
+        assert!(
+            pack.get_media("blocked.opus".to_string(), MediaTypes::ALL)
+                .unwrap()
+                .is_none()
+        );
+    }
+
+    #[tokio::test]
+    async fn quarantined_media_is_left_out() {
+        let db = Connection::open_in_memory().unwrap();
+        migrate(&db).unwrap();
+        db.execute_batch(
+            "INSERT INTO media (file_name, file_type, width, height, duration, hash)
+                VALUES ('fine.webm', 'video', 1, 1, 1, x'01');
+             INSERT INTO media (file_name, file_type, width, height, duration, hash)
+                VALUES ('crashes.webm', 'video', 1, 1, 1, x'02');",
+        )
+        .unwrap();
+
+        let (_file, pack) = pack_from_db(&db);
+        let names = || {
+            pack.list_media(MediaTypes::ALL, None, TagMatchMode::Any)
+                .unwrap()
+                .into_iter()
+                .map(|media| media.name)
+                .collect::<Vec<_>>()
+        };
+
+        // Crashing once gets it another try.
+        pack.quarantine().record_crash(2);
+        assert_eq!(names(), ["fine.webm", "crashes.webm"]);
+
+        pack.quarantine().record_crash(2);
+        assert_eq!(names(), ["fine.webm"]);
+        assert!(pack.get_video_data(2).await.is_err());
+    }
+
+    #[test]
+    fn favorites_are_picked_more_often_or_only() {
+        let db = Connection::open_in_memory().unwrap();
+        migrate(&db).unwrap();
+        db.execute_batch(
+            "INSERT INTO media (file_name, file_type, duration, hash)
+                VALUES ('favorite.opus', 'audio', 1, x'01');
+             INSERT INTO media (file_name, file_type, duration, hash)
+                VALUES ('other.opus', 'audio', 1, x'02');",
+        )
+        .unwrap();
+
+        let file = write_pack(&db, &Metadata::default(), &[]);
+
+        rng::seed(7, rng::Stream::Media);
+        let mut pack = MediaPack::open(file.path()).unwrap();
+        // Holding back recent picks would skew the odds being measured.
+        pack.recent_limit = 0;
+        let favorite_picks = |pack: &MediaPack| {
+            (0..1000)
+                .filter(|_| {
+                    let media = pack
+                        .random_media(MediaTypes::ALL, None, TagMatchMode::Any)
+                        .unwrap()
+                        .unwrap();
+                    media.name == "favorite.opus"
+                })
+                .count()
+        };
+
+        // With no favorites, "only" doesn't leave everything out.
+        pack.set_favorites_mode(FavoritesMode::Only);
+        assert!((400..600).contains(&favorite_picks(&pack)));
+
+        assert_eq!(pack.toggle_favorite(1).unwrap(), ("01".to_string(), true));
+        assert_eq!(favorite_picks(&pack), 1000);
+        // The rest of the pack is still there when asked for by name.
+        assert!(
+            pack.get_media("other.opus".to_string(), MediaTypes::ALL)
+                .unwrap()
+                .is_some()
+        );
+
+        pack.set_favorites_mode(FavoritesMode::Boost);
+        assert!((700..900).contains(&favorite_picks(&pack)));
+
+        pack.set_favorites_mode(FavoritesMode::Normal);
+        assert!((400..600).contains(&favorite_picks(&pack)));
+
+        assert_eq!(pack.toggle_favorite(1).unwrap(), ("01".to_string(), false));
+        pack.set_favorites_mode(FavoritesMode::Only);
+        assert!((400..600).contains(&favorite_picks(&pack)));
+    }
+
+    #[test]
+    fn weighted_media_is_picked_in_proportion() {
+        let db = Connection::open_in_memory().unwrap();
+        migrate(&db).unwrap();
+        db.execute_batch(
+            "INSERT INTO media (file_name, file_type, duration, hash, weight)
+                VALUES ('rare.opus', 'audio', 1, x'01', 0.25);
+             INSERT INTO media (file_name, file_type, duration, hash)
+                VALUES ('common.opus', 'audio', 1, x'02');
+             INSERT INTO media (file_name, file_type, duration, hash, weight)
+                VALUES ('never.opus', 'audio', 1, x'03', 0);",
+        )
+        .unwrap();
+
+        let file = write_pack(&db, &Metadata::default(), &[]);
+
+        rng::seed(7, rng::Stream::Media);
+        let mut pack = MediaPack::open(file.path()).unwrap();
+        // Holding back recent picks would skew the odds being measured.
+        pack.recent_limit = 0;
+
+        let mut picks = HashMap::new();
+        for _ in 0..1000 {
+            let media = pack
+                .random_media(MediaTypes::ALL, None, TagMatchMode::Any)
+                .unwrap()
+                .unwrap();
+            *picks.entry(media.name).or_insert(0) += 1;
+        }
+
+        // 1 in 5 picks, for a weight of 0.25 against 1.
+        assert!((120..280).contains(&picks["rare.opus"]));
+        assert!(!picks.contains_key("never.opus"));
+        assert!(
+            pack.get_media("never.opus".to_string(), MediaTypes::ALL)
+                .unwrap()
+                .is_some()
+        );
+    }
+
+    #[test]
+    fn recently_added_media_is_picked_more_often_at_first() {
+        let db = Connection::open_in_memory().unwrap();
+        migrate(&db).unwrap();
+        db.execute_batch(
+            "INSERT INTO media (file_name, file_type, duration, hash, added_at)
+                VALUES ('new.opus', 'audio', 1, x'01', 1000000000);
+             INSERT INTO media (file_name, file_type, duration, hash, added_at)
+                VALUES ('old.opus', 'audio', 1, x'02', 990000000);
+             INSERT INTO media (file_name, file_type, duration, hash)
+                VALUES ('legacy.opus', 'audio', 1, x'03');",
+        )
+        .unwrap();
+
+        let metadata = Metadata {
+            recency_bias: Some(RecencyBias {
+                boost: 3.0,
+                days: 14,
+                minutes: 1,
+            }),
+            ..Default::default()
+        };
+        let file = write_pack(&db, &metadata, &[]);
+
+        rng::seed(7, rng::Stream::Media);
+        let mut pack = MediaPack::open(file.path()).unwrap();
+        // Holding back recent picks would skew the odds being measured.
+        pack.recent_limit = 0;
+        let new_picks = |pack: &MediaPack| {
+            (0..1000)
+                .filter(|_| {
+                    let media = pack
+                        .random_media(MediaTypes::ALL, None, TagMatchMode::Any)
+                        .unwrap()
+                        .unwrap();
+                    media.name == "new.opus"
+                })
+                .count()
+        };
+
+        // Three times as often as each of the others, which were added too long ago.
+        assert!((500..700).contains(&new_picks(&pack)));
+
+        pack.opened -= Duration::from_secs(61);
+        assert!((250..420).contains(&new_picks(&pack)));
+    }
+
+    #[tokio::test]
+    async fn preloaded_images_are_handed_out_once() {
+        let image = b"not really a png";
+        let offset = DATA_OFFSET;
+
+        let db = Connection::open_in_memory().unwrap();
+        migrate(&db).unwrap();
+        db.execute(
+            "INSERT INTO media (file_name, file_type, width, height, hash, \"offset\", length, codec)
+                VALUES ('a.png', 'image', 1, 1, x'01', ?, ?, 'png')",
+            params![offset, image.len()],
+        )
+        .unwrap();
+
+        let file = write_pack(&db, &Metadata::default(), image);
+        let pack = MediaPack::open(file.path()).unwrap();
+        pack.preload(1).await.unwrap();
+        pack.preload(1).await.unwrap();
+        let preloaded = pack.preloaded.borrow()[0].1.path().to_path_buf();
+        assert_eq!(pack.preloaded.borrow().len(), 1);
+
+        let first = pack.get_image_file(1).await.unwrap();
+        assert_eq!(first.path(), preloaded);
+        assert_eq!(std::fs::read(first.path()).unwrap(), image);
+
+        // Once it's been used, the next one is copied out again.
+        let second = pack.get_image_file(1).await.unwrap();
+        assert_ne!(second.path(), preloaded);
+        assert_eq!(std::fs::read(second.path()).unwrap(), image);
+    }
+
+    #[test]
+    fn random_text_prefers_the_users_language() {
+        let db = Connection::open_in_memory().unwrap();
+        migrate(&db).unwrap();
+        db.execute_batch(
+            "INSERT INTO texts (kind, text, tags) VALUES ('notification', 'Hello', '[\"a\"]');
+             INSERT INTO texts (kind, locale, text, tags)
+                VALUES ('notification', 'de', 'Hallo', '[\"b\"]');
+             INSERT INTO texts (kind, locale, text) VALUES ('notification', 'fr', 'Bonjour');",
+        )
+        .unwrap();
+
+        let (_file, mut pack) = pack_from_db(&db);
+        let text = |pack: &MediaPack, tag: Option<&str>| {
+            pack.random_text(
+                TextKind::Notification,
+                tag.map(|tag| vec![tag.to_string()]),
+                TagMatchMode::Any,
+            )
+            .unwrap()
+        };
+
+        assert_eq!(text(&pack, None).as_deref(), Some("Hello"));
+
+        pack.set_language(Some("de-AT".to_string()));
+        assert_eq!(text(&pack, None).as_deref(), Some("Hallo"));
+        // Falls back to texts without a language, but never to other languages.
+        assert_eq!(text(&pack, Some("a")).as_deref(), Some("Hello"));
+
+        pack.set_language(Some("es".to_string()));
+        assert_eq!(text(&pack, None).as_deref(), Some("Hello"));
+    }
+
+    #[test]
+    fn random_prompt_comes_with_its_theme() {
+        let db = Connection::open_in_memory().unwrap();
+        migrate(&db).unwrap();
+        db.execute_batch(
+            "INSERT INTO media (file_name, file_type, width, height, hash)
+                VALUES ('throne.avif', 'image', 1, 1, x'01');
+             INSERT INTO texts (kind, text, tags, background, text_color)
+                VALUES ('prompt', 'Kneel', '[\"a\"]', 'throne.avif', '#ffd700');
+             INSERT INTO texts (kind, text, tags, background)
+                VALUES ('prompt', 'Missing', '[\"b\"]', 'gone.avif');",
+        )
+        .unwrap();
+
+        let (_file, pack) = pack_from_db(&db);
+        let prompt = |tag: &str| {
+            pack.random_prompt(Some(vec![tag.to_string()]), TagMatchMode::Any)
+                .unwrap()
+                .unwrap()
+        };
+
+        let themed = prompt("a");
+        assert_eq!(themed.text, "Kneel");
+        assert_eq!(themed.background.unwrap().name, "throne.avif");
+        assert_eq!(themed.background_color, None);
+        assert_eq!(themed.text_color.as_deref(), Some("#ffd700"));
+
+        // A background the pack doesn't have is left out rather than failing the prompt.
+        let missing = prompt("b");
+        assert_eq!(missing.text, "Missing");
+        assert!(missing.background.is_none());
+    }
+
+    #[test]
+    fn packs_without_prompts_pop_up_sums() {
+        let db = Connection::open_in_memory().unwrap();
+        migrate(&db).unwrap();
+
+        let metadata = Metadata {
+            prompt_kinds: vec![PromptKind::RepeatText, PromptKind::Arithmetic],
+            ..Default::default()
+        };
+        let file = write_pack(&db, &metadata, &[]);
+        let mut pack = MediaPack::open(file.path()).unwrap();
+        let prompt = pack
+            .random_prompt(None, TagMatchMode::Any)
+            .unwrap()
+            .unwrap();
+        assert_eq!(prompt.kind, PromptKind::Arithmetic);
+        assert!(prompt.answer.unwrap().parse::<i64>().is_ok());
+
+        // Without sums turned on, there's nothing to pop up.
+        pack.metadata.prompt_kinds.clear();
+        assert!(
+            pack.random_prompt(None, TagMatchMode::Any)
+                .unwrap()
+                .is_none()
+        );
+    }
+
     /// End-to-end check of the zero-copy video path: builds a pack file with a real embedded
     /// video (offset/length recorded in the index, exactly like a real pack), then confirms
     /// `get_video_data` produces a `MediaSource` that ffmpeg can actually open and decode --
     /// exercising the same offset/length plumbing `MediaManager`/`VideoDecoder` rely on, not
     /// just the isolated `open_bounded` helper.
-    #[test]
-    fn get_video_data_opens_embedded_clip() {
+    #[tokio::test]
+    async fn get_video_data_opens_embedded_clip() {
         const TEST_CLIP: &[u8] = include_bytes!("test_fixtures/test_clip.mp4");
 
         ffmpeg::init().unwrap();
@@ -538,6 +2336,7 @@ mod tests {
         let metadata_bytes = metadata.to_buf().unwrap();
 
         let mut header = Header::new();
+        header.version = VERSION_1;
         header.metadata_offset = HEADER_SIZE as u64;
         header.metadata_length = metadata_bytes.len() as u64;
         header.index_offset = header.metadata_offset + header.metadata_length;
@@ -563,12 +2362,12 @@ mod tests {
 
         let pack = MediaPack::open(file.path()).unwrap();
         let media = pack
-            .list_media(MediaTypes::VIDEO, None)
+            .list_media(MediaTypes::VIDEO, None, TagMatchMode::Any)
             .unwrap()
             .pop()
             .unwrap();
 
-        let data = pack.get_video_data(media.id).unwrap();
+        let data = pack.get_video_data(media.id).await.unwrap();
         assert_eq!(data.source.offset, video_offset);
         assert_eq!(data.source.length, TEST_CLIP.len() as u64);
 
@@ -1,8 +1,12 @@
 use std::path::{Path, PathBuf};
 
+use shared::encode::VideoContainer;
 use tempfile::NamedTempFile;
 
-use crate::media::bounded_input::{BoundedInput, open_bounded};
+use crate::media::{
+    PackLocation,
+    bounded_input::{BoundedInput, open_bounded},
+};
 
 // #[derive(Debug)]
 // pub enum Media {
@@ -37,14 +41,29 @@ pub struct VideoData {
 /// out to a temp file first.
 #[derive(Debug, Clone)]
 pub struct MediaSource {
-    pub path: PathBuf,
+    pub location: PackLocation,
     pub offset: u64,
     pub length: u64,
+    /// The container the pack recorded for a video, so ffmpeg doesn't have to guess it. `None`
+    /// (audio, and videos added before the container was recorded) leaves ffmpeg to probe.
+    pub container: Option<VideoContainer>,
 }
 
 impl MediaSource {
     pub fn open(&self) -> anyhow::Result<BoundedInput> {
-        open_bounded(&self.path, self.offset, self.length)
+        match open_bounded(&self.location, self.offset, self.length, self.container) {
+            Err(err) if self.container.is_some() => {
+                // The pack says one container but the file is in another, e.g. one edited by
+                // hand. Worth knowing about, but the file itself may well be fine.
+                tracing::warn!(
+                    "Media at offset {} isn't the {:?} the pack recorded ({err}), probing instead",
+                    self.offset,
+                    self.container
+                );
+                open_bounded(&self.location, self.offset, self.length, None)
+            }
+            result => result,
+        }
     }
 }
 
@@ -0,0 +1,346 @@
+//! Counters for keeping an eye on long sessions, served in Prometheus' text format at
+//! `http://127.0.0.1:<port>/metrics` when `metrics_port` is set in the config.
+//!
+//! Everything is counted in [`METRICS`] whether or not it's served, since it's only a few atomic
+//! adds.
+
+use std::{
+    fmt::Write as _,
+    io::{BufRead, BufReader, Write},
+    net::{Ipv4Addr, TcpListener, TcpStream},
+    sync::atomic::{AtomicU64, Ordering},
+    thread,
+    time::Duration,
+};
+
+use anyhow::Result;
+
+use crate::{video::VideoStats, window::WindowType};
+
+pub static METRICS: Metrics = Metrics::new();
+
+/// How long a client gets to send its request before it's dropped, so one that never does can't
+/// hold up everyone else.
+const READ_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The popup kinds counted separately, as they're labelled in the output.
+const POPUP_KINDS: [&str; 6] = ["image", "video", "prompt", "choice", "text", "minigame"];
+
+pub struct Metrics {
+    popups_spawned: [AtomicU64; POPUP_KINDS.len()],
+    open_windows: AtomicU64,
+    audio_started: AtomicU64,
+    images_decoded: AtomicU64,
+    image_decode_nanos: AtomicU64,
+    video_frames_decoded: AtomicU64,
+    video_frames_presented: AtomicU64,
+    video_frames_dropped: AtomicU64,
+    video_frames_late: AtomicU64,
+    video_decode_nanos: AtomicU64,
+    preloaded_bytes: AtomicU64,
+    image_cache_bytes: AtomicU64,
+    image_cache_hits: AtomicU64,
+    image_cache_evictions: AtomicU64,
+}
+
+impl Metrics {
+    const fn new() -> Self {
+        Self {
+            popups_spawned: [const { AtomicU64::new(0) }; POPUP_KINDS.len()],
+            open_windows: AtomicU64::new(0),
+            audio_started: AtomicU64::new(0),
+            images_decoded: AtomicU64::new(0),
+            image_decode_nanos: AtomicU64::new(0),
+            video_frames_decoded: AtomicU64::new(0),
+            video_frames_presented: AtomicU64::new(0),
+            video_frames_dropped: AtomicU64::new(0),
+            video_frames_late: AtomicU64::new(0),
+            video_decode_nanos: AtomicU64::new(0),
+            preloaded_bytes: AtomicU64::new(0),
+            image_cache_bytes: AtomicU64::new(0),
+            image_cache_hits: AtomicU64::new(0),
+            image_cache_evictions: AtomicU64::new(0),
+        }
+    }
+
+    pub fn window_opened(&self, window: &WindowType) {
+        let kind = match window {
+            WindowType::Image(_) => 0,
+            WindowType::Video(_) => 1,
+            WindowType::Prompt(_) => 2,
+            WindowType::Choice(_) => 3,
+            WindowType::Text(_) => 4,
+            WindowType::Minigame(_) => 5,
+        };
+        self.popups_spawned[kind].fetch_add(1, Ordering::Relaxed);
+        self.open_windows.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn window_closed(&self) {
+        let _ = self
+            .open_windows
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |open| {
+                open.checked_sub(1)
+            });
+    }
+
+    pub fn audio_started(&self) {
+        self.audio_started.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn image_decoded(&self, time: Duration) {
+        self.images_decoded.fetch_add(1, Ordering::Relaxed);
+        self.image_decode_nanos
+            .fetch_add(time.as_nanos() as u64, Ordering::Relaxed);
+    }
+
+    /// Adds the frames of a video that has closed.
+    pub fn video_closed(&self, stats: &VideoStats) {
+        self.video_frames_decoded
+            .fetch_add(stats.decoded, Ordering::Relaxed);
+        self.video_frames_presented
+            .fetch_add(stats.presented, Ordering::Relaxed);
+        self.video_frames_dropped
+            .fetch_add(stats.dropped, Ordering::Relaxed);
+        self.video_frames_late
+            .fetch_add(stats.late, Ordering::Relaxed);
+        self.video_decode_nanos
+            .fetch_add(stats.decode_time.as_nanos() as u64, Ordering::Relaxed);
+    }
+
+    /// The size of the images copied out of the pack ahead of time. See
+    /// [`crate::media::pack::MediaPack::preload`].
+    pub fn set_preloaded_bytes(&self, bytes: u64) {
+        self.preloaded_bytes.store(bytes, Ordering::Relaxed);
+    }
+
+    /// The size of the decoded images kept in memory. See [`crate::media::image_cache`].
+    pub fn set_image_cache_bytes(&self, bytes: u64) {
+        self.image_cache_bytes.store(bytes, Ordering::Relaxed);
+    }
+
+    pub fn image_cache_hit(&self) {
+        self.image_cache_hits.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn image_cache_evicted(&self) {
+        self.image_cache_evictions.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Everything in Prometheus' text format.
+    pub fn render(&self) -> String {
+        let count = |counter: &AtomicU64| vec![(String::new(), get(counter).to_string())];
+        let seconds = |nanos: &AtomicU64| {
+            let seconds = Duration::from_nanos(get(nanos)).as_secs_f64();
+            vec![(String::new(), seconds.to_string())]
+        };
+
+        let mut out = String::new();
+        write_metric(
+            &mut out,
+            "lewdware_popups_spawned_total",
+            "counter",
+            "Popups spawned, by kind.",
+            POPUP_KINDS
+                .iter()
+                .zip(&self.popups_spawned)
+                .map(|(kind, spawned)| (format!("{{kind=\"{kind}\"}}"), get(spawned).to_string()))
+                .collect(),
+        );
+        write_metric(
+            &mut out,
+            "lewdware_open_windows",
+            "gauge",
+            "Popups open right now, including video wallpapers.",
+            count(&self.open_windows),
+        );
+        write_metric(
+            &mut out,
+            "lewdware_audio_started_total",
+            "counter",
+            "Audio tracks started.",
+            count(&self.audio_started),
+        );
+        write_metric(
+            &mut out,
+            "lewdware_images_decoded_total",
+            "counter",
+            "Images decoded for popups and wallpapers.",
+            count(&self.images_decoded),
+        );
+        write_metric(
+            &mut out,
+            "lewdware_image_decode_seconds_total",
+            "counter",
+            "Time spent decoding images.",
+            seconds(&self.image_decode_nanos),
+        );
+        write_metric(
+            &mut out,
+            "lewdware_video_frames_total",
+            "counter",
+            "Frames of videos that have closed, by what happened to them.",
+            [
+                ("decoded", &self.video_frames_decoded),
+                ("presented", &self.video_frames_presented),
+                ("dropped", &self.video_frames_dropped),
+                ("late", &self.video_frames_late),
+            ]
+            .into_iter()
+            .map(|(state, frames)| (format!("{{state=\"{state}\"}}"), get(frames).to_string()))
+            .collect(),
+        );
+        write_metric(
+            &mut out,
+            "lewdware_video_decode_seconds_total",
+            "counter",
+            "Time spent decoding the frames of videos that have closed.",
+            seconds(&self.video_decode_nanos),
+        );
+        write_metric(
+            &mut out,
+            "lewdware_preloaded_bytes",
+            "gauge",
+            "Size of the images copied out of the pack ahead of time.",
+            count(&self.preloaded_bytes),
+        );
+        write_metric(
+            &mut out,
+            "lewdware_image_cache_bytes",
+            "gauge",
+            "Size of the decoded images kept in memory.",
+            count(&self.image_cache_bytes),
+        );
+        write_metric(
+            &mut out,
+            "lewdware_image_cache_hits_total",
+            "counter",
+            "Images shown from memory rather than decoded again.",
+            count(&self.image_cache_hits),
+        );
+        write_metric(
+            &mut out,
+            "lewdware_image_cache_evictions_total",
+            "counter",
+            "Decoded images thrown away to make room for others.",
+            count(&self.image_cache_evictions),
+        );
+        if let Some(resident) = resident_memory() {
+            write_metric(
+                &mut out,
+                "process_resident_memory_bytes",
+                "gauge",
+                "Resident memory size in bytes.",
+                vec![(String::new(), resident.to_string())],
+            );
+        }
+
+        out
+    }
+}
+
+fn get(counter: &AtomicU64) -> u64 {
+    counter.load(Ordering::Relaxed)
+}
+
+/// Writes a metric's help and type, then a line for each of its samples, as (labels, value).
+fn write_metric(
+    out: &mut String,
+    name: &str,
+    kind: &str,
+    help: &str,
+    samples: Vec<(String, String)>,
+) {
+    let _ = writeln!(out, "# HELP {name} {help}");
+    let _ = writeln!(out, "# TYPE {name} {kind}");
+    for (labels, value) in samples {
+        let _ = writeln!(out, "{name}{labels} {value}");
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn resident_memory() -> Option<u64> {
+    // The second field is the resident set, in pages.
+    let statm = std::fs::read_to_string("/proc/self/statm").ok()?;
+    let pages: u64 = statm.split_whitespace().nth(1)?.parse().ok()?;
+    let page_size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) };
+    Some(pages * u64::try_from(page_size).ok()?)
+}
+
+#[cfg(not(target_os = "linux"))]
+fn resident_memory() -> Option<u64> {
+    None
+}
+
+/// Serves [`METRICS`] on localhost, on a thread of its own. Only `/metrics` is there; anything
+/// else gets a 404.
+pub fn spawn_metrics_server(port: u16) -> Result<()> {
+    let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, port))?;
+    tracing::info!("Serving metrics at http://127.0.0.1:{port}/metrics");
+
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            match stream {
+                Ok(stream) => {
+                    if let Err(err) = respond(stream) {
+                        tracing::debug!("Error serving metrics: {err}");
+                    }
+                }
+                Err(err) => tracing::warn!("Error accepting a metrics connection: {err}"),
+            }
+        }
+    });
+
+    Ok(())
+}
+
+fn respond(mut stream: TcpStream) -> std::io::Result<()> {
+    stream.set_read_timeout(Some(READ_TIMEOUT))?;
+
+    let mut request_line = String::new();
+    BufReader::new(&stream).read_line(&mut request_line)?;
+    let mut parts = request_line.split_whitespace();
+    let (method, path) = (parts.next(), parts.next());
+
+    let (status, body) = match (method, path) {
+        (Some("GET"), Some("/metrics")) => ("200 OK", METRICS.render()),
+        _ => ("404 Not Found", "Not found\n".to_string()),
+    };
+
+    write!(
+        stream,
+        "HTTP/1.1 {status}\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\n\
+         Connection: close\r\n\r\n{body}",
+        body.len()
+    )?;
+    stream.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_prometheus_text() {
+        let metrics = Metrics::new();
+        metrics.audio_started();
+        metrics.image_decoded(Duration::from_millis(250));
+        metrics.video_closed(&VideoStats {
+            decoded: 10,
+            presented: 8,
+            dropped: 2,
+            late: 1,
+            decode_time: Duration::from_secs(1),
+        });
+        metrics.window_closed();
+
+        let text = metrics.render();
+        assert!(text.contains("# TYPE lewdware_popups_spawned_total counter\n"));
+        assert!(text.contains("lewdware_popups_spawned_total{kind=\"video\"} 0\n"));
+        assert!(text.contains("lewdware_open_windows 0\n"));
+        assert!(text.contains("lewdware_audio_started_total 1\n"));
+        assert!(text.contains("lewdware_image_decode_seconds_total 0.25\n"));
+        assert!(text.contains("lewdware_video_frames_total{state=\"dropped\"} 2\n"));
+        assert!(text.contains("lewdware_video_decode_seconds_total 1\n"));
+    }
+}
@@ -5,7 +5,7 @@ use rand::seq::IndexedRandom;
 use serde::{Deserialize, Serialize};
 use winit::{event_loop::ActiveEventLoop, monitor::MonitorHandle};
 
-use crate::error::MonitorError;
+use crate::{error::MonitorError, rng};
 
 pub struct Monitors {
     disabled: Vec<String>,
@@ -87,11 +87,8 @@ impl Monitors {
     pub fn random(&mut self, event_loop: &ActiveEventLoop) -> Result<Monitor> {
         let monitors = self.list(event_loop);
 
-        let mut rng = rand::rng();
-        monitors
-            .choose(&mut rng)
+        rng::with_rng(|rng| monitors.choose(rng).cloned())
             .ok_or(MonitorError::NoAvailableMonitors)
-            .cloned()
     }
 
     fn refresh(&mut self, event_loop: &ActiveEventLoop) {
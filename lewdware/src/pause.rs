@@ -0,0 +1,104 @@
+//! Why the session is paused. Several things can hold it back at once (the user, the lock screen,
+//! a screen recording, the schedule, going idle and hibernation), and it only carries on once none
+//! of them are. Kept apart from the app so how they interact can be tested without a window system.
+
+/// Everything currently holding the session back.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct PauseReasons {
+    /// Paused from the tray.
+    pub user: bool,
+    pub locked: bool,
+    /// The screen is being recorded or shared. Windows are hidden as well.
+    pub captured: bool,
+    pub off_schedule: bool,
+    pub idle: bool,
+    /// Popups are closed as well, and there's a burst of them on waking up.
+    pub hibernating: bool,
+}
+
+impl PauseReasons {
+    pub fn is_paused(&self) -> bool {
+        self.user
+            || self.locked
+            || self.captured
+            || self.off_schedule
+            || self.idle
+            || self.hibernating
+    }
+
+    /// The message for the tray explaining why the session is paused, or `None` if it isn't.
+    /// With several reasons, the one the user most needs to know about wins: hidden windows
+    /// first, then the one they chose themselves, then the one that'll clear up first.
+    pub fn status(&self) -> Option<&'static str> {
+        if self.captured {
+            Some("lewdware-tray-paused-captured")
+        } else if self.user {
+            Some("lewdware-tray-paused")
+        } else if self.locked {
+            Some("lewdware-tray-paused-locked")
+        } else if self.idle {
+            Some("lewdware-tray-paused-idle")
+        } else if self.off_schedule {
+            Some("lewdware-tray-paused-schedule")
+        } else if self.hibernating {
+            Some("lewdware-tray-hibernating")
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn paused_until_every_reason_clears() {
+        let mut reasons = PauseReasons::default();
+        assert!(!reasons.is_paused());
+        assert_eq!(reasons.status(), None);
+
+        reasons.locked = true;
+        reasons.off_schedule = true;
+        assert!(reasons.is_paused());
+        assert_eq!(reasons.status(), Some("lewdware-tray-paused-locked"));
+
+        // Unlocking outside the schedule stays paused.
+        reasons.locked = false;
+        assert!(reasons.is_paused());
+        assert_eq!(reasons.status(), Some("lewdware-tray-paused-schedule"));
+
+        reasons.off_schedule = false;
+        assert!(!reasons.is_paused());
+    }
+
+    #[test]
+    fn capture_and_user_pauses_are_shown_first() {
+        let mut reasons = PauseReasons {
+            locked: true,
+            idle: true,
+            ..Default::default()
+        };
+        reasons.user = true;
+        assert_eq!(reasons.status(), Some("lewdware-tray-paused"));
+
+        reasons.captured = true;
+        assert_eq!(reasons.status(), Some("lewdware-tray-paused-captured"));
+    }
+
+    #[test]
+    fn other_reasons_are_shown_over_hibernation() {
+        let mut reasons = PauseReasons {
+            hibernating: true,
+            ..Default::default()
+        };
+        assert!(reasons.is_paused());
+        assert_eq!(reasons.status(), Some("lewdware-tray-hibernating"));
+
+        // Waking up while the screen's locked stays paused.
+        reasons.locked = true;
+        assert_eq!(reasons.status(), Some("lewdware-tray-paused-locked"));
+        reasons.hibernating = false;
+        assert!(reasons.is_paused());
+    }
+}
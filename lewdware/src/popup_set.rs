@@ -0,0 +1,129 @@
+//! Layout for popup sets: media grouped with `set` in their sidecars, which is spawned together
+//! in a row on one monitor (e.g. the three panels of a triptych).
+
+use crate::window::HEADER_HEIGHT;
+
+/// Where the windows in a set go, relative to the top-left corner of the set.
+#[derive(Debug, PartialEq)]
+pub struct SetLayout {
+    pub windows: Vec<SetWindow>,
+    /// The size of the whole set, including window decorations and the gaps between windows.
+    pub outer_width: u32,
+    pub outer_height: u32,
+}
+
+/// One window in a [`SetLayout`]. `width` and `height` are the size of the media, not counting
+/// decorations.
+#[derive(Debug, PartialEq)]
+pub struct SetWindow {
+    pub x: i32,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Lays out windows for media of the given sizes side by side, all the same height so that they
+/// line up.
+///
+/// `height` is the height of the media in each window. If it isn't given, the row is as tall as
+/// the shortest media (so nothing is scaled up), but at most half as tall as the monitor. Either
+/// way it's shrunk if the row wouldn't fit on the monitor.
+pub fn arrange(
+    media: &[(u32, u32)],
+    height: Option<u32>,
+    decorations: bool,
+    gap: u32,
+    monitor_width: u32,
+    monitor_height: u32,
+) -> SetLayout {
+    let (border_width, border_height) = if decorations {
+        (2, HEADER_HEIGHT + 2)
+    } else {
+        (0, 0)
+    };
+
+    // The width of the row for each pixel of height, and the space taken up by borders and gaps.
+    let aspect_ratios: Vec<f64> = media
+        .iter()
+        .map(|&(width, height)| width as f64 / height.max(1) as f64)
+        .collect();
+    let count = media.len() as u32;
+    let fixed_width = count * border_width + count.saturating_sub(1) * gap;
+
+    let height = height.map(f64::from).unwrap_or_else(|| {
+        let shortest = media.iter().map(|&(_, height)| height).min().unwrap_or(0);
+        (shortest as f64).min(monitor_height as f64 / 2.0)
+    });
+    let max_height = (monitor_width.saturating_sub(fixed_width) as f64
+        / aspect_ratios.iter().sum::<f64>())
+    .min(monitor_height.saturating_sub(border_height) as f64);
+    let height = height.min(max_height).max(1.0);
+
+    let mut windows = Vec::with_capacity(media.len());
+    let mut x = 0;
+    for aspect_ratio in aspect_ratios {
+        let width = (aspect_ratio * height).round().max(1.0) as u32;
+        windows.push(SetWindow {
+            x,
+            width,
+            height: height.round() as u32,
+        });
+        x += (width + border_width + gap) as i32;
+    }
+
+    SetLayout {
+        windows,
+        outer_width: (x as u32).saturating_sub(gap),
+        outer_height: height.round() as u32 + border_height,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lines_windows_up() {
+        let layout = arrange(
+            &[(100, 200), (200, 400), (300, 200)],
+            None,
+            false,
+            0,
+            1920,
+            1080,
+        );
+        assert_eq!(
+            layout.windows,
+            [
+                SetWindow {
+                    x: 0,
+                    width: 100,
+                    height: 200
+                },
+                SetWindow {
+                    x: 100,
+                    width: 100,
+                    height: 200
+                },
+                SetWindow {
+                    x: 200,
+                    width: 300,
+                    height: 200
+                },
+            ]
+        );
+        assert_eq!((layout.outer_width, layout.outer_height), (500, 200));
+
+        let layout = arrange(&[(100, 100), (100, 100)], Some(50), false, 10, 1920, 1080);
+        assert_eq!(layout.windows[1].x, 60);
+        assert_eq!((layout.outer_width, layout.outer_height), (110, 50));
+    }
+
+    #[test]
+    fn shrinks_to_fit_the_monitor() {
+        let layout = arrange(&[(2000, 1000), (2000, 1000)], None, false, 0, 1920, 1080);
+        assert_eq!((layout.outer_width, layout.outer_height), (1920, 480));
+
+        let layout = arrange(&[(100, 100)], Some(2000), true, 0, 1920, 1080);
+        assert_eq!(layout.outer_height, 1080);
+    }
+}
@@ -0,0 +1,167 @@
+//! Watches whether a laptop is running on its battery, so the session can cut back while it is
+//! (see [`BatterySaver`]). Like the lock watcher, each platform is polled from a plain thread.
+
+use std::{thread, time::Duration};
+
+use shared::user_config::BatterySaver;
+use winit::event_loop::EventLoopProxy;
+
+use crate::app::UserEvent;
+
+const POLL_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Whether the machine is running on its battery, and how full the battery is, if known.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct PowerStatus {
+    pub on_battery: bool,
+    pub percent: Option<u8>,
+}
+
+/// Whether the battery saver should be on, given the user's thresholds. If the battery level
+/// can't be read, being unplugged is enough.
+pub fn should_save(battery_saver: &BatterySaver, status: PowerStatus) -> bool {
+    battery_saver.enabled
+        && status.on_battery
+        && match (battery_saver.below_percent, status.percent) {
+            (Some(threshold), Some(percent)) => percent <= threshold,
+            _ => true,
+        }
+}
+
+/// Starts a thread that sends [`UserEvent::SetBatterySaver`] whenever the battery saver should
+/// be turned on or off. Does nothing if the battery saver is disabled.
+pub fn spawn_power_watcher(
+    event_loop_proxy: EventLoopProxy<UserEvent>,
+    battery_saver: BatterySaver,
+) {
+    if !battery_saver.enabled {
+        return;
+    }
+
+    thread::spawn(move || {
+        let mut on = false;
+
+        loop {
+            let status = power_status();
+            let now_on = should_save(&battery_saver, status);
+
+            if now_on != on {
+                on = now_on;
+                tracing::info!(
+                    "Battery saver {} ({status:?})",
+                    if on { "on" } else { "off" }
+                );
+
+                if event_loop_proxy
+                    .send_event(UserEvent::SetBatterySaver { on })
+                    .is_err()
+                {
+                    // The event loop has shut down.
+                    break;
+                }
+            }
+
+            thread::sleep(POLL_INTERVAL);
+        }
+    });
+}
+
+/// Looks through `/sys/class/power_supply` for a system battery (not e.g. a wireless mouse,
+/// which has its scope set to `Device`) that's discharging.
+#[cfg(target_os = "linux")]
+fn power_status() -> PowerStatus {
+    use std::fs;
+
+    let Ok(entries) = fs::read_dir("/sys/class/power_supply") else {
+        return PowerStatus::default();
+    };
+
+    let read = |path: &std::path::Path, name: &str| {
+        fs::read_to_string(path.join(name))
+            .map(|value| value.trim().to_string())
+            .unwrap_or_default()
+    };
+
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if read(&path, "type") != "Battery" || read(&path, "scope") == "Device" {
+            continue;
+        }
+
+        if read(&path, "status") == "Discharging" {
+            return PowerStatus {
+                on_battery: true,
+                percent: read(&path, "capacity").parse().ok(),
+            };
+        }
+    }
+
+    PowerStatus::default()
+}
+
+/// `pmset -g batt` prints e.g. `Now drawing from 'Battery Power'`, followed by a line for each
+/// battery containing `85%; discharging;`.
+#[cfg(target_os = "macos")]
+fn power_status() -> PowerStatus {
+    use std::process::Command;
+
+    let Ok(output) = Command::new("pmset").args(["-g", "batt"]).output() else {
+        return PowerStatus::default();
+    };
+    let output = String::from_utf8_lossy(&output.stdout);
+
+    PowerStatus {
+        on_battery: output.contains("'Battery Power'"),
+        percent: output
+            .split_whitespace()
+            .find_map(|word| word.strip_suffix("%;")?.parse().ok()),
+    }
+}
+
+#[cfg(target_os = "windows")]
+fn power_status() -> PowerStatus {
+    use windows::Win32::System::Power::{GetSystemPowerStatus, SYSTEM_POWER_STATUS};
+
+    let mut status = SYSTEM_POWER_STATUS::default();
+    if unsafe { GetSystemPowerStatus(&mut status) }.is_err() {
+        return PowerStatus::default();
+    }
+
+    PowerStatus {
+        // An `ACLineStatus` of 0 is offline (1 is online and 255 unknown), and a `BatteryFlag` of
+        // 128 means there's no system battery.
+        on_battery: status.ACLineStatus == 0 && status.BatteryFlag != 128,
+        // 255 means the level is unknown.
+        percent: (status.BatteryLifePercent <= 100).then_some(status.BatteryLifePercent),
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+fn power_status() -> PowerStatus {
+    PowerStatus::default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn respects_thresholds() {
+        let unplugged = |percent| PowerStatus {
+            on_battery: true,
+            percent,
+        };
+        let mut battery_saver = BatterySaver::default();
+
+        assert!(should_save(&battery_saver, unplugged(Some(90))));
+        assert!(!should_save(&battery_saver, PowerStatus::default()));
+
+        battery_saver.below_percent = Some(30);
+        assert!(!should_save(&battery_saver, unplugged(Some(90))));
+        assert!(should_save(&battery_saver, unplugged(Some(30))));
+        assert!(should_save(&battery_saver, unplugged(None)));
+
+        battery_saver.enabled = false;
+        assert!(!should_save(&battery_saver, unplugged(Some(10))));
+    }
+}
@@ -0,0 +1,88 @@
+//! Random numbers for the engine. Every random choice goes through a generator here rather than
+//! `rand::rng()`, so that setting `seed` in the config (or passing `--seed`) makes the choices in
+//! a session come out the same each time it's run.
+//!
+//! Each thread has its own generator. The main thread picks where popups go, the Lua thread where
+//! popup sets go, and the media thread picks which media to show (through the `random_order()`
+//! and `random_weighted()` SQL functions). A blended-in pack's media thread has a generator of
+//! its own, and so does the thread picking how long each hibernation lasts.
+
+use std::{
+    cell::RefCell,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use rand::{SeedableRng, make_rng, rngs::StdRng};
+
+thread_local! {
+    static RNG: RefCell<StdRng> = RefCell::new(make_rng());
+}
+
+/// Which thread a generator belongs to. Mixed into the seed so threads don't make the same
+/// choices as each other.
+#[derive(Clone, Copy)]
+pub enum Stream {
+    Main = 1,
+    Media = 2,
+    Lua = 3,
+    BlendedMedia = 4,
+    Hibernation = 5,
+}
+
+/// Seeds the calling thread's generator from the session seed.
+pub fn seed(seed: u64, stream: Stream) {
+    let seed = seed ^ (stream as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15);
+    RNG.with_borrow_mut(|rng| *rng = StdRng::seed_from_u64(seed));
+}
+
+/// A seed for the day `now` falls on (in UTC), the same for everyone that day. See
+/// `daily_shuffle` in the config.
+pub fn daily_seed(now: SystemTime) -> u64 {
+    let day = now.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs() / (24 * 60 * 60);
+    // Days next to each other would otherwise give seeds that only differ in their last bit.
+    let mut seed = day.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    seed = (seed ^ (seed >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    seed = (seed ^ (seed >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    seed ^ (seed >> 31)
+}
+
+pub fn with_rng<T>(f: impl FnOnce(&mut StdRng) -> T) -> T {
+    RNG.with_borrow_mut(f)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use rand::RngExt;
+
+    use super::*;
+
+    #[test]
+    fn same_seed_gives_same_choices() {
+        let draw = || {
+            with_rng(|rng| {
+                (0..8)
+                    .map(|_| rng.random_range(0..1000))
+                    .collect::<Vec<u32>>()
+            })
+        };
+
+        seed(42, Stream::Main);
+        let first = draw();
+        seed(42, Stream::Main);
+        assert_eq!(draw(), first);
+
+        seed(42, Stream::Media);
+        assert_ne!(draw(), first);
+    }
+
+    #[test]
+    fn daily_seed_changes_at_midnight_utc() {
+        let day = |secs| daily_seed(UNIX_EPOCH + Duration::from_secs(secs));
+        let midnight = 20_000 * 24 * 60 * 60;
+
+        assert_eq!(day(midnight), day(midnight + 24 * 60 * 60 - 1));
+        assert_ne!(day(midnight - 1), day(midnight));
+    }
+}
@@ -0,0 +1,50 @@
+//! Keeps the session to the hours set in the config (see [`Schedule`]). Outside them the session
+//! is paused, the same way it is while the screen is locked, and picks up again when the next
+//! window opens. Like the lock watcher, the clock is polled from a plain thread.
+
+use std::{thread, time::Duration};
+
+use chrono::{Datelike, Local, Timelike};
+use shared::user_config::Schedule;
+use winit::event_loop::EventLoopProxy;
+
+use crate::app::UserEvent;
+
+const POLL_INTERVAL: Duration = Duration::from_secs(15);
+
+/// Starts a thread that sends [`UserEvent::SetScheduled`] whenever the session goes in or out of
+/// its schedule. Does nothing if the schedule is off.
+pub fn spawn_schedule_watcher(event_loop_proxy: EventLoopProxy<UserEvent>, schedule: Schedule) {
+    if !schedule.enabled {
+        return;
+    }
+
+    thread::spawn(move || {
+        let mut active = true;
+
+        loop {
+            let now = Local::now();
+            let minute = (now.hour() * 60 + now.minute()) as u16;
+            let now_active =
+                schedule.is_active(now.weekday().num_days_from_monday() as usize, minute);
+
+            if now_active != active {
+                active = now_active;
+                tracing::info!(
+                    "{} the session's schedule",
+                    if active { "Inside" } else { "Outside" }
+                );
+
+                if event_loop_proxy
+                    .send_event(UserEvent::SetScheduled { active })
+                    .is_err()
+                {
+                    // The event loop has shut down.
+                    break;
+                }
+            }
+
+            thread::sleep(POLL_INTERVAL);
+        }
+    });
+}
@@ -0,0 +1,175 @@
+//! Watches for the screen being recorded or shared, so the session can be paused and everything
+//! hidden before it ends up in a video call or a recording (see `pause_while_recording` in the
+//! config). Like the lock watcher, each platform is polled from a plain thread.
+//!
+//! This is best effort: not every way of capturing the screen can be seen from another process,
+//! so users shouldn't rely on it alone.
+
+use std::{thread, time::Duration};
+
+use winit::event_loop::EventLoopProxy;
+
+use crate::app::UserEvent;
+
+const POLL_INTERVAL: Duration = Duration::from_secs(3);
+
+/// Starts a thread that sends [`UserEvent::SetCaptured`] whenever the screen starts or stops
+/// being captured. Does nothing unless `enabled`.
+pub fn spawn_capture_watcher(event_loop_proxy: EventLoopProxy<UserEvent>, enabled: bool) {
+    if !enabled {
+        return;
+    }
+
+    thread::spawn(move || {
+        let mut captured = false;
+
+        loop {
+            let now_captured = is_captured();
+
+            if now_captured != captured {
+                captured = now_captured;
+                tracing::info!(
+                    "Screen capture {}",
+                    if captured { "started" } else { "stopped" }
+                );
+
+                if event_loop_proxy
+                    .send_event(UserEvent::SetCaptured { captured })
+                    .is_err()
+                {
+                    // The event loop has shut down.
+                    break;
+                }
+            }
+
+            thread::sleep(POLL_INTERVAL);
+        }
+    });
+}
+
+/// Screen sharing on Wayland goes through the desktop portal, which hands out a PipeWire stream
+/// of the screen. Those show up in `pw-dump` as video nodes that aren't backed by a device (unlike
+/// cameras), and are running while something is reading from them.
+#[cfg(target_os = "linux")]
+fn is_captured() -> bool {
+    use std::process::Command;
+
+    Command::new("pw-dump")
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .and_then(|output| serde_json::from_slice(&output.stdout).ok())
+        .is_some_and(|dump| has_running_screencast(&dump))
+}
+
+#[cfg(any(target_os = "linux", test))]
+fn has_running_screencast(dump: &serde_json::Value) -> bool {
+    let Some(objects) = dump.as_array() else {
+        return false;
+    };
+
+    objects.iter().any(|object| {
+        let info = &object["info"];
+        let Some(props) = info["props"].as_object() else {
+            return false;
+        };
+
+        object["type"] == "PipeWire:Interface:Node"
+            && info["state"] == "running"
+            && matches!(
+                props.get("media.class").and_then(|class| class.as_str()),
+                Some("Video/Source" | "Stream/Output/Video")
+            )
+            && !props
+                .keys()
+                .any(|key| key.starts_with("api.") || key == "device.api")
+    })
+}
+
+/// Windows has no way of asking whether anything is using Windows Graphics Capture, so this looks
+/// for the recorders and screen sharing helpers that use it instead.
+#[cfg(target_os = "windows")]
+fn is_captured() -> bool {
+    use std::{os::windows::process::CommandExt, process::Command};
+
+    Command::new("tasklist")
+        .args(["/fo", "csv", "/nh"])
+        // CREATE_NO_WINDOW, so a console doesn't flash up every few seconds.
+        .creation_flags(0x08000000)
+        .output()
+        .is_ok_and(|output| running_recorder(&String::from_utf8_lossy(&output.stdout)).is_some())
+}
+
+/// Programs that only run while they're recording or sharing the screen, or (for dedicated
+/// recorders) that people only keep open to do so.
+#[cfg(any(target_os = "windows", test))]
+const RECORDERS: [&str; 6] = [
+    "obs64.exe",
+    "obs32.exe",
+    "Streamlabs OBS.exe",
+    "XSplit.Core.exe",
+    "bdcam.exe",
+    // Zoom's screen sharing helper.
+    "CptHost.exe",
+];
+
+/// The first recorder in `tasklist /fo csv /nh` output, whose lines start with the quoted image
+/// name.
+#[cfg(any(target_os = "windows", test))]
+fn running_recorder(tasklist: &str) -> Option<&'static str> {
+    tasklist
+        .lines()
+        .filter_map(|line| line.strip_prefix('"')?.split('"').next())
+        .find_map(|name| {
+            RECORDERS
+                .into_iter()
+                .find(|recorder| recorder.eq_ignore_ascii_case(name))
+        })
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "windows")))]
+fn is_captured() -> bool {
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn finds_running_screencasts() {
+        let node = |state: &str, props: serde_json::Value| {
+            json!({
+                "type": "PipeWire:Interface:Node",
+                "info": { "state": state, "props": props },
+            })
+        };
+        let camera = node(
+            "running",
+            json!({ "media.class": "Video/Source", "api.v4l2.path": "/dev/video0" }),
+        );
+        let screencast = |state| {
+            node(
+                state,
+                json!({ "media.class": "Video/Source", "node.name": "xdpw_stream" }),
+            )
+        };
+
+        let idle = json!([camera, screencast("idle")]);
+        let running = json!([camera, screencast("running")]);
+        assert!(!has_running_screencast(&idle));
+        assert!(has_running_screencast(&running));
+        assert!(!has_running_screencast(&json!({})));
+    }
+
+    #[test]
+    fn finds_running_recorders() {
+        let tasklist = "\"System\",\"4\",\"Services\",\"0\",\"144 K\"\r\n\
+                        \"OBS64.EXE\",\"9120\",\"Console\",\"1\",\"210,512 K\"\r\n";
+
+        assert_eq!(running_recorder(tasklist), Some("obs64.exe"));
+        assert_eq!(running_recorder("\"explorer.exe\",\"1\""), None);
+    }
+}
@@ -0,0 +1,139 @@
+//! Watches for the screen being locked or the screensaver coming on, so the session can be paused
+//! while nobody can see it. Otherwise popups keep piling up behind the lock screen, and are all
+//! waiting there when the user comes back.
+//!
+//! None of the platforms have a simple way of being told about this from a plain thread, so each
+//! one is polled instead.
+
+use std::{thread, time::Duration};
+
+use winit::event_loop::EventLoopProxy;
+
+use crate::app::UserEvent;
+
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Starts a thread that sends [`UserEvent::SetLocked`] whenever the screen is locked or
+/// unlocked.
+pub fn spawn_lock_watcher(event_loop_proxy: EventLoopProxy<UserEvent>) {
+    thread::spawn(move || {
+        let mut locked = false;
+
+        loop {
+            let now_locked = is_locked();
+
+            if now_locked != locked {
+                locked = now_locked;
+                tracing::info!("Screen {}", if locked { "locked" } else { "unlocked" });
+
+                if event_loop_proxy
+                    .send_event(UserEvent::SetLocked { locked })
+                    .is_err()
+                {
+                    // The event loop has shut down.
+                    break;
+                }
+            }
+
+            thread::sleep(POLL_INTERVAL);
+        }
+    });
+}
+
+/// Asks logind whether the session is locked, and the desktop whether its screensaver is on.
+/// Desktops that don't set logind's `LockedHint` usually still implement the screensaver
+/// interface.
+#[cfg(target_os = "linux")]
+fn is_locked() -> bool {
+    use std::process::Command;
+
+    let session = std::env::var("XDG_SESSION_ID").unwrap_or_else(|_| "auto".to_string());
+    let locked_hint = Command::new("loginctl")
+        .args(["show-session", &session, "--property=LockedHint", "--value"])
+        .output()
+        .is_ok_and(|output| output.status.success() && output.stdout.trim_ascii() == b"yes");
+
+    locked_hint
+        || Command::new("dbus-send")
+            .args([
+                "--session",
+                "--print-reply=literal",
+                "--dest=org.freedesktop.ScreenSaver",
+                "/org/freedesktop/ScreenSaver",
+                "org.freedesktop.ScreenSaver.GetActive",
+            ])
+            .output()
+            .is_ok_and(|output| {
+                output.status.success() && output.stdout.trim_ascii().ends_with(b"true")
+            })
+}
+
+/// The console user's session has `CGSSessionScreenIsLocked` set while the screen is locked, and
+/// the screensaver runs as its own process.
+#[cfg(target_os = "macos")]
+fn is_locked() -> bool {
+    use std::process::Command;
+
+    let screen_locked = Command::new("ioreg")
+        .args(["-n", "Root", "-d1", "-a"])
+        .output()
+        .is_ok_and(|output| {
+            let output = String::from_utf8_lossy(&output.stdout);
+            output
+                .split("<key>CGSSessionScreenIsLocked</key>")
+                .nth(1)
+                .is_some_and(|rest| rest.trim_start().starts_with("<true/>"))
+        });
+
+    screen_locked
+        || Command::new("pgrep")
+            .args(["-x", "ScreenSaverEngine"])
+            .output()
+            .is_ok_and(|output| output.status.success())
+}
+
+/// While the workstation is locked, the secure desktop has the input, so we aren't allowed to
+/// open the input desktop.
+#[cfg(target_os = "windows")]
+fn is_locked() -> bool {
+    use windows::{
+        Win32::{
+            System::StationsAndDesktops::{
+                CloseDesktop, DESKTOP_CONTROL_FLAGS, DESKTOP_SWITCHDESKTOP, OpenInputDesktop,
+            },
+            UI::WindowsAndMessaging::{
+                SPI_GETSCREENSAVERRUNNING, SYSTEM_PARAMETERS_INFO_UPDATE_FLAGS,
+                SystemParametersInfoW,
+            },
+        },
+        core::BOOL,
+    };
+
+    let workstation_locked =
+        match unsafe { OpenInputDesktop(DESKTOP_CONTROL_FLAGS(0), false, DESKTOP_SWITCHDESKTOP) } {
+            Ok(desktop) => {
+                let _ = unsafe { CloseDesktop(desktop) };
+                false
+            }
+            Err(_) => true,
+        };
+
+    let mut screensaver_running = BOOL(0);
+    let screensaver_running = unsafe {
+        SystemParametersInfoW(
+            SPI_GETSCREENSAVERRUNNING,
+            0,
+            Some(&mut screensaver_running as *mut BOOL as *mut _),
+            SYSTEM_PARAMETERS_INFO_UPDATE_FLAGS(0),
+        )
+    }
+    .is_ok()
+        && screensaver_running.as_bool();
+
+    workstation_locked || screensaver_running
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+fn is_locked() -> bool {
+    false
+}
@@ -0,0 +1,123 @@
+//! Layout for toasts: small images with the `toast` category in their sidecars, which are shown in
+//! one corner of a monitor, stacked away from it, and close themselves after a few seconds.
+
+use serde::{Deserialize, Serialize};
+
+/// The corner of the monitor a stack of toasts grows from.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Corner {
+    #[serde(rename = "top-left")]
+    TopLeft,
+    #[serde(rename = "top-right")]
+    TopRight,
+    #[serde(rename = "bottom-left")]
+    BottomLeft,
+    #[serde(rename = "bottom-right")]
+    #[default]
+    BottomRight,
+}
+
+impl Corner {
+    /// Where a toast of the given size goes, `offset` pixels along the stack from the corner and
+    /// `margin` pixels in from the edges of the monitor.
+    pub fn position(
+        self,
+        offset: u32,
+        (width, height): (u32, u32),
+        margin: u32,
+        (monitor_width, monitor_height): (u32, u32),
+    ) -> (i32, i32) {
+        let left = margin as i32;
+        let right = monitor_width as i32 - margin as i32 - width as i32;
+        let top = (margin + offset) as i32;
+        let bottom = monitor_height as i32 - (margin + offset + height) as i32;
+
+        match self {
+            Corner::TopLeft => (left, top),
+            Corner::TopRight => (right, top),
+            Corner::BottomLeft => (left, bottom),
+            Corner::BottomRight => (right, bottom),
+        }
+    }
+}
+
+/// The space a toast takes up in its stack: how far its near edge is from the corner, and its
+/// height.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Slot {
+    pub offset: u32,
+    pub height: u32,
+}
+
+/// Finds room for a toast `height` tall in a stack of toasts that's `length` pixels long: the gap
+/// closest to the corner that fits it with `gap` pixels either side. `None` if the stack is full.
+pub fn stack_offset(stack: &[Slot], height: u32, gap: u32, length: u32) -> Option<u32> {
+    let mut stack = stack.to_vec();
+    stack.sort_by_key(|slot| slot.offset);
+
+    let mut offset = 0;
+    for slot in stack {
+        if offset + height + gap <= slot.offset {
+            break;
+        }
+        offset = offset.max(slot.offset + slot.height + gap);
+    }
+
+    (offset + height <= length).then_some(offset)
+}
+
+/// The size of a toast for an image of the given size: `height` tall, unless that would make it
+/// wider than two fifths of the monitor.
+pub fn size(media_width: u32, media_height: u32, height: u32, monitor_width: u32) -> (u32, u32) {
+    let aspect_ratio = media_width as f64 / media_height.max(1) as f64;
+    let max_width = monitor_width as f64 * 0.4;
+
+    let width = (height as f64 * aspect_ratio).min(max_width);
+    let height = (width / aspect_ratio).round().max(1.0) as u32;
+
+    (width.round().max(1.0) as u32, height)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stacks_from_the_corner() {
+        let slot = |offset, height| Slot { offset, height };
+
+        assert_eq!(stack_offset(&[], 100, 10, 1000), Some(0));
+        assert_eq!(stack_offset(&[slot(0, 100)], 100, 10, 1000), Some(110));
+        // Fills the gap left by a toast that has closed.
+        assert_eq!(
+            stack_offset(&[slot(220, 100), slot(0, 100)], 100, 10, 1000),
+            Some(110)
+        );
+        // Too small a gap.
+        assert_eq!(
+            stack_offset(&[slot(0, 100), slot(200, 100)], 100, 10, 1000),
+            Some(310)
+        );
+        assert_eq!(stack_offset(&[slot(0, 900)], 100, 10, 1000), None);
+    }
+
+    #[test]
+    fn places_toasts_in_corners() {
+        let monitor = (1920, 1080);
+
+        assert_eq!(
+            Corner::TopLeft.position(0, (200, 100), 16, monitor),
+            (16, 16)
+        );
+        assert_eq!(
+            Corner::BottomRight.position(110, (200, 100), 16, monitor),
+            (1704, 854)
+        );
+    }
+
+    #[test]
+    fn limits_toast_width() {
+        assert_eq!(size(400, 200, 100, 1920), (200, 100));
+        assert_eq!(size(4000, 100, 100, 1000), (400, 10));
+    }
+}
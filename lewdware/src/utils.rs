@@ -1,24 +1,78 @@
-use std::{collections::HashSet, path::PathBuf, thread};
+use std::{collections::HashSet, path::PathBuf, sync::Mutex, thread};
 
 use anyhow::Result;
-use shared::user_config::{Key, Modifiers};
-use winit::event_loop::EventLoopProxy;
+use rand::RngExt;
+use shared::{
+    locale::{tr, tr_args},
+    user_config::{Key, Modifiers},
+};
+use winit::{dpi::LogicalPosition, event_loop::EventLoopProxy, monitor::MonitorHandle};
 
 use crate::{
+    activity,
     app::UserEvent,
-    lua::{Coord, TextFont},
-    text_font,
+    lua::{Coord, DEFAULT_INTENSITY, MAX_INTENSITY, TextFont},
+    rng, text_font,
 };
 
-// Create a tray icon that can be used to close the program
+#[cfg(not(target_os = "linux"))]
+thread_local! {
+    // The intensity dial's entries, indexed by level. Kept so that `update_tray_intensity` can
+    // make them behave like radio buttons, since muda has no radio menu items.
+    static INTENSITY_ITEMS: std::cell::RefCell<Vec<tray_icon::menu::CheckMenuItem>> =
+        const { std::cell::RefCell::new(Vec::new()) };
+    // Kept so that `update_tray_paused` can switch it between pausing and resuming.
+    static PAUSE_ITEM: std::cell::RefCell<Option<tray_icon::menu::MenuItem>> =
+        const { std::cell::RefCell::new(None) };
+    // Kept so that `set_tray_status` can update its tooltip. Never dropped, since the icon
+    // disappears as soon as it is.
+    static TRAY_ICON: std::cell::RefCell<Option<tray_icon::TrayIcon>> =
+        const { std::cell::RefCell::new(None) };
+}
+
+/// Where the hotkey listener last saw the cursor, in the coordinates rdev reports (physical pixels,
+/// or points on macOS). Only tracked when popups have to keep away from it.
+static CURSOR_POSITION: Mutex<Option<(f64, f64)>> = Mutex::new(None);
+
+#[cfg(target_os = "linux")]
+static TRAY_HANDLE: std::sync::OnceLock<ksni::Handle<LewdwareTray>> = std::sync::OnceLock::new();
+
+// Create a tray icon that can be used to close the program and set the intensity
 #[cfg(not(target_os = "linux"))]
 pub fn create_tray_icon(event_loop_proxy: EventLoopProxy<UserEvent>) -> Result<()> {
     use tray_icon::{
         Icon, TrayIconBuilder,
-        menu::{Menu, MenuEvent, MenuItem},
+        menu::{CheckMenuItem, IsMenuItem, Menu, MenuEvent, MenuItem, Submenu},
     };
 
-    let tray_menu = Menu::with_items(&[&MenuItem::new("Panic", true, None)])?;
+    let intensity_items: Vec<_> = (0..=MAX_INTENSITY)
+        .map(|level| {
+            CheckMenuItem::with_id(
+                format!("intensity-{level}"),
+                level.to_string(),
+                true,
+                level == DEFAULT_INTENSITY,
+                None,
+            )
+        })
+        .collect();
+    let intensity_refs: Vec<&dyn IsMenuItem> = intensity_items
+        .iter()
+        .map(|item| item as &dyn IsMenuItem)
+        .collect();
+    let intensity_menu = Submenu::with_items(tr("lewdware-tray-intensity"), true, &intensity_refs)?;
+    INTENSITY_ITEMS.with_borrow_mut(|items| *items = intensity_items);
+
+    let pause_item = MenuItem::with_id("pause", tr("lewdware-tray-pause"), true, None);
+
+    let tray_menu = Menu::with_items(&[
+        &intensity_menu,
+        &pause_item,
+        &MenuItem::with_id("credits", tr("lewdware-tray-credits"), true, None),
+        &MenuItem::with_id("panic", tr("lewdware-tray-panic"), true, None),
+        &MenuItem::with_id("wipe", tr("lewdware-tray-wipe"), true, None),
+    ])?;
+    PAUSE_ITEM.with_borrow_mut(|item| *item = Some(pause_item));
 
     #[cfg(target_os = "windows")]
     let icon_bytes = include_bytes!("../assets/tray-windows.ico");
@@ -35,30 +89,122 @@ pub fn create_tray_icon(event_loop_proxy: EventLoopProxy<UserEvent>) -> Result<(
         .with_icon_as_template(cfg!(target_vendor = "apple"))
         .build()?;
 
-    // The TrayIcon must be kept alive for the icon to remain visible. Since it should
-    // live for the entire application lifetime, we intentionally leak it here.
-    std::mem::forget(tray_icon);
-
-    MenuEvent::set_event_handler(Some(move |_| {
-        let _ = event_loop_proxy.send_event(UserEvent::Exit);
+    TRAY_ICON.with_borrow_mut(|icon| *icon = Some(tray_icon));
+
+    MenuEvent::set_event_handler(Some(move |event: MenuEvent| {
+        let user_event = match event.id.as_ref().strip_prefix("intensity-") {
+            Some(level) => match level.parse() {
+                Ok(level) => UserEvent::SetIntensity { level },
+                Err(_) => return,
+            },
+            None if event.id == "pause" => UserEvent::TogglePause,
+            None if event.id == "credits" => UserEvent::ShowCredits,
+            None if event.id == "wipe" => UserEvent::WipeAndExit,
+            None => UserEvent::Exit,
+        };
+        let _ = event_loop_proxy.send_event(user_event);
     }));
 
     Ok(())
 }
 
+/// Checks the tray's entry for `level` and unchecks the rest. Must be called on the thread that
+/// created the tray icon.
+#[cfg(not(target_os = "linux"))]
+pub fn update_tray_intensity(level: u8) {
+    INTENSITY_ITEMS.with_borrow(|items| {
+        for (i, item) in items.iter().enumerate() {
+            item.set_checked(i == level as usize);
+        }
+    });
+}
+
+/// Switches the tray's pause entry to resuming while the user has paused the session, and back.
+/// Must be called on the thread that created the tray icon.
+#[cfg(not(target_os = "linux"))]
+pub fn update_tray_paused(paused: bool) {
+    PAUSE_ITEM.with_borrow(|item| {
+        if let Some(item) = item {
+            item.set_text(tr(pause_label(paused)));
+        }
+    });
+}
+
+/// Shows what Lewdware is busy with (e.g. opening the pack) in the tray icon's tooltip, or
+/// clears it with `None`. Must be called on the thread that created the tray icon.
+#[cfg(not(target_os = "linux"))]
+pub fn set_tray_status(status: Option<&str>) {
+    let tooltip = match status {
+        Some(status) => tr_args("lewdware-tray-status", [("status", status.into())]),
+        None => "Lewdware".to_string(),
+    };
+    TRAY_ICON.with_borrow(|icon| {
+        if let Some(icon) = icon {
+            if let Err(err) = icon.set_tooltip(Some(tooltip)) {
+                tracing::warn!("Failed to update tray tooltip: {err}");
+            }
+        }
+    });
+}
+
+/// ksni keeps track of the selected radio item itself, so there's nothing to do here.
 #[cfg(target_os = "linux")]
-pub fn create_tray_icon(event_loop_proxy: EventLoopProxy<UserEvent>) -> Result<()> {
-    use ksni::{Tray, TrayService, menu::StandardItem};
+pub fn update_tray_intensity(_level: u8) {}
 
-    struct LewdwareTray {
-        proxy: EventLoopProxy<UserEvent>,
-        icon_theme_path: String,
+/// Switches the tray's pause entry to resuming while the user has paused the session, and back.
+#[cfg(target_os = "linux")]
+pub fn update_tray_paused(paused: bool) {
+    if let Some(handle) = TRAY_HANDLE.get() {
+        handle.update(|tray| tray.paused = paused);
     }
+}
+
+/// The tray's pause entry, which resumes the session if it's paused.
+fn pause_label(paused: bool) -> &'static str {
+    if paused {
+        "lewdware-tray-resume"
+    } else {
+        "lewdware-tray-pause"
+    }
+}
+
+/// Shows what Lewdware is busy with (e.g. opening the pack) in the tray icon's tooltip, or
+/// clears it with `None`.
+#[cfg(target_os = "linux")]
+pub fn set_tray_status(status: Option<&str>) {
+    if let Some(handle) = TRAY_HANDLE.get() {
+        let status = status.map(str::to_string);
+        handle.update(|tray| tray.status = status);
+    }
+}
+
+#[cfg(target_os = "linux")]
+struct LewdwareTray {
+    proxy: EventLoopProxy<UserEvent>,
+    icon_theme_path: String,
+    intensity: u8,
+    paused: bool,
+    status: Option<String>,
+}
+
+#[cfg(target_os = "linux")]
+pub fn create_tray_icon(event_loop_proxy: EventLoopProxy<UserEvent>) -> Result<()> {
+    use ksni::{
+        ToolTip, Tray, TrayService,
+        menu::{RadioGroup, RadioItem, StandardItem, SubMenu},
+    };
 
     impl Tray for LewdwareTray {
         fn title(&self) -> String {
             "Lewdware".into()
         }
+        fn tool_tip(&self) -> ToolTip {
+            ToolTip {
+                title: "Lewdware".into(),
+                description: self.status.clone().unwrap_or_default(),
+                ..Default::default()
+            }
+        }
         fn icon_name(&self) -> String {
             "lewdware-symbolic".into()
         }
@@ -67,23 +213,74 @@ pub fn create_tray_icon(event_loop_proxy: EventLoopProxy<UserEvent>) -> Result<(
         }
         fn menu(&self) -> Vec<ksni::MenuItem<Self>> {
             vec![
+                SubMenu {
+                    label: tr("lewdware-tray-intensity"),
+                    submenu: vec![
+                        RadioGroup {
+                            selected: self.intensity as usize,
+                            select: Box::new(|this: &mut Self, index| {
+                                this.intensity = index as u8;
+                                let _ = this.proxy.send_event(UserEvent::SetIntensity {
+                                    level: this.intensity,
+                                });
+                            }),
+                            options: (0..=MAX_INTENSITY)
+                                .map(|level| RadioItem {
+                                    label: level.to_string(),
+                                    ..Default::default()
+                                })
+                                .collect(),
+                        }
+                        .into(),
+                    ],
+                    ..Default::default()
+                }
+                .into(),
                 StandardItem {
-                    label: "Panic".into(),
+                    label: tr(pause_label(self.paused)),
+                    activate: Box::new(|this: &mut Self| {
+                        let _ = this.proxy.send_event(UserEvent::TogglePause);
+                    }),
+                    ..Default::default()
+                }
+                .into(),
+                StandardItem {
+                    label: tr("lewdware-tray-credits"),
+                    activate: Box::new(|this: &mut Self| {
+                        let _ = this.proxy.send_event(UserEvent::ShowCredits);
+                    }),
+                    ..Default::default()
+                }
+                .into(),
+                StandardItem {
+                    label: tr("lewdware-tray-panic"),
                     activate: Box::new(|this: &mut Self| {
                         let _ = this.proxy.send_event(UserEvent::Exit);
                     }),
                     ..Default::default()
                 }
                 .into(),
+                StandardItem {
+                    label: tr("lewdware-tray-wipe"),
+                    activate: Box::new(|this: &mut Self| {
+                        let _ = this.proxy.send_event(UserEvent::WipeAndExit);
+                    }),
+                    ..Default::default()
+                }
+                .into(),
             ]
         }
     }
 
-    TrayService::new(LewdwareTray {
+    let service = TrayService::new(LewdwareTray {
         proxy: event_loop_proxy,
         icon_theme_path: install_symbolic_icon().unwrap_or_default(),
-    })
-    .spawn();
+        intensity: DEFAULT_INTENSITY,
+        paused: false,
+        status: None,
+    });
+    let _ = TRAY_HANDLE.set(service.handle());
+    service.spawn();
     Ok(())
 }
 
@@ -100,10 +297,19 @@ fn install_symbolic_icon() -> Option<String> {
 
 /// Spawn a thread that will listen for the panic key being pressed, and send
 /// [UserEvent::PanicButtonPressed] to the event loop.
-pub fn spawn_panic_thread(event_loop_proxy: EventLoopProxy<UserEvent>, target_key: Key) {
-    tracing::info!("Spawning panic thread");
+/// Listens for global hotkeys and sends the paired event whenever one is pressed. rdev only
+/// supports a single listener per process, so every hotkey (panic, mute, ...) goes through here,
+/// as does keeping track of the cursor for [`cursor_position`] if `track_cursor` is set, and of
+/// when there was last any input for [`activity`] if `track_activity` is.
+pub fn spawn_hotkey_thread(
+    event_loop_proxy: EventLoopProxy<UserEvent>,
+    hotkeys: Vec<(Key, UserEvent)>,
+    track_cursor: bool,
+    track_activity: bool,
+) {
+    tracing::info!("Spawning hotkey thread");
     thread::spawn(move || {
-        tracing::info!("Panic thread started");
+        tracing::info!("Hotkey thread started");
 
         // On Windows, rdev installs a WH_KEYBOARD_LL hook whose callback is called as a
         // sent message to this thread. Windows will silently remove the hook if the
@@ -116,51 +322,68 @@ pub fn spawn_panic_thread(event_loop_proxy: EventLoopProxy<UserEvent>, target_ke
                 GetCurrentThread, SetThreadPriority, THREAD_PRIORITY_TIME_CRITICAL,
             };
             match SetThreadPriority(GetCurrentThread(), THREAD_PRIORITY_TIME_CRITICAL) {
-                Ok(()) => tracing::info!("Panic thread priority set to TIME_CRITICAL"),
-                Err(e) => tracing::error!("Failed to set panic thread priority: {e}"),
+                Ok(()) => tracing::info!("Hotkey thread priority set to TIME_CRITICAL"),
+                Err(e) => tracing::error!("Failed to set hotkey thread priority: {e}"),
             }
         }
 
-        let rdev_key = match key_to_rdev(&target_key) {
-            Some(x) => x,
-            None => {
-                tracing::error!("Key cannot be matched: {:?}", target_key.code);
-                return;
-            }
-        };
+        let hotkeys: Vec<_> = hotkeys
+            .into_iter()
+            .filter_map(|(key, event)| match key_to_rdev(&key) {
+                Some(rdev_key) => {
+                    tracing::info!(
+                        "Hotkey listener watching for {:?} with modifiers {:?} ({:?})",
+                        rdev_key,
+                        key.modifiers,
+                        event
+                    );
+                    Some((rdev_key, key.modifiers, event))
+                }
+                None => {
+                    tracing::error!("Key cannot be matched: {:?}", key.code);
+                    None
+                }
+            })
+            .collect();
 
-        tracing::info!(
-            "Panic listener starting: watching for {:?} with modifiers {:?}",
-            rdev_key,
-            target_key.modifiers
-        );
+        if hotkeys.is_empty() && !track_cursor && !track_activity {
+            return;
+        }
 
         let mut keys = HashSet::new();
 
         if let Err(err) = rdev::listen(move |event| {
+            if track_activity {
+                activity::record_input();
+            }
+
             if let rdev::EventType::KeyPress(key) = event.event_type {
                 keys.insert(key);
 
-                if key == rdev_key {
-                    let modifiers = rdev_keys_to_modifiers(&keys);
+                let modifiers = rdev_keys_to_modifiers(&keys);
 
-                    if modifier_matches(&modifiers, &target_key.modifiers) {
-                        if let Err(err) = event_loop_proxy.send_event(UserEvent::Exit) {
-                            tracing::error!("Could not send panic button event: {}", err);
+                for (rdev_key, target_modifiers, user_event) in &hotkeys {
+                    if key == *rdev_key && modifier_matches(&modifiers, target_modifiers) {
+                        if let Err(err) = event_loop_proxy.send_event(user_event.clone()) {
+                            tracing::error!("Could not send hotkey event: {}", err);
                         }
                     }
                 }
             } else if let rdev::EventType::KeyRelease(key) = event.event_type {
                 keys.remove(&key);
+            } else if let rdev::EventType::MouseMove { x, y } = event.event_type
+                && track_cursor
+            {
+                *CURSOR_POSITION.lock().unwrap() = Some((x, y));
             }
         }) {
             #[cfg(target_vendor = "apple")]
             tracing::error!(
-                "Panic key listener failed (this usually means accessibility permission was not granted): {:?}",
+                "Hotkey listener failed (this usually means accessibility permission was not granted): {:?}",
                 err
             );
             #[cfg(not(target_vendor = "apple"))]
-            tracing::error!("Panic key listener failed: {:?}", err);
+            tracing::error!("Hotkey listener failed: {:?}", err);
         }
     });
 }
@@ -324,6 +547,77 @@ pub fn key_to_rdev(key: &Key) -> Option<rdev::Key> {
     }
 }
 
+/// A random position for a window along one axis, keeping it entirely on the monitor if it fits.
+pub fn random_position(window_size: u32, total_size: u32) -> i32 {
+    if window_size > total_size {
+        0
+    } else {
+        rng::with_rng(|rng| rng.random_range(0i32..=(total_size - window_size) as i32))
+    }
+}
+
+/// Where the cursor is relative to the top left of `monitor`, in logical pixels. `None` until it's
+/// first moved after the hotkey listener starts, or if the listener can't see it (e.g. outside
+/// XWayland windows on Wayland).
+pub fn cursor_position(monitor: &MonitorHandle) -> Option<LogicalPosition<i32>> {
+    let (x, y) = (*CURSOR_POSITION.lock().unwrap())?;
+    let scale_factor = monitor.scale_factor();
+
+    // rdev reports points on macOS, which are already logical.
+    #[cfg(target_vendor = "apple")]
+    let cursor = LogicalPosition::new(x, y);
+    #[cfg(not(target_vendor = "apple"))]
+    let cursor: LogicalPosition<f64> =
+        winit::dpi::PhysicalPosition::new(x, y).to_logical(scale_factor);
+
+    let monitor_position: LogicalPosition<f64> = monitor.position().to_logical(scale_factor);
+    Some(LogicalPosition::new(
+        (cursor.x - monitor_position.x).round() as i32,
+        (cursor.y - monitor_position.y).round() as i32,
+    ))
+}
+
+/// Moves a popup of `size` at `position` so that no part of it is within `margin` pixels of
+/// `cursor`. It's only moved along the axes in `movable` (those it was placed on at random), by as
+/// little as possible and without leaving `area`. If there's no room for it anywhere clear of the
+/// cursor, it's left where it is.
+pub fn avoid_cursor(
+    position: (i32, i32),
+    size: (u32, u32),
+    area: (u32, u32),
+    cursor: LogicalPosition<i32>,
+    margin: u32,
+    movable: (bool, bool),
+) -> (i32, i32) {
+    let margin = margin as i32;
+    let clear = |start: i32, size: u32, cursor: i32| {
+        start + size as i32 <= cursor - margin || start > cursor + margin
+    };
+
+    let (x, y) = position;
+    if clear(x, size.0, cursor.x) || clear(y, size.1, cursor.y) {
+        return position;
+    }
+
+    // Just before or just after the area around the cursor, whichever is in bounds.
+    let candidates = |movable: bool, start: i32, size: u32, total: u32, cursor: i32| {
+        let max = total as i32 - size as i32;
+        [cursor - margin - size as i32, cursor + margin + 1]
+            .into_iter()
+            .filter(move |&candidate| movable && (0..=max).contains(&candidate))
+            .map(move |candidate| ((candidate - start).abs(), candidate))
+    };
+
+    candidates(movable.0, x, size.0, area.0, cursor.x)
+        .map(|(distance, x)| (distance, (x, y)))
+        .chain(
+            candidates(movable.1, y, size.1, area.1, cursor.y)
+                .map(|(distance, y)| (distance, (x, y))),
+        )
+        .min_by_key(|&(distance, _)| distance)
+        .map_or(position, |(_, position)| position)
+}
+
 pub fn calculate_media_popup_size(
     width: Option<Coord>,
     height: Option<Coord>,
@@ -351,6 +645,18 @@ pub fn calculate_media_popup_size(
     }
 }
 
+/// The smallest size an image can be scaled to, keeping its aspect ratio, that covers a `width` x
+/// `height` area.
+pub fn cover_size(media_width: u32, media_height: u32, width: u32, height: u32) -> (u32, u32) {
+    let scale =
+        (width as f64 / media_width.max(1) as f64).max(height as f64 / media_height.max(1) as f64);
+
+    (
+        ((media_width as f64 * scale).round() as u32).max(width),
+        ((media_height as f64 * scale).round() as u32).max(height),
+    )
+}
+
 /// Resolve the size of a text popup. Unlike `calculate_media_popup_size`, text has no fixed
 /// aspect ratio to scale, so an omitted width/height wraps the text to fit rather than scaling a
 /// font size the caller explicitly chose.
@@ -508,16 +814,7 @@ pub fn raise_fd_limit() {
 #[cfg(not(unix))]
 pub fn raise_fd_limit() {}
 
-/// The directory lewdware extracts media (images/video/audio, the pack's SQLite index) into
-/// while running. A subdirectory of the regular system temp dir (`$TMPDIR`/`/tmp`), *not*
-/// `$XDG_RUNTIME_DIR` (used for the lock file) — the runtime dir is conventionally sized for
-/// small runtime objects like sockets, and is typically much smaller than `/tmp` (on this
-/// machine: ~1.6GB vs. ~6GB), which matters since these extracted files can be large (whole
-/// video/audio files, a pack's full SQLite index). Kept in its own subdirectory so leftovers
-/// from a previous run can be identified and swept away, see [`prepare_temp_dir`].
-pub fn temp_dir() -> PathBuf {
-    std::env::temp_dir().join("lewdware-tmp")
-}
+pub use shared::footprint::temp_dir;
 
 /// Clears out [`temp_dir`] and recreates it. Only safe to call while holding the single-instance
 /// lock: if a previous session crashed or was force-killed, its `NamedTempFile`s never got a
@@ -536,3 +833,35 @@ pub fn prepare_temp_dir() -> std::io::Result<PathBuf> {
 
     Ok(dir)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn popups_avoid_the_cursor() {
+        let cursor = LogicalPosition::new(500, 500);
+        let avoid = |position, movable| {
+            avoid_cursor(position, (100, 100), (1000, 800), cursor, 50, movable)
+        };
+
+        // Already clear.
+        assert_eq!(avoid((0, 0), (true, true)), (0, 0));
+        // Moved along whichever axis is the shortest way out.
+        assert_eq!(avoid((440, 380), (true, true)), (440, 350));
+        assert_eq!(avoid((380, 440), (true, true)), (350, 440));
+        // Fixed positions stay put.
+        assert_eq!(avoid((440, 380), (true, false)), (350, 380));
+        assert_eq!(avoid((440, 380), (false, false)), (440, 380));
+        // Never pushed off the monitor.
+        let big = avoid_cursor((0, 0), (980, 780), (1000, 800), cursor, 50, (true, true));
+        assert_eq!(big, (0, 0));
+    }
+
+    #[test]
+    fn backgrounds_cover_the_window() {
+        assert_eq!(cover_size(1920, 1080, 400, 400), (711, 400));
+        assert_eq!(cover_size(1000, 2000, 400, 300), (400, 800));
+        assert_eq!(cover_size(100, 100, 400, 400), (400, 400));
+    }
+}
@@ -2,6 +2,7 @@ use std::{
     cell::Cell,
     sync::{
         Arc,
+        atomic::{AtomicU64, Ordering},
         mpsc::{Receiver, SyncSender, TryRecvError, sync_channel},
     },
     thread,
@@ -9,15 +10,20 @@ use std::{
 };
 
 use anyhow::{Context, Result};
-use ffmpeg::codec;
+use ffmpeg::{codec, software::scaling};
 use ffmpeg_next::{self as ffmpeg, ffi, frame::Video};
 
 use crate::{
     audio::AudioPlayer,
+    decode_helper::{self, Isolation},
     media::MediaSource,
     zero_copy::{HardwareFrame, initialize_hardware_device, preferred_hw_type},
 };
 
+/// How soon to check again for a frame the decoder hasn't produced yet. Frames arriving from the
+/// decoder thread don't wake the event loop, so it has to come back for them.
+const DECODER_RETRY: Duration = Duration::from_millis(4);
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum VideoPixelFormat {
     Yuv420p,
@@ -115,7 +121,60 @@ pub struct VideoDecoder {
     pixel_format: VideoPixelFormat,
     packed_alpha: bool,
     paused: bool,
-    pub lag_count: u32,
+    // Set when a frame was due but the decoder didn't have one ready.
+    waiting_for_decoder: bool,
+    stats: VideoStats,
+    // The size the decoder thread scales software-decoded frames to, packed by `pack_size`, or 0
+    // to leave them at the video's own size. See `downscale_to`.
+    output_size: Arc<AtomicU64>,
+}
+
+/// How well a video kept up, for judging performance regressions and codec choices. Logged when
+/// a video popup closes, and for the whole session on exit.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct VideoStats {
+    /// Frames that came out of the decoder.
+    pub decoded: u64,
+    /// Frames shown in the window.
+    pub presented: u64,
+    /// Frames skipped to catch up with the audio.
+    pub dropped: u64,
+    /// How many times a frame was due before the decoder had it ready.
+    pub late: u64,
+    /// Time spent decoding all of the decoded frames.
+    pub decode_time: Duration,
+}
+
+impl VideoStats {
+    pub fn average_decode_ms(&self) -> f64 {
+        if self.decoded == 0 {
+            return 0.0;
+        }
+
+        self.decode_time.as_secs_f64() * 1000.0 / self.decoded as f64
+    }
+
+    pub fn add(&mut self, other: &VideoStats) {
+        self.decoded += other.decoded;
+        self.presented += other.presented;
+        self.dropped += other.dropped;
+        self.late += other.late;
+        self.decode_time += other.decode_time;
+    }
+}
+
+impl std::fmt::Display for VideoStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} frames decoded ({:.1}ms each), {} presented, {} dropped, {} late",
+            self.decoded,
+            self.average_decode_ms(),
+            self.presented,
+            self.dropped,
+            self.late
+        )
+    }
 }
 
 pub struct VideoFrame {
@@ -124,6 +183,9 @@ pub struct VideoFrame {
     pub hardware_frame: Option<HardwareFrame>,
     pub pts: Duration,
     pub recycle_tx: SyncSender<Video>,
+    /// How long the decoder thread spent producing this frame, not counting time waiting for
+    /// the player to take the last one.
+    pub decode_time: Duration,
 }
 
 impl Drop for VideoFrame {
@@ -142,10 +204,29 @@ impl VideoDecoder {
         play_audio: bool,
         loop_video: bool,
         packed_alpha: bool,
+        max_fps: Option<u32>,
         wgpu_device: Option<Arc<wgpu::Device>>,
+        isolation: Option<Isolation>,
     ) -> Result<Self> {
-        let (receiver, native_width, native_height, full_range, pixel_format) =
-            spawn_video_stream(source.clone(), loop_video, packed_alpha, wgpu_device)?;
+        let output_size = Arc::new(AtomicU64::new(0));
+        let (receiver, native_width, native_height, full_range, pixel_format) = match isolation {
+            Some(isolation) => decode_helper::spawn(
+                source.clone(),
+                loop_video,
+                packed_alpha,
+                max_fps,
+                output_size.clone(),
+                isolation,
+            )?,
+            None => spawn_video_stream(
+                source.clone(),
+                loop_video,
+                packed_alpha,
+                max_fps,
+                output_size.clone(),
+                wgpu_device,
+            )?,
+        };
 
         let audio_player = if play_audio {
             match AudioPlayer::new(source, loop_video, None, None) {
@@ -172,10 +253,49 @@ impl VideoDecoder {
             video_clock: Duration::ZERO,
             tolerance: Duration::from_millis(200),
             paused: true,
-            lag_count: 0,
+            waiting_for_decoder: false,
+            stats: VideoStats::default(),
+            output_size,
         })
     }
 
+    /// Has the decoder thread scale frames down to `width` x `height` when the video is bigger,
+    /// so they aren't decoded at full size only to be shrunk for a small window. Should be called
+    /// before anything is created from the video's size, since it changes what
+    /// [`Self::native_width`] and [`Self::native_height`] return.
+    ///
+    /// Only software-decoded frames are scaled: hardware-decoded ones stay on the GPU, which
+    /// scales them when they're drawn.
+    pub fn downscale_to(&mut self, width: u32, height: u32) {
+        if self.pixel_format != VideoPixelFormat::Yuv420p {
+            return;
+        }
+
+        // YUV 4:2:0 needs even dimensions.
+        let width = width.min(self.native_width).max(2) & !1;
+        let height = height.min(self.native_height).max(2) & !1;
+        if width == self.native_width && height == self.native_height {
+            return;
+        }
+
+        tracing::debug!(
+            "Scaling video down from {}x{} to {width}x{height}",
+            self.native_width,
+            self.native_height
+        );
+
+        self.native_width = width;
+        self.native_height = height;
+        // Packed-alpha frames hold the alpha below the colour.
+        let frame_height = if self.packed_alpha {
+            height * 2
+        } else {
+            height
+        };
+        self.output_size
+            .store(pack_size(width, frame_height), Ordering::Relaxed);
+    }
+
     pub fn width(&self) -> u32 {
         self.native_width
     }
@@ -210,6 +330,8 @@ impl VideoDecoder {
             return NextFrame::None;
         }
 
+        self.waiting_for_decoder = false;
+
         if !self.needs_next_frame() {
             // Video is ahead of or in sync with audio. Ensure audio is playing.
             if let Some(audio_player) = &self.audio_player {
@@ -221,6 +343,19 @@ impl VideoDecoder {
         let frame = loop {
             match self.receiver.try_recv() {
                 Ok(Some(frame)) => {
+                    self.stats.decoded += 1;
+                    self.stats.decode_time += frame.decode_time;
+
+                    // Decoded before `downscale_to` was called, so it's the wrong size.
+                    if self.output_size.load(Ordering::Relaxed) != 0
+                        && frame.hardware_frame.is_none()
+                        && frame.frame.width() > 0
+                        && frame.frame.width() != self.native_width
+                    {
+                        self.stats.dropped += 1;
+                        continue;
+                    }
+
                     // We got a frame, so if we were waiting for it, resume the audio.
                     if let Some(audio_player) = &self.audio_player {
                         audio_player.play();
@@ -235,6 +370,7 @@ impl VideoDecoder {
                     if let Some(audio_player) = &self.audio_player {
                         // Compare directly with total audio position
                         if frame.pts < audio_player.position().saturating_sub(self.tolerance) {
+                            self.stats.dropped += 1;
                             continue;
                         }
                     }
@@ -242,10 +378,12 @@ impl VideoDecoder {
                 }
                 Ok(None) => {
                     // End of stream from decoder. If looping, it will start again.
+                    self.waiting_for_decoder = true;
                     return NextFrame::None;
                 }
                 Err(TryRecvError::Empty) => {
-                    self.lag_count += 1;
+                    self.stats.late += 1;
+                    self.waiting_for_decoder = true;
                     // The decoder is lagging behind the audio, so we pause the audio to wait for it.
                     if let Some(audio_player) = &self.audio_player {
                         audio_player.pause();
@@ -268,10 +406,37 @@ impl VideoDecoder {
 
         self.video_clock = next_pts;
         self.last_frame_time = Instant::now();
+        self.stats.presented += 1;
 
         NextFrame::Ready(frame)
     }
 
+    pub fn stats(&self) -> VideoStats {
+        self.stats
+    }
+
+    /// When [`Self::next_frame`] should next be called to keep up with the video. `None` while
+    /// it's paused, since nothing's due until it's played again.
+    pub fn next_frame_at(&self) -> Option<Instant> {
+        if self.paused {
+            return None;
+        }
+
+        let now = Instant::now();
+        if self.waiting_for_decoder {
+            return Some(now + DECODER_RETRY);
+        }
+
+        Some(match &self.audio_player {
+            // `needs_next_frame` wants the audio to be strictly past the current frame.
+            Some(audio_player) => {
+                now + self.video_clock.saturating_sub(audio_player.position())
+                    + Duration::from_millis(1)
+            }
+            None => self.last_frame_time + self.frame_duration,
+        })
+    }
+
     fn needs_next_frame(&self) -> bool {
         match &self.audio_player {
             Some(audio_player) => {
@@ -295,6 +460,14 @@ impl VideoDecoder {
         }
         self.paused = false;
     }
+
+    /// Mutes the video's audio without affecting playback, so the audio clock keeps running and
+    /// the video stays in sync.
+    pub fn set_muted(&self, muted: bool) {
+        if let Some(audio_player) = &self.audio_player {
+            audio_player.set_muted(muted);
+        }
+    }
 }
 
 pub enum NextFrame {
@@ -311,10 +484,12 @@ struct VideoMetadata {
 }
 
 /// Spawn a thread to decode frames from a video.
-fn spawn_video_stream(
+pub fn spawn_video_stream(
     source: MediaSource,
     loop_video: bool,
     packed_alpha: bool,
+    max_fps: Option<u32>,
+    output_size: Arc<AtomicU64>,
     wgpu_device: Option<Arc<wgpu::Device>>,
 ) -> Result<(
     Receiver<Option<VideoFrame>>,
@@ -333,6 +508,8 @@ fn spawn_video_stream(
             tx,
             loop_video,
             packed_alpha,
+            max_fps,
+            output_size,
             meta_tx,
             recycle_rx,
             recycle_tx.clone(),
@@ -369,6 +546,7 @@ fn hw_frame_to_video_frame(
             hardware_frame: Some(frame),
             pts,
             recycle_tx: recycle_tx.clone(),
+            decode_time: Duration::ZERO,
         });
     }
 
@@ -385,6 +563,7 @@ fn hw_frame_to_video_frame(
         hardware_frame: None,
         pts,
         recycle_tx: recycle_tx.clone(),
+        decode_time: Duration::ZERO,
     })
 }
 
@@ -393,6 +572,8 @@ fn decode_video(
     tx: SyncSender<Option<VideoFrame>>,
     loop_video: bool,
     packed_alpha: bool,
+    max_fps: Option<u32>,
+    output_size: Arc<AtomicU64>,
     meta_tx: SyncSender<VideoMetadata>,
     recycle_rx: Receiver<Video>,
     recycle_tx: SyncSender<Video>,
@@ -473,6 +654,9 @@ fn decode_video(
 
     let mut current_loop_offset = Duration::ZERO;
     let mut last_pts_duration = Duration::ZERO;
+    let mut decode_start = Instant::now();
+    let mut frame_limiter = FrameLimiter::new(max_fps);
+    let mut downscaler = Downscaler::new(output_size);
 
     'main: loop {
         for (stream, packet) in ictx.packets() {
@@ -484,6 +668,10 @@ fn decode_video(
                     let pts_duration = Duration::from_secs_f64(pts_seconds);
                     last_pts_duration = pts_duration;
 
+                    if !frame_limiter.keep(pts_duration + current_loop_offset) {
+                        continue;
+                    }
+
                     let video_frame = if let Some(hw_fmt) = hw_pix_fmt {
                         if unsafe { (*decoded.as_ptr()).format } == hw_fmt as i32 {
                             hw_frame_to_video_frame(
@@ -493,34 +681,38 @@ fn decode_video(
                             )
                         } else {
                             let next = recycle_rx.try_recv().unwrap_or_else(|_| Video::empty());
-                            let frame = std::mem::replace(&mut decoded, next);
+                            let frame = downscaler.scale(std::mem::replace(&mut decoded, next));
                             Ok(VideoFrame {
                                 frame,
                                 hardware_frame: None,
                                 pts: pts_duration + current_loop_offset,
                                 recycle_tx: recycle_tx.clone(),
+                                decode_time: Duration::ZERO,
                             })
                         }
                     } else {
                         // Software decode: swap decoded out so ffmpeg can reuse the buffer.
                         let next = recycle_rx.try_recv().unwrap_or_else(|_| Video::empty());
-                        let frame = std::mem::replace(&mut decoded, next);
+                        let frame = downscaler.scale(std::mem::replace(&mut decoded, next));
                         Ok(VideoFrame {
                             frame,
                             hardware_frame: None,
                             pts: pts_duration + current_loop_offset,
                             recycle_tx: recycle_tx.clone(),
+                            decode_time: Duration::ZERO,
                         })
                     };
 
-                    let video_frame = match video_frame {
+                    let mut video_frame = match video_frame {
                         Ok(f) => f,
                         Err(_) => continue,
                     };
+                    video_frame.decode_time = decode_start.elapsed();
 
                     if tx.send(Some(video_frame)).is_err() {
                         break 'main;
                     }
+                    decode_start = Instant::now();
                 }
             }
         }
@@ -533,6 +725,10 @@ fn decode_video(
             let pts_duration = Duration::from_secs_f64(pts_seconds);
             last_pts_duration = pts_duration;
 
+            if !frame_limiter.keep(pts_duration + current_loop_offset) {
+                continue;
+            }
+
             let video_frame = if let Some(hw_fmt) = hw_pix_fmt {
                 if unsafe { (*decoded.as_ptr()).format } == hw_fmt as i32 {
                     hw_frame_to_video_frame(
@@ -542,33 +738,37 @@ fn decode_video(
                     )
                 } else {
                     let next = recycle_rx.try_recv().unwrap_or_else(|_| Video::empty());
-                    let frame = std::mem::replace(&mut decoded, next);
+                    let frame = downscaler.scale(std::mem::replace(&mut decoded, next));
                     Ok(VideoFrame {
                         frame,
                         hardware_frame: None,
                         pts: pts_duration + current_loop_offset,
                         recycle_tx: recycle_tx.clone(),
+                        decode_time: Duration::ZERO,
                     })
                 }
             } else {
                 let next = recycle_rx.try_recv().unwrap_or_else(|_| Video::empty());
-                let frame = std::mem::replace(&mut decoded, next);
+                let frame = downscaler.scale(std::mem::replace(&mut decoded, next));
                 Ok(VideoFrame {
                     frame,
                     hardware_frame: None,
                     pts: pts_duration + current_loop_offset,
                     recycle_tx: recycle_tx.clone(),
+                    decode_time: Duration::ZERO,
                 })
             };
 
-            let video_frame = match video_frame {
+            let mut video_frame = match video_frame {
                 Ok(f) => f,
                 Err(_) => continue,
             };
+            video_frame.decode_time = decode_start.elapsed();
 
             if tx.send(Some(video_frame)).is_err() {
                 break 'main;
             }
+            decode_start = Instant::now();
         }
 
         if tx.send(None).is_err() {
@@ -586,3 +786,127 @@ fn decode_video(
 
     Ok(())
 }
+
+/// Drops frames so no more than `max_fps` are shown a second. A frame is kept when it's the
+/// first in a new `1 / max_fps` slot of the timeline, so the ones kept stay evenly spaced (a
+/// 60fps video capped at 24fps keeps every second frame, then every third, and so on).
+struct FrameLimiter {
+    max_fps: Option<u32>,
+    last_slot: Option<u64>,
+}
+
+impl FrameLimiter {
+    fn new(max_fps: Option<u32>) -> Self {
+        Self {
+            max_fps: max_fps.filter(|&fps| fps > 0),
+            last_slot: None,
+        }
+    }
+
+    fn keep(&mut self, pts: Duration) -> bool {
+        let Some(max_fps) = self.max_fps else {
+            return true;
+        };
+
+        // Timestamps have been through floating point, so one on a slot's boundary may land
+        // just short of it.
+        let slot = ((pts.as_secs_f64() + 1e-6) * max_fps as f64) as u64;
+        if self.last_slot.is_some_and(|last_slot| slot <= last_slot) {
+            return false;
+        }
+
+        self.last_slot = Some(slot);
+        true
+    }
+}
+
+fn pack_size(width: u32, height: u32) -> u64 {
+    (width as u64) << 32 | height as u64
+}
+
+/// Scales software-decoded frames to the size set by [`VideoDecoder::downscale_to`], once it's
+/// been set.
+pub struct Downscaler {
+    output_size: Arc<AtomicU64>,
+    scaler: Option<scaling::Context>,
+}
+
+impl Downscaler {
+    pub fn new(output_size: Arc<AtomicU64>) -> Self {
+        Self {
+            output_size,
+            scaler: None,
+        }
+    }
+
+    /// Returns `frame` scaled down, or as it is if it doesn't need scaling or can't be scaled.
+    pub fn scale(&mut self, frame: Video) -> Video {
+        let output_size = self.output_size.load(Ordering::Relaxed);
+        if output_size == 0 {
+            return frame;
+        }
+
+        let (width, height) = ((output_size >> 32) as u32, output_size as u32);
+        if frame.width() == width && frame.height() == height {
+            return frame;
+        }
+
+        let matches = |scaler: &scaling::Context| {
+            let (input, output) = (scaler.input(), scaler.output());
+            input.format == frame.format()
+                && input.width == frame.width()
+                && input.height == frame.height()
+                && output.width == width
+                && output.height == height
+        };
+
+        if !self.scaler.as_ref().is_some_and(matches) {
+            // Scaling to the same pixel format leaves the colour range alone, which the renderers
+            // have already been told about.
+            match scaling::Context::get(
+                frame.format(),
+                frame.width(),
+                frame.height(),
+                frame.format(),
+                width,
+                height,
+                scaling::Flags::AREA,
+            ) {
+                Ok(scaler) => self.scaler = Some(scaler),
+                Err(err) => {
+                    tracing::error!("Couldn't scale video frames: {err}");
+                    return frame;
+                }
+            }
+        }
+
+        let Some(scaler) = &mut self.scaler else {
+            return frame;
+        };
+
+        let mut scaled = Video::empty();
+        if let Err(err) = scaler.run(&frame, &mut scaled) {
+            tracing::error!("Error scaling video frame: {err}");
+            return frame;
+        }
+
+        scaled
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frame_limiter_keeps_evenly_spaced_frames() {
+        let frame = |i: u64| Duration::from_secs_f64(i as f64 / 60.0);
+
+        let mut limiter = FrameLimiter::new(Some(24));
+        let kept: Vec<_> = (0..10).filter(|&i| limiter.keep(frame(i))).collect();
+        assert_eq!(kept, [0, 3, 5, 8]);
+
+        let mut limiter = FrameLimiter::new(None);
+        assert!((0..10).all(|i| limiter.keep(frame(i))));
+    }
+}
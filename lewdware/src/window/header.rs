@@ -24,7 +24,7 @@ pub struct Header {
 
 pub const HEADER_HEIGHT: u32 = 24;
 
-static FONT: LazyLock<Option<FontArc>> = LazyLock::new(|| {
+pub(super) static FONT: LazyLock<Option<FontArc>> = LazyLock::new(|| {
     let font_definitions = egui::FontDefinitions::default();
     let font_data = font_definitions.font_data.get("Ubuntu-Light");
 
@@ -0,0 +1,71 @@
+//! The overlay the info hotkey shows over a popup (see `info_button` in the config), so pack
+//! authors can tell which file in the pack a popup came from while testing.
+
+use ab_glyph::{Font, PxScale, ScaleFont};
+use tiny_skia::{Color, Pixmap};
+use winit::dpi::PhysicalSize;
+
+use super::header::FONT;
+
+const FONT_SIZE: f32 = 13.0;
+const PADDING: f32 = 6.0;
+
+/// Renders `lines` as white text on a dark box, cut off at `max_size`. `None` if there's no room
+/// for it, or the font couldn't be loaded.
+pub fn render(lines: &[String], scale_factor: f64, max_size: PhysicalSize<u32>) -> Option<Pixmap> {
+    let font = FONT.as_ref()?;
+    let scale_factor = scale_factor as f32;
+    let scale = PxScale::from(FONT_SIZE * scale_factor);
+    let scaled_font = font.as_scaled(scale);
+    let padding = PADDING * scale_factor;
+    let line_height = scaled_font.height() + scaled_font.line_gap();
+
+    let text_width = lines
+        .iter()
+        .map(|line| {
+            line.chars()
+                .map(|c| scaled_font.h_advance(scaled_font.glyph_id(c)))
+                .sum::<f32>()
+        })
+        .fold(0.0, f32::max);
+    let width = ((text_width + padding * 2.0).ceil() as u32).min(max_size.width);
+    let height =
+        ((line_height * lines.len() as f32 + padding * 2.0).ceil() as u32).min(max_size.height);
+
+    // Opaque, since the overlay is copied over the popup rather than blended.
+    let mut pixmap = Pixmap::new(width, height)?;
+    pixmap.fill(Color::from_rgba8(20, 20, 20, 255));
+    let data = pixmap.data_mut();
+
+    for (i, line) in lines.iter().enumerate() {
+        let mut pen_x = padding;
+        let pen_y = padding + scaled_font.ascent() + line_height * i as f32;
+
+        for c in line.chars() {
+            let glyph_id = scaled_font.glyph_id(c);
+            let glyph = glyph_id.with_scale_and_position(scale, ab_glyph::point(pen_x, pen_y));
+
+            if let Some(outlined) = font.outline_glyph(glyph) {
+                let bounds = outlined.px_bounds();
+
+                outlined.draw(|x, y, coverage| {
+                    let px = bounds.min.x as i32 + x as i32;
+                    let py = bounds.min.y as i32 + y as i32;
+
+                    if px >= 0 && px < width as i32 && py >= 0 && py < height as i32 {
+                        let idx = ((py as u32 * width + px as u32) * 4) as usize;
+
+                        // Blend towards white, leaving alpha alone.
+                        for channel in &mut data[idx..idx + 3] {
+                            *channel += ((255 - *channel) as f32 * coverage.min(1.0)) as u8;
+                        }
+                    }
+                });
+            }
+
+            pen_x += scaled_font.h_advance(glyph_id);
+        }
+    }
+
+    Some(pixmap)
+}
@@ -5,6 +5,7 @@ use std::time::{Duration, Instant};
 
 use anyhow::{Context, Result, anyhow, bail};
 use shared::once;
+use tiny_skia::Pixmap;
 use tokio::sync::mpsc;
 use winit::dpi::{LogicalPosition, LogicalSize, PhysicalPosition, PhysicalSize, PhysicalUnit};
 use winit::window::Window;
@@ -13,10 +14,14 @@ use crate::error::LewdwareError;
 use crate::lua::{self, Coord, Easing, FadeOpts, MoveOpts};
 use crate::wgpu::WgpuState;
 use crate::window::header::HEADER_HEIGHT;
+use crate::window::info;
 use crate::window::opts::WindowOpts;
 use crate::window::surface::Buffer;
 use crate::window::{header::Header, surface::Surface};
 
+/// How often moves and fades update the window, which is ~30 fps.
+const ANIMATION_INTERVAL: Duration = Duration::from_millis(33);
+
 pub struct InnerWindow {
     window: Arc<winit::window::Window>,
     surface: Surface,
@@ -41,6 +46,14 @@ pub struct InnerWindow {
     last_fade_update: Instant,
     pub opacity: f32,
     background_color: Option<lua::Color>,
+    created_at: Instant,
+    // The info overlay, if it's showing (see `set_info`). `info_changed` and `info_drawn` (the
+    // size it was last drawn at) are for overlay buffers that keep their contents between frames.
+    info: Option<Pixmap>,
+    info_changed: bool,
+    info_drawn: Option<(u32, u32)>,
+    // Windows start out hidden (or offscreen, on Linux) until `set_visible`.
+    visible: bool,
 }
 
 struct Move {
@@ -184,9 +197,65 @@ impl InnerWindow {
             last_fade_update: Instant::now(),
             opacity: opts.opacity,
             background_color: opts.background_color,
+            created_at: Instant::now(),
+            info: None,
+            info_changed: false,
+            info_drawn: None,
+            visible: false,
         })
     }
 
+    /// When the popup was created. The `winit` window itself may be older, since windows are
+    /// reused through the pool.
+    pub fn created_at(&self) -> Instant {
+        self.created_at
+    }
+
+    /// Shows `lines` over the top left of the window's contents, or takes them away with `None`.
+    pub fn set_info(&mut self, lines: Option<&[String]>) {
+        self.info = lines
+            .and_then(|lines| info::render(lines, self.window.scale_factor(), self.inner_size));
+        self.info_changed = true;
+        self.window.request_redraw();
+    }
+
+    pub fn showing_info(&self) -> bool {
+        self.info.is_some()
+    }
+
+    /// Draws the info overlay (see [`Self::set_info`]) over buffers that are drawn from scratch
+    /// each frame.
+    pub fn render_info(&self, buffer: &mut Buffer) {
+        if let Some(info) = &self.info {
+            let (x, y) = self.inner_offset();
+            buffer.copy_from_pixmap(info, x, y);
+        }
+    }
+
+    /// Like [`Self::render_info`], for overlay buffers that keep their contents between frames:
+    /// only draws once the overlay has changed, clearing the old one first. Returns whether it
+    /// drew anything.
+    pub fn render_info_overlay(&mut self, buffer: &mut Buffer) -> bool {
+        if !std::mem::take(&mut self.info_changed) {
+            return false;
+        }
+
+        let (x, y) = self.inner_offset();
+        if let Some(clear) = self
+            .info_drawn
+            .take()
+            .and_then(|(width, height)| Pixmap::new(width, height))
+        {
+            buffer.copy_from_pixmap(&clear, x, y);
+        }
+        if let Some(info) = &self.info {
+            buffer.copy_from_pixmap(info, x, y);
+            self.info_drawn = Some((info.width(), info.height()));
+        }
+
+        true
+    }
+
     pub fn set_opacity(&mut self, opacity: f32) {
         self.opacity = opacity;
     }
@@ -377,6 +446,7 @@ impl InnerWindow {
     }
 
     pub fn draw_softbuffer(&mut self, draw_fn: impl FnOnce(&mut Buffer)) -> Result<()> {
+        let info_offset = self.inner_offset();
         let softbuffer_surface = match &mut self.surface {
             Surface::Softbuffer { surface, .. } => surface,
             _ => bail!("Called draw_softbuffer on a non-CPU surface"),
@@ -400,6 +470,9 @@ impl InnerWindow {
                 buffer.copy_from_pixmap(pixmap, border_offset, border_offset);
             }
         }
+        if let Some(info) = &self.info {
+            buffer.copy_from_pixmap(info, info_offset.0, info_offset.1);
+        }
 
         match buffer {
             Buffer::Softbuffer(b) => b.present().map_err(|err| anyhow!("{err}"))?,
@@ -550,8 +623,7 @@ impl InnerWindow {
             // Throttle visual updates to ~30 fps; always apply the final position on completion
             // so the window lands exactly on the wall edge before the next move starts.
             if new_position != self.position
-                && (complete
-                    || self.last_move_update.elapsed() >= Duration::from_millis(33))
+                && (complete || self.last_move_update.elapsed() >= ANIMATION_INTERVAL)
             {
                 self.window.set_outer_position(LogicalPosition::new(
                     self.monitor_position.x + new_position.x,
@@ -597,6 +669,21 @@ impl InnerWindow {
         self.current_fade.is_some()
     }
 
+    /// When the current move or fade next needs updating, if there is one.
+    pub fn next_animation_update(&self) -> Option<Instant> {
+        let next = Instant::now() + ANIMATION_INTERVAL;
+        let moving = self
+            .current_move
+            .as_ref()
+            .map(|current_move| next.min(current_move.start + current_move.duration));
+        let fading = self
+            .current_fade
+            .as_ref()
+            .map(|current_fade| next.min(current_fade.start + current_fade.duration));
+
+        moving.into_iter().chain(fading).min()
+    }
+
     pub fn update_fade(&mut self) {
         let (new_opacity, is_finished, fade_id) =
             if let Some(current_fade) = &self.current_fade {
@@ -617,8 +704,7 @@ impl InnerWindow {
             };
 
         if new_opacity != self.opacity
-            && (is_finished
-                || self.last_fade_update.elapsed() >= Duration::from_millis(33))
+            && (is_finished || self.last_fade_update.elapsed() >= ANIMATION_INTERVAL)
         {
             self.set_opacity(new_opacity);
             self.window.request_redraw();
@@ -663,7 +749,13 @@ impl InnerWindow {
         }
     }
 
-    pub fn set_visible(&self, visible: bool) {
+    pub fn is_visible(&self) -> bool {
+        self.visible
+    }
+
+    pub fn set_visible(&mut self, visible: bool) {
+        self.visible = visible;
+
         #[cfg(target_os = "linux")]
         {
             if visible {
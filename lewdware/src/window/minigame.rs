@@ -0,0 +1,193 @@
+//! The click-target minigame (see `minigames` in the config): a small target bounces around a
+//! popup, and the popup only closes once it's been clicked enough times. Kept apart from the
+//! window so the game can be tested without a window system. Everything here is in physical
+//! pixels, relative to the popup's content area.
+
+use std::time::Duration;
+
+use rand::RngExt;
+use tiny_skia::{Color, FillRule, Paint, PathBuilder, Pixmap, Transform};
+
+use crate::rng;
+
+/// How much faster the target moves after each hit.
+const SPEEDUP: f32 = 1.15;
+
+/// The progress dots along the top of the popup, one per hit needed.
+const DOT_RADIUS: f32 = 4.0;
+const DOT_GAP: f32 = 6.0;
+
+pub struct Minigame {
+    width: f32,
+    height: f32,
+    x: f32,
+    y: f32,
+    /// Pixels per second.
+    vx: f32,
+    vy: f32,
+    radius: f32,
+    speed: f32,
+    hits: u32,
+    hits_needed: u32,
+    /// How much the progress dots are scaled by, to follow the window's scale factor.
+    scale: f32,
+}
+
+impl Minigame {
+    pub fn new(
+        (width, height): (u32, u32),
+        radius: f32,
+        speed: f32,
+        hits_needed: u32,
+        scale: f32,
+    ) -> Self {
+        let mut game = Self {
+            width: width as f32,
+            height: height as f32,
+            x: 0.0,
+            y: 0.0,
+            vx: 0.0,
+            vy: 0.0,
+            // Never too big to move around the popup.
+            radius: radius.min(width.min(height) as f32 / 4.0).max(1.0),
+            speed,
+            hits: 0,
+            hits_needed: hits_needed.max(1),
+            scale,
+        };
+        game.place();
+        game
+    }
+
+    /// Moves the target somewhere random, heading off in a random direction.
+    fn place(&mut self) {
+        let (x, y, angle) = rng::with_rng(|rng| {
+            (
+                rng.random_range(self.radius..=(self.width - self.radius).max(self.radius)),
+                rng.random_range(self.radius..=(self.height - self.radius).max(self.radius)),
+                rng.random_range(0.0..std::f32::consts::TAU),
+            )
+        });
+        self.x = x;
+        self.y = y;
+        self.vx = angle.cos() * self.speed;
+        self.vy = angle.sin() * self.speed;
+    }
+
+    /// Moves the target on by `elapsed`, bouncing it off the edges.
+    pub fn step(&mut self, elapsed: Duration) {
+        let elapsed = elapsed.as_secs_f32();
+        let (x, vx) = bounce(self.x + self.vx * elapsed, self.vx, self.radius, self.width);
+        let (y, vy) = bounce(
+            self.y + self.vy * elapsed,
+            self.vy,
+            self.radius,
+            self.height,
+        );
+        (self.x, self.y, self.vx, self.vy) = (x, y, vx, vy);
+    }
+
+    /// A click at `(x, y)`. Returns whether it hit the target, which then jumps somewhere else
+    /// and speeds up.
+    pub fn click(&mut self, x: f32, y: f32) -> bool {
+        if self.won() || (x - self.x).hypot(y - self.y) > self.radius {
+            return false;
+        }
+
+        self.hits += 1;
+        self.speed *= SPEEDUP;
+        self.place();
+
+        true
+    }
+
+    pub fn hits(&self) -> u32 {
+        self.hits
+    }
+
+    pub fn won(&self) -> bool {
+        self.hits >= self.hits_needed
+    }
+
+    /// Draws the game over `background`: the target as a ring of `color` around a white centre,
+    /// and a dot for every hit needed, filled in for the ones so far.
+    pub fn render(&self, pixmap: &mut Pixmap, background: Color, color: Color) {
+        pixmap.fill(background);
+
+        let mut paint = Paint::default();
+        paint.anti_alias = true;
+        let mut circle = |x: f32, y: f32, radius: f32, color: Color| {
+            if let Some(path) = PathBuilder::from_circle(x, y, radius) {
+                paint.set_color(color);
+                pixmap.fill_path(&path, &paint, FillRule::Winding, Transform::default(), None);
+            }
+        };
+
+        circle(self.x, self.y, self.radius, color);
+        circle(self.x, self.y, self.radius * 0.6, Color::WHITE);
+        circle(self.x, self.y, self.radius * 0.3, color);
+
+        let (radius, gap) = (DOT_RADIUS * self.scale, DOT_GAP * self.scale);
+        let mut faded = color;
+        faded.apply_opacity(0.3);
+        for i in 0..self.hits_needed {
+            let x = gap + radius + i as f32 * (radius * 2.0 + gap);
+            circle(
+                x,
+                gap + radius,
+                radius,
+                if i < self.hits { color } else { faded },
+            );
+        }
+    }
+}
+
+/// Keeps a coordinate within `radius` of both ends of `0..size`, flipping its velocity when it
+/// runs past one.
+fn bounce(position: f32, velocity: f32, radius: f32, size: f32) -> (f32, f32) {
+    let max = (size - radius).max(radius);
+    if position < radius {
+        (radius, velocity.abs())
+    } else if position > max {
+        (max, -velocity.abs())
+    } else {
+        (position, velocity)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn target_stays_inside_the_popup() {
+        let mut game = Minigame::new((200, 100), 10.0, 500.0, 3, 1.0);
+
+        for _ in 0..1000 {
+            game.step(Duration::from_millis(16));
+            assert!((10.0..=190.0).contains(&game.x), "x = {}", game.x);
+            assert!((10.0..=90.0).contains(&game.y), "y = {}", game.y);
+        }
+    }
+
+    #[test]
+    fn won_after_enough_hits() {
+        let mut game = Minigame::new((200, 200), 10.0, 100.0, 2, 1.0);
+
+        // Well outside the target.
+        let (x, y) = (game.x, game.y);
+        assert!(!game.click(x + 20.0, y));
+        assert_eq!(game.hits(), 0);
+
+        assert!(game.click(x + 5.0, y - 5.0));
+        assert!(game.speed > 100.0);
+        let (x, y) = (game.x, game.y);
+        assert!(game.click(x, y));
+        assert!(game.won());
+
+        // Clicks after winning don't count.
+        let (x, y) = (game.x, game.y);
+        assert!(!game.click(x, y));
+        assert_eq!(game.hits(), 2);
+    }
+}
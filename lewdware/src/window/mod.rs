@@ -1,6 +1,8 @@
 mod gpu_renderer;
 mod header;
+mod info;
 mod inner_window;
+mod minigame;
 pub mod opts;
 mod pool;
 mod surface;
@@ -10,4 +12,7 @@ pub use header::HEADER_HEIGHT;
 pub use inner_window::InnerWindow;
 pub use opts::WindowOpts;
 pub use pool::WindowPool;
-pub use window_type::{ChoiceWindow, ImageWindow, PromptWindow, TextWindow, VideoWindow, WindowType};
+pub use window_type::{
+    ChoiceWindow, DEFAULT_PROMPT_SIZE, ImageWindow, MinigameStyle, MinigameWindow, PopupMedia,
+    PromptStyle, PromptWindow, TextWindow, VideoWindow, WindowType,
+};
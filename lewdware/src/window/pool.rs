@@ -49,6 +49,8 @@ impl WindowPool {
     /// Park the window offscreen and return it to the pool for later reuse.
     pub fn release(&mut self, window: Arc<Window>, transparent: bool) {
         window.set_outer_position(LogicalPosition::new(-32000i32, -32000i32));
+        // Video wallpapers lower their window to the bottom; put it back before reuse.
+        window.set_window_level(WindowLevel::AlwaysOnTop);
         if transparent {
             self.transparent.push(window);
         } else {
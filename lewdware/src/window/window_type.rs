@@ -2,10 +2,17 @@ use std::time::{Duration, Instant};
 
 use anyhow::Result;
 use egui::{RichText, TextEdit};
+use shared::{
+    locale::{tr, tr_args},
+    texts::PromptKind,
+    user_config::VideoHoverBehaviour,
+};
 use tiny_skia::{IntSize, Pixmap, PixmapMut};
+use tokio::sync::mpsc::UnboundedSender;
 use winit::{
-    dpi::{LogicalPosition, PhysicalPosition},
-    event::{Touch, WindowEvent},
+    dpi::{LogicalPosition, PhysicalPosition, PhysicalSize},
+    event::{ElementState, MouseButton, Touch, WindowEvent},
+    window::WindowId,
 };
 
 use crate::{
@@ -13,44 +20,156 @@ use crate::{
     lua::{self, ChoiceWindowOption, TextStyle},
     media::ImageData,
     text_font,
-    video::{NextFrame, VideoDecoder, VideoFrame, VideoPixelFormat},
+    video::{NextFrame, VideoDecoder, VideoFrame, VideoPixelFormat, VideoStats},
     window::{
         gpu_renderer::{DecorationOverlay, GpuRenderer, GpuRendererType},
         header::HEADER_HEIGHT,
         inner_window::InnerWindow,
+        minigame::Minigame,
         surface::Buffer,
     },
 };
 
+/// The file from the pack a popup is showing.
+#[derive(Debug, Clone)]
+pub struct PopupMedia {
+    pub id: u64,
+    /// The file's path in the pack.
+    pub name: String,
+    pub tags: Vec<String>,
+    /// The file's own size, before it was scaled to fit the popup.
+    pub width: u32,
+    pub height: u32,
+}
+
+/// The width and height of a prompt that isn't given a size.
+pub const DEFAULT_PROMPT_SIZE: u32 = 400;
+
+/// How a prompt looks, beyond its window's background color.
+#[derive(Default)]
+pub struct PromptStyle {
+    /// What the prompt asks for, which sets its heading.
+    pub kind: PromptKind,
+    /// Drawn behind the prompt, cropped to cover the window.
+    pub background: Option<ImageData>,
+    pub text_color: Option<lua::Color>,
+    /// How long a typing test gives to type the passage.
+    pub time_limit: Option<Duration>,
+}
+
+impl PopupMedia {
+    fn info_lines(&self, shown_size: PhysicalSize<u32>) -> Vec<String> {
+        let tags = if self.tags.is_empty() {
+            tr("lewdware-info-no-tags")
+        } else {
+            tr_args(
+                "lewdware-info-tags",
+                [("tags", self.tags.join(", ").into())],
+            )
+        };
+        let size = tr_args(
+            "lewdware-info-size",
+            [
+                ("width", self.width.into()),
+                ("height", self.height.into()),
+                ("shown_width", shown_size.width.into()),
+                ("shown_height", shown_size.height.into()),
+            ],
+        );
+
+        vec![self.name.clone(), tags, size]
+    }
+}
+
+fn toggle_media_info(inner_window: &mut InnerWindow, media: Option<&PopupMedia>) {
+    if inner_window.showing_info() {
+        inner_window.set_info(None);
+    } else if let Some(media) = media {
+        let lines = media.info_lines(inner_window.inner_size());
+        inner_window.set_info(Some(&lines));
+    }
+}
+
+/// What the event loop needs from every kind of popup. The app drives windows only through this
+/// in `window_event` and `about_to_wait`, so a new kind of window needs an implementation of
+/// this and a [`WindowType`] variant, but no changes to the dispatch there. The variants are only
+/// matched on for requests that make sense for one kind of window, like setting a prompt's text.
+pub trait PopupWindow {
+    fn inner_window(&self) -> &InnerWindow;
+
+    fn inner_window_mut(&mut self) -> &mut InnerWindow;
+
+    /// Handles an event for this window. Events that every window handles the same way (being
+    /// closed, and the cursor and mouse buttons for dragging and the close button) are handled
+    /// by the app afterwards.
+    fn handle_event(&mut self, event: &WindowEvent);
+
+    /// Called on every pass of the event loop. Returns when the window next needs updating, if
+    /// it's animating: the loop sleeps until the earliest of these across all windows, or the
+    /// next event.
+    fn update(&mut self) -> Result<Option<Instant>> {
+        Ok(None)
+    }
+
+    /// Whether the window is done and should be closed, e.g. because its video has finished.
+    fn wants_close(&self) -> bool {
+        false
+    }
+
+    /// The media the window shows, if it shows one from the pack (see
+    /// [`ImageWindow::set_media`]).
+    fn media_id(&self) -> Option<u64> {
+        None
+    }
+
+    /// Shows or hides the info overlay, for windows showing media from the pack.
+    fn toggle_info(&mut self) {}
+
+    fn created_at(&self) -> Instant {
+        self.inner_window().created_at()
+    }
+}
+
 pub enum WindowType {
     Image(ImageWindow),
     Video(VideoWindow),
     Prompt(PromptWindow),
     Choice(ChoiceWindow),
     Text(TextWindow),
+    Minigame(MinigameWindow),
 }
 
 impl WindowType {
-    pub fn inner_window(&self) -> &InnerWindow {
+    pub fn popup(&self) -> &dyn PopupWindow {
         match self {
-            Self::Image(image_window) => &image_window.inner_window,
-            Self::Video(video_window) => &video_window.inner_window,
-            Self::Prompt(prompt_window) => &prompt_window.inner_window,
-            Self::Choice(choice_window) => &choice_window.inner_window,
-            Self::Text(text_window) => &text_window.inner_window,
+            Self::Image(window) => window,
+            Self::Video(window) => window,
+            Self::Prompt(window) => window,
+            Self::Choice(window) => window,
+            Self::Text(window) => window,
+            Self::Minigame(window) => window,
         }
     }
 
-    pub fn inner_window_mut(&mut self) -> &mut InnerWindow {
+    pub fn popup_mut(&mut self) -> &mut dyn PopupWindow {
         match self {
-            Self::Image(image_window) => &mut image_window.inner_window,
-            Self::Video(video_window) => &mut video_window.inner_window,
-            Self::Prompt(prompt_window) => &mut prompt_window.inner_window,
-            Self::Choice(choice_window) => &mut choice_window.inner_window,
-            Self::Text(text_window) => &mut text_window.inner_window,
+            Self::Image(window) => window,
+            Self::Video(window) => window,
+            Self::Prompt(window) => window,
+            Self::Choice(window) => window,
+            Self::Text(window) => window,
+            Self::Minigame(window) => window,
         }
     }
 
+    pub fn inner_window(&self) -> &InnerWindow {
+        self.popup().inner_window()
+    }
+
+    pub fn inner_window_mut(&mut self) -> &mut InnerWindow {
+        self.popup_mut().inner_window_mut()
+    }
+
     /// Consume this `WindowType`, dropping all rendering resources and returning the
     /// underlying `InnerWindow`. Field declaration order on each variant ensures that
     /// egui/overlay resources (which hold `Arc<Window>` clones) are dropped first.
@@ -61,6 +180,7 @@ impl WindowType {
             Self::Prompt(w) => w.inner_window,
             Self::Choice(w) => w.inner_window,
             Self::Text(w) => w.inner_window,
+            Self::Minigame(w) => w.inner_window,
         }
     }
 }
@@ -71,6 +191,7 @@ pub struct ImageWindow {
     image: Pixmap,
     gpu_renderer: Option<GpuRenderer>,
     frame_buffer: Vec<u8>,
+    media: Option<PopupMedia>,
 }
 
 impl ImageWindow {
@@ -102,9 +223,16 @@ impl ImageWindow {
             image: image_pixmap,
             gpu_renderer,
             frame_buffer,
+            media: None,
         })
     }
 
+    /// Sets the media shown in the window, so the user can block it or find out where it's
+    /// from.
+    pub fn set_media(&mut self, media: PopupMedia) {
+        self.media = Some(media);
+    }
+
     pub fn draw(&mut self) -> Result<Option<wgpu::SubmissionIndex>> {
         self.inner_window.start_render()?;
         let (x, y) = self.inner_window.inner_offset();
@@ -127,6 +255,7 @@ impl ImageWindow {
 
                 buffer.copy_from_pixmap(&self.image, x, y);
                 self.inner_window.render_decorations(&mut buffer)?;
+                self.inner_window.render_info(&mut buffer);
             }
 
             gpu_renderer.upload_frame_buffer(
@@ -160,6 +289,32 @@ impl ImageWindow {
     }
 }
 
+impl PopupWindow for ImageWindow {
+    fn inner_window(&self) -> &InnerWindow {
+        &self.inner_window
+    }
+
+    fn inner_window_mut(&mut self) -> &mut InnerWindow {
+        &mut self.inner_window
+    }
+
+    fn media_id(&self) -> Option<u64> {
+        self.media.as_ref().map(|media| media.id)
+    }
+
+    fn toggle_info(&mut self) {
+        toggle_media_info(&mut self.inner_window, self.media.as_ref());
+    }
+
+    fn handle_event(&mut self, event: &WindowEvent) {
+        if let WindowEvent::RedrawRequested = event {
+            if let Err(err) = self.draw() {
+                tracing::error!("Error drawing image window: {err}");
+            }
+        }
+    }
+}
+
 /// A video popup, rendered using wgpu (GPU path) or software YUV conversion (CPU fallback).
 pub struct VideoWindow {
     pub inner_window: InnerWindow,
@@ -167,12 +322,25 @@ pub struct VideoWindow {
     last_frame_time: Instant,
     duration: Option<Duration>,
     paused: bool,
+    // Set when the video was paused because the cursor is over it, so that moving away only
+    // resumes videos we paused ourselves (and not ones paused from Lua).
+    hover_paused: bool,
+    // The audio is muted if either of these is set: `muted` by the global mute hotkey,
+    // `hover_muted` while the cursor is over the window.
+    muted: bool,
+    hover_muted: bool,
+    // What happens when the cursor enters the window. Video wallpapers leave this as `None`.
+    hover_behaviour: VideoHoverBehaviour,
+    // Set once the video has finished, or the cursor entered a window that closes on hover.
+    wants_close: bool,
     // Present when the window was initialised with GPU support.
     gpu_renderer: Option<GpuRenderer>,
     // GPU path: RGBA overlay for decorations / UI.
     ui_frame_buffer: Vec<u8>,
     // CPU path: ARGB pixel buffer sized to inner_size (display area).
     cpu_frame_buffer: Vec<u32>,
+    // Left unset for video wallpapers, which can't be blocked.
+    media: Option<PopupMedia>,
 }
 
 impl VideoWindow {
@@ -216,13 +384,20 @@ impl VideoWindow {
             last_frame_time: Instant::now(),
             duration: None,
             paused: false,
+            hover_paused: false,
+            muted: false,
+            hover_muted: false,
+            hover_behaviour: VideoHoverBehaviour::None,
+            wants_close: false,
             gpu_renderer,
             ui_frame_buffer,
             cpu_frame_buffer,
+            media: None,
         })
     }
 
-    pub fn update(&mut self) -> Result<bool> {
+    /// Renders the next frame, if it's due. Returns `true` once the video has finished.
+    fn render_next_frame(&mut self) -> Result<bool> {
         self.inner_window.start_render()?;
 
         if self.inner_window.is_gpu() {
@@ -243,7 +418,9 @@ impl VideoWindow {
                 )
                 .unwrap();
                 let mut buffer = Buffer::Pixmap(pixmap);
-                self.inner_window.render_decorations(&mut buffer)?
+                let decorations = self.inner_window.render_decorations(&mut buffer)?;
+                let info = self.inner_window.render_info_overlay(&mut buffer);
+                decorations || info
             };
 
             if decorations_rendered {
@@ -358,24 +535,111 @@ impl VideoWindow {
     pub fn pause(&mut self) {
         self.video_player.pause();
         self.paused = true;
+        self.hover_paused = false;
 
         if let Some(duration) = self.duration.take() {
             self.duration = Some(duration - self.last_frame_time.elapsed());
         }
     }
 
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
     pub fn play(&mut self) {
         self.paused = false;
+        self.hover_paused = false;
         self.last_frame_time = Instant::now();
 
         self.video_player.play();
     }
+
+    pub fn set_muted(&mut self, muted: bool) {
+        self.muted = muted;
+        self.update_muted();
+    }
+
+    fn update_muted(&self) {
+        self.video_player.set_muted(self.muted || self.hover_muted);
+    }
+
+    pub fn stats(&self) -> VideoStats {
+        self.video_player.stats()
+    }
+
+    pub fn set_hover_behaviour(&mut self, behaviour: VideoHoverBehaviour) {
+        self.hover_behaviour = behaviour;
+    }
+
+    pub fn set_media(&mut self, media: PopupMedia) {
+        self.media = Some(media);
+    }
+
+    /// Applies the [`VideoHoverBehaviour`] when the cursor enters (`hovered = true`) or leaves
+    /// the window.
+    fn set_hovered(&mut self, hovered: bool) {
+        match self.hover_behaviour {
+            VideoHoverBehaviour::None => {}
+            VideoHoverBehaviour::Mute => {
+                self.hover_muted = hovered;
+                self.update_muted();
+            }
+            VideoHoverBehaviour::Pause => {
+                if hovered && !self.paused {
+                    self.pause();
+                    self.hover_paused = true;
+                } else if !hovered && self.hover_paused {
+                    self.play();
+                }
+            }
+            VideoHoverBehaviour::Close => self.wants_close |= hovered,
+        }
+    }
+}
+
+// Video windows are driven from `update` rather than through `RedrawRequested`, waking the event
+// loop when each frame is due; see `about_to_wait` in the app for why.
+impl PopupWindow for VideoWindow {
+    fn inner_window(&self) -> &InnerWindow {
+        &self.inner_window
+    }
+
+    fn inner_window_mut(&mut self) -> &mut InnerWindow {
+        &mut self.inner_window
+    }
+
+    fn handle_event(&mut self, event: &WindowEvent) {
+        match event {
+            WindowEvent::CursorEntered { .. } => self.set_hovered(true),
+            WindowEvent::CursorLeft { .. } => self.set_hovered(false),
+            _ => {}
+        }
+    }
+
+    fn update(&mut self) -> Result<Option<Instant>> {
+        if self.render_next_frame()? {
+            self.wants_close = true;
+            return Ok(None);
+        }
+
+        Ok(self.video_player.next_frame_at())
+    }
+
+    fn wants_close(&self) -> bool {
+        self.wants_close
+    }
+
+    fn media_id(&self) -> Option<u64> {
+        self.media.as_ref().map(|media| media.id)
+    }
+
+    fn toggle_info(&mut self) {
+        toggle_media_info(&mut self.inner_window, self.media.as_ref());
+    }
 }
 
 pub struct PromptWindow {
-    text: Option<String>,
-    placeholder: Option<String>,
-    value: String,
+    contents: PromptContents,
     egui_cpu: Option<EguiCPUWindow>,
     egui_gpu: Option<EguiGpuRenderer>,
     decoration_overlay: Option<DecorationOverlay>,
@@ -389,6 +653,7 @@ impl PromptWindow {
         text: Option<String>,
         placeholder: Option<String>,
         initial_value: Option<String>,
+        style: PromptStyle,
     ) -> Result<Self> {
         let (egui_cpu, egui_gpu, decoration_overlay) = if inner_window.is_gpu() {
             let surface_format = inner_window.surface_format().unwrap();
@@ -428,9 +693,14 @@ impl PromptWindow {
         };
 
         Ok(Self {
-            text,
-            placeholder,
-            value: initial_value.unwrap_or_default(),
+            contents: PromptContents {
+                text,
+                placeholder,
+                value: initial_value.unwrap_or_default(),
+                timer: style.time_limit.map(PromptTimer::new),
+                style,
+                background_texture: None,
+            },
             egui_cpu,
             egui_gpu,
             decoration_overlay,
@@ -438,7 +708,7 @@ impl PromptWindow {
         })
     }
 
-    pub fn handle_event(&mut self, event: &WindowEvent) {
+    fn forward_to_egui(&mut self, event: &WindowEvent) {
         let translated = if self.inner_window.decorations() {
             Some(translate_event_position(
                 event.clone(),
@@ -470,43 +740,12 @@ impl PromptWindow {
             let window = self.inner_window.window().clone();
 
             // Render egui into the intermediate texture.
-            let text = self.text.clone();
-            let placeholder = self.placeholder.clone();
+            let contents = &mut self.contents;
             self.egui_gpu.as_mut().unwrap().render_to_texture(
                 &wgpu_state,
                 &window,
                 inner_size,
-                |ui| {
-                    egui::CentralPanel::default().show_inside(ui, |ui| {
-                        ui.with_layout(egui::Layout::top_down(egui::Align::Center), |ui| {
-                            ui.heading("Repeat after me");
-                            ui.add_space(20.0);
-
-                            if let Some(text) = &text {
-                                ui.label(RichText::new(text).heading());
-                            }
-
-                            let mut prompt = TextEdit::singleline(&mut self.value);
-                            if let Some(placeholder) = &placeholder {
-                                prompt = prompt.hint_text(placeholder);
-                            }
-                            let response = ui.add(prompt);
-                            response.request_focus();
-
-                            ui.add_space(ui.available_height() - 50.0);
-                            ui.with_layout(egui::Layout::top_down(egui::Align::Center), |ui| {
-                                if ui.add(egui::Button::new("Submit")).clicked() {
-                                    if let Err(err) = lua_event_tx.send(lua::Event::PromptSubmit {
-                                        id,
-                                        text: self.value.clone(),
-                                    }) {
-                                        tracing::error!("{err}");
-                                    }
-                                }
-                            });
-                        });
-                    });
-                },
+                |ui| contents.ui(ui, id, &lua_event_tx),
             )?;
 
             // Upload header pixmap to decoration overlay if it changed.
@@ -554,8 +793,9 @@ impl PromptWindow {
                 }
             })?;
         } else {
-            // CPU (softbuffer) path — unchanged behaviour.
+            // CPU (softbuffer) path.
             let egui_cpu = self.egui_cpu.as_mut().unwrap();
+            let contents = &mut self.contents;
             self.inner_window.draw_softbuffer(|buffer| {
                 let mut egui_buffer = vec![0u32; (inner_size.width * inner_size.height) as usize];
                 let mut buffer_ref = egui_software_backend::BufferMutRef::new(
@@ -564,37 +804,7 @@ impl PromptWindow {
                     inner_size.height as usize,
                 );
 
-                let _ = egui_cpu.redraw(&mut buffer_ref, |ui| {
-                    egui::CentralPanel::default().show_inside(ui, |ui| {
-                        ui.with_layout(egui::Layout::top_down(egui::Align::Center), |ui| {
-                            ui.heading("Repeat after me");
-                            ui.add_space(20.0);
-
-                            if let Some(text) = &self.text {
-                                ui.label(RichText::new(text).heading());
-                            }
-
-                            let mut prompt = TextEdit::singleline(&mut self.value);
-                            if let Some(placeholder) = &self.placeholder {
-                                prompt = prompt.hint_text(placeholder);
-                            }
-                            let response = ui.add(prompt);
-                            response.request_focus();
-
-                            ui.add_space(ui.available_height() - 50.0);
-                            ui.with_layout(egui::Layout::top_down(egui::Align::Center), |ui| {
-                                if ui.add(egui::Button::new("Submit")).clicked() {
-                                    if let Err(err) = lua_event_tx.send(lua::Event::PromptSubmit {
-                                        id,
-                                        text: self.value.clone(),
-                                    }) {
-                                        tracing::error!("{err}");
-                                    }
-                                }
-                            });
-                        });
-                    });
-                });
+                let _ = egui_cpu.redraw(&mut buffer_ref, |ui| contents.ui(ui, id, &lua_event_tx));
 
                 buffer.copy_from_u32_buf(&egui_buffer, inner_size.width, ox, oy);
             })?;
@@ -604,14 +814,211 @@ impl PromptWindow {
     }
 
     pub fn set_text(&mut self, text: Option<String>) {
-        self.text = text;
+        self.contents.text = text;
         self.inner_window.window().request_redraw();
     }
 
     pub fn set_value(&mut self, value: Option<String>) {
-        self.value = value.unwrap_or_default();
+        self.contents.value = value.unwrap_or_default();
         self.inner_window.window().request_redraw();
     }
+
+    /// Starts a typing test over with a new passage, clearing what's been typed so far.
+    pub fn restart_typing_test(&mut self, text: String, time_limit: Duration) {
+        self.contents.text = Some(text);
+        self.contents.value.clear();
+        self.contents.timer = Some(PromptTimer::new(time_limit));
+        self.inner_window.window().request_redraw();
+    }
+}
+
+/// A typing test's countdown. Whatever's been typed is submitted once it runs out.
+struct PromptTimer {
+    started: Instant,
+    limit: Duration,
+    /// The whole seconds left that were last drawn.
+    shown: u64,
+    /// Stops the timer once something's been submitted, until the test is started over.
+    submitted: bool,
+}
+
+impl PromptTimer {
+    fn new(limit: Duration) -> Self {
+        Self {
+            started: Instant::now(),
+            limit,
+            shown: limit.as_secs_f64().ceil() as u64,
+            submitted: false,
+        }
+    }
+
+    fn left(&self) -> Duration {
+        self.limit.saturating_sub(self.started.elapsed())
+    }
+
+    fn seconds_left(&self) -> u64 {
+        self.left().as_secs_f64().ceil() as u64
+    }
+}
+
+/// What a prompt shows, kept apart from its window so the egui closures can borrow it.
+struct PromptContents {
+    text: Option<String>,
+    placeholder: Option<String>,
+    value: String,
+    timer: Option<PromptTimer>,
+    style: PromptStyle,
+    /// Uploaded from `style.background` the first time the prompt is drawn.
+    background_texture: Option<egui::TextureHandle>,
+}
+
+impl PromptContents {
+    fn ui(&mut self, ui: &mut egui::Ui, id: WindowId, lua_event_tx: &UnboundedSender<lua::Event>) {
+        egui::CentralPanel::default().show_inside(ui, |ui| {
+            if let Some(background) = &self.style.background {
+                let texture = self.background_texture.get_or_insert_with(|| {
+                    ui.ctx().load_texture(
+                        "prompt-background",
+                        egui::ColorImage::from_rgba_unmultiplied(
+                            [background.width() as usize, background.height() as usize],
+                            background.as_raw(),
+                        ),
+                        egui::TextureOptions::LINEAR,
+                    )
+                });
+
+                let rect = ui.clip_rect();
+                ui.painter().image(
+                    texture.id(),
+                    rect,
+                    cover_uv(texture.size_vec2(), rect.size()),
+                    egui::Color32::WHITE,
+                );
+            }
+
+            if let Some(color) = self.style.text_color {
+                ui.visuals_mut().override_text_color = Some(to_color32(color));
+            }
+
+            ui.with_layout(egui::Layout::top_down(egui::Align::Center), |ui| {
+                ui.heading(tr(match self.style.kind {
+                    PromptKind::RepeatText => "lewdware-prompt-heading",
+                    PromptKind::Arithmetic => "lewdware-prompt-heading-arithmetic",
+                    PromptKind::TypingTest => "lewdware-prompt-heading-typing-test",
+                }));
+                ui.add_space(20.0);
+
+                if let Some(text) = &self.text {
+                    ui.label(RichText::new(text).heading());
+                }
+
+                if let Some(timer) = &self.timer {
+                    ui.label(tr_args(
+                        "lewdware-prompt-time-left",
+                        [("seconds", timer.seconds_left().into())],
+                    ));
+                }
+
+                let mut prompt = TextEdit::singleline(&mut self.value);
+                if let Some(placeholder) = &self.placeholder {
+                    prompt = prompt.hint_text(placeholder);
+                }
+                let response = ui.add(prompt);
+                response.request_focus();
+
+                ui.add_space(ui.available_height() - 50.0);
+                ui.with_layout(egui::Layout::top_down(egui::Align::Center), |ui| {
+                    if ui
+                        .add(egui::Button::new(tr("lewdware-prompt-submit")))
+                        .clicked()
+                    {
+                        let timed_out = self.timer.as_mut().is_some_and(|timer| {
+                            timer.submitted = true;
+                            timer.left().is_zero()
+                        });
+                        if let Err(err) = lua_event_tx.send(lua::Event::PromptSubmit {
+                            id,
+                            text: self.value.clone(),
+                            timed_out,
+                        }) {
+                            tracing::error!("{err}");
+                        }
+                    }
+                });
+            });
+        });
+    }
+}
+
+/// The part of an image that covers a `size` rect without stretching, cropping the middle.
+fn cover_uv(image: egui::Vec2, size: egui::Vec2) -> egui::Rect {
+    let scale = (size.x / image.x).max(size.y / image.y);
+    let uv = egui::vec2(size.x / scale / image.x, size.y / scale / image.y);
+
+    egui::Rect::from_center_size(egui::pos2(0.5, 0.5), uv)
+}
+
+impl PopupWindow for PromptWindow {
+    fn inner_window(&self) -> &InnerWindow {
+        &self.inner_window
+    }
+
+    fn inner_window_mut(&mut self) -> &mut InnerWindow {
+        &mut self.inner_window
+    }
+
+    fn handle_event(&mut self, event: &WindowEvent) {
+        match event {
+            WindowEvent::RedrawRequested => {
+                if let Err(err) = self.render() {
+                    tracing::error!("Error rendering prompt window: {err}");
+                }
+            }
+            event => self.forward_to_egui(event),
+        }
+    }
+
+    /// Ticks a typing test's timer down a second at a time, and submits what's been typed once
+    /// it runs out.
+    fn update(&mut self) -> Result<Option<Instant>> {
+        let Some(timer) = self
+            .contents
+            .timer
+            .as_mut()
+            .filter(|timer| !timer.submitted)
+        else {
+            return Ok(None);
+        };
+
+        let seconds = timer.seconds_left();
+        if seconds != timer.shown {
+            timer.shown = seconds;
+            self.inner_window.window().request_redraw();
+        }
+
+        if seconds > 0 {
+            // When the next second is up.
+            let left = timer.left();
+            return Ok(Some(
+                Instant::now() + left.saturating_sub(Duration::from_secs(seconds - 1)),
+            ));
+        }
+
+        timer.submitted = true;
+        if let Err(err) = self
+            .inner_window
+            .lua_event_tx()
+            .send(lua::Event::PromptSubmit {
+                id: self.inner_window.window().id(),
+                text: self.contents.value.clone(),
+                timed_out: true,
+            })
+        {
+            tracing::error!("{err}");
+        }
+
+        Ok(None)
+    }
 }
 
 pub struct ChoiceWindow {
@@ -675,7 +1082,7 @@ impl ChoiceWindow {
         })
     }
 
-    pub fn handle_event(&mut self, event: &WindowEvent) {
+    fn forward_to_egui(&mut self, event: &WindowEvent) {
         let translated = if self.inner_window.decorations() {
             Some(translate_event_position(
                 event.clone(),
@@ -786,6 +1193,7 @@ impl ChoiceWindow {
             })?;
         } else {
             let egui_cpu = self.egui_cpu.as_mut().unwrap();
+            let contents = &mut self.contents;
             self.inner_window.draw_softbuffer(|buffer| {
                 let mut egui_buffer = vec![0u32; (inner_size.width * inner_size.height) as usize];
                 let mut buffer_ref = egui_software_backend::BufferMutRef::new(
@@ -834,7 +1242,7 @@ impl ChoiceWindow {
     }
 
     pub fn set_text(&mut self, text: Option<String>) {
-        self.text = text;
+        self.contents.text = text;
         self.inner_window.window().request_redraw();
     }
 
@@ -844,6 +1252,27 @@ impl ChoiceWindow {
     }
 }
 
+impl PopupWindow for ChoiceWindow {
+    fn inner_window(&self) -> &InnerWindow {
+        &self.inner_window
+    }
+
+    fn inner_window_mut(&mut self) -> &mut InnerWindow {
+        &mut self.inner_window
+    }
+
+    fn handle_event(&mut self, event: &WindowEvent) {
+        match event {
+            WindowEvent::RedrawRequested => {
+                if let Err(err) = self.render() {
+                    tracing::error!("Error rendering choice window: {err}");
+                }
+            }
+            event => self.forward_to_egui(event),
+        }
+    }
+}
+
 /// A window displaying static text. Unlike `PromptWindow`/`ChoiceWindow`, the egui content has
 /// no interactive widgets, so (per egui's repaint-on-demand model) it only ever redraws when
 /// `set_text()` is called or the window is first shown — the same one-shot behaviour as
@@ -916,7 +1345,7 @@ impl TextWindow {
         })
     }
 
-    pub fn handle_event(&mut self, event: &WindowEvent) {
+    fn forward_to_egui(&mut self, event: &WindowEvent) {
         let translated = if self.inner_window.decorations() {
             Some(translate_event_position(
                 event.clone(),
@@ -995,6 +1424,7 @@ impl TextWindow {
             })?;
         } else {
             let egui_cpu = self.egui_cpu.as_mut().unwrap();
+            let contents = &mut self.contents;
             self.inner_window.draw_softbuffer(|buffer| {
                 let mut egui_buffer = vec![0u32; (inner_size.width * inner_size.height) as usize];
                 let mut buffer_ref = egui_software_backend::BufferMutRef::new(
@@ -1019,6 +1449,233 @@ impl TextWindow {
     }
 }
 
+impl PopupWindow for TextWindow {
+    fn inner_window(&self) -> &InnerWindow {
+        &self.inner_window
+    }
+
+    fn inner_window_mut(&mut self) -> &mut InnerWindow {
+        &mut self.inner_window
+    }
+
+    fn handle_event(&mut self, event: &WindowEvent) {
+        match event {
+            WindowEvent::RedrawRequested => {
+                if let Err(err) = self.render() {
+                    tracing::error!("Error rendering text window: {err}");
+                }
+            }
+            event => self.forward_to_egui(event),
+        }
+    }
+}
+
+/// How long the target in a minigame waits between moves.
+const MINIGAME_FRAME: Duration = Duration::from_millis(16);
+
+/// How a minigame plays. Sizes and speeds are logical pixels.
+#[derive(Debug, Clone, Copy)]
+pub struct MinigameStyle {
+    /// How many times the target has to be clicked to close the popup.
+    pub hits: u32,
+    pub target_radius: f32,
+    /// How fast the target starts off moving, in pixels per second.
+    pub speed: f32,
+    pub target_color: lua::Color,
+}
+
+/// A popup with a target bouncing around it, that only closes once the target has been clicked
+/// enough times (see [`Minigame`]). Drawn into a pixmap every frame and copied over like an image
+/// popup's.
+pub struct MinigameWindow {
+    pub inner_window: InnerWindow,
+    game: Minigame,
+    target_color: tiny_skia::Color,
+    background: tiny_skia::Color,
+    pixmap: Pixmap,
+    cursor: Option<PhysicalPosition<f64>>,
+    last_update: Instant,
+    gpu_renderer: Option<GpuRenderer>,
+    frame_buffer: Vec<u8>,
+}
+
+impl MinigameWindow {
+    pub fn new(inner_window: InnerWindow, style: MinigameStyle) -> Result<Self> {
+        let inner_size = inner_window.inner_size();
+        let scale_factor = inner_window.window().scale_factor() as f32;
+        let color = |color: lua::Color| {
+            tiny_skia::Color::from_rgba(color.r, color.g, color.b, color.a)
+                .ok_or_else(|| anyhow::anyhow!("Invalid color {color:?}"))
+        };
+
+        let game = Minigame::new(
+            (inner_size.width, inner_size.height),
+            style.target_radius * scale_factor,
+            style.speed * scale_factor,
+            style.hits,
+            scale_factor,
+        );
+        let background = match inner_window.background_color() {
+            Some(background) => color(background)?,
+            None => tiny_skia::Color::from_rgba8(248, 248, 248, 255),
+        };
+        let pixmap = Pixmap::new(inner_size.width, inner_size.height)
+            .ok_or_else(|| anyhow::anyhow!("Invalid window size {inner_size:?}"))?;
+
+        let (gpu_renderer, frame_buffer) = if inner_window.is_gpu() {
+            let outer_size = inner_window.outer_size();
+            let frame_buffer = vec![0; (outer_size.width * outer_size.height * 4) as usize];
+            let gpu_renderer = GpuRenderer::new_image(
+                inner_window.wgpu_state(),
+                outer_size.width,
+                outer_size.height,
+                inner_window.opacity,
+                inner_window.premultiplied_alpha(),
+                inner_window.force_opaque(),
+            );
+            (Some(gpu_renderer), frame_buffer)
+        } else {
+            (None, Vec::new())
+        };
+
+        Ok(Self {
+            inner_window,
+            game,
+            target_color: color(style.target_color)?,
+            background,
+            pixmap,
+            cursor: None,
+            last_update: Instant::now(),
+            gpu_renderer,
+            frame_buffer,
+        })
+    }
+
+    pub fn draw(&mut self) -> Result<Option<wgpu::SubmissionIndex>> {
+        self.game
+            .render(&mut self.pixmap, self.background, self.target_color);
+
+        self.inner_window.start_render()?;
+        let (x, y) = self.inner_window.inner_offset();
+
+        if let Some(gpu_renderer) = &mut self.gpu_renderer {
+            gpu_renderer.set_opacity(self.inner_window.wgpu_state(), self.inner_window.opacity);
+
+            let outer_size = self.inner_window.outer_size();
+            {
+                let pixmap = PixmapMut::from_bytes(
+                    &mut self.frame_buffer,
+                    outer_size.width,
+                    outer_size.height,
+                )
+                .unwrap();
+                let mut buffer = Buffer::Pixmap(pixmap);
+
+                buffer.copy_from_pixmap(&self.pixmap, x, y);
+                self.inner_window.render_decorations(&mut buffer)?;
+            }
+
+            gpu_renderer.upload_frame_buffer(
+                &self.inner_window.wgpu_state().queue,
+                &self.frame_buffer,
+                outer_size.width,
+                outer_size.height,
+            );
+
+            let pipeline = self
+                .inner_window
+                .wgpu_state()
+                .get_pipeline(self.inner_window.surface_format().unwrap());
+
+            return self.inner_window.draw_wgpu(|rpass, _x, _y| {
+                if let GpuRendererType::Image { bind_group, .. } = &gpu_renderer.renderer_type {
+                    rpass.set_pipeline(&pipeline);
+                    rpass.set_bind_group(0, bind_group, &[]);
+                    rpass.set_bind_group(1, &gpu_renderer.window_bind_group, &[]);
+                    rpass.draw(0..4, 0..1);
+                }
+            });
+        } else {
+            let pixmap = &self.pixmap;
+            self.inner_window.draw_softbuffer(|buffer| {
+                buffer.copy_from_pixmap(pixmap, x, y);
+            })?;
+        }
+
+        Ok(None)
+    }
+
+    fn click(&mut self) {
+        let Some(cursor) = self.cursor else {
+            return;
+        };
+        let (x, y) = self.inner_window.inner_offset();
+
+        if !self
+            .game
+            .click(cursor.x as f32 - x as f32, cursor.y as f32 - y as f32)
+        {
+            return;
+        }
+
+        let hit = lua::Event::MinigameHit {
+            id: self.inner_window.window().id(),
+            hits: self.game.hits(),
+        };
+        if let Err(err) = self.inner_window.lua_event_tx().send(hit) {
+            tracing::error!("{err}");
+        }
+    }
+}
+
+impl PopupWindow for MinigameWindow {
+    fn inner_window(&self) -> &InnerWindow {
+        &self.inner_window
+    }
+
+    fn inner_window_mut(&mut self) -> &mut InnerWindow {
+        &mut self.inner_window
+    }
+
+    fn handle_event(&mut self, event: &WindowEvent) {
+        match event {
+            WindowEvent::RedrawRequested => {
+                if let Err(err) = self.draw() {
+                    tracing::error!("Error drawing minigame window: {err}");
+                }
+            }
+            WindowEvent::CursorMoved { position, .. } => self.cursor = Some(*position),
+            WindowEvent::CursorLeft { .. } => self.cursor = None,
+            WindowEvent::MouseInput {
+                state: ElementState::Pressed,
+                button: MouseButton::Left,
+                ..
+            } => self.click(),
+            _ => {}
+        }
+    }
+
+    /// Moves the target along and draws it, every frame while the popup's showing.
+    fn update(&mut self) -> Result<Option<Instant>> {
+        let now = Instant::now();
+        let elapsed = now - self.last_update;
+        self.last_update = now;
+
+        if !self.inner_window.is_visible() {
+            return Ok(None);
+        }
+
+        self.game.step(elapsed);
+        self.draw()?;
+
+        Ok(Some(now + MINIGAME_FRAME))
+    }
+
+    fn wants_close(&self) -> bool {
+        self.game.won()
+    }
+}
+
 /// Paint `text` styled by `style`, centred vertically and horizontally-aligned per
 /// `style.align` within the available area. `border_color`/`bold` are faked by repainting the
 /// same laid-out `Galley` at small offsets before the crisp final draw, since egui has no native
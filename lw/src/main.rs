@@ -1,10 +1,14 @@
 mod mode;
+mod pack;
 mod update;
 
 use anyhow::Result;
 use clap::{Parser, Subcommand};
 
-use crate::mode::{ModeCommand, handle_mode_command};
+use crate::{
+    mode::{ModeCommand, handle_mode_command},
+    pack::{PackCommand, handle_pack_command},
+};
 
 #[derive(Parser)]
 #[command(name = "lw")]
@@ -21,6 +25,11 @@ enum Commands {
         #[command(subcommand)]
         command: ModeCommand,
     },
+    /// Tools for pack (.lwpack) files
+    Pack {
+        #[command(subcommand)]
+        command: PackCommand,
+    },
     /// Check for and install updates
     Update {
         /// Download and install the update
@@ -34,6 +43,7 @@ fn main() -> Result<()> {
 
     match cli.command {
         Commands::Mode { command } => handle_mode_command(command),
+        Commands::Pack { command } => handle_pack_command(command),
         Commands::Update { install } => update::run(install),
     }
 }
@@ -207,6 +207,7 @@ mod tests {
 
 fn create_metadata(
     Config {
+        schema: _,
         include: _,
         name,
         version,
@@ -2,53 +2,79 @@ use std::{collections::HashSet, path::PathBuf};
 
 use anyhow::{Context, Result, bail};
 use indexmap::IndexMap;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use serde_json::Value as JsonValue;
 use shared::mode;
 
-#[derive(Serialize, Deserialize)]
+/// Configuration file for a mode
+#[derive(Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+#[schemars(
+    title = "Mode Config",
+    extend("$id" = "https://lewdware.github.com/config.schema.json")
+)]
 pub struct Config {
+    /// The schema used by editors to check this file
+    #[serde(rename = "$schema", default, skip_serializing_if = "Option::is_none")]
+    pub schema: Option<String>,
+    /// The folders to bundle into the mode file
     pub include: Vec<PathBuf>,
+    /// The name of the mode file
     pub name: String,
     pub version: Option<String>,
     pub author: Option<String>,
     pub modes: IndexMap<String, Mode>,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
 pub struct Mode {
     pub name: String,
+    /// The path to the main lua file for the mode
     pub entrypoint: String,
     #[serde(default)]
+    #[schemars(with = "IndexMap<String, Entry>")]
     pub options: IndexMap<String, JsonValue>,
 }
 
 /// A flat option entry as parsed from JSONC.
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, JsonSchema)]
+#[schemars(extend("unevaluatedProperties" = false))]
 pub struct ModeOption {
+    /// The label shown to the user
     pub label: String,
+    /// An optional description shown below the label
     pub description: Option<String>,
+    /// If true, the value may be null/disabled. Use "enabled_by_default" to control the initial
+    /// state.
     #[serde(default)]
     pub optional: bool,
+    /// For optional options: whether the option starts enabled. Defaults to false.
     #[serde(default)]
     pub enabled_by_default: bool,
     #[serde(default)]
+    #[schemars(with = "Option<ShowWhen>")]
     pub show_when: Option<IndexMap<String, JsonValue>>,
     #[serde(flatten)]
     pub option_type: OptionType,
 }
 
 /// A group entry as parsed from JSONC.
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, JsonSchema)]
 pub struct GroupConfig {
+    /// The heading shown to the user
     pub label: String,
     pub description: Option<String>,
     #[serde(default)]
+    #[schemars(with = "Option<ShowWhen>")]
     pub show_when: Option<IndexMap<String, JsonValue>>,
+    /// The options inside this group
+    #[schemars(with = "IndexMap<String, Entry>")]
     pub options: IndexMap<String, JsonValue>,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, JsonSchema)]
 #[serde(tag = "type")]
 pub enum OptionType {
     #[serde(rename = "integer")]
@@ -78,10 +104,43 @@ pub enum OptionType {
     #[serde(rename = "enum")]
     Enum {
         default: Option<String>,
+        /// The values to pick from, mapped to the labels shown to the user
+        #[schemars(extend("minProperties" = 1))]
         values: IndexMap<String, String>,
     },
 }
 
+// Entries are kept as raw JSON until `parse_entries`, so these types only exist to describe
+// them in the schema.
+
+/// An option, or a collapsible group of options
+#[derive(JsonSchema)]
+#[serde(tag = "type")]
+#[allow(dead_code)]
+enum Entry {
+    #[serde(rename = "group")]
+    #[schemars(extend("unevaluatedProperties" = false))]
+    Group(GroupConfig),
+    #[serde(untagged)]
+    Option(ModeOption),
+}
+
+/// Map of option keys to required values. All conditions must match for this entry to be
+/// visible. null values never satisfy any condition.
+#[derive(JsonSchema)]
+#[allow(dead_code)]
+struct ShowWhen(IndexMap<String, ConditionValue>);
+
+/// A primitive value used in a show_when condition
+#[derive(JsonSchema)]
+#[serde(untagged)]
+#[allow(dead_code)]
+enum ConditionValue {
+    Bool(bool),
+    Number(f64),
+    String(String),
+}
+
 // ─── Parsing ─────────────────────────────────────────────────────────────────
 
 /// Parse a raw JSON options map into `mode::ModeEntry` values, validating that
@@ -11,6 +11,7 @@ use clap::Args;
 use notify::{Event, EventKind, Watcher};
 
 use crate::mode::build::build_to;
+use crate::mode::config::{Config, parse_entries};
 use crate::mode::{find_root, read_config, types::write_type_stubs};
 
 #[derive(Args)]
@@ -49,28 +50,18 @@ pub fn dev(args: DevArgs) -> anyhow::Result<()> {
     let build_dir = root.join("build");
     fs::create_dir_all(&build_dir)?;
 
-    let config = read_config(&root)?;
+    let (config, mode) = read_mode(&root, &args)?;
 
     let path = build_dir.join(format!("{}.lwmode", config.name));
     let mut file = BuildFile::new(path)?;
 
     println!("Created build file");
 
-    let mode = args
-        .mode
-        .clone()
-        .or_else(|| config.modes.keys().next().cloned())
-        .context("config.jsonc contains no modes")?;
-
-    if !config.modes.contains_key(&mode) {
-        bail!("Invalid mode '{mode}'");
-    }
-
     build_to(&mut file.file, &root, config)?;
 
     println!("Built");
 
-    let mut process = spawn_lewdware(&file.path, &mode)?;
+    let mut process = Some(spawn_lewdware(&file.path, &mode)?);
 
     println!("Spawned");
 
@@ -87,30 +78,57 @@ pub fn dev(args: DevArgs) -> anyhow::Result<()> {
             }
         }
 
-        terminate(&mut process);
-        process.wait().ok();
-
-        let config = read_config(&root)?;
-
-        let mode = args
-            .mode
-            .clone()
-            .or_else(|| config.modes.keys().next().cloned())
-            .context("config.jsonc contains no modes")?;
+        // A broken config shouldn't end the session: report it, keep the running instance, and
+        // try again on the next change.
+        let (config, mode) = match read_mode(&root, &args) {
+            Ok(result) => result,
+            Err(err) => {
+                eprintln!("Not reloading: {err:#}");
+                continue;
+            }
+        };
 
-        if !config.modes.contains_key(&mode) {
-            bail!("Invalid mode '{mode}'");
+        if let Some(mut process) = process.take() {
+            terminate(&mut process);
+            process.wait().ok();
         }
 
         file.file.seek(SeekFrom::Start(0))?;
-        build_to(&mut file.file, &root, config)?;
+        if let Err(err) = build_to(&mut file.file, &root, config) {
+            eprintln!("Build failed: {err:#}");
+            continue;
+        }
+
+        println!("Reloaded");
 
-        process = spawn_lewdware(&file.path, &mode)?;
+        process = Some(spawn_lewdware(&file.path, &mode)?);
     }
 
     Ok(())
 }
 
+/// Reads the config, checks its options, and works out which mode to run.
+fn read_mode(root: &Path, args: &DevArgs) -> anyhow::Result<(Config, String)> {
+    let config = read_config(root)?;
+
+    for mode in config.modes.values() {
+        parse_entries(mode.options.clone())
+            .with_context(|| format!("Error in mode '{}'", mode.name))?;
+    }
+
+    let mode = args
+        .mode
+        .clone()
+        .or_else(|| config.modes.keys().next().cloned())
+        .context("config.jsonc contains no modes")?;
+
+    if !config.modes.contains_key(&mode) {
+        bail!("Invalid mode '{mode}'");
+    }
+
+    Ok((config, mode))
+}
+
 fn spawn_lewdware(path: &Path, mode: &str) -> anyhow::Result<Child> {
     let mut command = find_lewdware_binary().context("Couldn't find lewdware binary")?;
 
@@ -2,6 +2,7 @@ mod build;
 mod config;
 mod dev;
 mod new;
+mod schema;
 mod types;
 
 use std::{
@@ -11,12 +12,14 @@ use std::{
 
 use anyhow::{Result, bail};
 use clap::Subcommand;
+use serde_json::Value as JsonValue;
 
 use crate::mode::{
     build::{BuildArgs, build},
     config::Config,
     dev::{DevArgs, dev},
     new::create_new_mode,
+    schema::{check_config, schema},
     types::types,
 };
 
@@ -32,6 +35,8 @@ pub enum ModeCommand {
     Dev(DevArgs),
     /// Update .types/lewdware.d.lua to match the installed lw version
     Types,
+    /// Print the JSON schema for config.jsonc
+    Schema,
 }
 
 pub fn handle_mode_command(command: ModeCommand) -> Result<()> {
@@ -40,6 +45,7 @@ pub fn handle_mode_command(command: ModeCommand) -> Result<()> {
         ModeCommand::Build(args) => build(args),
         ModeCommand::Dev(args) => dev(args),
         ModeCommand::Types => types(),
+        ModeCommand::Schema => schema(),
     }
 }
 
@@ -60,7 +66,11 @@ fn find_root() -> Result<PathBuf> {
 }
 
 fn read_config(root: &Path) -> Result<Config> {
-    Ok(json5::from_str(&fs::read_to_string(
-        root.join("config.jsonc"),
-    )?)?)
+    let contents = fs::read_to_string(root.join("config.jsonc"))?;
+
+    // Unknown fields are checked against the schema first, since serde can't point them out
+    // inside options (which are only parsed later).
+    check_config(&json5::from_str::<JsonValue>(&contents)?)?;
+
+    Ok(json5::from_str(&contents)?)
 }
@@ -0,0 +1,277 @@
+//! The JSON schema for `config.jsonc`, generated from the types in [`super::config`]. It's printed
+//! by `lw mode schema` (and checked in at `docs/src/data/config.schema.json`), and used to point
+//! out misspelled or misplaced fields before the config is parsed.
+
+use std::fmt;
+
+use anyhow::{Result, bail};
+use indexmap::IndexMap;
+use schemars::{Schema, schema_for};
+use serde_json::{Map, Value as JsonValue};
+
+use crate::mode::config::Config;
+
+pub fn config_schema() -> Schema {
+    schema_for!(Config)
+}
+
+/// Prints the schema for `config.jsonc`.
+pub fn schema() -> Result<()> {
+    println!("{}", serde_json::to_string_pretty(&config_schema())?);
+    Ok(())
+}
+
+/// A field in `config.jsonc` that the schema doesn't allow.
+#[derive(Debug, PartialEq)]
+pub struct UnknownField {
+    /// Where the field is, e.g. `modes.main.options.count.maxx`.
+    pub path: String,
+    /// The fields allowed next to it.
+    pub expected: Vec<String>,
+}
+
+impl fmt::Display for UnknownField {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "unknown field `{}`", self.path)?;
+        if !self.expected.is_empty() {
+            let expected: Vec<_> = self.expected.iter().map(|key| format!("`{key}`")).collect();
+            write!(f, ", expected one of {}", expected.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+/// Fails with every unknown field in `config`, if there are any. Anything else wrong with the
+/// config is left for parsing to report.
+pub fn check_config(config: &JsonValue) -> Result<()> {
+    let unknown = unknown_fields(config);
+    if unknown.is_empty() {
+        return Ok(());
+    }
+
+    let lines: Vec<_> = unknown.iter().map(|field| format!("  {field}")).collect();
+    bail!("Error in config.jsonc:\n{}", lines.join("\n"))
+}
+
+pub fn unknown_fields(config: &JsonValue) -> Vec<UnknownField> {
+    let schema = config_schema();
+    let mut checker = Checker {
+        root: schema.as_value(),
+        unknown: Vec::new(),
+    };
+    checker.check(schema.as_value(), config, "");
+    checker.unknown
+}
+
+/// The fields an object can have, merged from a schema and everything it refers to.
+#[derive(Default)]
+struct Fields<'a> {
+    properties: IndexMap<&'a str, &'a JsonValue>,
+    additional: Option<&'a JsonValue>,
+    closed: bool,
+}
+
+struct Checker<'a> {
+    root: &'a JsonValue,
+    unknown: Vec<UnknownField>,
+}
+
+impl<'a> Checker<'a> {
+    fn check(&mut self, schema: &'a JsonValue, value: &JsonValue, path: &str) {
+        let JsonValue::Object(object) = value else {
+            return;
+        };
+
+        let mut fields = Fields::default();
+        if !self.collect(schema, object, &mut fields) {
+            return;
+        }
+
+        for (key, field) in object {
+            let field_path = if path.is_empty() {
+                key.clone()
+            } else {
+                format!("{path}.{key}")
+            };
+
+            if let Some(schema) = fields.properties.get(key.as_str()) {
+                self.check(schema, field, &field_path);
+            } else if let Some(schema) = fields.additional {
+                self.check(schema, field, &field_path);
+            } else if fields.closed {
+                self.unknown.push(UnknownField {
+                    path: field_path,
+                    expected: fields
+                        .properties
+                        .keys()
+                        .map(|key| key.to_string())
+                        .collect(),
+                });
+            }
+        }
+    }
+
+    /// Adds the fields described by `schema` for `object`. Returns false if it can't tell which
+    /// fields are allowed, e.g. because `type` doesn't match any of the option types.
+    fn collect(
+        &self,
+        schema: &'a JsonValue,
+        object: &Map<String, JsonValue>,
+        fields: &mut Fields<'a>,
+    ) -> bool {
+        let JsonValue::Object(schema) = schema else {
+            return true;
+        };
+
+        if let Some(target) = schema.get("$ref").and_then(|r| self.resolve(r))
+            && !self.collect(target, object, fields)
+        {
+            return false;
+        }
+
+        if let Some(JsonValue::Object(properties)) = schema.get("properties") {
+            for (key, property) in properties {
+                fields.properties.insert(key, property);
+            }
+        }
+
+        match schema.get("additionalProperties") {
+            Some(JsonValue::Bool(false)) => fields.closed = true,
+            Some(additional @ JsonValue::Object(_)) => fields.additional = Some(additional),
+            _ => {}
+        }
+        if schema.get("unevaluatedProperties") == Some(&JsonValue::Bool(false)) {
+            fields.closed = true;
+        }
+
+        for keyword in ["oneOf", "anyOf"] {
+            let Some(JsonValue::Array(branches)) = schema.get(keyword) else {
+                continue;
+            };
+            match self.pick_branch(branches, object) {
+                Some(branch) => {
+                    if !self.collect(branch, object, fields) {
+                        return false;
+                    }
+                }
+                None => return false,
+            }
+        }
+
+        true
+    }
+
+    /// Picks the branch of a `oneOf`/`anyOf` that applies to `object`: the one whose `type`
+    /// constant matches, or else the only one without a constant.
+    fn pick_branch(
+        &self,
+        branches: &'a [JsonValue],
+        object: &Map<String, JsonValue>,
+    ) -> Option<&'a JsonValue> {
+        let branches: Vec<_> = branches
+            .iter()
+            .filter(|branch| self.allows_objects(branch))
+            .collect();
+
+        let tag = object.get("type").and_then(JsonValue::as_str);
+        if let Some(branch) = branches
+            .iter()
+            .find(|branch| tag.is_some() && self.type_const(branch) == tag)
+        {
+            return Some(*branch);
+        }
+
+        let mut untagged = branches
+            .into_iter()
+            .filter(|branch| self.type_const(branch).is_none());
+        match (untagged.next(), untagged.next()) {
+            (Some(branch), None) => Some(branch),
+            _ => None,
+        }
+    }
+
+    fn allows_objects(&self, schema: &JsonValue) -> bool {
+        match schema.get("type") {
+            Some(JsonValue::String(ty)) => ty == "object",
+            Some(JsonValue::Array(types)) => types.iter().any(|ty| ty == "object"),
+            _ => match schema.get("$ref").and_then(|r| self.resolve(r)) {
+                Some(target) => self.allows_objects(target),
+                None => true,
+            },
+        }
+    }
+
+    fn type_const(&self, schema: &'a JsonValue) -> Option<&'a str> {
+        schema
+            .pointer("/properties/type/const")
+            .and_then(JsonValue::as_str)
+            .or_else(|| {
+                let target = schema.get("$ref").and_then(|r| self.resolve(r))?;
+                self.type_const(target)
+            })
+    }
+
+    fn resolve(&self, reference: &JsonValue) -> Option<&'a JsonValue> {
+        let pointer = reference.as_str()?.strip_prefix('#')?;
+        self.root.pointer(pointer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn checked_in_schema_is_up_to_date() {
+        let checked_in: JsonValue =
+            serde_json::from_str(include_str!("../../../docs/src/data/config.schema.json"))
+                .unwrap();
+        assert!(
+            checked_in == *config_schema().as_value(),
+            "docs/src/data/config.schema.json is out of date, regenerate it with `lw mode schema`"
+        );
+    }
+
+    #[test]
+    fn finds_unknown_fields() {
+        let config = serde_json::json!({
+            "$schema": "https://lewdware.net/reference/config.schema.json",
+            "include": ["src"],
+            "name": "test",
+            "autor": "typo",
+            "modes": {
+                "main": {
+                    "name": "Main",
+                    "entrypoint": "src/main.lua",
+                    "options": {
+                        "count": { "label": "Count", "type": "integer", "default": 1, "maxx": 3 },
+                        "text": { "label": "Text", "type": "string", "default": "", "min": 1 },
+                        "group": {
+                            "type": "group",
+                            "label": "Group",
+                            "show_when": { "count": 2 },
+                            "options": {
+                                "flag": { "label": "Flag", "type": "boolean", "default": true, "color": "red" }
+                            }
+                        },
+                        "odd": { "label": "Odd", "type": "colour", "hue": 1 }
+                    }
+                }
+            }
+        });
+
+        let unknown = unknown_fields(&config);
+        let paths: Vec<_> = unknown.iter().map(|field| field.path.as_str()).collect();
+        assert_eq!(
+            paths,
+            [
+                "autor",
+                "modes.main.options.count.maxx",
+                "modes.main.options.text.min",
+                "modes.main.options.group.options.flag.color",
+            ]
+        );
+        assert!(unknown[1].expected.iter().any(|key| key == "max"));
+        assert!(!unknown[2].expected.iter().any(|key| key == "max"));
+    }
+}
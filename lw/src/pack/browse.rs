@@ -0,0 +1,396 @@
+use std::{
+    fs::{self, File},
+    path::PathBuf,
+};
+
+use anyhow::{Result, bail};
+use clap::Args;
+use ratatui::{
+    DefaultTerminal, Frame,
+    crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind},
+    layout::{Constraint, Layout},
+    style::{Style, Stylize},
+    text::{Line, Span},
+    widgets::{Block, List, ListItem, ListState, Paragraph, Wrap},
+};
+use rusqlite::Connection;
+use shared::{
+    extract,
+    manifest::{Manifest, ManifestEntry},
+};
+
+use crate::pack::open_pack;
+
+#[derive(Args)]
+pub struct BrowseArgs {
+    /// The pack file to browse
+    pack: PathBuf,
+    /// Extract files into this folder instead of the current one
+    #[arg(short, long, default_value = ".")]
+    output: PathBuf,
+}
+
+/// What the type filter cycles through, after showing every type.
+const FILE_TYPES: [&str; 3] = ["image", "video", "audio"];
+
+pub fn browse(args: BrowseArgs) -> Result<()> {
+    let pack = open_pack(&args.pack)?;
+    let manifest = Manifest::read(&pack.db, pack.metadata)?;
+    let mut extractor = Extractor {
+        db: pack.db,
+        file: File::open(&args.pack)?,
+        dir: args.output,
+    };
+
+    let mut browser = Browser::new(manifest);
+    let mut terminal = ratatui::init();
+    let result = run(&mut terminal, &mut browser, &mut extractor);
+    ratatui::restore();
+
+    result
+}
+
+fn run(
+    terminal: &mut DefaultTerminal,
+    browser: &mut Browser,
+    extractor: &mut Extractor,
+) -> Result<()> {
+    loop {
+        terminal.draw(|frame| browser.draw(frame))?;
+
+        if let Event::Key(key) = event::read()?
+            && key.kind == KeyEventKind::Press
+            && !browser.handle_key(key, extractor)
+        {
+            return Ok(());
+        }
+    }
+}
+
+/// Copies entries out of the pack, like the config app does with favorites.
+struct Extractor {
+    db: Connection,
+    file: File,
+    dir: PathBuf,
+}
+
+impl Extractor {
+    /// Returns where the entry was written to.
+    fn extract(&mut self, id: u64) -> Result<PathBuf> {
+        let Some(entry) = extract::entry(&self.db, id)? else {
+            bail!("It has no data in the pack");
+        };
+
+        let data = extract::read_entry(&mut self.file, &entry)?;
+        if let Some(checksum) = &entry.checksum
+            && blake3::hash(&data).as_bytes().as_slice() != checksum.as_slice()
+        {
+            bail!("It doesn't match its checksum; the pack may be corrupted");
+        }
+
+        fs::create_dir_all(&self.dir)?;
+        let path = extract::unused_path(&self.dir, &entry.name);
+        fs::write(&path, data)?;
+
+        Ok(path)
+    }
+}
+
+struct Browser {
+    manifest: Manifest,
+    /// The indices in `manifest.entries` of the entries that match the filters.
+    shown: Vec<usize>,
+    list: ListState,
+    /// Words that each have to be in an entry's name or one of its tags.
+    filter: String,
+    editing_filter: bool,
+    /// Only entries of this type, if set.
+    file_type: Option<&'static str>,
+    /// The result of the last extraction.
+    status: Option<String>,
+}
+
+impl Browser {
+    fn new(manifest: Manifest) -> Self {
+        let mut browser = Self {
+            manifest,
+            shown: Vec::new(),
+            list: ListState::default(),
+            filter: String::new(),
+            editing_filter: false,
+            file_type: None,
+            status: None,
+        };
+        browser.apply_filters();
+
+        browser
+    }
+
+    fn apply_filters(&mut self) {
+        let words: Vec<String> = self
+            .filter
+            .split_whitespace()
+            .map(str::to_lowercase)
+            .collect();
+
+        self.shown = self
+            .manifest
+            .entries
+            .iter()
+            .enumerate()
+            .filter(|(_, entry)| matches(entry, &words, self.file_type))
+            .map(|(i, _)| i)
+            .collect();
+
+        self.list.select((!self.shown.is_empty()).then_some(0));
+    }
+
+    fn selected(&self) -> Option<&ManifestEntry> {
+        let i = *self.shown.get(self.list.selected()?)?;
+        Some(&self.manifest.entries[i])
+    }
+
+    /// Returns false once the user has asked to quit.
+    fn handle_key(&mut self, key: KeyEvent, extractor: &mut Extractor) -> bool {
+        if self.editing_filter {
+            match key.code {
+                KeyCode::Enter => self.editing_filter = false,
+                KeyCode::Esc => {
+                    self.editing_filter = false;
+                    self.filter.clear();
+                    self.apply_filters();
+                }
+                KeyCode::Backspace => {
+                    self.filter.pop();
+                    self.apply_filters();
+                }
+                KeyCode::Char(c) => {
+                    self.filter.push(c);
+                    self.apply_filters();
+                }
+                _ => {}
+            }
+
+            return true;
+        }
+
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => return false,
+            KeyCode::Down | KeyCode::Char('j') => self.list.select_next(),
+            KeyCode::Up | KeyCode::Char('k') => self.list.select_previous(),
+            KeyCode::PageDown => self.list.scroll_down_by(20),
+            KeyCode::PageUp => self.list.scroll_up_by(20),
+            KeyCode::Home | KeyCode::Char('g') => self.list.select_first(),
+            KeyCode::End | KeyCode::Char('G') => self.list.select_last(),
+            KeyCode::Char('/') => self.editing_filter = true,
+            KeyCode::Char('t') => {
+                self.file_type = match self.file_type {
+                    None => Some(FILE_TYPES[0]),
+                    Some(file_type) => FILE_TYPES
+                        .iter()
+                        .position(|&other| other == file_type)
+                        .and_then(|i| FILE_TYPES.get(i + 1))
+                        .copied(),
+                };
+                self.apply_filters();
+            }
+            KeyCode::Enter | KeyCode::Char('e') => {
+                if let Some(entry) = self.selected() {
+                    let name = entry.name.clone();
+                    self.status = Some(match extractor.extract(entry.id) {
+                        Ok(path) => format!("Extracted {name} to {}", path.display()),
+                        Err(err) => format!("Couldn't extract {name}: {err}"),
+                    });
+                }
+            }
+            _ => {}
+        }
+
+        true
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let [header, body, footer] = Layout::vertical([
+            Constraint::Length(2),
+            Constraint::Min(0),
+            Constraint::Length(1),
+        ])
+        .areas(frame.area());
+        let [list_area, details_area] =
+            Layout::horizontal([Constraint::Percentage(60), Constraint::Percentage(40)])
+                .areas(body);
+
+        let metadata = &self.manifest.metadata;
+        let mut title = vec![metadata.name.clone().bold()];
+        if let Some(version) = &metadata.version {
+            title.push(format!(" {version}").into());
+        }
+        if let Some(creator) = &metadata.creator {
+            title.push(format!(" by {creator}").into());
+        }
+
+        let filter = format!(
+            "{} of {} files · type: {} · filter: {}{}",
+            self.shown.len(),
+            self.manifest.entries.len(),
+            self.file_type.unwrap_or("all"),
+            self.filter,
+            if self.editing_filter { "▏" } else { "" },
+        );
+        frame.render_widget(
+            Paragraph::new(vec![Line::from(title), Line::from(filter).dim()]),
+            header,
+        );
+
+        let items: Vec<ListItem> = self
+            .shown
+            .iter()
+            .map(|&i| {
+                let entry = &self.manifest.entries[i];
+                ListItem::new(Line::from(vec![
+                    Span::raw(format!("{:<6}", entry.file_type)).dim(),
+                    Span::raw(entry.name.clone()),
+                ]))
+            })
+            .collect();
+        frame.render_stateful_widget(
+            List::new(items)
+                .block(Block::bordered().title("Files"))
+                .highlight_style(Style::new().reversed()),
+            list_area,
+            &mut self.list,
+        );
+
+        let details = self.selected().map(details).unwrap_or_default();
+        frame.render_widget(
+            Paragraph::new(details)
+                .block(Block::bordered().title("Details"))
+                .wrap(Wrap { trim: false }),
+            details_area,
+        );
+
+        let help = if self.editing_filter {
+            "Type words to match in names and tags · Enter: done · Esc: clear".to_string()
+        } else {
+            self.status.clone().unwrap_or_else(|| {
+                "↑/↓: move · /: filter · t: type · e: extract · q: quit".to_string()
+            })
+        };
+        frame.render_widget(Line::from(help).dim(), footer);
+    }
+}
+
+/// Whether every word is in the entry's name or one of its tags, ignoring case, and the entry is
+/// of `file_type` (if set).
+fn matches(entry: &ManifestEntry, words: &[String], file_type: Option<&str>) -> bool {
+    if file_type.is_some_and(|file_type| entry.file_type != file_type) {
+        return false;
+    }
+
+    let name = entry.name.to_lowercase();
+    let tags: Vec<String> = entry.tags.iter().map(|tag| tag.to_lowercase()).collect();
+
+    words
+        .iter()
+        .all(|word| name.contains(word) || tags.iter().any(|tag| tag.contains(word)))
+}
+
+fn details(entry: &ManifestEntry) -> Vec<Line<'static>> {
+    let mut lines = vec![
+        Line::from(entry.name.clone().bold()),
+        Line::default(),
+        field(
+            "Type",
+            entry.codec.as_ref().map_or_else(
+                || entry.file_type.clone(),
+                |codec| format!("{} ({codec})", entry.file_type),
+            ),
+        ),
+        field("Size", format_size(entry.size)),
+    ];
+
+    if let (Some(width), Some(height)) = (entry.width, entry.height) {
+        lines.push(field("Dimensions", format!("{width} × {height}")));
+    }
+    if let Some(duration) = entry.duration {
+        lines.push(field("Duration", format!("{duration:.1} s")));
+    }
+    lines.push(field(
+        "Tags",
+        if entry.tags.is_empty() {
+            "none".to_string()
+        } else {
+            entry.tags.join(", ")
+        },
+    ));
+    if let Some(path) = &entry.path {
+        lines.push(field("Added from", path.clone()));
+    }
+    lines.push(field("Hash", entry.hash.clone()));
+    lines.push(field("Id", entry.id.to_string()));
+
+    lines
+}
+
+fn field(name: &str, value: String) -> Line<'static> {
+    Line::from(vec![Span::raw(format!("{name}: ")).dim(), Span::raw(value)])
+}
+
+fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["B", "KB", "MB", "GB"];
+
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1000.0 && unit < UNITS.len() - 1 {
+        size /= 1000.0;
+        unit += 1;
+    }
+
+    if unit == 0 {
+        format!("{bytes} B")
+    } else {
+        format!("{size:.1} {}", UNITS[unit])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(name: &str, file_type: &str, tags: &[&str]) -> ManifestEntry {
+        ManifestEntry {
+            id: 1,
+            name: name.to_string(),
+            path: None,
+            file_type: file_type.to_string(),
+            codec: None,
+            size: 0,
+            width: None,
+            height: None,
+            duration: None,
+            hash: String::new(),
+            tags: tags.iter().map(|tag| tag.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn filters_by_name_tags_and_type() {
+        let words = |filter: &str| -> Vec<String> {
+            filter.split_whitespace().map(str::to_lowercase).collect()
+        };
+        let beach = entry("Beach.png", "image", &["Outdoors", "summer"]);
+
+        assert!(matches(&beach, &words(""), None));
+        assert!(matches(&beach, &words("beach"), Some("image")));
+        assert!(matches(&beach, &words("outdoor SUMMER"), None));
+        assert!(!matches(&beach, &words("beach winter"), None));
+        assert!(!matches(&beach, &words("beach"), Some("video")));
+    }
+
+    #[test]
+    fn formats_sizes() {
+        assert_eq!(format_size(999), "999 B");
+        assert_eq!(format_size(1_500), "1.5 KB");
+        assert_eq!(format_size(23_400_000), "23.4 MB");
+    }
+}
@@ -0,0 +1,135 @@
+//! A small image composition toolkit for rendering preview cards.
+
+use ab_glyph::{Font, FontRef, PxScale, ScaleFont, point};
+use image::{
+    DynamicImage, Rgba, RgbaImage,
+    imageops::{self, FilterType},
+};
+
+const FONT: &[u8] = include_bytes!("../../../lewdware/assets/fonts/Anton-Regular.ttf");
+
+pub struct Canvas {
+    image: RgbaImage,
+    font: FontRef<'static>,
+}
+
+impl Canvas {
+    pub fn new(width: u32, height: u32, background: [u8; 3]) -> Self {
+        let [r, g, b] = background;
+
+        Self {
+            image: RgbaImage::from_pixel(width, height, Rgba([r, g, b, 255])),
+            font: FontRef::try_from_slice(FONT).expect("bundled font is valid"),
+        }
+    }
+
+    pub fn width(&self) -> u32 {
+        self.image.width()
+    }
+
+    pub fn height(&self) -> u32 {
+        self.image.height()
+    }
+
+    /// Fill the canvas with a grid of the given images, each cropped to fill its cell, then
+    /// blurred so the collage works as a background.
+    pub fn draw_collage(&mut self, images: &[DynamicImage], columns: u32, blur: f32) {
+        if images.is_empty() || columns == 0 {
+            return;
+        }
+
+        let rows = (images.len() as u32).div_ceil(columns);
+        let cell_width = self.width().div_ceil(columns);
+        let cell_height = self.height().div_ceil(rows);
+
+        let mut collage = RgbaImage::new(self.width(), self.height());
+        for (i, image) in images.iter().enumerate() {
+            let i = i as u32;
+            let cell = image
+                .resize_to_fill(cell_width, cell_height, FilterType::Triangle)
+                .to_rgba8();
+            imageops::replace(
+                &mut collage,
+                &cell,
+                ((i % columns) * cell_width) as i64,
+                ((i / columns) * cell_height) as i64,
+            );
+        }
+
+        self.image = imageops::blur(&collage, blur);
+    }
+
+    /// Darken every pixel by the given factor, from 0.0 (unchanged) to 1.0 (black).
+    pub fn darken(&mut self, amount: f32) {
+        let keep = 1.0 - amount.clamp(0.0, 1.0);
+
+        for pixel in self.image.pixels_mut() {
+            for channel in &mut pixel.0[..3] {
+                *channel = (*channel as f32 * keep) as u8;
+            }
+        }
+    }
+
+    /// The width in pixels `text` would take up at `size`.
+    pub fn text_width(&self, text: &str, size: f32) -> f32 {
+        let font = self.font.as_scaled(PxScale::from(size));
+
+        let mut width = 0.0;
+        let mut previous = None;
+        for c in text.chars() {
+            let glyph = font.glyph_id(c);
+            if let Some(previous) = previous {
+                width += font.kern(previous, glyph);
+            }
+            width += font.h_advance(glyph);
+            previous = Some(glyph);
+        }
+
+        width
+    }
+
+    /// Draw a single line of text with its top-left corner at (`x`, `y`). Returns the width of
+    /// the drawn text.
+    pub fn draw_text(&mut self, text: &str, size: f32, x: f32, y: f32, colour: [u8; 3]) -> f32 {
+        let font = self.font.as_scaled(PxScale::from(size));
+        let baseline = y + font.ascent();
+        let (width, height) = self.image.dimensions();
+
+        let mut caret = x;
+        let mut previous = None;
+        for c in text.chars() {
+            let glyph_id = font.glyph_id(c);
+            if let Some(previous) = previous {
+                caret += font.kern(previous, glyph_id);
+            }
+            let glyph = glyph_id.with_scale_and_position(size, point(caret, baseline));
+            caret += font.h_advance(glyph_id);
+            previous = Some(glyph_id);
+
+            let Some(outline) = self.font.outline_glyph(glyph) else {
+                continue;
+            };
+            let bounds = outline.px_bounds();
+
+            outline.draw(|gx, gy, coverage| {
+                let px = bounds.min.x as i64 + gx as i64;
+                let py = bounds.min.y as i64 + gy as i64;
+                if px < 0 || py < 0 || px >= width as i64 || py >= height as i64 {
+                    return;
+                }
+
+                let pixel = self.image.get_pixel_mut(px as u32, py as u32);
+                for (channel, target) in pixel.0[..3].iter_mut().zip(colour) {
+                    *channel =
+                        (*channel as f32 * (1.0 - coverage) + target as f32 * coverage) as u8;
+                }
+            });
+        }
+
+        caret - x
+    }
+
+    pub fn into_image(self) -> RgbaImage {
+        self.image
+    }
+}
@@ -0,0 +1,109 @@
+use std::{
+    collections::HashMap,
+    fs::{self, File},
+    path::PathBuf,
+};
+
+use anyhow::{Result, bail};
+use clap::Args;
+use shared::{
+    extract,
+    sidecar::{Sidecar, sidecar_path},
+    texts::{self, TextKind},
+};
+
+use crate::pack::open_pack;
+
+#[derive(Args)]
+pub struct ExtractArgs {
+    /// The pack file to extract
+    pack: PathBuf,
+    /// The folder to extract it into. It's created if it doesn't exist, and has to be empty if it
+    /// does
+    output: PathBuf,
+}
+
+/// Copies everything out of a pack, in a folder the pack editor can add back: the files, a sidecar
+/// next to each one with its tags and settings, and the notifications, prompts and links as
+/// `.csv` files. The pack's name and other details go in `metadata.json`.
+///
+/// Packs don't keep the folders their files were added from, so the files all end up next to
+/// each other.
+pub fn extract(args: ExtractArgs) -> Result<()> {
+    let pack = open_pack(&args.pack)?;
+    let mut file = File::open(&args.pack)?;
+
+    if args.output.exists() && fs::read_dir(&args.output)?.next().is_some() {
+        bail!("{} isn't empty", args.output.display());
+    }
+    fs::create_dir_all(&args.output)?;
+
+    let entries = extract::entries(&pack.db)?;
+
+    // What each file was written as, by the name it was added under, so `only_after` can point at
+    // the right file.
+    let mut written = HashMap::new();
+    let mut paths = Vec::new();
+    for entry in &entries {
+        let data = extract::read_entry(&mut file, entry)?;
+        if let Some(checksum) = &entry.checksum
+            && blake3::hash(&data).as_bytes().as_slice() != checksum.as_slice()
+        {
+            bail!(
+                "{} doesn't match its checksum; the pack may be corrupted",
+                entry.file_name
+            );
+        }
+
+        let path = extract::unused_path(&args.output, &entry.name);
+        fs::write(&path, data)?;
+
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        written
+            .entry(entry.file_name.clone())
+            .or_insert_with(|| name.into_owned());
+        paths.push(path);
+    }
+
+    for (entry, path) in entries.iter().zip(&paths) {
+        let mut sidecar = extract::sidecar(&pack.db, entry.id)?;
+        if let Some(only_after) = &mut sidecar.only_after
+            && let Some(file) = &only_after.file
+            && let Some(name) = written.get(file)
+        {
+            only_after.file = Some(name.clone());
+        }
+
+        if sidecar != Sidecar::default() {
+            fs::write(sidecar_path(path), serde_json::to_string_pretty(&sidecar)?)?;
+        }
+    }
+
+    let mut text_count = 0;
+    for kind in TextKind::ALL {
+        for locale in texts::locales(&pack.db, kind)? {
+            let entries = texts::read_texts(&pack.db, kind, locale.as_deref())?;
+            text_count += entries.len();
+
+            // The locale comes from the pack, so keep the file in the output folder whatever it is.
+            let name = texts::csv_file_name(kind, locale.as_deref());
+            fs::write(
+                extract::unused_path(&args.output, &name),
+                texts::write_csv(&entries),
+            )?;
+        }
+    }
+
+    fs::write(
+        args.output.join("metadata.json"),
+        serde_json::to_string_pretty(&pack.metadata)?,
+    )?;
+
+    println!(
+        "Extracted {} files and {text_count} texts to {}",
+        entries.len(),
+        args.output.display()
+    );
+
+    Ok(())
+}
@@ -0,0 +1,36 @@
+use std::{fs, path::PathBuf};
+
+use anyhow::Result;
+use clap::Args;
+use shared::manifest::Manifest;
+
+use crate::pack::open_pack;
+
+#[derive(Args)]
+pub struct ManifestArgs {
+    /// The pack file to list
+    pack: PathBuf,
+    /// Write the manifest to this file instead of stdout
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+}
+
+pub fn manifest(args: ManifestArgs) -> Result<()> {
+    let pack = open_pack(&args.pack)?;
+    let manifest = Manifest::read(&pack.db, pack.metadata)?;
+    let json = serde_json::to_string_pretty(&manifest)?;
+
+    match args.output {
+        Some(output) => {
+            fs::write(&output, json)?;
+            println!(
+                "Wrote manifest of {} entries to {}",
+                manifest.entries.len(),
+                output.display()
+            );
+        }
+        None => println!("{json}"),
+    }
+
+    Ok(())
+}
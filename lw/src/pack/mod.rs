@@ -0,0 +1,56 @@
+mod browse;
+mod card;
+mod extract;
+mod manifest;
+mod preview;
+mod test_glob;
+mod verify;
+
+use std::{fs::File, io::BufReader, path::Path};
+
+use anyhow::{Context, Result};
+use clap::Subcommand;
+use shared::pack_index::PackIndex;
+
+use crate::pack::{
+    browse::{BrowseArgs, browse},
+    extract::{ExtractArgs, extract},
+    manifest::{ManifestArgs, manifest},
+    preview::{PreviewArgs, preview},
+    test_glob::{TestGlobArgs, test_glob},
+    verify::{VerifyArgs, verify},
+};
+
+#[derive(Subcommand)]
+pub enum PackCommand {
+    /// Render a shareable preview card (PNG) for a pack
+    Preview(PreviewArgs),
+    /// Print a JSON listing of the media in a pack, without the media data
+    Manifest(ManifestArgs),
+    /// Check a pack for corrupted or out-of-bounds media
+    Verify(VerifyArgs),
+    /// Show which files the pack editor would skip or tag when adding a folder, going by its
+    /// .nomedia, .lewdignore and sidecar files
+    TestGlob(TestGlobArgs),
+    /// Browse the files in a pack in the terminal, filter them by tag or type, and extract them
+    Browse(BrowseArgs),
+    /// Extract everything in a pack into a folder that the pack editor can add back
+    Extract(ExtractArgs),
+}
+
+pub fn handle_pack_command(command: PackCommand) -> Result<()> {
+    match command {
+        PackCommand::Preview(args) => preview(args),
+        PackCommand::Manifest(args) => manifest(args),
+        PackCommand::Verify(args) => verify(args),
+        PackCommand::TestGlob(args) => test_glob(args),
+        PackCommand::Browse(args) => browse(args),
+        PackCommand::Extract(args) => extract(args),
+    }
+}
+
+/// Opens a pack file for reading, with its index loaded so it can be queried.
+fn open_pack(path: &Path) -> Result<PackIndex> {
+    let file = File::open(path).with_context(|| format!("Could not open {}", path.display()))?;
+    PackIndex::read(BufReader::new(file), |_| {})
+}
@@ -0,0 +1,290 @@
+use std::path::PathBuf;
+
+use anyhow::Result;
+use clap::Args;
+use rusqlite::Connection;
+
+use crate::pack::{card::Canvas, open_pack};
+
+const WIDTH: u32 = 1200;
+const HEIGHT: u32 = 630;
+const MARGIN: f32 = 60.0;
+const COLLAGE_SIZE: usize = 6;
+const MAX_TAGS: usize = 16;
+
+const WHITE: [u8; 3] = [255, 255, 255];
+const GREY: [u8; 3] = [200, 200, 200];
+const ACCENT: [u8; 3] = [255, 120, 180];
+
+#[derive(Args)]
+pub struct PreviewArgs {
+    /// The pack file to preview
+    pack: PathBuf,
+    /// Where to write the card (PNG)
+    #[arg(short, long, default_value = "card.png")]
+    output: PathBuf,
+}
+
+struct MediaCounts {
+    images: u64,
+    videos: u64,
+    audio: u64,
+}
+
+pub fn preview(args: PreviewArgs) -> Result<()> {
+    let pack = open_pack(&args.pack)?;
+
+    let counts = media_counts(&pack.db)?;
+    let tags = tag_counts(&pack.db)?;
+    let thumbnails = thumbnails(&pack.db)?;
+
+    let mut canvas = Canvas::new(WIDTH, HEIGHT, [24, 24, 28]);
+    canvas.draw_collage(&thumbnails, 3, 12.0);
+    canvas.darken(0.55);
+
+    let mut y = MARGIN;
+    canvas.draw_text(&pack.metadata.name, 80.0, MARGIN, y, WHITE);
+    y += 100.0;
+
+    if let Some(creator) = &pack.metadata.creator {
+        canvas.draw_text(&format!("by {creator}"), 32.0, MARGIN, y, GREY);
+        y += 50.0;
+    }
+
+    canvas.draw_text(&counts.summary(), 36.0, MARGIN, y, ACCENT);
+    y += 80.0;
+
+    draw_tag_cloud(&mut canvas, &tags, y);
+
+    canvas.into_image().save(&args.output)?;
+
+    println!("Wrote preview card to {}", args.output.display());
+
+    Ok(())
+}
+
+impl MediaCounts {
+    fn summary(&self) -> String {
+        let parts = [
+            (self.images, "image", "images"),
+            (self.videos, "video", "videos"),
+            (self.audio, "audio file", "audio files"),
+        ];
+
+        let parts: Vec<String> = parts
+            .into_iter()
+            .filter(|(count, _, _)| *count > 0)
+            .map(|(count, singular, plural)| {
+                format!("{count} {}", if count == 1 { singular } else { plural })
+            })
+            .collect();
+
+        if parts.is_empty() {
+            "Empty pack".to_string()
+        } else {
+            parts.join("  ·  ")
+        }
+    }
+}
+
+fn media_counts(db: &Connection) -> Result<MediaCounts> {
+    let mut counts = MediaCounts {
+        images: 0,
+        videos: 0,
+        audio: 0,
+    };
+
+    let mut stmt = db.prepare("SELECT file_type, COUNT(*) AS count FROM media GROUP BY file_type")?;
+    let rows = stmt.query_map([], |row| {
+        Ok((row.get::<_, String>("file_type")?, row.get::<_, u64>("count")?))
+    })?;
+
+    for row in rows {
+        let (file_type, count) = row?;
+        match file_type.as_str() {
+            "image" => counts.images = count,
+            "video" => counts.videos = count,
+            "audio" => counts.audio = count,
+            _ => {}
+        }
+    }
+
+    Ok(counts)
+}
+
+/// The most used tags in the pack, most used first.
+fn tag_counts(db: &Connection) -> Result<Vec<(String, u64)>> {
+    let mut stmt = db.prepare(
+        "SELECT tags.name, COUNT(media_tags.media_id) AS count
+         FROM tags
+         JOIN media_tags ON media_tags.tag_id = tags.id
+         GROUP BY tags.id
+         ORDER BY count DESC, tags.name
+         LIMIT ?",
+    )?;
+
+    let tags = stmt
+        .query_map([MAX_TAGS], |row| Ok((row.get("name")?, row.get("count")?)))?
+        .collect::<rusqlite::Result<_>>()?;
+
+    Ok(tags)
+}
+
+/// A random selection of thumbnails to use for the collage. Thumbnails that fail to decode are
+/// skipped.
+fn thumbnails(db: &Connection) -> Result<Vec<image::DynamicImage>> {
+    let mut stmt =
+        db.prepare("SELECT thumbnail FROM media WHERE thumbnail IS NOT NULL ORDER BY RANDOM() LIMIT ?")?;
+
+    let thumbnails = stmt
+        .query_map([COLLAGE_SIZE], |row| row.get::<_, Vec<u8>>("thumbnail"))?
+        .collect::<rusqlite::Result<Vec<_>>>()?
+        .into_iter()
+        .filter_map(|data| image::load_from_memory(&data).ok())
+        .collect();
+
+    Ok(thumbnails)
+}
+
+/// Lay the tags out in wrapped rows, sizing each tag by how often it is used.
+fn draw_tag_cloud(canvas: &mut Canvas, tags: &[(String, u64)], top: f32) {
+    let Some(max) = tags.iter().map(|(_, count)| *count).max() else {
+        return;
+    };
+
+    const MIN_SIZE: f32 = 22.0;
+    const MAX_SIZE: f32 = 40.0;
+    const GAP: f32 = 24.0;
+
+    let right = canvas.width() as f32 - MARGIN;
+    let bottom = canvas.height() as f32 - MARGIN;
+
+    let mut x = MARGIN;
+    let mut y = top;
+    let mut row_height: f32 = 0.0;
+
+    for (name, count) in tags {
+        let size = MIN_SIZE + (MAX_SIZE - MIN_SIZE) * (*count as f32 / max as f32);
+        let text = format!("#{name}");
+        let width = canvas.text_width(&text, size);
+
+        if x + width > right && x > MARGIN {
+            x = MARGIN;
+            y += row_height + 8.0;
+            row_height = 0.0;
+        }
+
+        if y + size > bottom {
+            break;
+        }
+
+        canvas.draw_text(&text, size, x, y, WHITE);
+        x += width + GAP;
+        row_height = row_height.max(size * 1.2);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        fs,
+        io::{Cursor, Write},
+    };
+
+    use image::{DynamicImage, ImageFormat, RgbaImage};
+    use shared::{
+        db::migrate,
+        read_pack::{HEADER_SIZE, Header, Metadata},
+    };
+
+    use super::*;
+
+    fn webp_thumbnail() -> Vec<u8> {
+        let image = DynamicImage::ImageRgba8(RgbaImage::from_pixel(
+            32,
+            32,
+            image::Rgba([200, 40, 90, 255]),
+        ));
+        let mut buf = Cursor::new(Vec::new());
+        image.write_to(&mut buf, ImageFormat::WebP).unwrap();
+        buf.into_inner()
+    }
+
+    #[test]
+    fn media_counts_summary_skips_empty_types() {
+        let counts = MediaCounts {
+            images: 3,
+            videos: 0,
+            audio: 1,
+        };
+        assert_eq!(counts.summary(), "3 images  ·  1 audio file");
+
+        let empty = MediaCounts {
+            images: 0,
+            videos: 0,
+            audio: 0,
+        };
+        assert_eq!(empty.summary(), "Empty pack");
+    }
+
+    #[test]
+    fn preview_renders_card() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let db_path = dir.path().join("index.db");
+        {
+            let db = Connection::open(&db_path).unwrap();
+            migrate(&db).unwrap();
+
+            db.execute("INSERT INTO tags (name) VALUES ('test-tag')", [])
+                .unwrap();
+            db.execute(
+                "INSERT INTO media (file_name, file_type, width, height, transparent, hash, thumbnail)
+                 VALUES ('pic.avif', 'image', 64, 32, 0, x'00', ?)",
+                [webp_thumbnail()],
+            )
+            .unwrap();
+            db.execute("INSERT INTO media_tags (media_id, tag_id) VALUES (1, 1)", [])
+                .unwrap();
+        }
+        let db_bytes = fs::read(&db_path).unwrap();
+
+        let metadata = Metadata {
+            name: "test-pack".to_string(),
+            creator: Some("tester".to_string()),
+            ..Default::default()
+        };
+        let mut header = Header::new();
+        let metadata_bytes = header.encode(&metadata.to_buf().unwrap()).unwrap();
+        let db_bytes = header.encode(&db_bytes).unwrap();
+        header.metadata_offset = HEADER_SIZE as u64;
+        header.metadata_length = metadata_bytes.len() as u64;
+        header.index_offset = header.metadata_offset + header.metadata_length;
+        header.index_length = db_bytes.len() as u64;
+
+        let pack_path = dir.path().join("test.lwpack");
+        let mut file = fs::File::create(&pack_path).unwrap();
+        file.write_all(&header.to_buf().unwrap()).unwrap();
+        file.write_all(&metadata_bytes).unwrap();
+        file.write_all(&db_bytes).unwrap();
+        drop(file);
+
+        let pack = open_pack(&pack_path).unwrap();
+        assert_eq!(media_counts(&pack.db).unwrap().images, 1);
+        assert_eq!(
+            tag_counts(&pack.db).unwrap(),
+            vec![("test-tag".to_string(), 1)]
+        );
+        assert_eq!(thumbnails(&pack.db).unwrap().len(), 1);
+
+        let output = dir.path().join("card.png");
+        preview(PreviewArgs {
+            pack: pack_path,
+            output: output.clone(),
+        })
+        .unwrap();
+
+        let card = image::open(&output).unwrap();
+        assert_eq!((card.width(), card.height()), (WIDTH, HEIGHT));
+    }
+}
@@ -0,0 +1,169 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use clap::Args;
+use shared::{
+    ignore::{IGNORE_FILE, IgnoreRules, NOMEDIA_FILE, is_nomedia_dir},
+    sidecar::{read_sidecar, sidecar_path},
+    texts::{self, read_text_file},
+};
+
+#[derive(Args)]
+pub struct TestGlobArgs {
+    /// The folder that would be added to the pack. Ignore rules above it don't apply.
+    #[arg(long, default_value = ".")]
+    root: PathBuf,
+    /// The files or folders to check, inside the root folder
+    #[arg(required = true)]
+    paths: Vec<PathBuf>,
+}
+
+/// Prints what adding `root` to a pack would do with each path: whether a `.nomedia` or
+/// `.lewdignore` file skips it, and the tags and category from its sidecar file.
+pub fn test_glob(args: TestGlobArgs) -> Result<()> {
+    let root = args
+        .root
+        .canonicalize()
+        .with_context(|| format!("Could not find {}", args.root.display()))?;
+
+    for path in &args.paths {
+        let full = path
+            .canonicalize()
+            .with_context(|| format!("Could not find {}", path.display()))?;
+
+        match check_path(&root, &full)? {
+            Some(reason) => println!("{}: skipped, {reason}", path.display()),
+            None => println!("{}: {}", path.display(), describe(&full)?),
+        }
+    }
+
+    Ok(())
+}
+
+/// Why `path` would be skipped, if it would be. Goes down from `root` the same way the pack
+/// editor does, picking up each folder's rules on the way.
+fn check_path(root: &Path, path: &Path) -> Result<Option<String>> {
+    let relative = path
+        .strip_prefix(root)
+        .with_context(|| format!("{} isn't inside {}", path.display(), root.display()))?;
+
+    let mut rules = IgnoreRules::default();
+    let mut dir = root.to_path_buf();
+    let mut components = relative.components().peekable();
+
+    loop {
+        if is_nomedia_dir(&dir) {
+            return Ok(Some(format!(
+                "{} has a {NOMEDIA_FILE} file",
+                display_dir(root, &dir)
+            )));
+        }
+        rules = rules.for_dir(&dir)?;
+
+        let Some(component) = components.next() else {
+            return Ok(None);
+        };
+        dir.push(component);
+
+        if let Some((base, pattern)) = rules.matched_by(&dir) {
+            return Ok(Some(format!(
+                "matches {pattern:?} in {}",
+                display_dir(root, &base.join(IGNORE_FILE))
+            )));
+        }
+
+        if components.peek().is_none() && !dir.is_dir() {
+            return Ok(None);
+        }
+    }
+}
+
+fn describe(path: &Path) -> Result<String> {
+    if path.is_dir() {
+        return Ok("included".to_string());
+    }
+
+    if texts::is_text_file(path) {
+        let file = read_text_file(path)?;
+        let mut description = format!("imported as {} {}s", file.entries.len(), file.kind.as_str());
+        if let Some(locale) = &file.locale {
+            description.push_str(&format!(" in {locale}"));
+        }
+        return Ok(description);
+    }
+
+    let Some(sidecar) = read_sidecar(path)? else {
+        return Ok("included, no sidecar".to_string());
+    };
+
+    let mut parts = vec![format!(
+        "sidecar {}",
+        sidecar_path(path)
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+    )];
+    if !sidecar.tags.is_empty() {
+        parts.push(format!("tags: {}", sidecar.tags.join(", ")));
+    }
+    if let Some(category) = &sidecar.category {
+        parts.push(format!("category: {category}"));
+    }
+    if let Some(weight) = sidecar.weight {
+        parts.push(format!("weight: {weight}"));
+    }
+    if let Some(cooldown) = sidecar.cooldown {
+        parts.push(format!("cooldown: {cooldown}s"));
+    }
+    if let Some(only_after) = &sidecar.only_after {
+        let after = match (&only_after.file, &only_after.tag) {
+            (Some(file), _) => file.clone(),
+            (None, Some(tag)) => format!("tag {tag}"),
+            (None, None) => unreachable!("checked when the sidecar is read"),
+        };
+        parts.push(format!("only after {after} x{}", only_after.times));
+    }
+    if let Some(set) = &sidecar.set {
+        parts.push(format!("set: {set}"));
+    }
+    if let Some(rating) = sidecar.rating {
+        parts.push(format!("rating: {rating}"));
+    }
+
+    Ok(format!("included, {}", parts.join("; ")))
+}
+
+fn display_dir(root: &Path, path: &Path) -> String {
+    match path.strip_prefix(root) {
+        Ok(relative) if relative.as_os_str().is_empty() => ".".to_string(),
+        Ok(relative) => relative.display().to_string(),
+        Err(_) => path.display().to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+
+    #[test]
+    fn reports_why_paths_are_skipped() {
+        let root = tempfile::tempdir().unwrap();
+        let root = root.path();
+        fs::create_dir_all(root.join("raw")).unwrap();
+        fs::create_dir_all(root.join("sub/hidden")).unwrap();
+        fs::write(root.join(IGNORE_FILE), "*.gif\n").unwrap();
+        fs::write(root.join("sub/hidden").join(NOMEDIA_FILE), "").unwrap();
+
+        let skipped = check_path(root, &root.join("raw/a.gif")).unwrap().unwrap();
+        assert!(skipped.contains("\"*.gif\""), "{skipped}");
+
+        let skipped = check_path(root, &root.join("sub/hidden/b.png"))
+            .unwrap()
+            .unwrap();
+        assert!(skipped.starts_with("sub/hidden"), "{skipped}");
+
+        assert_eq!(check_path(root, &root.join("raw/c.png")).unwrap(), None);
+    }
+}
@@ -0,0 +1,105 @@
+use std::{
+    fs::File,
+    io::{Read, Seek, SeekFrom},
+    path::PathBuf,
+};
+
+use anyhow::{Result, bail};
+use clap::Args;
+use shared::{pack_index::all_stored_media, read_pack::HEADER_SIZE};
+
+use crate::pack::open_pack;
+
+#[derive(Args)]
+pub struct VerifyArgs {
+    /// The pack file to check
+    pack: PathBuf,
+}
+
+pub fn verify(args: VerifyArgs) -> Result<()> {
+    let pack = open_pack(&args.pack)?;
+    let mut file = File::open(&args.pack)?;
+    let file_length = file.metadata()?.len();
+
+    let mut problems = Vec::new();
+
+    let header = &pack.header;
+    if header.index_offset + header.index_length > file_length {
+        problems.push("The index runs past the end of the file".to_string());
+    }
+    if header.metadata_offset + header.metadata_length > file_length {
+        problems.push("The metadata runs past the end of the file".to_string());
+    }
+
+    // A damaged index can still open, and only fail once the broken pages are queried.
+    let mut stmt = pack.db.prepare("PRAGMA integrity_check")?;
+    let index_problems = stmt
+        .query_map([], |row| row.get::<_, String>(0))?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    problems.extend(
+        index_problems
+            .into_iter()
+            .filter(|problem| problem != "ok")
+            .map(|problem| format!("The index is damaged: {problem}")),
+    );
+
+    // Media is stored between the header and whichever of the index and metadata comes first.
+    let media_end = header.index_offset.min(header.metadata_offset);
+
+    let entries = all_stored_media(&pack.db)?;
+
+    let mut unchecked = 0;
+
+    for entry in &entries {
+        let Some((offset, length)) = entry.range() else {
+            problems.push(format!(
+                "{} (id {}) has no data in the pack",
+                entry.file_name, entry.id
+            ));
+            continue;
+        };
+
+        if offset < HEADER_SIZE as u64 || offset + length > media_end {
+            problems.push(format!(
+                "{} (id {}) points outside the media section of the file",
+                entry.file_name, entry.id
+            ));
+            continue;
+        }
+
+        let Some(checksum) = &entry.checksum else {
+            unchecked += 1;
+            continue;
+        };
+
+        file.seek(SeekFrom::Start(offset))?;
+        let mut hasher = blake3::Hasher::new();
+        hasher.update_reader((&mut file).take(length))?;
+
+        if hasher.finalize().as_bytes().as_slice() != checksum.as_slice() {
+            problems.push(format!(
+                "{} (id {}) does not match its checksum",
+                entry.file_name, entry.id
+            ));
+        }
+    }
+
+    println!("Checked {} entries in {}", entries.len(), args.pack.display());
+
+    if unchecked > 0 {
+        println!(
+            "{unchecked} entries have no checksum (they were added by an older pack editor) and were only bounds-checked"
+        );
+    }
+
+    if !problems.is_empty() {
+        for problem in &problems {
+            println!("  {problem}");
+        }
+        bail!("Found {} problems", problems.len());
+    }
+
+    println!("No problems found");
+
+    Ok(())
+}
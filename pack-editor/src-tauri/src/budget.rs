@@ -0,0 +1,107 @@
+use std::collections::{BTreeMap, HashSet};
+
+use serde::Serialize;
+
+use crate::pack::MediaFile;
+
+#[derive(Serialize, Clone, Debug)]
+pub struct CutFile {
+    pub id: u64,
+    pub file_name: String,
+    pub size: u64,
+}
+
+/// How a tag stands against its size budget.
+#[derive(Serialize, Clone, Debug)]
+pub struct BudgetReport {
+    pub tag: String,
+    pub budget: u64,
+    /// The total size of the files with this tag, before anything is cut.
+    pub used: u64,
+    /// The files that have to go for the tag to fit its budget.
+    pub cut: Vec<CutFile>,
+}
+
+/// Works out which files to drop for every tag to fit its budget (in bytes). The largest files
+/// are dropped first, so as few files as possible go. A file dropped for one tag also frees up
+/// space in the other tags it has.
+pub fn plan_cuts(files: &[MediaFile], budgets: &BTreeMap<String, u64>) -> Vec<BudgetReport> {
+    let mut removed = HashSet::new();
+
+    budgets
+        .iter()
+        .map(|(tag, &budget)| {
+            let mut tagged: Vec<_> = files.iter().filter(|f| f.tags.contains(tag)).collect();
+            let used = tagged.iter().map(|f| f.size).sum();
+            let mut remaining: u64 = tagged
+                .iter()
+                .filter(|f| !removed.contains(&f.id))
+                .map(|f| f.size)
+                .sum();
+
+            tagged.sort_by(|a, b| b.size.cmp(&a.size));
+
+            let mut cut = Vec::new();
+            for file in tagged {
+                if remaining <= budget {
+                    break;
+                }
+                if removed.insert(file.id) {
+                    remaining -= file.size;
+                    cut.push(CutFile {
+                        id: file.id,
+                        file_name: file.file_name.clone(),
+                        size: file.size,
+                    });
+                }
+            }
+
+            BudgetReport {
+                tag: tag.clone(),
+                budget,
+                used,
+                cut,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use shared::encode::FileInfo;
+
+    use super::*;
+
+    fn file(id: u64, size: u64, tags: &[&str]) -> MediaFile {
+        MediaFile {
+            id,
+            file_info: FileInfo::Audio { duration: 1.0 },
+            file_name: format!("{id}.opus"),
+            hash: String::new(),
+            tags: tags.iter().map(|t| t.to_string()).collect(),
+            size,
+            encode: None,
+        }
+    }
+
+    #[test]
+    fn cuts_largest_files_and_shares_savings_across_tags() {
+        let files = [
+            file(1, 100, &["a", "b"]),
+            file(2, 50, &["a"]),
+            file(3, 30, &["a"]),
+            file(4, 40, &["b"]),
+        ];
+        let budgets = BTreeMap::from([("a".to_string(), 90), ("b".to_string(), 50)]);
+
+        let reports = plan_cuts(&files, &budgets);
+
+        assert_eq!(reports[0].used, 180);
+        let cut: Vec<_> = reports[0].cut.iter().map(|f| f.id).collect();
+        assert_eq!(cut, [1]);
+
+        // File 1 already went for "a", which brings "b" down to 40.
+        assert_eq!(reports[1].used, 140);
+        assert!(reports[1].cut.is_empty());
+    }
+}
@@ -1,22 +1,39 @@
 use std::{
-    fs::File,
-    io::{self, BufRead, BufReader, Read},
+    collections::HashMap,
+    fs::{self, File},
+    io::{self, BufRead, BufReader, Read, Write},
     path::{Path, PathBuf},
     process::{Command, Stdio},
     sync::{
         atomic::{AtomicBool, Ordering},
-        Arc, OnceLock,
+        Arc, Mutex, OnceLock,
     },
     thread::available_parallelism,
+    time::Instant,
 };
 
 fn new_command<S: AsRef<std::ffi::OsStr>>(program: S) -> Command {
-    #[allow(unused_mut)]
+    let low_priority = LOW_PRIORITY.load(Ordering::Relaxed);
+
+    // There's no portable way to lower a child's priority from std, so go through `nice`
+    // where it's available.
+    #[cfg(unix)]
+    let mut cmd = if low_priority && Path::new(NICE_PATH).exists() {
+        let mut cmd = Command::new(NICE_PATH);
+        cmd.args(["-n", "10"]).arg(program);
+        cmd
+    } else {
+        Command::new(program)
+    };
+    #[cfg(not(unix))]
     let mut cmd = Command::new(program);
+
     #[cfg(target_os = "windows")]
     {
         use std::os::windows::process::CommandExt;
-        cmd.creation_flags(0x08000000);
+        // CREATE_NO_WINDOW, plus BELOW_NORMAL_PRIORITY_CLASS when asked to.
+        let priority = if low_priority { 0x00004000 } else { 0 };
+        cmd.creation_flags(0x08000000 | priority);
     }
     shared::utils::sanitize_child_env(&mut cmd);
     cmd
@@ -25,20 +42,72 @@ fn new_command<S: AsRef<std::ffi::OsStr>>(program: S) -> Command {
 use anyhow::{anyhow, bail, Context, Result};
 use futures::{stream, StreamExt};
 use infer::MatcherType;
-use shared::encode::FileInfo;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use shared::{
+    children,
+    encode::{FileInfo, ImageCodec, VideoContainer},
+    ignore::{is_nomedia_dir, IgnoreRules},
+    locale::{tr, tr_args},
+    sidecar::read_sidecar,
+    user_config::{EncodeSettings, OnEncodeError},
+};
 use tempfile::NamedTempFile;
 use tokio::sync::{oneshot, RwLock, Semaphore};
 use uuid::Uuid;
-use walkdir::WalkDir;
 
 use tauri::Emitter;
 
-use crate::pack::MediaFile;
+use crate::{
+    pack::{MediaFile, MediaPackView},
+    upload_stats::{FileStats, StageTimings, UploadStats},
+};
 
 pub struct EncodedFile {
     pub info: FileInfo,
     pub thumbnail: Option<Vec<u8>>,
     pub path: PathBuf,
+    /// The format the file was encoded to, for images.
+    pub codec: Option<ImageCodec>,
+    /// The container the file was written to, for videos.
+    pub container: Option<VideoContainer>,
+    /// The settings it was encoded with, for images and videos.
+    pub params: Option<EncodeParams>,
+}
+
+/// The settings a file was encoded with, stored with it in the index so they can be shown in its
+/// details.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EncodeParams {
+    /// The ffmpeg encoder, e.g. `libx264`.
+    pub encoder: String,
+    /// The encoder's own quality setting: CRF for most of them, but e.g. a distance for JPEG XL
+    /// and 0-100 for WebP.
+    pub quality: f64,
+    /// The longest the image or video could be on either side.
+    pub max_size: u64,
+}
+
+/// Settings to re-encode a file with in place of the defaults, e.g. for one that came out too
+/// blurry. Anything left unset keeps the default.
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+pub struct EncodeOverrides {
+    /// In the same units as [`EncodeParams::quality`]. The hardware encoders all measure quality
+    /// differently, so a video with its quality set is encoded in software.
+    pub quality: Option<f64>,
+    pub max_size: Option<u64>,
+}
+
+/// The longest side of an image, unless it's overridden.
+const MAX_IMAGE_SIZE: u64 = 2560;
+/// The longest side of a video, unless it's overridden.
+const MAX_VIDEO_SIZE: u64 = 1280;
+
+/// What happened to a file that was encoded.
+pub enum Processed {
+    Added(MediaFile),
+    /// It had been added before, and has changed since, so its entry was updated.
+    Replaced(MediaFile),
 }
 
 #[derive(Debug)]
@@ -131,6 +200,38 @@ impl HardwareEncoder {
         }
     }
 
+    /// The arguments for encoding videos into `container`. WebM needs VP9, which none of the
+    /// hardware encoders can do, so it's always encoded in software.
+    pub fn video_args(&self, container: VideoContainer) -> &[&'static str] {
+        match container {
+            VideoContainer::Mp4 => self.ffmpeg_args(),
+            VideoContainer::Webm => &[
+                "-c:v",
+                "libvpx-vp9",
+                "-crf",
+                "32",
+                "-b:v",
+                "0",
+                "-row-mt",
+                "1",
+            ],
+        }
+    }
+
+    /// The ffmpeg encoder videos are encoded with, e.g. `libx264`.
+    pub fn video_codec(&self, container: VideoContainer) -> &'static str {
+        self.video_args(container)[1]
+    }
+
+    /// The quality [`Self::video_args`] encodes videos with, in the encoder's own units.
+    fn video_quality(&self, container: VideoContainer) -> f64 {
+        match (container, self) {
+            (VideoContainer::Webm, _) => 32.0,
+            (VideoContainer::Mp4, Self::Apple) => 60.0,
+            (VideoContainer::Mp4, _) => 23.0,
+        }
+    }
+
     pub fn test(self) -> Self {
         if self != Self::SoftwareFallback {
             if new_command(get_ffmpeg_path())
@@ -155,19 +256,49 @@ impl HardwareEncoder {
     }
 }
 
+/// [`HardwareEncoder::video_args`] for the software encoder, with the quality set.
+fn software_video_args(container: VideoContainer, quality: f64) -> Vec<String> {
+    let quality = quality.to_string();
+    let args: &[&str] = match container {
+        VideoContainer::Mp4 => &["-c:v", "libx264", "-crf", &quality],
+        VideoContainer::Webm => &[
+            "-c:v",
+            "libvpx-vp9",
+            "-crf",
+            &quality,
+            "-b:v",
+            "0",
+            "-row-mt",
+            "1",
+        ],
+    };
+    args.iter().map(|arg| arg.to_string()).collect()
+}
+
+fn error(key: &str, error: &impl std::fmt::Display) -> String {
+    tr_args(key, [("error", error.to_string().into())])
+}
+
 impl std::fmt::Display for ProcessErrorKind {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            Self::Skipped => write!(f, "Duplicate (skipped)"),
-            Self::EncodeError(e) => write!(f, "Encode error: {e}"),
-            Self::PackError(e) => write!(f, "Pack error: {e}"),
-            Self::HashError(e) => write!(f, "Hash error: {e}"),
+            Self::Skipped => write!(f, "{}", tr("pack-editor-skipped")),
+            Self::EncodeError(e) => write!(f, "{}", error("pack-editor-encode-error", e)),
+            Self::PackError(e) => write!(f, "{}", error("pack-editor-pack-error", e)),
+            Self::HashError(e) => write!(f, "{}", error("pack-editor-hash-error", e)),
             Self::Other(e) => write!(f, "{e}"),
         }
     }
 }
 
-static ENCODE_SEMAPHORE: OnceLock<Semaphore> = OnceLock::new();
+#[cfg(unix)]
+const NICE_PATH: &str = "/usr/bin/nice";
+
+// Replaced whenever the encode settings change. Batches already running keep the semaphore they
+// started with.
+static ENCODE_SEMAPHORE: Mutex<Option<Arc<Semaphore>>> = Mutex::new(None);
+static LOW_PRIORITY: AtomicBool = AtomicBool::new(false);
+static ON_ERROR: Mutex<OnEncodeError> = Mutex::new(OnEncodeError::Skip);
 static FFMPEG_PATH: OnceLock<PathBuf> = OnceLock::new();
 static FFPROBE_PATH: OnceLock<PathBuf> = OnceLock::new();
 
@@ -176,26 +307,75 @@ pub fn init_binary_paths(ffmpeg: PathBuf, ffprobe: PathBuf) {
     let _ = FFPROBE_PATH.set(ffprobe);
 }
 
-fn encode_semaphore() -> &'static Semaphore {
-    ENCODE_SEMAPHORE.get_or_init(|| {
-        let permits = available_parallelism()
-            .map(|x| (x.get() / 4).max(2))
-            .unwrap_or(2);
-        Semaphore::new(permits)
-    })
+/// How many files are encoded at once when the settings don't say.
+pub fn default_encode_jobs() -> usize {
+    available_parallelism()
+        .map(|x| (x.get() / 4).max(2))
+        .unwrap_or(2)
+}
+
+pub fn apply_encode_settings(settings: &EncodeSettings) {
+    LOW_PRIORITY.store(settings.low_priority, Ordering::Relaxed);
+    *ON_ERROR.lock().unwrap() = settings.on_error;
+    let jobs = settings.jobs.unwrap_or_else(default_encode_jobs).max(1);
+    *ENCODE_SEMAPHORE.lock().unwrap() = Some(Arc::new(Semaphore::new(jobs)));
+}
+
+fn encode_semaphore() -> Arc<Semaphore> {
+    ENCODE_SEMAPHORE
+        .lock()
+        .unwrap()
+        .get_or_insert_with(|| Arc::new(Semaphore::new(default_encode_jobs())))
+        .clone()
 }
 
+/// Finds the media files in a folder, skipping anything excluded by `.nomedia` or `.lewdignore`
+/// files (see [`shared::ignore`]). Subfolders are scanned in parallel, since sniffing file types
+/// means opening every file.
 pub fn explore_folder(path: &Path, recursive: bool) -> Vec<PathBuf> {
-    let mut walkdir = WalkDir::new(path);
-    if !recursive {
-        walkdir = walkdir.max_depth(1);
+    let mut paths = scan_dir(path, &IgnoreRules::default(), recursive);
+    paths.sort();
+    paths
+}
+
+fn scan_dir(dir: &Path, parent_rules: &IgnoreRules, recursive: bool) -> Vec<PathBuf> {
+    if is_nomedia_dir(dir) {
+        return Vec::new();
     }
-    walkdir
-        .into_iter()
+
+    let rules = parent_rules.for_dir(dir).unwrap_or_else(|err| {
+        tracing::warn!("Failed to read ignore file in {}: {err}", dir.display());
+        parent_rules.clone()
+    });
+
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(err) => {
+            tracing::warn!("Failed to read {}: {err}", dir.display());
+            return Vec::new();
+        }
+    };
+
+    // Symlinked folders aren't followed, to avoid loops, but symlinked files are.
+    let (dirs, files): (Vec<_>, Vec<_>) = entries
         .filter_map(|e| e.ok())
-        .filter(|e| e.path().is_file() && is_media_path(e.path()).unwrap_or(false))
-        .map(|e| e.path().to_path_buf())
-        .collect()
+        .filter(|e| !rules.is_ignored(&e.path()))
+        .partition(|e| e.file_type().is_ok_and(|t| t.is_dir()));
+
+    let mut paths: Vec<PathBuf> = files
+        .into_par_iter()
+        .map(|e| e.path())
+        .filter(|path| path.is_file() && is_media_path(path).unwrap_or(false))
+        .collect();
+
+    if recursive {
+        paths.par_extend(
+            dirs.into_par_iter()
+                .flat_map_iter(|e| scan_dir(&e.path(), &rules, true)),
+        );
+    }
+
+    paths
 }
 
 pub fn is_media_path(path: &Path) -> anyhow::Result<bool> {
@@ -291,10 +471,7 @@ fn file_info(path: &Path) -> Result<Option<FileInfo>> {
         "json",
     ];
 
-    let output = new_command(get_ffprobe_path())
-        .args(args)
-        .arg(path)
-        .output()?;
+    let output = children::output(new_command(get_ffprobe_path()).args(args).arg(path))?;
 
     if !output.status.success() {
         return Ok(None);
@@ -308,23 +485,59 @@ pub fn encode_file(
     input: &Path,
     output: &Path,
     encoder: HardwareEncoder,
+    image_codec: ImageCodec,
+    video_container: VideoContainer,
+    overrides: EncodeOverrides,
 ) -> Result<Option<EncodedFile>> {
-    let info = match file_info(input)? {
-        Some(x) => x,
-        None => return Ok(None),
-    };
+    match file_info(input)? {
+        Some(info) => encode_probed_file(
+            input,
+            info,
+            output,
+            encoder,
+            image_codec,
+            video_container,
+            overrides,
+        )
+        .map(Some),
+        None => Ok(None),
+    }
+}
 
+/// Encodes a file whose info has already been read with `file_info`.
+fn encode_probed_file(
+    input: &Path,
+    info: FileInfo,
+    output: &Path,
+    encoder: HardwareEncoder,
+    image_codec: ImageCodec,
+    video_container: VideoContainer,
+    overrides: EncodeOverrides,
+) -> Result<EncodedFile> {
     let output = match info {
-        FileInfo::Image { .. } => output.with_extension("avif"),
-        FileInfo::Video { .. } => output.with_extension("mp4"),
+        FileInfo::Image { .. } => output.with_extension(image_codec.extension()),
+        FileInfo::Video { .. } => output.with_extension(video_container.extension()),
         FileInfo::Audio { .. } => output.with_extension("opus"),
     };
 
     let mut thumbnail = None;
+    let mut codec = None;
+    let mut container = None;
+    let mut params = None;
     let info = match info {
         FileInfo::Image { width, height, .. } => {
-            let (thumb, w, h, transparent) = encode_image(input, &output, width, height)?;
+            let image_params = EncodeParams {
+                encoder: image_encoder(image_codec).to_string(),
+                quality: overrides
+                    .quality
+                    .unwrap_or_else(|| image_quality(image_codec)),
+                max_size: overrides.max_size.unwrap_or(MAX_IMAGE_SIZE),
+            };
+            let (thumb, w, h, transparent) =
+                encode_image(input, &output, width, height, image_codec, &image_params)?;
             thumbnail = Some(thumb);
+            codec = Some(image_codec);
+            params = Some(image_params);
             FileInfo::Image {
                 width: w,
                 height: h,
@@ -338,9 +551,20 @@ pub fn encode_file(
             audio,
             ..
         } => {
-            let (thumb, w, h, transparent) =
-                encode_video(input, &output, width, height, audio, encoder, false)?;
+            let (thumb, w, h, transparent, video_params) = encode_video(
+                input,
+                &output,
+                width,
+                height,
+                audio,
+                encoder,
+                video_container,
+                overrides,
+                false,
+            )?;
             thumbnail = Some(thumb);
+            container = Some(video_container);
+            params = Some(video_params);
             FileInfo::Video {
                 width: w,
                 height: h,
@@ -355,11 +579,32 @@ pub fn encode_file(
         }
     };
 
-    Ok(Some(EncodedFile {
+    Ok(EncodedFile {
         info,
         thumbnail,
         path: output,
-    }))
+        codec,
+        container,
+        params,
+    })
+}
+
+/// The ffmpeg encoder images in `codec` are encoded with.
+fn image_encoder(codec: ImageCodec) -> &'static str {
+    match codec {
+        ImageCodec::Avif => "libaom-av1",
+        ImageCodec::Webp => "libwebp",
+        ImageCodec::Jxl => "libjxl",
+    }
+}
+
+/// The quality images in `codec` are encoded with by default, in the encoder's own units.
+fn image_quality(codec: ImageCodec) -> f64 {
+    match codec {
+        ImageCodec::Avif => 32.0,
+        ImageCodec::Webp => 80.0,
+        ImageCodec::Jxl => 1.5,
+    }
 }
 
 fn encode_image(
@@ -367,14 +612,23 @@ fn encode_image(
     output: &Path,
     width: u64,
     height: u64,
+    codec: ImageCodec,
+    params: &EncodeParams,
 ) -> Result<(Vec<u8>, u64, u64, bool)> {
-    let (width, height) = resize_dimensions(width, height, 2560, true);
+    let (width, height) = resize_dimensions(width, height, params.max_size, true);
+    let quality = params.quality.to_string();
+
+    // libjxl doesn't take YUV input.
+    let pixel_format = match codec {
+        ImageCodec::Avif | ImageCodec::Webp => "yuva420p",
+        ImageCodec::Jxl => "rgba",
+    };
 
     let thumb_temp = NamedTempFile::new()?;
     let thumb_path = thumb_temp.path();
 
     let filter = format!(
-        "[0:v]scale=w='{width}':h='{height}',format=yuva420p[main]; \
+        "[0:v]scale=w='{width}':h='{height}',format={pixel_format}[main]; \
          [0:v]scale='min(iw,100)':'min(ih,100)':force_original_aspect_ratio=decrease[thumb]; \
          [0:v]format=rgba,alphaextract,format=gray,signalstats,metadata=print:key=lavfi.signalstats.YMIN[alpha]"
     );
@@ -386,30 +640,35 @@ fn encode_image(
         .arg("-filter_complex")
         .arg(&filter);
 
-    cmd.args([
-        "-map",
-        "[main]",
-        "-c:v",
-        "libaom-av1",
-        "-cpu-used",
-        "6",
-        "-crf",
-        "32",
-        "-b:v",
-        "0",
-        "-still-picture",
-        "1",
-        "-f",
-        "avif",
-    ])
-    .arg(output);
+    cmd.args(["-map", "[main]"]);
+    match codec {
+        ImageCodec::Avif => cmd.args([
+            "-c:v",
+            "libaom-av1",
+            "-cpu-used",
+            "6",
+            "-crf",
+            &quality,
+            "-b:v",
+            "0",
+            "-still-picture",
+            "1",
+            "-f",
+            "avif",
+        ]),
+        ImageCodec::Webp => cmd.args(["-c:v", "libwebp", "-quality", &quality, "-f", "webp"]),
+        ImageCodec::Jxl => cmd.args([
+            "-c:v", "libjxl", "-distance", &quality, "-effort", "5", "-f", "image2", "-update", "1",
+        ]),
+    };
+    cmd.arg(output);
 
     cmd.args(["-map", "[thumb]", "-frames:v", "1", "-f", "webp"])
         .arg(thumb_path);
 
     cmd.args(["-map", "[alpha]", "-f", "null", "-"]);
 
-    let mut child = cmd.stderr(Stdio::piped()).spawn()?;
+    let mut child = children::spawn(cmd.stderr(Stdio::piped()))?;
     let stderr = child.stderr.take().context("Failed to take stderr")?;
     let reader = BufReader::new(stderr);
 
@@ -450,9 +709,27 @@ fn encode_video(
     height: u64,
     audio: bool,
     encoder: HardwareEncoder,
+    container: VideoContainer,
+    overrides: EncodeOverrides,
     fixed_fps: bool,
-) -> Result<(Vec<u8>, u64, u64, bool)> {
-    let (width, height) = resize_dimensions(width, height, 1280, true);
+) -> Result<(Vec<u8>, u64, u64, bool, EncodeParams)> {
+    let max_size = overrides.max_size.unwrap_or(MAX_VIDEO_SIZE);
+    let (width, height) = resize_dimensions(width, height, max_size, true);
+
+    // The hardware encoders' quality settings don't line up with CRF, so a set quality means
+    // encoding in software.
+    let encoder = if overrides.quality.is_some() {
+        HardwareEncoder::SoftwareFallback
+    } else {
+        encoder
+    };
+    let params = EncodeParams {
+        encoder: encoder.video_codec(container).to_string(),
+        quality: overrides
+            .quality
+            .unwrap_or_else(|| encoder.video_quality(container)),
+        max_size,
+    };
 
     let thumb_temp = NamedTempFile::new()?;
     let thumb_path = thumb_temp.path();
@@ -477,7 +754,11 @@ fn encode_video(
         cmd.arg("-an");
     }
 
-    cmd.args(encoder.ffmpeg_args()).args(["-f", "mp4"]);
+    match overrides.quality {
+        Some(quality) => cmd.args(software_video_args(container, quality)),
+        None => cmd.args(encoder.video_args(container)),
+    };
+    cmd.args(["-f", container.ffmpeg_format()]);
 
     if fixed_fps {
         cmd.arg("-r").arg("30");
@@ -490,7 +771,7 @@ fn encode_video(
 
     cmd.args(["-map", "[alpha]", "-f", "null", "-"]);
 
-    let mut child = cmd.stderr(Stdio::piped()).spawn()?;
+    let mut child = children::spawn(cmd.stderr(Stdio::piped()))?;
     let stderr = child.stderr.take().context("Failed to take stderr")?;
     let reader = BufReader::new(stderr);
 
@@ -504,11 +785,10 @@ fn encode_video(
             if let Some(val_str) = line.split('=').last() {
                 if let Ok(y_min) = val_str.trim().parse::<f64>() {
                     if y_min < 255.0 {
-                        let _ = child.kill();
-                        let _ = child.wait();
+                        child.kill();
                         let _ = std::fs::remove_file(output);
                         return encode_video_with_transparency(
-                            input, output, width, height, audio, false,
+                            input, output, width, height, audio, container, overrides, false,
                         );
                     }
                 }
@@ -531,6 +811,8 @@ fn encode_video(
                 height,
                 audio,
                 HardwareEncoder::SoftwareFallback,
+                container,
+                overrides,
                 true,
             ) {
                 return Ok(r);
@@ -542,7 +824,7 @@ fn encode_video(
 
     let mut thumbnail = Vec::new();
     File::open(thumb_path)?.read_to_end(&mut thumbnail)?;
-    Ok((thumbnail, width, height, false))
+    Ok((thumbnail, width, height, false, params))
 }
 
 fn encode_video_with_transparency(
@@ -551,9 +833,15 @@ fn encode_video_with_transparency(
     width: u64,
     height: u64,
     audio: bool,
+    container: VideoContainer,
+    overrides: EncodeOverrides,
     fixed_fps: bool,
-) -> anyhow::Result<(Vec<u8>, u64, u64, bool)> {
-    let (width, height) = resize_dimensions(width, height, 1280, true);
+) -> anyhow::Result<(Vec<u8>, u64, u64, bool, EncodeParams)> {
+    let max_size = overrides.max_size.unwrap_or(MAX_VIDEO_SIZE);
+    let (width, height) = resize_dimensions(width, height, max_size, true);
+    let quality = overrides
+        .quality
+        .unwrap_or_else(|| HardwareEncoder::SoftwareFallback.video_quality(container));
 
     let thumb_temp = NamedTempFile::new()?;
     let thumb_path = thumb_temp.path();
@@ -584,11 +872,7 @@ fn encode_video_with_transparency(
         command.arg("-an");
     }
 
-    command.args([
-        "-c:v",
-        "libx264",
-        "-crf",
-        "23",
+    command.args(software_video_args(container, quality)).args([
         "-color_range",
         "pc",
         "-pix_fmt",
@@ -600,20 +884,21 @@ fn encode_video_with_transparency(
     }
 
     command
+        .args(["-f", container.ffmpeg_format()])
         .arg(output)
         .args(["-map", "[thumb]", "-frames:v", "1", "-f", "webp"])
         .arg(thumb_path);
 
-    let result = command.output()?;
+    let result = children::output(&mut command)?;
 
     if !result.status.success() {
         tracing::error!("{}", String::from_utf8_lossy(&result.stderr));
         if !fixed_fps {
             tracing::error!("Encoding with non-fixed FPS failed; trying fixed FPS");
 
-            if let Ok(res) =
-                encode_video_with_transparency(input, output, width, height, audio, true)
-            {
+            if let Ok(res) = encode_video_with_transparency(
+                input, output, width, height, audio, container, overrides, true,
+            ) {
                 return Ok(res);
             }
         }
@@ -624,7 +909,14 @@ fn encode_video_with_transparency(
     let mut thumbnail = Vec::new();
     File::open(thumb_path)?.read_to_end(&mut thumbnail)?;
 
-    Ok((thumbnail, width, height, true))
+    let params = EncodeParams {
+        encoder: HardwareEncoder::SoftwareFallback
+            .video_codec(container)
+            .to_string(),
+        quality,
+        max_size,
+    };
+    Ok((thumbnail, width, height, true, params))
 }
 
 fn encode_audio(input: &Path, output: &Path) -> Result<()> {
@@ -636,7 +928,7 @@ fn encode_audio(input: &Path, output: &Path) -> Result<()> {
         .args(["-c:a", "libopus", "-b:a", "64k"])
         .arg(output);
 
-    let output = command.output()?;
+    let output = children::output(&mut command)?;
 
     if !output.status.success() {
         tracing::error!("{}", String::from_utf8_lossy(&output.stderr));
@@ -730,15 +1022,23 @@ pub async fn process_files(
     let total = paths.len();
     let _ = app.emit("upload:start", serde_json::json!({ "total": total }));
 
-    let dir = {
+    let (dir, image_codec, video_container, pack_path) = {
         let lock = pack_state.lock().await;
         match lock.as_ref() {
-            Some(pack) => pack.dir().to_path_buf(),
+            Some(pack) => (
+                pack.dir().to_path_buf(),
+                pack.metadata().image_codec.unwrap_or_default(),
+                pack.metadata().video_container.unwrap_or_default(),
+                pack.path().to_path_buf(),
+            ),
             None => return,
         }
     };
 
+    let on_error = *ON_ERROR.lock().unwrap();
     let limit = available_parallelism().map(|x| x.get()).ok();
+    let stats = std::sync::Mutex::new(UploadStats::new(total, Instant::now()));
+    let failed = std::sync::Mutex::new(Vec::new());
 
     stream::iter(paths)
         .for_each_concurrent(limit, |path| {
@@ -748,27 +1048,225 @@ pub async fn process_files(
             let encoder = encoder.clone();
             let upload_lock = upload_lock.clone();
             let cancel = cancel.clone();
+            let stats = &stats;
+            let failed = &failed;
             async move {
                 if cancel.load(Ordering::Relaxed) {
+                    stats.lock().unwrap().finish_file(Instant::now(), None);
                     let _ = app.emit("upload:file-done", ());
                     return;
                 }
                 // Hold read lock for duration of file processing so save can acquire
                 // the write lock and run exclusively between file uploads.
                 let _read_guard = upload_lock.read().await;
-                match process_one_file(&pack_state, &path, &dir, encoder).await {
-                    Ok(Some(media_file)) => {
+                let mut file_stats = None;
+                match process_one_file(
+                    &pack_state,
+                    &path,
+                    &dir,
+                    encoder,
+                    image_codec,
+                    video_container,
+                )
+                .await
+                {
+                    Ok(Some((Processed::Added(media_file), added))) => {
                         let _ = app.emit("upload:added", &media_file);
+                        file_stats = Some(added);
+                    }
+                    Ok(Some((Processed::Replaced(media_file), added))) => {
+                        let _ = app.emit("upload:replaced", [&media_file]);
+                        file_stats = Some(added);
                     }
                     Ok(None) => {}
                     Err(ProcessErrorKind::Skipped) => {
                         let _ = app.emit("upload:skipped", path.to_string_lossy().as_ref());
                     }
                     Err(err) => {
+                        let error = err.to_string();
                         let _ = app.emit(
                             "upload:error",
                             serde_json::json!({
                                 "path": path.to_string_lossy(),
+                                "error": error,
+                            }),
+                        );
+                        if on_error == OnEncodeError::Abort && !cancel.swap(true, Ordering::SeqCst)
+                        {
+                            let _ = app.emit("upload:aborted", path.to_string_lossy().as_ref());
+                        }
+                        failed.lock().unwrap().push((path, error));
+                    }
+                }
+                let throughput = {
+                    let mut stats = stats.lock().unwrap();
+                    let now = Instant::now();
+                    stats.finish_file(now, file_stats);
+                    stats.throughput(now)
+                };
+                let _ = app.emit("upload:stats", throughput);
+                let _ = app.emit("upload:file-done", ());
+            }
+        })
+        .await;
+
+    if let Some(pack) = pack_state.lock().await.as_ref() {
+        pack.seal_history();
+    }
+
+    let failed = failed.into_inner().unwrap();
+    if on_error == OnEncodeError::Quarantine && !failed.is_empty() {
+        let stem = pack_path.file_stem().unwrap_or_default().to_string_lossy();
+        let quarantine_dir = pack_path.with_file_name(format!("{stem} failed uploads"));
+        let dir = quarantine_dir.clone();
+        match tokio::task::spawn_blocking(move || quarantine_files(&dir, &failed)).await {
+            Ok(Ok(())) => {
+                let _ = app.emit("upload:quarantined", quarantine_dir);
+            }
+            Ok(Err(err)) => tracing::error!("Failed to quarantine files: {err}"),
+            Err(err) => tracing::error!("{err}"),
+        }
+    }
+
+    let summary = stats.into_inner().unwrap().summary(Instant::now());
+    tracing::info!(
+        "Processed {} files in {:.1}s (probe {:.1}s, encode {:.1}s, write {:.1}s)",
+        summary.files,
+        summary.elapsed_secs,
+        summary.probe_secs,
+        summary.encode_secs,
+        summary.write_secs,
+    );
+    let _ = app.emit("upload:summary", summary);
+    let _ = app.emit("upload:done", ());
+}
+
+/// An entry in a quarantine folder's `errors.json`.
+#[derive(Serialize, Deserialize)]
+struct QuarantinedFile {
+    source: PathBuf,
+    /// The copy's name in the quarantine folder, unless copying failed.
+    file: Option<String>,
+    error: String,
+}
+
+/// Copies files that failed to upload into `dir`, and adds them to its `errors.json` report.
+fn quarantine_files(dir: &Path, failed: &[(PathBuf, String)]) -> Result<()> {
+    fs::create_dir_all(dir)?;
+
+    let report_path = dir.join("errors.json");
+    let mut report: Vec<QuarantinedFile> = fs::read_to_string(&report_path)
+        .ok()
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_default();
+
+    for (source, error) in failed {
+        let name = source
+            .file_name()
+            .map_or_else(|| "file".to_string(), |n| n.to_string_lossy().into_owned());
+        let mut copy = dir.join(&name);
+        let mut i = 1;
+        while copy.exists() {
+            copy = dir.join(format!("{i} {name}"));
+            i += 1;
+        }
+
+        let file = match fs::copy(source, &copy) {
+            Ok(_) => copy.file_name().map(|n| n.to_string_lossy().into_owned()),
+            Err(err) => {
+                tracing::warn!("Failed to copy {} to quarantine: {err}", source.display());
+                None
+            }
+        };
+
+        report.push(QuarantinedFile {
+            source: source.clone(),
+            file,
+            error: error.clone(),
+        });
+    }
+
+    fs::write(report_path, serde_json::to_string_pretty(&report)?)?;
+    Ok(())
+}
+
+/// Re-encodes existing files with the pack's current settings (e.g. after changing the image
+/// codec) and any `overrides`, reporting progress through the same events as an upload. The
+/// results are swapped in together at the end, so the whole batch is a single undo step.
+pub async fn reencode_files(
+    pack_state: crate::PackState,
+    ids: Vec<u64>,
+    app: tauri::AppHandle,
+    encoder: HardwareEncoder,
+    overrides: EncodeOverrides,
+    upload_lock: Arc<RwLock<()>>,
+    cancel: Arc<AtomicBool>,
+) {
+    let total = ids.len();
+    let _ = app.emit("upload:start", serde_json::json!({ "total": total }));
+
+    // Held for the whole batch, since saving would delete the staged output before it's used.
+    let _read_guard = upload_lock.read().await;
+
+    let (dir, image_codec, video_container, view, names) = {
+        let lock = pack_state.lock().await;
+        let Some(pack) = lock.as_ref() else { return };
+        let view = match pack.get_view() {
+            Ok(view) => view,
+            Err(err) => {
+                tracing::error!("{err}");
+                let _ = app.emit("upload:done", ());
+                return;
+            }
+        };
+        let names: HashMap<u64, String> = pack
+            .get_files()
+            .await
+            .map(|files| files.into_iter().map(|f| (f.id, f.file_name)).collect())
+            .unwrap_or_default();
+        (
+            pack.dir().to_path_buf(),
+            pack.metadata().image_codec.unwrap_or_default(),
+            pack.metadata().video_container.unwrap_or_default(),
+            view,
+            names,
+        )
+    };
+
+    let limit = available_parallelism().map(|x| x.get()).ok();
+    let encoded = Arc::new(std::sync::Mutex::new(Vec::new()));
+
+    stream::iter(ids)
+        .for_each_concurrent(limit, |id| {
+            let app = app.clone();
+            let dir = dir.clone();
+            let encoder = encoder.clone();
+            let cancel = cancel.clone();
+            let view = &view;
+            let names = &names;
+            let encoded = encoded.clone();
+            async move {
+                if cancel.load(Ordering::Relaxed) {
+                    let _ = app.emit("upload:file-done", ());
+                    return;
+                }
+                match reencode_one_file(
+                    view,
+                    id,
+                    &dir,
+                    encoder,
+                    image_codec,
+                    video_container,
+                    overrides,
+                )
+                .await
+                {
+                    Ok(file) => encoded.lock().unwrap().push((id, file)),
+                    Err(err) => {
+                        let _ = app.emit(
+                            "upload:error",
+                            serde_json::json!({
+                                "path": names.get(&id).cloned().unwrap_or_else(|| id.to_string()),
                                 "error": err.to_string()
                             }),
                         );
@@ -779,39 +1277,148 @@ pub async fn process_files(
         })
         .await;
 
+    let encoded = std::mem::take(&mut *encoded.lock().unwrap());
+    let replaced: Vec<u64> = encoded.iter().map(|(id, _)| *id).collect();
+
+    if let Some(pack) = pack_state.lock().await.as_ref() {
+        let result = match pack.replace_files(encoded).await {
+            Ok(()) => pack.get_files().await,
+            Err(err) => Err(err),
+        };
+        match result {
+            Ok(files) => {
+                let files: Vec<MediaFile> = files
+                    .into_iter()
+                    .filter(|f| replaced.contains(&f.id))
+                    .collect();
+                let _ = app.emit("upload:replaced", &files);
+            }
+            Err(err) => {
+                let _ = app.emit(
+                    "upload:error",
+                    serde_json::json!({ "path": "", "error": err.to_string() }),
+                );
+            }
+        }
+    }
+
     let _ = app.emit("upload:done", ());
 }
 
+async fn reencode_one_file(
+    view: &MediaPackView,
+    id: u64,
+    dir: &Path,
+    encoder: HardwareEncoder,
+    image_codec: ImageCodec,
+    video_container: VideoContainer,
+    overrides: EncodeOverrides,
+) -> Result<EncodedFile> {
+    let (data, _) = view.get_file_data(id).await?;
+
+    let _permit = encode_semaphore().acquire_owned().await?;
+
+    let output_path = dir.join("media").join(Uuid::new_v4().to_string());
+
+    let (tx, rx) = oneshot::channel();
+    rayon::spawn(move || {
+        let result = (|| -> Result<Option<EncodedFile>> {
+            let mut input = NamedTempFile::new()?;
+            input.write_all(&data)?;
+            input.flush()?;
+            encode_file(
+                input.path(),
+                &output_path,
+                encoder,
+                image_codec,
+                video_container,
+                overrides,
+            )
+        })();
+        let _ = tx.send(result);
+    });
+
+    rx.await??
+        .ok_or_else(|| anyhow!("ffprobe couldn't read the stored file"))
+}
+
 async fn process_one_file(
     pack_state: &crate::PackState,
     path: &Path,
     dir: &Path,
     encoder: HardwareEncoder,
-) -> Result<Option<MediaFile>, ProcessErrorKind> {
-    let path_owned = path.to_path_buf();
-    let hash = tokio::task::spawn_blocking(move || hash_file(&path_owned))
-        .await
-        .map_err(|e| ProcessErrorKind::Other(e.into()))?
-        .map_err(ProcessErrorKind::HashError)?;
+    image_codec: ImageCodec,
+    video_container: VideoContainer,
+) -> Result<Option<(Processed, FileStats)>, ProcessErrorKind> {
+    let mut timings = StageTimings::default();
+    let hash_start = Instant::now();
+
+    // Read before encoding, so a broken sidecar file doesn't waste an encode.
+    let sidecar = read_sidecar(path)
+        .map_err(ProcessErrorKind::Other)?
+        .unwrap_or_default();
+
+    let metadata = std::fs::metadata(path).map_err(ProcessErrorKind::HashError)?;
+    let input_bytes = metadata.len();
+
+    // What the file was like the last time it was added to this pack, if it has been.
+    let source = {
+        let lock = pack_state.lock().await;
+        match lock.as_ref() {
+            Some(pack) => pack.sources().get(path).map_err(ProcessErrorKind::Other)?,
+            None => None,
+        }
+    };
+
+    // Reading every file again to hash it is most of the work of adding a folder that's
+    // already in the pack, so files that don't look changed keep the hash they had.
+    let hash = match source {
+        Some(source) if source.unchanged(&metadata) => source.hash,
+        _ => {
+            let path_owned = path.to_path_buf();
+            tokio::task::spawn_blocking(move || hash_file(&path_owned))
+                .await
+                .map_err(|e| ProcessErrorKind::Other(e.into()))?
+                .map_err(ProcessErrorKind::HashError)?
+        }
+    };
+    timings.probe = hash_start.elapsed();
 
     // Duplicates are always rejected (add_file enforces this with a DB-level
     // constraint, so this can't be turned off) - checking here just avoids
     // wasting an encode on a file we already know will be rejected.
-    {
+    //
+    // A file that was added before but has changed since replaces the version
+    // that was added, if it's still in the pack.
+    let replaces = {
         let lock = pack_state.lock().await;
-        if let Some(pack) = lock.as_ref() {
-            if pack
-                .check_hash(&hash)
-                .await
-                .map_err(|e| ProcessErrorKind::Other(e))?
-            {
-                return Err(ProcessErrorKind::Skipped);
+        match lock.as_ref() {
+            Some(pack) => {
+                if pack
+                    .check_hash(&hash)
+                    .await
+                    .map_err(|e| ProcessErrorKind::Other(e))?
+                {
+                    pack.sources()
+                        .set(path, &metadata, hash)
+                        .map_err(ProcessErrorKind::Other)?;
+                    return Err(ProcessErrorKind::Skipped);
+                }
+
+                match source {
+                    Some(source) => pack
+                        .file_with_hash(&source.hash)
+                        .await
+                        .map_err(ProcessErrorKind::Other)?,
+                    None => None,
+                }
             }
+            None => None,
         }
-    }
+    };
 
     let _permit = encode_semaphore()
-        .acquire()
+        .acquire_owned()
         .await
         .map_err(|e| ProcessErrorKind::Other(anyhow!("{e}")))?;
 
@@ -820,8 +1427,27 @@ async fn process_one_file(
     let path_owned = path.to_path_buf();
 
     let (tx, rx) = oneshot::channel();
+    let video_codec = encoder.video_codec(video_container);
     rayon::spawn(move || {
-        let _ = tx.send(encode_file(&path_owned, &output_path, encoder));
+        let probe_start = Instant::now();
+        let result = file_info(&path_owned).and_then(|info| {
+            let probe = probe_start.elapsed();
+            let encode_start = Instant::now();
+            info.map(|info| {
+                encode_probed_file(
+                    &path_owned,
+                    info,
+                    &output_path,
+                    encoder,
+                    image_codec,
+                    video_container,
+                    EncodeOverrides::default(),
+                )
+                .map(|encoded| (encoded, probe, encode_start.elapsed()))
+            })
+            .transpose()
+        });
+        let _ = tx.send(result);
     });
 
     let encoded = rx
@@ -829,23 +1455,58 @@ async fn process_one_file(
         .map_err(|e| ProcessErrorKind::Other(e.into()))?
         .map_err(ProcessErrorKind::EncodeError)?;
 
-    let encoded = match encoded {
+    let (encoded, probe, encode) = match encoded {
         Some(e) => e,
         None => return Ok(None),
     };
+    timings.probe += probe;
+    timings.encode = encode;
+
+    let codec = |codec: &str| [("codec", codec.to_string().into())];
+    let label = match (&encoded.info, encoded.codec) {
+        (FileInfo::Image { .. }, Some(image_codec)) => tr_args(
+            "pack-editor-encoded-image-codec",
+            codec(image_codec.as_str()),
+        ),
+        (FileInfo::Image { .. }, None) => tr("pack-editor-encoded-image"),
+        (FileInfo::Video { .. }, _) => tr_args("pack-editor-encoded-video", codec(video_codec)),
+        (FileInfo::Audio { .. }, _) => tr_args("pack-editor-encoded-audio", codec("opus")),
+    };
 
+    let write_start = Instant::now();
     let mut lock = pack_state.lock().await;
     if let Some(pack) = lock.as_mut() {
-        let media = pack
-            .add_file(encoded, path, hash)
-            .await
-            .map_err(ProcessErrorKind::PackError)?;
+        let media = match replaces {
+            Some(id) => pack
+                .replace_source(id, encoded, hash)
+                .await
+                .map(|media| media.map(Processed::Replaced)),
+            None => pack
+                .add_file(encoded, path, hash, sidecar)
+                .await
+                .map(|media| media.map(Processed::Added)),
+        }
+        .map_err(ProcessErrorKind::PackError)?;
+        timings.write = write_start.elapsed();
         match media {
             // The pre-check above already handles the common case; this only
             // fires when a race let a since-inserted duplicate through, and the
             // DB's own uniqueness constraint caught it - treat it the same as
             // the pre-check's skip.
-            Some(media) => Ok(Some(media)),
+            Some(media) => {
+                pack.sources()
+                    .set(path, &metadata, hash)
+                    .map_err(ProcessErrorKind::Other)?;
+
+                let (Processed::Added(file) | Processed::Replaced(file)) = &media;
+                let stats = FileStats {
+                    label,
+                    input_bytes,
+                    output_bytes: file.size,
+                    timings,
+                };
+                Ok(Some((media, stats)))
+            }
             None => Err(ProcessErrorKind::Skipped),
         }
     } else {
@@ -0,0 +1,183 @@
+use rusqlite::types::Value;
+use serde::{Deserialize, Serialize};
+use shared::{
+    locale::{tr, tr_args},
+    read_pack::Metadata,
+    texts::{TextEntry, TextKind},
+};
+
+/// How many operations are kept for undoing. The oldest ones are dropped past this.
+const MAX_HISTORY: usize = 200;
+
+/// A full copy of a `media` row and its tags, so a deleted file can be put back exactly as it
+/// was. Columns are kept generically so this doesn't need updating with every migration.
+#[derive(Debug, Clone)]
+pub struct MediaRow {
+    pub id: u64,
+    pub columns: Vec<(String, Value)>,
+    pub tags: Vec<String>,
+}
+
+/// A single edit to a pack, holding enough to both revert and re-apply it.
+#[derive(Debug, Clone)]
+pub enum Operation {
+    AddFiles {
+        rows: Vec<MediaRow>,
+    },
+    RemoveFiles {
+        rows: Vec<MediaRow>,
+    },
+    /// A tag was added to (`added = true`) or removed from files. `ids` only holds the files
+    /// that actually changed. `created` is set if the tag didn't exist before, so undoing the
+    /// addition deletes it again.
+    Tag {
+        ids: Vec<u64>,
+        tag: String,
+        added: bool,
+        created: bool,
+    },
+    /// Files were re-encoded. Both versions of each row are kept: the old data stays in the pack
+    /// file (or the staging directory) until the next save.
+    ReplaceFiles {
+        old: Vec<MediaRow>,
+        new: Vec<MediaRow>,
+    },
+    SetTitle {
+        id: u64,
+        old: String,
+        new: String,
+    },
+    SetMetadata {
+        old: Metadata,
+        new: Metadata,
+    },
+    /// All the texts of one kind in one language were replaced by an import.
+    SetTexts {
+        kind: TextKind,
+        locale: Option<String>,
+        old: Vec<TextEntry>,
+        new: Vec<TextEntry>,
+    },
+}
+
+impl Operation {
+    fn label(&self) -> String {
+        let count = |count: usize| [("count", count.into())];
+        let tag = |tag: &str| [("tag", tag.to_string().into())];
+
+        match self {
+            Operation::AddFiles { rows } => tr_args("pack-editor-add-files", count(rows.len())),
+            Operation::RemoveFiles { rows } => {
+                tr_args("pack-editor-delete-files", count(rows.len()))
+            }
+            Operation::Tag {
+                tag: name,
+                added: true,
+                ..
+            } => tr_args("pack-editor-add-tag", tag(name)),
+            Operation::Tag {
+                tag: name,
+                added: false,
+                ..
+            } => tr_args("pack-editor-remove-tag", tag(name)),
+            Operation::ReplaceFiles { new, .. } => {
+                tr_args("pack-editor-reencode-files", count(new.len()))
+            }
+            Operation::SetTitle { .. } => tr("pack-editor-rename-file"),
+            Operation::SetMetadata { .. } => tr("pack-editor-edit-details"),
+            Operation::SetTexts { kind, .. } => tr(match kind {
+                TextKind::Notification => "pack-editor-import-notifications",
+                TextKind::Prompt => "pack-editor-import-prompts",
+                TextKind::Link => "pack-editor-import-links",
+            }),
+        }
+    }
+
+    /// Folds `next` into `self` if they belong in the same undo step, which is only the case
+    /// for files added by the same upload.
+    fn merge(&mut self, next: Operation) -> Result<(), Operation> {
+        match (self, next) {
+            (Operation::AddFiles { rows }, Operation::AddFiles { rows: next_rows }) => {
+                rows.extend(next_rows);
+                Ok(())
+            }
+            (_, next) => Err(next),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct HistoryState {
+    pub undo: Option<String>,
+    pub redo: Option<String>,
+}
+
+/// Undo and redo stacks for a [`crate::pack::MediaPack`]. Only covers changes since the last
+/// save: saving compacts the pack file and deletes staged files, so removed media can't be
+/// brought back after that.
+#[derive(Default)]
+pub struct History {
+    undo: Vec<Operation>,
+    redo: Vec<Operation>,
+    // Whether the next operation may be merged into the last one (see `Operation::merge`).
+    open: bool,
+}
+
+impl History {
+    pub fn record(&mut self, op: Operation) {
+        self.redo.clear();
+
+        let op = match self.undo.last_mut() {
+            Some(last) if self.open => match last.merge(op) {
+                Ok(()) => return,
+                Err(op) => op,
+            },
+            _ => op,
+        };
+
+        self.undo.push(op);
+        self.open = true;
+        if self.undo.len() > MAX_HISTORY {
+            self.undo.remove(0);
+        }
+    }
+
+    /// Stops the last operation from absorbing the next one, e.g. once an upload finishes.
+    pub fn seal(&mut self) {
+        self.open = false;
+    }
+
+    pub fn pop_undo(&mut self) -> Option<Operation> {
+        self.open = false;
+        self.undo.pop()
+    }
+
+    pub fn pop_redo(&mut self) -> Option<Operation> {
+        self.open = false;
+        self.redo.pop()
+    }
+
+    /// Puts an operation that was just undone onto the redo stack.
+    pub fn push_redo(&mut self, op: Operation) {
+        self.redo.push(op);
+    }
+
+    /// Puts an operation that was just redone (or failed to undo) back onto the undo stack,
+    /// without clearing the redo stack as [`Self::record`] would.
+    pub fn push_undo(&mut self, op: Operation) {
+        self.undo.push(op);
+    }
+
+    pub fn clear(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+        self.open = false;
+    }
+
+    pub fn state(&self) -> HistoryState {
+        HistoryState {
+            undo: self.undo.last().map(Operation::label),
+            redo: self.redo.last().map(Operation::label),
+        }
+    }
+}
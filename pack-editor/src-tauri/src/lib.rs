@@ -1,16 +1,25 @@
+mod budget;
 mod encode;
+mod history;
 mod media_server;
 mod pack;
+mod sources;
 mod thumbnail;
+mod upload_stats;
 
 use std::{
-    path::PathBuf,
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
     sync::{
         atomic::{AtomicBool, Ordering},
         Arc, OnceLock,
     },
+    time::Duration,
 };
 
+use budget::BudgetReport;
+use history::HistoryState;
 use pack::{MediaFile, MediaPack};
 use serde::{Deserialize, Serialize};
 
@@ -44,11 +53,20 @@ async fn check_for_update() -> Result<Option<String>, String> {
         Ok(None)
     }
 }
-use shared::read_pack::Metadata;
-use tauri::{AppHandle, Emitter, Manager, State};
+use shared::{
+    children,
+    db::PackStats,
+    encode::{ImageCodec, VideoContainer},
+    goals::{Goal, Milestone},
+    locale::tr,
+    read_pack::{Credit, Metadata, RecencyBias},
+    texts::{self, PromptKind, TextKind},
+    user_config::{self, BackupSettings, EncodeSettings, RecentPack},
+};
+use tauri::{AppHandle, Emitter, Manager, RunEvent, State};
 use tokio::sync::{Mutex, RwLock};
 
-use crate::encode::HardwareEncoder;
+use crate::encode::{EncodeOverrides, HardwareEncoder};
 
 pub type PackState = Arc<Mutex<Option<MediaPack>>>;
 
@@ -76,6 +94,8 @@ impl AppState {
 pub struct PackInfo {
     pub name: String,
     pub has_unsaved_changes: bool,
+    /// The pack file can't be written to, so it can only be saved with Save As.
+    pub read_only: bool,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
@@ -84,6 +104,27 @@ pub struct MetadataDto {
     pub creator: Option<String>,
     pub description: Option<String>,
     pub version: Option<String>,
+    pub image_codec: ImageCodec,
+    #[serde(default)]
+    pub video_container: VideoContainer,
+    #[serde(default)]
+    pub size_budgets: BTreeMap<String, u64>,
+    #[serde(default)]
+    pub goals: Vec<Goal>,
+    #[serde(default)]
+    pub milestones: Vec<Milestone>,
+    #[serde(default)]
+    pub ratings: Vec<String>,
+    #[serde(default)]
+    pub tag_ratings: BTreeMap<String, u32>,
+    #[serde(default)]
+    pub recency_bias: Option<RecencyBias>,
+    #[serde(default)]
+    pub prompt_kinds: Vec<PromptKind>,
+    #[serde(default)]
+    pub license: Option<String>,
+    #[serde(default)]
+    pub credits: Vec<Credit>,
 }
 
 impl From<Metadata> for MetadataDto {
@@ -93,6 +134,17 @@ impl From<Metadata> for MetadataDto {
             creator: m.creator,
             description: m.description,
             version: m.version,
+            image_codec: m.image_codec.unwrap_or_default(),
+            video_container: m.video_container.unwrap_or_default(),
+            size_budgets: m.size_budgets,
+            goals: m.goals,
+            milestones: m.milestones,
+            ratings: m.ratings,
+            tag_ratings: m.tag_ratings,
+            recency_bias: m.recency_bias,
+            prompt_kinds: m.prompt_kinds,
+            license: m.license,
+            credits: m.credits,
         }
     }
 }
@@ -104,7 +156,17 @@ impl From<MetadataDto> for Metadata {
             creator: d.creator,
             description: d.description,
             version: d.version,
-            ..Default::default()
+            image_codec: Some(d.image_codec),
+            video_container: Some(d.video_container),
+            size_budgets: d.size_budgets,
+            goals: d.goals,
+            milestones: d.milestones,
+            ratings: d.ratings,
+            tag_ratings: d.tag_ratings,
+            recency_bias: d.recency_bias,
+            prompt_kinds: d.prompt_kinds,
+            license: d.license,
+            credits: d.credits,
         }
     }
 }
@@ -122,8 +184,8 @@ async fn new_pack_dialog(
         app_c
             .dialog()
             .file()
-            .set_title("Create new pack")
-            .add_filter("Lewdware Pack", &["lwpack"])
+            .set_title(tr("pack-editor-create-pack"))
+            .add_filter(tr("pack-editor-pack-filter"), &["lwpack"])
             .blocking_save_file()
     })
     .await
@@ -136,15 +198,17 @@ async fn new_pack_dialog(
     let name = path
         .file_stem()
         .map(|s| s.to_string_lossy().to_string())
-        .unwrap_or_else(|| "New Pack".to_string());
+        .unwrap_or_else(|| tr("pack-editor-new-pack"));
 
     let data_dir = dirs::data_dir().ok_or("Couldn't find data dir")?;
     let pack = MediaPack::new(path, &data_dir, &name)
         .await
         .map_err(|e| e.to_string())?;
+    remember_pack(&pack);
     let info = PackInfo {
         name: pack.name(),
         has_unsaved_changes: false,
+        read_only: false,
     };
     *state.pack.lock().await = Some(pack);
     Ok(Some(info))
@@ -154,6 +218,7 @@ async fn new_pack_dialog(
 async fn open_pack_dialog(
     state: State<'_, AppState>,
     app: AppHandle,
+    read_only: bool,
 ) -> Result<Option<PackInfo>, String> {
     use tauri_plugin_dialog::DialogExt;
     let app_c = app.clone();
@@ -161,8 +226,8 @@ async fn open_pack_dialog(
         app_c
             .dialog()
             .file()
-            .set_title("Open pack")
-            .add_filter("Lewdware Pack", &["lwpack"])
+            .set_title(tr("pack-editor-open-pack"))
+            .add_filter(tr("pack-editor-pack-filter"), &["lwpack"])
             .blocking_pick_file()
     })
     .await
@@ -171,17 +236,68 @@ async fn open_pack_dialog(
     let Some(path) = file else { return Ok(None) };
     let path: PathBuf = path.into_path().map_err(|e| e.to_string())?;
 
+    open_pack_at(&state, &app, path, read_only).await.map(Some)
+}
+
+/// Opens a pack, emitting `open:progress` events while it does, since extracting the index of a
+/// large pack can take a while. Packs that can't be written to are opened read-only even if
+/// `read_only` isn't set.
+async fn open_pack_at(
+    state: &AppState,
+    app: &AppHandle,
+    path: PathBuf,
+    read_only: bool,
+) -> Result<PackInfo, String> {
     let data_dir = dirs::data_dir().ok_or("Couldn't find data dir")?;
-    let pack = MediaPack::open(path, &data_dir)
-        .await
-        .map_err(|e| e.to_string())?;
+    let pack = MediaPack::open_with_progress(path, &data_dir, read_only, |progress| {
+        let _ = app.emit("open:progress", progress);
+    })
+    .await
+    .map_err(|e| e.to_string())?;
+    remember_pack(&pack);
     let has_unsaved_changes = !pack.is_saved().await;
     let info = PackInfo {
         name: pack.name(),
         has_unsaved_changes,
+        read_only: pack.read_only(),
     };
     *state.pack.lock().await = Some(pack);
-    Ok(Some(info))
+    Ok(info)
+}
+
+/// Adds the pack to the recent packs list. Failing to do so isn't worth interrupting anything.
+fn remember_pack(pack: &MediaPack) {
+    if let Err(err) =
+        user_config::record_recent_pack(pack.path(), &pack.id().to_string(), &pack.name())
+    {
+        tracing::error!("Failed to update recent packs: {err}");
+    }
+}
+
+// ── Recent packs ─────────────────────────────────────────────────────────────
+
+#[tauri::command]
+fn get_recent_packs() -> Result<Vec<RecentPack>, String> {
+    user_config::load_recent_packs().map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn open_recent_pack(
+    state: State<'_, AppState>,
+    app: AppHandle,
+    path: PathBuf,
+) -> Result<PackInfo, String> {
+    open_pack_at(&state, &app, path, false).await
+}
+
+#[tauri::command]
+fn set_recent_pack_pinned(path: PathBuf, pinned: bool) -> Result<Vec<RecentPack>, String> {
+    user_config::set_recent_pack_pinned(&path, pinned).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn forget_recent_pack(path: PathBuf) -> Result<Vec<RecentPack>, String> {
+    user_config::forget_recent_pack(&path).map_err(|e| e.to_string())
 }
 
 #[tauri::command]
@@ -194,7 +310,7 @@ async fn save_pack(state: State<'_, AppState>, app: AppHandle) -> Result<(), Str
     if let Some(pack) = lock.as_ref() {
         let app_cb = app.clone();
         tracing::warn!("Here!");
-        pack.save(move |saved, t| {
+        pack.save(backups_to_keep(), move |saved, t| {
             let _ = app_cb.emit(
                 "save:progress",
                 serde_json::json!({ "saved": saved, "total": t }),
@@ -218,8 +334,8 @@ async fn save_pack_as_dialog(
         app_c
             .dialog()
             .file()
-            .set_title("Save pack as")
-            .add_filter("Lewdware Pack", &["lwpack"])
+            .set_title(tr("pack-editor-save-pack-as"))
+            .add_filter(tr("pack-editor-pack-filter"), &["lwpack"])
             .blocking_save_file()
     })
     .await
@@ -233,7 +349,7 @@ async fn save_pack_as_dialog(
     if let Some(pack) = lock.as_ref() {
         let app_cb = app.clone();
         let new_pack = pack
-            .save_as(&path, move |saved, t| {
+            .save_as(&path, backups_to_keep(), move |saved, t| {
                 let _ = app_cb.emit(
                     "save:progress",
                     serde_json::json!({ "saved": saved, "total": t }),
@@ -243,9 +359,11 @@ async fn save_pack_as_dialog(
             .map_err(|e| e.to_string())?;
 
         if let Some(new_pack) = new_pack {
+            remember_pack(&new_pack);
             let info = PackInfo {
                 name: new_pack.name(),
                 has_unsaved_changes: false,
+                read_only: new_pack.read_only(),
             };
             *lock = Some(new_pack);
             let _ = app.emit("save:done", ());
@@ -255,6 +373,41 @@ async fn save_pack_as_dialog(
     Ok(None)
 }
 
+#[tauri::command]
+async fn export_manifest_dialog(state: State<'_, AppState>, app: AppHandle) -> Result<bool, String> {
+    use tauri_plugin_dialog::DialogExt;
+
+    let manifest = {
+        let lock = state.pack.lock().await;
+        match lock.as_ref() {
+            Some(pack) => pack.manifest().await.map_err(|e| e.to_string())?,
+            None => return Err(tr("pack-editor-no-pack")),
+        }
+    };
+
+    let file_name = format!("{}.json", manifest.metadata.name);
+    let file = tokio::task::spawn_blocking(move || {
+        app.dialog()
+            .file()
+            .set_title(tr("pack-editor-export-manifest"))
+            .set_file_name(file_name)
+            .add_filter("JSON", &["json"])
+            .blocking_save_file()
+    })
+    .await
+    .map_err(|e| e.to_string())?;
+
+    let Some(path) = file else { return Ok(false) };
+    let path: PathBuf = path.into_path().map_err(|e| e.to_string())?;
+
+    let json = serde_json::to_string_pretty(&manifest).map_err(|e| e.to_string())?;
+    tokio::fs::write(&path, json)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    Ok(true)
+}
+
 #[tauri::command]
 async fn discard_changes(state: State<'_, AppState>) -> Result<MetadataDto, String> {
     let lock = state.pack.lock().await;
@@ -262,7 +415,7 @@ async fn discard_changes(state: State<'_, AppState>) -> Result<MetadataDto, Stri
         let metadata = pack.discard_changes().await.map_err(|e| e.to_string())?;
         Ok(metadata.into())
     } else {
-        Err("No pack open".to_string())
+        Err(tr("pack-editor-no-pack"))
     }
 }
 
@@ -319,6 +472,164 @@ async fn set_file_title(state: State<'_, AppState>, id: u64, name: String) -> Re
 
 // ── Tags ─────────────────────────────────────────────────────────────────────
 
+#[tauri::command]
+async fn get_pack_stats(state: State<'_, AppState>) -> Result<PackStats, String> {
+    let lock = state.pack.lock().await;
+    match lock.as_ref() {
+        Some(pack) => pack.stats().await.map_err(|e| e.to_string()),
+        None => Err(tr("pack-editor-no-pack")),
+    }
+}
+
+/// How each tag with a size budget stands, and what would be cut to fit.
+#[tauri::command]
+async fn get_budget_report(state: State<'_, AppState>) -> Result<Vec<BudgetReport>, String> {
+    let lock = state.pack.lock().await;
+    let Some(pack) = lock.as_ref() else {
+        return Err(tr("pack-editor-no-pack"));
+    };
+    let files = pack.get_files().await.map_err(|e| e.to_string())?;
+    Ok(budget::plan_cuts(&files, &pack.metadata().size_budgets))
+}
+
+/// Removes the files listed by `get_budget_report` as a single undo step, and returns the report
+/// of what was cut.
+#[tauri::command]
+async fn apply_size_budgets(state: State<'_, AppState>) -> Result<Vec<BudgetReport>, String> {
+    let lock = state.pack.lock().await;
+    let Some(pack) = lock.as_ref() else {
+        return Err(tr("pack-editor-no-pack"));
+    };
+    let files = pack.get_files().await.map_err(|e| e.to_string())?;
+    let reports = budget::plan_cuts(&files, &pack.metadata().size_budgets);
+
+    let ids: Vec<u64> = reports
+        .iter()
+        .flat_map(|r| r.cut.iter().map(|f| f.id))
+        .collect();
+    if !ids.is_empty() {
+        pack.remove_files(ids).await.map_err(|e| e.to_string())?;
+    }
+    Ok(reports)
+}
+
+#[derive(Serialize, Clone, Debug)]
+struct TextImport {
+    path: PathBuf,
+    kind: TextKind,
+    locale: Option<String>,
+    count: usize,
+}
+
+/// How many notifications, prompts and links the pack has.
+#[tauri::command]
+async fn get_text_counts(state: State<'_, AppState>) -> Result<Vec<(TextKind, u64)>, String> {
+    let lock = state.pack.lock().await;
+    match lock.as_ref() {
+        Some(pack) => pack.text_counts().await.map_err(|e| e.to_string()),
+        None => Err(tr("pack-editor-no-pack")),
+    }
+}
+
+/// Imports each file in `paths`, replacing the pack's texts of the kind and language it holds.
+/// Each file is its own undo step.
+async fn import_text_files(
+    pack: &MediaPack,
+    paths: Vec<PathBuf>,
+) -> Result<Vec<TextImport>, String> {
+    let mut imports = Vec::new();
+    for path in paths {
+        let file = texts::read_text_file(&path).map_err(|e| e.to_string())?;
+        let count = file.entries.len();
+        pack.set_texts(file.kind, file.locale.clone(), file.entries)
+            .await
+            .map_err(|e| e.to_string())?;
+        imports.push(TextImport {
+            path,
+            kind: file.kind,
+            locale: file.locale,
+            count,
+        });
+    }
+    Ok(imports)
+}
+
+/// The text files among `paths`, plus any directly inside the folders in `paths`.
+fn find_text_files(paths: &[PathBuf]) -> Vec<PathBuf> {
+    let mut result = Vec::new();
+    for path in paths {
+        if path.is_dir() {
+            result.extend(text_files_in(path));
+        } else if texts::is_text_file(path) {
+            result.push(path.clone());
+        }
+    }
+    result
+}
+
+fn text_files_in(dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut paths: Vec<PathBuf> = entries
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|path| path.is_file() && texts::is_text_file(path))
+        .collect();
+    paths.sort();
+    paths
+}
+
+/// Imports notifications, prompts or links from `.txt` or `.csv` files picked by the user.
+#[tauri::command]
+async fn import_texts_dialog(
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<Vec<TextImport>, String> {
+    use tauri_plugin_dialog::DialogExt;
+    let files = tokio::task::spawn_blocking(move || {
+        app.dialog()
+            .file()
+            .set_title(tr("pack-editor-import-texts"))
+            .add_filter(tr("pack-editor-text-filter"), &["txt", "csv"])
+            .blocking_pick_files()
+    })
+    .await
+    .map_err(|e| e.to_string())?;
+
+    let Some(handles) = files else {
+        return Ok(Vec::new());
+    };
+    let paths: Vec<PathBuf> = handles
+        .into_iter()
+        .filter_map(|h| h.into_path().ok())
+        .collect();
+
+    let lock = state.pack.lock().await;
+    let Some(pack) = lock.as_ref() else {
+        return Err(tr("pack-editor-no-pack"));
+    };
+    import_text_files(pack, paths).await
+}
+
+/// Imports the text files that came with some added files or folders, and lets the frontend know
+/// with `texts:imported`. Errors go out as `texts:error`, rather than stopping the upload.
+async fn import_added_texts(pack_state: &PackState, app: &AppHandle, paths: Vec<PathBuf>) {
+    if paths.is_empty() {
+        return;
+    }
+    let lock = pack_state.lock().await;
+    let Some(pack) = lock.as_ref() else { return };
+    match import_text_files(pack, paths).await {
+        Ok(imports) => {
+            let _ = app.emit("texts:imported", imports);
+        }
+        Err(err) => {
+            let _ = app.emit("texts:error", err);
+        }
+    }
+}
+
 #[tauri::command]
 async fn get_all_tags(state: State<'_, AppState>) -> Result<Vec<String>, String> {
     let lock = state.pack.lock().await;
@@ -374,6 +685,36 @@ async fn create_and_add_tag(
     Ok(())
 }
 
+#[tauri::command]
+async fn add_tag_to_files(
+    state: State<'_, AppState>,
+    ids: Vec<u64>,
+    tag: String,
+) -> Result<(), String> {
+    let lock = state.pack.lock().await;
+    if let Some(pack) = lock.as_ref() {
+        pack.add_tag_to_files(ids, tag)
+            .await
+            .map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+#[tauri::command]
+async fn remove_tag_from_files(
+    state: State<'_, AppState>,
+    ids: Vec<u64>,
+    tag: String,
+) -> Result<(), String> {
+    let lock = state.pack.lock().await;
+    if let Some(pack) = lock.as_ref() {
+        pack.remove_tag_from_files(ids, tag)
+            .await
+            .map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
 // ── Metadata ─────────────────────────────────────────────────────────────────
 
 #[tauri::command]
@@ -381,7 +722,7 @@ async fn get_pack_metadata(state: State<'_, AppState>) -> Result<MetadataDto, St
     let lock = state.pack.lock().await;
     match lock.as_ref() {
         Some(pack) => Ok(pack.metadata().into()),
-        None => Err("No pack open".to_string()),
+        None => Err(tr("pack-editor-no-pack")),
     }
 }
 
@@ -414,6 +755,32 @@ async fn mark_pack_unsaved(state: State<'_, AppState>) -> Result<(), String> {
     Ok(())
 }
 
+// ── History ──────────────────────────────────────────────────────────────────
+
+#[tauri::command]
+async fn get_history(state: State<'_, AppState>) -> Result<HistoryState, String> {
+    let lock = state.pack.lock().await;
+    Ok(lock.as_ref().map(|pack| pack.history()).unwrap_or_default())
+}
+
+#[tauri::command]
+async fn undo(state: State<'_, AppState>) -> Result<HistoryState, String> {
+    let lock = state.pack.lock().await;
+    match lock.as_ref() {
+        Some(pack) => pack.undo().await.map_err(|e| e.to_string()),
+        None => Ok(HistoryState::default()),
+    }
+}
+
+#[tauri::command]
+async fn redo(state: State<'_, AppState>) -> Result<HistoryState, String> {
+    let lock = state.pack.lock().await;
+    match lock.as_ref() {
+        Some(pack) => pack.redo().await.map_err(|e| e.to_string()),
+        None => Ok(HistoryState::default()),
+    }
+}
+
 // ── Upload ───────────────────────────────────────────────────────────────────
 
 #[tauri::command]
@@ -424,7 +791,7 @@ async fn add_files_dialog(state: State<'_, AppState>, app: AppHandle) -> Result<
         app_c
             .dialog()
             .file()
-            .set_title("Select files")
+            .set_title(tr("pack-editor-select-files"))
             .blocking_pick_files()
     })
     .await
@@ -472,7 +839,7 @@ async fn add_folder_dialog(
         app_c
             .dialog()
             .file()
-            .set_title("Select folder")
+            .set_title(tr("pack-editor-select-folder"))
             .blocking_pick_folder()
     })
     .await
@@ -481,6 +848,8 @@ async fn add_folder_dialog(
     let Some(folder) = folder else { return Ok(()) };
     let folder: PathBuf = folder.into_path().map_err(|e| e.to_string())?;
 
+    import_added_texts(&state.pack, &app, text_files_in(&folder)).await;
+
     let paths = tokio::task::spawn_blocking(move || encode::explore_folder(&folder, recursive))
         .await
         .map_err(|e| e.to_string())?;
@@ -515,6 +884,8 @@ async fn add_paths(
     app: AppHandle,
     paths: Vec<PathBuf>,
 ) -> Result<(), String> {
+    import_added_texts(&state.pack, &app, find_text_files(&paths)).await;
+
     let paths = tokio::task::spawn_blocking(move || {
         let mut result = Vec::new();
         for path in paths {
@@ -553,12 +924,93 @@ async fn add_paths(
     Ok(())
 }
 
+#[tauri::command]
+async fn reencode_files(
+    state: State<'_, AppState>,
+    app: AppHandle,
+    ids: Vec<u64>,
+    overrides: Option<EncodeOverrides>,
+) -> Result<(), String> {
+    if ids.is_empty() {
+        return Ok(());
+    }
+
+    let pack_state = state.pack.clone();
+    let encoder = state
+        .hardware_encoder
+        .get()
+        .cloned()
+        .unwrap_or(HardwareEncoder::SoftwareFallback);
+    let upload_lock = state.upload_lock.clone();
+    let cancel = state.cancel_flag.clone();
+    cancel.store(false, Ordering::SeqCst);
+    tauri::async_runtime::spawn(encode::reencode_files(
+        pack_state,
+        ids,
+        app,
+        encoder,
+        overrides.unwrap_or_default(),
+        upload_lock,
+        cancel,
+    ));
+    Ok(())
+}
+
 #[tauri::command]
 async fn cancel_upload(state: State<'_, AppState>) -> Result<(), String> {
     state.cancel_flag.store(true, Ordering::SeqCst);
     Ok(())
 }
 
+// ── Encode settings ──────────────────────────────────────────────────────────
+
+#[derive(Serialize)]
+struct EncodeSettingsInfo {
+    #[serde(flatten)]
+    settings: EncodeSettings,
+    /// What `jobs` falls back to when unset, for display.
+    default_jobs: usize,
+}
+
+#[tauri::command]
+fn get_encode_settings() -> Result<EncodeSettingsInfo, String> {
+    Ok(EncodeSettingsInfo {
+        settings: user_config::load_encode_settings().map_err(|e| e.to_string())?,
+        default_jobs: encode::default_encode_jobs(),
+    })
+}
+
+/// Saves and applies the settings. Uploads already running keep their old job limit.
+#[tauri::command]
+fn set_encode_settings(settings: EncodeSettings) -> Result<(), String> {
+    user_config::save_encode_settings(&settings).map_err(|e| e.to_string())?;
+    encode::apply_encode_settings(&settings);
+    Ok(())
+}
+
+// ── Backups ──────────────────────────────────────────────────────────────────
+
+#[tauri::command]
+fn get_backup_settings() -> Result<BackupSettings, String> {
+    user_config::load_backup_settings().map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn set_backup_settings(settings: BackupSettings) -> Result<(), String> {
+    user_config::save_backup_settings(&settings).map_err(|e| e.to_string())
+}
+
+/// Falls back to no backups if the settings can't be read, rather than failing the save.
+fn backups_to_keep() -> usize {
+    match user_config::load_backup_settings() {
+        Ok(settings) => settings.keep,
+        Err(err) => {
+            tracing::error!("Failed to load backup settings: {err}");
+            0
+        }
+    }
+}
+
 // ── Media server port ────────────────────────────────────────────────────────
 
 #[tauri::command]
@@ -568,9 +1020,14 @@ fn get_media_port(state: State<'_, AppState>) -> u16 {
 
 // ── Entry point ──────────────────────────────────────────────────────────────
 
+/// How long quitting waits for ffmpeg processes to exit once they've been killed.
+const CHILD_SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(5);
+
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
     let _log_guard = shared::logging::init("pack-editor");
+    let language = user_config::load_config().ok().and_then(|config| config.language);
+    shared::locale::init(language.as_deref());
 
     tauri::Builder::default()
         .plugin(tauri_plugin_opener::init())
@@ -601,6 +1058,10 @@ pub fn run() {
                     encode::init_binary_paths(ffmpeg, ffprobe);
                 }
             }
+            match user_config::load_encode_settings() {
+                Ok(settings) => encode::apply_encode_settings(&settings),
+                Err(err) => tracing::error!("Failed to load encode settings: {err}"),
+            }
             let _ = state
                 .hardware_encoder
                 .set(HardwareEncoder::detect_and_test());
@@ -632,8 +1093,13 @@ pub fn run() {
         .invoke_handler(tauri::generate_handler![
             new_pack_dialog,
             open_pack_dialog,
+            get_recent_packs,
+            open_recent_pack,
+            set_recent_pack_pinned,
+            forget_recent_pack,
             save_pack,
             save_pack_as_dialog,
+            export_manifest_dialog,
             discard_changes,
             close_pack,
             confirm_close,
@@ -642,21 +1108,42 @@ pub fn run() {
             remove_files,
             set_file_title,
             get_all_tags,
+            get_pack_stats,
+            get_budget_report,
+            apply_size_budgets,
+            get_text_counts,
+            import_texts_dialog,
             get_file_tags,
             add_tag_to_file,
             remove_tag_from_file,
             create_and_add_tag,
+            add_tag_to_files,
+            remove_tag_from_files,
             get_pack_metadata,
             set_pack_metadata,
             save_pack_metadata,
             mark_pack_unsaved,
+            get_history,
+            undo,
+            redo,
             add_files_dialog,
             add_folder_dialog,
             add_paths,
+            reencode_files,
             cancel_upload,
+            get_encode_settings,
+            set_encode_settings,
+            get_backup_settings,
+            set_backup_settings,
             get_media_port,
             check_for_update,
         ])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("error while building tauri application")
+        .run(|_, event| {
+            // Otherwise an upload's ffmpeg processes keep encoding after the window has gone.
+            if let RunEvent::ExitRequested { .. } = event {
+                children::shutdown(CHILD_SHUTDOWN_TIMEOUT);
+            }
+        });
 }
@@ -128,8 +128,8 @@ async fn file_handler(
             Err(()) => return (StatusCode::RANGE_NOT_SATISFIABLE, "Invalid range").into_response(),
         };
         match view.get_file_range(id, range).await {
-            Ok((dr, ft)) => {
-                let ct = file_type_mime(ft);
+            Ok((dr, media)) => {
+                let ct = media.mime_type().unwrap_or("application/octet-stream");
                 Response::builder()
                     .status(206)
                     .header("Content-Type", ct)
@@ -147,8 +147,8 @@ async fn file_handler(
         }
     } else {
         match view.get_file_data(id).await {
-            Ok((data, ft)) => {
-                let ct = file_type_mime(ft);
+            Ok((data, media)) => {
+                let ct = media.mime_type().unwrap_or("application/octet-stream");
                 Response::builder()
                     .status(200)
                     .header("Content-Type", ct)
@@ -162,14 +162,6 @@ async fn file_handler(
     }
 }
 
-fn file_type_mime(ft: shared::encode::FileType) -> &'static str {
-    match ft {
-        shared::encode::FileType::Image => "image/avif",
-        shared::encode::FileType::Video => "video/mp4",
-        shared::encode::FileType::Audio => "audio/ogg",
-    }
-}
-
 fn parse_range(s: &str) -> Result<Range, ()> {
     let value = s.strip_prefix("bytes=").ok_or(())?;
     let mut parts = value.split('-');
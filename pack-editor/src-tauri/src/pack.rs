@@ -7,17 +7,26 @@ use std::{
         Arc, Condvar, Mutex, RwLock as StdRwLock,
     },
     thread::available_parallelism,
+    time::{SystemTime, UNIX_EPOCH},
 };
 
 use anyhow::{anyhow, bail, Result};
 use r2d2::{Pool, PooledConnection};
 use r2d2_sqlite::SqliteConnectionManager;
-use rusqlite::{named_params, params, params_from_iter, OptionalExtension};
+use rusqlite::{
+    named_params, params, params_from_iter, types::Value, Connection, OptionalExtension,
+};
 use serde::{Deserialize, Serialize};
 use shared::{
-    db::migrate,
-    encode::{FileInfo, FileInfoParts, FileType},
-    read_pack::{Header, Metadata, HEADER_SIZE},
+    db::{migrate, read_stats, PackStats},
+    encode::{FileInfo, FileInfoParts},
+    locale::tr,
+    manifest::Manifest,
+    pack_index::{stored_media, StoredMedia},
+    read_pack::{copy_index_async, Header, Metadata, OpenProgress, HEADER_SIZE},
+    sidecar::{OnlyAfter, Sidecar},
+    texts::{count_texts, read_texts, replace_texts, TextEntry, TextKind},
+    write_pack::PackWriter,
 };
 use tokio::{
     fs::{remove_file, File, OpenOptions},
@@ -27,7 +36,11 @@ use tokio::{
 };
 use uuid::Uuid;
 
-use crate::encode::EncodedFile;
+use crate::{
+    encode::{EncodeParams, EncodedFile},
+    history::{History, HistoryState, MediaRow, Operation},
+    sources::Sources,
+};
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct MediaFile {
@@ -37,6 +50,9 @@ pub struct MediaFile {
     pub hash: String,
     pub tags: Vec<String>,
     pub size: u64,
+    /// The settings it was encoded with, if it's an image or video that was added since they've
+    /// been recorded.
+    pub encode: Option<EncodeParams>,
 }
 
 struct Lock {
@@ -45,13 +61,31 @@ struct Lock {
 }
 
 impl Lock {
-    fn new(path: PathBuf) -> Result<Self> {
+    fn new(path: PathBuf) -> io::Result<Self> {
         let file = fs::File::create(&path)?;
         file.try_lock()?;
         Ok(Self { file, path })
     }
 }
 
+/// Whether an error opening a file means we aren't allowed to write to it, e.g. because it's on
+/// a read-only network share.
+fn is_unwritable(err: &io::Error) -> bool {
+    matches!(
+        err.kind(),
+        io::ErrorKind::PermissionDenied | io::ErrorKind::ReadOnlyFilesystem
+    )
+}
+
+/// `<pack>.bak` for the newest backup, then `<pack>.bak.1`, `<pack>.bak.2`, ...
+fn backup_path(pack_path: &Path, n: usize) -> PathBuf {
+    if n == 0 {
+        pack_path.with_added_extension("bak")
+    } else {
+        pack_path.with_added_extension(format!("bak.{n}"))
+    }
+}
+
 impl Drop for Lock {
     fn drop(&mut self) {
         if let Err(err) = self.file.unlock() {
@@ -63,17 +97,25 @@ impl Drop for Lock {
     }
 }
 
+/// A pack being edited. Changes are staged in a folder in the data dir, and only written to the
+/// pack file itself by [`Self::save`].
+///
+/// Packs opened read-only (see [`Self::open_with_progress`]) are never written to, and don't
+/// get a lock file next to them. They can still be edited, and saved with [`Self::save_as`].
 pub struct MediaPack {
     path: PathBuf,
     data_dir: PathBuf,
     saving: Arc<RwLock<()>>,
-    _lock: Lock,
+    _lock: Option<Lock>,
+    read_only: bool,
     header: StdRwLock<Header>,
     dir: PathBuf,
     metadata: StdRwLock<Metadata>,
     db_pool: Pool<SqliteConnectionManager>,
     db_path: PathBuf,
     saved: AtomicBool,
+    history: Mutex<History>,
+    sources: Sources,
 }
 
 pub struct MediaPackView {
@@ -240,30 +282,67 @@ impl MediaPack {
         .await??;
 
         File::create(dir.join("UNSAVED")).await?;
+        let sources = Sources::open(&dir.join("sources.db"))?;
 
         Ok(Self {
             path,
             data_dir: data_dir.to_path_buf(),
             saving: Arc::new(RwLock::new(())),
-            _lock: lock,
+            _lock: Some(lock),
+            read_only: false,
             header: StdRwLock::new(header),
             dir,
             metadata: StdRwLock::new(metadata),
             db_pool,
             saved: AtomicBool::new(false),
             db_path,
+            history: Mutex::new(History::default()),
+            sources,
         })
     }
 
     pub async fn open(path: PathBuf, data_dir: &Path) -> Result<Self> {
-        let mut file = OpenOptions::new()
-            .read(true)
-            .write(true)
-            .open(&path)
-            .await?;
+        Self::open_with_progress(path, data_dir, false, |_| {}).await
+    }
 
-        let lock_path = path.with_added_extension("lock");
-        let lock = Lock::new(lock_path)?;
+    /// Like [`Self::open`], reporting each stage as it goes. Extracting the index is skipped
+    /// when the pack has unsaved changes, since the staged copy is used instead.
+    ///
+    /// With `read_only` set the pack file is only ever read from. Packs that can't be written to
+    /// (or whose folder can't be, since the lock file goes there) are opened read-only anyway.
+    pub async fn open_with_progress(
+        path: PathBuf,
+        data_dir: &Path,
+        read_only: bool,
+        mut on_progress: impl FnMut(OpenProgress) + Send,
+    ) -> Result<Self> {
+        let lock = if read_only {
+            None
+        } else {
+            match Lock::new(path.with_added_extension("lock")) {
+                Ok(lock) => Some(lock),
+                Err(err) if is_unwritable(&err) => {
+                    tracing::info!(
+                        "Can't create a lock file next to the pack ({err}); opening it read-only"
+                    );
+                    None
+                }
+                Err(err) => return Err(err.into()),
+            }
+        };
+
+        let (mut file, lock) = match lock {
+            Some(lock) => match OpenOptions::new().read(true).write(true).open(&path).await {
+                Ok(file) => (file, Some(lock)),
+                Err(err) if is_unwritable(&err) => {
+                    tracing::info!("Can't write to the pack ({err}); opening it read-only");
+                    (File::open(&path).await?, None)
+                }
+                Err(err) => return Err(err.into()),
+            },
+            None => (File::open(&path).await?, None),
+        };
+        let read_only = lock.is_none();
 
         let mut buf = [0u8; HEADER_SIZE];
         file.read_exact(&mut buf).await?;
@@ -289,17 +368,12 @@ impl MediaPack {
             file.seek(SeekFrom::Start(header.metadata_offset)).await?;
             let mut buf = vec![0u8; header.metadata_length as usize];
             file.read_exact(&mut buf).await?;
-            Metadata::from_buf(&buf)?
+            Metadata::from_buf(&header.decode(buf)?)?
         };
 
         if !has_unsaved {
-            file.seek(SeekFrom::Start(header.index_offset)).await?;
-            let mut db_data = vec![0u8; header.index_length as usize];
-            file.read_exact(&mut db_data).await?;
-
-            let mut db_file = File::create(&db_path).await?;
-            db_file.write_all(&db_data).await?;
-            db_file.flush().await?;
+            let db_file = File::create(&db_path).await?;
+            copy_index_async(&mut file, &header, db_file, &mut on_progress).await?;
         }
 
         let manager = SqliteConnectionManager::file(&db_path);
@@ -310,6 +384,7 @@ impl MediaPack {
         let pool_size = available_parallelism().map(|n| n.get() as u32).unwrap_or(4);
         let db_pool = Pool::builder().max_size(pool_size).build(manager)?;
 
+        on_progress(OpenProgress::Migrating);
         let pool = db_pool.clone();
         spawn_blocking(move || -> Result<_> {
             let conn = pool.get()?;
@@ -317,17 +392,23 @@ impl MediaPack {
         })
         .await??;
 
+        let sources = Sources::open(&dir.join("sources.db"))?;
+
+        on_progress(OpenProgress::Done);
         Ok(Self {
             path,
             data_dir: data_dir.to_path_buf(),
             saving: Arc::new(RwLock::new(())),
             _lock: lock,
+            read_only,
             header: StdRwLock::new(header),
             dir,
             metadata: StdRwLock::new(metadata),
             db_pool,
             saved: AtomicBool::new(!has_unsaved),
             db_path,
+            history: Mutex::new(History::default()),
+            sources,
         })
     }
 
@@ -354,6 +435,23 @@ impl MediaPack {
         &self.dir
     }
 
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn id(&self) -> Uuid {
+        self.header.read().unwrap().id
+    }
+
+    /// The files on disk the pack's media was added from.
+    pub fn sources(&self) -> &Sources {
+        &self.sources
+    }
+
+    pub fn read_only(&self) -> bool {
+        self.read_only
+    }
+
     pub async fn is_saved(&self) -> bool {
         let _handle = self.saving.write().await;
         self.saved.load(Ordering::Relaxed)
@@ -396,18 +494,21 @@ impl MediaPack {
         OpenOptions::new().read(true).open(&self.path).await
     }
 
-    async fn open_write(&self) -> io::Result<File> {
-        OpenOptions::new().write(true).open(&self.path).await
-    }
-
+    /// Saves the pack in place, first copying it to `<pack>.bak` if `backups` is above 0. Up to
+    /// `backups` copies are kept, the older ones as `<pack>.bak.1`, `<pack>.bak.2`, and so on.
     pub async fn save(
         &self,
+        backups: usize,
         on_progress: impl Fn(usize, usize) + Send + Sync + 'static,
     ) -> Result<()> {
         if self.saved.load(Ordering::Relaxed) {
             return Ok(());
         }
+        if self.read_only {
+            bail!(tr("pack-editor-read-only"));
+        }
         let _handle = self.saving.write().await;
+        self.back_up(backups).await?;
         let on_progress = Arc::new(on_progress);
 
         tracing::warn!("Writing files");
@@ -419,38 +520,64 @@ impl MediaPack {
         self.db_execute(|conn| conn.execute("VACUUM", []).map_err(|err| err.into()))
             .await?;
 
-        let mut file = self.open_write().await?;
-        file.seek(SeekFrom::Start(offset)).await?;
-
-        let index_length = {
-            let mut dbf = File::open(&self.db_path).await?;
-            tokio::io::copy(&mut dbf, &mut file).await?
-        };
-
-        let buf = self.metadata.read().unwrap().to_buf()?;
-        let metadata_length = buf.len() as u64;
-        file.write_all(&buf).await?;
-        file.set_len(offset + metadata_length + index_length)
+        // Packs opened in an older version of the format are saved in the current one.
+        let id = self.header.read().unwrap().id;
+        let header = self
+            .finish_pack(self.path.clone(), Some(id), offset)
             .await?;
-
-        let header = Header {
-            id: self.header.read().unwrap().id,
-            index_offset: offset,
-            index_length,
-            metadata_offset: offset + index_length,
-            metadata_length,
-        };
-
-        file.seek(SeekFrom::Start(0)).await?;
-        file.write_all(&header.to_buf()?).await?;
         *self.header.write().unwrap() = header;
-        file.sync_data().await?;
 
         self.clean_media()?;
+        self.history.lock().unwrap().clear();
         self.mark_saved().await?;
         Ok(())
     }
 
+    /// Writes the index and metadata to the pack at `path` after its media, which ends at
+    /// `offset`, then the header. `id` is kept if the pack already has one.
+    async fn finish_pack(&self, path: PathBuf, id: Option<Uuid>, offset: u64) -> Result<Header> {
+        let db_path = self.db_path.clone();
+        let metadata = self.metadata.read().unwrap().clone();
+
+        spawn_blocking(move || -> Result<Header> {
+            let file = std::fs::OpenOptions::new().write(true).open(path)?;
+            let writer = match id {
+                Some(id) => PackWriter::with_id(file, id),
+                None => PackWriter::new(file),
+            };
+            let (file, header) = writer
+                .after_media(offset)
+                .finish(std::fs::File::open(db_path)?, &metadata)?;
+            file.set_len(header.end())?;
+            file.sync_data()?;
+            Ok(header)
+        })
+        .await?
+    }
+
+    /// Copies the pack file before it's saved over. A copy rather than a hard link, since the
+    /// save rewrites the file in place. The save is abandoned if this fails.
+    async fn back_up(&self, backups: usize) -> Result<()> {
+        // A pack that's never been saved has nothing in it worth keeping.
+        if backups == 0 || self.header.read().unwrap().is_default() {
+            return Ok(());
+        }
+
+        let temp_path = self.path.with_added_extension("bak.tmp");
+        tokio::fs::copy(&self.path, &temp_path).await?;
+        File::open(&temp_path).await?.sync_all().await?;
+
+        for n in (1..backups).rev() {
+            let from = backup_path(&self.path, n - 1);
+            if tokio::fs::try_exists(&from).await? {
+                tokio::fs::rename(from, backup_path(&self.path, n)).await?;
+            }
+        }
+        tokio::fs::rename(temp_path, backup_path(&self.path, 0)).await?;
+
+        Ok(())
+    }
+
     async fn write_files(
         &self,
         to_path: Option<PathBuf>,
@@ -701,13 +828,16 @@ impl MediaPack {
         .await
     }
 
+    /// Saving to the pack's own path is a plain [`save`](Self::save), which is what `backups` is
+    /// for.
     pub async fn save_as(
         &self,
         path: &Path,
+        backups: usize,
         on_progress: impl Fn(usize, usize) + Send + Sync + 'static,
     ) -> Result<Option<Self>> {
         if path == &self.path {
-            self.save(on_progress).await?;
+            self.save(backups, on_progress).await?;
             return Ok(None);
         }
 
@@ -734,29 +864,7 @@ impl MediaPack {
             self.db_execute(|conn| conn.execute("VACUUM", []).map_err(|err| err.into()))
                 .await?;
 
-            file.seek(SeekFrom::Start(offset)).await?;
-            let index_length = {
-                let mut dbf = File::open(&self.db_path).await?;
-                tokio::io::copy(&mut dbf, &mut file).await?
-            };
-
-            let buf = self.metadata.read().unwrap().to_buf()?;
-            let metadata_length = buf.len() as u64;
-            file.write_all(&buf).await?;
-            file.set_len(offset + metadata_length + index_length)
-                .await?;
-
-            let header = Header {
-                id: Uuid::new_v4(),
-                index_offset: offset,
-                index_length,
-                metadata_offset: offset + index_length,
-                metadata_length,
-            };
-
-            file.seek(SeekFrom::Start(0)).await?;
-            file.write_all(&header.to_buf()?).await?;
-            file.sync_data().await?;
+            self.finish_pack(path.to_path_buf(), None, offset).await?;
             self.mark_saved().await?;
         }
 
@@ -771,36 +879,29 @@ impl MediaPack {
         let _handle = self.saving.write().await;
         let mut file = self.open_read().await?;
 
-        // Extract all header fields before any .await so Ref<Header> doesn't cross await points
-        let (not_saved_yet, metadata_offset, metadata_length, index_offset, index_length) = {
-            let h = self.header.read().unwrap();
-            (
-                h.is_default(),
-                h.metadata_offset,
-                h.metadata_length,
-                h.index_offset,
-                h.index_length,
-            )
-        };
+        // Clone the header before any .await so Ref<Header> doesn't cross await points
+        let header = self.header.read().unwrap().clone();
 
-        let metadata = if not_saved_yet {
+        let metadata = if header.is_default() {
             None
         } else {
-            file.seek(SeekFrom::Start(metadata_offset)).await?;
-            let mut buf = vec![0u8; metadata_length as usize];
+            file.seek(SeekFrom::Start(header.metadata_offset)).await?;
+            let mut buf = vec![0u8; header.metadata_length as usize];
             file.read_exact(&mut buf).await?;
-            Some(Metadata::from_buf(&buf)?)
+            Some(Metadata::from_buf(&header.decode(buf)?)?)
         };
 
-        file.seek(SeekFrom::Start(index_offset)).await?;
-        let mut db_data = vec![0u8; index_length as usize];
+        file.seek(SeekFrom::Start(header.index_offset)).await?;
+        let mut db_data = vec![0u8; header.index_length as usize];
         file.read_exact(&mut db_data).await?;
+        let db_data = header.decode(db_data)?;
 
         let mut db_file = File::create(&self.db_path).await?;
         db_file.write_all(&db_data).await?;
         db_file.flush().await?;
 
         self.clean_media()?;
+        self.history.lock().unwrap().clear();
 
         let final_meta = if let Some(m) = metadata {
             *self.metadata.write().unwrap() = m.clone();
@@ -819,7 +920,13 @@ impl MediaPack {
 
     pub async fn set_metadata(&self, metadata: &Metadata) -> Result<()> {
         let _handle = self.saving.read().await;
-        *self.metadata.write().unwrap() = metadata.clone();
+        let old = std::mem::replace(&mut *self.metadata.write().unwrap(), metadata.clone());
+        if &old != metadata {
+            self.record(Operation::SetMetadata {
+                old,
+                new: metadata.clone(),
+            });
+        }
         self.mark_unsaved().await
     }
 
@@ -849,11 +956,14 @@ impl MediaPack {
     /// is safe even when two uploads with identical content race each other (the
     /// pre-check alone can't catch that: both can see "not present yet" before
     /// either has inserted its row - the constraint is what actually closes it).
+    ///
+    /// The settings and tags from the file's `sidecar` are stored along with it.
     pub async fn add_file(
         &self,
         encoded_file: EncodedFile,
         path: &Path,
         hash: blake3::Hash,
+        sidecar: Sidecar,
     ) -> Result<Option<MediaFile>> {
         let _handle = self.saving.read().await;
 
@@ -869,15 +979,28 @@ impl MediaPack {
         let file_name = file_name(path);
         let file_path = encoded_file.path.to_string_lossy().to_string();
         let file_info = encoded_file.info.clone();
+        let encode = encoded_file.params.clone();
         let file_name_clone = file_name.clone();
         let hash_bytes = *hash.as_bytes();
         let size = tokio::fs::metadata(&encoded_file.path).await?.len();
 
+        let checksum = file_checksum(encoded_file.path.clone()).await?;
+        let tags = sidecar.tags.clone();
+        let (after_file, after_tag, after_count) = match &sidecar.only_after {
+            Some(OnlyAfter { file, tag, times }) => (file.clone(), tag.clone(), Some(*times)),
+            None => (None, None, None),
+        };
+
+        let added_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |since| since.as_secs() as i64);
+
         let insert_result = self
-            .db_execute(move |conn| {
-                conn.query_row(
-                    "INSERT INTO media (file_name, file_type, path, length, width, height, transparent, duration, audio, hash, thumbnail)
-                    VALUES (:file_name, :file_type, :path, :length, :width, :height, :transparent, :duration, :audio, :hash, :thumbnail) RETURNING id",
+            .db_execute(move |mut conn| {
+                let tx = conn.transaction()?;
+                let id = tx.query_row(
+                    "INSERT INTO media (file_name, file_type, path, length, width, height, transparent, duration, audio, hash, checksum, codec, container, encoder, quality, max_size, thumbnail, caption, category, weight, cooldown, after_file, after_tag, after_count, popup_set, rating, added_at)
+                    VALUES (:file_name, :file_type, :path, :length, :width, :height, :transparent, :duration, :audio, :hash, :checksum, :codec, :container, :encoder, :quality, :max_size, :thumbnail, :caption, :category, :weight, :cooldown, :after_file, :after_tag, :after_count, :popup_set, :rating, :added_at) RETURNING id",
                     named_params! {
                         ":file_name": file_name_clone,
                         ":file_type": file_type.as_str(),
@@ -889,11 +1012,31 @@ impl MediaPack {
                         ":duration": duration,
                         ":audio": audio,
                         ":hash": hash_bytes,
+                        ":checksum": checksum,
+                        ":codec": encoded_file.codec.map(|codec| codec.as_str()),
+                        ":container": encoded_file.container.map(|container| container.as_str()),
+                        ":encoder": encoded_file.params.as_ref().map(|params| &params.encoder),
+                        ":quality": encoded_file.params.as_ref().map(|params| params.quality),
+                        ":max_size": encoded_file.params.as_ref().map(|params| params.max_size),
                         ":thumbnail": encoded_file.thumbnail,
+                        ":caption": sidecar.caption,
+                        ":category": sidecar.category,
+                        ":weight": sidecar.weight,
+                        ":cooldown": sidecar.cooldown,
+                        ":after_file": after_file,
+                        ":after_tag": after_tag,
+                        ":after_count": after_count,
+                        ":popup_set": sidecar.set,
+                        ":rating": sidecar.rating,
+                        ":added_at": added_at,
                     },
                     |row| row.get::<_, u64>("id"),
-                )
-                .map_err(|err| err.into())
+                )?;
+                for tag in &sidecar.tags {
+                    add_tag_by_name(&tx, id, tag)?;
+                }
+                tx.commit()?;
+                Ok(id)
             })
             .await;
 
@@ -903,6 +1046,11 @@ impl MediaPack {
             Err(err) => return Err(err),
         };
 
+        let rows = self
+            .db_execute(move |conn| read_media_rows(&conn, &[id]))
+            .await?;
+        self.record(Operation::AddFiles { rows });
+
         self.mark_unsaved().await?;
 
         Ok(Some(MediaFile {
@@ -910,21 +1058,26 @@ impl MediaPack {
             file_name,
             file_info,
             hash: hash.to_string(),
-            tags: vec![],
+            tags,
             size,
+            encode,
         }))
     }
 
     pub async fn remove_files(&self, ids: Vec<u64>) -> Result<()> {
         let _handle = self.saving.read().await;
-        self.db_execute(move |conn| {
-            conn.execute(
-                &format!("DELETE FROM media WHERE id IN ({})", repeat_vars(ids.len())),
-                params_from_iter(&ids),
-            )?;
-            Ok(())
-        })
-        .await?;
+        let rows = self
+            .db_execute(move |mut conn| {
+                let tx = conn.transaction()?;
+                let rows = read_media_rows(&tx, &ids)?;
+                delete_media(&tx, &ids)?;
+                tx.commit()?;
+                Ok(rows)
+            })
+            .await?;
+        if !rows.is_empty() {
+            self.record(Operation::RemoveFiles { rows });
+        }
         self.mark_unsaved().await
     }
 
@@ -932,26 +1085,11 @@ impl MediaPack {
         let _handle = self.saving.read().await;
         self.db_execute(move |conn| {
             let mut stmt = conn.prepare(
-                "SELECT id, file_type, file_name, width, height, transparent, duration, audio, hash, length FROM media",
+                "SELECT id, file_type, file_name, width, height, transparent, duration, audio, hash, length, encoder, quality, max_size
+                FROM media",
             )?;
             let mut files: Vec<MediaFile> = {
-                let rows = stmt.query_and_then([], |row| -> Result<_> {
-                    Ok(MediaFile {
-                        id: row.get("id")?,
-                        file_name: row.get("file_name")?,
-                        file_info: FileInfo::try_from_parts(&FileInfoParts {
-                            file_type: row.get::<_, String>("file_type")?.parse()?,
-                            width: row.get("width")?,
-                            height: row.get("height")?,
-                            transparent: row.get("transparent")?,
-                            duration: row.get("duration")?,
-                            audio: row.get("audio")?,
-                        })?,
-                        hash: blake3::Hash::from_bytes(row.get("hash")?).to_string(),
-                        tags: vec![],
-                        size: row.get::<_, Option<u64>>("length")?.unwrap_or(0),
-                    })
-                })?;
+                let rows = stmt.query_and_then([], media_file_from_row)?;
                 rows.collect::<Result<Vec<_>>>()?
             };
 
@@ -977,6 +1115,57 @@ impl MediaPack {
         .await
     }
 
+    pub async fn manifest(&self) -> Result<Manifest> {
+        let _handle = self.saving.read().await;
+        let metadata = self.metadata();
+        self.db_execute(move |conn| Ok(Manifest::read(&conn, metadata.clone())?))
+            .await
+    }
+
+    pub async fn stats(&self) -> Result<PackStats> {
+        let _handle = self.saving.read().await;
+        self.db_execute(move |conn| read_stats(&conn)).await
+    }
+
+    /// How many texts of each kind the pack has.
+    pub async fn text_counts(&self) -> Result<Vec<(TextKind, u64)>> {
+        let _handle = self.saving.read().await;
+        self.db_execute(move |conn| {
+            TextKind::ALL
+                .into_iter()
+                .map(|kind| Ok((kind, count_texts(&conn, kind)?)))
+                .collect()
+        })
+        .await
+    }
+
+    /// Replaces the pack's texts of one kind in one language with `entries`, as a single undo
+    /// step.
+    pub async fn set_texts(
+        &self,
+        kind: TextKind,
+        locale: Option<String>,
+        entries: Vec<TextEntry>,
+    ) -> Result<()> {
+        let _handle = self.saving.read().await;
+        let new = entries.clone();
+        let db_locale = locale.clone();
+        let old = self
+            .db_execute(move |mut conn| {
+                let old = read_texts(&conn, kind, db_locale.as_deref())?;
+                replace_texts(&mut conn, kind, db_locale.as_deref(), &entries)?;
+                Ok(old)
+            })
+            .await?;
+        self.record(Operation::SetTexts {
+            kind,
+            locale,
+            old,
+            new,
+        });
+        self.mark_unsaved().await
+    }
+
     pub async fn get_all_tags(&self) -> Result<Vec<String>> {
         let _handle = self.saving.read().await;
         self.db_execute(move |conn| {
@@ -1001,6 +1190,7 @@ impl MediaPack {
 
     pub async fn add_tag(&self, id: u64, tag: String) -> Result<()> {
         let _handle = self.saving.read().await;
+        let tag_clone = tag.clone();
         self.db_execute(move |conn| {
             let tag_id: u64 =
                 conn.query_row("SELECT id FROM tags WHERE name = ?", params![tag], |row| {
@@ -1013,11 +1203,18 @@ impl MediaPack {
             Ok(())
         })
         .await?;
+        self.record(Operation::Tag {
+            ids: vec![id],
+            tag: tag_clone,
+            added: true,
+            created: false,
+        });
         self.mark_unsaved().await
     }
 
     pub async fn create_and_add_tag(&self, id: u64, tag: String) -> Result<()> {
         let _handle = self.saving.read().await;
+        let tag_clone = tag.clone();
         self.db_execute(move |mut conn| {
             let tx = conn.transaction()?;
             let tag_id: u64 = tx.query_row(
@@ -1033,22 +1230,219 @@ impl MediaPack {
             Ok(())
         })
         .await?;
+        self.record(Operation::Tag {
+            ids: vec![id],
+            tag: tag_clone,
+            added: true,
+            created: true,
+        });
         self.mark_unsaved().await
     }
 
-    pub async fn remove_tag(&self, id: u64, tag: String) -> Result<()> {
+    /// Adds `tag` to every file in `ids`, creating it if needed, as a single undo step.
+    pub async fn add_tag_to_files(&self, ids: Vec<u64>, tag: String) -> Result<()> {
+        let _handle = self.saving.read().await;
+        let tag_clone = tag.clone();
+        let (changed, created) = self
+            .db_execute(move |mut conn| {
+                let tx = conn.transaction()?;
+                let created =
+                    tx.execute("INSERT OR IGNORE INTO tags (name) VALUES (?)", params![tag])? > 0;
+                let mut changed = Vec::new();
+                {
+                    let mut stmt = tx.prepare(
+                        "INSERT OR IGNORE INTO media_tags (media_id, tag_id) SELECT ?, id FROM tags WHERE name = ?",
+                    )?;
+                    for id in ids {
+                        if stmt.execute(params![id, tag])? > 0 {
+                            changed.push(id);
+                        }
+                    }
+                }
+                tx.commit()?;
+                Ok((changed, created))
+            })
+            .await?;
+        if !changed.is_empty() {
+            self.record(Operation::Tag {
+                ids: changed,
+                tag: tag_clone,
+                added: true,
+                created,
+            });
+        }
+        self.mark_unsaved().await
+    }
+
+    /// Removes `tag` from every file in `ids` as a single undo step.
+    pub async fn remove_tag_from_files(&self, ids: Vec<u64>, tag: String) -> Result<()> {
+        let _handle = self.saving.read().await;
+        let tag_clone = tag.clone();
+        let changed = self
+            .db_execute(move |mut conn| {
+                let tx = conn.transaction()?;
+                let mut changed = Vec::new();
+                for id in ids {
+                    if remove_tag_by_name(&tx, id, &tag, false)? {
+                        changed.push(id);
+                    }
+                }
+                tx.commit()?;
+                Ok(changed)
+            })
+            .await?;
+        if !changed.is_empty() {
+            self.record(Operation::Tag {
+                ids: changed,
+                tag: tag_clone,
+                added: false,
+                created: false,
+            });
+        }
+        self.mark_unsaved().await
+    }
+
+    /// Swaps in re-encoded data for existing files, keeping their ids, names and tags. The old
+    /// data is left where it is until the next save, so this can be undone.
+    pub async fn replace_files(&self, files: Vec<(u64, EncodedFile)>) -> Result<()> {
+        let files = files
+            .into_iter()
+            .map(|(id, encoded_file)| (id, encoded_file, None))
+            .collect();
+        self.replace(files).await
+    }
+
+    /// Replaces a file with a new version of the file it was added from, keeping its tags and
+    /// settings. Returns the file as it is now.
+    pub async fn replace_source(
+        &self,
+        id: u64,
+        encoded_file: EncodedFile,
+        hash: blake3::Hash,
+    ) -> Result<Option<MediaFile>> {
+        self.replace(vec![(id, encoded_file, Some(hash))]).await?;
+
         let _handle = self.saving.read().await;
         self.db_execute(move |conn| {
-            conn.execute(
-                "DELETE FROM media_tags WHERE media_id = ? AND tag_id IN (SELECT id FROM tags WHERE name = ?)",
-                params![id, tag],
+            let mut stmt = conn.prepare(
+                "SELECT id, file_type, file_name, width, height, transparent, duration, audio, hash, length,
+                encoder, quality, max_size FROM media WHERE id = ?",
             )?;
-            Ok(())
+            let Some(mut file) = stmt
+                .query_and_then(params![id], media_file_from_row)?
+                .next()
+                .transpose()?
+            else {
+                return Ok(None);
+            };
+
+            let mut stmt = conn.prepare(
+                "SELECT tags.name FROM media_tags JOIN tags ON media_tags.tag_id = tags.id WHERE media_tags.media_id = ?",
+            )?;
+            file.tags = stmt
+                .query_map(params![id], |row| row.get("name"))?
+                .collect::<rusqlite::Result<_>>()?;
+            Ok(Some(file))
         })
-        .await?;
+        .await
+    }
+
+    /// Swaps in newly encoded data for files, and a new source hash if one is given.
+    async fn replace(&self, files: Vec<(u64, EncodedFile, Option<blake3::Hash>)>) -> Result<()> {
+        let _handle = self.saving.read().await;
+        if files.is_empty() {
+            return Ok(());
+        }
+
+        let mut updates = Vec::with_capacity(files.len());
+        for (id, encoded_file, hash) in files {
+            let size = tokio::fs::metadata(&encoded_file.path).await?.len();
+            let checksum = file_checksum(encoded_file.path.clone()).await?;
+            let hash = hash.map(|hash| *hash.as_bytes());
+            updates.push((id, encoded_file, size, checksum, hash));
+        }
+        let ids: Vec<u64> = updates.iter().map(|(id, ..)| *id).collect();
+
+        let (old, new) = self
+            .db_execute(move |mut conn| {
+                let tx = conn.transaction()?;
+                let old = read_media_rows(&tx, &ids)?;
+                for (id, encoded_file, size, checksum, hash) in &updates {
+                    let FileInfoParts {
+                        width,
+                        height,
+                        transparent,
+                        duration,
+                        audio,
+                        ..
+                    } = encoded_file.info.to_parts();
+                    tx.execute(
+                        "UPDATE media SET \"offset\" = NULL, path = :path, length = :length, width = :width,
+                        height = :height, transparent = :transparent, duration = :duration, audio = :audio,
+                        checksum = :checksum, codec = :codec, container = :container, encoder = :encoder,
+                        quality = :quality, max_size = :max_size, thumbnail = :thumbnail, hash = COALESCE(:hash, hash) WHERE id = :id",
+                        named_params! {
+                            ":id": id,
+                            ":hash": hash,
+                            ":path": encoded_file.path.to_string_lossy(),
+                            ":length": size,
+                            ":width": width,
+                            ":height": height,
+                            ":transparent": transparent,
+                            ":duration": duration,
+                            ":audio": audio,
+                            ":checksum": checksum,
+                            ":codec": encoded_file.codec.map(|codec| codec.as_str()),
+                            ":container": encoded_file.container.map(|container| container.as_str()),
+                            ":encoder": encoded_file.params.as_ref().map(|params| &params.encoder),
+                            ":quality": encoded_file.params.as_ref().map(|params| params.quality),
+                            ":max_size": encoded_file.params.as_ref().map(|params| params.max_size),
+                            ":thumbnail": encoded_file.thumbnail,
+                        },
+                    )?;
+                }
+                let new = read_media_rows(&tx, &ids)?;
+                tx.commit()?;
+                Ok((old, new))
+            })
+            .await?;
+
+        self.record(Operation::ReplaceFiles { old, new });
+        self.mark_unsaved().await
+    }
+
+    pub async fn remove_tag(&self, id: u64, tag: String) -> Result<()> {
+        let _handle = self.saving.read().await;
+        let tag_clone = tag.clone();
+        let removed = self
+            .db_execute(move |conn| remove_tag_by_name(&conn, id, &tag, false))
+            .await?;
+        if removed {
+            self.record(Operation::Tag {
+                ids: vec![id],
+                tag: tag_clone,
+                added: false,
+                created: false,
+            });
+        }
         self.mark_unsaved().await
     }
 
+    /// The id of the file added from a source with this hash, if there is one.
+    pub async fn file_with_hash(&self, hash: &blake3::Hash) -> Result<Option<u64>> {
+        let hash_bytes = *hash.as_bytes();
+        self.db_execute(move |conn| {
+            Ok(conn
+                .query_row(
+                    "SELECT id FROM media WHERE hash = ?",
+                    params![hash_bytes],
+                    |row| row.get("id"),
+                )
+                .optional()?)
+        })
+        .await
+    }
+
     pub async fn check_hash(&self, hash: &blake3::Hash) -> Result<bool> {
         let hash_bytes = *hash.as_bytes();
         self.db_execute(move |conn| {
@@ -1066,15 +1460,146 @@ impl MediaPack {
 
     pub async fn set_title(&self, id: u64, name: String) -> Result<()> {
         let _handle = self.saving.read().await;
-        self.db_execute(move |conn| {
-            conn.execute(
-                "UPDATE media SET file_name = ? WHERE id = ?",
-                params![name, id],
-            )?;
+        let new = name.clone();
+        let old = self
+            .db_execute(move |conn| {
+                let old: String = conn.query_row(
+                    "SELECT file_name FROM media WHERE id = ?",
+                    params![id],
+                    |row| row.get("file_name"),
+                )?;
+                conn.execute(
+                    "UPDATE media SET file_name = ? WHERE id = ?",
+                    params![name, id],
+                )?;
+                Ok(old)
+            })
+            .await?;
+        if old != new {
+            self.record(Operation::SetTitle { id, old, new });
+        }
+        self.mark_unsaved().await
+    }
+
+    fn record(&self, op: Operation) {
+        self.history.lock().unwrap().record(op);
+    }
+
+    /// Marks the end of an upload, so that the next one gets its own undo step.
+    pub fn seal_history(&self) {
+        self.history.lock().unwrap().seal();
+    }
+
+    pub fn history(&self) -> HistoryState {
+        self.history.lock().unwrap().state()
+    }
+
+    /// Reverts the most recent edit. Does nothing if there is nothing to undo.
+    pub async fn undo(&self) -> Result<HistoryState> {
+        let _handle = self.saving.read().await;
+        let op = self.history.lock().unwrap().pop_undo();
+        let Some(op) = op else {
+            return Ok(self.history());
+        };
+
+        match self.apply(op.clone(), false).await {
+            Ok(()) => self.history.lock().unwrap().push_redo(op),
+            Err(err) => {
+                self.history.lock().unwrap().push_undo(op);
+                return Err(err);
+            }
+        }
+
+        self.mark_unsaved().await?;
+        Ok(self.history())
+    }
+
+    /// Re-applies the most recently undone edit. Does nothing if there is nothing to redo.
+    pub async fn redo(&self) -> Result<HistoryState> {
+        let _handle = self.saving.read().await;
+        let op = self.history.lock().unwrap().pop_redo();
+        let Some(op) = op else {
+            return Ok(self.history());
+        };
+
+        match self.apply(op.clone(), true).await {
+            Ok(()) => self.history.lock().unwrap().push_undo(op),
+            Err(err) => {
+                self.history.lock().unwrap().push_redo(op);
+                return Err(err);
+            }
+        }
+
+        self.mark_unsaved().await?;
+        Ok(self.history())
+    }
+
+    /// Applies `op` (`forward = true`) or its inverse.
+    async fn apply(&self, op: Operation, forward: bool) -> Result<()> {
+        if let Operation::SetMetadata { old, new } = op {
+            *self.metadata.write().unwrap() = if forward { new } else { old };
+            return self.save_metadata().await;
+        }
+
+        if let Operation::SetTexts {
+            kind,
+            locale,
+            old,
+            new,
+        } = op
+        {
+            return self
+                .db_execute(move |mut conn| {
+                    replace_texts(
+                        &mut conn,
+                        kind,
+                        locale.as_deref(),
+                        if forward { &new } else { &old },
+                    )
+                })
+                .await;
+        }
+
+        self.db_execute(move |mut conn| {
+            let tx = conn.transaction()?;
+            match &op {
+                Operation::AddFiles { rows } | Operation::RemoveFiles { rows } => {
+                    if matches!(op, Operation::AddFiles { .. }) == forward {
+                        insert_media_rows(&tx, rows)?;
+                    } else {
+                        let ids: Vec<u64> = rows.iter().map(|row| row.id).collect();
+                        delete_media(&tx, &ids)?;
+                    }
+                }
+                Operation::Tag {
+                    ids,
+                    tag,
+                    added,
+                    created,
+                } => {
+                    for &id in ids {
+                        if *added == forward {
+                            add_tag_by_name(&tx, id, tag)?;
+                        } else {
+                            remove_tag_by_name(&tx, id, tag, *created)?;
+                        }
+                    }
+                }
+                Operation::ReplaceFiles { old, new } => {
+                    update_media_rows(&tx, if forward { new } else { old })?;
+                }
+                Operation::SetTitle { id, old, new } => {
+                    tx.execute(
+                        "UPDATE media SET file_name = ? WHERE id = ?",
+                        params![if forward { new } else { old }, id],
+                    )?;
+                }
+                Operation::SetMetadata { .. } | Operation::SetTexts { .. } => unreachable!(),
+            }
+            tx.commit()?;
             Ok(())
         })
-        .await?;
-        self.mark_unsaved().await
+        .await
     }
 }
 
@@ -1106,40 +1631,30 @@ impl MediaPackView {
         OpenOptions::new().read(true).open(&self.path).await
     }
 
-    async fn get_raw_file(&self, id: u64) -> Result<(FileData, FileType, bool)> {
-        let (offset, length, path, file_type, transparent) = self
-            .db_execute(move |conn| {
-                conn.query_row_and_then(
-                    "SELECT offset, length, path, file_type, transparent FROM media WHERE id = ?",
-                    params![id],
-                    |row| -> Result<_> {
-                        Ok((
-                            row.get::<_, Option<u64>>("offset")?,
-                            row.get::<_, Option<usize>>("length")?,
-                            row.get::<_, Option<String>>("path")?,
-                            row.get::<_, String>("file_type")?.parse()?,
-                            row.get::<_, Option<bool>>("transparent")?.unwrap_or(false),
-                        ))
-                    },
-                )
-                .map_err(Into::into)
-            })
-            .await?;
+    /// Where the file with this id is, in the pack or staged next to it, and its format.
+    async fn get_stored_media(&self, id: u64) -> Result<StoredMedia> {
+        self.db_execute(move |conn| {
+            stored_media(&conn, id)?.ok_or_else(|| anyhow!("No file with id {id}"))
+        })
+        .await
+    }
 
-        let mut file = self.open_read().await?;
+    async fn get_raw_file(&self, id: u64) -> Result<(FileData, StoredMedia)> {
+        let media = self.get_stored_media(id).await?;
 
-        let file_data = match (offset, length, path) {
-            (Some(offset), Some(length), _) => {
+        let file_data = match (media.range(), &media.path) {
+            (Some((offset, length)), _) => {
+                let mut file = self.open_read().await?;
                 file.seek(SeekFrom::Start(offset)).await?;
-                let mut buf = vec![0u8; length];
+                let mut buf = vec![0u8; length as usize];
                 file.read_exact(&mut buf).await?;
                 FileData::Data(buf)
             }
-            (_, _, Some(path)) => FileData::Path(self.dir.join("media").join(path)),
+            (_, Some(path)) => FileData::Path(self.dir.join("media").join(path)),
             _ => bail!("No offset, length or path"),
         };
 
-        Ok((file_data, file_type, transparent))
+        Ok((file_data, media))
     }
 
     pub async fn get_thumbnail(&self, id: u64) -> Result<Vec<u8>> {
@@ -1154,52 +1669,43 @@ impl MediaPackView {
 
     pub async fn get_preview(&self, id: u64) -> Result<Vec<u8>> {
         let _handle = self.saving.read().await;
-        let (file_data, file_type, transparent) = self.get_raw_file(id).await?;
-        crate::thumbnail::generate_preview(file_data, file_type == FileType::Image, transparent)
-            .await
+        let (file_data, media) = self.get_raw_file(id).await?;
+        let transparent = self
+            .db_execute(move |conn| {
+                conn.query_row("SELECT transparent FROM media WHERE id = ?", [id], |row| {
+                    row.get::<_, Option<bool>>("transparent")
+                })
+                .map_err(Into::into)
+            })
+            .await?
+            .unwrap_or(false);
+        crate::thumbnail::generate_preview(file_data, &media, transparent).await
     }
 
     pub async fn get_display(&self, id: u64) -> Result<Vec<u8>> {
         let _handle = self.saving.read().await;
-        let (file_data, _, _) = self.get_raw_file(id).await?;
-        crate::thumbnail::generate_display_image(file_data).await
+        let (file_data, media) = self.get_raw_file(id).await?;
+        crate::thumbnail::generate_display_image(file_data, &media).await
     }
 
-    pub async fn get_file_data(&self, id: u64) -> Result<(Vec<u8>, FileType)> {
+    pub async fn get_file_data(&self, id: u64) -> Result<(Vec<u8>, StoredMedia)> {
         let _handle = self.saving.read().await;
-        let (file_data, file_type, _) = self.get_raw_file(id).await?;
+        let (file_data, media) = self.get_raw_file(id).await?;
         let data = match file_data {
             FileData::Path(path) => tokio::fs::read(path).await?,
             FileData::Data(data) => data,
         };
-        Ok((data, file_type))
+        Ok((data, media))
     }
 
-    pub async fn get_file_range(&self, id: u64, range: Range) -> Result<(DataRange, FileType)> {
+    pub async fn get_file_range(&self, id: u64, range: Range) -> Result<(DataRange, StoredMedia)> {
         let _handle = self.saving.read().await;
 
-        let (offset, length, path, file_type) = self
-            .db_execute(move |conn| {
-                conn.query_row_and_then(
-                    "SELECT offset, length, path, file_type FROM media WHERE id = ?",
-                    params![id],
-                    |row| -> Result<_> {
-                        Ok((
-                            row.get::<_, Option<u64>>("offset")?,
-                            row.get::<_, Option<u64>>("length")?,
-                            row.get::<_, Option<String>>("path")?,
-                            row.get::<_, String>("file_type")?.parse()?,
-                        ))
-                    },
-                )
-                .map_err(Into::into)
-            })
-            .await?;
-
-        let mut file = self.open_read().await?;
+        let media = self.get_stored_media(id).await?;
 
-        let data_range = match (offset, length, path) {
-            (Some(offset), Some(length), _) => {
+        let data_range = match (media.range(), &media.path) {
+            (Some((offset, length)), _) => {
+                let mut file = self.open_read().await?;
                 let (start, end) = resolve_range(range, length)?;
                 file.seek(SeekFrom::Start(offset + start)).await?;
                 let mut buf = vec![0u8; (end - start) as usize];
@@ -1211,7 +1717,7 @@ impl MediaPackView {
                     total_size: length,
                 }
             }
-            (_, _, Some(path)) => {
+            (_, Some(path)) => {
                 let path = self.dir.join("media").join(path);
                 let mut file = tokio::fs::File::open(&path).await?;
                 let size = file.metadata().await?.len();
@@ -1229,7 +1735,7 @@ impl MediaPackView {
             _ => bail!("No offset, length or path"),
         };
 
-        Ok((data_range, file_type))
+        Ok((data_range, media))
     }
 }
 
@@ -1348,6 +1854,167 @@ fn is_unique_violation(err: &anyhow::Error) -> bool {
     )
 }
 
+/// Reads a [`MediaFile`] without its tags from a row with `id, file_type, file_name, width,
+/// height, transparent, duration, audio, hash, length, encoder, quality, max_size`.
+fn media_file_from_row(row: &rusqlite::Row) -> Result<MediaFile> {
+    Ok(MediaFile {
+        id: row.get("id")?,
+        file_name: row.get("file_name")?,
+        file_info: FileInfo::try_from_parts(&FileInfoParts {
+            file_type: row.get::<_, String>("file_type")?.parse()?,
+            width: row.get("width")?,
+            height: row.get("height")?,
+            transparent: row.get("transparent")?,
+            duration: row.get("duration")?,
+            audio: row.get("audio")?,
+        })?,
+        hash: blake3::Hash::from_bytes(row.get("hash")?).to_string(),
+        tags: vec![],
+        size: row.get::<_, Option<u64>>("length")?.unwrap_or(0),
+        encode: match row.get::<_, Option<String>>("encoder")? {
+            Some(encoder) => Some(EncodeParams {
+                encoder,
+                quality: row.get("quality")?,
+                max_size: row.get("max_size")?,
+            }),
+            None => None,
+        },
+    })
+}
+
+/// Snapshots the given media rows and their tags, for [`Operation::AddFiles`] and
+/// [`Operation::RemoveFiles`].
+fn read_media_rows(conn: &Connection, ids: &[u64]) -> Result<Vec<MediaRow>> {
+    if ids.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let mut stmt = conn.prepare(&format!(
+        "SELECT * FROM media WHERE id IN ({})",
+        repeat_vars(ids.len())
+    ))?;
+    let names: Vec<String> = stmt.column_names().into_iter().map(String::from).collect();
+    let mut rows = stmt
+        .query_map(params_from_iter(ids), |row| {
+            let columns = names
+                .iter()
+                .enumerate()
+                .map(|(i, name)| Ok((name.clone(), row.get::<_, Value>(i)?)))
+                .collect::<rusqlite::Result<Vec<_>>>()?;
+            Ok(MediaRow {
+                id: row.get("id")?,
+                columns,
+                tags: Vec::new(),
+            })
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    let mut tag_stmt = conn.prepare(
+        "SELECT tags.name FROM media_tags JOIN tags ON media_tags.tag_id = tags.id WHERE media_tags.media_id = ?",
+    )?;
+    for row in &mut rows {
+        row.tags = tag_stmt
+            .query_map(params![row.id], |r| r.get("name"))?
+            .collect::<rusqlite::Result<_>>()?;
+    }
+
+    Ok(rows)
+}
+
+/// Puts back rows taken by [`read_media_rows`], with their original ids.
+fn insert_media_rows(conn: &Connection, rows: &[MediaRow]) -> Result<()> {
+    for row in rows {
+        let names = row
+            .columns
+            .iter()
+            .map(|(name, _)| format!("\"{name}\""))
+            .collect::<Vec<_>>()
+            .join(", ");
+        conn.execute(
+            &format!(
+                "INSERT INTO media ({names}) VALUES ({})",
+                repeat_vars(row.columns.len())
+            ),
+            params_from_iter(row.columns.iter().map(|(_, value)| value)),
+        )?;
+
+        for tag in &row.tags {
+            add_tag_by_name(conn, row.id, tag)?;
+        }
+    }
+    Ok(())
+}
+
+/// Overwrites rows in place with a snapshot from [`read_media_rows`], leaving their tags alone.
+fn update_media_rows(conn: &Connection, rows: &[MediaRow]) -> Result<()> {
+    for row in rows {
+        let assignments = row
+            .columns
+            .iter()
+            .map(|(name, _)| format!("\"{name}\" = ?"))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let values = row
+            .columns
+            .iter()
+            .map(|(_, value)| value.clone())
+            .chain([Value::Integer(row.id as i64)]);
+        conn.execute(
+            &format!("UPDATE media SET {assignments} WHERE id = ?"),
+            params_from_iter(values),
+        )?;
+    }
+    Ok(())
+}
+
+fn delete_media(conn: &Connection, ids: &[u64]) -> Result<()> {
+    if ids.is_empty() {
+        return Ok(());
+    }
+    conn.execute(
+        &format!("DELETE FROM media WHERE id IN ({})", repeat_vars(ids.len())),
+        params_from_iter(ids),
+    )?;
+    Ok(())
+}
+
+/// Tags a file, creating the tag first if it doesn't exist (e.g. it was removed by an undo).
+fn add_tag_by_name(conn: &Connection, id: u64, tag: &str) -> Result<()> {
+    conn.execute("INSERT OR IGNORE INTO tags (name) VALUES (?)", params![tag])?;
+    conn.execute(
+        "INSERT OR IGNORE INTO media_tags (media_id, tag_id) SELECT ?, id FROM tags WHERE name = ?",
+        params![id, tag],
+    )?;
+    Ok(())
+}
+
+/// Untags a file, returning whether it had the tag. With `delete_unused`, the tag itself is
+/// deleted too if nothing else uses it.
+fn remove_tag_by_name(conn: &Connection, id: u64, tag: &str, delete_unused: bool) -> Result<bool> {
+    let removed = conn.execute(
+        "DELETE FROM media_tags WHERE media_id = ? AND tag_id IN (SELECT id FROM tags WHERE name = ?)",
+        params![id, tag],
+    )?;
+    if delete_unused {
+        conn.execute(
+            "DELETE FROM tags WHERE name = ? AND NOT EXISTS (SELECT 1 FROM media_tags WHERE tag_id = tags.id)",
+            params![tag],
+        )?;
+    }
+    Ok(removed > 0)
+}
+
+/// Checksum of the encoded bytes that end up in the pack, so players can detect corruption.
+async fn file_checksum(path: PathBuf) -> Result<[u8; 32]> {
+    let checksum = spawn_blocking(move || -> io::Result<[u8; 32]> {
+        let mut hasher = blake3::Hasher::new();
+        hasher.update_reader(fs::File::open(&path)?)?;
+        Ok(*hasher.finalize().as_bytes())
+    })
+    .await??;
+    Ok(checksum)
+}
+
 fn file_name(path: &Path) -> String {
     path.file_name()
         .unwrap_or(path.as_os_str())
@@ -1373,7 +2040,7 @@ fn resolve_range(range: Range, size: u64) -> Result<(u64, u64)> {
 #[cfg(test)]
 mod tests {
     use rusqlite::params;
-    use shared::read_pack::Metadata;
+    use shared::{encode::ImageCodec, read_pack::Metadata};
     use tempfile::tempdir;
 
     use super::*;
@@ -1413,7 +2080,7 @@ mod tests {
         let pack_path = tmp.path().join("test.lwpack");
 
         let pack = new_test_pack(&pack_path, data_dir.path(), "My Pack").await;
-        pack.save(|_, _| {}).await.unwrap();
+        pack.save(0, |_, _| {}).await.unwrap();
         drop(pack);
 
         let pack2 = MediaPack::open(pack_path, data_dir.path()).await.unwrap();
@@ -1421,6 +2088,61 @@ mod tests {
         assert!(pack2.is_saved().await);
     }
 
+    #[tokio::test]
+    async fn read_only_packs_are_never_written() {
+        let tmp = tempdir().unwrap();
+        let data_dir = tempdir().unwrap();
+        let pack_path = tmp.path().join("test.lwpack");
+
+        let pack = new_test_pack(&pack_path, data_dir.path(), "Test").await;
+        pack.save(0, |_, _| {}).await.unwrap();
+        drop(pack);
+        let original = fs::read(&pack_path).unwrap();
+
+        let pack = MediaPack::open_with_progress(pack_path.clone(), data_dir.path(), true, |_| {})
+            .await
+            .unwrap();
+        assert!(pack.read_only());
+        assert!(!fs::exists(pack_path.with_added_extension("lock")).unwrap());
+
+        insert_staged_audio(&pack, b"new audio").await;
+        pack.mark_unsaved().await.unwrap();
+        assert!(pack.save(0, |_, _| {}).await.is_err());
+
+        let copy_path = tmp.path().join("copy.lwpack");
+        let copy = pack
+            .save_as(&copy_path, 0, |_, _| {})
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(!copy.read_only());
+        assert_eq!(fs::read(&pack_path).unwrap(), original);
+    }
+
+    #[tokio::test]
+    async fn saving_keeps_backups_of_the_previous_versions() {
+        let tmp = tempdir().unwrap();
+        let data_dir = tempdir().unwrap();
+        let pack_path = tmp.path().join("test.lwpack");
+
+        let pack = new_test_pack(&pack_path, data_dir.path(), "Test").await;
+        pack.save(2, |_, _| {}).await.unwrap();
+        assert!(!fs::exists(backup_path(&pack_path, 0)).unwrap());
+
+        let mut versions = vec![fs::read(&pack_path).unwrap()];
+        for content in [b"first", b"other", b"third"] {
+            insert_staged_audio(&pack, content).await;
+            pack.mark_unsaved().await.unwrap();
+            pack.save(2, |_, _| {}).await.unwrap();
+            versions.push(fs::read(&pack_path).unwrap());
+        }
+
+        assert_eq!(fs::read(backup_path(&pack_path, 0)).unwrap(), versions[2]);
+        assert_eq!(fs::read(backup_path(&pack_path, 1)).unwrap(), versions[1]);
+        assert!(!fs::exists(backup_path(&pack_path, 2)).unwrap());
+        assert!(!fs::exists(pack_path.with_added_extension("bak.tmp")).unwrap());
+    }
+
     #[tokio::test]
     async fn file_content_survives_save_and_reopen() {
         let tmp = tempdir().unwrap();
@@ -1431,7 +2153,7 @@ mod tests {
 
         let pack = new_test_pack(&pack_path, data_dir.path(), "Test").await;
         let file_id = insert_staged_audio(&pack, content).await;
-        pack.save(|_, _| {}).await.unwrap();
+        pack.save(0, |_, _| {}).await.unwrap();
         drop(pack);
 
         let pack2 = MediaPack::open(pack_path, data_dir.path()).await.unwrap();
@@ -1495,7 +2217,7 @@ mod tests {
         for payload in payloads {
             ids.push(insert_staged_audio(&pack, payload).await);
         }
-        pack.save(|_, _| {}).await.unwrap();
+        pack.save(0, |_, _| {}).await.unwrap();
         drop(pack);
 
         let pack2 = MediaPack::open(pack_path, data_dir.path()).await.unwrap();
@@ -1533,7 +2255,7 @@ mod tests {
         }
 
         // First save: embeds everything contiguously, no gaps yet.
-        pack.save(|_, _| {}).await.unwrap();
+        pack.save(0, |_, _| {}).await.unwrap();
 
         // Delete a scattered subset (near the start, middle, and end) so the
         // gap-closing loop has to shift a large tail of survivors across several
@@ -1543,7 +2265,7 @@ mod tests {
         pack.remove_files(deleted_ids).await.unwrap();
 
         // Second save: triggers the parallel shift/compaction logic under test.
-        pack.save(|_, _| {}).await.unwrap();
+        pack.save(0, |_, _| {}).await.unwrap();
         drop(pack);
 
         let pack2 = MediaPack::open(pack_path, data_dir.path()).await.unwrap();
@@ -1583,7 +2305,7 @@ mod tests {
             ids.push(insert_staged_audio(&pack, content).await);
         }
 
-        pack.save(|_, _| {}).await.unwrap();
+        pack.save(0, |_, _| {}).await.unwrap();
         drop(pack);
 
         let pack2 = MediaPack::open(pack_path, data_dir.path()).await.unwrap();
@@ -1614,7 +2336,7 @@ mod tests {
             ids.push(insert_staged_audio(&pack, &content).await);
         }
 
-        tokio::time::timeout(std::time::Duration::from_secs(30), pack.save(|_, _| {}))
+        tokio::time::timeout(std::time::Duration::from_secs(30), pack.save(0, |_, _| {}))
             .await
             .expect("first save timed out (hang)")
             .unwrap();
@@ -1624,7 +2346,7 @@ mod tests {
         let deleted: Vec<u64> = (0..n).step_by(7).map(|i| ids[i]).collect();
         pack.remove_files(deleted).await.unwrap();
 
-        tokio::time::timeout(std::time::Duration::from_secs(30), pack.save(|_, _| {}))
+        tokio::time::timeout(std::time::Duration::from_secs(30), pack.save(0, |_, _| {}))
             .await
             .expect("second save timed out (hang)")
             .unwrap();
@@ -1652,7 +2374,7 @@ mod tests {
         }
 
         for cycle in 0..5 {
-            tokio::time::timeout(std::time::Duration::from_secs(60), pack.save(|_, _| {}))
+            tokio::time::timeout(std::time::Duration::from_secs(60), pack.save(0, |_, _| {}))
                 .await
                 .unwrap_or_else(|_| panic!("save timed out (hang) on cycle {cycle}"))
                 .unwrap();
@@ -1699,6 +2421,9 @@ mod tests {
             info: FileInfo::Audio { duration: 1.0 },
             thumbnail: None,
             path: encoded_path_1,
+            codec: None,
+            container: None,
+            params: None,
         };
 
         let encoded_path_2 = pack.dir.join("media").join("upload-2");
@@ -1707,16 +2432,19 @@ mod tests {
             info: FileInfo::Audio { duration: 1.0 },
             thumbnail: None,
             path: encoded_path_2,
+            codec: None,
+            container: None,
+            params: None,
         };
 
         let first = pack
-            .add_file(encoded_1, Path::new("a.wav"), hash)
+            .add_file(encoded_1, Path::new("a.wav"), hash, Sidecar::default())
             .await
             .unwrap();
         assert!(first.is_some(), "first upload of new content should succeed");
 
         let second = pack
-            .add_file(encoded_2, Path::new("b.wav"), hash)
+            .add_file(encoded_2, Path::new("b.wav"), hash, Sidecar::default())
             .await
             .unwrap();
         assert!(
@@ -1731,4 +2459,184 @@ mod tests {
             "only one row should exist for the duplicate hash"
         );
     }
+
+    #[tokio::test]
+    async fn encode_params_are_stored_with_the_file() {
+        let tmp = tempdir().unwrap();
+        let data_dir = tempdir().unwrap();
+        let pack_path = tmp.path().join("test.lwpack");
+
+        let pack = new_test_pack(&pack_path, data_dir.path(), "Params").await;
+
+        let content = b"an encoded image";
+        let encoded_path = pack.dir.join("media").join("upload");
+        tokio::fs::write(&encoded_path, content).await.unwrap();
+        let params = EncodeParams {
+            encoder: "libwebp".to_string(),
+            quality: 90.0,
+            max_size: 1024,
+        };
+        let encoded = EncodedFile {
+            info: FileInfo::Image {
+                width: 1024,
+                height: 768,
+                transparent: false,
+            },
+            thumbnail: None,
+            path: encoded_path,
+            codec: Some(ImageCodec::Webp),
+            container: None,
+            params: Some(params.clone()),
+        };
+
+        let hash = blake3::hash(content);
+        let file = pack
+            .add_file(encoded, Path::new("a.png"), hash, Sidecar::default())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(file.encode.as_ref(), Some(&params));
+
+        let files = pack.get_files().await.unwrap();
+        assert_eq!(files[0].encode, Some(params));
+    }
+
+    #[tokio::test]
+    async fn changed_source_replaces_its_file() {
+        let tmp = tempdir().unwrap();
+        let data_dir = tempdir().unwrap();
+        let pack_path = tmp.path().join("test.lwpack");
+
+        let pack = new_test_pack(&pack_path, data_dir.path(), "Replace").await;
+        let id = insert_staged_audio(&pack, b"first version").await;
+        pack.create_and_add_tag(id, "kept".to_string())
+            .await
+            .unwrap();
+
+        let content = b"second version";
+        let hash = blake3::hash(content);
+        let encoded_path = pack.dir.join("media").join("upload");
+        tokio::fs::write(&encoded_path, content).await.unwrap();
+        let encoded = EncodedFile {
+            info: FileInfo::Audio { duration: 2.0 },
+            thumbnail: None,
+            path: encoded_path,
+            codec: None,
+            container: None,
+            params: None,
+        };
+
+        let file = pack
+            .replace_source(id, encoded, hash)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(file.id, id);
+        assert_eq!(file.hash, hash.to_string());
+        assert_eq!(file.tags, vec!["kept".to_string()]);
+        assert_eq!(pack.file_with_hash(&hash).await.unwrap(), Some(id));
+        assert_eq!(pack.get_files().await.unwrap().len(), 1);
+
+        pack.undo().await.unwrap();
+        let old = blake3::hash(b"first version");
+        assert_eq!(pack.file_with_hash(&old).await.unwrap(), Some(id));
+    }
+
+    #[tokio::test]
+    async fn undo_restores_deleted_files_with_tags() {
+        let tmp = tempdir().unwrap();
+        let data_dir = tempdir().unwrap();
+        let pack_path = tmp.path().join("test.lwpack");
+
+        let content = b"audio that gets deleted by accident";
+
+        let pack = new_test_pack(&pack_path, data_dir.path(), "Undo").await;
+        let id = insert_staged_audio(&pack, content).await;
+        let other = insert_staged_audio(&pack, b"some other audio").await;
+        pack.create_and_add_tag(id, "kept".to_string())
+            .await
+            .unwrap();
+
+        pack.remove_files(vec![id, other]).await.unwrap();
+        assert!(pack.get_files().await.unwrap().is_empty());
+        assert_eq!(pack.history().undo.as_deref(), Some("Delete 2 files"));
+
+        let state = pack.undo().await.unwrap();
+        assert_eq!(state.redo.as_deref(), Some("Delete 2 files"));
+        let files = pack.get_files().await.unwrap();
+        assert_eq!(files.len(), 2);
+        let file = files.iter().find(|f| f.id == id).unwrap();
+        assert_eq!(file.tags, vec!["kept".to_string()]);
+
+        pack.redo().await.unwrap();
+        assert!(pack.get_files().await.unwrap().is_empty());
+
+        pack.undo().await.unwrap();
+        pack.save(0, |_, _| {}).await.unwrap();
+        assert_eq!(pack.history(), HistoryState::default());
+        drop(pack);
+
+        let pack2 = MediaPack::open(pack_path, data_dir.path()).await.unwrap();
+        let view = pack2.get_view().unwrap();
+        let (data, _) = view.get_file_data(id).await.unwrap();
+        assert_eq!(data.as_slice(), content.as_slice());
+    }
+
+    #[tokio::test]
+    async fn undoing_new_tag_deletes_it() {
+        let tmp = tempdir().unwrap();
+        let data_dir = tempdir().unwrap();
+        let pack_path = tmp.path().join("test.lwpack");
+
+        let pack = new_test_pack(&pack_path, data_dir.path(), "Undo").await;
+        let id = insert_staged_audio(&pack, b"tagged audio").await;
+        pack.create_and_add_tag(id, "new".to_string())
+            .await
+            .unwrap();
+
+        pack.undo().await.unwrap();
+        assert!(pack.get_all_tags().await.unwrap().is_empty());
+
+        pack.redo().await.unwrap();
+        assert_eq!(pack.get_all_tags().await.unwrap(), vec!["new".to_string()]);
+    }
+
+    #[tokio::test]
+    async fn bulk_tagging_is_one_undo_step() {
+        let tmp = tempdir().unwrap();
+        let data_dir = tempdir().unwrap();
+        let pack_path = tmp.path().join("test.lwpack");
+
+        let pack = new_test_pack(&pack_path, data_dir.path(), "Bulk").await;
+        let a = insert_staged_audio(&pack, b"first").await;
+        let b = insert_staged_audio(&pack, b"second").await;
+        pack.create_and_add_tag(a, "shared".to_string())
+            .await
+            .unwrap();
+
+        pack.add_tag_to_files(vec![a, b], "shared".to_string())
+            .await
+            .unwrap();
+        let files = pack.get_files().await.unwrap();
+        assert!(files.iter().all(|f| f.tags == vec!["shared".to_string()]));
+
+        // Only `b` changed, so undoing leaves `a`'s earlier tag alone.
+        pack.undo().await.unwrap();
+        let files = pack.get_files().await.unwrap();
+        let tags = |id| files.iter().find(|f| f.id == id).unwrap().tags.clone();
+        assert_eq!(tags(a), vec!["shared".to_string()]);
+        assert!(tags(b).is_empty());
+
+        pack.redo().await.unwrap();
+        pack.remove_tag_from_files(vec![a, b], "shared".to_string())
+            .await
+            .unwrap();
+        assert!(pack
+            .get_files()
+            .await
+            .unwrap()
+            .iter()
+            .all(|f| f.tags.is_empty()));
+        assert_eq!(pack.history().undo.as_deref(), Some("Remove tag \"shared\""));
+    }
 }
@@ -0,0 +1,125 @@
+//! The files on disk that a pack's media was added from, so adding the same folder again only
+//! reads and encodes what's new or has changed since.
+//!
+//! This is kept in the pack's staging directory rather than its index: packs don't hold on to
+//! where their files came from, since those paths are private to whoever made the pack.
+
+use std::{fs, path::Path, sync::Mutex, time::UNIX_EPOCH};
+
+use anyhow::Result;
+use rusqlite::{params, Connection, OptionalExtension};
+
+/// A source file as it was when it was last added.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Source {
+    pub size: u64,
+    /// When the file was last modified, in nanoseconds since the Unix epoch.
+    pub modified: i64,
+    /// The hash of the file's contents, matching the `hash` column of the pack's media.
+    pub hash: blake3::Hash,
+}
+
+impl Source {
+    /// Whether the file is most likely still the same as when it was added. Its size and
+    /// modification time are compared rather than its contents, to avoid reading it.
+    pub fn unchanged(&self, metadata: &fs::Metadata) -> bool {
+        self.size == metadata.len() && Some(self.modified) == modified(metadata)
+    }
+}
+
+/// When a file was last modified, in nanoseconds since the Unix epoch, if the platform says.
+pub fn modified(metadata: &fs::Metadata) -> Option<i64> {
+    let since = metadata.modified().ok()?.duration_since(UNIX_EPOCH).ok()?;
+    i64::try_from(since.as_nanos()).ok()
+}
+
+pub struct Sources {
+    conn: Mutex<Connection>,
+}
+
+impl Sources {
+    pub fn open(path: &Path) -> Result<Self> {
+        let conn = Connection::open(path)?;
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS sources (
+                path TEXT PRIMARY KEY,
+                size INTEGER NOT NULL,
+                modified INTEGER NOT NULL,
+                hash BLOB NOT NULL
+            ) STRICT;",
+        )?;
+
+        Ok(Self {
+            conn: Mutex::new(conn),
+        })
+    }
+
+    /// What the file at `path` was like when it was last added, if it has been.
+    pub fn get(&self, path: &Path) -> Result<Option<Source>> {
+        let conn = self.conn.lock().unwrap();
+        Ok(conn
+            .query_row(
+                "SELECT size, modified, hash FROM sources WHERE path = ?",
+                params![path.to_string_lossy()],
+                |row| {
+                    Ok(Source {
+                        size: row.get("size")?,
+                        modified: row.get("modified")?,
+                        hash: blake3::Hash::from_bytes(row.get("hash")?),
+                    })
+                },
+            )
+            .optional()?)
+    }
+
+    /// Records the file at `path` as added with the given hash. Files whose modification time
+    /// can't be read aren't recorded, since they can't be checked later without reading them.
+    pub fn set(&self, path: &Path, metadata: &fs::Metadata, hash: blake3::Hash) -> Result<()> {
+        let Some(modified) = modified(metadata) else {
+            return Ok(());
+        };
+
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT OR REPLACE INTO sources (path, size, modified, hash) VALUES (?, ?, ?, ?)",
+            params![
+                path.to_string_lossy(),
+                metadata.len(),
+                modified,
+                hash.as_bytes()
+            ],
+        )?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, SystemTime};
+
+    use super::*;
+
+    #[test]
+    fn remembers_unchanged_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let sources = Sources::open(&dir.path().join("sources.db")).unwrap();
+        let path = dir.path().join("a.png");
+
+        fs::write(&path, b"first").unwrap();
+        assert_eq!(sources.get(&path).unwrap(), None);
+
+        let hash = blake3::hash(b"first");
+        sources
+            .set(&path, &fs::metadata(&path).unwrap(), hash)
+            .unwrap();
+        let source = sources.get(&path).unwrap().unwrap();
+        assert_eq!(source.hash, hash);
+        assert!(source.unchanged(&fs::metadata(&path).unwrap()));
+
+        // Rewritten with the same size, but later.
+        let file = fs::File::options().write(true).open(&path).unwrap();
+        file.set_modified(SystemTime::now() + Duration::from_secs(60))
+            .unwrap();
+        assert!(!source.unchanged(&fs::metadata(&path).unwrap()));
+    }
+}
@@ -1,18 +1,18 @@
-use std::io::Write;
+use std::{io::Write, process::Command};
 
 use anyhow::{bail, Result};
+use shared::{children, pack_index::StoredMedia};
 use tempfile::NamedTempFile;
-use tokio::process::Command;
 
 use crate::pack::FileData;
 
-pub async fn generate_display_image(file_data: FileData) -> Result<Vec<u8>> {
+pub async fn generate_display_image(file_data: FileData, media: &StoredMedia) -> Result<Vec<u8>> {
     let mut _temp_file = None;
 
     let path = match file_data {
         FileData::Path(path) => path,
         FileData::Data(data) => {
-            let mut tempfile = NamedTempFile::with_suffix(".avif")?;
+            let mut tempfile = NamedTempFile::with_suffix(temp_file_suffix(media))?;
             tempfile.write_all(&data)?;
             let path = tempfile.path().to_path_buf();
             _temp_file = Some(tempfile);
@@ -21,14 +21,13 @@ pub async fn generate_display_image(file_data: FileData) -> Result<Vec<u8>> {
     };
 
     #[allow(unused_mut)]
-    let mut std_cmd = std::process::Command::new(crate::encode::get_ffmpeg_path());
+    let mut cmd = Command::new(crate::encode::get_ffmpeg_path());
     #[cfg(target_os = "windows")]
     {
         use std::os::windows::process::CommandExt;
-        std_cmd.creation_flags(0x08000000);
+        cmd.creation_flags(0x08000000);
     }
-    shared::utils::sanitize_child_env(&mut std_cmd);
-    let mut cmd = Command::from(std_cmd);
+    shared::utils::sanitize_child_env(&mut cmd);
 
     cmd.args(["-y", "-i"]).arg(&path).args([
         "-vf",
@@ -42,7 +41,7 @@ pub async fn generate_display_image(file_data: FileData) -> Result<Vec<u8>> {
         "pipe:1",
     ]);
 
-    let output = cmd.output().await?;
+    let output = tokio::task::spawn_blocking(move || children::output(&mut cmd)).await??;
 
     if !output.status.success() {
         bail!("ffmpeg display image generation failed");
@@ -53,15 +52,16 @@ pub async fn generate_display_image(file_data: FileData) -> Result<Vec<u8>> {
 
 pub async fn generate_preview(
     file_data: FileData,
-    is_image: bool,
+    media: &StoredMedia,
     transparent: bool,
 ) -> Result<Vec<u8>> {
+    let is_image = media.file_type == "image";
     let mut _temp_file = None;
 
     let path = match file_data {
         FileData::Path(path) => path,
         FileData::Data(data) => {
-            let mut tempfile = NamedTempFile::with_suffix(if is_image { ".avif" } else { ".mp4" })?;
+            let mut tempfile = NamedTempFile::with_suffix(temp_file_suffix(media))?;
             tempfile.write_all(&data)?;
             let path = tempfile.path().to_path_buf();
             _temp_file = Some(tempfile);
@@ -70,14 +70,13 @@ pub async fn generate_preview(
     };
 
     #[allow(unused_mut)]
-    let mut std_cmd = std::process::Command::new(crate::encode::get_ffmpeg_path());
+    let mut cmd = Command::new(crate::encode::get_ffmpeg_path());
     #[cfg(target_os = "windows")]
     {
         use std::os::windows::process::CommandExt;
-        std_cmd.creation_flags(0x08000000);
+        cmd.creation_flags(0x08000000);
     }
-    shared::utils::sanitize_child_env(&mut std_cmd);
-    let mut cmd = Command::from(std_cmd);
+    shared::utils::sanitize_child_env(&mut cmd);
     cmd.args(["-y"]);
 
     if !is_image {
@@ -104,7 +103,7 @@ pub async fn generate_preview(
         "-vf", &filter, "-pix_fmt", "yuv420p", "-f", "mjpeg", "-q:v", "4", "pipe:1",
     ]);
 
-    let output = cmd.output().await?;
+    let output = tokio::task::spawn_blocking(move || children::output(&mut cmd)).await??;
 
     if !output.status.success() {
         bail!("ffmpeg preview generation failed");
@@ -112,3 +111,11 @@ pub async fn generate_preview(
 
     Ok(output.stdout)
 }
+
+/// The extension of the format the file is stored in, for the temp file ffmpeg reads it from.
+fn temp_file_suffix(media: &StoredMedia) -> String {
+    media
+        .extension()
+        .map(|extension| format!(".{extension}"))
+        .unwrap_or_default()
+}
@@ -0,0 +1,206 @@
+use std::{
+    collections::{BTreeMap, VecDeque},
+    time::{Duration, Instant},
+};
+
+use serde::Serialize;
+
+/// How far back the rolling throughput looks, so the rate follows e.g. a run of large videos
+/// rather than averaging over the whole batch.
+const WINDOW: Duration = Duration::from_secs(60);
+
+/// Time spent in each stage of processing a file: probing covers hashing and reading the file's
+/// info, writing covers adding it to the pack.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct StageTimings {
+    pub probe: Duration,
+    pub encode: Duration,
+    pub write: Duration,
+}
+
+/// What's recorded for each file that makes it into the pack.
+pub struct FileStats {
+    /// The media type and what it was encoded to, e.g. "Image (avif)".
+    pub label: String,
+    pub input_bytes: u64,
+    pub output_bytes: u64,
+    pub timings: StageTimings,
+}
+
+/// Sent with `upload:stats` after every file.
+#[derive(Serialize, Clone, Debug)]
+pub struct Throughput {
+    pub files_per_min: f64,
+    pub mb_per_min: f64,
+    /// Estimated seconds until the batch is done, once a file has finished.
+    pub eta_secs: Option<f64>,
+}
+
+#[derive(Serialize, Clone, Debug, Default)]
+pub struct TypeSummary {
+    pub label: String,
+    pub files: usize,
+    pub input_bytes: u64,
+    pub output_bytes: u64,
+    pub encode_secs: f64,
+}
+
+/// Sent with `upload:summary` once a batch finishes. Stage times are summed across files, so
+/// with several files in flight they add up to more than `elapsed_secs`.
+#[derive(Serialize, Clone, Debug)]
+pub struct UploadSummary {
+    pub files: usize,
+    pub elapsed_secs: f64,
+    pub input_bytes: u64,
+    pub output_bytes: u64,
+    pub probe_secs: f64,
+    pub encode_secs: f64,
+    pub write_secs: f64,
+    pub by_type: Vec<TypeSummary>,
+}
+
+/// Timing and throughput for a single upload batch.
+pub struct UploadStats {
+    started: Instant,
+    total: usize,
+    finished: usize,
+    // When each file finished, and how many input bytes it had (0 if it wasn't added).
+    recent: VecDeque<(Instant, u64)>,
+    timings: StageTimings,
+    by_type: BTreeMap<String, TypeSummary>,
+}
+
+impl UploadStats {
+    pub fn new(total: usize, now: Instant) -> Self {
+        Self {
+            started: now,
+            total,
+            finished: 0,
+            recent: VecDeque::new(),
+            timings: StageTimings::default(),
+            by_type: BTreeMap::new(),
+        }
+    }
+
+    /// Records a finished file. `file` is `None` for files that were skipped, failed or
+    /// cancelled, which still count towards the ETA.
+    pub fn finish_file(&mut self, now: Instant, file: Option<FileStats>) {
+        self.finished += 1;
+        self.recent
+            .push_back((now, file.as_ref().map_or(0, |f| f.input_bytes)));
+        while self
+            .recent
+            .front()
+            .is_some_and(|(at, _)| now.duration_since(*at) > WINDOW)
+        {
+            self.recent.pop_front();
+        }
+
+        let Some(file) = file else { return };
+        self.timings.probe += file.timings.probe;
+        self.timings.encode += file.timings.encode;
+        self.timings.write += file.timings.write;
+
+        let summary = self
+            .by_type
+            .entry(file.label.clone())
+            .or_insert_with(|| TypeSummary {
+                label: file.label,
+                ..Default::default()
+            });
+        summary.files += 1;
+        summary.input_bytes += file.input_bytes;
+        summary.output_bytes += file.output_bytes;
+        summary.encode_secs += file.timings.encode.as_secs_f64();
+    }
+
+    pub fn throughput(&self, now: Instant) -> Throughput {
+        let window_start = now
+            .checked_sub(WINDOW)
+            .map_or(self.started, |start| start.max(self.started));
+        let recent: Vec<_> = self
+            .recent
+            .iter()
+            .filter(|(at, _)| *at >= window_start)
+            .collect();
+        let secs = now.duration_since(window_start).as_secs_f64();
+        if secs <= 0.0 || recent.is_empty() {
+            return Throughput {
+                files_per_min: 0.0,
+                mb_per_min: 0.0,
+                eta_secs: None,
+            };
+        }
+
+        let bytes: u64 = recent.iter().map(|(_, bytes)| bytes).sum();
+        let remaining = self.total.saturating_sub(self.finished);
+
+        Throughput {
+            files_per_min: recent.len() as f64 * 60.0 / secs,
+            mb_per_min: bytes as f64 / 1_000_000.0 * 60.0 / secs,
+            eta_secs: Some(remaining as f64 * secs / recent.len() as f64),
+        }
+    }
+
+    pub fn summary(&self, now: Instant) -> UploadSummary {
+        let by_type: Vec<_> = self.by_type.values().cloned().collect();
+        UploadSummary {
+            files: by_type.iter().map(|t| t.files).sum(),
+            elapsed_secs: now.duration_since(self.started).as_secs_f64(),
+            input_bytes: by_type.iter().map(|t| t.input_bytes).sum(),
+            output_bytes: by_type.iter().map(|t| t.output_bytes).sum(),
+            probe_secs: self.timings.probe.as_secs_f64(),
+            encode_secs: self.timings.encode.as_secs_f64(),
+            write_secs: self.timings.write.as_secs_f64(),
+            by_type,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn file(label: &str, bytes: u64) -> Option<FileStats> {
+        Some(FileStats {
+            label: label.to_string(),
+            input_bytes: bytes,
+            output_bytes: bytes / 2,
+            timings: StageTimings {
+                encode: Duration::from_secs(2),
+                ..Default::default()
+            },
+        })
+    }
+
+    #[test]
+    fn throughput_and_summary() {
+        let start = Instant::now();
+        let mut stats = UploadStats::new(4, start);
+
+        stats.finish_file(
+            start + Duration::from_secs(10),
+            file("Image (avif)", 1_000_000),
+        );
+        stats.finish_file(start + Duration::from_secs(20), None);
+        stats.finish_file(
+            start + Duration::from_secs(30),
+            file("Video (libx264)", 3_000_000),
+        );
+
+        let throughput = stats.throughput(start + Duration::from_secs(30));
+        assert_eq!(throughput.files_per_min, 6.0);
+        assert_eq!(throughput.mb_per_min, 8.0);
+        assert_eq!(throughput.eta_secs, Some(10.0));
+
+        // Files older than the window no longer count towards the rate.
+        let later = start + Duration::from_secs(85);
+        assert_eq!(stats.throughput(later).files_per_min, 1.0);
+
+        let summary = stats.summary(later);
+        assert_eq!(summary.files, 2);
+        assert_eq!(summary.output_bytes, 2_000_000);
+        assert_eq!(summary.encode_secs, 4.0);
+        assert_eq!(summary.by_type[0].label, "Image (avif)");
+    }
+}
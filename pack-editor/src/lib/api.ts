@@ -1,11 +1,32 @@
 import { invoke } from "@tauri-apps/api/core";
-import type { MediaFile, MetadataDto, PackInfo } from "./types.js";
+import type {
+  BackupSettings,
+  BudgetReport,
+  EncodeOverrides,
+  EncodeSettings,
+  EncodeSettingsInfo,
+  HistoryState,
+  MediaFile,
+  MetadataDto,
+  PackInfo,
+  PackStats,
+  RecentPack,
+  TextImport,
+  TextKind,
+} from "./types.js";
 
 export const api = {
   newPackDialog: () => invoke<PackInfo | null>("new_pack_dialog"),
-  openPackDialog: () => invoke<PackInfo | null>("open_pack_dialog"),
+  openPackDialog: (readOnly = false) =>
+    invoke<PackInfo | null>("open_pack_dialog", { readOnly }),
+  getRecentPacks: () => invoke<RecentPack[]>("get_recent_packs"),
+  openRecentPack: (path: string) => invoke<PackInfo>("open_recent_pack", { path }),
+  setRecentPackPinned: (path: string, pinned: boolean) =>
+    invoke<RecentPack[]>("set_recent_pack_pinned", { path, pinned }),
+  forgetRecentPack: (path: string) => invoke<RecentPack[]>("forget_recent_pack", { path }),
   savePack: () => invoke<void>("save_pack"),
   savePackAsDialog: () => invoke<PackInfo | null>("save_pack_as_dialog"),
+  exportManifestDialog: () => invoke<boolean>("export_manifest_dialog"),
   discardChanges: () => invoke<MetadataDto>("discard_changes"),
   closePack: () => invoke<void>("close_pack"),
   confirmClose: () => invoke<void>("confirm_close"),
@@ -22,16 +43,41 @@ export const api = {
     invoke<void>("remove_tag_from_file", { id, tag }),
   createAndAddTag: (id: number, tag: string) =>
     invoke<void>("create_and_add_tag", { id, tag }),
+  addTagToFiles: (ids: number[], tag: string) =>
+    invoke<void>("add_tag_to_files", { ids, tag }),
+  removeTagFromFiles: (ids: number[], tag: string) =>
+    invoke<void>("remove_tag_from_files", { ids, tag }),
+
+  getPackStats: () => invoke<PackStats>("get_pack_stats"),
+  getBudgetReport: () => invoke<BudgetReport[]>("get_budget_report"),
+  applySizeBudgets: () => invoke<BudgetReport[]>("apply_size_budgets"),
+
+  getTextCounts: () => invoke<[TextKind, number][]>("get_text_counts"),
+  importTextsDialog: () => invoke<TextImport[]>("import_texts_dialog"),
 
   getPackMetadata: () => invoke<MetadataDto>("get_pack_metadata"),
   setPackMetadata: (dto: MetadataDto) => invoke<void>("set_pack_metadata", { dto }),
   savePackMetadata: () => invoke<void>("save_pack_metadata"),
   markPackUnsaved: () => invoke<void>("mark_pack_unsaved"),
 
+  getHistory: () => invoke<HistoryState>("get_history"),
+  undo: () => invoke<HistoryState>("undo"),
+  redo: () => invoke<HistoryState>("redo"),
+
   addFilesDialog: () => invoke<void>("add_files_dialog"),
   addFolderDialog: (recursive: boolean) => invoke<void>("add_folder_dialog", { recursive }),
   addPaths: (paths: string[]) => invoke<void>("add_paths", { paths }),
+  reencodeFiles: (ids: number[], overrides?: EncodeOverrides) =>
+    invoke<void>("reencode_files", { ids, overrides: overrides ?? null }),
   cancelUpload: () => invoke<void>("cancel_upload"),
 
+  getEncodeSettings: () => invoke<EncodeSettingsInfo>("get_encode_settings"),
+  setEncodeSettings: (settings: EncodeSettings) =>
+    invoke<void>("set_encode_settings", { settings }),
+
+  getBackupSettings: () => invoke<BackupSettings>("get_backup_settings"),
+  setBackupSettings: (settings: BackupSettings) =>
+    invoke<void>("set_backup_settings", { settings }),
+
   getMediaPort: () => invoke<number>("get_media_port"),
 };
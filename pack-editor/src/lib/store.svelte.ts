@@ -1,4 +1,14 @@
-import type { MediaFile, MetadataDto, UploadError } from "./types.js";
+import { api } from "./api.js";
+import type {
+  HistoryState,
+  MediaFile,
+  MetadataDto,
+  TextImport,
+  TextKind,
+  Throughput,
+  UploadError,
+  UploadSummary,
+} from "./types.js";
 
 // Reused across sorts: constructing a Collator per comparison (e.g. via
 // a.localeCompare(b, undefined, opts)) is drastically slower at scale.
@@ -13,6 +23,11 @@ class AppStore {
   packOpen = $state(false);
   packName = $state("");
   packSaved = $state(true);
+  // The pack file can't be written to, so only Save As works.
+  packReadOnly = $state(false);
+
+  // Undo / redo
+  history = $state<HistoryState>({ undo: null, redo: null });
 
   // Files and tags
   files = $state<MediaFile[]>([]);
@@ -45,6 +60,12 @@ class AppStore {
   uploadDone = $state(0);
   uploadBatches = $state(0);
   uploadErrors = $state<UploadError[]>([]);
+  uploadThroughput = $state<Throughput | null>(null);
+  uploadSummary = $state<UploadSummary | null>(null);
+  /** Set when an upload stopped at its first error. */
+  uploadAborted = $state(false);
+  /** Where files that failed to upload were copied, if they were. */
+  quarantineDir = $state<string | null>(null);
   _showDoneBriefly = $state(false);
   _doneTimer: ReturnType<typeof setTimeout> | null = null;
 
@@ -61,6 +82,11 @@ class AppStore {
   // Options form state
   metadata = $state<MetadataDto | null>(null);
 
+  // Notifications, prompts and links
+  textCounts = $state<[TextKind, number][]>([]);
+  /** The outcome of the last text import, shown in the options. */
+  textImportMessage = $state<string | null>(null);
+
   filteredFiles = $derived.by(() => {
     const files = this.files;
     const query = this.searchQuery.toLowerCase();
@@ -99,10 +125,11 @@ class AppStore {
     return this.files.find((f) => f.id === id) ?? null;
   });
 
-  openPack(name: string, files: MediaFile[], tags: string[]) {
+  openPack(name: string, files: MediaFile[], tags: string[], readOnly = false) {
     this.packOpen = true;
     this.packName = name;
     this.packSaved = true;
+    this.packReadOnly = readOnly;
     this.files = files;
     this.allTags = tags;
     this.selectedIds = new Set();
@@ -113,12 +140,14 @@ class AppStore {
     this.mediaTypeFilter = "all";
     this.tagFilter = new Set();
     this.metadata = null;
+    this.history = { undo: null, redo: null };
   }
 
   closePack() {
     this.packOpen = false;
     this.packName = "";
     this.packSaved = true;
+    this.packReadOnly = false;
     this.files = [];
     this.allTags = [];
     this.selectedIds = new Set();
@@ -127,6 +156,29 @@ class AppStore {
     this.searchQuery = "";
     this.mediaTypeFilter = "all";
     this.tagFilter = new Set();
+    this.history = { undo: null, redo: null };
+  }
+
+  async refreshHistory() {
+    this.history = await api.getHistory();
+  }
+
+  async refreshTextCounts() {
+    this.textCounts = await api.getTextCounts();
+  }
+
+  onTextsImported(imports: TextImport[]) {
+    if (imports.length === 0) return;
+    this.textImportMessage = imports
+      .map(
+        (i) =>
+          `Imported ${i.count} ${i.kind}${i.count === 1 ? "" : "s"}` +
+          `${i.locale ? ` (${i.locale})` : ""} from ${i.path}`,
+      )
+      .join("\n");
+    this.packSaved = false;
+    this.refreshHistory();
+    this.refreshTextCounts();
   }
 
   addFile(file: MediaFile) {
@@ -134,6 +186,12 @@ class AppStore {
     this.packSaved = false;
   }
 
+  replaceFiles(files: MediaFile[]) {
+    const byId = new Map(files.map((f) => [f.id, f]));
+    this.files = this.files.map((f) => byId.get(f.id) ?? f);
+    this.packSaved = false;
+  }
+
   removeFilesById(ids: number[]) {
     const idSet = new Set(ids);
     this.files = this.files.filter((f) => !idSet.has(f.id));
@@ -157,6 +215,23 @@ class AppStore {
     }
   }
 
+  addTagToFiles(ids: number[], tag: string) {
+    const idSet = new Set(ids);
+    this.files = this.files.map((f) =>
+      idSet.has(f.id) && !f.tags.includes(tag) ? { ...f, tags: [...f.tags, tag] } : f,
+    );
+    if (!this.allTags.includes(tag)) this.allTags.push(tag);
+    this.packSaved = false;
+  }
+
+  removeTagFromFiles(ids: number[], tag: string) {
+    const idSet = new Set(ids);
+    this.files = this.files.map((f) =>
+      idSet.has(f.id) ? { ...f, tags: f.tags.filter((t) => t !== tag) } : f,
+    );
+    this.packSaved = false;
+  }
+
   removeTagFromFile(id: number, tag: string) {
     const idx = this.files.findIndex((f) => f.id === id);
     if (idx >= 0) {
@@ -207,6 +282,10 @@ class AppStore {
     if (this.uploadBatches === 0) {
       this.uploadTotal = total;
       this.uploadDone = 0;
+      this.uploadThroughput = null;
+      this.uploadSummary = null;
+      this.uploadAborted = false;
+      this.quarantineDir = null;
     } else {
       this.uploadTotal += total;
     }
@@ -10,6 +10,20 @@ export interface MediaFile {
   hash: string;
   tags: string[];
   size: number;
+  encode: EncodeParams | null;
+}
+
+/** The settings a file was encoded with. `quality` is in the encoder's own units. */
+export interface EncodeParams {
+  encoder: string;
+  quality: number;
+  max_size: number;
+}
+
+/** Settings to re-encode files with in place of the defaults. */
+export interface EncodeOverrides {
+  quality?: number;
+  max_size?: number;
 }
 
 export interface MetadataDto {
@@ -17,11 +31,110 @@ export interface MetadataDto {
   creator: string | null;
   description: string | null;
   version: string | null;
+  image_codec: ImageCodec;
+  video_container: VideoContainer;
+  /** Maximum total size in bytes of the files with each tag. */
+  size_budgets: Record<string, number>;
+  /** Goals for each session, on top of the user's own. */
+  goals: Goal[];
+  /** Tags held back until part way into each session. */
+  milestones: Milestone[];
+  /** Names for the content rating levels, mildest first. Empty uses Softcore and Hardcore. */
+  ratings: string[];
+  /** The rating level of the files with each tag. */
+  tag_ratings: Record<string, number>;
+  /** Picks recently added files more often at the start of each session. */
+  recency_bias?: RecencyBias | null;
+  /** The kinds of prompt the pack pops up. Empty only pops up the pack's own prompts. */
+  prompt_kinds: PromptKind[];
+  /** The license the pack as a whole is shared under. */
+  license: string | null;
+  /** The people whose work went into the pack. */
+  credits: Credit[];
+}
+
+export interface Credit {
+  name: string;
+  /** What they did, e.g. "Artist". */
+  role?: string | null;
+  /** The license their work is used under, if it isn't the pack's own. */
+  license?: string | null;
+  urls?: string[];
+}
+
+export type PromptKind = "repeat-text" | "arithmetic" | "typing-test";
+
+export interface RecencyBias {
+  /** How many times as often the newest file is picked, at the start of a session. */
+  boost: number;
+  /** How many days before the newest file a file still counts as recent. */
+  days: number;
+  /** How many minutes into a session the bias wears off by. */
+  minutes: number;
+}
+
+export type GoalStat = "minutes" | "popups" | "prompts" | "choices";
+
+export interface Goal {
+  name: string;
+  stat: GoalStat;
+  target: number;
+  /** A popup set shown when the goal is reached. */
+  popup_set?: string;
+}
+
+export interface Milestone {
+  name: string;
+  stat: GoalStat;
+  target: number;
+  /** The tags whose files and texts aren't shown until the milestone is reached. */
+  unlocks: string[];
+}
+
+/** What a pack is made up of, from the totals kept in its index. */
+export interface PackStats {
+  types: { file_type: string; count: number; bytes: number }[];
+  /** Largest tags first. */
+  tags: { tag: string; count: number; bytes: number }[];
+}
+
+export interface BudgetReport {
+  tag: string;
+  budget: number;
+  /** Total size of the tag's files, before anything is cut. */
+  used: number;
+  /** Files that would have to go (or went) for the tag to fit. */
+  cut: { id: number; file_name: string; size: number }[];
+}
+
+export type ImageCodec = "avif" | "webp" | "jxl";
+
+export type VideoContainer = "mp4" | "webm";
+
+export type TextKind = "notification" | "prompt" | "link";
+
+/** A text file imported into the pack, replacing its texts of that kind and language. */
+export interface TextImport {
+  path: string;
+  kind: TextKind;
+  /** The language the texts are in, e.g. `pt-BR`, from the file's name. */
+  locale: string | null;
+  count: number;
 }
 
 export interface PackInfo {
   name: string;
   has_unsaved_changes: boolean;
+  read_only: boolean;
+}
+
+export interface RecentPack {
+  path: string;
+  id: string;
+  name: string;
+  /** Unix timestamp, in seconds. */
+  last_opened: number;
+  pinned: boolean;
 }
 
 export interface UploadError {
@@ -29,7 +142,67 @@ export interface UploadError {
   error: string;
 }
 
+/** Rolling rates over the last minute of an upload. */
+export interface Throughput {
+  files_per_min: number;
+  mb_per_min: number;
+  eta_secs: number | null;
+}
+
+export interface TypeSummary {
+  /** Media type and codec, e.g. "Image (avif)". */
+  label: string;
+  files: number;
+  input_bytes: number;
+  output_bytes: number;
+  encode_secs: number;
+}
+
+/** Sent when an upload finishes. Stage times are summed across files processed in parallel. */
+export interface UploadSummary {
+  files: number;
+  elapsed_secs: number;
+  input_bytes: number;
+  output_bytes: number;
+  probe_secs: number;
+  encode_secs: number;
+  write_secs: number;
+  by_type: TypeSummary[];
+}
+
+export interface HistoryState {
+  /** Label of the edit that undo would revert, if any. */
+  undo: string | null;
+  redo: string | null;
+}
+
 export interface SaveProgress {
   saved: number;
   total: number;
 }
+
+export type OpenProgress =
+  | { stage: "extracting_index"; done: number; total: number }
+  | { stage: "migrating" }
+  | { stage: "loading" }
+  | { stage: "done" };
+
+/** App-wide encoding settings, not stored in the pack. */
+export interface EncodeSettings {
+  /** Files encoded at once; null uses `default_jobs`. */
+  jobs: number | null;
+  /** Runs ffmpeg at a lower priority so the machine stays usable. */
+  low_priority: boolean;
+  /** What an upload does when a file fails to encode. */
+  on_error: "skip" | "abort" | "quarantine";
+}
+
+export interface EncodeSettingsInfo extends EncodeSettings {
+  default_jobs: number;
+}
+
+/** App-wide, like `EncodeSettings`. */
+export interface BackupSettings {
+  /** Copies kept from before each save; 0 turns backups off. */
+  keep: number;
+}
@@ -0,0 +1,6 @@
+## Used by more than one app.
+
+common-open-progress-reading-index = Reading index ({ $percent }%)
+common-open-progress-migrating = Updating index
+common-open-progress-loading = Loading tags
+common-open-progress-done = Done
@@ -0,0 +1,468 @@
+## The config app. Its frontend formats messages itself, so it can only use plain `{ $name }`
+## arguments. Messages that depend on a count have an attribute for each plural category that
+## needs its own wording (e.g. `.one`), picked using the `count` argument.
+
+config-settings = Settings
+config-tab-general = General
+config-tab-pack-mode = Pack & Mode
+config-loading = Loading…
+
+config-update-available = A new version of Lewdware is available.
+config-update-download = Download update
+config-dismiss = Dismiss
+
+## General
+
+config-launch = Launch
+config-stop = Stop
+config-running = Running
+config-no-pack = No pack selected. Upload a pack to launch Lewdware.
+config-open-pack-mode = Pack & Mode settings
+
+config-panic-key = Panic key
+config-panic-key-description = Pressing this key combination closes the app immediately.
+config-input-monitoring-needed = The panic key requires Input Monitoring permission.
+config-input-monitoring-open = Open Settings
+config-input-monitoring-failed =
+    The permission prompt could not be shown (the app may need to be signed). To enable
+    manually: open
+config-input-monitoring-settings = System Settings → Privacy & Security → Input Monitoring
+config-input-monitoring-failed-end = and add Lewdware, then restart the app.
+config-press-a-key = Press a key…
+config-key-not-set = Not set
+config-clear = Clear
+
+config-mute-key = Mute key
+config-mute-key-description = Pressing this key combination mutes or unmutes all audio, including videos.
+config-pause-key = Pause key
+config-pause-key-description = Pressing this key combination pauses or resumes the session. Open popups stay, but no new ones appear and audio stops until it's resumed.
+config-block-key = Block key
+config-block-key-description = Pressing this key combination closes the image or video under the cursor (or the focused one), and it's never shown again, in any pack.
+config-blocked-count =
+    { $count ->
+        [one] { $count } file blocked
+       *[other] { $count } files blocked
+    }
+config-blocked-unblock-all = Unblock all
+config-favorite-key = Favorite key
+config-favorite-key-description = Pressing this key combination adds the image or video under the cursor (or the focused one) to the pack's favorites, or takes it off them.
+config-info-key = Info key
+config-info-key-description = Pressing this key combination shows (or hides) which file in the pack the image or video under the cursor (or the focused one) came from, along with its tags and size. Useful when testing a pack.
+
+config-video-hover = Hovering over a video
+config-video-hover-description =
+    What happens when the cursor is over a video popup. Muting and pausing stop when the cursor
+    leaves the popup.
+config-video-hover-none = Do nothing
+config-video-hover-mute = Mute audio
+config-video-hover-pause = Pause video
+config-video-hover-close = Close popup
+
+config-video-performance = Video performance
+config-video-performance-description =
+    Ways to use less CPU when several videos are playing at once, at some cost to how they look.
+config-video-max-fps = Highest frame rate for videos
+config-video-max-fps-any = Any
+config-video-downscale = Decode videos at the size of their popup
+
+config-image-cache = Image memory
+config-image-cache-description =
+    Keep images in memory once they've been shown, so ones that pop up again appear straight away.
+    The least recently shown are dropped to stay under the limit. 0 keeps none.
+config-image-cache-megabytes = MB at most
+
+config-verify-media = Verify media
+config-verify-media-description =
+    Check each file against the checksum stored in the pack before showing it, and skip any that
+    are corrupted. Makes loading media slower.
+config-verify-media-label = Verify media before showing it
+
+config-isolate-video-decoding = Isolate video decoding
+config-isolate-video-decoding-description =
+    Decode videos in a separate process, so a broken file can't crash Lewdware. Videos that crash
+    the decoder are skipped for the rest of the session. Uses a little more memory and CPU, and
+    videos are never decoded on the GPU.
+config-isolate-video-decoding-label = Decode videos in a separate process
+
+config-seed = Random seed
+config-seed-description =
+    Makes every session pick the same media and popup positions, in the same order. Useful for
+    tracking down a problem with a pack: each session's seed is written to the log. Leave empty for
+    a new seed each time.
+config-seed-placeholder = Random
+
+config-daily-shuffle = Daily shuffle
+config-daily-shuffle-description =
+    Picks the seed from today's date, so everyone playing the same pack with the same settings gets
+    the same session that day. A new day starts at midnight UTC. A random seed set above takes
+    precedence.
+config-daily-shuffle-label = Same shuffle for everyone each day
+
+config-metrics-port = Metrics port
+config-metrics-port-description =
+    Serves counters about the session, like how many popups have spawned and how long decoding
+    takes, at http://127.0.0.1:<port>/metrics for tools like Prometheus. Leave empty to turn it off.
+config-metrics-port-placeholder = Off
+
+config-battery-saver = Battery saver
+config-battery-saver-description =
+    While a laptop is running on its battery, spawn popups less often, skip videos and leave the
+    wallpaper alone.
+config-battery-saver-label = Save battery when unplugged
+config-battery-saver-any = Any
+config-battery-saver-below = Only below this battery percentage
+config-popup-rate = % of the usual popup rate
+
+config-throttle = Throttle when overloaded
+config-throttle-description =
+    If Lewdware is using most of the CPU or can't render videos smoothly, spawn popups less often
+    and show images instead of videos until it catches up.
+config-throttle-label = Throttle when overloaded
+
+config-start-delay = Start delay
+config-start-delay-description =
+    Wait a while after launching before anything spawns, so you have time to get ready. The wait
+    doesn't count towards your goals.
+config-start-delay-seconds = seconds
+config-start-delay-countdown = Show a notification as it starts and shortly before it ends
+
+config-escalation = Escalation
+config-escalation-description =
+    Start gently and build up: spawn rates and popup limits are scaled from the first percentage
+    to the second over the session, then stay there. Time spent paused doesn't count. Works with
+    modes that follow the intensity dial, like the default ones.
+config-escalation-enabled = Build up over the session
+config-escalation-from = From
+config-escalation-to = % to
+config-escalation-over = % over
+config-escalation-minutes = minutes
+
+config-pacing = Pacing
+config-pacing-description =
+    Keep up, or else: failing a prompt or closing a popup straight away speeds the session up a
+    step, and answering a prompt right slows it back down. Works with modes that follow the
+    intensity dial, like the default ones.
+config-pacing-enabled = Pace the session by how I'm keeping up
+config-pacing-between = Keep spawn rates between
+config-pacing-and = % and
+config-pacing-percent = %
+config-pacing-step = Change by
+config-pacing-quick-close = Popups closed within
+config-pacing-seconds = seconds count as closed straight away
+
+config-prompt-failure = Failed prompts
+config-prompt-failure-description =
+    What happens when a prompt is answered wrong, on top of whatever the mode does. Set the
+    percentage to 100 to leave spawn rates alone.
+config-prompt-failure-popups = Pop up
+config-prompt-failure-popups-unit = extra images
+config-prompt-failure-boost = Scale spawn rates to
+config-prompt-failure-for = % for
+config-prompt-failure-seconds = seconds
+
+config-minigames = Minigames
+config-minigames-description =
+    Popups with a target moving around them, that only close once you've clicked it enough
+    times. It speeds up with every hit. Only shows up in modes that use it.
+config-minigames-label = Allow minigame popups
+
+config-hibernation = Hibernation
+config-hibernation-description =
+    Calm, then storm: every so often every popup is closed and the audio stops for a random
+    while, then the session wakes up with a burst of popups. Works best with modes that follow
+    the intensity dial, like the default ones.
+config-hibernation-enabled = Hibernate every so often
+config-hibernation-every = Every
+config-hibernation-for = minutes, for between
+config-hibernation-to = and
+config-hibernation-minutes = minutes
+config-hibernation-burst = Spawn rates on waking up, for a minute:
+
+config-schedule = Schedule
+config-schedule-description =
+    Only run at certain times. Outside them everything pauses, as it does while the screen is
+    locked, until the next time comes around. A time that ends before it starts runs on past
+    midnight.
+config-schedule-enabled = Only run at these times
+config-schedule-none = No times set, so it runs whenever it's launched.
+config-schedule-start = From
+config-schedule-end = Until
+config-schedule-add = Add time
+config-schedule-remove = Remove
+config-schedule-day-mon = Mon
+config-schedule-day-tue = Tue
+config-schedule-day-wed = Wed
+config-schedule-day-thu = Thu
+config-schedule-day-fri = Fri
+config-schedule-day-sat = Sat
+config-schedule-day-sun = Sun
+
+config-pause-while-recording = Screen recording
+config-pause-while-recording-description =
+    Pause and hide everything while the screen is being recorded or shared. This is best effort:
+    screen sharing through the desktop portal is detected on Linux, and common recorders and
+    Zoom's screen sharing on Windows, but not every way of capturing the screen can be.
+config-pause-while-recording-label = Pause while the screen is recorded or shared
+
+config-duck-other-apps = Other apps' audio
+config-duck-other-apps-description =
+    Turn other programs down, like music or a video in the browser, while the pack's audio or a
+    video with sound is playing, and back up once it stops. Works on Windows, and on Linux with
+    PulseAudio or PipeWire.
+config-duck-other-apps-label = Lower other apps while audio plays
+
+config-cursor-margin = Keep away from the cursor
+config-cursor-margin-description =
+    Never spawn a popup within this many pixels of the mouse cursor, so you don't click one by
+    accident the moment it appears. Popups placed by the mode at a fixed spot are left alone.
+    Leave empty to let popups go anywhere.
+config-cursor-margin-placeholder = Off
+config-cursor-margin-pixels = pixels
+
+config-pause-while-idle = Only while you're using the computer
+config-pause-while-idle-description =
+    Pause once there's been no typing or mouse movement for this many seconds, and carry on as soon
+    as there is again, so timers and goals only count time you're actually at the computer. Leave
+    empty to keep going regardless.
+config-pause-while-idle-placeholder = Off
+config-pause-while-idle-seconds = seconds
+
+config-language = Language
+config-language-description = The language of Lewdware's own text. Packs and modes aren't translated.
+config-language-system = System default
+
+config-goals = Goals
+config-goals-description =
+    Things to aim for in each session. You'll get a notification as you reach each one, and the
+    pack may have some goals of its own.
+config-goals-none = No goals set.
+config-goal-name = Goal
+config-goal-target = Target
+config-goal-add = Add goal
+config-goal-remove = Remove
+config-goal-stat-minutes = minutes
+config-goal-stat-popups = popups
+config-goal-stat-prompts = prompts answered
+config-goal-stat-choices = choices made
+
+config-prompt-history = Prompt history
+config-prompt-history-description =
+    Keep the prompts you're shown, and what you type into them, to look back on. They stay on
+    this computer unless you export them.
+config-prompt-history-off = Don't keep prompts
+config-prompt-history-hashed = Keep whether I typed each prompt correctly
+config-prompt-history-plaintext = Keep exactly what I typed
+config-prompt-history-count = { $count } prompts kept
+    .one = { $count } prompt kept
+config-prompt-history-export = Export…
+config-prompt-history-clear = Clear
+
+config-settings-password = Settings password
+config-settings-password-description =
+    Your blocked files, favorites and session stats are kept encrypted. With a password, they
+    can't be read without it: you'll need to type it here each time you open the settings.
+config-settings-password-locked =
+    Your blocked files, favorites and session stats are locked. Type the settings password to
+    unlock them, for here and for the next session you start.
+config-settings-password-placeholder = Password
+config-settings-password-new-placeholder = New password
+config-settings-password-unlock = Unlock
+config-settings-password-set = Set password
+config-settings-password-change = Change password
+config-settings-password-remove = Remove password
+config-settings-password-wrong = That's not the right password.
+
+config-last-session = Last session
+config-last-session-ended = Ended { $date }
+config-last-session-minutes = { $count } minutes
+    .one = { $count } minute
+config-last-session-popups = { $count } popups
+    .one = { $count } popup
+config-last-session-prompts = { $count } prompts answered
+    .one = { $count } prompt answered
+config-last-session-choices = { $count } choices made
+    .one = { $count } choice made
+config-last-session-uptime = { $count } minutes running
+    .one = { $count } minute running
+config-last-session-popups_closed = { $count } popups closed
+    .one = { $count } popup closed
+config-last-session-prompts_failed = { $count } prompts failed
+    .one = { $count } prompt failed
+config-last-session-links = { $count } links opened
+    .one = { $count } link opened
+config-last-session-goals = Reached { $reached } of { $count } goals
+    .one = Reached { $reached } of { $count } goal
+config-stats-totals = Over { $sessions } sessions:
+
+config-logs = Logs
+config-logs-description = Open the folder containing log files for all Lewdware apps.
+config-logs-open = Open logs folder
+
+config-privacy = Privacy
+config-privacy-description = Everything Lewdware accesses on your system. It never accesses your { $never }.
+config-privacy-keyboard = Keyboard input
+    .reason =
+        Key presses are watched across the whole system, only to notice the panic and mute
+        hotkeys. They aren't stored or sent anywhere.
+config-privacy-wallpaper = Desktop wallpaper
+    .reason =
+        Read when a session starts, changed if the mode sets a wallpaper, and restored when the
+        session ends.
+config-privacy-notifications = Notifications
+    .reason = Shown if the mode sends them.
+config-privacy-browser = Web browser
+    .reason = Opened if the mode opens a link.
+config-privacy-windows = Windows and monitors
+    .reason = Popups are opened on the monitors you've enabled. The tray icon controls the session.
+config-privacy-audio = Audio output
+    .reason =
+        Plays audio and the sound of videos. If you turn it on, other apps' volume is lowered
+        while it does, and put back afterwards.
+config-privacy-power = Screen lock and battery
+    .reason =
+        Checked regularly, to pause while the screen is locked and cut back while running on
+        battery.
+config-privacy-files = Files
+    .reason =
+        Reads the pack and modes you choose, and writes its config, logs and temporary files.
+        Packs are never modified by a session.
+config-privacy-network = Network
+    .reason = Only the config app, to check lewdware.net for updates.
+config-privacy-camera = camera
+config-privacy-microphone = microphone
+config-privacy-screen-recording = screen recording
+
+config-footprint = Left on disk
+config-footprint-description =
+    Files Lewdware has written that could show what a session was: wallpapers copied out of the
+    pack, video buffers, its logs and its lock file. Wiping overwrites them before deleting them.
+config-footprint-summary = { $count ->
+    [one] One file, { $size } MB
+   *[other] { $count } files, { $size } MB
+}
+config-footprint-empty = Nothing's been left behind.
+config-footprint-wallpaper = Wallpaper
+config-footprint-video-buffer = Video buffer
+config-footprint-log = Log
+config-footprint-lock = Lock file
+config-footprint-wipe = Wipe
+config-footprint-running = Stop Lewdware first. It can also be wiped from the tray as it quits.
+config-footprint-failed = { $count ->
+    [one] One file couldn't be wiped. See the logs for why.
+   *[other] { $count } files couldn't be wiped. See the logs for why.
+}
+config-incognito-label = Incognito
+config-incognito-description =
+    Don't write a log file, decode videos without buffer files, and wipe everything else a
+    session writes as soon as it ends.
+
+config-monitors = Monitors
+config-monitors-description = Select which monitors to show media on.
+config-monitor-primary = (primary)
+config-no-monitors = No monitors detected.
+
+## Pack & Mode
+
+config-on = On
+config-off = Off
+config-media-pack = Media Pack
+config-current-pack = Current pack
+config-no-pack-selected = No pack selected
+config-remove = Remove
+config-browse = Browse…
+config-recent-packs = Recent packs
+config-pack-url = Or stream one from the web
+config-pack-url-placeholder = https://example.com/pack.lwpack
+config-pack-url-open = Open
+config-pack-url-invalid = That isn't an http:// or https:// link.
+config-pack-url-error = Couldn't open the pack: { $error }
+config-pinned = Pinned
+config-pin = Pin
+config-forget-recent-pack = Remove from list
+config-recent-pack-error = Couldn't open { $name }: { $error }
+config-max-rating = Content rating
+config-max-rating-description =
+    Only show files up to this rating. Packs rate their own files, so what each level means is up
+    to the pack.
+config-max-rating-any = Everything
+config-excluded-tags = Excluded tags
+config-excluded-tags-description =
+    Never show files or texts with these tags, even if they have other tags you want to see.
+config-excluded-tags-choose = Choose a tag…
+config-excluded-tags-add = Exclude
+config-excluded-tags-remove = Show again
+config-blend-pack = Blend in another pack
+config-blend-pack-description =
+    Take some of the random files, notifications and prompts from a second pack, e.g. to ease into
+    a new one. Modes, goals and milestones still come from this pack.
+config-blend-pack-pick = Choose a pack…
+config-blend-pack-remove = Stop blending
+config-blend-pack-percent = { $percent }% from the blended pack
+config-blend-pack-error = Couldn't use that pack: { $error }
+config-favorites = Favorites
+config-favorites-description = How often the files you've added to this pack's favorites are shown.
+config-favorites-normal = As often as anything else
+config-favorites-boost = More often
+config-favorites-only = Only favorites
+config-favorites-count =
+    { $count ->
+        [one] { $count } favorite
+       *[other] { $count } favorites
+    }
+config-favorites-export = Export to a folder…
+config-favorites-exported =
+    { $count ->
+        [one] Exported { $count } file. Images are saved in the format the pack stores them in, so they may not have their original file type.
+       *[other] Exported { $count } files. Images are saved in the format the pack stores them in, so they may not have their original file type.
+    }
+config-favorites-export-error = Couldn't export favorites: { $error }
+config-mode = Mode
+config-upload-mode = + Upload
+config-remove-mode = Remove this mode
+config-uploaded-modes = Uploaded
+config-no-uploaded-modes = No uploaded modes.
+config-mode-options = Mode Options
+config-mode-filter = Mode
+config-pack-filter = Pack
+config-json-filter = JSON
+
+# Shown when a `lewd://add-pack` link on a website is clicked.
+config-pack-link-title = Add a pack from the web
+config-pack-link-confirm =
+    A link wants to add the pack "{ $file }", downloaded from { $host }. Packs can contain
+    anything, so only add packs from people you trust.
+config-pack-link-download = Download…
+config-pack-link-cancel = Cancel
+config-pack-link-invalid = That link isn't a valid pack link.
+config-pack-link-not-a-pack = The downloaded file isn't a Lewdware pack.
+config-pack-link-redirected = The download was sent on to { $host }, which isn't the site you agreed to download from.
+config-pack-link-downloading = Downloading { $file }… { $progress }
+config-pack-link-failed = Couldn't add the pack: { $error }
+
+## The pack preview
+
+config-preview = Preview
+config-shuffle = Shuffle
+config-no-preview = No preview available
+config-thumbnail = (thumbnail)
+config-preview-image = Image
+config-preview-video = Video
+config-no-matching-media = No images or videos match the selected tags.
+config-matching-files = { $count } matching files
+    .one = { $count } matching file
+config-file-type-image = { $count } images
+    .one = { $count } image
+config-file-type-video = { $count } videos
+    .one = { $count } video
+config-file-type-audio = { $count } audio files
+    .one = { $count } audio file
+config-size-gb = { $size } GB
+config-size-mb = { $size } MB
+config-no-tag = The pack has no tag called "{ $tag }"
+config-selection-no-image = None of the pack's images match these settings, so no images will be shown.
+config-selection-no-video = None of the pack's videos match these settings, so no videos will be shown.
+config-selection-no-audio = None of the pack's audio matches these settings, so no audio will be played.
+config-selection-no-notification = None of the pack's notifications match these settings, so no notifications will be shown.
+config-selection-no-prompt = None of the pack's prompts match these settings, so no prompts will be shown.
+config-selection-no-link = None of the pack's links match these settings, so no links will be opened.
+config-lewdware-not-found = Could not find lewdware binary
@@ -0,0 +1,80 @@
+## The tray icon.
+
+lewdware-tray-intensity = Intensity
+lewdware-tray-pause = Pause
+lewdware-tray-resume = Resume
+lewdware-tray-credits = Credits
+lewdware-tray-panic = Panic
+lewdware-tray-wipe = Quit and wipe
+lewdware-tray-status = Lewdware: { $status }
+lewdware-tray-paused = Paused
+lewdware-tray-paused-locked = Paused while the screen is locked
+lewdware-tray-paused-captured = Paused and hidden while the screen is being recorded or shared
+lewdware-tray-paused-schedule = Paused until the next scheduled time
+lewdware-tray-paused-idle = Paused until you're back
+lewdware-tray-hibernating = Hibernating…
+
+## Prompt popups.
+
+lewdware-prompt-heading = Repeat after me
+lewdware-prompt-heading-arithmetic = Solve this
+lewdware-prompt-heading-typing-test = Type this before time runs out
+lewdware-prompt-time-left = { $seconds }s left
+lewdware-prompt-submit = Submit
+
+## The countdown before a session starts, if the user has set a start delay.
+
+lewdware-start-delay = Lewdware is starting
+lewdware-start-delay-body =
+    { $minutes ->
+        [0]
+            { $seconds ->
+                [one] Popups start in 1 second.
+               *[other] Popups start in { $seconds } seconds.
+            }
+        [one] Popups start in 1 minute.
+       *[other] Popups start in { $minutes } minutes.
+    }
+
+## Goals and milestones.
+
+lewdware-goal-reached = Goal reached
+lewdware-milestone-reached = New content unlocked
+
+## Favorites.
+
+lewdware-favorite-added = Added to favorites
+lewdware-favorite-removed = Removed from favorites
+
+## The info overlay, shown over a popup by the info key.
+
+lewdware-info-tags = Tags: { $tags }
+lewdware-info-no-tags = No tags
+lewdware-info-size = { $width } × { $height }, shown at { $shown_width } × { $shown_height }
+
+## The credits window, opened from the tray.
+
+lewdware-credits-title = Credits
+lewdware-credits-creator = by { $creator }
+lewdware-credits-version = Version { $version }
+lewdware-credits-license = License: { $license }
+lewdware-credits-role = { $name } ({ $role })
+lewdware-credits-none = The pack doesn't credit anyone else.
+
+## Errors shown as notifications.
+
+# Shown in a notification, so this should stay on one line.
+lewdware-no-avif = This build of Lewdware can't decode AVIF images, which packs use for all their images. Install an FFmpeg build with an AV1 decoder (e.g. dav1d), or download the official Lewdware release.
+lewdware-media-error-title = Lewdware: { $kind }
+lewdware-media-error =
+    { $message }
+
+    Further errors like this won't be shown; see the logs for details.
+lewdware-media-error-index = The pack's index could not be read
+lewdware-media-error-io = The pack file could not be read
+lewdware-media-error-image = An image could not be decoded
+lewdware-media-error-video = A video could not be decoded
+lewdware-media-error-audio = An audio file could not be played
+lewdware-media-error-internal = Something went wrong loading media
+lewdware-media-error-corrupted = Some media in the pack is corrupted
+lewdware-media-error-no-avif = Images can't be shown
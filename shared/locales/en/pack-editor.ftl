@@ -0,0 +1,50 @@
+## The pack editor's backend.
+
+pack-editor-pack-filter = Lewdware Pack
+pack-editor-text-filter = Text
+pack-editor-create-pack = Create new pack
+pack-editor-open-pack = Open pack
+pack-editor-save-pack-as = Save pack as
+pack-editor-export-manifest = Export manifest
+pack-editor-import-texts = Import notifications, prompts or links
+pack-editor-select-files = Select files
+pack-editor-select-folder = Select folder
+pack-editor-new-pack = New Pack
+pack-editor-no-pack = No pack open
+pack-editor-read-only = This pack is read-only. Use Save As to save a copy somewhere else.
+
+## Undo and redo.
+
+pack-editor-add-files =
+    { $count ->
+        [one] Add file
+       *[other] Add { $count } files
+    }
+pack-editor-delete-files =
+    { $count ->
+        [one] Delete file
+       *[other] Delete { $count } files
+    }
+pack-editor-reencode-files =
+    { $count ->
+        [one] Re-encode file
+       *[other] Re-encode { $count } files
+    }
+pack-editor-add-tag = Add tag "{ $tag }"
+pack-editor-remove-tag = Remove tag "{ $tag }"
+pack-editor-rename-file = Rename file
+pack-editor-edit-details = Edit pack details
+pack-editor-import-notifications = Import notifications
+pack-editor-import-prompts = Import prompts
+pack-editor-import-links = Import links
+
+## Encoding files.
+
+pack-editor-skipped = Duplicate (skipped)
+pack-editor-encode-error = Encode error: { $error }
+pack-editor-pack-error = Pack error: { $error }
+pack-editor-hash-error = Hash error: { $error }
+pack-editor-encoded-image = Image
+pack-editor-encoded-image-codec = Image ({ $codec })
+pack-editor-encoded-video = Video ({ $codec })
+pack-editor-encoded-audio = Audio ({ $codec })
@@ -0,0 +1,233 @@
+//! Helper processes (ffmpeg, the engine's video decode helper) started through here are kept
+//! track of, so quitting can stop whichever are still running rather than leaving them behind,
+//! encoding or decoding for nobody.
+
+use std::{
+    io::{self, Read},
+    mem,
+    process::{Child, ChildStderr, ChildStdin, ChildStdout, Command, ExitStatus, Output, Stdio},
+    sync::Mutex,
+    thread,
+    time::{Duration, Instant},
+};
+
+/// How often a process is checked on while waiting for it to exit.
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+static CHILDREN: Children = Children::new();
+
+/// Starts `command` as a tracked process. Fails once [`shutdown`] has been called.
+pub fn spawn(command: &mut Command) -> io::Result<TrackedChild> {
+    CHILDREN.spawn(command)
+}
+
+/// Like [`Command::output`], but tracked.
+pub fn output(command: &mut Command) -> io::Result<Output> {
+    let child = spawn(
+        command
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped()),
+    )?;
+    child.wait_with_output()
+}
+
+/// Kills every tracked process that's still running, and waits up to `timeout` for them to
+/// exit. Nothing can be spawned afterwards, so a task that's still going can't start another.
+pub fn shutdown(timeout: Duration) {
+    CHILDREN.shutdown(timeout);
+}
+
+struct Children {
+    state: Mutex<State>,
+}
+
+struct State {
+    next_id: u64,
+    running: Vec<(u64, Child)>,
+    shut_down: bool,
+}
+
+impl Children {
+    const fn new() -> Self {
+        Self {
+            state: Mutex::new(State {
+                next_id: 0,
+                running: Vec::new(),
+                shut_down: false,
+            }),
+        }
+    }
+
+    fn spawn(&'static self, command: &mut Command) -> io::Result<TrackedChild> {
+        // Spawned with the lock held, so a shutdown can't slip in between and miss it.
+        let mut state = self.state.lock().unwrap();
+        if state.shut_down {
+            return Err(stopped());
+        }
+
+        let mut child = command.spawn()?;
+        let id = state.next_id;
+        state.next_id += 1;
+
+        let tracked = TrackedChild {
+            id,
+            children: self,
+            stdin: child.stdin.take(),
+            stdout: child.stdout.take(),
+            stderr: child.stderr.take(),
+        };
+        state.running.push((id, child));
+
+        Ok(tracked)
+    }
+
+    fn shutdown(&self, timeout: Duration) {
+        let mut running = {
+            let mut state = self.state.lock().unwrap();
+            state.shut_down = true;
+            mem::take(&mut state.running)
+        };
+        if running.is_empty() {
+            return;
+        }
+
+        tracing::info!("Stopping {} helper processes", running.len());
+        for (_, child) in &mut running {
+            let _ = child.kill();
+        }
+
+        let deadline = Instant::now() + timeout;
+        for (_, child) in &mut running {
+            loop {
+                match child.try_wait() {
+                    Ok(None) if Instant::now() < deadline => thread::sleep(POLL_INTERVAL),
+                    Ok(None) => {
+                        tracing::warn!("Helper process {} didn't exit in time", child.id());
+                        break;
+                    }
+                    Ok(Some(_)) | Err(_) => break,
+                }
+            }
+        }
+    }
+
+    fn take(&self, id: u64) -> Option<Child> {
+        let mut state = self.state.lock().unwrap();
+        let index = state.running.iter().position(|(other, _)| *other == id)?;
+        Some(state.running.swap_remove(index).1)
+    }
+}
+
+/// A process started by [`spawn`]. It's killed if it's dropped while still running, and by
+/// [`shutdown`], after which waiting for it fails with [`io::ErrorKind::Interrupted`].
+pub struct TrackedChild {
+    id: u64,
+    children: &'static Children,
+    pub stdin: Option<ChildStdin>,
+    pub stdout: Option<ChildStdout>,
+    pub stderr: Option<ChildStderr>,
+}
+
+impl TrackedChild {
+    /// Like [`Child::wait`], closing stdin first. The process stays tracked until it exits, so
+    /// a shutdown can still stop it.
+    pub fn wait(&mut self) -> io::Result<ExitStatus> {
+        drop(self.stdin.take());
+
+        loop {
+            {
+                let mut state = self.children.state.lock().unwrap();
+                let Some(index) = state.running.iter().position(|(id, _)| *id == self.id) else {
+                    return Err(stopped());
+                };
+                if let Some(status) = state.running[index].1.try_wait()? {
+                    state.running.swap_remove(index);
+                    return Ok(status);
+                }
+            }
+            thread::sleep(POLL_INTERVAL);
+        }
+    }
+
+    /// Like [`Child::wait_with_output`].
+    pub fn wait_with_output(mut self) -> io::Result<Output> {
+        drop(self.stdin.take());
+
+        let stderr = self.stderr.take().map(|mut stderr| {
+            thread::spawn(move || {
+                let mut buf = Vec::new();
+                stderr.read_to_end(&mut buf).map(|_| buf)
+            })
+        });
+        let mut stdout = Vec::new();
+        if let Some(out) = &mut self.stdout {
+            out.read_to_end(&mut stdout)?;
+        }
+        let stderr = match stderr {
+            Some(handle) => handle.join().unwrap_or_else(|_| Ok(Vec::new()))?,
+            None => Vec::new(),
+        };
+
+        let status = self.wait()?;
+        Ok(Output {
+            status,
+            stdout,
+            stderr,
+        })
+    }
+
+    /// Kills the process, if it's still running, and waits for it to exit.
+    pub fn kill(&mut self) {
+        if let Some(mut child) = self.children.take(self.id) {
+            let _ = child.kill();
+            let _ = child.wait();
+        }
+    }
+}
+
+impl Drop for TrackedChild {
+    fn drop(&mut self) {
+        self.kill();
+    }
+}
+
+fn stopped() -> io::Error {
+    io::Error::new(io::ErrorKind::Interrupted, "stopped while shutting down")
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn collects_output() {
+        static CHILDREN: Children = Children::new();
+
+        let child = CHILDREN
+            .spawn(
+                Command::new("sh")
+                    .args(["-c", "echo out; echo err >&2"])
+                    .stdout(Stdio::piped())
+                    .stderr(Stdio::piped()),
+            )
+            .unwrap();
+        let output = child.wait_with_output().unwrap();
+        assert!(output.status.success());
+        assert_eq!(output.stdout, b"out\n");
+        assert_eq!(output.stderr, b"err\n");
+    }
+
+    #[test]
+    fn shutdown_stops_running_children() {
+        static CHILDREN: Children = Children::new();
+
+        let mut child = CHILDREN.spawn(Command::new("sleep").arg("30")).unwrap();
+        let started = Instant::now();
+        CHILDREN.shutdown(Duration::from_secs(5));
+        assert!(started.elapsed() < Duration::from_secs(5));
+
+        assert_eq!(child.wait().unwrap_err().kind(), io::ErrorKind::Interrupted);
+        assert!(CHILDREN.spawn(&mut Command::new("true")).is_err());
+    }
+}
@@ -1,5 +1,10 @@
+use std::collections::BTreeSet;
+
 use anyhow::Result;
-use rusqlite::{OptionalExtension, params};
+use rusqlite::{OptionalExtension, params, params_from_iter};
+use serde::Serialize;
+
+use crate::{rating, user_config::TagMatchMode};
 
 pub fn migrate(db: &rusqlite::Connection) -> Result<()> {
     db.execute(
@@ -42,4 +47,310 @@ pub fn migrate(db: &rusqlite::Connection) -> Result<()> {
     Ok(())
 }
 
-const MIGRATIONS: [&str; 1] = [include_str!("migrations/0001_init_schema.sql")];
+const MIGRATIONS: [&str; 14] = [
+    include_str!("migrations/0001_init_schema.sql"),
+    include_str!("migrations/0002_media_checksum.sql"),
+    include_str!("migrations/0003_image_codec.sql"),
+    include_str!("migrations/0004_pack_stats.sql"),
+    include_str!("migrations/0005_texts.sql"),
+    include_str!("migrations/0006_media_sidecar.sql"),
+    include_str!("migrations/0007_media_only_after.sql"),
+    include_str!("migrations/0008_media_popup_set.sql"),
+    include_str!("migrations/0009_text_locale.sql"),
+    include_str!("migrations/0010_media_rating.sql"),
+    include_str!("migrations/0011_media_added_at.sql"),
+    include_str!("migrations/0012_text_theme.sql"),
+    include_str!("migrations/0013_video_container.sql"),
+    include_str!("migrations/0014_media_encode_params.sql"),
+];
+
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct TypeStats {
+    pub file_type: String,
+    pub count: u64,
+    pub bytes: u64,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct TagStats {
+    pub tag: String,
+    pub count: u64,
+    pub bytes: u64,
+}
+
+/// What a pack is made up of, by media type and by tag.
+#[derive(Serialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct PackStats {
+    pub types: Vec<TypeStats>,
+    /// Largest tags first.
+    pub tags: Vec<TagStats>,
+}
+
+/// Reads the totals kept up to date by triggers on the index (see `0004_pack_stats.sql`), which
+/// is much quicker than going over the media table for large packs.
+pub fn read_stats(db: &rusqlite::Connection) -> Result<PackStats> {
+    let types = db
+        .prepare(
+            "SELECT file_type, count, bytes FROM type_stats WHERE count > 0 ORDER BY file_type",
+        )?
+        .query_map([], |row| {
+            Ok(TypeStats {
+                file_type: row.get(0)?,
+                count: row.get(1)?,
+                bytes: row.get(2)?,
+            })
+        })?
+        .collect::<rusqlite::Result<_>>()?;
+
+    let tags = db
+        .prepare(
+            "SELECT tags.name, tag_stats.count, tag_stats.bytes
+            FROM tag_stats JOIN tags ON tags.id = tag_stats.tag_id
+            WHERE tag_stats.count > 0
+            ORDER BY tag_stats.bytes DESC",
+        )?
+        .query_map([], |row| {
+            Ok(TagStats {
+                tag: row.get(0)?,
+                count: row.get(1)?,
+                bytes: row.get(2)?,
+            })
+        })?
+        .collect::<rusqlite::Result<_>>()?;
+
+    Ok(PackStats { types, tags })
+}
+
+/// How much of each media type and text kind in a pack a selection of tags leaves to show.
+#[derive(Serialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct SelectionCounts {
+    pub media: Vec<KindCounts>,
+    pub texts: Vec<KindCounts>,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct KindCounts {
+    /// The media type (`image`, `video` or `audio`) or text kind (`notification`, `prompt` or
+    /// `link`).
+    pub kind: String,
+    pub total: u64,
+    pub matching: u64,
+}
+
+/// What to count in [`count_selection`], the same filters the player applies to every query.
+#[derive(Debug, Clone, Copy)]
+pub struct Selection<'a> {
+    /// `None` for everything. Unlike the preview, tags the pack doesn't have just match nothing.
+    pub tags: Option<&'a [String]>,
+    pub tag_match_mode: TagMatchMode,
+    pub max_rating: Option<u32>,
+    /// See [`rating::tags_above`].
+    pub tags_above_rating: &'a [String],
+    pub excluded_tags: &'a [String],
+}
+
+/// Counts the media of each type and texts of each kind in the pack, and how many of them
+/// `selection` lets through, so the config app can point out when it leaves none of something the
+/// pack has (e.g. no notifications). Kinds the pack has none of at all are left out.
+pub fn count_selection(
+    db: &rusqlite::Connection,
+    selection: &Selection,
+) -> Result<SelectionCounts> {
+    let mut media_filter = Vec::new();
+    let mut media_params: Vec<&String> = Vec::new();
+    let mut text_filter = Vec::new();
+    let mut text_params: Vec<&String> = Vec::new();
+
+    if let Some(tags) = selection.tags {
+        let tags: BTreeSet<&String> = tags.iter().collect();
+        let matching = match selection.tag_match_mode {
+            TagMatchMode::Any => "> 0".to_string(),
+            TagMatchMode::All => format!("= {}", tags.len()),
+        };
+        let vars = vec!["?"; tags.len()].join(", ");
+        media_filter.push(format!(
+            "(SELECT COUNT(DISTINCT tags.name) FROM media_tags JOIN tags ON tags.id = media_tags.tag_id
+                WHERE media_tags.media_id = media.id AND tags.name IN ({vars})) {matching}"
+        ));
+        text_filter.push(format!(
+            "(SELECT COUNT(DISTINCT value) FROM json_each(texts.tags) WHERE value IN ({vars})) \
+             {matching}"
+        ));
+        media_params.extend(&tags);
+        text_params.extend(&tags);
+    }
+
+    if let Some(max_rating) = selection.max_rating {
+        media_filter.push(rating::media_filter(
+            max_rating,
+            selection.tags_above_rating,
+        ));
+        media_params.extend(selection.tags_above_rating);
+        if let Some(filter) = rating::text_filter(selection.tags_above_rating) {
+            text_filter.push(filter);
+            text_params.extend(selection.tags_above_rating);
+        }
+    }
+
+    if !selection.excluded_tags.is_empty() {
+        media_filter.push(format!(
+            "media.id NOT IN (SELECT media_id FROM media_tags
+                JOIN tags ON tags.id = media_tags.tag_id WHERE tags.name IN ({}))",
+            vec!["?"; selection.excluded_tags.len()].join(", ")
+        ));
+        media_params.extend(selection.excluded_tags);
+    }
+    if let Some(filter) = rating::text_filter(selection.excluded_tags) {
+        text_filter.push(filter);
+        text_params.extend(selection.excluded_tags);
+    }
+
+    let count = |sql: String, params: Vec<&String>| -> Result<Vec<KindCounts>> {
+        Ok(db
+            .prepare(&sql)?
+            .query_map(params_from_iter(params), |row| {
+                Ok(KindCounts {
+                    kind: row.get(0)?,
+                    total: row.get(1)?,
+                    matching: row.get(2)?,
+                })
+            })?
+            .collect::<rusqlite::Result<_>>()?)
+    };
+
+    let condition = |filter: Vec<String>| {
+        if filter.is_empty() {
+            "1".to_string()
+        } else {
+            filter.join(" AND ")
+        }
+    };
+
+    Ok(SelectionCounts {
+        media: count(
+            format!(
+                "SELECT file_type, COUNT(*), SUM({}) FROM media GROUP BY file_type ORDER BY file_type",
+                condition(media_filter)
+            ),
+            media_params,
+        )?,
+        texts: count(
+            format!(
+                "SELECT kind, COUNT(*), SUM({}) FROM texts GROUP BY kind ORDER BY kind",
+                condition(text_filter)
+            ),
+            text_params,
+        )?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stats_follow_media_and_tag_changes() {
+        let db = rusqlite::Connection::open_in_memory().unwrap();
+        migrate(&db).unwrap();
+
+        db.execute_batch(
+            "INSERT INTO media (id, file_name, file_type, length, hash) VALUES
+                (1, 'a', 'image', 100, x'01'),
+                (2, 'b', 'video', 1000, x'02');
+            INSERT INTO tags (id, name) VALUES (1, 'tag');
+            INSERT INTO media_tags (media_id, tag_id) VALUES (1, 1), (2, 1);
+            UPDATE media SET length = 500 WHERE id = 2;
+            DELETE FROM media WHERE id = 1;",
+        )
+        .unwrap();
+
+        let stats = read_stats(&db).unwrap();
+        assert_eq!(
+            stats.types,
+            [TypeStats {
+                file_type: "video".to_string(),
+                count: 1,
+                bytes: 500,
+            }]
+        );
+        assert_eq!(
+            stats.tags,
+            [TagStats {
+                tag: "tag".to_string(),
+                count: 1,
+                bytes: 500,
+            }]
+        );
+    }
+
+    #[test]
+    fn counts_what_a_selection_leaves() {
+        let db = rusqlite::Connection::open_in_memory().unwrap();
+        migrate(&db).unwrap();
+
+        db.execute_batch(
+            "INSERT INTO media (id, file_name, file_type, hash) VALUES
+                (1, 'a', 'image', x'01'),
+                (2, 'b', 'image', x'02'),
+                (3, 'c', 'video', x'03');
+            INSERT INTO tags (id, name) VALUES (1, 'a'), (2, 'b');
+            INSERT INTO media_tags (media_id, tag_id) VALUES (1, 1), (1, 2), (2, 1), (3, 2);
+            INSERT INTO texts (kind, text, tags) VALUES
+                ('notification', 'Both', '[\"a\", \"b\"]'),
+                ('prompt', 'Just b', '[\"b\"]');",
+        )
+        .unwrap();
+
+        let counts = |tags: &[&str], tag_match_mode, excluded_tags: &[&str]| {
+            let tags: Vec<String> = tags.iter().map(|tag| tag.to_string()).collect();
+            let excluded_tags: Vec<String> =
+                excluded_tags.iter().map(|tag| tag.to_string()).collect();
+            let counts = count_selection(
+                &db,
+                &Selection {
+                    tags: Some(&tags),
+                    tag_match_mode,
+                    max_rating: None,
+                    tags_above_rating: &[],
+                    excluded_tags: &excluded_tags,
+                },
+            )
+            .unwrap();
+            counts
+                .media
+                .iter()
+                .chain(&counts.texts)
+                .map(|kind| (kind.kind.clone(), kind.total, kind.matching))
+                .collect::<Vec<_>>()
+        };
+        let kind = |kind: &str, total, matching| (kind.to_string(), total, matching);
+
+        assert_eq!(
+            counts(&["a"], TagMatchMode::Any, &[]),
+            [
+                kind("image", 2, 2),
+                kind("video", 1, 0),
+                kind("notification", 1, 1),
+                kind("prompt", 1, 0),
+            ]
+        );
+        assert_eq!(
+            counts(&["a", "b"], TagMatchMode::All, &[]),
+            [
+                kind("image", 2, 1),
+                kind("video", 1, 0),
+                kind("notification", 1, 1),
+                kind("prompt", 1, 0),
+            ]
+        );
+        assert_eq!(
+            counts(&["a", "missing"], TagMatchMode::Any, &["b"]),
+            [
+                kind("image", 2, 1),
+                kind("video", 1, 0),
+                kind("notification", 1, 0),
+                kind("prompt", 1, 0),
+            ]
+        );
+    }
+}
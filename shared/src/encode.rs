@@ -54,6 +54,108 @@ impl FileType {
     }
 }
 
+/// The format images are encoded to when they're added to a pack. Stored in the `codec` column of
+/// the media table, where NULL means AVIF (the only format before this was configurable).
+#[derive(Clone, Copy, Serialize, Deserialize, Default, PartialEq, Eq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum ImageCodec {
+    /// Smallest files, but slow to encode.
+    #[default]
+    Avif,
+    /// Much faster to encode than AVIF, at the cost of larger files.
+    Webp,
+    /// JPEG XL. Fast to encode with good compression, but players need an ffmpeg build with
+    /// libjxl to show it.
+    Jxl,
+}
+
+impl ImageCodec {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ImageCodec::Avif => "avif",
+            ImageCodec::Webp => "webp",
+            ImageCodec::Jxl => "jxl",
+        }
+    }
+
+    /// The file extension for images in this format.
+    pub fn extension(&self) -> &'static str {
+        self.as_str()
+    }
+
+    pub fn mime_type(&self) -> &'static str {
+        match self {
+            ImageCodec::Avif => "image/avif",
+            ImageCodec::Webp => "image/webp",
+            ImageCodec::Jxl => "image/jxl",
+        }
+    }
+}
+
+impl std::str::FromStr for ImageCodec {
+    type Err = InvalidFileType;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "avif" => Ok(ImageCodec::Avif),
+            "webp" => Ok(ImageCodec::Webp),
+            "jxl" => Ok(ImageCodec::Jxl),
+            _ => Err(InvalidFileType()),
+        }
+    }
+}
+
+/// The container videos are written to when they're added to a pack. Stored in the `container`
+/// column of the media table, where NULL means MP4 (the only container before this was
+/// configurable).
+#[derive(Clone, Copy, Serialize, Deserialize, Default, PartialEq, Eq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum VideoContainer {
+    /// H.264, which can be hardware encoded, and plays anywhere.
+    #[default]
+    Mp4,
+    /// VP9, which is smaller than H.264 at the same quality but always encoded in software.
+    Webm,
+}
+
+impl VideoContainer {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            VideoContainer::Mp4 => "mp4",
+            VideoContainer::Webm => "webm",
+        }
+    }
+
+    /// The file extension for videos in this container.
+    pub fn extension(&self) -> &'static str {
+        self.as_str()
+    }
+
+    /// The name of ffmpeg's muxer and demuxer for the container.
+    pub fn ffmpeg_format(&self) -> &'static str {
+        self.as_str()
+    }
+
+    pub fn mime_type(&self) -> &'static str {
+        match self {
+            VideoContainer::Mp4 => "video/mp4",
+            VideoContainer::Webm => "video/webm",
+        }
+    }
+}
+
+impl std::str::FromStr for VideoContainer {
+    type Err = InvalidFileType;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "mp4" => Ok(VideoContainer::Mp4),
+            "webm" => Ok(VideoContainer::Webm),
+            _ => Err(InvalidFileType()),
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct InvalidFileType();
 
@@ -0,0 +1,310 @@
+//! Copying media back out of a pack, in the format it's stored in. Packs don't keep the files
+//! they were made from, so images come out in whatever format the pack editor encoded them to
+//! (see [`ImageCodec`](crate::encode::ImageCodec)), videos as MP4 and audio as Opus, under their
+//! original names. `lw pack extract` copies out a whole pack, with sidecars and text files that
+//! add it back to the pack editor the way it was.
+
+use std::{
+    collections::BTreeSet,
+    io::{self, Read, Seek, SeekFrom},
+    path::{Path, PathBuf},
+};
+
+use rusqlite::Connection;
+
+use crate::{
+    pack_index::{COLUMNS, StoredMedia, all_stored_media, stored_media},
+    sidecar::{OnlyAfter, Sidecar},
+};
+
+/// A file in the pack, and where its data is.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Entry {
+    pub id: u64,
+    /// The name it was added to the pack under.
+    pub file_name: String,
+    /// The name to extract it as: the name it was added under, with the extension of the format
+    /// it's stored in (see [`StoredMedia::extension`]). Always a bare file name, so it can be
+    /// joined onto the folder being extracted to; see [`extracted_name`].
+    pub name: String,
+    pub offset: u64,
+    pub length: u64,
+    /// The BLAKE3 hash of the data, if the pack editor that added it recorded one.
+    pub checksum: Option<Vec<u8>>,
+}
+
+/// Every file in the pack that has data in it, ordered by id.
+pub fn entries(db: &Connection) -> rusqlite::Result<Vec<Entry>> {
+    Ok(all_stored_media(db)?
+        .into_iter()
+        .filter_map(Entry::new)
+        .collect())
+}
+
+/// The files in the pack with the given content hashes (as hex, like the favorites and blocklist
+/// store them), ordered by id. Files with no data in the pack are left out.
+pub fn entries_with_hashes(
+    db: &Connection,
+    hashes: &BTreeSet<String>,
+) -> rusqlite::Result<Vec<Entry>> {
+    let mut stmt = db.prepare(&format!(
+        "SELECT {COLUMNS}, lower(hex(hash)) AS hash FROM media ORDER BY id"
+    ))?;
+
+    let mut entries = Vec::new();
+    let mut rows = stmt.query([])?;
+    while let Some(row) = rows.next()? {
+        if hashes.contains(&row.get::<_, String>("hash")?) {
+            entries.extend(Entry::new(StoredMedia::from_row(row)?));
+        }
+    }
+
+    Ok(entries)
+}
+
+/// The file in the pack with this id, unless there's no such file or it has no data in the pack.
+pub fn entry(db: &Connection, id: u64) -> rusqlite::Result<Option<Entry>> {
+    Ok(stored_media(db, id)?.and_then(Entry::new))
+}
+
+impl Entry {
+    /// `None` if the file has no data in the pack.
+    fn new(media: StoredMedia) -> Option<Self> {
+        let (offset, length) = media.range()?;
+
+        Some(Self {
+            id: media.id,
+            name: extracted_name(&media),
+            offset,
+            length,
+            checksum: media.checksum,
+            file_name: media.file_name,
+        })
+    }
+}
+
+/// The file's name with the extension of the format it's stored in.
+///
+/// Packs aren't trusted, so only the last component of the name is kept: anything that could
+/// point outside the folder being extracted to (an absolute path, `..`, nothing at all) falls
+/// back to the file's id.
+fn extracted_name(media: &StoredMedia) -> String {
+    let name = Path::new(&media.file_name);
+    let name = match name.file_name() {
+        Some(file_name) if !name.is_absolute() => Path::new(file_name).to_path_buf(),
+        _ => PathBuf::from(media.id.to_string()),
+    };
+
+    let Some(extension) = media.extension() else {
+        return name.to_string_lossy().into_owned();
+    };
+
+    name.with_extension(extension)
+        .to_string_lossy()
+        .into_owned()
+}
+
+/// The settings the file with this id was added with, as a sidecar that adds it back the same way.
+/// `only_after` still names the file it waits for by the name it was added under.
+pub fn sidecar(db: &Connection, id: u64) -> rusqlite::Result<Sidecar> {
+    db.query_row(
+        "SELECT caption, category, weight, cooldown, after_file, after_tag, after_count,
+             popup_set, rating,
+             (SELECT json_group_array(tags.name) FROM media_tags
+                 JOIN tags ON tags.id = media_tags.tag_id
+                 WHERE media_tags.media_id = media.id) AS tag_names
+         FROM media WHERE id = ?",
+        [id],
+        |row| {
+            let tags: String = row.get("tag_names")?;
+            let only_after = match row.get::<_, Option<u32>>("after_count")? {
+                Some(times) => Some(OnlyAfter {
+                    file: row.get("after_file")?,
+                    tag: row.get("after_tag")?,
+                    times,
+                }),
+                None => None,
+            };
+
+            Ok(Sidecar {
+                tags: serde_json::from_str(&tags).unwrap_or_default(),
+                category: row.get("category")?,
+                caption: row.get("caption")?,
+                weight: row.get("weight")?,
+                cooldown: row.get("cooldown")?,
+                only_after,
+                set: row.get("popup_set")?,
+                rating: row.get("rating")?,
+            })
+        },
+    )
+}
+
+/// Reads the entry's data from the pack file.
+pub fn read_entry<F: Read + Seek>(pack: &mut F, entry: &Entry) -> io::Result<Vec<u8>> {
+    // The range comes from the pack's index, so check it fits in the file before allocating
+    // anything for it.
+    let size = pack.seek(SeekFrom::End(0))?;
+    if entry
+        .offset
+        .checked_add(entry.length)
+        .is_none_or(|end| end > size)
+    {
+        return Err(io::ErrorKind::UnexpectedEof.into());
+    }
+
+    pack.seek(SeekFrom::Start(entry.offset))?;
+    let mut data = Vec::with_capacity(entry.length as usize);
+    pack.take(entry.length).read_to_end(&mut data)?;
+
+    if data.len() as u64 != entry.length {
+        return Err(io::ErrorKind::UnexpectedEof.into());
+    }
+
+    Ok(data)
+}
+
+/// A path in `dir` for a file called `name` that doesn't exist yet, adding " (2)", " (3)" and so
+/// on before the extension if it has to. Packs can have several files with the same name.
+///
+/// `name` should be a bare file name like [`Entry::name`]; any folders in it are dropped, so the
+/// path is always directly in `dir`.
+pub fn unused_path(dir: &Path, name: &str) -> PathBuf {
+    let name = Path::new(name)
+        .file_name()
+        .map(|name| name.to_string_lossy())
+        .unwrap_or_default();
+    let path = dir.join(name.as_ref());
+    if !path.exists() {
+        return path;
+    }
+
+    let name = Path::new(name.as_ref());
+    let stem = name.file_stem().unwrap_or_default().to_string_lossy();
+    let extension = name
+        .extension()
+        .map(|extension| format!(".{}", extension.to_string_lossy()))
+        .unwrap_or_default();
+
+    (2..)
+        .map(|n| dir.join(format!("{stem} ({n}){extension}")))
+        .find(|path| !path.exists())
+        .unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::db::migrate;
+
+    #[test]
+    fn finds_entries_by_hash() {
+        let db = Connection::open_in_memory().unwrap();
+        migrate(&db).unwrap();
+
+        db.execute_batch(
+            "INSERT INTO media (file_name, file_type, \"offset\", length, hash, codec, container)
+                 VALUES ('a.png', 'image', 10, 3, x'0a', NULL, NULL),
+                        ('b.png', 'image', 13, 4, x'0b', 'webp', NULL),
+                        ('c.mov', 'video', 17, 5, x'0c', NULL, NULL),
+                        ('d.png', 'image', NULL, NULL, x'0d', NULL, NULL),
+                        ('e.mkv', 'video', 22, 6, x'0e', NULL, 'webm');",
+        )
+        .unwrap();
+
+        let hashes = ["0b", "0c", "0d", "0e"].map(String::from).into();
+        let entries = entries_with_hashes(&db, &hashes).unwrap();
+        let names: Vec<_> = entries.iter().map(|entry| entry.name.as_str()).collect();
+        assert_eq!(names, ["b.webp", "c.mp4", "e.webm"]);
+
+        let mut pack = Cursor::new((0..32).collect::<Vec<u8>>());
+        assert_eq!(
+            read_entry(&mut pack, &entries[0]).unwrap(),
+            [13, 14, 15, 16]
+        );
+
+        assert_eq!(entry(&db, 2).unwrap().as_ref(), Some(&entries[0]));
+        assert_eq!(entry(&db, 4).unwrap(), None);
+        assert_eq!(super::entries(&db).unwrap().len(), 4);
+    }
+
+    #[test]
+    fn keeps_names_inside_the_folder() {
+        let db = Connection::open_in_memory().unwrap();
+        migrate(&db).unwrap();
+
+        db.execute_batch(
+            "INSERT INTO media (file_name, file_type, \"offset\", length, hash, codec)
+                 VALUES ('../../a.png', 'image', 0, 1, x'0a', 'webp'),
+                        ('/etc/b.png', 'image', 0, 1, x'0b', 'webp'),
+                        ('..', 'image', 0, 1, x'0c', 'webp'),
+                        ('', 'audio', 0, 1, x'0d', NULL),
+                        ('big.png', 'image', 1, 9223372036854775807, x'0e', 'webp');",
+        )
+        .unwrap();
+
+        let entries = super::entries(&db).unwrap();
+        let names: Vec<_> = entries.iter().map(|entry| entry.name.as_str()).collect();
+        assert_eq!(names, ["a.webp", "2.webp", "3.webp", "4.opus", "big.webp"]);
+
+        let mut pack = Cursor::new(vec![0; 8]);
+        assert_eq!(
+            read_entry(&mut pack, &entries[4]).unwrap_err().kind(),
+            io::ErrorKind::UnexpectedEof
+        );
+
+        let dir = tempfile::tempdir().unwrap();
+        assert_eq!(
+            unused_path(dir.path(), "../a.webp"),
+            dir.path().join("a.webp")
+        );
+    }
+
+    #[test]
+    fn rebuilds_sidecars() {
+        let db = Connection::open_in_memory().unwrap();
+        migrate(&db).unwrap();
+
+        db.execute_batch(
+            "INSERT INTO media (file_name, file_type, hash, weight, after_tag, after_count, rating)
+                 VALUES ('a.png', 'image', x'0a', 2.0, 'intro', 3, 1),
+                        ('b.png', 'image', x'0b', NULL, NULL, NULL, NULL);
+             INSERT INTO tags (name) VALUES ('outdoors'), ('intro');
+             INSERT INTO media_tags (media_id, tag_id) VALUES (1, 1), (1, 2);",
+        )
+        .unwrap();
+
+        let mut a = sidecar(&db, 1).unwrap();
+        a.tags.sort();
+        assert_eq!(
+            a,
+            Sidecar {
+                tags: vec!["intro".to_string(), "outdoors".to_string()],
+                weight: Some(2.0),
+                only_after: Some(OnlyAfter {
+                    file: None,
+                    tag: Some("intro".to_string()),
+                    times: 3,
+                }),
+                rating: Some(1),
+                ..Sidecar::default()
+            }
+        );
+        assert_eq!(sidecar(&db, 2).unwrap(), Sidecar::default());
+    }
+
+    #[test]
+    fn numbers_clashing_names() {
+        let dir = tempfile::tempdir().unwrap();
+        assert_eq!(unused_path(dir.path(), "a.avif"), dir.path().join("a.avif"));
+
+        std::fs::write(dir.path().join("a.avif"), []).unwrap();
+        std::fs::write(dir.path().join("a (2).avif"), []).unwrap();
+        assert_eq!(
+            unused_path(dir.path(), "a.avif"),
+            dir.path().join("a (3).avif")
+        );
+    }
+}
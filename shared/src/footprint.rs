@@ -0,0 +1,201 @@
+//! What a session leaves on disk: the files copied out of the pack into the player's temp dir
+//! (wallpapers, and the buffers isolated video decoding goes through), its logs and its lock file.
+//! The privacy wipe lists them and overwrites them before deleting them, either from the tray as
+//! a session ends or from the config app afterwards.
+
+use std::{
+    fs::{self, File, OpenOptions, TryLockError},
+    io::{self, Read},
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use serde::Serialize;
+
+use crate::logging::log_dir;
+
+/// The directory the player extracts media into while running. A subdirectory of the regular
+/// system temp dir (`$TMPDIR`/`/tmp`), *not* `$XDG_RUNTIME_DIR` (used for the lock file) — the
+/// runtime dir is conventionally sized for small runtime objects like sockets, and is typically
+/// much smaller than `/tmp`, which matters since these files can be large. Kept in its own
+/// subdirectory so leftovers from a previous run can be identified and swept away.
+pub fn temp_dir() -> PathBuf {
+    std::env::temp_dir().join("lewdware-tmp")
+}
+
+/// Held by the player for as long as it's running, so only one session runs at a time.
+pub fn lock_path() -> PathBuf {
+    dirs::runtime_dir()
+        .unwrap_or_else(std::env::temp_dir)
+        .join("lewdware.lock")
+}
+
+/// Whether a player is running, however it was started, found by trying to take the lock it holds
+/// (see [`lock_path`]).
+pub fn player_running() -> bool {
+    File::open(lock_path())
+        .is_ok_and(|file| matches!(file.try_lock(), Err(TryLockError::WouldBlock)))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum FootprintKind {
+    /// An image copied out of the pack to be set as the wallpaper.
+    Wallpaper,
+    /// A buffer frames are handed over through when video decoding is isolated.
+    VideoBuffer,
+    Log,
+    Lock,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FootprintEntry {
+    pub path: PathBuf,
+    pub kind: FootprintKind,
+    pub size: u64,
+}
+
+/// Every file the player has written since `since`, or that it's left lying around at all with
+/// `None`. Only the logs are checked against it: the temp dir is cleared whenever a session
+/// starts, so everything in it is from the latest one.
+pub fn footprint(since: Option<SystemTime>) -> Vec<FootprintEntry> {
+    footprint_in(&temp_dir(), log_dir().as_deref(), &lock_path(), since)
+}
+
+fn footprint_in(
+    temp_dir: &Path,
+    log_dir: Option<&Path>,
+    lock_path: &Path,
+    since: Option<SystemTime>,
+) -> Vec<FootprintEntry> {
+    let file = |path: PathBuf, kind| {
+        let metadata = fs::metadata(&path)
+            .ok()
+            .filter(|metadata| metadata.is_file())?;
+        Some(FootprintEntry {
+            path,
+            kind,
+            size: metadata.len(),
+        })
+    };
+    let mut entries = Vec::new();
+
+    for entry in fs::read_dir(temp_dir).into_iter().flatten().flatten() {
+        // Decode buffers are named so by `decode_helper`, and wallpapers are the only other
+        // thing that needs a real path.
+        let kind = if entry.file_name().to_string_lossy().starts_with("decode-") {
+            FootprintKind::VideoBuffer
+        } else {
+            FootprintKind::Wallpaper
+        };
+        entries.extend(file(entry.path(), kind));
+    }
+
+    // The player's own logs and its decode helper's, not the config app's or the pack editor's.
+    for entry in log_dir
+        .map(fs::read_dir)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .flatten()
+    {
+        let modified = entry.metadata().and_then(|metadata| metadata.modified());
+        if entry.file_name().to_string_lossy().starts_with("lewdware")
+            && since.is_none_or(|since| modified.is_ok_and(|modified| modified >= since))
+        {
+            entries.extend(file(entry.path(), FootprintKind::Log));
+        }
+    }
+
+    entries.extend(file(lock_path.to_path_buf(), FootprintKind::Lock));
+
+    entries
+}
+
+/// Overwrites each file with zeroes and syncs it before deleting it, so what was in it can't be
+/// read back off the disk. This is as far as a program can go: SSDs and copy-on-write filesystems
+/// may still keep the old blocks around until they're reused. Returns the files that couldn't be
+/// wiped; ones that are already gone don't count.
+pub fn wipe(entries: &[FootprintEntry]) -> Vec<PathBuf> {
+    entries
+        .iter()
+        .filter_map(|entry| match shred(&entry.path) {
+            Ok(()) => None,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => None,
+            Err(err) => {
+                tracing::warn!("Couldn't wipe {}: {err}", entry.path.display());
+                Some(entry.path.clone())
+            }
+        })
+        .collect()
+}
+
+fn shred(path: &Path) -> io::Result<()> {
+    let mut file = OpenOptions::new().write(true).open(path)?;
+    let length = file.metadata()?.len();
+    io::copy(&mut io::repeat(0).take(length), &mut file)?;
+    file.sync_all()?;
+    drop(file);
+
+    fs::remove_file(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[test]
+    fn lists_and_wipes_a_session() {
+        let root = tempfile::tempdir().unwrap();
+        let (temp_dir, log_dir) = (root.path().join("tmp"), root.path().join("logs"));
+        let lock_path = root.path().join("lewdware.lock");
+        fs::create_dir(&temp_dir).unwrap();
+        fs::create_dir(&log_dir).unwrap();
+
+        fs::write(temp_dir.join("decode-abc"), [1; 100]).unwrap();
+        fs::write(temp_dir.join(".tmpxyz.png"), [1; 10]).unwrap();
+        fs::write(log_dir.join("lewdware.log.2026-10-17"), "log").unwrap();
+        fs::write(log_dir.join("config.log.2026-10-17"), "log").unwrap();
+        fs::write(&lock_path, "").unwrap();
+
+        let mut entries = footprint_in(&temp_dir, Some(&log_dir), &lock_path, None);
+        entries.sort_by(|a, b| a.path.cmp(&b.path));
+        let kinds: Vec<_> = entries
+            .iter()
+            .map(|entry| (entry.kind, entry.size))
+            .collect();
+        assert_eq!(
+            kinds,
+            [
+                (FootprintKind::Lock, 0),
+                (FootprintKind::Log, 3),
+                (FootprintKind::Wallpaper, 10),
+                (FootprintKind::VideoBuffer, 100),
+            ]
+        );
+
+        assert_eq!(wipe(&entries), Vec::<PathBuf>::new());
+        assert!(entries.iter().all(|entry| !entry.path.exists()));
+        // The config app's logs are left alone.
+        assert!(log_dir.join("config.log.2026-10-17").exists());
+        // Wiping twice is fine.
+        assert_eq!(wipe(&entries), Vec::<PathBuf>::new());
+    }
+
+    #[test]
+    fn only_lists_logs_from_the_session() {
+        let root = tempfile::tempdir().unwrap();
+        fs::write(root.path().join("lewdware.log.2026-10-17"), "log").unwrap();
+
+        let later = SystemTime::now() + Duration::from_secs(60);
+        let entries = footprint_in(
+            &root.path().join("tmp"),
+            Some(root.path()),
+            &root.path().join("lewdware.lock"),
+            Some(later),
+        );
+        assert_eq!(entries, []);
+    }
+}
@@ -0,0 +1,323 @@
+//! Goals for a session, like "complete 5 prompts" or "last 30 minutes". The user can set their
+//! own in the config app, and packs can bring some too. The player counts towards them as the
+//! session goes, lets the user know as each one is reached, and writes a [`SessionSummary`] when
+//! the session ends, adding it to the [`SessionTotals`], both of which the config app shows.
+//!
+//! Packs can also have [`Milestone`]s, counted the same way, which hold some of their tags back
+//! until they're reached.
+
+use std::collections::BTreeSet;
+
+use serde::{Deserialize, Serialize};
+
+/// What a goal counts.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum GoalStat {
+    /// Minutes spent in the session, not counting time it was paused for.
+    Minutes,
+    /// Popups spawned, of any kind.
+    Popups,
+    /// Prompts answered.
+    Prompts,
+    /// Choices made.
+    Choices,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Goal {
+    pub name: String,
+    pub stat: GoalStat,
+    /// Reached once `stat` gets to this.
+    pub target: u64,
+    /// A popup set in the pack to show when the goal is reached.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub popup_set: Option<String>,
+}
+
+/// A point in a session, counted like a goal, that a pack holds some of its tags back until,
+/// e.g. its most intense files until 20 minutes in, or until 50 popups have been shown. Once
+/// reached, the tags stay unlocked for the rest of the session.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Milestone {
+    pub name: String,
+    pub stat: GoalStat,
+    /// Reached once `stat` gets to this.
+    pub target: u64,
+    /// The tags whose files and texts aren't shown until the milestone is reached.
+    pub unlocks: Vec<String>,
+}
+
+impl Milestone {
+    pub fn reached(&self, stats: &SessionStats) -> bool {
+        stats.get(self.stat) >= self.target
+    }
+}
+
+/// The tags locked at the start of a session: every tag one of `milestones` unlocks. A tag more
+/// than one of them unlocks is unlocked by whichever is reached first.
+pub fn locked_tags(milestones: &[Milestone]) -> BTreeSet<String> {
+    milestones
+        .iter()
+        .flat_map(|milestone| milestone.unlocks.iter().cloned())
+        .collect()
+}
+
+/// Everything a session has counted so far.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct SessionStats {
+    pub minutes: u64,
+    pub popups: u64,
+    pub prompts: u64,
+    pub choices: u64,
+    /// Prompts answered with something other than what they asked for. Not a goal, but texts
+    /// can show it (see [`SessionStats::variable`]).
+    #[serde(default)]
+    pub prompts_failed: u64,
+    /// Popups the user closed themselves, rather than ones that closed on their own or were
+    /// closed by the mode.
+    #[serde(default)]
+    pub popups_closed: u64,
+    #[serde(default)]
+    pub links: u64,
+    /// Minutes since the session started, counting time it was paused for, unlike `minutes`.
+    #[serde(default)]
+    pub uptime: u64,
+}
+
+impl SessionStats {
+    pub fn get(&self, stat: GoalStat) -> u64 {
+        match stat {
+            GoalStat::Minutes => self.minutes,
+            GoalStat::Popups => self.popups,
+            GoalStat::Prompts => self.prompts,
+            GoalStat::Choices => self.choices,
+        }
+    }
+
+    pub fn get_mut(&mut self, stat: GoalStat) -> &mut u64 {
+        match stat {
+            GoalStat::Minutes => &mut self.minutes,
+            GoalStat::Popups => &mut self.popups,
+            GoalStat::Prompts => &mut self.prompts,
+            GoalStat::Choices => &mut self.choices,
+        }
+    }
+
+    /// The stat called `name`, for placeholders like `{popups}` in notifications (see
+    /// [`crate::template`]).
+    pub fn variable(&self, name: &str) -> Option<u64> {
+        match name {
+            "minutes" => Some(self.minutes),
+            "popups" => Some(self.popups),
+            "prompts" => Some(self.prompts),
+            "prompts_failed" => Some(self.prompts_failed),
+            "choices" => Some(self.choices),
+            "popups_closed" => Some(self.popups_closed),
+            "links" => Some(self.links),
+            "uptime" => Some(self.uptime),
+            _ => None,
+        }
+    }
+}
+
+/// Everything counted over every session so far, kept next to the last session's summary.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct SessionTotals {
+    pub sessions: u64,
+    pub stats: SessionStats,
+}
+
+impl SessionTotals {
+    pub fn add(&mut self, stats: &SessionStats) {
+        let SessionStats {
+            minutes,
+            popups,
+            prompts,
+            choices,
+            prompts_failed,
+            popups_closed,
+            links,
+            uptime,
+        } = *stats;
+
+        self.sessions += 1;
+        self.stats.minutes += minutes;
+        self.stats.popups += popups;
+        self.stats.prompts += prompts;
+        self.stats.choices += choices;
+        self.stats.prompts_failed += prompts_failed;
+        self.stats.popups_closed += popups_closed;
+        self.stats.links += links;
+        self.stats.uptime += uptime;
+    }
+}
+
+/// How the last session went, saved by the player when it ends.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct SessionSummary {
+    /// When the session ended, in seconds since the Unix epoch.
+    pub ended_at: u64,
+    pub stats: SessionStats,
+    /// The session's goals, in the order they were reached, then the ones that weren't.
+    pub goals: Vec<Goal>,
+    /// How many of `goals` were reached.
+    pub reached: usize,
+}
+
+/// Works out which goals have been reached, given what's been counted.
+pub struct GoalTracker {
+    goals: Vec<Goal>,
+    /// The indices of the goals reached so far, in the order they were reached.
+    reached: Vec<usize>,
+}
+
+impl GoalTracker {
+    pub fn new(goals: Vec<Goal>) -> Self {
+        Self {
+            goals,
+            reached: Vec::new(),
+        }
+    }
+
+    /// The goals that `stats` reaches for the first time.
+    pub fn update(&mut self, stats: &SessionStats) -> Vec<Goal> {
+        let mut newly_reached = Vec::new();
+
+        for (i, goal) in self.goals.iter().enumerate() {
+            if !self.reached.contains(&i) && stats.get(goal.stat) >= goal.target {
+                self.reached.push(i);
+                newly_reached.push(goal.clone());
+            }
+        }
+
+        newly_reached
+    }
+
+    pub fn summary(&self, stats: SessionStats, ended_at: u64) -> SessionSummary {
+        let goals = self
+            .reached
+            .iter()
+            .copied()
+            .chain((0..self.goals.len()).filter(|i| !self.reached.contains(i)))
+            .map(|i| self.goals[i].clone())
+            .collect();
+
+        SessionSummary {
+            ended_at,
+            stats,
+            goals,
+            reached: self.reached.len(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn goal(name: &str, stat: GoalStat, target: u64) -> Goal {
+        Goal {
+            name: name.to_string(),
+            stat,
+            target,
+            popup_set: None,
+        }
+    }
+
+    #[test]
+    fn milestones_lock_their_tags_until_reached() {
+        let milestones = [
+            Milestone {
+                name: "Ten minutes in".to_string(),
+                stat: GoalStat::Minutes,
+                target: 10,
+                unlocks: vec!["wild".to_string(), "wilder".to_string()],
+            },
+            Milestone {
+                name: "Twenty popups".to_string(),
+                stat: GoalStat::Popups,
+                target: 20,
+                unlocks: vec!["wild".to_string()],
+            },
+        ];
+        assert_eq!(
+            locked_tags(&milestones),
+            BTreeSet::from(["wild".to_string(), "wilder".to_string()])
+        );
+
+        let stats = SessionStats {
+            popups: 20,
+            ..Default::default()
+        };
+        assert!(!milestones[0].reached(&stats));
+        assert!(milestones[1].reached(&stats));
+    }
+
+    #[test]
+    fn reaches_each_goal_once() {
+        let mut tracker = GoalTracker::new(vec![
+            goal("Last an hour", GoalStat::Minutes, 60),
+            goal("Answer 2 prompts", GoalStat::Prompts, 2),
+            goal("See a popup", GoalStat::Popups, 1),
+        ]);
+        let mut stats = SessionStats::default();
+
+        assert!(tracker.update(&stats).is_empty());
+
+        stats.popups = 3;
+        stats.prompts = 1;
+        assert_eq!(
+            tracker.update(&stats),
+            [goal("See a popup", GoalStat::Popups, 1)]
+        );
+        assert!(tracker.update(&stats).is_empty());
+
+        *stats.get_mut(GoalStat::Prompts) += 1;
+        assert_eq!(
+            tracker.update(&stats),
+            [goal("Answer 2 prompts", GoalStat::Prompts, 2)]
+        );
+
+        let summary = tracker.summary(stats, 0);
+        assert_eq!(summary.reached, 2);
+        let names: Vec<_> = summary
+            .goals
+            .iter()
+            .map(|goal| goal.name.as_str())
+            .collect();
+        assert_eq!(names, ["See a popup", "Answer 2 prompts", "Last an hour"]);
+    }
+
+    #[test]
+    fn totals_add_up_every_session() {
+        let mut totals = SessionTotals::default();
+        totals.add(&SessionStats {
+            minutes: 10,
+            popups: 30,
+            popups_closed: 12,
+            uptime: 15,
+            ..Default::default()
+        });
+        totals.add(&SessionStats {
+            minutes: 5,
+            popups: 8,
+            links: 2,
+            uptime: 5,
+            ..Default::default()
+        });
+
+        assert_eq!(totals.sessions, 2);
+        assert_eq!(
+            totals.stats,
+            SessionStats {
+                minutes: 15,
+                popups: 38,
+                popups_closed: 12,
+                links: 2,
+                uptime: 20,
+                ..Default::default()
+            }
+        );
+    }
+}
@@ -0,0 +1,108 @@
+//! The session history: a record of the prompts shown in past sessions, and what the user typed
+//! into them. It's only kept if the user turns it on in the config app (see
+//! [`PromptLogging`](crate::user_config::PromptLogging)), for people who use prompts as a journal
+//! or for training, and can be exported from there as JSON.
+
+use serde::{Deserialize, Serialize};
+
+/// A prompt that was answered, or closed without being answered.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct PromptRecord {
+    /// When the session the prompt was shown in started, in seconds since the Unix epoch. Tells
+    /// sessions apart.
+    pub session: u64,
+    /// When it was answered or closed, in seconds since the Unix epoch.
+    pub at: u64,
+    /// The text the prompt asked for.
+    pub prompt: Option<String>,
+    pub response: PromptResponse,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum PromptResponse {
+    /// Closed without being answered.
+    Unanswered,
+    /// What the user typed. `matches` is whether it was the prompt's text.
+    Plaintext { text: String, matches: bool },
+    /// The BLAKE3 hash of what the user typed, as hex, so the same answers can be spotted
+    /// without keeping them.
+    Hashed { blake3: String, matches: bool },
+}
+
+impl PromptResponse {
+    /// Whether `text` is what `prompt` asked for, ignoring surrounding whitespace and case.
+    pub fn matches(prompt: Option<&str>, text: &str) -> bool {
+        prompt.is_some_and(|prompt| prompt.trim().to_lowercase() == text.trim().to_lowercase())
+    }
+
+    /// How much of `prompt` was typed correctly, from 0 to 1: one less the edit distance between
+    /// them over the length of the prompt. Surrounding whitespace and case are ignored, like in
+    /// [`Self::matches`].
+    pub fn accuracy(prompt: &str, text: &str) -> f64 {
+        let prompt: Vec<char> = prompt.trim().to_lowercase().chars().collect();
+        let text: Vec<char> = text.trim().to_lowercase().chars().collect();
+        if prompt.is_empty() {
+            return if text.is_empty() { 1.0 } else { 0.0 };
+        }
+
+        // Levenshtein distance, a row at a time.
+        let mut row: Vec<usize> = (0..=text.len()).collect();
+        for (i, p) in prompt.iter().enumerate() {
+            let mut diagonal = row[0];
+            row[0] = i + 1;
+            for (j, t) in text.iter().enumerate() {
+                let above = row[j + 1];
+                row[j + 1] = (diagonal + usize::from(p != t))
+                    .min(above + 1)
+                    .min(row[j] + 1);
+                diagonal = above;
+            }
+        }
+
+        (1.0 - row[text.len()] as f64 / prompt.len() as f64).max(0.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn prompt_records_roundtrip_as_json() {
+        let record = PromptRecord {
+            session: 1,
+            at: 2,
+            prompt: Some("Good morning".to_string()),
+            response: PromptResponse::Plaintext {
+                text: "good morning ".to_string(),
+                matches: PromptResponse::matches(Some("Good morning"), "good morning "),
+            },
+        };
+
+        let json = serde_json::to_string(&record).unwrap();
+        assert!(json.contains(r#""type":"plaintext""#));
+        assert!(json.contains(r#""matches":true"#));
+        assert_eq!(serde_json::from_str::<PromptRecord>(&json).unwrap(), record);
+
+        assert!(!PromptResponse::matches(None, ""));
+        assert!(!PromptResponse::matches(Some("Good morning"), "Good night"));
+    }
+
+    #[test]
+    fn accuracy_counts_typos() {
+        assert_eq!(
+            PromptResponse::accuracy("Good morning", " good morning"),
+            1.0
+        );
+        // One letter missing and one swapped, out of 12.
+        assert_eq!(
+            PromptResponse::accuracy("Good morning", "Good mornng!"),
+            1.0 - 2.0 / 12.0
+        );
+        assert_eq!(PromptResponse::accuracy("Good morning", ""), 0.0);
+        // Never below zero, however much extra is typed.
+        assert_eq!(PromptResponse::accuracy("Hi", "Hello there"), 0.0);
+        assert_eq!(PromptResponse::accuracy("", ""), 1.0);
+    }
+}
@@ -0,0 +1,132 @@
+//! Rules for skipping parts of a folder when importing media from it.
+//!
+//! * A folder containing a `.nomedia` file is skipped, along with everything below it.
+//! * A `.lewdignore` file holds glob patterns, one per line, matched against paths relative to
+//!   the folder it's in. Patterns without a `/` match a file or folder name at any depth. Blank
+//!   lines and lines starting with `#` are skipped. Subfolders inherit their parents' rules.
+
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use glob::{MatchOptions, Pattern};
+
+pub const NOMEDIA_FILE: &str = ".nomedia";
+pub const IGNORE_FILE: &str = ".lewdignore";
+
+const MATCH_OPTIONS: MatchOptions = MatchOptions {
+    case_sensitive: true,
+    require_literal_separator: true,
+    require_literal_leading_dot: false,
+};
+
+struct IgnoreFile {
+    base: PathBuf,
+    patterns: Vec<IgnorePattern>,
+}
+
+struct IgnorePattern {
+    pattern: Pattern,
+    // Whether the pattern contains a `/`, and so has to match the whole relative path rather
+    // than just a name.
+    anchored: bool,
+}
+
+/// The ignore rules that apply in a folder. Cheap to clone, so each subfolder can extend its
+/// parent's rules.
+#[derive(Clone, Default)]
+pub struct IgnoreRules {
+    files: Vec<Arc<IgnoreFile>>,
+}
+
+impl IgnoreRules {
+    /// Adds the patterns from an ignore file in `base`. Invalid patterns are skipped with a
+    /// warning, rather than failing the whole import.
+    pub fn add(&mut self, base: &Path, contents: &str) {
+        let patterns: Vec<_> = contents
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .filter_map(|line| {
+                let line = line.trim_end_matches('/');
+                let anchored = line.contains('/');
+                match Pattern::new(line.trim_start_matches('/')) {
+                    Ok(pattern) => Some(IgnorePattern { pattern, anchored }),
+                    Err(err) => {
+                        tracing::warn!("Invalid pattern {line:?} in {}: {err}", base.display());
+                        None
+                    }
+                }
+            })
+            .collect();
+
+        if !patterns.is_empty() {
+            self.files.push(Arc::new(IgnoreFile {
+                base: base.to_path_buf(),
+                patterns,
+            }));
+        }
+    }
+
+    /// The rules for `dir`: these ones, plus `dir`'s own `.lewdignore` if it has one.
+    pub fn for_dir(&self, dir: &Path) -> io::Result<Self> {
+        let mut rules = self.clone();
+        match fs::read_to_string(dir.join(IGNORE_FILE)) {
+            Ok(contents) => rules.add(dir, &contents),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {}
+            Err(err) => return Err(err),
+        }
+        Ok(rules)
+    }
+
+    pub fn is_ignored(&self, path: &Path) -> bool {
+        self.matched_by(path).is_some()
+    }
+
+    /// The folder of the ignore file, and the pattern in it, that makes `path` ignored.
+    pub fn matched_by(&self, path: &Path) -> Option<(&Path, &str)> {
+        self.files.iter().find_map(|file| {
+            let relative = path.strip_prefix(&file.base).ok()?;
+            let relative = relative.to_string_lossy().replace('\\', "/");
+            let name = relative.rsplit('/').next().unwrap_or(&relative);
+
+            file.patterns
+                .iter()
+                .find(|p| {
+                    if p.anchored {
+                        p.pattern.matches_with(&relative, MATCH_OPTIONS)
+                    } else {
+                        p.pattern.matches_with(name, MATCH_OPTIONS)
+                    }
+                })
+                .map(|p| (file.base.as_path(), p.pattern.as_str()))
+        })
+    }
+}
+
+/// Whether `dir` opts out of being imported with a `.nomedia` file.
+pub fn is_nomedia_dir(dir: &Path) -> bool {
+    dir.join(NOMEDIA_FILE).exists()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn patterns_match_names_or_relative_paths() {
+        let base = Path::new("/media");
+        let mut rules = IgnoreRules::default();
+        rules.add(base, "# comment\n\n*.gif\nraw/\n/drafts/*.png\n");
+
+        assert!(rules.is_ignored(&base.join("a.gif")));
+        assert!(rules.is_ignored(&base.join("sub/dir/b.gif")));
+        assert!(rules.is_ignored(&base.join("sub/raw")));
+        assert!(rules.is_ignored(&base.join("drafts/c.png")));
+        assert!(!rules.is_ignored(&base.join("sub/drafts/c.png")));
+        assert!(!rules.is_ignored(&base.join("c.png")));
+        assert!(!rules.is_ignored(Path::new("/elsewhere/a.gif")));
+    }
+}
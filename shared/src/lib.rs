@@ -1,10 +1,27 @@
+pub mod children;
 pub mod db;
 pub mod encode;
+pub mod extract;
+pub mod footprint;
+pub mod goals;
+pub mod history;
+pub mod ignore;
+pub mod locale;
+pub mod location;
 pub mod logging;
+pub mod manifest;
 pub mod mode;
 mod once;
+pub mod pack_index;
+pub mod privacy;
+pub mod rating;
 pub mod read_pack;
+pub mod sidecar;
+pub mod template;
+pub mod texts;
 pub mod user_config;
+pub mod user_data;
 pub mod utils;
+pub mod write_pack;
 
 pub const VERSION: &'static str = env!("CARGO_PKG_VERSION");
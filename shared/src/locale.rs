@@ -0,0 +1,315 @@
+//! Translations of the apps' own text, using [Fluent](https://projectfluent.org). Text from packs
+//! and modes isn't translated here: they bring their own.
+//!
+//! Each language has a directory under `shared/locales` holding an `.ftl` file per app, which are
+//! embedded at build time. Message ids start with the app they're used in (`lewdware-`, `config-`
+//! or `pack-editor-`), or `common-` if they're used in more than one. Anything missing from a
+//! translation falls back to English.
+//!
+//! To add a language, copy `locales/en`, translate it, and add it to [`LANGUAGES`] and
+//! `sources`.
+
+use std::{
+    collections::{BTreeSet, HashMap},
+    sync::OnceLock,
+};
+
+use fluent_bundle::{FluentArgs, FluentResource, concurrent::FluentBundle};
+use fluent_syntax::ast;
+use unic_langid::LanguageIdentifier;
+
+pub use fluent_bundle::FluentValue;
+
+/// Used when there's no translation for the user's language, and for anything a translation is
+/// missing.
+pub const FALLBACK: &str = "en";
+
+/// The languages there are translations for, with their names in that language.
+pub const LANGUAGES: &[(&str, &str)] = &[("en", "English")];
+
+fn sources(language: &str) -> &'static [&'static str] {
+    match language {
+        "en" => &[
+            include_str!("../locales/en/common.ftl"),
+            include_str!("../locales/en/lewdware.ftl"),
+            include_str!("../locales/en/config.ftl"),
+            include_str!("../locales/en/pack-editor.ftl"),
+        ],
+        _ => &[],
+    }
+}
+
+/// Picks the closest language there's a translation for to `requested` (a tag like `pt-BR`, or a
+/// POSIX locale like `pt_BR.UTF-8`), or to the system's locale if it's `None`.
+pub fn resolve_language(requested: Option<&str>) -> &'static str {
+    requested
+        .map(str::to_string)
+        .or_else(sys_locale::get_locale)
+        .and_then(|requested| match_language(&requested))
+        .unwrap_or(FALLBACK)
+}
+
+/// The user's language as a tag like `pt-BR`, from `requested` or the system's locale, for
+/// picking between content in several languages (like a pack's texts). Unlike
+/// [`resolve_language`], this isn't limited to the languages the apps are translated into.
+pub fn preferred_language(requested: Option<&str>) -> Option<String> {
+    requested
+        .map(str::to_string)
+        .or_else(sys_locale::get_locale)
+        .and_then(|requested| normalize_language(&requested))
+}
+
+/// Turns a tag like `pt-br`, or a POSIX locale like `pt_BR.UTF-8`, into a tag like `pt-BR`.
+/// Returns `None` if it isn't a valid language, like the `C` locale.
+pub fn normalize_language(language: &str) -> Option<String> {
+    parse_language(language).map(|language| language.to_string())
+}
+
+/// Whether content in `content` is suitable for someone reading `language`: they're the same
+/// language, and if `content` is for a particular region, it's the same region.
+pub fn language_matches(content: &str, language: &str) -> bool {
+    let (Some(content), Some(language)) = (parse_language(content), parse_language(language))
+    else {
+        return false;
+    };
+
+    content.language == language.language
+        && content
+            .script
+            .is_none_or(|script| language.script == Some(script))
+        && content
+            .region
+            .is_none_or(|region| language.region == Some(region))
+}
+
+fn parse_language(language: &str) -> Option<LanguageIdentifier> {
+    language
+        .split(['.', '@'])
+        .next()?
+        .replace('_', "-")
+        .parse()
+        .ok()
+}
+
+fn match_language(requested: &str) -> Option<&'static str> {
+    let requested = parse_language(requested)?;
+    let supported = || {
+        LANGUAGES.iter().filter_map(|&(code, _)| {
+            let language: LanguageIdentifier = code.parse().ok()?;
+            Some((code, language))
+        })
+    };
+
+    // An exact match, or failing that one for the same language in another region.
+    supported()
+        .find(|(_, language)| *language == requested)
+        .or_else(|| supported().find(|(_, language)| language.language == requested.language))
+        .map(|(code, _)| code)
+}
+
+/// Formats messages in one language.
+pub struct Localizer {
+    language: &'static str,
+    /// The language's translations, followed by the fallback's.
+    bundles: Vec<FluentBundle<FluentResource>>,
+    /// Every message id, and every attribute as `id.attribute`.
+    keys: BTreeSet<String>,
+}
+
+impl Localizer {
+    pub fn new(language: &'static str) -> Self {
+        let mut languages = vec![language];
+        if language != FALLBACK {
+            languages.push(FALLBACK);
+        }
+
+        let mut keys = BTreeSet::new();
+        let bundles = languages
+            .into_iter()
+            .map(|language| {
+                let mut bundle = FluentBundle::new_concurrent(
+                    language.parse::<LanguageIdentifier>().into_iter().collect(),
+                );
+                // The Unicode isolation marks around arguments show up as boxes in some fonts.
+                bundle.set_use_isolating(false);
+
+                for source in sources(language) {
+                    // A broken translation shouldn't stop the app from starting: whatever parsed
+                    // is still used, and the rest falls back to English.
+                    let resource = FluentResource::try_new(source.to_string()).unwrap_or_else(
+                        |(resource, errors)| {
+                            tracing::error!("Errors in {language} translation: {errors:?}");
+                            resource
+                        },
+                    );
+
+                    for entry in resource.entries() {
+                        if let ast::Entry::Message(message) = entry {
+                            keys.insert(message.id.name.to_string());
+                            keys.extend(message.attributes.iter().map(|attribute| {
+                                format!("{}.{}", message.id.name, attribute.id.name)
+                            }));
+                        }
+                    }
+
+                    if let Err(errors) = bundle.add_resource(resource) {
+                        tracing::error!("Errors in {language} translation: {errors:?}");
+                    }
+                }
+
+                bundle
+            })
+            .collect();
+
+        Self {
+            language,
+            bundles,
+            keys,
+        }
+    }
+
+    pub fn language(&self) -> &'static str {
+        self.language
+    }
+
+    /// Formats the message `key`, which is a message id or `id.attribute`. Falls back to the key
+    /// itself if there's no such message.
+    pub fn tr(&self, key: &str) -> String {
+        self.format(key, None)
+    }
+
+    pub fn tr_args(&self, key: &str, args: &FluentArgs) -> String {
+        self.format(key, Some(args))
+    }
+
+    fn format(&self, key: &str, args: Option<&FluentArgs>) -> String {
+        self.try_format(key, args, true).unwrap_or_else(|| {
+            tracing::warn!("No translation for {key}");
+            key.to_string()
+        })
+    }
+
+    fn try_format(&self, key: &str, args: Option<&FluentArgs>, log: bool) -> Option<String> {
+        let (id, attribute) = match key.split_once('.') {
+            Some((id, attribute)) => (id, Some(attribute)),
+            None => (key, None),
+        };
+
+        self.bundles.iter().find_map(|bundle| {
+            let message = bundle.get_message(id)?;
+            let pattern = match attribute {
+                Some(attribute) => message.get_attribute(attribute)?.value(),
+                None => message.value()?,
+            };
+
+            let mut errors = Vec::new();
+            let value = bundle.format_pattern(pattern, args, &mut errors);
+            if log && !errors.is_empty() {
+                tracing::warn!("Errors formatting {key}: {errors:?}");
+            }
+
+            Some(value.into_owned())
+        })
+    }
+
+    /// Every message and attribute, for frontends that format messages themselves. Arguments are
+    /// left in as `{$name}`, and selectors always pick their default variant.
+    pub fn messages(&self) -> HashMap<String, String> {
+        self.keys
+            .iter()
+            .filter_map(|key| Some((key.clone(), self.try_format(key, None, false)?)))
+            .collect()
+    }
+}
+
+static LOCALIZER: OnceLock<Localizer> = OnceLock::new();
+
+/// Sets the language used by [`tr`], from the `language` in the config. Should be called once at
+/// startup, otherwise the system's language is used.
+pub fn init(language: Option<&str>) {
+    let language = resolve_language(language);
+    if LOCALIZER.set(Localizer::new(language)).is_err() {
+        tracing::warn!("Language was already set");
+    }
+
+    tracing::info!("Language: {language}");
+}
+
+pub fn localizer() -> &'static Localizer {
+    LOCALIZER.get_or_init(|| Localizer::new(resolve_language(None)))
+}
+
+/// Formats the message `key` in the user's language.
+pub fn tr(key: &str) -> String {
+    localizer().tr(key)
+}
+
+/// Formats the message `key` in the user's language, with arguments, e.g.
+/// `tr_args("id", [("count", count.into())])`.
+pub fn tr_args<'a>(
+    key: &str,
+    args: impl IntoIterator<Item = (&'a str, FluentValue<'a>)>,
+) -> String {
+    localizer().tr_args(key, &args.into_iter().collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn translations_are_valid() {
+        let english = Localizer::new(FALLBACK);
+
+        for &(language, _) in LANGUAGES {
+            for source in sources(language) {
+                if let Err((_, errors)) = FluentResource::try_new(source.to_string()) {
+                    panic!("{language}: {errors:?}");
+                }
+            }
+
+            // Catches duplicate ids, and ids that have been renamed in English.
+            let localizer = Localizer::new(language);
+            for key in &localizer.keys {
+                assert!(
+                    english.keys.contains(key),
+                    "{language}: unknown message {key}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn matches_languages() {
+        assert_eq!(match_language("en"), Some("en"));
+        assert_eq!(match_language("en-GB"), Some("en"));
+        assert_eq!(match_language("en_US.UTF-8"), Some("en"));
+        assert_eq!(match_language("xx"), None);
+        assert_eq!(match_language("C"), None);
+
+        assert_eq!(normalize_language("pt_br.UTF-8").as_deref(), Some("pt-BR"));
+        assert_eq!(normalize_language("C"), None);
+        assert!(language_matches("de", "de-AT"));
+        assert!(language_matches("de-AT", "de-AT"));
+        assert!(!language_matches("de-AT", "de-DE"));
+        assert!(!language_matches("de-AT", "de"));
+        assert!(!language_matches("en", "de"));
+    }
+
+    #[test]
+    fn formats_messages() {
+        let localizer = Localizer::new("en");
+        let mut args = FluentArgs::new();
+        args.set("percent", 50);
+
+        assert_eq!(
+            localizer.tr_args("common-open-progress-reading-index", &args),
+            "Reading index (50%)"
+        );
+        assert_eq!(localizer.tr("no-such-message"), "no-such-message");
+        assert_eq!(
+            localizer.messages()["common-open-progress-reading-index"],
+            "Reading index ({$percent}%)"
+        );
+    }
+}
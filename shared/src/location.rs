@@ -0,0 +1,345 @@
+//! Where a pack is read from: a file, or a URL it's streamed from.
+//!
+//! A pack on a web server is read with HTTP range requests, the same way a file is read with
+//! seeks: the header, metadata and index once when it's opened, then each file as it's shown.
+//! So a multi-GB pack can be played without downloading all of it first, and the config app can
+//! show its modes and tags having only fetched its index. The server has to support range
+//! requests, which most static file servers do.
+
+use std::{
+    fs::File,
+    io::{self, Read, Seek, SeekFrom},
+    path::PathBuf,
+    sync::LazyLock,
+    time::Duration,
+};
+
+use serde::{Deserialize, Serialize};
+use ureq::{Agent, http::StatusCode};
+use url::Url;
+
+/// How much is fetched at once when reading over HTTP. Reads are usually much smaller than this
+/// (the engine's ffmpeg reads 32 KiB at a time), and a request per read
+/// would be far too slow.
+const READ_AHEAD: usize = 1024 * 1024;
+
+/// How long a single request can take before the read fails.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Shared by every [`HttpReader`], so connections to the server are kept open and reused rather
+/// than a new one being made for each reader.
+static AGENT: LazyLock<Agent> = LazyLock::new(|| {
+    Agent::config_builder()
+        .timeout_global(Some(REQUEST_TIMEOUT))
+        .http_status_as_error(false)
+        .build()
+        .into()
+});
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PackLocation {
+    File(PathBuf),
+    Url(Url),
+}
+
+impl PackLocation {
+    /// `pack_path` in the config can be an `http://` or `https://` URL as well as a path.
+    pub fn new(path: PathBuf) -> Self {
+        path.to_str()
+            .and_then(|s| Url::parse(s).ok())
+            .filter(|url| matches!(url.scheme(), "http" | "https"))
+            .map_or(Self::File(path), Self::Url)
+    }
+
+    /// Opens a new reader, which isn't shared with any other, so the pack can be read from
+    /// several threads at once.
+    pub fn open(&self) -> io::Result<PackReader> {
+        match self {
+            Self::File(path) => File::open(path).map(PackReader::File),
+            Self::Url(url) => Ok(PackReader::Http(HttpReader::new(
+                AGENT.clone(),
+                url.clone(),
+            ))),
+        }
+    }
+}
+
+pub enum PackReader {
+    File(File),
+    Http(HttpReader),
+}
+
+impl PackReader {
+    /// Reads from `offset` without moving the reader's position (for files, at least), like
+    /// `pread`.
+    pub fn read_at(&mut self, buf: &mut [u8], offset: u64) -> io::Result<usize> {
+        match self {
+            #[cfg(unix)]
+            Self::File(file) => {
+                use std::os::unix::fs::FileExt;
+                file.read_at(buf, offset)
+            }
+            #[cfg(windows)]
+            Self::File(file) => {
+                use std::os::windows::fs::FileExt;
+                file.seek_read(buf, offset)
+            }
+            Self::Http(reader) => {
+                reader.seek(SeekFrom::Start(offset))?;
+                reader.read(buf)
+            }
+        }
+    }
+}
+
+impl Read for PackReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            Self::File(file) => file.read(buf),
+            Self::Http(reader) => reader.read(buf),
+        }
+    }
+}
+
+impl Seek for PackReader {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        match self {
+            Self::File(file) => file.seek(pos),
+            Self::Http(reader) => reader.seek(pos),
+        }
+    }
+}
+
+/// Reads a file on a web server with range requests, fetching [`READ_AHEAD`] bytes (or as much
+/// as the read asks for, if that's more) at a time.
+pub struct HttpReader {
+    agent: Agent,
+    url: Url,
+    pos: u64,
+    /// The last bytes fetched, and where they start.
+    buf: Vec<u8>,
+    buf_start: u64,
+    /// The size of the whole file, once a response has said.
+    len: Option<u64>,
+}
+
+impl HttpReader {
+    fn new(agent: Agent, url: Url) -> Self {
+        Self {
+            agent,
+            url,
+            pos: 0,
+            buf: Vec::new(),
+            buf_start: 0,
+            len: None,
+        }
+    }
+
+    fn fetch(&mut self, length: usize) -> io::Result<()> {
+        let length = match self.len {
+            Some(len) => (length as u64).min(len - self.pos),
+            None => length as u64,
+        };
+        let end = self.pos + length - 1;
+        let response = self
+            .agent
+            .get(self.url.as_str())
+            .header("Range", format!("bytes={}-{end}", self.pos))
+            // A compressed body would be compressed as a whole, rather than the range asked for.
+            .header("Accept-Encoding", "identity")
+            .call()
+            .map_err(io::Error::other)?;
+
+        if let Some(len) = response
+            .headers()
+            .get("Content-Range")
+            .and_then(|value| value.to_str().ok())
+            .and_then(content_range_len)
+        {
+            self.len = Some(len);
+        }
+
+        match response.status() {
+            StatusCode::PARTIAL_CONTENT => {}
+            StatusCode::RANGE_NOT_SATISFIABLE => {
+                // Past the end of the file.
+                self.buf.clear();
+                self.buf_start = self.pos;
+                return Ok(());
+            }
+            StatusCode::OK => {
+                return Err(io::Error::new(
+                    io::ErrorKind::Unsupported,
+                    format!("{} doesn't support range requests", self.url),
+                ));
+            }
+            status => {
+                return Err(io::Error::other(format!(
+                    "Fetching {} failed: {status}",
+                    self.url
+                )));
+            }
+        }
+
+        self.buf.clear();
+        self.buf_start = self.pos;
+        response
+            .into_body()
+            .into_reader()
+            .take(length)
+            .read_to_end(&mut self.buf)?;
+
+        Ok(())
+    }
+}
+
+impl Read for HttpReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() || self.len.is_some_and(|len| self.pos >= len) {
+            return Ok(0);
+        }
+
+        let buf_end = self.buf_start + self.buf.len() as u64;
+        if !(self.buf_start..buf_end).contains(&self.pos) {
+            self.fetch(buf.len().max(READ_AHEAD))?;
+        }
+
+        let start = (self.pos - self.buf_start) as usize;
+        let available = &self.buf[start.min(self.buf.len())..];
+        let n = available.len().min(buf.len());
+        buf[..n].copy_from_slice(&available[..n]);
+        self.pos += n as u64;
+
+        Ok(n)
+    }
+}
+
+impl Seek for HttpReader {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let new_pos = match pos {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::Current(offset) => self.pos.checked_add_signed(offset),
+            SeekFrom::End(offset) => {
+                let Some(len) = self.len else {
+                    return Err(io::Error::new(
+                        io::ErrorKind::Unsupported,
+                        "The size of the pack isn't known yet",
+                    ));
+                };
+                len.checked_add_signed(offset)
+            }
+        };
+
+        self.pos = new_pos.ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidInput, "Seeked before the start")
+        })?;
+        Ok(self.pos)
+    }
+}
+
+/// The size of the whole file from a `Content-Range` header, e.g. `bytes 0-1023/146515`.
+fn content_range_len(value: &str) -> Option<u64> {
+    let (_, len) = value.strip_prefix("bytes ")?.split_once('/')?;
+    len.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::{BufRead, BufReader, Write},
+        net::TcpListener,
+        sync::LazyLock,
+        thread,
+    };
+
+    use super::*;
+
+    /// Serves `data` to every request, honoring its `Range` header.
+    fn serve(data: &'static [u8]) -> Url {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/pack.lwpack", listener.local_addr().unwrap());
+
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let mut range = None;
+                for line in BufReader::new(&stream).lines() {
+                    let line = line.unwrap();
+                    if line.is_empty() {
+                        break;
+                    }
+                    if let Some(value) = line.to_ascii_lowercase().strip_prefix("range: bytes=") {
+                        let (start, end) = value.split_once('-').unwrap();
+                        range = Some((
+                            start.parse::<usize>().unwrap(),
+                            end.parse::<usize>().unwrap(),
+                        ));
+                    }
+                }
+
+                let (start, end) = range.unwrap();
+                let response = if start >= data.len() {
+                    format!(
+                        "HTTP/1.1 416 Range Not Satisfiable\r\nContent-Range: bytes */{}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+                        data.len()
+                    )
+                    .into_bytes()
+                } else {
+                    let end = end.min(data.len() - 1);
+                    let mut response = format!(
+                        "HTTP/1.1 206 Partial Content\r\nContent-Range: bytes {start}-{end}/{}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                        data.len(),
+                        end - start + 1
+                    )
+                    .into_bytes();
+                    response.extend_from_slice(&data[start..=end]);
+                    response
+                };
+                stream.write_all(&response).unwrap();
+            }
+        });
+
+        Url::parse(&url).unwrap()
+    }
+
+    #[test]
+    fn urls_are_told_apart_from_paths() {
+        assert!(matches!(
+            PackLocation::new("https://example.com/pack.lwpack".into()),
+            PackLocation::Url(_)
+        ));
+        assert!(matches!(
+            PackLocation::new("/home/user/pack.lwpack".into()),
+            PackLocation::File(_)
+        ));
+        assert!(matches!(
+            PackLocation::new("C:\\Packs\\pack.lwpack".into()),
+            PackLocation::File(_)
+        ));
+    }
+
+    #[test]
+    fn reads_ranges_over_http() {
+        // A few MB of bytes that aren't all the same, so reading the wrong range is caught.
+        static DATA: LazyLock<Vec<u8>> =
+            LazyLock::new(|| (0..3 * READ_AHEAD).map(|i| (i % 251) as u8).collect());
+        let data = DATA.as_slice();
+        let mut reader = PackLocation::Url(serve(data)).open().unwrap();
+
+        let mut buf = [0u8; 16];
+        reader.seek(SeekFrom::Start(1000)).unwrap();
+        reader.read_exact(&mut buf).unwrap();
+        assert_eq!(buf, data[1000..1016]);
+
+        // Across the edge of what was fetched.
+        let offset = (1000 + READ_AHEAD - 8) as u64;
+        assert_eq!(reader.read_at(&mut buf, offset).unwrap(), 8);
+        reader.read_exact(&mut buf[8..]).unwrap();
+        assert_eq!(buf, data[offset as usize..offset as usize + 16]);
+
+        let mut rest = Vec::new();
+        reader.seek(SeekFrom::End(-100)).unwrap();
+        reader.read_to_end(&mut rest).unwrap();
+        assert_eq!(rest, data[data.len() - 100..]);
+    }
+}
@@ -23,16 +23,6 @@ pub fn init(log_file_prefix: &str) -> WorkerGuard {
     let file_appender = tracing_appender::rolling::daily(&dir, format!("{log_file_prefix}.log"));
     let (file_writer, guard) = tracing_appender::non_blocking(file_appender);
 
-    let stderr_filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| {
-        // In debug builds default to debug for our own crates, warn for everything else.
-        // Set RUST_LOG to override (e.g. RUST_LOG=debug to see all deps).
-        EnvFilter::new(if cfg!(debug_assertions) {
-            "warn,lewdware=debug,shared=debug,lewdware_config=debug,lewdware_config_lib=debug,lewdware_pack_editor=debug,lewdware_pack_editor_lib=debug"
-        } else {
-            "warn"
-        })
-    });
-
     tracing_subscriber::registry()
         .with(
             fmt::layer()
@@ -47,9 +37,35 @@ pub fn init(log_file_prefix: &str) -> WorkerGuard {
                 .with_writer(std::io::stderr)
                 .with_file(true)
                 .with_line_number(true)
-                .with_filter(stderr_filter),
+                .with_filter(stderr_filter()),
         )
         .init();
 
     guard
 }
+
+/// Like [`init`], but without the log file, for incognito sessions (see
+/// [`AppConfig::incognito`](crate::user_config::AppConfig::incognito)).
+pub fn init_stderr_only() {
+    tracing_subscriber::registry()
+        .with(
+            fmt::layer()
+                .with_writer(std::io::stderr)
+                .with_file(true)
+                .with_line_number(true)
+                .with_filter(stderr_filter()),
+        )
+        .init();
+}
+
+fn stderr_filter() -> EnvFilter {
+    EnvFilter::try_from_default_env().unwrap_or_else(|_| {
+        // In debug builds default to debug for our own crates, warn for everything else.
+        // Set RUST_LOG to override (e.g. RUST_LOG=debug to see all deps).
+        EnvFilter::new(if cfg!(debug_assertions) {
+            "warn,lewdware=debug,shared=debug,lewdware_config=debug,lewdware_config_lib=debug,lewdware_pack_editor=debug,lewdware_pack_editor_lib=debug"
+        } else {
+            "warn"
+        })
+    })
+}
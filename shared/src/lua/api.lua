@@ -20,6 +20,15 @@ lewdware.config = {}
 ---@class Media
 ---@field id number A unique identifier for the file.
 ---@field name string The name of the file.
+---@field tags string[] The file's tags.
+---@field caption? string From the file's sidecar, if it had one when it was packed.
+---@field category? string From the file's sidecar, e.g. `"wallpaper"`. Files in the `"toast"`
+---category are never picked at random, except by [random_toast()](lua://lewdware.media.random_toast).
+---@field weight? number From the file's sidecar: how likely the file should be to be picked,
+---relative to the default of 1.
+---@field cooldown? number From the file's sidecar: the minimum number of seconds between showings.
+---@field set? string From the file's sidecar: the popup set the file belongs to. See
+---[random_set()](lua://lewdware.media.random_set).
 
 ---@class Image : Media
 ---@field type '"image"'
@@ -142,10 +151,17 @@ function VideoWindow:set_loop(loop) end
 ---  submits the text, so it's better to use `on_submit()` to get this value.
 PromptWindow = {}
 
----Call a function every time the user submits a value.
+---Call a function every time the user submits a value. A typing test that's failed doesn't count:
+---it starts over with a new passage instead.
 ---@param cb fun(text: string) A function that takes the value that the user has submitted.
 function PromptWindow:on_submit(cb) end
 
+---Call a function every time the user submits something other than the prompt's text, or fails a
+---typing test, e.g. to reset a countdown. Called as well as the `on_submit()` callbacks (except
+---for typing tests), and after any consequences the user has set for failing a prompt.
+---@param cb fun(text: string) A function that takes the value that the user has submitted.
+function PromptWindow:on_fail(cb) end
+
 ---Set the text/subtitle of the window.
 ---@param text? string
 function PromptWindow:set_text(text) end
@@ -182,6 +198,17 @@ TextWindow = {}
 ---@param text string
 function TextWindow:set_text(text) end
 
+---@class MinigameWindow : Window
+---@field type "'minigame'"
+---@field hits integer How many times the target has been clicked so far.
+---@field hits_needed integer How many times the target has to be clicked to close the popup.
+MinigameWindow = {}
+
+---Call a function every time the user clicks the target. Once `hits` reaches `hits_needed`, the
+---popup closes itself.
+---@param cb fun(hits: integer) A function taking how many times the target has been clicked.
+function MinigameWindow:on_hit(cb) end
+
 ---@class LewdwareMedia
 lewdware.media = {}
 
@@ -209,6 +236,12 @@ function lewdware.media.get_audio(name) end
 ---@field type? MediaType | (MediaType)[] The type of media to include in the result. By default,
 ---  all media will be included (including audio).
 ---@field tags? string[] If specified, only media with these tags will be included in the result.
+---@field match? TagMatchMode Whether media needs any of `tags` or all of them. Defaults to the
+---  user's setting, which is `"any"` unless they've changed it.
+
+---@alias TagMatchMode
+---| '"any"' # Entries with at least one of the tags.
+---| '"all"' # Only entries with every one of the tags.
 
 ---List all files in the pack.
 ---@param opts? QueryMediaOpts
@@ -218,6 +251,7 @@ function lewdware.media.list(opts) end
 ---List all image files in the pack.
 ---@param opts? {
 ---   tags?: string[],
+---   match?: TagMatchMode,
 ---}
 ---@return Image[]
 function lewdware.media.list_images(opts) end
@@ -225,6 +259,7 @@ function lewdware.media.list_images(opts) end
 ---List all video files in the pack.
 ---@param opts? {
 ---   tags?: string[],
+---   match?: TagMatchMode,
 ---}
 ---@return Video[]
 function lewdware.media.list_videos(opts) end
@@ -232,6 +267,7 @@ function lewdware.media.list_videos(opts) end
 ---List all audio files in the pack.
 ---@param opts? {
 ---   tags?: string[],
+---   match?: TagMatchMode,
 ---}
 ---@return Audio[]
 function lewdware.media.list_audio(opts) end
@@ -241,6 +277,30 @@ function lewdware.media.list_audio(opts) end
 ---@return Image | Video | Audio | nil
 function lewdware.media.random(opts) end
 
+---Get up to `n` different random media files at once. This is much cheaper than calling
+---`random()` `n` times.
+---@param n integer The maximum number of files to return.
+---@param opts? QueryMediaOpts
+---@return (Image | Video | Audio)[]
+function lewdware.media.random_n(n, opts) end
+
+---Get every file in a random popup set (files given the same `set` in their sidecars), in order
+---of file name. The set is picked from the files matching `opts`, but all of its files of the
+---requested types are returned, even if they don't have the tags. Pass the result to
+---[spawn_popup_set()](lua://lewdware.spawn_popup_set) to show them side by side.
+---@param opts? QueryMediaOpts
+---@return (Image | Video | Audio)[] files Empty if no matching file is in a set.
+function lewdware.media.random_set(opts) end
+
+---Get a random toast: an image with `category: "toast"` in its sidecar, for
+---[spawn_toast()](lua://lewdware.spawn_toast).
+---@param opts? {
+---   tags?: string[],
+---   match?: TagMatchMode,
+---}
+---@return Image | nil
+function lewdware.media.random_toast(opts) end
+
 ---Get a random image file
 ---@param opts? QueryMediaOpts
 ---@return Image | nil
@@ -249,6 +309,7 @@ function lewdware.media.random_image(opts) end
 ---Get a random video file
 ---@param opts? {
 ---   tags?: string[],
+---   match?: TagMatchMode,
 ---}
 ---@return Video | nil
 function lewdware.media.random_video(opts) end
@@ -256,10 +317,46 @@ function lewdware.media.random_video(opts) end
 ---Get a random audio file
 ---@param opts? {
 ---   tags?: string[],
+---   match?: TagMatchMode,
 ---}
 ---@return Audio | nil
 function lewdware.media.random_audio(opts) end
 
+---Get a random notification, prompt or link from the pack. With `tags`, only texts with at
+---least one of them (or all of them, with `match = "all"`) are picked.
+---@param kind '"notification"' | '"prompt"' | '"link"'
+---@param opts? {
+---   tags?: string[],
+---   match?: TagMatchMode,
+---}
+---@return string | nil
+function lewdware.media.random_text(kind, opts) end
+
+---Get a random prompt from the pack, along with the look the pack gives it. The result can be
+---passed straight to [spawn_prompt()](lua://lewdware.spawn_prompt).
+---@param opts? {
+---   tags?: string[],
+---   match?: TagMatchMode,
+---}
+---@return Prompt | nil
+function lewdware.media.random_prompt(opts) end
+
+---@class Prompt
+---@field text string The prompt, or the sum to solve for an arithmetic prompt.
+---@field kind '"repeat-text"' | '"arithmetic"' | '"typing-test"'
+---@field answer? string What the user has to type, for an arithmetic prompt.
+---@field background? Image An image from the pack to draw behind the prompt.
+---@field background_color? string
+---@field text_color? string
+
+---Get a file ready ahead of time, so using it later doesn't stall: images are copied out of the
+---pack for [set_wallpaper()](lua://lewdware.set_wallpaper), and everything is checked against
+---its checksum if the user has that on. Call it a little before switching to a new wallpaper or
+---track, e.g. when the next part of your mode is coming up. Only the last few images preloaded
+---are kept.
+---@param media Image | Video | Audio
+function lewdware.media.preload(media) end
+
 ---Spawn a popup displaying an image.
 ---@param image Image
 ---@param opts? SpawnImageOpts
@@ -326,6 +423,46 @@ function lewdware.spawn_video_popup(video, opts) end
 ---  closed when the video ends.
 ---@field audio? boolean Whether to play the video's audio (if there is any). Defaults to true.
 
+---Spawn a popup for each image or video in `media`, next to each other on one monitor and all
+---the same height, e.g. for a set from [random_set()](lua://lewdware.media.random_set). The
+---windows appear together once they've all been spawned, and closing any of them closes the
+---rest.
+---@param media (Image | Video)[] The files, from left to right.
+---@param opts? SpawnPopupSetOpts
+---@return (ImageWindow | VideoWindow)[]
+function lewdware.spawn_popup_set(media, opts) end
+
+---@class SpawnPopupSetOpts : SpawnWindowOpts
+---Options for `spawn_popup_set()`. These apply to every window in the set, except that `x`, `y`
+---and `anchor` place the set as a whole, `height` is the height of each window, and `width` is
+---ignored (each window is as wide as its media at that height). By default, the set is as tall
+---as its shortest file, at most half the monitor's height, and shrunk to fit on the monitor.
+---
+---@field gap? integer The space between windows, in pixels (defaults to 0).
+---@field loop? boolean Whether to loop videos (defaults to true).
+---@field audio? boolean Whether to play the videos' audio. Defaults to true.
+
+---Show an image as a toast, e.g. one from [random_toast()](lua://lewdware.media.random_toast):
+---a small borderless popup in a corner of the monitor, stacked next to any toasts already there,
+---that closes itself after a few seconds.
+---@param image Image
+---@param opts? SpawnToastOpts
+---@return ImageWindow | nil window `nil` if there's no room left in the corner.
+function lewdware.spawn_toast(image, opts) end
+
+---@class SpawnToastOpts
+---@field corner? '"top-left"' | '"top-right"' | '"bottom-left"' | '"bottom-right"' The corner the
+---  toasts stack up from (defaults to `"bottom-right"`).
+---@field duration? integer How long the toast stays up, in milliseconds (defaults to 4000).
+---@field height? Coord The toast's height (defaults to 15% of the monitor's). It's made shorter if
+---  it would be more than two fifths of the monitor wide.
+---@field gap? integer The space between toasts, in pixels (defaults to 8).
+---@field margin? integer The space between the toasts and the edges of the monitor, in pixels
+---  (defaults to 16).
+---@field monitor? Monitor Defaults to the primary monitor.
+---@field opacity? number
+---@field click_through? boolean
+
 ---Play an audio file.
 ---@param audio Audio
 ---@param opts? PlayAudioOpts
@@ -353,9 +490,13 @@ function AudioHandle:pause() end
 function AudioHandle:play() end
 
 ---Set the current wallpaper.
----@param image Image
+---
+---Videos are played muted and on loop in a borderless window behind all other windows on each
+---monitor. They pause while several video popups are open, to save CPU. `opts.mode` only applies
+---to images.
+---@param media Image|Video
 ---@param opts? SetWallpaperOpts
-function lewdware.set_wallpaper(image, opts) end
+function lewdware.set_wallpaper(media, opts) end
 
 ---Set the current wallpaper to the user's default.
 function lewdware.reset_wallpaper() end
@@ -375,6 +516,16 @@ function lewdware.spawn_prompt(opts) end
 ---@field placeholder? string A placeholder value that is shown in the text input before the user
 ---  has typed anything.
 ---@field initial_value? string An initial value for the text input.
+---@field background? Image An image drawn behind the prompt, cropped to cover it.
+---@field text_color? string The colour of the prompt's text, e.g. `"#ffd700"`.
+---@field kind? '"repeat-text"' | '"arithmetic"' | '"typing-test"' Whether the user has to type
+---  `text` back, solve it, or type it back before a timer runs out. Changes the prompt's heading.
+---@field answer? string What counts as a correct answer, if it isn't `text` itself.
+---@field time_limit? number Seconds to type a typing test's passage in. By default, half a second
+---  per character, and at least 10 seconds.
+---@field accuracy? number The percentage of a typing test's passage that has to be typed
+---  correctly, from 0 to 100 and 90 by default. Running out of time or falling short counts as
+---  failing the prompt.
 
 ---Spawn a choice popup. This will present the user with one or more options to click.
 ---@param opts? SpawnChoiceOpts
@@ -388,6 +539,22 @@ function lewdware.spawn_choice(opts) end
 ---@field options { id: string, label: string }[] The list of options, which determine the buttons
 ---  to present to the user. Only the label is displayed, the id is used in `on_select()`.
 
+---Spawn a minigame popup: a target bounces around the popup, and it only closes once the user has
+---clicked the target enough times. It has no close button. Returns nil if the user has turned
+---minigames off, so the mode can spawn something else instead.
+---@param opts? SpawnMinigameOpts
+---@return MinigameWindow | nil
+function lewdware.spawn_minigame(opts) end
+
+---@class SpawnMinigameOpts : SpawnWindowOpts
+---Options that can be passed into `spawn_minigame()`.
+---
+---@field hits? integer How many times the target has to be clicked. Defaults to 5.
+---@field target_size? number The target's width in pixels. Defaults to 40.
+---@field speed? number How fast the target starts off moving, in pixels per second. It speeds up
+---  with every hit. Defaults to 150.
+---@field target_color? string The target's colour, e.g. `"#e0245e"`.
+
 ---Spawn a popup displaying text.
 ---@param text string
 ---@param opts? SpawnTextOpts
@@ -435,11 +602,44 @@ function lewdware.open_link(url) end
 ---@field summary? string
 ---@field body string
 
----Show a notification
+---Show a notification. `{popups}`, `{minutes}`, `{prompts}`, `{prompts_failed}`, `{choices}`,
+---`{popups_closed}`, `{links}` and `{uptime}` in the summary or body are replaced with the
+---session's counts so far, so texts from the pack can count along (e.g. "That's {popups} popups
+---already").
 ---@param notification Notification
 function lewdware.show_notification(notification) end
 
----Call a function after a certain period of time.
+---The multiplier set by the intensity dial in the tray menu. This is `1` by default, and ranges
+---from `0` to `2`. Modes should scale how often they spawn popups, and how many they allow at
+---once, by this value.
+---
+---While a laptop is running on its battery, or the app is struggling to keep up, this is scaled
+---down further. Random queries also return images instead of videos (`random_video()` returns
+---nothing), and on battery `lewdware.set_wallpaper()` does nothing.
+---@return number
+function lewdware.intensity() end
+
+---Run a function whenever the user changes the intensity dial, or the battery saver or throttle
+---turns on or off.
+---@param fun fun(multiplier: number) Called with the new value of `lewdware.intensity()`.
+function lewdware.on_intensity_change(fun) end
+
+---@class Goal
+---@field name string
+---@field stat "minutes"|"popups"|"prompts"|"choices" What the goal counts. Popups are counted
+---  when they're spawned, prompts when they're answered and choices when they're made. Minutes
+---  don't count while the session is paused.
+---@field target number
+---@field popup_set? string A popup set in the pack, shown when the goal is reached.
+
+---Run a function whenever the user reaches one of the session's goals, which they set in the
+---config app, or the pack sets. The user is already notified, and the goal's popup set shown,
+---so this is for anything else the mode wants to do.
+---@param fun fun(goal: Goal)
+function lewdware.on_goal(fun) end
+
+---Call a function after a certain period of time. While the session is paused (e.g. the screen
+---is locked), the call is held back until it's resumed.
 ---@param duration number The amount of time to wait for, in milliseconds.
 ---@param fun fun() The function to run.
 ---@return Timer
@@ -452,7 +652,8 @@ Timer = {}
 ---Stop a timer from running
 function Timer:stop() end
 
----Periodically run a function
+---Periodically run a function. While the session is paused (e.g. the screen is locked), it
+---isn't run; it's run once on resuming, and then every `duration` milliseconds from then.
 ---@param duration number The function will be run every `duration` milliseconds.
 ---@param fun fun() The function to run.
 ---@return Interval
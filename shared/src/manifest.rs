@@ -0,0 +1,144 @@
+//! A JSON-friendly listing of a pack's contents, without any of the media data.
+
+use std::collections::HashMap;
+
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+
+use crate::read_pack::Metadata;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Manifest {
+    #[serde(flatten)]
+    pub metadata: Metadata,
+    pub entries: Vec<ManifestEntry>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ManifestEntry {
+    pub id: u64,
+    pub name: String,
+    /// The path the file was originally added from, if recorded.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    pub file_type: String,
+    /// The format images are stored in.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub codec: Option<String>,
+    /// Size of the encoded file in bytes.
+    pub size: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub width: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub height: Option<u64>,
+    /// Duration in seconds, for videos and audio.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub duration: Option<f64>,
+    /// Hex-encoded content hash, so entries can be matched between versions of a pack.
+    pub hash: String,
+    pub tags: Vec<String>,
+}
+
+impl Manifest {
+    /// Build a manifest from a pack index. Entries are ordered by id, and each entry's tags are
+    /// sorted, so that manifests of the same pack diff cleanly.
+    pub fn read(db: &Connection, metadata: Metadata) -> rusqlite::Result<Self> {
+        let mut stmt = db.prepare(
+            "SELECT id, file_name, path, file_type, length, width, height, duration, hash,
+                 CASE WHEN file_type = 'image' THEN COALESCE(codec, 'avif') END AS codec
+             FROM media ORDER BY id",
+        )?;
+
+        let mut entries = stmt
+            .query_map([], |row| {
+                Ok(ManifestEntry {
+                    id: row.get("id")?,
+                    name: row.get("file_name")?,
+                    path: row.get("path")?,
+                    file_type: row.get("file_type")?,
+                    codec: row.get("codec")?,
+                    size: row.get::<_, Option<u64>>("length")?.unwrap_or(0),
+                    width: row.get("width")?,
+                    height: row.get("height")?,
+                    duration: row.get("duration")?,
+                    hash: to_hex(&row.get::<_, Vec<u8>>("hash")?),
+                    tags: vec![],
+                })
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+
+        let id_to_idx: HashMap<u64, usize> =
+            entries.iter().enumerate().map(|(i, e)| (e.id, i)).collect();
+
+        let mut tag_stmt = db.prepare(
+            "SELECT media_tags.media_id, tags.name
+             FROM media_tags JOIN tags ON media_tags.tag_id = tags.id
+             ORDER BY tags.name",
+        )?;
+        let tag_rows = tag_stmt.query_map([], |row| {
+            Ok((row.get::<_, u64>("media_id")?, row.get::<_, String>("name")?))
+        })?;
+        for row in tag_rows {
+            let (media_id, name) = row?;
+            if let Some(&idx) = id_to_idx.get(&media_id) {
+                entries[idx].tags.push(name);
+            }
+        }
+
+        Ok(Self { metadata, entries })
+    }
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::migrate;
+
+    #[test]
+    fn manifest_lists_entries_with_sorted_tags() {
+        let db = Connection::open_in_memory().unwrap();
+        migrate(&db).unwrap();
+
+        db.execute_batch(
+            "INSERT INTO tags (name) VALUES ('zebra'), ('apple');
+             INSERT INTO media (file_name, file_type, length, width, height, hash)
+                 VALUES ('pic.avif', 'image', 1234, 64, 32, x'abcd');
+             INSERT INTO media (file_name, file_type, length, duration, hash)
+                 VALUES ('song.opus', 'audio', 99, 2.5, x'01');
+             INSERT INTO media_tags (media_id, tag_id) VALUES (1, 1), (1, 2);",
+        )
+        .unwrap();
+
+        let manifest = Manifest::read(
+            &db,
+            Metadata {
+                name: "test-pack".to_string(),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        assert_eq!(manifest.metadata.name, "test-pack");
+        assert_eq!(manifest.entries.len(), 2);
+
+        let pic = &manifest.entries[0];
+        assert_eq!(pic.name, "pic.avif");
+        assert_eq!(pic.size, 1234);
+        assert_eq!((pic.width, pic.height), (Some(64), Some(32)));
+        assert_eq!(pic.hash, "abcd");
+        assert_eq!(pic.codec.as_deref(), Some("avif"));
+        assert_eq!(pic.tags, vec!["apple", "zebra"]);
+
+        let song = &manifest.entries[1];
+        assert_eq!(song.duration, Some(2.5));
+        assert!(song.tags.is_empty());
+
+        let json = serde_json::to_value(&manifest).unwrap();
+        assert_eq!(json["name"], "test-pack");
+        assert!(json["entries"][1].get("width").is_none());
+    }
+}
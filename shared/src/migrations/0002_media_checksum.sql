@@ -0,0 +1,3 @@
+-- BLAKE3 hash of each media file's bytes as stored in the pack (unlike `hash`, which is the hash
+-- of the original source file). NULL for media added before this column existed.
+ALTER TABLE media ADD COLUMN checksum BLOB;
@@ -0,0 +1,3 @@
+-- The format an image is stored in ('avif', 'webp' or 'jxl'). NULL for images added before this
+-- column existed, which are all AVIF, and for videos and audio.
+ALTER TABLE media ADD COLUMN codec TEXT;
@@ -0,0 +1,69 @@
+-- Running totals of files and bytes per media type and per tag, kept up to date by the triggers
+-- below, so a pack's composition can be shown without scanning the media table.
+CREATE TABLE IF NOT EXISTS type_stats (
+    file_type TEXT PRIMARY KEY,
+    count INTEGER NOT NULL,
+    bytes INTEGER NOT NULL
+) STRICT;
+
+CREATE TABLE IF NOT EXISTS tag_stats (
+    tag_id INTEGER PRIMARY KEY,
+    count INTEGER NOT NULL,
+    bytes INTEGER NOT NULL
+) STRICT;
+
+-- Foreign keys aren't enforced, so deleting media used to leave its tag links behind.
+DELETE FROM media_tags WHERE media_id NOT IN (SELECT id FROM media);
+
+INSERT INTO type_stats (file_type, count, bytes)
+SELECT file_type, COUNT(*), SUM(COALESCE(length, 0)) FROM media GROUP BY file_type;
+
+INSERT INTO tag_stats (tag_id, count, bytes)
+SELECT media_tags.tag_id, COUNT(*), SUM(COALESCE(media.length, 0))
+FROM media_tags JOIN media ON media.id = media_tags.media_id
+GROUP BY media_tags.tag_id;
+
+CREATE TRIGGER media_stats_insert AFTER INSERT ON media
+BEGIN
+    INSERT INTO type_stats (file_type, count, bytes) VALUES (NEW.file_type, 1, COALESCE(NEW.length, 0))
+    ON CONFLICT (file_type) DO UPDATE SET count = count + 1, bytes = bytes + excluded.bytes;
+END;
+
+-- Unlinking the tags first lets `media_tags_stats_delete` take the file's size off each tag.
+CREATE TRIGGER media_stats_delete BEFORE DELETE ON media
+BEGIN
+    DELETE FROM media_tags WHERE media_id = OLD.id;
+    UPDATE type_stats SET count = count - 1, bytes = bytes - COALESCE(OLD.length, 0)
+    WHERE file_type = OLD.file_type;
+END;
+
+CREATE TRIGGER media_stats_update AFTER UPDATE OF file_type, length ON media
+BEGIN
+    UPDATE type_stats SET count = count - 1, bytes = bytes - COALESCE(OLD.length, 0)
+    WHERE file_type = OLD.file_type;
+    INSERT INTO type_stats (file_type, count, bytes) VALUES (NEW.file_type, 1, COALESCE(NEW.length, 0))
+    ON CONFLICT (file_type) DO UPDATE SET count = count + 1, bytes = bytes + excluded.bytes;
+    UPDATE tag_stats SET bytes = bytes - COALESCE(OLD.length, 0) + COALESCE(NEW.length, 0)
+    WHERE tag_id IN (SELECT tag_id FROM media_tags WHERE media_id = NEW.id);
+END;
+
+CREATE TRIGGER media_tags_stats_insert AFTER INSERT ON media_tags
+BEGIN
+    INSERT INTO tag_stats (tag_id, count, bytes)
+    VALUES (NEW.tag_id, 1, COALESCE((SELECT length FROM media WHERE id = NEW.media_id), 0))
+    ON CONFLICT (tag_id) DO UPDATE SET count = count + 1, bytes = bytes + excluded.bytes;
+END;
+
+CREATE TRIGGER media_tags_stats_delete AFTER DELETE ON media_tags
+BEGIN
+    UPDATE tag_stats
+    SET count = count - 1,
+        bytes = bytes - COALESCE((SELECT length FROM media WHERE id = OLD.media_id), 0)
+    WHERE tag_id = OLD.tag_id;
+END;
+
+CREATE TRIGGER tags_stats_delete AFTER DELETE ON tags
+BEGIN
+    DELETE FROM media_tags WHERE tag_id = OLD.id;
+    DELETE FROM tag_stats WHERE tag_id = OLD.id;
+END;
@@ -0,0 +1,11 @@
+-- Notifications, prompts and links bundled with a pack, so modes don't have to hard-code them.
+-- `tags` is a JSON array of tag names. These are matched by name, and aren't linked to the
+-- `tags` table, which only covers media.
+CREATE TABLE IF NOT EXISTS texts (
+    id INTEGER PRIMARY KEY,
+    kind TEXT CHECK (kind IN ('notification', 'prompt', 'link')) NOT NULL,
+    text TEXT NOT NULL,
+    tags TEXT NOT NULL DEFAULT '[]'
+) STRICT;
+
+CREATE INDEX texts_kind_index ON texts (kind);
@@ -0,0 +1,7 @@
+-- Per-file settings from sidecar files (see `shared::sidecar`). NULL when a file doesn't set them.
+-- `category` is free-form, e.g. 'wallpaper'. `weight` scales how likely the file is to be picked
+-- at random (1 by default), and `cooldown` is the minimum number of seconds between showings.
+ALTER TABLE media ADD COLUMN caption TEXT;
+ALTER TABLE media ADD COLUMN category TEXT;
+ALTER TABLE media ADD COLUMN weight REAL;
+ALTER TABLE media ADD COLUMN cooldown REAL;
@@ -0,0 +1,6 @@
+-- "Only after" gates from sidecar files (see `shared::sidecar::OnlyAfter`): a file with
+-- `after_count` set is only picked once the file named `after_file`, or any file tagged
+-- `after_tag`, has been shown that many times in the session. At most one of the two is set.
+ALTER TABLE media ADD COLUMN after_file TEXT;
+ALTER TABLE media ADD COLUMN after_tag TEXT;
+ALTER TABLE media ADD COLUMN after_count INTEGER;
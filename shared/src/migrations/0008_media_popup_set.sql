@@ -0,0 +1,4 @@
+-- Popup sets from sidecar files: media with the same `popup_set` is spawned together, side by
+-- side, in order of file name.
+ALTER TABLE media ADD COLUMN popup_set TEXT;
+CREATE INDEX media_popup_set_index ON media (popup_set);
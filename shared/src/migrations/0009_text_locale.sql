@@ -0,0 +1,3 @@
+-- The language a text is in, as a tag like `pt-BR`. Texts without one are shown to everyone who
+-- doesn't have any in their own language.
+ALTER TABLE texts ADD COLUMN locale TEXT;
@@ -0,0 +1,3 @@
+-- The content rating level of a file from its sidecar, counting from 0 for the mildest. Files
+-- can also be rated through their tags, in the pack's metadata.
+ALTER TABLE media ADD COLUMN rating INTEGER;
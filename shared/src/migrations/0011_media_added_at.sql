@@ -0,0 +1,4 @@
+-- When the file was added to the pack, in seconds since the Unix epoch, so packs can have new
+-- files picked more often (see `recency_bias` in the pack's metadata). NULL for files added by
+-- older pack editors, which count as old.
+ALTER TABLE media ADD COLUMN added_at INTEGER;
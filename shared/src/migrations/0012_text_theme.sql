@@ -0,0 +1,6 @@
+-- How a prompt looks, so packs can match prompts to their content (see `PromptTheme` in
+-- `shared::texts`). `background` is the file name of an image in the pack, and the colors are
+-- `#rrggbb` or `#rrggbbaa`. NULL leaves the default look.
+ALTER TABLE texts ADD COLUMN background TEXT;
+ALTER TABLE texts ADD COLUMN background_color TEXT;
+ALTER TABLE texts ADD COLUMN text_color TEXT;
@@ -0,0 +1,3 @@
+-- The container a video is stored in ('mp4' or 'webm'). NULL for videos added before this column
+-- existed, which are all MP4, and for images and audio.
+ALTER TABLE media ADD COLUMN container TEXT;
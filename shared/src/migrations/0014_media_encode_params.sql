@@ -0,0 +1,6 @@
+-- The settings a file was encoded with: the ffmpeg encoder, its quality setting (in the encoder's
+-- own units) and the longest side it was scaled down to. NULL for audio, and for files added
+-- before these columns existed.
+ALTER TABLE media ADD COLUMN encoder TEXT;
+ALTER TABLE media ADD COLUMN quality REAL;
+ALTER TABLE media ADD COLUMN max_size INTEGER;
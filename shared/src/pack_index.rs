@@ -0,0 +1,208 @@
+//! Opening a pack's index and looking up where its files are, shared by the engine, `lw` and the
+//! pack editor so they all read packs the same way. The pack editor keeps its own copy of the
+//! index on disk, since it writes to it, but looks its files up with [`stored_media`] too.
+
+use std::io::{Read, Seek};
+
+use rusqlite::{Connection, MAIN_DB, OptionalExtension, Row};
+
+use crate::{
+    db::migrate,
+    encode::{ImageCodec, InvalidFileType, VideoContainer},
+    read_pack::{Header, Metadata, OpenProgress, read_index, read_pack_metadata},
+};
+
+/// A pack's header and metadata, and its index, loaded into memory and migrated to the current
+/// schema.
+pub struct PackIndex {
+    pub header: Header,
+    pub metadata: Metadata,
+    pub db: Connection,
+}
+
+impl PackIndex {
+    /// Reads the pack in `file`, calling `on_progress` as it goes, since reading the index of a
+    /// large pack can take a while.
+    pub fn read<F: Read + Seek>(
+        mut file: F,
+        mut on_progress: impl FnMut(OpenProgress),
+    ) -> anyhow::Result<Self> {
+        let (header, metadata) = read_pack_metadata(&mut file)?;
+
+        // Load the SQLite database straight into memory (no temp file: `deserialize_read_exact`
+        // hands the bytes we just read directly to SQLite's own in-memory representation via
+        // `sqlite3_deserialize`).
+        let data = read_index(&mut file, &header, &mut on_progress)?;
+
+        let mut db = Connection::open_in_memory()?;
+        db.deserialize_read_exact(MAIN_DB, data.as_slice(), data.len(), false)?;
+
+        on_progress(OpenProgress::Migrating);
+        migrate(&db)?;
+
+        Ok(Self {
+            header,
+            metadata,
+            db,
+        })
+    }
+}
+
+/// A file in the pack: what it's called, where its data is, and the format it's stored in.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StoredMedia {
+    pub id: u64,
+    /// The name it was added to the pack under.
+    pub file_name: String,
+    pub file_type: String,
+    /// Where the data is in the pack. `None` for a file that's been added to the index but not
+    /// written to the pack yet, which only a damaged pack should have.
+    pub offset: Option<u64>,
+    pub length: Option<u64>,
+    /// Where the pack editor has staged the file, relative to its media directory, until it's
+    /// written to the pack.
+    pub path: Option<String>,
+    pub codec: Option<String>,
+    pub container: Option<String>,
+    /// The BLAKE3 hash of the data, if the pack editor that added it recorded one.
+    pub checksum: Option<Vec<u8>>,
+}
+
+impl StoredMedia {
+    /// The offset and length of the data, if there's any in the pack.
+    pub fn range(&self) -> Option<(u64, u64)> {
+        self.offset.zip(self.length)
+    }
+
+    /// The format an image is stored in. Images added before the codec column existed are all
+    /// AVIF.
+    pub fn image_codec(&self) -> Result<ImageCodec, InvalidFileType> {
+        self.codec
+            .as_deref()
+            .map_or(Ok(ImageCodec::Avif), str::parse)
+    }
+
+    /// The container a video is stored in, if it was recorded and this version knows it.
+    pub fn video_container(&self) -> Option<VideoContainer> {
+        self.container.as_deref()?.parse().ok()
+    }
+
+    /// The content type of the format the file is stored in, e.g. to serve it with. `None` for
+    /// formats this version doesn't know.
+    pub fn mime_type(&self) -> Option<&'static str> {
+        match self.file_type.as_str() {
+            "image" => self.image_codec().ok().map(|codec| codec.mime_type()),
+            "video" => self
+                .container
+                .as_deref()
+                .map_or(Ok(VideoContainer::Mp4), str::parse::<VideoContainer>)
+                .ok()
+                .map(|container| container.mime_type()),
+            "audio" => Some("audio/ogg"),
+            _ => None,
+        }
+    }
+
+    /// The extension of the format the file is stored in, e.g. to extract it as. Videos added
+    /// before the container column existed are all MP4, and audio is always Opus.
+    pub fn extension(&self) -> Option<&str> {
+        match self.file_type.as_str() {
+            "image" => Some(self.codec.as_deref().unwrap_or("avif")),
+            "video" => Some(self.container.as_deref().unwrap_or("mp4")),
+            "audio" => Some("opus"),
+            _ => None,
+        }
+    }
+
+    /// Reads a row selected with [`COLUMNS`]. Any other columns are ignored.
+    pub(crate) fn from_row(row: &Row) -> rusqlite::Result<Self> {
+        Ok(Self {
+            id: row.get("id")?,
+            file_name: row.get("file_name")?,
+            file_type: row.get("file_type")?,
+            offset: row.get("offset")?,
+            length: row.get("length")?,
+            path: row.get("path")?,
+            codec: row.get("codec")?,
+            container: row.get("container")?,
+            checksum: row.get("checksum")?,
+        })
+    }
+}
+
+/// The columns of the media table [`StoredMedia`] is read from.
+pub(crate) const COLUMNS: &str =
+    "id, file_name, file_type, \"offset\", length, path, codec, container, checksum";
+
+/// The file with this id, if there is one.
+pub fn stored_media(db: &Connection, id: u64) -> rusqlite::Result<Option<StoredMedia>> {
+    db.query_row(
+        &format!("SELECT {COLUMNS} FROM media WHERE id = ?"),
+        [id],
+        StoredMedia::from_row,
+    )
+    .optional()
+}
+
+/// Every file in the pack, ordered by id.
+pub fn all_stored_media(db: &Connection) -> rusqlite::Result<Vec<StoredMedia>> {
+    let mut stmt = db.prepare(&format!("SELECT {COLUMNS} FROM media ORDER BY id"))?;
+    stmt.query_map([], StoredMedia::from_row)?.collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use crate::write_pack::PackWriter;
+
+    use super::*;
+
+    #[test]
+    fn reads_a_pack_and_its_files() {
+        let db = Connection::open_in_memory().unwrap();
+        migrate(&db).unwrap();
+        db.execute_batch(
+            "INSERT INTO media (file_name, file_type, hash, \"offset\", length, codec, container)
+             VALUES ('a.png', 'image', x'0a', 100, 10, NULL, NULL),
+                    ('b.png', 'image', x'0b', 110, 20, 'webp', NULL),
+                    ('c.mkv', 'video', x'0c', 130, 30, NULL, 'webm'),
+                    ('d.mkv', 'video', x'0d', NULL, NULL, NULL, 'h266');",
+        )
+        .unwrap();
+        let index = db.serialize(MAIN_DB).unwrap();
+
+        let metadata = Metadata {
+            name: "test-pack".to_string(),
+            ..Default::default()
+        };
+        let (pack, _) = PackWriter::new(Cursor::new(Vec::new()))
+            .finish(index.as_slice(), &metadata)
+            .unwrap();
+
+        let mut reports = Vec::new();
+        let index = PackIndex::read(Cursor::new(pack.into_inner()), |p| reports.push(p)).unwrap();
+        assert_eq!(index.metadata, metadata);
+        assert_eq!(reports.last(), Some(&OpenProgress::Migrating));
+
+        let files = all_stored_media(&index.db).unwrap();
+        assert_eq!(files.len(), 4);
+
+        assert_eq!(files[0].image_codec().unwrap(), ImageCodec::Avif);
+        assert_eq!(files[1].image_codec().unwrap(), ImageCodec::Webp);
+        assert_eq!(files[1].range(), Some((110, 20)));
+        assert_eq!(files[2].video_container(), Some(VideoContainer::Webm));
+        assert_eq!(files[2].extension(), Some("webm"));
+        assert_eq!(files[2].mime_type(), Some("video/webm"));
+        assert_eq!(files[0].mime_type(), Some("image/avif"));
+        assert_eq!(files[3].range(), None);
+        assert_eq!(files[3].video_container(), None);
+        assert_eq!(files[3].mime_type(), None);
+
+        assert_eq!(
+            stored_media(&index.db, files[2].id).unwrap().as_ref(),
+            Some(&files[2])
+        );
+        assert_eq!(stored_media(&index.db, 100).unwrap(), None);
+    }
+}
@@ -0,0 +1,84 @@
+//! Everything Lewdware accesses on the user's system, shown as a privacy report in the config app.
+//!
+//! The camera and microphone are never accessed. The tests below back that up by checking that
+//! nothing which can capture from them is built in: ffmpeg is built without libavdevice, rodio
+//! without `recording`, and no camera crates are in the lockfile. If you add a new kind of system
+//! access, list it here too.
+
+/// Every kind of system access, by id. The config app describes each one, and what it's used
+/// for, in its `config-privacy-<id>` message.
+pub const SYSTEM_ACCESS: &[&str] = &[
+    "keyboard",
+    "wallpaper",
+    "notifications",
+    "browser",
+    "windows",
+    "audio",
+    "power",
+    "files",
+    "network",
+];
+
+/// Never accessed, by any of the apps.
+pub const NEVER_ACCESSED: &[&str] = &["camera", "microphone", "screen-recording"];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::locale::{FALLBACK, Localizer};
+
+    const LOCKFILE: &str = include_str!("../../Cargo.lock");
+    const LEWDWARE_MANIFEST: &str = include_str!("../../lewdware/Cargo.toml");
+
+    /// Crates whose whole purpose is capturing from a camera or microphone.
+    const CAPTURE_CRATES: &[&str] = &[
+        "escapi",
+        "gstreamer",
+        "libcamera",
+        "nokhwa",
+        "opencv",
+        "uvc",
+        "v4l",
+        "v4l2-sys-mit",
+    ];
+
+    fn dependency_line(name: &str) -> &'static str {
+        LEWDWARE_MANIFEST
+            .lines()
+            .find(|line| line.starts_with(&format!("{name} = ")))
+            .unwrap_or_else(|| panic!("{name} isn't a dependency"))
+    }
+
+    #[test]
+    fn no_capture_crates_are_linked() {
+        for name in CAPTURE_CRATES {
+            assert!(
+                !LOCKFILE.contains(&format!("name = \"{name}\"\n")),
+                "{name} can capture from a camera or microphone"
+            );
+        }
+    }
+
+    #[test]
+    fn no_capture_features_are_enabled() {
+        // Both would pull in the capture features by default.
+        let ffmpeg = dependency_line("ffmpeg-next");
+        assert!(ffmpeg.contains("default-features = false"));
+        assert!(!ffmpeg.contains("\"device\""));
+
+        let rodio = dependency_line("rodio");
+        assert!(rodio.contains("default-features = false"));
+        assert!(!rodio.contains("\"recording\""));
+    }
+
+    #[test]
+    fn everything_is_described() {
+        let messages = Localizer::new(FALLBACK).messages();
+        for id in SYSTEM_ACCESS {
+            assert!(messages.contains_key(&format!("config-privacy-{id}.reason")));
+        }
+        for id in NEVER_ACCESSED {
+            assert!(messages.contains_key(&format!("config-privacy-{id}")));
+        }
+    }
+}
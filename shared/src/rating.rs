@@ -0,0 +1,110 @@
+//! Content ratings, so users can keep a session to the milder parts of a pack. A pack's ratings
+//! are levels, mildest first: a file can be rated in its sidecar, and whole tags in the pack's
+//! metadata ([`Metadata::tag_ratings`]). A file counts as rated at the highest of its own rating
+//! and its tags', and files with no rating at all are always shown.
+//!
+//! Each pack can name its own levels ([`Metadata::ratings`]), and the user picks the highest they
+//! want to see (`max_rating` in the config), which the player leaves everything above out of.
+
+use std::collections::BTreeMap;
+
+use crate::read_pack::Metadata;
+
+/// The levels used by packs that don't name their own.
+pub const DEFAULT_RATINGS: [&str; 2] = ["Softcore", "Hardcore"];
+
+impl Metadata {
+    /// The names of the pack's rating levels, mildest first.
+    pub fn rating_names(&self) -> Vec<String> {
+        if self.ratings.is_empty() {
+            DEFAULT_RATINGS
+                .iter()
+                .map(|name| name.to_string())
+                .collect()
+        } else {
+            self.ratings.clone()
+        }
+    }
+}
+
+/// The tags in `tag_ratings` rated above `max_rating`.
+pub fn tags_above(max_rating: u32, tag_ratings: &BTreeMap<String, u32>) -> Vec<String> {
+    tag_ratings
+        .iter()
+        .filter(|&(_, &rating)| rating > max_rating)
+        .map(|(tag, _)| tag.clone())
+        .collect()
+}
+
+/// A condition on the `media` table leaving out files rated above `max_rating`, either in their
+/// own right or through one of their tags. Takes the names of the tags rated above it (see
+/// [`tags_above`]) as parameters, in order.
+pub fn media_filter(max_rating: u32, tags_above: &[String]) -> String {
+    let mut filter = format!("(media.rating IS NULL OR media.rating <= {max_rating})");
+
+    if !tags_above.is_empty() {
+        filter.push_str(&format!(
+            " AND media.id NOT IN (SELECT media_tags.media_id FROM media_tags
+                JOIN tags ON tags.id = media_tags.tag_id WHERE tags.name IN ({}))",
+            vec!["?"; tags_above.len()].join(", ")
+        ));
+    }
+
+    filter
+}
+
+/// A condition on the `texts` table leaving out texts with any of `tags_above`, which it takes as
+/// parameters. Texts don't have ratings of their own.
+pub fn text_filter(tags_above: &[String]) -> Option<String> {
+    (!tags_above.is_empty()).then(|| {
+        format!(
+            "NOT EXISTS (SELECT 1 FROM json_each(texts.tags) WHERE value IN ({}))",
+            vec!["?"; tags_above.len()].join(", ")
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use rusqlite::{Connection, params_from_iter};
+
+    use super::*;
+    use crate::db::migrate;
+
+    #[test]
+    fn leaves_out_media_rated_too_high() {
+        let db = Connection::open_in_memory().unwrap();
+        migrate(&db).unwrap();
+
+        db.execute_batch(
+            "INSERT INTO tags (name) VALUES ('mild'), ('wild');
+             INSERT INTO media (file_name, file_type, length, hash, rating)
+                 VALUES ('unrated.png', 'image', 1, x'01', NULL),
+                        ('soft.png', 'image', 1, x'02', 0),
+                        ('hard.png', 'image', 1, x'03', 1),
+                        ('tagged.png', 'image', 1, x'04', 0);
+             INSERT INTO media_tags (media_id, tag_id) VALUES (2, 1), (4, 2);",
+        )
+        .unwrap();
+
+        let tag_ratings = BTreeMap::from([("mild".to_string(), 0), ("wild".to_string(), 1)]);
+        let names = |max_rating| {
+            let tags = tags_above(max_rating, &tag_ratings);
+            let sql = format!(
+                "SELECT file_name FROM media WHERE {} ORDER BY id",
+                media_filter(max_rating, &tags)
+            );
+            let mut stmt = db.prepare(&sql).unwrap();
+            stmt.query_map(params_from_iter(&tags), |row| row.get::<_, String>(0))
+                .unwrap()
+                .collect::<rusqlite::Result<Vec<_>>>()
+                .unwrap()
+        };
+
+        assert_eq!(names(0), ["unrated.png", "soft.png"]);
+        assert_eq!(
+            names(1),
+            ["unrated.png", "soft.png", "hard.png", "tagged.png"]
+        );
+    }
+}
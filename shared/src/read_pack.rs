@@ -1,19 +1,73 @@
 use std::{
+    collections::BTreeMap,
     error, fmt,
     io::{self, Cursor, Read, Seek, SeekFrom, Write},
 };
 
 use ciborium::{from_reader, into_writer};
 use serde::{Deserialize, Serialize};
-use tokio::io::{AsyncRead, AsyncSeek};
+use tokio::io::{AsyncRead, AsyncSeek, AsyncWrite};
 use uuid::Uuid;
 
+use crate::{
+    encode::{ImageCodec, VideoContainer},
+    goals::{Goal, Milestone},
+    locale::{tr, tr_args},
+    texts::PromptKind,
+};
+
 pub const MAGIC: &[u8; 6] = b"LWPACK";
-pub const VERSION: u8 = 0;
+/// The first version of the format, which stores the index and metadata as they are.
+pub const VERSION_1: u8 = 0;
+/// Stores the index and metadata zstd-compressed. The index of a large pack can take up a lot of
+/// space otherwise, and compresses well.
+pub const VERSION_2: u8 = 1;
+/// The version new packs are written in.
+pub const VERSION: u8 = VERSION_2;
 pub const HEADER_SIZE: usize = 64;
+/// Packs are written far less often than they're read, so this leans towards smaller files.
+pub(crate) const COMPRESSION_LEVEL: i32 = 10;
+/// How much of the index is read between progress reports while opening a pack.
+const INDEX_CHUNK_SIZE: usize = 4 << 20;
+
+/// How far along opening a pack is. Indexes of large packs can take a few seconds to pull out of
+/// the pack file, so callers pass this on to the user.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(tag = "stage", rename_all = "snake_case")]
+pub enum OpenProgress {
+    /// Copying the SQLite index out of the pack file.
+    ExtractingIndex {
+        done: u64,
+        total: u64,
+    },
+    /// Bringing the index up to the current schema.
+    Migrating,
+    /// Building lookup tables from the index, e.g. the tag map.
+    Loading,
+    Done,
+}
+
+impl fmt::Display for OpenProgress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OpenProgress::ExtractingIndex { done, total } => {
+                let percent = if *total == 0 { 100 } else { done * 100 / total };
+                let message = tr_args(
+                    "common-open-progress-reading-index",
+                    [("percent", percent.into())],
+                );
+                write!(f, "{message}")
+            }
+            OpenProgress::Migrating => write!(f, "{}", tr("common-open-progress-migrating")),
+            OpenProgress::Loading => write!(f, "{}", tr("common-open-progress-loading")),
+            OpenProgress::Done => write!(f, "{}", tr("common-open-progress-done")),
+        }
+    }
+}
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Header {
+    pub version: u8,
     pub index_offset: u64,
     pub index_length: u64,
     pub metadata_offset: u64,
@@ -30,6 +84,74 @@ pub struct Metadata {
     pub description: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub version: Option<String>,
+    /// The format new images are encoded to. Only used by the pack editor: players go by each
+    /// image's own codec, since a pack can mix formats.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub image_codec: Option<ImageCodec>,
+    /// The container new videos are written to. Like `image_codec`, only used by the pack editor:
+    /// each video records its own in the index.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub video_container: Option<VideoContainer>,
+    /// The most space (in bytes) the files with each tag should take up, e.g. to keep a pack
+    /// under a distribution limit. Only used by the pack editor.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub size_budgets: BTreeMap<String, u64>,
+    /// Goals for every session played with the pack, on top of the user's own.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub goals: Vec<Goal>,
+    /// Tags held back until part way into each session.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub milestones: Vec<Milestone>,
+    /// Names for the pack's content rating levels, mildest first (see [`crate::rating`]). Unset
+    /// uses [`DEFAULT_RATINGS`](crate::rating::DEFAULT_RATINGS).
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub ratings: Vec<String>,
+    /// The rating level of the files with each tag.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub tag_ratings: BTreeMap<String, u32>,
+    /// Picks recently added files more often at the start of each session.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub recency_bias: Option<RecencyBias>,
+    /// The kinds of prompt the pack pops up, picked between at random. Unset only pops up the
+    /// pack's own prompts.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub prompt_kinds: Vec<PromptKind>,
+    /// The license the pack as a whole is shared under, for anything not covered by a credit.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub license: Option<String>,
+    /// The people whose work went into the pack, shown from the player's tray.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub credits: Vec<Credit>,
+}
+
+/// Attribution for someone whose work is in a pack.
+#[derive(Serialize, Deserialize, Default, Clone, PartialEq, Eq, Debug)]
+pub struct Credit {
+    pub name: String,
+    /// What they did, e.g. "Artist" or "Voice".
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub role: Option<String>,
+    /// The license their work is used under, if it isn't the pack's own.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub license: Option<String>,
+    /// Where their work can be found.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub urls: Vec<String>,
+}
+
+/// Has random picks favor the files added to a pack most recently (going by the `added_at`
+/// column of the media table), so people who update the pack see what's new first. Wears off as
+/// the session goes on.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+pub struct RecencyBias {
+    /// How many times as often the newest file is picked as one that isn't recent, at the start
+    /// of a session.
+    pub boost: f64,
+    /// Files added up to this many days before the newest one count as recent, less so the older
+    /// they are.
+    pub days: u32,
+    /// How many minutes into a session the bias has worn off by.
+    pub minutes: u32,
 }
 
 impl Metadata {
@@ -78,6 +200,7 @@ impl Default for Header {
 impl Header {
     pub fn new() -> Self {
         Self {
+            version: VERSION,
             index_offset: 0,
             index_length: 0,
             metadata_offset: 0,
@@ -97,7 +220,7 @@ impl Header {
         let mut cursor = Cursor::new(&mut buffer as &mut [u8]);
 
         cursor.write_all(MAGIC)?; // 6 bytes
-        cursor.write_all(&VERSION.to_le_bytes())?; // 1 byte
+        cursor.write_all(&self.version.to_le_bytes())?; // 1 byte
         cursor.write_all(&[0u8])?; // 1 byte
         cursor.write_all(&self.index_offset.to_le_bytes())?; // 8 bytes
         cursor.write_all(&self.index_length.to_le_bytes())?; // 8 bytes
@@ -149,6 +272,7 @@ impl Header {
         let id = Uuid::from_bytes(buf16);
 
         Ok(Self {
+            version,
             index_offset,
             index_length,
             metadata_offset,
@@ -163,11 +287,41 @@ impl Header {
             && self.metadata_offset == 0
             && self.metadata_length == 0;
     }
+
+    /// Where the pack ends: the metadata comes last.
+    pub fn end(&self) -> u64 {
+        self.metadata_offset + self.metadata_length
+    }
+
+    /// Whether the index and metadata are zstd-compressed (see [`VERSION_2`]).
+    pub fn is_compressed(&self) -> bool {
+        self.version >= VERSION_2
+    }
+
+    /// The index or metadata as it's stored in a pack with this header.
+    pub fn encode(&self, data: &[u8]) -> io::Result<Vec<u8>> {
+        if self.is_compressed() {
+            zstd::encode_all(data, COMPRESSION_LEVEL)
+        } else {
+            Ok(data.to_vec())
+        }
+    }
+
+    /// The index or metadata as it was before [`Self::encode`]. Packs that haven't been saved yet
+    /// have neither, whatever their version.
+    pub fn decode(&self, data: Vec<u8>) -> io::Result<Vec<u8>> {
+        if self.is_compressed() && !data.is_empty() {
+            zstd::decode_all(data.as_slice())
+        } else {
+            Ok(data)
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::goals::GoalStat;
 
     fn make_header(
         index_offset: u64,
@@ -176,6 +330,7 @@ mod tests {
         metadata_length: u64,
     ) -> Header {
         Header {
+            version: VERSION_1,
             index_offset,
             index_length,
             metadata_offset,
@@ -186,10 +341,13 @@ mod tests {
 
     #[test]
     fn header_roundtrip() {
-        let original = make_header(64, 512, 576, 128);
-        let buf = original.to_buf().unwrap();
-        let decoded = Header::from_buf(buf).unwrap();
-        assert_eq!(original, decoded);
+        let mut original = make_header(64, 512, 576, 128);
+        for version in [VERSION_1, VERSION_2] {
+            original.version = version;
+            let buf = original.to_buf().unwrap();
+            let decoded = Header::from_buf(buf).unwrap();
+            assert_eq!(original, decoded);
+        }
     }
 
     #[test]
@@ -247,6 +405,36 @@ mod tests {
             creator: Some("Alice".to_string()),
             description: Some("A test pack".to_string()),
             version: Some("1.0.0".to_string()),
+            image_codec: Some(ImageCodec::Webp),
+            video_container: Some(VideoContainer::Webm),
+            size_budgets: BTreeMap::from([("wallpaper".to_string(), 500_000_000)]),
+            goals: vec![Goal {
+                name: "Last 10 minutes".to_string(),
+                stat: GoalStat::Minutes,
+                target: 10,
+                popup_set: Some("reward".to_string()),
+            }],
+            milestones: vec![Milestone {
+                name: "Warmed up".to_string(),
+                stat: GoalStat::Popups,
+                target: 50,
+                unlocks: vec!["wild".to_string()],
+            }],
+            ratings: vec!["Mild".to_string(), "Wild".to_string()],
+            tag_ratings: BTreeMap::from([("wild".to_string(), 1)]),
+            recency_bias: Some(RecencyBias {
+                boost: 3.0,
+                days: 14,
+                minutes: 30,
+            }),
+            prompt_kinds: vec![PromptKind::RepeatText, PromptKind::Arithmetic],
+            license: Some("CC BY-NC 4.0".to_string()),
+            credits: vec![Credit {
+                name: "Bob".to_string(),
+                role: Some("Artist".to_string()),
+                license: None,
+                urls: vec!["https://example.com/bob".to_string()],
+            }],
         };
         let buf = original.to_buf().unwrap();
         let decoded = Metadata::from_buf(&buf).unwrap();
@@ -265,6 +453,140 @@ mod tests {
         assert!(decoded.creator.is_none());
         assert!(decoded.version.is_none());
     }
+
+    /// A pack with just a header, metadata and index, in the format `version` stores them in.
+    fn make_pack(version: u8, metadata: &Metadata, index: &[u8]) -> Vec<u8> {
+        let mut header = Header::new();
+        header.version = version;
+        let metadata = header.encode(&metadata.to_buf().unwrap()).unwrap();
+        let index = header.encode(index).unwrap();
+        header.metadata_offset = HEADER_SIZE as u64;
+        header.metadata_length = metadata.len() as u64;
+        header.index_offset = header.metadata_offset + header.metadata_length;
+        header.index_length = index.len() as u64;
+
+        let mut pack = header.to_buf().unwrap().to_vec();
+        pack.extend(metadata);
+        pack.extend(index);
+        pack
+    }
+
+    #[test]
+    fn reads_both_versions() {
+        let metadata = Metadata {
+            name: "test-pack".to_string(),
+            ..Default::default()
+        };
+        let index: Vec<u8> = (0..INDEX_CHUNK_SIZE + 10)
+            .map(|i| (i / 1000) as u8)
+            .collect();
+
+        let v1 = make_pack(VERSION_1, &metadata, &index);
+        let v2 = make_pack(VERSION_2, &metadata, &index);
+        assert!(v2.len() < v1.len() / 10);
+
+        for pack in [v1, v2] {
+            let (header, read_metadata) = read_pack_metadata(Cursor::new(&pack)).unwrap();
+            assert_eq!(read_metadata, metadata);
+            assert_eq!(
+                read_index(Cursor::new(&pack), &header, |_| {}).unwrap(),
+                index
+            );
+
+            let mut copied = Vec::new();
+            copy_index(Cursor::new(&pack), &header, &mut copied).unwrap();
+            assert_eq!(copied, index);
+
+            let mut copied = Vec::new();
+            tokio::runtime::Builder::new_current_thread()
+                .build()
+                .unwrap()
+                .block_on(copy_index_async(
+                    Cursor::new(&pack),
+                    &header,
+                    &mut copied,
+                    |_| {},
+                ))
+                .unwrap();
+            assert_eq!(copied, index);
+        }
+    }
+
+    #[test]
+    fn write_index_async_matches_encode() {
+        let header = Header::new();
+        let index: Vec<u8> = (0..INDEX_CHUNK_SIZE * 2).map(|i| (i % 7) as u8).collect();
+
+        let mut written = Vec::new();
+        let length = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap()
+            .block_on(write_index_async(&header, index.as_slice(), &mut written))
+            .unwrap();
+
+        assert_eq!(length, written.len() as u64);
+        assert_eq!(header.decode(written).unwrap(), index);
+    }
+
+    #[test]
+    fn read_index_reports_every_chunk() {
+        let index = vec![7u8; INDEX_CHUNK_SIZE * 2 + 10];
+        let mut file = vec![0u8; 3];
+        file.extend_from_slice(&index);
+        let header = make_header(3, index.len() as u64, 0, 0);
+
+        let mut reports = Vec::new();
+        let data = read_index(Cursor::new(file), &header, |p| reports.push(p)).unwrap();
+
+        assert_eq!(data, index);
+        let total = index.len() as u64;
+        assert_eq!(
+            reports,
+            vec![
+                OpenProgress::ExtractingIndex { done: 0, total },
+                OpenProgress::ExtractingIndex {
+                    done: INDEX_CHUNK_SIZE as u64,
+                    total
+                },
+                OpenProgress::ExtractingIndex {
+                    done: INDEX_CHUNK_SIZE as u64 * 2,
+                    total
+                },
+                OpenProgress::ExtractingIndex { done: total, total },
+            ]
+        );
+    }
+
+    #[test]
+    fn copy_index_async_matches_read_index() {
+        let index: Vec<u8> = (0..INDEX_CHUNK_SIZE + 10).map(|i| i as u8).collect();
+        let mut file = vec![0u8; 3];
+        file.extend_from_slice(&index);
+        let header = make_header(3, index.len() as u64, 0, 0);
+
+        let mut reports = Vec::new();
+        let mut out = Vec::new();
+        tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap()
+            .block_on(copy_index_async(
+                Cursor::new(&file),
+                &header,
+                &mut out,
+                |p| reports.push(p),
+            ))
+            .unwrap();
+
+        assert_eq!(out, index);
+        assert_eq!(
+            reports.last(),
+            Some(&OpenProgress::ExtractingIndex {
+                done: index.len() as u64,
+                total: index.len() as u64
+            })
+        );
+        assert_eq!(reports.len(), 3);
+    }
 }
 
 /// Read the header and metadata of a pack file.
@@ -281,11 +603,131 @@ pub fn read_pack_metadata<F: Read + Seek>(mut file: F) -> anyhow::Result<(Header
     let mut buf = vec![0u8; header.metadata_length as usize];
     file.read_exact(&mut buf)?;
 
-    let metadata = Metadata::from_buf(&buf)?;
+    let metadata = Metadata::from_buf(&header.decode(buf)?)?;
 
     Ok((header, metadata))
 }
 
+/// Read a pack's index, reporting progress after every few megabytes of the pack file.
+pub fn read_index<F: Read + Seek>(
+    mut file: F,
+    header: &Header,
+    mut on_progress: impl FnMut(OpenProgress),
+) -> io::Result<Vec<u8>> {
+    file.seek(SeekFrom::Start(header.index_offset))?;
+
+    let total = header.index_length;
+    let mut data = vec![0u8; total as usize];
+    on_progress(OpenProgress::ExtractingIndex { done: 0, total });
+    for (i, chunk) in data.chunks_mut(INDEX_CHUNK_SIZE).enumerate() {
+        file.read_exact(chunk)?;
+        let done = (i * INDEX_CHUNK_SIZE + chunk.len()) as u64;
+        on_progress(OpenProgress::ExtractingIndex { done, total });
+    }
+
+    header.decode(data)
+}
+
+/// Copies a pack's index into `out`, e.g. a temporary file to open it from.
+pub fn copy_index<F: Read + Seek, W: Write>(
+    mut file: F,
+    header: &Header,
+    mut out: W,
+) -> io::Result<()> {
+    file.seek(SeekFrom::Start(header.index_offset))?;
+    let mut data = file.take(header.index_length);
+
+    if header.is_compressed() {
+        zstd::stream::copy_decode(data, out)
+    } else {
+        io::copy(&mut data, &mut out).map(|_| ())
+    }
+}
+
+/// Async version of [`read_index`], which copies the index into `out` a chunk at a time rather
+/// than holding all of it in memory. Packs on slow drives (e.g. network shares) then only ever
+/// have one chunk in flight.
+pub async fn copy_index_async<F, W>(
+    mut file: F,
+    header: &Header,
+    mut out: W,
+    mut on_progress: impl FnMut(OpenProgress),
+) -> io::Result<()>
+where
+    F: AsyncRead + AsyncSeek + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    use tokio::io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt};
+
+    file.seek(SeekFrom::Start(header.index_offset)).await?;
+
+    // Decompressed a chunk at a time too, into a buffer that's emptied after each one.
+    let mut decoder = if header.is_compressed() {
+        Some(zstd::stream::write::Decoder::new(Vec::new())?)
+    } else {
+        None
+    };
+
+    let total = header.index_length;
+    let mut buf = vec![0u8; INDEX_CHUNK_SIZE.min(total as usize)];
+    let mut done = 0;
+    on_progress(OpenProgress::ExtractingIndex { done, total });
+    while done < total {
+        let chunk = &mut buf[..(total - done).min(INDEX_CHUNK_SIZE as u64) as usize];
+        file.read_exact(chunk).await?;
+        match &mut decoder {
+            Some(decoder) => {
+                decoder.write_all(chunk)?;
+                decoder.flush()?;
+                out.write_all(decoder.get_ref()).await?;
+                decoder.get_mut().clear();
+            }
+            None => out.write_all(chunk).await?,
+        }
+        done += chunk.len() as u64;
+        on_progress(OpenProgress::ExtractingIndex { done, total });
+    }
+    out.flush().await?;
+
+    Ok(())
+}
+
+/// Writes an index to a pack with this header, a chunk at a time, returning how long it is in
+/// the pack.
+pub async fn write_index_async<R, W>(header: &Header, mut index: R, mut out: W) -> io::Result<u64>
+where
+    R: AsyncRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    if !header.is_compressed() {
+        return tokio::io::copy(&mut index, &mut out).await;
+    }
+
+    let mut encoder = zstd::stream::write::Encoder::new(Vec::new(), COMPRESSION_LEVEL)?;
+    let mut buf = vec![0u8; INDEX_CHUNK_SIZE];
+    let mut written = 0;
+    loop {
+        let read = index.read(&mut buf).await?;
+        if read == 0 {
+            break;
+        }
+
+        encoder.write_all(&buf[..read])?;
+        out.write_all(encoder.get_ref()).await?;
+        written += encoder.get_ref().len() as u64;
+        encoder.get_mut().clear();
+    }
+
+    let rest = encoder.finish()?;
+    out.write_all(&rest).await?;
+    written += rest.len() as u64;
+    out.flush().await?;
+
+    Ok(written)
+}
+
 pub async fn read_pack_metadata_async<F: AsyncRead + AsyncSeek + Unpin>(
     mut file: F,
 ) -> anyhow::Result<(Header, Metadata)> {
@@ -302,7 +744,7 @@ pub async fn read_pack_metadata_async<F: AsyncRead + AsyncSeek + Unpin>(
     let mut buf = vec![0u8; header.metadata_length as usize];
     file.read_exact(&mut buf).await?;
 
-    let metadata = Metadata::from_buf(&buf)?;
+    let metadata = Metadata::from_buf(&header.decode(buf)?)?;
 
     Ok((header, metadata))
 }
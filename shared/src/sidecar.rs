@@ -0,0 +1,166 @@
+//! Sidecar files, holding settings for a single media file. They sit next to the file they
+//! describe, named after it with `.json5` added, e.g. `image.png.json5`:
+//!
+//! ```json5
+//! {
+//!     tags: ["outdoors"],
+//!     category: "wallpaper",
+//!     caption: "Look at me",
+//!     weight: 2,      // Twice as likely to be picked
+//!     cooldown: 600,  // Seconds between showings
+//!     // Not shown until something tagged "intro" has been shown 3 times this session
+//!     only_after: { tag: "intro", times: 3 },
+//!     // Shown side by side with the other files in the set, in order of file name
+//!     set: "triptych",
+//!     // The pack's second content rating level
+//!     rating: 1,
+//! }
+//! ```
+
+use std::{
+    ffi::OsString,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Result, anyhow, bail};
+use serde::{Deserialize, Serialize};
+
+pub const SIDECAR_EXTENSION: &str = "json5";
+
+/// The category for toasts: small images (e.g. reactions) that are shown stacked in a corner of
+/// the screen for a few seconds, and never as ordinary popups.
+pub const TOAST_CATEGORY: &str = "toast";
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct Sidecar {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    /// Free-form, except for [`TOAST_CATEGORY`].
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub category: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub caption: Option<String>,
+    /// How likely the file is to be picked at random, compared to files without a weight (which
+    /// count as 1). 0 leaves it out of random picks altogether.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub weight: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cooldown: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub only_after: Option<OnlyAfter>,
+    /// The popup set the file belongs to. Files in the same set are spawned together, next to
+    /// each other on one monitor.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub set: Option<String>,
+    /// The file's content rating level, counting from 0 for the mildest (see
+    /// [`crate::rating`]).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rating: Option<u32>,
+}
+
+/// Holds a file back until another file, or any file with a tag, has been shown `times` times in
+/// the session. Exactly one of `file` and `tag` is set.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct OnlyAfter {
+    /// The name of the file, as added to the pack (e.g. `intro.png`).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub file: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tag: Option<String>,
+    #[serde(default = "default_times")]
+    pub times: u32,
+}
+
+fn default_times() -> u32 {
+    1
+}
+
+/// Where the sidecar file for `path` would be.
+pub fn sidecar_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().map(OsString::from).unwrap_or_default();
+    name.push(".");
+    name.push(SIDECAR_EXTENSION);
+    path.with_file_name(name)
+}
+
+/// Reads the sidecar file for the media at `path`, if it has one.
+pub fn read_sidecar(path: &Path) -> Result<Option<Sidecar>> {
+    let sidecar = sidecar_path(path);
+    let contents = match fs::read_to_string(&sidecar) {
+        Ok(contents) => contents,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(err) => return Err(err.into()),
+    };
+
+    parse_sidecar(&contents)
+        .map(Some)
+        .map_err(|err| anyhow!("Error reading {}: {err}", sidecar.display()))
+}
+
+pub fn parse_sidecar(contents: &str) -> Result<Sidecar> {
+    let sidecar: Sidecar = json5::from_str(contents)?;
+
+    let valid = |value: f64| value.is_finite() && value >= 0.0;
+    if sidecar.weight.is_some_and(|weight| !valid(weight)) {
+        bail!("weight has to be a positive number");
+    }
+    if sidecar.cooldown.is_some_and(|cooldown| !valid(cooldown)) {
+        bail!("cooldown has to be a positive number");
+    }
+    if let Some(only_after) = &sidecar.only_after
+        && only_after.file.is_some() == only_after.tag.is_some()
+    {
+        bail!("only_after needs either a file or a tag");
+    }
+    if sidecar.set.as_ref().is_some_and(|set| set.trim().is_empty()) {
+        bail!("set can't be empty");
+    }
+
+    Ok(sidecar)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_sidecars() {
+        assert_eq!(
+            sidecar_path(Path::new("media/image.png")),
+            Path::new("media/image.png.json5")
+        );
+
+        let sidecar = parse_sidecar(
+            "{
+                // Comments are fine
+                tags: ['a', 'b'],
+                weight: 2,
+                rating: 1,
+            }",
+        )
+        .unwrap();
+        assert_eq!(sidecar.tags, ["a", "b"]);
+        assert_eq!(sidecar.weight, Some(2.0));
+        assert_eq!(sidecar.rating, Some(1));
+        assert_eq!(sidecar.caption, None);
+
+        let sidecar = parse_sidecar("{ only_after: { tag: 'intro' } }").unwrap();
+        assert_eq!(
+            sidecar.only_after,
+            Some(OnlyAfter {
+                file: None,
+                tag: Some("intro".to_string()),
+                times: 1,
+            })
+        );
+
+        assert!(parse_sidecar("{ weight: -1 }").is_err());
+        assert!(parse_sidecar("{ only_after: { times: 2 } }").is_err());
+        assert!(parse_sidecar("{ tag: 'typo' }").is_err());
+        assert!(parse_sidecar("{ set: ' ' }").is_err());
+        assert!(parse_sidecar("{ rating: -1 }").is_err());
+    }
+}
@@ -0,0 +1,60 @@
+//! Placeholders in texts that are filled in when they're shown, like `{popups}` in a
+//! notification. A name in braces is replaced if it's a variable, and left alone otherwise, so
+//! texts that happen to contain braces still come out as written.
+//!
+//! The variables notifications can use are the session's stats (see
+//! [`SessionStats::variable`](crate::goals::SessionStats::variable)).
+
+use std::borrow::Cow;
+
+/// `text` with every `{name}` that `value` knows replaced by its value.
+pub fn expand(text: &str, value: impl Fn(&str) -> Option<String>) -> Cow<'_, str> {
+    if !text.contains('{') {
+        return Cow::Borrowed(text);
+    }
+
+    let mut expanded = String::with_capacity(text.len());
+    let mut rest = text;
+
+    while let Some(start) = rest.find('{') {
+        expanded.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        let replacement = rest
+            .find('}')
+            .and_then(|end| Some((value(&rest[1..end])?, end)));
+
+        match replacement {
+            Some((replacement, end)) => {
+                expanded.push_str(&replacement);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                expanded.push('{');
+                rest = &rest[1..];
+            }
+        }
+    }
+    expanded.push_str(rest);
+
+    Cow::Owned(expanded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn value(name: &str) -> Option<String> {
+        (name == "popups").then(|| "12".to_string())
+    }
+
+    #[test]
+    fn replaces_known_variables_only() {
+        assert_eq!(expand("{popups} popups so far", value), "12 popups so far");
+        assert_eq!(
+            expand("{ {unknown} {popups}} {", value),
+            "{ {unknown} 12} {"
+        );
+        assert!(matches!(expand("No braces", value), Cow::Borrowed(_)));
+    }
+}
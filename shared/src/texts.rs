@@ -0,0 +1,568 @@
+//! Notifications, prompts and links stored in a pack, and importing them from text files.
+//!
+//! Each file holds one kind of text, going by its name: `notifications`, `prompts` or `links`,
+//! with a `.txt` or `.csv` extension.
+//!
+//! Packs can have texts in several languages. Texts in a file with a language tag in its name,
+//! like `notifications.de.txt` or `prompts.pt-BR.csv`, are only shown to people using that
+//! language (see [`preferred_language`](crate::locale::preferred_language)). Texts from files
+//! without one are shown to everyone else, or if the pack has none in their language.
+//!
+//! * A `.txt` file has one entry per line. Blank lines and lines starting with `#` are skipped.
+//! * A `.csv` file has the text in the first column, and optionally tags in the ones after it.
+//!   Fields can be quoted, with `""` for a literal quote. A first row starting with a `text`
+//!   column is taken as a header. With a header, prompts can also be themed (see
+//!   [`PromptTheme`]) with `background`, `background_color` and `text_color` columns.
+//!
+//! Notifications can count along with the session: `{popups}`, `{minutes}`, `{prompts}`,
+//! `{prompts_failed}` and `{choices}` are filled in when they're shown (see [`crate::template`]).
+
+use std::{fs, path::Path};
+
+use anyhow::{Result, anyhow, bail};
+use rusqlite::params;
+use serde::{Deserialize, Serialize};
+
+use crate::locale::normalize_language;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum TextKind {
+    Notification,
+    Prompt,
+    Link,
+}
+
+impl TextKind {
+    pub const ALL: [TextKind; 3] = [TextKind::Notification, TextKind::Prompt, TextKind::Link];
+
+    /// The value stored in the `kind` column.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TextKind::Notification => "notification",
+            TextKind::Prompt => "prompt",
+            TextKind::Link => "link",
+        }
+    }
+
+    /// The name of a file holding this kind of text, without the extension.
+    pub fn file_stem(&self) -> &'static str {
+        match self {
+            TextKind::Notification => "notifications",
+            TextKind::Prompt => "prompts",
+            TextKind::Link => "links",
+        }
+    }
+
+    /// The kind of text held by a file with this name (without the extension).
+    pub fn from_file_stem(stem: &str) -> Option<Self> {
+        match stem.to_lowercase().as_str() {
+            "notifications" => Some(TextKind::Notification),
+            "prompts" => Some(TextKind::Prompt),
+            "links" => Some(TextKind::Link),
+            _ => None,
+        }
+    }
+}
+
+/// What a prompt asks the user to type in. Packs choose which kinds they pop up with
+/// [`Metadata::prompt_kinds`](crate::read_pack::Metadata::prompt_kinds).
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum PromptKind {
+    /// One of the pack's prompts, word for word.
+    #[default]
+    RepeatText,
+    /// The answer to a random sum.
+    Arithmetic,
+    /// One of the pack's prompts, against the clock. Close enough is good enough, but a passage
+    /// that's too slow or too sloppy is swapped for a new one instead of being accepted.
+    TypingTest,
+}
+
+/// The kind of text held by a file with this name (without the extension), and the language
+/// it's in, if it has one.
+fn parse_file_stem(stem: &str) -> Option<(TextKind, Option<String>)> {
+    match stem.split_once('.') {
+        Some((kind, locale)) => Some((
+            TextKind::from_file_stem(kind)?,
+            Some(normalize_language(locale)?),
+        )),
+        None => Some((TextKind::from_file_stem(stem)?, None)),
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct TextEntry {
+    pub text: String,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Only used for prompts.
+    #[serde(default)]
+    pub theme: PromptTheme,
+}
+
+/// How a prompt looks, so that it can match the pack's content. Unset parts keep the default
+/// look.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct PromptTheme {
+    /// The file name of an image in the pack, drawn behind the prompt.
+    pub background: Option<String>,
+    /// `#rrggbb` or `#rrggbbaa`.
+    pub background_color: Option<String>,
+    pub text_color: Option<String>,
+}
+
+impl PromptTheme {
+    /// The theme's parts, in the order of [`THEME_COLUMNS`].
+    fn columns(&self) -> [Option<&str>; 3] {
+        [
+            self.background.as_deref(),
+            self.background_color.as_deref(),
+            self.text_color.as_deref(),
+        ]
+    }
+}
+
+/// The CSV columns that hold a [`PromptTheme`] rather than tags.
+const THEME_COLUMNS: [&str; 3] = ["background", "background_color", "text_color"];
+
+/// Whether `color` is `#rrggbb` or `#rrggbbaa`.
+fn is_color(color: &str) -> bool {
+    color
+        .strip_prefix('#')
+        .is_some_and(|hex| matches!(hex.len(), 6 | 8) && hex.chars().all(|c| c.is_ascii_hexdigit()))
+}
+
+/// Whether `path` looks like a text file to import, going by its name and extension.
+pub fn is_text_file(path: &Path) -> bool {
+    let has_extension = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("txt") || ext.eq_ignore_ascii_case("csv"));
+
+    has_extension
+        && path
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .and_then(parse_file_stem)
+            .is_some()
+}
+
+/// The texts read from one file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TextFile {
+    pub kind: TextKind,
+    /// The language the texts are in, as a tag like `pt-BR`, if the file's name has one.
+    pub locale: Option<String>,
+    pub entries: Vec<TextEntry>,
+}
+
+/// Reads a `.txt` or `.csv` file of texts, working out their kind and language from the file's
+/// name.
+pub fn read_text_file(path: &Path) -> Result<TextFile> {
+    let (kind, locale) = path
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .and_then(parse_file_stem)
+        .ok_or_else(|| {
+            anyhow!(
+                "Can't tell what {} holds: it should be called notifications, prompts or links, \
+                 optionally followed by a language (e.g. notifications.de.txt)",
+                path.display()
+            )
+        })?;
+
+    let contents = fs::read_to_string(path)?;
+
+    let entries = match path.extension().and_then(|ext| ext.to_str()) {
+        Some(ext) if ext.eq_ignore_ascii_case("txt") => parse_txt(&contents),
+        Some(ext) if ext.eq_ignore_ascii_case("csv") => parse_csv(&contents)
+            .map_err(|err| anyhow!("Error reading {}: {err}", path.display()))?,
+        _ => bail!("{} isn't a .txt or .csv file", path.display()),
+    };
+
+    Ok(TextFile {
+        kind,
+        locale,
+        entries,
+    })
+}
+
+pub fn parse_txt(contents: &str) -> Vec<TextEntry> {
+    contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| TextEntry {
+            text: line.to_string(),
+            tags: Vec::new(),
+            theme: PromptTheme::default(),
+        })
+        .collect()
+}
+
+pub fn parse_csv(contents: &str) -> Result<Vec<TextEntry>> {
+    let mut rows = split_csv(contents)?.into_iter().peekable();
+
+    // Without a header, every column after the text is a tag.
+    let mut header = Vec::new();
+    if rows
+        .peek()
+        .and_then(|row| row.first())
+        .is_some_and(|first| first.trim().eq_ignore_ascii_case("text"))
+    {
+        header = rows
+            .next()
+            .unwrap_or_default()
+            .into_iter()
+            .map(|column| column.trim().to_lowercase())
+            .collect();
+    }
+
+    let mut entries = Vec::new();
+    for (index, row) in rows.enumerate() {
+        let mut fields = row.into_iter().map(|field| field.trim().to_string());
+        let Some(text) = fields.next().filter(|text| !text.is_empty()) else {
+            continue;
+        };
+
+        let mut entry = TextEntry {
+            text,
+            tags: Vec::new(),
+            theme: PromptTheme::default(),
+        };
+        for (column, field) in fields.enumerate() {
+            if field.is_empty() {
+                continue;
+            }
+
+            let name = header.get(column + 1).map(String::as_str);
+            if matches!(name, Some("background_color" | "text_color")) && !is_color(&field) {
+                bail!(
+                    "Row {}: {field} isn't a color like #ff0080",
+                    index + 1 + !header.is_empty() as usize
+                );
+            }
+
+            match name.filter(|name| THEME_COLUMNS.contains(name)) {
+                Some("background") => entry.theme.background = Some(field),
+                Some("background_color") => entry.theme.background_color = Some(field),
+                Some(_) => entry.theme.text_color = Some(field),
+                None => entry.tags.push(field),
+            }
+        }
+        entries.push(entry);
+    }
+
+    Ok(entries)
+}
+
+fn split_csv(contents: &str) -> Result<Vec<Vec<String>>> {
+    let mut rows = Vec::new();
+    let mut row = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut chars = contents.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if in_quotes {
+            match c {
+                '"' if chars.peek() == Some(&'"') => {
+                    chars.next();
+                    field.push('"');
+                }
+                '"' => in_quotes = false,
+                c => field.push(c),
+            }
+            continue;
+        }
+
+        match c {
+            '"' if field.trim().is_empty() => {
+                field.clear();
+                in_quotes = true;
+            }
+            ',' => row.push(std::mem::take(&mut field)),
+            '\r' if chars.peek() == Some(&'\n') => {}
+            '\n' => {
+                row.push(std::mem::take(&mut field));
+                rows.push(std::mem::take(&mut row));
+            }
+            c => field.push(c),
+        }
+    }
+
+    if in_quotes {
+        bail!("Unterminated quote on row {}", rows.len() + 1);
+    }
+
+    if !field.is_empty() || !row.is_empty() {
+        row.push(field);
+        rows.push(row);
+    }
+
+    Ok(rows)
+}
+
+/// The texts of one kind in one language, or the ones without a language if `locale` is `None`.
+pub fn read_texts(
+    db: &rusqlite::Connection,
+    kind: TextKind,
+    locale: Option<&str>,
+) -> Result<Vec<TextEntry>> {
+    let mut stmt = db.prepare(
+        "SELECT text, tags, background, background_color, text_color FROM texts
+         WHERE kind = ? AND locale IS ? ORDER BY id",
+    )?;
+    let rows = stmt.query_map(params![kind.as_str(), locale], |row| {
+        Ok((
+            row.get::<_, String>("text")?,
+            row.get::<_, String>("tags")?,
+            read_theme(row)?,
+        ))
+    })?;
+
+    rows.map(|row| {
+        let (text, tags, theme) = row?;
+        Ok(TextEntry {
+            text,
+            tags: serde_json::from_str(&tags)?,
+            theme,
+        })
+    })
+    .collect()
+}
+
+/// Reads a [`PromptTheme`] from a row with the `texts` table's theme columns.
+pub fn read_theme(row: &rusqlite::Row) -> rusqlite::Result<PromptTheme> {
+    Ok(PromptTheme {
+        background: row.get("background")?,
+        background_color: row.get("background_color")?,
+        text_color: row.get("text_color")?,
+    })
+}
+
+/// Replaces all the texts of one kind in one language, so re-importing an edited file doesn't
+/// duplicate entries. Texts in other languages are left alone.
+pub fn replace_texts(
+    db: &mut rusqlite::Connection,
+    kind: TextKind,
+    locale: Option<&str>,
+    entries: &[TextEntry],
+) -> Result<()> {
+    let tx = db.transaction()?;
+    tx.execute(
+        "DELETE FROM texts WHERE kind = ? AND locale IS ?",
+        params![kind.as_str(), locale],
+    )?;
+    {
+        let mut stmt = tx.prepare(
+            "INSERT INTO texts (kind, locale, text, tags, background, background_color, text_color)
+             VALUES (?, ?, ?, ?, ?, ?, ?)",
+        )?;
+        for entry in entries {
+            stmt.execute(params![
+                kind.as_str(),
+                locale,
+                entry.text,
+                serde_json::to_string(&entry.tags)?,
+                entry.theme.background,
+                entry.theme.background_color,
+                entry.theme.text_color,
+            ])?;
+        }
+    }
+    tx.commit()?;
+    Ok(())
+}
+
+/// The languages there are texts of one kind in, with `None` for the ones without a language.
+pub fn locales(db: &rusqlite::Connection, kind: TextKind) -> Result<Vec<Option<String>>> {
+    let mut stmt =
+        db.prepare("SELECT DISTINCT locale FROM texts WHERE kind = ? ORDER BY locale")?;
+    let locales = stmt
+        .query_map(params![kind.as_str()], |row| row.get(0))?
+        .collect::<rusqlite::Result<_>>()?;
+
+    Ok(locales)
+}
+
+/// The name of a `.csv` file that [`read_text_file`] reads back as these texts.
+pub fn csv_file_name(kind: TextKind, locale: Option<&str>) -> String {
+    match locale {
+        Some(locale) => format!("{}.{locale}.csv", kind.file_stem()),
+        None => format!("{}.csv", kind.file_stem()),
+    }
+}
+
+/// Writes texts as a `.csv` file that [`parse_csv`] reads back the same, with a header row. The
+/// theme columns are only there if one of the texts has a theme.
+pub fn write_csv(entries: &[TextEntry]) -> String {
+    let themed = entries
+        .iter()
+        .any(|entry| entry.theme != PromptTheme::default());
+    let tag_columns = entries
+        .iter()
+        .map(|entry| entry.tags.len())
+        .max()
+        .unwrap_or(0);
+
+    let mut header = vec!["text"];
+    if themed {
+        header.extend(THEME_COLUMNS);
+    }
+    header.extend(std::iter::repeat_n("tag", tag_columns));
+
+    let mut csv = header.join(",");
+    csv.push('\n');
+    for entry in entries {
+        let mut row = vec![entry.text.as_str()];
+        if themed {
+            row.extend(entry.theme.columns().map(Option::unwrap_or_default));
+        }
+        row.extend(entry.tags.iter().map(String::as_str));
+
+        let row: Vec<_> = row.into_iter().map(csv_field).collect();
+        csv.push_str(&row.join(","));
+        csv.push('\n');
+    }
+
+    csv
+}
+
+/// Quotes a field if it has to be.
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// How many texts of one kind there are, in every language.
+pub fn count_texts(db: &rusqlite::Connection, kind: TextKind) -> Result<u64> {
+    Ok(db.query_row(
+        "SELECT COUNT(*) FROM texts WHERE kind = ?",
+        params![kind.as_str()],
+        |row| row.get(0),
+    )?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_txt_and_csv() {
+        let txt = parse_txt("# comment\nFirst\n\n  Second  \n");
+        let texts: Vec<_> = txt.iter().map(|e| e.text.as_str()).collect();
+        assert_eq!(texts, ["First", "Second"]);
+
+        let csv = parse_csv(
+            "text,tag\r\nPlain,a\n\"Quoted, with \"\"quotes\"\"\",a,b\n\"Two\nlines\"\n,orphan\n",
+        )
+        .unwrap();
+        assert_eq!(
+            csv,
+            [
+                TextEntry {
+                    text: "Plain".to_string(),
+                    tags: vec!["a".to_string()],
+                    theme: PromptTheme::default(),
+                },
+                TextEntry {
+                    text: "Quoted, with \"quotes\"".to_string(),
+                    tags: vec!["a".to_string(), "b".to_string()],
+                    theme: PromptTheme::default(),
+                },
+                TextEntry {
+                    text: "Two\nlines".to_string(),
+                    tags: vec![],
+                    theme: PromptTheme::default(),
+                },
+            ]
+        );
+
+        assert!(parse_csv("\"unterminated\n").is_err());
+    }
+
+    #[test]
+    fn parses_prompt_themes() {
+        let csv =
+            parse_csv("text,tag,Background,text_color\nKneel,a,throne.png,#ffd700\nPlain,b,,\n")
+                .unwrap();
+        assert_eq!(csv[0].tags, ["a"]);
+        assert_eq!(
+            csv[0].theme,
+            PromptTheme {
+                background: Some("throne.png".to_string()),
+                background_color: None,
+                text_color: Some("#ffd700".to_string()),
+            }
+        );
+        assert_eq!(csv[1].theme, PromptTheme::default());
+
+        assert!(parse_csv("text,text_color\nKneel,gold\n").is_err());
+    }
+
+    #[test]
+    fn writes_csv_that_reads_back() {
+        let entries = vec![
+            TextEntry {
+                text: "Quoted, with \"quotes\"\nand lines".to_string(),
+                tags: vec!["a".to_string(), "b".to_string()],
+                theme: PromptTheme {
+                    background: Some("throne.avif".to_string()),
+                    background_color: None,
+                    text_color: Some("#ffd700".to_string()),
+                },
+            },
+            TextEntry {
+                text: "Plain".to_string(),
+                tags: vec![],
+                theme: PromptTheme::default(),
+            },
+        ];
+
+        assert_eq!(parse_csv(&write_csv(&entries)).unwrap(), entries);
+        assert_eq!(write_csv(&entries[1..]), "text\nPlain\n");
+        assert_eq!(
+            csv_file_name(TextKind::Prompt, Some("pt-BR")),
+            "prompts.pt-BR.csv"
+        );
+    }
+
+    #[test]
+    fn parses_file_names() {
+        assert_eq!(
+            parse_file_stem("Notifications"),
+            Some((TextKind::Notification, None))
+        );
+        assert_eq!(
+            parse_file_stem("prompts.pt_br"),
+            Some((TextKind::Prompt, Some("pt-BR".to_string())))
+        );
+        assert_eq!(parse_file_stem("links.not a language"), None);
+        assert_eq!(parse_file_stem("notes.de"), None);
+    }
+
+    #[test]
+    fn replaces_texts_per_language() {
+        let mut db = rusqlite::Connection::open_in_memory().unwrap();
+        crate::db::migrate(&db).unwrap();
+
+        let entries = |text: &str| parse_txt(text);
+        let kind = TextKind::Notification;
+        replace_texts(&mut db, kind, None, &entries("Hello")).unwrap();
+        replace_texts(&mut db, kind, Some("de"), &entries("Hallo")).unwrap();
+        replace_texts(&mut db, kind, Some("de"), &entries("Guten Tag")).unwrap();
+
+        assert_eq!(read_texts(&db, kind, None).unwrap(), entries("Hello"));
+        assert_eq!(
+            read_texts(&db, kind, Some("de")).unwrap(),
+            entries("Guten Tag")
+        );
+        assert_eq!(count_texts(&db, kind).unwrap(), 2);
+    }
+}
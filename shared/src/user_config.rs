@@ -1,22 +1,505 @@
-use std::{collections::HashMap, fs, path::PathBuf};
+use std::{
+    collections::{BTreeSet, HashMap},
+    fs,
+    io::Write,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
 
 use anyhow::{Result, anyhow};
 use serde::{Deserialize, Serialize};
 use serde_with::serde_as;
 
-use crate::mode::OptionValue;
+use crate::{
+    goals::{Goal, SessionSummary, SessionTotals},
+    history::PromptRecord,
+    mode::OptionValue,
+    user_data,
+};
 
 #[serde_as]
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct AppConfig {
+    /// The pack to play. Can also be an `http://` or `https://` URL, which the engine streams the
+    /// pack from rather than downloading it first.
     pub pack_path: Option<PathBuf>,
+    /// A second pack mixed into random picks from `pack_path`.
+    #[serde(default)]
+    pub blend_pack: Option<BlendPack>,
     pub uploaded_modes: Vec<PathBuf>,
     pub mode: Mode,
     #[serde_as(as = "Vec<(_, _)>")]
     pub mode_options: HashMap<Mode, HashMap<String, OptionValue>>,
     pub tags: Option<Vec<String>>,
+    /// Whether media and texts need any of the tags they're asked for, or all of them. Modes can
+    /// pick either for each query, and this is what they get when they don't.
+    #[serde(default)]
+    pub tag_match_mode: TagMatchMode,
     pub panic_button: Key,
+    /// Toggles all audio on and off. Unset by default, since any single key is likely to clash
+    /// with something the user types.
+    #[serde(default)]
+    pub mute_button: Option<Key>,
+    /// Pauses or resumes the session, like the tray's pause item. Popups already open stay, but
+    /// no new ones are spawned and audio stops until it's resumed.
+    #[serde(default)]
+    pub pause_button: Option<Key>,
+    /// Closes the popup under the cursor (or the focused one) and never shows its file again (see
+    /// [`load_blocked_media`]). Unset by default, like `mute_button`.
+    #[serde(default)]
+    pub block_button: Option<Key>,
+    /// Adds the popup under the cursor (or the focused one) to the pack's favorites, or takes it
+    /// off them if it's already there (see [`load_favorites`]).
+    #[serde(default)]
+    pub favorite_button: Option<Key>,
+    #[serde(default)]
+    pub favorites: FavoritesMode,
+    /// Shows which file in the pack the popup under the cursor (or the focused one) came from,
+    /// with its tags and size, for pack authors testing their packs.
+    #[serde(default)]
+    pub info_button: Option<Key>,
     pub disabled_monitors: Vec<String>,
+    #[serde(default)]
+    pub video_hover: VideoHoverBehaviour,
+    #[serde(default)]
+    pub video_performance: VideoPerformance,
+    #[serde(default)]
+    pub image_cache: ImageCache,
+    /// Check each media file against the checksum stored in the pack before decoding it, and
+    /// skip any that don't match.
+    #[serde(default)]
+    pub verify_media: bool,
+    /// Decode videos in a separate process, so a file that crashes the decoder doesn't take the
+    /// session down with it. Videos that crash it are skipped for the rest of the session.
+    #[serde(default)]
+    pub isolate_video_decoding: bool,
+    /// The highest content rating level to show (see [`crate::rating`]), counting from 0 for the
+    /// pack's mildest. Unset shows everything.
+    #[serde(default)]
+    pub max_rating: Option<u32>,
+    /// Files and texts with any of these tags are never shown, whatever else they're tagged with.
+    #[serde(default)]
+    pub excluded_tags: Vec<String>,
+    /// Seeds every random choice in a session (which media, where popups go, and `math.random`
+    /// in modes), so a session can be played back when debugging a pack. Unset picks a new seed
+    /// each session, which is written to the log.
+    #[serde(default)]
+    pub seed: Option<u64>,
+    /// With no `seed` set, seeds the session from the date instead, so everyone playing the same
+    /// pack with the same settings sees the same "daily" sequence. Days go by UTC, so they start
+    /// at the same moment everywhere.
+    #[serde(default)]
+    pub daily_shuffle: bool,
+    /// Serve counters about the session (popups spawned, decode times and so on) for Prometheus
+    /// at `http://127.0.0.1:<port>/metrics`. Unset doesn't serve them.
+    #[serde(default)]
+    pub metrics_port: Option<u16>,
+    #[serde(default)]
+    pub battery_saver: BatterySaver,
+    #[serde(default)]
+    pub throttle: Throttle,
+    #[serde(default)]
+    pub start_delay: StartDelay,
+    #[serde(default)]
+    pub schedule: Schedule,
+    #[serde(default)]
+    pub escalation: Escalation,
+    #[serde(default)]
+    pub pacing: Pacing,
+    #[serde(default)]
+    pub prompt_failure: PromptFailure,
+    /// Let modes spawn minigame popups, which only close once a target moving around them has
+    /// been clicked enough times. Off, modes that ask for one get nothing.
+    #[serde(default)]
+    pub minigames: bool,
+    #[serde(default)]
+    pub hibernation: Hibernation,
+    /// Pause the session and hide everything while the screen is being recorded or shared, as far
+    /// as that can be told from outside the program doing it.
+    #[serde(default)]
+    pub pause_while_recording: bool,
+    /// Turn other programs down while the pack's audio, or a video with sound, is playing, and
+    /// back up once it stops. Only on Windows and Linux (through PulseAudio or PipeWire).
+    #[serde(default)]
+    pub duck_other_apps: bool,
+    /// Leave as little on disk as possible: no log file is written, videos are decoded in
+    /// process rather than through buffer files, and whatever still had to be written (image
+    /// wallpapers, the lock file) is wiped when the session ends (see [`crate::footprint`]).
+    #[serde(default)]
+    pub incognito: bool,
+    /// Never spawn a popup within this many pixels of the cursor, so a click that was already on
+    /// its way doesn't land on one the moment it appears. Unset lets popups go anywhere.
+    #[serde(default)]
+    pub cursor_margin: Option<u32>,
+    /// Only let the session run while the computer's being used: once there's been no typing or
+    /// mouse movement for this many seconds, it's paused until there is again. So timers track
+    /// time actually spent at the computer. Unset never pauses.
+    #[serde(default)]
+    pub pause_while_idle: Option<u32>,
+    /// The language of the apps' own text (see [`crate::locale`]), e.g. `de`. Unset follows the
+    /// system's language.
+    #[serde(default)]
+    pub language: Option<String>,
+    /// The user's own goals for each session (see [`crate::goals`]), on top of any the pack has.
+    #[serde(default)]
+    pub goals: Vec<Goal>,
+    #[serde(default)]
+    pub prompt_logging: PromptLogging,
+}
+
+/// Cuts back on what a session does while a laptop is running on its battery: spawning slows
+/// down (through the intensity multiplier modes already use), video popups and video wallpapers
+/// stop, and the wallpaper is left alone.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct BatterySaver {
+    pub enabled: bool,
+    /// Only kick in once the battery is at or below this percentage. Unset kicks in as soon as
+    /// the laptop is unplugged.
+    pub below_percent: Option<u8>,
+    /// What spawn rates are multiplied by while saving battery, between 0 and 1.
+    pub rate: f64,
+}
+
+impl Default for BatterySaver {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            below_percent: None,
+            rate: 0.5,
+        }
+    }
+}
+
+/// Whether prompts, and what the user types into them, are kept in the session history (see
+/// [`crate::history`]).
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum PromptLogging {
+    #[default]
+    Off,
+    /// Keep a hash of what the user typed, and whether it matched the prompt.
+    Hashed,
+    /// Keep what the user typed as it is.
+    Plaintext,
+}
+
+/// How entries are matched against the tags asked for.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum TagMatchMode {
+    /// Entries with at least one of the tags.
+    #[default]
+    Any,
+    /// Only entries with every one of the tags.
+    All,
+}
+
+/// How favorites (see [`load_favorites`]) are picked when a mode asks for random media.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum FavoritesMode {
+    /// No differently from anything else.
+    #[default]
+    Normal,
+    /// More often than the rest of the pack.
+    Boost,
+    /// Only favorites, as long as the pack has any.
+    Only,
+}
+
+/// Scales back spawning for a while when the app can't keep up (it's using most of the CPU, or
+/// frames are taking too long to render), so sessions stay smooth on weak hardware. Random
+/// queries return images instead of videos while throttled.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct Throttle {
+    pub enabled: bool,
+    /// What spawn rates are multiplied by while throttled, between 0 and 1.
+    pub rate: f64,
+}
+
+impl Default for Throttle {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            rate: 0.5,
+        }
+    }
+}
+
+/// A second pack to take some of a session's random picks from, e.g. to ease into a new pack.
+/// Modes, goals and milestones only ever come from the main pack.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct BlendPack {
+    pub path: PathBuf,
+    /// The percentage of random picks taken from this pack, from 0 to 100.
+    pub percent: u8,
+}
+
+/// Waits a while after the session is started before anything spawns, so the user has time to
+/// get ready. Time spent waiting doesn't count towards goals.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct StartDelay {
+    /// How long to wait. 0 starts straight away.
+    pub seconds: u32,
+    /// Show a notification when the wait starts, and another shortly before it ends.
+    pub countdown: bool,
+}
+
+impl Default for StartDelay {
+    fn default() -> Self {
+        Self {
+            seconds: 0,
+            countdown: true,
+        }
+    }
+}
+
+/// Ramps the session up as it goes on: the intensity multiplier modes scale their spawn rates and
+/// popup limits by goes from `start_percent` to `end_percent` over `minutes` of the session, then
+/// stays there. Time spent paused doesn't count.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct Escalation {
+    pub enabled: bool,
+    pub start_percent: u32,
+    pub end_percent: u32,
+    pub minutes: u32,
+}
+
+impl Escalation {
+    /// What to scale the intensity multiplier by, `elapsed` into the session. `1.0` while it's
+    /// off.
+    pub fn factor(&self, elapsed: Duration) -> f64 {
+        if !self.enabled {
+            return 1.0;
+        }
+
+        let start = self.start_percent as f64 / 100.0;
+        let end = self.end_percent as f64 / 100.0;
+        let progress = if self.minutes == 0 {
+            1.0
+        } else {
+            (elapsed.as_secs_f64() / (self.minutes as f64 * 60.0)).min(1.0)
+        };
+
+        start + (end - start) * progress
+    }
+}
+
+impl Default for Escalation {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            start_percent: 50,
+            end_percent: 150,
+            minutes: 30,
+        }
+    }
+}
+
+/// Paces the session by how the user's keeping up: failing a prompt or closing a popup within
+/// `quick_close_seconds` of it opening pushes the intensity multiplier up by `step_percent`, and
+/// answering a prompt right brings it back down by the same, keeping it between `min_percent`
+/// and `max_percent`.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct Pacing {
+    pub enabled: bool,
+    pub min_percent: u32,
+    pub max_percent: u32,
+    pub step_percent: u32,
+    pub quick_close_seconds: u32,
+}
+
+impl Pacing {
+    /// What to scale the intensity multiplier by next, given what it's scaled by now: one step
+    /// up if `harder`, and one down otherwise.
+    pub fn step(&self, factor: f64, harder: bool) -> f64 {
+        let step = self.step_percent as f64 / 100.0;
+        let min = self.min_percent.min(self.max_percent) as f64 / 100.0;
+        let max = self.min_percent.max(self.max_percent) as f64 / 100.0;
+
+        let factor = if harder { factor + step } else { factor - step };
+        factor.clamp(min, max)
+    }
+
+    /// Whether a popup the user closed after `open_for` was closed quickly enough to count.
+    pub fn is_quick_close(&self, open_for: Duration) -> bool {
+        open_for < Duration::from_secs(self.quick_close_seconds.into())
+    }
+}
+
+impl Default for Pacing {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            min_percent: 50,
+            max_percent: 200,
+            step_percent: 10,
+            quick_close_seconds: 3,
+        }
+    }
+}
+
+/// What happens when a prompt is answered with something other than what it asked for, on top of
+/// whatever the mode does about it.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct PromptFailure {
+    /// Random images popped up straight away.
+    pub extra_popups: u32,
+    /// What the intensity multiplier is scaled by for `boost_seconds` afterwards, as a
+    /// percentage. 100 leaves it alone.
+    pub boost_percent: u32,
+    pub boost_seconds: u32,
+}
+
+impl Default for PromptFailure {
+    fn default() -> Self {
+        Self {
+            extra_popups: 0,
+            boost_percent: 100,
+            boost_seconds: 30,
+        }
+    }
+}
+
+/// Calm, then storm: every so often the session goes quiet, closing its popups and stopping its
+/// audio, for a random while, then wakes up with a burst of popups.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct Hibernation {
+    pub enabled: bool,
+    /// How long the session runs between hibernations.
+    pub awake_minutes: u32,
+    /// How long each hibernation lasts is picked from between these.
+    pub min_minutes: u32,
+    pub max_minutes: u32,
+    /// What the intensity multiplier is scaled by for a while after waking up, as a percentage.
+    pub burst_percent: u32,
+}
+
+impl Hibernation {
+    pub fn awake(&self) -> Duration {
+        Duration::from_secs(self.awake_minutes as u64 * 60)
+    }
+
+    /// How long a hibernation lasts, for `t` picked at random from `0.0..1.0`.
+    pub fn length(&self, t: f64) -> Duration {
+        let min = self.min_minutes.min(self.max_minutes) as f64;
+        let max = self.min_minutes.max(self.max_minutes) as f64;
+        Duration::from_secs_f64((min + (max - min) * t.clamp(0.0, 1.0)) * 60.0)
+    }
+}
+
+impl Default for Hibernation {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            awake_minutes: 15,
+            min_minutes: 2,
+            max_minutes: 10,
+            burst_percent: 300,
+        }
+    }
+}
+
+/// Limits the session to certain hours of certain days. Outside them the session is paused, as it
+/// is while the screen is locked, and nothing spawns until the next window opens.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+#[serde(default)]
+pub struct Schedule {
+    /// Off runs the session whenever it's started, whatever `windows` says.
+    pub enabled: bool,
+    pub windows: Vec<ScheduleWindow>,
+}
+
+impl Schedule {
+    /// Whether the session should run at `minute` (past midnight, local time) on `weekday`
+    /// (0 for Monday). Always true if the schedule is off or has no windows.
+    pub fn is_active(&self, weekday: usize, minute: u16) -> bool {
+        !self.enabled
+            || self.windows.is_empty()
+            || self
+                .windows
+                .iter()
+                .any(|window| window.contains(weekday % 7, minute))
+    }
+}
+
+/// Hours on some days of the week, in local time, e.g. 20:00 to 23:00 on weekdays.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ScheduleWindow {
+    /// The days the window starts on, Monday first.
+    pub days: [bool; 7],
+    /// Minutes past midnight. A window that ends before it starts runs on past midnight into the
+    /// next day, and one that ends when it starts lasts the whole day.
+    pub start: u16,
+    pub end: u16,
+}
+
+impl ScheduleWindow {
+    fn contains(&self, weekday: usize, minute: u16) -> bool {
+        let yesterday = (weekday + 6) % 7;
+
+        if self.start < self.end {
+            self.days[weekday] && (self.start..self.end).contains(&minute)
+        } else if self.start > self.end {
+            (self.days[weekday] && minute >= self.start)
+                || (self.days[yesterday] && minute < self.end)
+        } else {
+            self.days[weekday]
+        }
+    }
+}
+
+/// What happens to a video popup while the cursor is over it.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+pub enum VideoHoverBehaviour {
+    #[default]
+    None,
+    /// Mute the video's audio until the cursor leaves the window.
+    Mute,
+    /// Pause the video until the cursor leaves the window.
+    Pause,
+    /// Close the window as soon as the cursor enters it.
+    Close,
+}
+
+/// Eases the CPU load of video popups, which adds up quickly with several playing at once. Both
+/// are off by default, since they cost some quality.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(default)]
+pub struct VideoPerformance {
+    /// Drop frames so videos play at no more than this many frames a second, e.g. 24. Unset
+    /// plays them at their own frame rate.
+    pub max_fps: Option<u32>,
+    /// Decode videos at the size of their window when they're bigger than it.
+    pub downscale: bool,
+}
+
+/// Keeps images in memory once they've been decoded, so one that pops up again doesn't have to be
+/// decoded again. The least recently shown are dropped once the limit is reached.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(default)]
+pub struct ImageCache {
+    /// How many megabytes of decoded images to keep at most. 0 keeps none.
+    pub megabytes: u32,
+}
+
+impl Default for ImageCache {
+    fn default() -> Self {
+        Self { megabytes: 256 }
+    }
+}
+
+impl ImageCache {
+    pub fn bytes(&self) -> u64 {
+        u64::from(self.megabytes) * 1024 * 1024
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Hash)]
@@ -84,10 +567,12 @@ impl Default for AppConfig {
     fn default() -> Self {
         Self {
             pack_path: None,
+            blend_pack: None,
             uploaded_modes: Vec::new(),
             mode: Mode::default(),
             mode_options: HashMap::new(),
             tags: None,
+            tag_match_mode: TagMatchMode::default(),
             panic_button: Key {
                 name: "Escape".to_string(),
                 code: "Escape".to_string(),
@@ -96,7 +581,40 @@ impl Default for AppConfig {
                     ..Default::default()
                 },
             },
+            mute_button: None,
+            pause_button: None,
+            block_button: None,
+            favorite_button: None,
+            favorites: FavoritesMode::default(),
+            info_button: None,
             disabled_monitors: Vec::new(),
+            video_hover: VideoHoverBehaviour::default(),
+            video_performance: VideoPerformance::default(),
+            image_cache: ImageCache::default(),
+            verify_media: false,
+            isolate_video_decoding: false,
+            max_rating: None,
+            excluded_tags: Vec::new(),
+            seed: None,
+            daily_shuffle: false,
+            metrics_port: None,
+            battery_saver: BatterySaver::default(),
+            throttle: Throttle::default(),
+            start_delay: StartDelay::default(),
+            schedule: Schedule::default(),
+            escalation: Escalation::default(),
+            pacing: Pacing::default(),
+            prompt_failure: PromptFailure::default(),
+            minigames: false,
+            hibernation: Hibernation::default(),
+            pause_while_recording: false,
+            duck_other_apps: false,
+            incognito: false,
+            cursor_margin: None,
+            pause_while_idle: None,
+            language: None,
+            goals: Vec::new(),
+            prompt_logging: PromptLogging::default(),
         }
     }
 }
@@ -136,6 +654,289 @@ pub async fn save_config_async(config: AppConfig) -> Result<()> {
     Ok(())
 }
 
+/// How many unpinned packs are remembered. Pinned packs don't count towards this.
+const MAX_RECENT_PACKS: usize = 10;
+
+/// A pack opened in the config app or the pack editor. Kept in its own file rather than in
+/// [`AppConfig`], since both apps update it and the config app always writes back its whole
+/// config.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct RecentPack {
+    pub path: PathBuf,
+    /// The id from the pack's header, which changes when a pack is saved under a new name. Used
+    /// to spot a different pack that has since been saved over the same path.
+    pub id: String,
+    pub name: String,
+    /// Unix timestamp, in seconds.
+    pub last_opened: u64,
+    /// Pinned packs are listed first and never drop off the list.
+    #[serde(default)]
+    pub pinned: bool,
+}
+
+pub fn load_recent_packs() -> Result<Vec<RecentPack>> {
+    let path = recent_packs_path()?;
+
+    Ok(fs::read_to_string(path)
+        .ok()
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_default())
+}
+
+/// Moves the pack at `path` to the top of the list, adding it if needed, and returns the new
+/// list.
+pub fn record_recent_pack(path: &Path, id: &str, name: &str) -> Result<Vec<RecentPack>> {
+    let last_opened = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+
+    update_recent_packs(|packs| {
+        add_recent_pack(
+            packs,
+            RecentPack {
+                path: path.to_path_buf(),
+                id: id.to_string(),
+                name: name.to_string(),
+                last_opened,
+                pinned: false,
+            },
+        )
+    })
+}
+
+pub fn set_recent_pack_pinned(path: &Path, pinned: bool) -> Result<Vec<RecentPack>> {
+    update_recent_packs(|packs| {
+        if let Some(pack) = packs.iter_mut().find(|p| p.path == path) {
+            pack.pinned = pinned;
+        }
+    })
+}
+
+pub fn forget_recent_pack(path: &Path) -> Result<Vec<RecentPack>> {
+    update_recent_packs(|packs| packs.retain(|p| p.path != path))
+}
+
+fn update_recent_packs(f: impl FnOnce(&mut Vec<RecentPack>)) -> Result<Vec<RecentPack>> {
+    let mut packs = load_recent_packs()?;
+    f(&mut packs);
+    sort_recent_packs(&mut packs);
+
+    let path = recent_packs_path()?;
+    let temp_path = path.with_added_extension("tmp");
+    fs::write(&temp_path, serde_json::to_string(&packs)?)?;
+    fs::rename(temp_path, path)?;
+
+    Ok(packs)
+}
+
+fn add_recent_pack(packs: &mut Vec<RecentPack>, mut pack: RecentPack) {
+    if let Some(i) = packs.iter().position(|p| p.path == pack.path) {
+        let old = packs.remove(i);
+        // Keep the pin, unless this is a different pack saved over the same path.
+        pack.pinned = old.pinned && old.id == pack.id;
+    }
+    packs.push(pack);
+}
+
+/// What an upload does when a file fails to encode.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum OnEncodeError {
+    /// Report the error and carry on with the other files.
+    #[default]
+    Skip,
+    /// Stop the upload at the first error. Files already being encoded still finish.
+    Abort,
+    /// Like `Skip`, but also copy the failed files to a folder next to the pack, along with an
+    /// `errors.json` report, so they can be fixed and uploaded again.
+    Quarantine,
+}
+
+/// How the pack editor encodes media. Kept apart from [`AppConfig`] for the same reason as
+/// [`RecentPack`].
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+#[serde(default)]
+pub struct EncodeSettings {
+    /// How many files are encoded at once. `None` picks a default from the number of CPU cores.
+    pub jobs: Option<usize>,
+    /// Runs ffmpeg at a lower priority, so the machine stays usable while a large upload is
+    /// encoding.
+    pub low_priority: bool,
+    pub on_error: OnEncodeError,
+}
+
+pub fn load_encode_settings() -> Result<EncodeSettings> {
+    let path = encode_settings_path()?;
+
+    Ok(fs::read_to_string(path)
+        .ok()
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_default())
+}
+
+pub fn save_encode_settings(settings: &EncodeSettings) -> Result<()> {
+    let path = encode_settings_path()?;
+    let temp_path = path.with_added_extension("tmp");
+
+    fs::write(&temp_path, serde_json::to_string(settings)?)?;
+    fs::rename(temp_path, path)?;
+
+    Ok(())
+}
+
+/// What the pack editor does before saving over a pack. Kept apart from [`AppConfig`] for the
+/// same reason as [`RecentPack`].
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+#[serde(default)]
+pub struct BackupSettings {
+    /// How many copies of a pack from before its last few saves are kept next to it. 0 turns
+    /// backups off, which is the default since each one is as large as the pack.
+    pub keep: usize,
+}
+
+pub fn load_backup_settings() -> Result<BackupSettings> {
+    let path = backup_settings_path()?;
+
+    Ok(fs::read_to_string(path)
+        .ok()
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_default())
+}
+
+pub fn save_backup_settings(settings: &BackupSettings) -> Result<()> {
+    let path = backup_settings_path()?;
+    let temp_path = path.with_added_extension("tmp");
+
+    fs::write(&temp_path, serde_json::to_string(settings)?)?;
+    fs::rename(temp_path, path)?;
+
+    Ok(())
+}
+
+/// How the last session went, if there's been one, and what's been counted over every session.
+/// Written by the player, and kept in the encrypted [`user_data`] store, since it says how
+/// Lewdware's been used.
+#[derive(Serialize, Debug, Clone, PartialEq, Default)]
+pub struct Stats {
+    pub last_session: Option<SessionSummary>,
+    pub totals: SessionTotals,
+}
+
+pub fn load_stats() -> Result<Stats> {
+    let data = user_data::load()?;
+    Ok(Stats {
+        last_session: data.last_session,
+        totals: data.totals,
+    })
+}
+
+/// Saves the session that's just ended as the last one, and adds it to the totals.
+pub fn save_last_session(summary: &SessionSummary) -> Result<()> {
+    user_data::update(|data| {
+        data.totals.add(&summary.stats);
+        data.last_session = Some(summary.clone());
+    })
+}
+
+/// The files the user never wants to see again, as the hex hashes of their contents (the `hash`
+/// column of a pack's index), so they stay blocked in every pack they're in. Kept in the
+/// encrypted [`user_data`] store, apart from [`AppConfig`], since the player adds to it
+/// mid-session.
+pub fn load_blocked_media() -> Result<BTreeSet<String>> {
+    Ok(user_data::load()?.blocked_media)
+}
+
+/// Adds `hash` to the blocked files, returning how many there are now.
+pub fn block_media(hash: &str) -> Result<usize> {
+    user_data::update(|data| {
+        data.blocked_media.insert(hash.to_string());
+        data.blocked_media.len()
+    })
+}
+
+pub fn clear_blocked_media() -> Result<()> {
+    user_data::update(|data| data.blocked_media.clear())
+}
+
+/// The user's favorite files in the pack with the id `pack_id` (from its header), as hex hashes
+/// like [`load_blocked_media`], and kept in the same store.
+pub fn load_favorites(pack_id: &str) -> Result<BTreeSet<String>> {
+    Ok(user_data::load()?
+        .favorites
+        .remove(pack_id)
+        .unwrap_or_default())
+}
+
+/// Adds `hash` to the pack's favorites, or takes it off them.
+pub fn set_favorite(pack_id: &str, hash: &str, favorite: bool) -> Result<()> {
+    user_data::update(|data| {
+        let favorites = data.favorites.entry(pack_id.to_string()).or_default();
+        if favorite {
+            favorites.insert(hash.to_string());
+        } else {
+            favorites.remove(hash);
+        }
+        data.favorites.retain(|_, favorites| !favorites.is_empty());
+    })
+}
+
+/// Every prompt in the session history, oldest first. Lines that can't be read are skipped.
+pub fn load_prompt_history() -> Result<Vec<PromptRecord>> {
+    let path = prompt_history_path()?;
+    let Ok(history) = fs::read_to_string(path) else {
+        return Ok(Vec::new());
+    };
+
+    Ok(history
+        .lines()
+        .filter_map(|line| {
+            serde_json::from_str(line)
+                .inspect_err(|err| tracing::warn!("Skipping prompt history entry: {err}"))
+                .ok()
+        })
+        .collect())
+}
+
+/// Adds `record` to the end of the session history. The history is a JSON object per line, so
+/// this doesn't need to read what's already there.
+pub fn append_prompt_history(record: &PromptRecord) -> Result<()> {
+    let mut file = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(prompt_history_path()?)?;
+
+    writeln!(file, "{}", serde_json::to_string(record)?)?;
+
+    Ok(())
+}
+
+pub fn clear_prompt_history() -> Result<()> {
+    match fs::remove_file(prompt_history_path()?) {
+        Err(err) if err.kind() != std::io::ErrorKind::NotFound => Err(err.into()),
+        _ => Ok(()),
+    }
+}
+
+/// Pinned packs first, then most recently opened first, dropping the oldest unpinned packs past
+/// [`MAX_RECENT_PACKS`].
+fn sort_recent_packs(packs: &mut Vec<RecentPack>) {
+    packs.sort_by(|a, b| {
+        b.pinned
+            .cmp(&a.pinned)
+            .then(b.last_opened.cmp(&a.last_opened))
+    });
+
+    let mut unpinned = 0;
+    packs.retain(|p| {
+        if p.pinned {
+            return true;
+        }
+        unpinned += 1;
+        unpinned <= MAX_RECENT_PACKS
+    });
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -151,6 +952,88 @@ mod tests {
         );
     }
 
+    #[test]
+    fn schedule_windows() {
+        const WEEKDAYS: [bool; 7] = [true, true, true, true, true, false, false];
+        let schedule = Schedule {
+            enabled: true,
+            windows: vec![
+                ScheduleWindow {
+                    days: WEEKDAYS,
+                    start: 20 * 60,
+                    end: 23 * 60,
+                },
+                // Friday night into Saturday morning.
+                ScheduleWindow {
+                    days: [false, false, false, false, true, false, false],
+                    start: 23 * 60,
+                    end: 2 * 60,
+                },
+            ],
+        };
+
+        assert!(schedule.is_active(0, 20 * 60));
+        assert!(!schedule.is_active(0, 23 * 60));
+        assert!(!schedule.is_active(5, 21 * 60));
+        assert!(schedule.is_active(4, 23 * 60 + 30));
+        assert!(schedule.is_active(5, 60));
+        assert!(!schedule.is_active(6, 60));
+
+        let off = Schedule {
+            enabled: false,
+            ..schedule.clone()
+        };
+        assert!(off.is_active(5, 21 * 60));
+    }
+
+    #[test]
+    fn hibernation_length_stays_in_range() {
+        let hibernation = Hibernation {
+            min_minutes: 10,
+            max_minutes: 2,
+            ..Default::default()
+        };
+
+        assert_eq!(hibernation.length(0.0), Duration::from_secs(2 * 60));
+        assert_eq!(hibernation.length(0.5), Duration::from_secs(6 * 60));
+        assert_eq!(hibernation.length(1.5), Duration::from_secs(10 * 60));
+    }
+
+    #[test]
+    fn escalation_ramps_then_holds() {
+        let escalation = Escalation {
+            enabled: true,
+            ..Default::default()
+        };
+        let minutes = |m: u64| escalation.factor(Duration::from_secs(m * 60));
+
+        assert_eq!(minutes(0), 0.5);
+        assert_eq!(minutes(15), 1.0);
+        assert_eq!(minutes(30), 1.5);
+        assert_eq!(minutes(90), 1.5);
+
+        let off = Escalation::default();
+        assert_eq!(off.factor(Duration::from_secs(15 * 60)), 1.0);
+    }
+
+    #[test]
+    fn pacing_steps_within_its_bounds() {
+        let pacing = Pacing {
+            enabled: true,
+            min_percent: 80,
+            max_percent: 120,
+            ..Default::default()
+        };
+
+        let harder = pacing.step(1.0, true);
+        assert!((harder - 1.1).abs() < 1e-9);
+        assert_eq!(pacing.step(1.15, true), 1.2);
+        assert_eq!(pacing.step(0.85, false), 0.8);
+
+        assert!(pacing.is_quick_close(Duration::from_secs(1)));
+        assert!(!pacing.is_quick_close(Duration::from_secs(3)));
+    }
+
     #[test]
     fn key_display_no_modifiers() {
         let key = Key {
@@ -196,17 +1079,83 @@ mod tests {
         assert!(config.panic_button.modifiers.shift);
         assert!(!config.panic_button.modifiers.ctrl);
     }
+
+    fn recent(path: &str, id: &str, last_opened: u64, pinned: bool) -> RecentPack {
+        RecentPack {
+            path: PathBuf::from(path),
+            id: id.to_string(),
+            name: path.to_string(),
+            last_opened,
+            pinned,
+        }
+    }
+
+    #[test]
+    fn recent_packs_keep_pins_and_drop_the_oldest() {
+        let mut packs = vec![recent("pinned.lwpack", "a", 0, true)];
+        for i in 0..MAX_RECENT_PACKS as u64 + 2 {
+            add_recent_pack(
+                &mut packs,
+                recent(&format!("{i}.lwpack"), "b", i + 1, false),
+            );
+        }
+        sort_recent_packs(&mut packs);
+
+        assert_eq!(packs.len(), MAX_RECENT_PACKS + 1);
+        assert_eq!(packs[0].path, PathBuf::from("pinned.lwpack"));
+        assert_eq!(
+            packs[1].path,
+            PathBuf::from(format!("{}.lwpack", MAX_RECENT_PACKS + 1))
+        );
+        assert!(!packs.iter().any(|p| p.path == PathBuf::from("0.lwpack")));
+    }
+
+    #[test]
+    fn reopening_pack_keeps_pin_unless_replaced() {
+        let mut packs = vec![recent("a.lwpack", "a", 0, true)];
+        add_recent_pack(&mut packs, recent("a.lwpack", "a", 5, false));
+        assert_eq!(packs, vec![recent("a.lwpack", "a", 5, true)]);
+
+        add_recent_pack(&mut packs, recent("a.lwpack", "other", 6, false));
+        assert_eq!(packs, vec![recent("a.lwpack", "other", 6, false)]);
+    }
+
+    #[test]
+    fn config_without_video_hover_still_loads() {
+        let mut value = serde_json::to_value(AppConfig::default()).unwrap();
+        value.as_object_mut().unwrap().remove("video_hover");
+        let decoded: AppConfig = serde_json::from_value(value).unwrap();
+        assert_eq!(decoded.video_hover, VideoHoverBehaviour::None);
+    }
 }
 
-fn config_path() -> Result<PathBuf> {
-    let mut config_path = dirs::config_dir()
+pub(crate) fn config_dir() -> Result<PathBuf> {
+    let mut config_dir = dirs::config_dir()
         .ok_or_else(|| anyhow!("Could not find a valid config dir for this OS"))?;
 
-    config_path.push("lewdware");
+    config_dir.push("lewdware");
+
+    fs::create_dir_all(&config_dir)?;
 
-    fs::create_dir_all(&config_path)?;
+    Ok(config_dir)
+}
 
-    config_path.push("config.json");
+fn config_path() -> Result<PathBuf> {
+    Ok(config_dir()?.join("config.json"))
+}
+
+fn recent_packs_path() -> Result<PathBuf> {
+    Ok(config_dir()?.join("recent_packs.json"))
+}
+
+fn encode_settings_path() -> Result<PathBuf> {
+    Ok(config_dir()?.join("encode_settings.json"))
+}
+
+fn backup_settings_path() -> Result<PathBuf> {
+    Ok(config_dir()?.join("backup_settings.json"))
+}
 
-    Ok(config_path)
+fn prompt_history_path() -> Result<PathBuf> {
+    Ok(config_dir()?.join("prompt_history.jsonl"))
 }
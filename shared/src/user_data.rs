@@ -0,0 +1,427 @@
+//! The store for per-user data that gives away how Lewdware has been used: the blocked files,
+//! each pack's favorites and the session stats. It's kept in its own file next to the
+//! config, encrypted with ChaCha20-Poly1305 under a key derived from the optional settings
+//! password.
+//!
+//! Without a password the key is derived from an empty one, which only stops the file being read
+//! at a glance. With one, the store stays locked until it's unlocked with [`unlock`]. The config
+//! app passes the key on to the player in [`KEY_VAR`], so the password only has to be typed once.
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    error, fmt,
+    fs::{self, File},
+    io::ErrorKind,
+    num::NonZeroU32,
+    path::PathBuf,
+    sync::Mutex,
+};
+
+use anyhow::{Result, bail};
+use ring::{
+    aead::{Aad, CHACHA20_POLY1305, LessSafeKey, NONCE_LEN, Nonce, UnboundKey},
+    pbkdf2,
+    rand::{SecureRandom, SystemRandom},
+};
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+
+use crate::{
+    goals::{SessionSummary, SessionTotals},
+    user_config::config_dir,
+};
+
+/// The environment variable the config app passes the store's key to the player in (see
+/// [`exported_key`]).
+pub const KEY_VAR: &str = "LEWDWARE_USER_DATA_KEY";
+
+const MAGIC: &[u8; 4] = b"LWUD";
+const VERSION: u8 = 1;
+const SALT_LEN: usize = 16;
+const KEY_LEN: usize = 32;
+/// The magic, version, PBKDF2 iteration count and salt. Authenticated along with the data, so
+/// none of it can be changed without the file failing to decrypt.
+const HEADER_LEN: usize = MAGIC.len() + 1 + 4 + SALT_LEN;
+
+/// PBKDF2 iterations for a password. Without one there's nothing to slow down guessing, so the
+/// key is derived with a single iteration, which is also how a store without a password is told
+/// apart.
+const PASSWORD_ITERATIONS: u32 = 600_000;
+
+#[derive(Serialize, Deserialize, Default, Debug, Clone, PartialEq)]
+pub struct UserData {
+    /// See [`crate::user_config::load_blocked_media`].
+    #[serde(default)]
+    pub blocked_media: BTreeSet<String>,
+    /// See [`crate::user_config::load_favorites`]. Keyed by pack id.
+    #[serde(default)]
+    pub favorites: BTreeMap<String, BTreeSet<String>>,
+    /// See [`crate::user_config::load_last_session`].
+    #[serde(default)]
+    pub last_session: Option<SessionSummary>,
+    /// See [`crate::user_config::load_stats`].
+    #[serde(default)]
+    pub totals: SessionTotals,
+}
+
+/// Returned (through [`anyhow::Error`]) when the store has a password and hasn't been unlocked,
+/// or the password was changed by another process since.
+#[derive(Debug)]
+pub struct Locked;
+
+impl fmt::Display for Locked {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "The user data is locked with the settings password")
+    }
+}
+
+impl error::Error for Locked {}
+
+/// The key the store is encrypted under, with what it was derived from.
+#[derive(Clone, Debug, PartialEq)]
+struct Key {
+    salt: [u8; SALT_LEN],
+    iterations: u32,
+    key: [u8; KEY_LEN],
+}
+
+impl Key {
+    fn derive(password: Option<&str>, salt: [u8; SALT_LEN], iterations: u32) -> Self {
+        let mut key = [0; KEY_LEN];
+        pbkdf2::derive(
+            pbkdf2::PBKDF2_HMAC_SHA256,
+            NonZeroU32::new(iterations).unwrap_or(NonZeroU32::MIN),
+            &salt,
+            password.unwrap_or_default().as_bytes(),
+            &mut key,
+        );
+
+        Self {
+            salt,
+            iterations,
+            key,
+        }
+    }
+
+    /// A key with a fresh salt.
+    fn generate(password: Option<&str>) -> Result<Self> {
+        let mut salt = [0; SALT_LEN];
+        SystemRandom::new()
+            .fill(&mut salt)
+            .map_err(|_| anyhow::anyhow!("Couldn't generate a salt"))?;
+
+        let iterations = if password.is_some() {
+            PASSWORD_ITERATIONS
+        } else {
+            1
+        };
+
+        Ok(Self::derive(password, salt, iterations))
+    }
+
+    fn header(&self) -> [u8; HEADER_LEN] {
+        let mut header = [0; HEADER_LEN];
+        header[..4].copy_from_slice(MAGIC);
+        header[4] = VERSION;
+        header[5..9].copy_from_slice(&self.iterations.to_le_bytes());
+        header[9..].copy_from_slice(&self.salt);
+        header
+    }
+
+    fn aead_key(&self) -> LessSafeKey {
+        LessSafeKey::new(UnboundKey::new(&CHACHA20_POLY1305, &self.key).expect("key is 32 bytes"))
+    }
+
+    fn encrypt(&self, data: &UserData) -> Result<Vec<u8>> {
+        let mut nonce = [0; NONCE_LEN];
+        SystemRandom::new()
+            .fill(&mut nonce)
+            .map_err(|_| anyhow::anyhow!("Couldn't generate a nonce"))?;
+
+        let header = self.header();
+        let mut contents = serde_json::to_vec(data)?;
+        self.aead_key()
+            .seal_in_place_append_tag(
+                Nonce::assume_unique_for_key(nonce),
+                Aad::from(header),
+                &mut contents,
+            )
+            .map_err(|_| anyhow::anyhow!("Couldn't encrypt the user data"))?;
+
+        Ok([&header[..], &nonce, &contents].concat())
+    }
+
+    /// `None` if the file wasn't encrypted with this key.
+    fn decrypt(&self, file: &[u8]) -> Result<Option<UserData>> {
+        read_header(file)?;
+        if file.len() < HEADER_LEN + NONCE_LEN {
+            bail!("The user data file is truncated");
+        }
+
+        let (header, rest) = file.split_at(HEADER_LEN);
+        let (nonce, contents) = rest.split_at(NONCE_LEN);
+        let mut contents = contents.to_vec();
+
+        let Ok(contents) = self.aead_key().open_in_place(
+            Nonce::assume_unique_for_key(nonce.try_into()?),
+            Aad::from(header),
+            &mut contents,
+        ) else {
+            return Ok(None);
+        };
+
+        Ok(Some(serde_json::from_slice(contents)?))
+    }
+
+    /// The salt, iteration count and key as hex, for [`KEY_VAR`].
+    fn to_hex(&self) -> String {
+        [&self.salt[..], &self.iterations.to_le_bytes(), &self.key]
+            .concat()
+            .iter()
+            .map(|b| format!("{b:02x}"))
+            .collect()
+    }
+
+    fn from_hex(hex: &str) -> Option<Self> {
+        let bytes = (0..hex.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+            .collect::<Option<Vec<u8>>>()?;
+        if bytes.len() != SALT_LEN + 4 + KEY_LEN {
+            return None;
+        }
+
+        let (salt, rest) = bytes.split_at(SALT_LEN);
+        let (iterations, key) = rest.split_at(4);
+
+        Some(Self {
+            salt: salt.try_into().ok()?,
+            iterations: u32::from_le_bytes(iterations.try_into().ok()?),
+            key: key.try_into().ok()?,
+        })
+    }
+}
+
+/// The salt and iteration count from the start of a store file.
+fn read_header(file: &[u8]) -> Result<([u8; SALT_LEN], u32)> {
+    if file.len() < HEADER_LEN || &file[..4] != MAGIC {
+        bail!("The user data file is corrupted");
+    }
+    if file[4] != VERSION {
+        bail!("The user data file is from a newer version of Lewdware");
+    }
+
+    Ok((
+        file[9..HEADER_LEN].try_into()?,
+        u32::from_le_bytes(file[5..9].try_into()?),
+    ))
+}
+
+/// The key the store was unlocked with in this process, if it has been.
+static KEY: Mutex<Option<Key>> = Mutex::new(None);
+
+/// Unlocks the store with the settings password, or with no password if it doesn't have one.
+/// Returns `false` if the password is wrong. Slow with a password, by design.
+pub fn unlock(password: Option<&str>) -> Result<bool> {
+    let key = match fs::read(user_data_path()?) {
+        Ok(file) => {
+            let (salt, iterations) = read_header(&file)?;
+            // Deriving a key from no password for a store that has one would take just as long,
+            // only to fail.
+            if password.is_none() && iterations > 1 {
+                return Ok(false);
+            }
+
+            let key = Key::derive(password, salt, iterations);
+            if key.decrypt(&file)?.is_none() {
+                return Ok(false);
+            }
+            key
+        }
+        Err(err) if err.kind() == ErrorKind::NotFound => Key::generate(None)?,
+        Err(err) => return Err(err.into()),
+    };
+
+    *KEY.lock().unwrap() = Some(key);
+    Ok(true)
+}
+
+/// Unlocks the store with a key from [`exported_key`]. Returns `false` if it's the wrong one.
+pub fn unlock_with_key(hex: &str) -> Result<bool> {
+    let Some(key) = Key::from_hex(hex) else {
+        bail!("Invalid user data key");
+    };
+
+    match fs::read(user_data_path()?) {
+        Ok(file) if key.decrypt(&file)?.is_none() => return Ok(false),
+        Err(err) if err.kind() != ErrorKind::NotFound => return Err(err.into()),
+        _ => {}
+    }
+
+    *KEY.lock().unwrap() = Some(key);
+    Ok(true)
+}
+
+/// The key the store was unlocked with, to be passed on to another process in [`KEY_VAR`].
+pub fn exported_key() -> Option<String> {
+    KEY.lock().unwrap().as_ref().map(Key::to_hex)
+}
+
+/// Whether the store needs the settings password to be unlocked first. Unlocks it if it doesn't.
+pub fn is_locked() -> Result<bool> {
+    Ok(KEY.lock().unwrap().is_none() && !unlock(None)?)
+}
+
+/// Whether the store has a settings password.
+pub fn has_password() -> Result<bool> {
+    match fs::read(user_data_path()?) {
+        Ok(file) => Ok(read_header(&file)?.1 > 1),
+        Err(err) if err.kind() == ErrorKind::NotFound => Ok(false),
+        Err(err) => Err(err.into()),
+    }
+}
+
+/// Re-encrypts the store with a new settings password, or with none. It has to be unlocked.
+pub fn set_password(password: Option<&str>) -> Result<()> {
+    let _lock = lock_store()?;
+    let data = load()?;
+    let key = Key::generate(password)?;
+    save(&key, &data)?;
+
+    *KEY.lock().unwrap() = Some(key);
+    Ok(())
+}
+
+/// Everything in the store. Fails with [`Locked`] if it hasn't been unlocked and has a password.
+pub fn load() -> Result<UserData> {
+    let key = current_key()?;
+
+    match fs::read(user_data_path()?) {
+        Ok(file) => Ok(key.decrypt(&file)?.ok_or(Locked)?),
+        Err(err) if err.kind() == ErrorKind::NotFound => Ok(load_unencrypted()),
+        Err(err) => Err(err.into()),
+    }
+}
+
+/// Changes the store with `f` and saves it, returning what `f` does.
+pub fn update<T>(f: impl FnOnce(&mut UserData) -> T) -> Result<T> {
+    let _lock = lock_store()?;
+    let key = current_key()?;
+    let mut data = load()?;
+    let result = f(&mut data);
+    save(&key, &data)?;
+
+    Ok(result)
+}
+
+fn current_key() -> Result<Key> {
+    if let Some(key) = KEY.lock().unwrap().clone() {
+        return Ok(key);
+    }
+
+    if !unlock(None)? {
+        return Err(Locked.into());
+    }
+
+    Ok(KEY.lock().unwrap().clone().expect("just unlocked"))
+}
+
+/// Waits for any other process that's changing the store to finish, and keeps others out until the
+/// returned file is dropped. The player and the config app both write the store, so without this
+/// they'd lose each other's changes, or one could save it under a key the other has just replaced.
+fn lock_store() -> Result<File> {
+    let file = File::create(config_dir()?.join("user_data.lock"))?;
+    file.lock()?;
+
+    Ok(file)
+}
+
+fn save(key: &Key, data: &UserData) -> Result<()> {
+    let path = user_data_path()?;
+    let temp_path = path.with_added_extension("tmp");
+
+    fs::write(&temp_path, key.encrypt(data)?)?;
+    fs::rename(temp_path, path)?;
+
+    // The unencrypted files from before the store existed have been copied in by now.
+    for name in UNENCRYPTED_FILES {
+        match fs::remove_file(config_dir()?.join(name)) {
+            Err(err) if err.kind() != ErrorKind::NotFound => {
+                tracing::warn!("Couldn't remove {name}: {err}")
+            }
+            _ => {}
+        }
+    }
+
+    Ok(())
+}
+
+/// Where the data in the store was kept, unencrypted, before it existed.
+const UNENCRYPTED_FILES: [&str; 3] = ["blocked_media.json", "favorites.json", "last_session.json"];
+
+fn load_unencrypted() -> UserData {
+    fn read<T: DeserializeOwned>(name: &str) -> Option<T> {
+        let path = config_dir().ok()?.join(name);
+        serde_json::from_str(&fs::read_to_string(path).ok()?).ok()
+    }
+
+    UserData {
+        blocked_media: read(UNENCRYPTED_FILES[0]).unwrap_or_default(),
+        favorites: read(UNENCRYPTED_FILES[1]).unwrap_or_default(),
+        last_session: read(UNENCRYPTED_FILES[2]),
+        totals: SessionTotals::default(),
+    }
+}
+
+fn user_data_path() -> Result<PathBuf> {
+    Ok(config_dir()?.join("user_data.bin"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn data() -> UserData {
+        UserData {
+            blocked_media: ["ab".to_string()].into(),
+            favorites: [("pack".to_string(), ["cd".to_string()].into())].into(),
+            last_session: None,
+            totals: SessionTotals::default(),
+        }
+    }
+
+    #[test]
+    fn only_the_right_password_decrypts() {
+        // Far fewer iterations than a real password gets, to keep the test quick.
+        let key = Key::derive(Some("hunter2"), [7; SALT_LEN], 1000);
+        let file = key.encrypt(&data()).unwrap();
+
+        assert_eq!(read_header(&file).unwrap(), ([7; SALT_LEN], 1000));
+        assert_eq!(key.decrypt(&file).unwrap(), Some(data()));
+
+        let wrong = Key::derive(Some("hunter3"), [7; SALT_LEN], 1000);
+        assert_eq!(wrong.decrypt(&file).unwrap(), None);
+        assert_eq!(
+            Key::derive(None, [7; SALT_LEN], 1000)
+                .decrypt(&file)
+                .unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn header_is_authenticated() {
+        let key = Key::derive(None, [1; SALT_LEN], 1);
+        let mut file = key.encrypt(&data()).unwrap();
+        file[5] = 2;
+
+        assert_eq!(key.decrypt(&file).unwrap(), None);
+    }
+
+    #[test]
+    fn exported_key_roundtrips() {
+        let key = Key::generate(None).unwrap();
+        assert_eq!(key.iterations, 1);
+        assert_eq!(Key::from_hex(&key.to_hex()), Some(key));
+        assert_eq!(Key::from_hex("abc"), None);
+    }
+}
@@ -0,0 +1,164 @@
+//! Writing packs, the other half of [`crate::read_pack`]. The media goes first, straight after the
+//! header, then the index and metadata, and the header is written last, once it's known where
+//! they are.
+
+use std::io::{self, Read, Seek, SeekFrom, Write};
+
+use uuid::Uuid;
+
+use crate::read_pack::{COMPRESSION_LEVEL, HEADER_SIZE, Header, Metadata};
+
+/// Writes a pack in the current version of the format, keeping track of where everything goes.
+pub struct PackWriter<W> {
+    out: W,
+    header: Header,
+    /// Where the next file goes.
+    end: u64,
+}
+
+impl<W: Write + Seek> PackWriter<W> {
+    /// A new pack, with a new id.
+    pub fn new(out: W) -> Self {
+        Self::with_id(out, Uuid::new_v4())
+    }
+
+    /// A new version of the pack with this id, e.g. when a pack is saved again.
+    pub fn with_id(out: W, id: Uuid) -> Self {
+        Self {
+            out,
+            header: Header {
+                id,
+                ..Header::new()
+            },
+            end: HEADER_SIZE as u64,
+        }
+    }
+
+    /// Carries on after media that's already been written up to `end`, e.g. by the pack editor,
+    /// which moves files around in the pack in place.
+    pub fn after_media(mut self, end: u64) -> Self {
+        self.end = end.max(HEADER_SIZE as u64);
+        self
+    }
+
+    /// Adds a file, returning the offset and length to store in the index.
+    pub fn append(&mut self, mut data: impl Read) -> io::Result<(u64, u64)> {
+        let offset = self.end;
+        self.out.seek(SeekFrom::Start(offset))?;
+        let length = io::copy(&mut data, &mut self.out)?;
+        self.end += length;
+
+        Ok((offset, length))
+    }
+
+    /// Writes the index (the SQLite database, as it is on disk) and the metadata after the media,
+    /// then the header that points at them. Returns `out` and the header. A file that had a
+    /// longer pack in it should be cut down to [`Header::end`].
+    pub fn finish(mut self, mut index: impl Read, metadata: &Metadata) -> io::Result<(W, Header)> {
+        self.out.seek(SeekFrom::Start(self.end))?;
+
+        let mut out = CountingWriter::new(&mut self.out);
+        if self.header.is_compressed() {
+            zstd::stream::copy_encode(&mut index, &mut out, COMPRESSION_LEVEL)?;
+        } else {
+            io::copy(&mut index, &mut out)?;
+        }
+        self.header.index_offset = self.end;
+        self.header.index_length = out.written;
+
+        let metadata = self
+            .header
+            .encode(&metadata.to_buf().map_err(io::Error::other)?)?;
+        self.out.write_all(&metadata)?;
+        self.header.metadata_offset = self.header.index_offset + self.header.index_length;
+        self.header.metadata_length = metadata.len() as u64;
+
+        self.out.seek(SeekFrom::Start(0))?;
+        self.out.write_all(&self.header.to_buf()?)?;
+        self.out.flush()?;
+
+        Ok((self.out, self.header))
+    }
+}
+
+/// Counts the bytes written through it, since a compressed index's length isn't known until it's
+/// been written.
+struct CountingWriter<W> {
+    inner: W,
+    written: u64,
+}
+
+impl<W> CountingWriter<W> {
+    fn new(inner: W) -> Self {
+        Self { inner, written: 0 }
+    }
+}
+
+impl<W: Write> Write for CountingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        self.written += n as u64;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::read_pack::{read_index, read_pack_metadata};
+
+    #[test]
+    fn writes_a_pack_that_reads_back() {
+        let metadata = Metadata {
+            name: "test-pack".to_string(),
+            ..Default::default()
+        };
+        let index: Vec<u8> = (0..100_000).map(|i| (i / 1000) as u8).collect();
+
+        let mut writer = PackWriter::new(Cursor::new(Vec::new()));
+        assert_eq!(
+            writer.append(&b"first"[..]).unwrap(),
+            (HEADER_SIZE as u64, 5)
+        );
+        assert_eq!(
+            writer.append(&b"second"[..]).unwrap(),
+            (HEADER_SIZE as u64 + 5, 6)
+        );
+        let (out, header) = writer.finish(index.as_slice(), &metadata).unwrap();
+        let pack = out.into_inner();
+
+        assert_eq!(header.index_offset, HEADER_SIZE as u64 + 11);
+        assert_eq!(header.end(), pack.len() as u64);
+        assert_eq!(&pack[HEADER_SIZE + 5..HEADER_SIZE + 11], b"second");
+
+        let (read_header, read_metadata) = read_pack_metadata(Cursor::new(&pack)).unwrap();
+        assert_eq!(read_header, header);
+        assert_eq!(read_metadata, metadata);
+        assert_eq!(
+            read_index(Cursor::new(&pack), &header, |_| {}).unwrap(),
+            index
+        );
+    }
+
+    #[test]
+    fn rewrites_a_pack_in_place() {
+        let metadata = Metadata::default();
+        let mut out = Cursor::new(vec![0xff; 1000]);
+        let id = Uuid::new_v4();
+
+        let writer = PackWriter::with_id(&mut out, id).after_media(HEADER_SIZE as u64 + 10);
+        let (_, header) = writer.finish(&b"index"[..], &metadata).unwrap();
+
+        assert_eq!(header.id, id);
+        assert_eq!(header.index_offset, HEADER_SIZE as u64 + 10);
+        // The media before it is left alone.
+        assert_eq!(out.get_ref()[HEADER_SIZE..HEADER_SIZE + 10], [0xff; 10]);
+        assert_eq!(read_index(&mut out, &header, |_| {}).unwrap(), b"index");
+    }
+}